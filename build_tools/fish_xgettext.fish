@@ -12,7 +12,7 @@
 # that `xgettext` can handle.
 
 # Start with the C++ source
-xgettext -k -k_ -kN_ -LC++ --no-wrap -o messages.pot src/*.cpp src/*.h
+xgettext -k -k_ -kN_ -kP_:1,2 -LC++ --no-wrap -o messages.pot src/*.cpp src/*.h
 
 # This regex handles descriptions for `complete` and `function` statements. These messages are not
 # particularly important to translate. Hence the "implicit" label.
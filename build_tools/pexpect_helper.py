@@ -126,8 +126,10 @@ class SpawnedProc(object):
             function to ensure that each printed prompt is distinct.
     """
 
-    def __init__(self, name="fish", timeout=TIMEOUT_SECS, env=os.environ.copy()):
-        """Construct from a name, timeout, and environment.
+    def __init__(
+        self, name="fish", timeout=TIMEOUT_SECS, env=os.environ.copy(), args=[], dimensions=(24, 80)
+    ):
+        """Construct from a name, timeout, environment, arguments and terminal size.
 
         Args:
             name: the name of the executable to launch, as a key into the
@@ -136,6 +138,9 @@ class SpawnedProc(object):
             timeout: A timeout to pass to pexpect. This indicates how long to wait
                      before giving up on some expected output.
             env: a string->string dictionary, describing the environment variables.
+            args: a list of arguments to pass to the executable.
+            dimensions: the (rows, columns) of the tty, which may be (0, 0) to act like a
+                        serial line.
         """
         if name not in env:
             raise ValueError("'name' variable not found in environment" % name)
@@ -143,7 +148,9 @@ class SpawnedProc(object):
         self.colorize = sys.stdout.isatty()
         self.messages = []
         self.start_time = None
-        self.spawn = pexpect.spawn(exe_path, env=env, encoding="utf-8", timeout=timeout)
+        self.spawn = pexpect.spawn(
+            exe_path, args, env=env, encoding="utf-8", timeout=timeout, dimensions=dimensions
+        )
         self.spawn.delaybeforesend = None
         self.prompt_counter = 0
 
@@ -51,6 +51,9 @@ if(CMAKE_VERSION VERSION_LESS 3.4.0)
 endif()
 find_package(Threads REQUIRED)
 
+# zlib is needed to read the objects of git repositories, for fish_git_status.
+find_package(ZLIB REQUIRED)
+
 # Detect WSL. Does not match against native Windows/WIN32.
 if (CMAKE_HOST_SYSTEM_VERSION MATCHES ".*-Microsoft")
   set(WSL 1)
@@ -81,7 +84,9 @@ check_cxx_symbol_exists(getifaddrs ifaddrs.h HAVE_GETIFADDRS)
 check_cxx_symbol_exists(getpwent pwd.h HAVE_GETPWENT)
 check_cxx_symbol_exists(getrusage sys/resource.h HAVE_GETRUSAGE)
 check_cxx_symbol_exists(gettext libintl.h HAVE_GETTEXT)
+check_cxx_symbol_exists(inotify_init1 sys/inotify.h HAVE_INOTIFY_INIT1)
 check_cxx_symbol_exists(killpg "sys/types.h;signal.h" HAVE_KILLPG)
+check_cxx_symbol_exists(kqueue "sys/types.h;sys/event.h" HAVE_KQUEUE)
 # mkostemp is in stdlib in glibc and FreeBSD, but unistd on macOS
 check_cxx_symbol_exists(mkostemp "stdlib.h;unistd.h" HAVE_MKOSTEMP)
 set(HAVE_CURSES_H ${CURSES_HAVE_CURSES_H})
@@ -93,6 +98,7 @@ endif()
 if(NOT HAVE_TERM_H)
     check_include_file_cxx("ncurses/term.h" HAVE_NCURSES_TERM_H)
 endif()
+check_include_file_cxx(libproc.h HAVE_LIBPROC_H)
 check_include_file_cxx(siginfo.h HAVE_SIGINFO_H)
 check_include_file_cxx(spawn.h HAVE_SPAWN_H)
 check_struct_has_member("struct stat" st_ctime_nsec "sys/stat.h" HAVE_STRUCT_STAT_ST_CTIME_NSEC
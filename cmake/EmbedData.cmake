@@ -0,0 +1,58 @@
+# Optionally embed fish's data files in fish, so it works without them. When fish finds no data
+# directory, it writes the embedded files to one of the user's and uses that; see
+# src/embedded_data.cpp.
+option(FISH_EMBED_DATA "embed the data files in fish, for a self-contained binary" OFF)
+add_feature_info(embed-data FISH_EMBED_DATA "embed the data files in fish")
+
+# The generator of headers which embed files. This is also used for the sample prompts.
+add_executable(embedded_data_gen src/embedded_data_gen.cpp)
+
+if(FISH_EMBED_DATA)
+  # Each argument of embedded_data_gen is the name of a file below the data directory, and where to
+  # read it.
+  set(EMBEDDED_FILE_ARGS)
+  set(EMBEDDED_FILE_DEPENDS)
+
+  # The contents of share, except the template which is only filled in when installing.
+  file(GLOB_RECURSE EMBEDDED_SHARE_FILES RELATIVE ${CMAKE_CURRENT_SOURCE_DIR}/share
+       ${CMAKE_CURRENT_SOURCE_DIR}/share/*)
+  list(REMOVE_ITEM EMBEDDED_SHARE_FILES __fish_build_paths.fish.in)
+  foreach(file ${EMBEDDED_SHARE_FILES})
+    list(APPEND EMBEDDED_FILE_ARGS ${file}=${CMAKE_CURRENT_SOURCE_DIR}/share/${file})
+    list(APPEND EMBEDDED_FILE_DEPENDS ${CMAKE_CURRENT_SOURCE_DIR}/share/${file})
+  endforeach()
+
+  # The message catalogs, laid out like below LOCALEDIR. These are built by cmake/gettext.cmake.
+  if(GETTEXT_FOUND)
+    foreach(lang ${languages})
+      set(catalog ${CMAKE_CURRENT_BINARY_DIR}/${lang}.gmo)
+      list(APPEND EMBEDDED_FILE_ARGS locale/${lang}/LC_MESSAGES/fish.mo=${catalog})
+      list(APPEND EMBEDDED_FILE_DEPENDS ${catalog})
+    endforeach()
+  endif()
+
+  # The terminfo entries of common terminals, from the database of the build system, for systems
+  # which have none. These are looked up after the system's own.
+  set(EMBEDDED_TERMS ansi dumb linux rxvt-unicode-256color screen screen-256color tmux
+      tmux-256color vt100 vt220 xterm xterm-256color)
+  foreach(term ${EMBEDDED_TERMS})
+    string(SUBSTRING ${term} 0 1 letter)
+    find_file(TERMINFO_${term} ${letter}/${term}
+              PATHS /usr/share/terminfo /lib/terminfo /usr/lib/terminfo /etc/terminfo
+              NO_DEFAULT_PATH)
+    if(TERMINFO_${term})
+      list(APPEND EMBEDDED_FILE_ARGS terminfo/${letter}/${term}=${TERMINFO_${term}})
+      list(APPEND EMBEDDED_FILE_DEPENDS ${TERMINFO_${term}})
+    else()
+      message(STATUS "No terminfo entry for ${term} to embed")
+    endif()
+  endforeach()
+
+  add_custom_command(OUTPUT ${CMAKE_CURRENT_BINARY_DIR}/embedded_data_files.h
+                     COMMAND embedded_data_gen embedded_files ${EMBEDDED_FILE_ARGS}
+                             > ${CMAKE_CURRENT_BINARY_DIR}/embedded_data_files.h
+                     DEPENDS embedded_data_gen ${EMBEDDED_FILE_DEPENDS})
+  set_source_files_properties(src/embedded_data.cpp
+                              PROPERTIES OBJECT_DEPENDS
+                              ${CMAKE_CURRENT_BINARY_DIR}/embedded_data_files.h)
+endif()
@@ -87,11 +87,11 @@ install(FILES etc/config.fish DESTINATION ${sysconfdir}/fish/)
 
 fish_create_dirs(${rel_datadir}/fish ${rel_datadir}/fish/completions
                  ${rel_datadir}/fish/functions ${rel_datadir}/fish/groff
-                 ${rel_datadir}/fish/man/man1 ${rel_datadir}/fish/tools
+                 ${rel_datadir}/fish/man/man1 ${rel_datadir}/fish/themes
+                 ${rel_datadir}/fish/tools
                  ${rel_datadir}/fish/tools/web_config
                  ${rel_datadir}/fish/tools/web_config/js
-                 ${rel_datadir}/fish/tools/web_config/partials
-                 ${rel_datadir}/fish/tools/web_config/sample_prompts)
+                 ${rel_datadir}/fish/tools/web_config/partials)
 
 configure_file(share/__fish_build_paths.fish.in share/__fish_build_paths.fish)
 install(FILES share/config.fish
@@ -128,6 +128,10 @@ install(DIRECTORY share/functions/
 install(DIRECTORY share/groff
         DESTINATION ${rel_datadir}/fish)
 
+install(DIRECTORY share/themes/
+        DESTINATION ${rel_datadir}/fish/themes
+        FILES_MATCHING PATTERN "*.theme")
+
 # CONDEMNED_PAGE is managed by the conditional above
 # Building the man pages is optional: if sphinx isn't installed, they're not built
 install(DIRECTORY ${CMAKE_CURRENT_BINARY_DIR}/user_doc/man/man1/
@@ -145,7 +149,6 @@ install(DIRECTORY share/tools/web_config
         PATTERN "*.png"
         PATTERN "*.css"
         PATTERN "*.html"
-        PATTERN "*.py"
         PATTERN "*.js"
         PATTERN "*.fish")
 
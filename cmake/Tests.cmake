@@ -73,6 +73,8 @@ add_custom_target(test_prep
     COMMAND ${CMAKE_COMMAND} -E make_directory ${TEST_DIR}/xdg_config
     COMMAND ${CMAKE_COMMAND} -E remove_directory ${TEST_DIR}/xdg_runtime
     COMMAND ${CMAKE_COMMAND} -E make_directory ${TEST_DIR}/xdg_runtime
+    COMMAND ${CMAKE_COMMAND} -E remove_directory ${TEST_DIR}/xdg_cache
+    COMMAND ${CMAKE_COMMAND} -E make_directory ${TEST_DIR}/xdg_cache
 
     DEPENDS tests_buildroot_target tests_dir
     USES_TERMINAL)
@@ -86,6 +88,7 @@ foreach(TESTTYPE test serial_test)
     COMMAND env XDG_DATA_HOME=${CMAKE_CURRENT_BINARY_DIR}/test/xdg_data
                 XDG_CONFIG_HOME=${CMAKE_CURRENT_BINARY_DIR}/test/xdg_config
                 XDG_RUNTIME_HOME=${CMAKE_CURRENT_BINARY_DIR}/test/xdg_runtime
+                XDG_CACHE_HOME=${CMAKE_CURRENT_BINARY_DIR}/test/xdg_cache
                 ./fish_tests
     WORKING_DIRECTORY ${CMAKE_CURRENT_BINARY_DIR}
     DEPENDS fish_tests
@@ -97,6 +100,7 @@ foreach(TESTTYPE test serial_test)
                         env XDG_DATA_HOME=${CMAKE_CURRENT_BINARY_DIR}/test/xdg_data
                             XDG_CONFIG_HOME=${CMAKE_CURRENT_BINARY_DIR}/test/xdg_config
                             XDG_RUNTIME_HOME=${CMAKE_CURRENT_BINARY_DIR}/test/xdg_runtime
+                            XDG_CACHE_HOME=${CMAKE_CURRENT_BINARY_DIR}/test/xdg_cache
                         ${TEST_ROOT_DIR}/bin/fish test.fish
                     DEPENDS test_prep
                     USES_TERMINAL)
@@ -106,6 +110,7 @@ foreach(TESTTYPE test serial_test)
                 env XDG_DATA_HOME=${CMAKE_CURRENT_BINARY_DIR}/test/xdg_data
                     XDG_CONFIG_HOME=${CMAKE_CURRENT_BINARY_DIR}/test/xdg_config
                     XDG_RUNTIME_HOME=${CMAKE_CURRENT_BINARY_DIR}/test/xdg_runtime
+                    XDG_CACHE_HOME=${CMAKE_CURRENT_BINARY_DIR}/test/xdg_cache
                 ${TEST_ROOT_DIR}/bin/fish interactive.fish
       DEPENDS test_prep
       USES_TERMINAL)
@@ -1,2 +1,5 @@
-complete -c cd -a "(__fish_complete_cd)"
+complete -c cd -n "not __fish_seen_argument -l jump" -a "(__fish_complete_cd)"
 complete -c cd -s h -l help -d 'Display help and exit'
+complete -c cd -l jump -d 'Change to the best match of the directories visited before'
+# The directories visited before, best first.
+complete -c cd -n "__fish_seen_argument -l jump" -f -k -a "(builtin cd --jump)"
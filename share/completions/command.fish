@@ -2,4 +2,6 @@ complete -c command -n 'test (count (commandline -opc)) -eq 1' -s h -l help -d '
 complete -c command -n 'test (count (commandline -opc)) -eq 1' -s a -l all -d 'Print all external commands by the given name'
 complete -c command -n 'test (count (commandline -opc)) -eq 1' -s q -l quiet -l query -d 'Do not print anything, only set exit status'
 complete -c command -n 'test (count (commandline -opc)) -eq 1' -s s -l search -d 'Print the file that would be executed'
-complete -c command -xa "(__fish_complete_subcommand)"
+complete -c command -n 'test (count (commandline -opc)) -eq 1' -l nice -x -d 'Run with the niceness increased by the given amount'
+complete -c command -n 'test (count (commandline -opc)) -eq 1' -l ionice -xa 'idle best-effort realtime' -d 'Run with the given I/O scheduling class'
+complete -c command -xa "(__fish_complete_subcommand -- --nice --ionice)"
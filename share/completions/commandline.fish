@@ -19,6 +19,8 @@ complete -c commandline -s C -l cursor -d "Set/get cursor position, not buffer c
 complete -c commandline -s L -l line -d "Print the line that the cursor is on"
 complete -c commandline -s S -l search-mode -d "Return true if performing a history search"
 complete -c commandline -s P -l paging-mode -d "Return true if showing pager content"
+complete -c commandline -s m -l message -d "Show a message below the command line until the next key"
+complete -c commandline -l insert-snippet -d "Insert text with placeholders that tab moves between"
 
 
 complete -c commandline -n '__fish_contains_opt -s f function' -a '(bind --function-names)' -d 'Function name' -x
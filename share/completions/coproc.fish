@@ -0,0 +1,6 @@
+complete -c coproc -a "(__fish_complete_subcommand -- -n --name)" -d Command
+
+complete -c coproc -s n -l name -n __fish_no_arguments -d 'Variable to store the fds in' -x
+complete -c coproc -s l -l local -n __fish_no_arguments -d 'Close the fds when the block ends (default)'
+complete -c coproc -s g -l global -n __fish_no_arguments -d 'Keep the fds open until they are closed'
+complete -c coproc -s h -l help -n __fish_no_arguments -d 'Display help and exit'
@@ -0,0 +1,3 @@
+complete -c emit -s h -l help -d 'Display help and exit'
+complete -c emit -s v -l var -x -d 'Set a variable for the handlers, as NAME=VALUE'
+complete -c emit -l json -x -d 'Set variables for the handlers from a JSON object'
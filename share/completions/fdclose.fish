@@ -0,0 +1,2 @@
+complete -c fdclose -xa "(fdopen | string replace -r '\t(\S+)\t' '\t\$1 ')"
+complete -c fdclose -s h -l help -d 'Display help and exit'
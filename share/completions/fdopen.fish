@@ -0,0 +1,9 @@
+complete -c fdopen -s r -l read -d 'Open for reading (default)'
+complete -c fdopen -s w -l write -d 'Open for writing, truncating the file'
+complete -c fdopen -s a -l append -d 'Open for writing at the end of the file'
+complete -c fdopen -l read-write -d 'Open for reading and writing'
+complete -c fdopen -s s -l socket -d 'Connect to a Unix domain socket'
+complete -c fdopen -s l -l local -d 'Close the fd when the block ends (default)'
+complete -c fdopen -s g -l global -d 'Keep the fd open until it is closed'
+complete -c fdopen -s q -l query -d 'Check if the fds are open'
+complete -c fdopen -s h -l help -d 'Display help and exit'
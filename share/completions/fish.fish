@@ -2,11 +2,13 @@ complete -c fish -s c -l command -d "Run specified command instead of interactiv
 complete -c fish -s C -l init-command -d "Run specified command before session" -x -a "(__fish_complete_command)"
 complete -c fish -s h -l help -d "Display help and exit"
 complete -c fish -s v -l version -d "Display version and exit"
+complete -c fish -s N -l no-config -d "Do not read configuration files"
 complete -c fish -s n -l no-execute -d "Only parse input, do not execute"
 complete -c fish -s i -l interactive -d "Run in interactive mode"
 complete -c fish -s l -l login -d "Run as a login shell"
 complete -c fish -s p -l profile -d "Output profiling information (excluding startup) to a file" -r
 complete -c fish -s p -l profile-startup -d "Output startup profiling information to a file" -r
+complete -c fish -l profile-format -d "Format of the profiling information" -xa "text json callgrind"
 complete -c fish -s d -l debug -d "Specify debug categories" -x -a "(fish --print-debug-categories | string replace ' ' \t)"
 complete -c fish -s o -l debug-output -d "Where to direct debug output to" -rF
 complete -c fish -s D -l debug-stack-frames -d "Show specified # of frames with debug output" -x -a "(seq 128)\t\n"
@@ -18,6 +20,11 @@ function __fish_complete_features
     printf "%s\n" "$arg_comma"$features #TODO: remove existing args
 end
 complete -c fish -s f -l features -d "Run with comma-separated feature flags enabled" -a "(__fish_complete_features)" -x
+complete -c fish -l debug-adapter -d "Run as a debugger for editors"
+complete -c fish -l doctor -d "Check the environment for common problems"
+complete -c fish -l listen -d "Listen on a socket for other programs" -r
+complete -c fish -l lsp -d "Run as a language server for editors"
+complete -c fish -l print-ast -d "Print the syntax tree as JSON, do not execute"
 complete -c fish -l print-rusage-self -d "Print stats from getrusage at exit" -f
 complete -c fish -l print-debug-categories -d "Print the debug categories fish knows" -f
 
@@ -0,0 +1,20 @@
+set -l __fish_config_theme_commands list show choose save dump
+set -l __fish_config_prompt_commands list show choose
+
+complete -c fish_config -f
+complete -c fish_config -s h -l help -d "Display help and exit"
+complete -c fish_config -n __fish_use_subcommand -a theme -d "Manage color themes"
+complete -c fish_config -n __fish_use_subcommand -a prompt -d "Choose a sample prompt"
+complete -c fish_config -n __fish_use_subcommand -a tui -d "Start the configuration in the terminal"
+complete -c fish_config -n "__fish_seen_subcommand_from tui; and test (count (commandline -opc)) -eq 2" -a "prompt theme variables" -d "Start the terminal configuration with this page"
+complete -c fish_config -n __fish_use_subcommand -a "colors prompt functions variables history bindings abbreviations" -d "Start the web-based configuration with this tab"
+complete -c fish_config -n "__fish_seen_subcommand_from theme; and not __fish_seen_subcommand_from $__fish_config_theme_commands" -a list -d "List the available themes"
+complete -c fish_config -n "__fish_seen_subcommand_from theme; and not __fish_seen_subcommand_from $__fish_config_theme_commands" -a show -d "Show what themes look like"
+complete -c fish_config -n "__fish_seen_subcommand_from theme; and not __fish_seen_subcommand_from $__fish_config_theme_commands" -a choose -d "Use a theme in this session"
+complete -c fish_config -n "__fish_seen_subcommand_from theme; and not __fish_seen_subcommand_from $__fish_config_theme_commands" -a save -d "Use a theme or the current colors from now on"
+complete -c fish_config -n "__fish_seen_subcommand_from theme; and not __fish_seen_subcommand_from $__fish_config_theme_commands" -a dump -d "Print the current colors as a theme"
+complete -c fish_config -n "__fish_seen_subcommand_from theme; and __fish_seen_subcommand_from show choose save" -a "(builtin fish_config theme list)"
+complete -c fish_config -n "__fish_seen_subcommand_from prompt; and not __fish_seen_subcommand_from $__fish_config_prompt_commands" -a list -d "List the sample prompts"
+complete -c fish_config -n "__fish_seen_subcommand_from prompt; and not __fish_seen_subcommand_from $__fish_config_prompt_commands" -a show -d "Show what sample prompts look like"
+complete -c fish_config -n "__fish_seen_subcommand_from prompt; and not __fish_seen_subcommand_from $__fish_config_prompt_commands" -a choose -d "Use a sample prompt from now on"
+complete -c fish_config -n "__fish_seen_subcommand_from prompt; and __fish_seen_subcommand_from show choose" -a "(builtin fish_config prompt list)"
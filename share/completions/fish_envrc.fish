@@ -0,0 +1,8 @@
+set -l __fish_envrc_commands allow deny status
+
+complete -c fish_envrc -f
+complete -c fish_envrc -s h -l help -d "Display help and exit"
+complete -c fish_envrc -n "not __fish_seen_subcommand_from $__fish_envrc_commands" -a allow -d "Trust the .envrc.fish file of a directory"
+complete -c fish_envrc -n "not __fish_seen_subcommand_from $__fish_envrc_commands" -a deny -d "Stop trusting the .envrc.fish file of a directory"
+complete -c fish_envrc -n "not __fish_seen_subcommand_from $__fish_envrc_commands" -a status -d "Show which .envrc.fish files are loaded"
+complete -c fish_envrc -n "__fish_seen_subcommand_from allow deny; and test (count (commandline -opc)) -eq 2" -a "(__fish_complete_directories)"
@@ -0,0 +1,4 @@
+complete -c fish_git_status -s b -l background -d "Count changes in the background"
+complete -c fish_git_status -s u -l untracked -d "Count untracked files"
+complete -c fish_git_status -s h -l help -d "Display help and exit"
+complete -c fish_git_status -f -a "git-dir work-tree inside-git-dir inside-work-tree bare branch sha operation step total staged dirty unmerged untracked"
@@ -8,3 +8,7 @@ complete -c fish_indent -s d -l debug -x -d 'Enable debug at specified verbosity
 complete -c fish_indent -s o -l debug-output -d "Where to direct debug output to" -rF
 complete -c fish_indent -s D -l debug-stack-frames -x -d 'Specify how many stack frames to display in debug messages'
 complete -c fish_indent -l dump-parse-tree -d 'Dump information about parsed statements to stderr'
+complete -c fish_indent -l dump-ast -d 'Print the syntax tree as JSON'
+complete -c fish_indent -l indent-width -x -d 'Number of spaces per indentation level'
+complete -c fish_indent -l max-line-length -x -d 'Split pipelines longer than this'
+complete -c fish_indent -l case-style -xa 'indented flat' -d 'How to indent case in switch'
@@ -0,0 +1,2 @@
+complete -c fish_reload_term -f
+complete -c fish_reload_term -s h -l help -d "Display help and exit"
@@ -0,0 +1,2 @@
+complete -c fish_spell_correct -s h -l help -d "Display help and exit"
+complete -c fish_spell_correct -f
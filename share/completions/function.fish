@@ -33,6 +33,9 @@ complete -c function -s p -l on-process-exit -d "Make the function a process exi
 complete -c function -s s -l on-signal -d "Make the function a signal event handler" -xka "(__fish_complete_signals)"
 complete -c function -s v -l on-variable -d "Make the function a variable update event handler" -xa "(__fish_complete_variables)"
 complete -c function -s e -l on-event -d "Make the function a generic event handler" -xa "(__fish_complete_function_event_handlers)"
+complete -c function -l on-interval -d "Make the function run periodically while waiting at the prompt" -x
+complete -c function -l on-file-change -d "Make the function a file change event handler" -rF
+complete -c function -l async -d "Run the event handlers of the function in the background"
 complete -c function -s a -l argument-names -d "Specify named arguments" -x
 complete -c function -s S -l no-scope-shadowing -d "Do not shadow variable scope of calling function"
 complete -c function -s w -l wraps -d "Inherit completions from the given command" -xa "(__fish_complete_command)"
@@ -23,6 +23,11 @@ complete -c history -n '__fish_seen_subcommand_from search' \
 complete -c history -n '__fish_seen_subcommand_from search' \
     -s R -l reverse -d "Output the oldest results first" -x
 
+# Note that this option is only valid with the "merge" subcommand.
+complete -c history -n '__fish_seen_subcommand_from merge' -l strategy -x \
+    -a "interleave\t'Order by time' session\t'Keep own commands the most recent' last-writer-wins\t'Only keep the most recent use of a command'" \
+    -d "How to order the merged history"
+
 # We don't include a completion for the "save" subcommand because it should not be used
 # interactively.
 complete -f -c history -n "not __fish_seen_subcommand_from $__fish_history_all_commands" \
@@ -6,3 +6,4 @@ complete -c jobs -s g -l group -d "Show group id of job"
 complete -c jobs -s c -l command -d "Show commandname of each job"
 complete -c jobs -s l -l last -d "Only show status for last job to be started"
 complete -c jobs -s q -l quiet -l query -d "Check if a job exists without output"
+complete -c jobs -s v -l verbose -d "Show details of each process in the job"
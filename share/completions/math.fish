@@ -1,2 +1,3 @@
 complete -f -c math -r
 complete -f -c math -s s -l scale -r -x
+complete -f -c math -s b -l base -x -a "hex octal 2 8 16" -d "Output the result in the given base"
@@ -0,0 +1,9 @@
+set -l subcommands convert
+
+complete -c path -f
+complete -c path -n "not __fish_seen_subcommand_from $subcommands" -s h -l help -d 'Display help and exit'
+complete -c path -n "not __fish_seen_subcommand_from $subcommands" -a convert -d 'Translate paths between Windows and Unix forms'
+complete -c path -n '__fish_seen_subcommand_from convert' -s u -l unix -d 'Translate Windows paths to the Unix form'
+complete -c path -n '__fish_seen_subcommand_from convert' -s w -l windows -d 'Translate Unix paths to the Windows form'
+complete -c path -n '__fish_seen_subcommand_from convert' -s s -l style -xa 'wsl msys' -d 'Form of the Unix paths'
+complete -c path -n '__fish_seen_subcommand_from convert' -F
@@ -0,0 +1,2 @@
+complete -c prompt_segment -s h -l help -d "Display help and exit"
+complete -c prompt_segment -n 'test (__fish_number_of_cmd_args_wo_opts) -ge 2' -d "Command to run" -x -a '(__fish_complete_subcommand --fcs-skip=2)'
@@ -11,6 +11,7 @@ complete -c read -s m -l mode-name -d "Name to load/save history under" -r -a "r
 complete -c read -s c -l command -d "Initial contents of read buffer when reading interactively" -r
 complete -c read -s S -l shell -d "Use syntax highlighting, tab completions and command termination suitable for entering shellscript code"
 complete -c read -s s -l silent -d "Secure mode: mask characters at the command line (suitable for passwords)"
+complete -c read -l mask -d "Show this character for each one typed in secure mode, or nothing if empty" -x
 complete -c read -s n -l nchars -d "Read the specified number of characters" -x
 complete -c read -s a -l list -l array -d "Store the results as an array"
 complete -c read -s R -l right-prompt -d "Set right-hand prompt command" -x
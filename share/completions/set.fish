@@ -79,9 +79,15 @@ complete -c set -n __fish_is_first_token -s h -l help -d "Display help and exit"
 complete -c set -n __fish_is_first_token -s n -l names -d "List the names of the variables, but not their value"
 complete -c set -n __fish_is_first_token -s a -l append -d "Append value to a list"
 complete -c set -n __fish_is_first_token -s p -l prepend -d "Prepend value to a list"
+complete -c set -n '__fish_seen_argument -s a -l append -s p -l prepend' -l unique -d "Skip values already in the list"
 complete -c set -n __fish_is_first_token -s S -l show -d "Show variable"
 complete -c set -n __fish_is_first_token -l path -d "Make variable as a path variable"
 complete -c set -n __fish_is_first_token -l unpath -d "Make variable not as a path variable"
+complete -c set -n __fish_is_first_token -l host -d "Make universal variable specific to this host"
+complete -c set -n __fish_is_first_token -l export-file -d "Write universal variables to a file" -rF
+complete -c set -n __fish_is_first_token -l import-file -d "Set universal variables from a file" -rF
+complete -c set -n '__fish_seen_argument -l import-file' -l overwrite -d "Replace the values of variables which are set"
+complete -c set -n '__fish_seen_argument -l import-file' -l dry-run -d "Print the changes instead of making them"
 
 #TODO: add CPP code to generate list of read-only variables and exclude them from the following completions
 
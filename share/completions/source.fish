@@ -1,2 +1,3 @@
 complete source -k -xa '(__fish_complete_suffix .fish)'
 complete source -s h -l help -d 'Display help and exit'
+complete source -s k -l keep-going -d 'Skip statements with errors instead of the whole file'
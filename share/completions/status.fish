@@ -1,5 +1,5 @@
 # Note that when a completion file is sourced a new block scope is created so `set -l` works.
-set -l __fish_status_all_commands current-command current-filename current-function current-line-number features filename fish-path function is-block is-breakpoint is-command-substitution is-full-job-control is-interactive is-interactive-job-control is-login is-no-job-control job-control line-number print-stack-trace stack-trace test-feature
+set -l __fish_status_all_commands current-command current-filename current-function current-line-number events fds features filename fish-path function is-block is-breakpoint is-command-substitution is-full-job-control is-interactive is-interactive-job-control is-login is-no-job-control job-control line-number log notify-ready notify-watchdog pipestatus print-stack-trace spawn-stats stack-trace test-feature
 
 # These are the recognized flags.
 complete -c status -s h -l help -d "Display help and exit"
@@ -27,7 +27,31 @@ complete -f -c status -n "not __fish_seen_subcommand_from $__fish_status_all_com
 complete -f -c status -n "not __fish_seen_subcommand_from $__fish_status_all_commands" -a features -d "List all feature flags"
 complete -f -c status -n "not __fish_seen_subcommand_from $__fish_status_all_commands" -a test-feature -d "Test if a feature flag is enabled"
 complete -f -c status -n "__fish_seen_subcommand_from test-feature" -a '(status features)'
+complete -f -c status -n "__fish_seen_subcommand_from features" -l json -d "Print the features as JSON"
+complete -f -c status -n "__fish_seen_subcommand_from stack-trace print-stack-trace" -l json -d "Print the stack trace as JSON"
+complete -f -c status -n "__fish_seen_subcommand_from features; and not __fish_seen_subcommand_from enable disable" -a "enable disable"
+complete -f -c status -n "__fish_seen_subcommand_from features; and __fish_seen_subcommand_from enable disable" -a '(status features)'
 complete -f -c status -n "not __fish_seen_subcommand_from $__fish_status_all_commands" -a fish-path -d "Print the path to the current instance of fish"
+complete -f -c status -n "not __fish_seen_subcommand_from $__fish_status_all_commands" -a spawn-stats -d "Print how external commands were launched"
+complete -f -c status -n "not __fish_seen_subcommand_from $__fish_status_all_commands" -a pipestatus -d "Print the statuses of the last pipeline"
+complete -f -c status -n "__fish_seen_subcommand_from pipestatus" -l pretty -d "Color failed stages"
+complete -f -c status -n "not __fish_seen_subcommand_from $__fish_status_all_commands" -a fds -d "List the open file descriptors"
+complete -f -c status -n "__fish_seen_subcommand_from fds" -l json -d "Print the file descriptors as JSON"
+complete -f -c status -n "not __fish_seen_subcommand_from $__fish_status_all_commands" -a notify-ready -d "Tell the service manager the service is ready"
+complete -f -c status -n "not __fish_seen_subcommand_from $__fish_status_all_commands" -a notify-watchdog -d "Tell the service manager the service is alive"
+
+# The log command shows and changes debug logging.
+complete -f -c status -n "not __fish_seen_subcommand_from $__fish_status_all_commands" -a log -d "List, enable or redirect debug categories"
+complete -f -c status -n "__fish_seen_subcommand_from log; and not __fish_seen_subcommand_from enable disable output" -a enable -d "Turn on debug categories"
+complete -f -c status -n "__fish_seen_subcommand_from log; and not __fish_seen_subcommand_from enable disable output" -a disable -d "Turn off debug categories"
+complete -f -c status -n "__fish_seen_subcommand_from log; and not __fish_seen_subcommand_from enable disable output" -a output -d "Send debug output elsewhere"
+complete -f -c status -n "__fish_seen_subcommand_from log; and __fish_seen_subcommand_from enable disable" -a "(status log | string replace -r '\t(on|off)\t' \t)"
+complete -F -c status -n "__fish_seen_subcommand_from log; and __fish_seen_subcommand_from output" -a "stderr syslog"
+
+# The events command traces event handlers.
+complete -f -c status -n "not __fish_seen_subcommand_from $__fish_status_all_commands" -a events -d "Trace events and their handlers"
+complete -F -c status -n "__fish_seen_subcommand_from events" -l follow -d "Start tracing events, optionally to a file"
+complete -f -c status -n "__fish_seen_subcommand_from events" -l no-follow -d "Stop tracing events"
 
 # The job-control command changes fish state.
 complete -f -c status -n "not __fish_seen_subcommand_from $__fish_status_all_commands" -a job-control -d "Set which jobs are under job control"
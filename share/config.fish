@@ -235,14 +235,12 @@ function fg
     builtin fg (__fish_expand_pid_args $argv)[-1]
 end
 
-function kill
-    command kill (__fish_expand_pid_args $argv)
-end
-
 # As last part of initialization, source the conf directories.
 # Implement precedence (User > Admin > Extra (e.g. vendors) > Fish) by basically doing "basename".
+# The directories are kept so `fish --doctor` can check for snippets that are shadowed.
+set -g __fish_conf_dirs $__fish_config_dir/conf.d $__fish_sysconf_dir/conf.d $vendor_confdirs
 set -l sourcelist
-for file in $__fish_config_dir/conf.d/*.fish $__fish_sysconf_dir/conf.d/*.fish $vendor_confdirs/*.fish
+for file in $__fish_conf_dirs/*.fish
     set -l basename (string replace -r '^.*/' '' -- $file)
     contains -- $basename $sourcelist
     and continue
@@ -250,5 +248,5 @@ for file in $__fish_config_dir/conf.d/*.fish $__fish_sysconf_dir/conf.d/*.fish $
     # Also skip non-files or unreadable files.
     # This allows one to use e.g. symlinks to /dev/null to "mask" something (like in systemd).
     [ -f $file -a -r $file ]
-    and source $file
+    and source --keep-going $file
 end
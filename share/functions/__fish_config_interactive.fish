@@ -222,17 +222,23 @@ function __fish_config_interactive -d "Initializations that should be performed
         __fish_enable_bracketed_paste
     end
 
-    # Similarly, enable TMUX's focus reporting when in tmux.
+    # Similarly, enable focus reporting when in tmux, or to notify about commands which took long.
     # This will be handled by
     # - The keybindings (reading the sequence and triggering an event)
     # - Any listeners (like the vi-cursor)
-    if set -q TMUX
-        and not set -q FISH_UNIT_TESTS_RUNNING
+    if not set -q FISH_UNIT_TESTS_RUNNING
         function __fish_enable_focus --on-event fish_postexec
-            echo -n \e\[\?1004h
+            if set -q TMUX
+                or set -q fish_notify_duration[1]
+                echo -n \e\[\?1004h
+                set -g __fish_focus_reporting
+            end
         end
         function __fish_disable_focus --on-event fish_preexec
-            echo -n \e\[\?1004l
+            if set -q __fish_focus_reporting
+                echo -n \e\[\?1004l
+                set -e __fish_focus_reporting
+            end
         end
         # Note: Don't call this initially because, even though we're in a fish_prompt event,
         # tmux reacts sooo quickly that we'll still get a sequence before we're prepared for it.
@@ -241,6 +247,46 @@ function __fish_config_interactive -d "Initializations that should be performed
         # __fish_enable_focus
     end
 
+    # Notify about commands which took at least $fish_notify_duration milliseconds, if the terminal
+    # is not focused when they finish. Terminals like tmux report whether they are focused
+    # when focus reporting is turned on again after the command.
+    function __fish_notify_postexec --on-event fish_postexec
+        set -l cmd_status $status
+        set -e __fish_notify_pending
+        string match -qr '^\d+$' -- "$fish_notify_duration"
+        and test "$CMD_DURATION" -ge $fish_notify_duration
+        and set -g __fish_notify_pending $argv[1] $CMD_DURATION $cmd_status
+    end
+    function __fish_notify_focus_out --on-event fish_focus_out
+        if set -q __fish_notify_pending[1]
+            __fish_notify_command_done $__fish_notify_pending
+            set -e __fish_notify_pending
+        end
+    end
+    function __fish_notify_cancel --on-event fish_focus_in --on-event fish_preexec
+        set -e __fish_notify_pending
+    end
+
+    # If $fish_spell_correction is set, offer to run a failed command line again with mistyped
+    # commands and cd targets corrected.
+    function __fish_spell_correct_postexec --on-event fish_postexec
+        test $status -ne 0
+        and set -q fish_spell_correction[1]
+        and not contains -- "$fish_spell_correction" 0 false
+        or return
+
+        set -l corrected (fish_spell_correct -- $argv[1] | string collect)
+        or return
+        set -l bold (set_color --bold)
+        set -l normal (set_color normal)
+        read -l -n 1 -P (printf (_ "Did you mean %s? [y/N] ") "$bold$corrected$normal") reply
+        or return
+        if string match -qi y -- $reply
+            commandline -r -- $corrected
+            commandline -f execute
+        end
+    end
+
     # Detect whether the terminal reflows on its own
     # If it does we shouldn't do it.
     # Allow $fish_handle_reflow to override it.
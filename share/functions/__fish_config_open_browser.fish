@@ -0,0 +1,23 @@
+# Open the web-based configuration of fish_config in a web browser.
+# file_url is a file which redirects to url, so the key in url is not on the browser's command line.
+function __fish_config_open_browser --argument-names file_url url
+    if set -q BROWSER[1]
+        echo $BROWSER | read -at browser
+        $browser $file_url >/dev/null 2>&1 &
+        disown $last_pid 2>/dev/null
+    else if test (uname) = Darwin
+        command open $file_url
+    else if string match -qi '*microsoft*' </proc/version 2>/dev/null
+        # The browsers of Windows can't open our temporary file.
+        cmd.exe /c start $url
+    else if command -sq termux-open-url
+        termux-open-url $url
+    else if command -sq xdg-open
+        # xdg-open may block until the browser exits.
+        xdg-open $file_url >/dev/null 2>&1 &
+        disown $last_pid 2>/dev/null
+    else
+        printf (_ '%s: Could not find a web browser. Open the address above in one.\n') fish_config >&2
+        return 1
+    end
+end
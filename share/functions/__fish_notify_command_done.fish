@@ -0,0 +1,39 @@
+# Tell the user that a command which took long has finished, as configured by $fish_notify_method.
+# Called with the command line, its duration in milliseconds and its exit status.
+function __fish_notify_command_done --argument-names cmdline duration cmd_status
+    set -l seconds (math --scale=0 $duration / 1000)
+    # Terminals would interpret control characters, and the ";" separates the title in OSC 777.
+    set -l cmd (string replace -ra '[[:cntrl:];]' ' ' -- $cmdline)
+    if test (string length -- $cmd) -gt 60
+        set cmd (string sub -l 59 -- $cmd)…
+    end
+    set -l body (printf (_ "'%s' finished after %ss") $cmd $seconds)
+    if test "$cmd_status" != 0
+        set body (printf (_ "'%s' failed with status %s after %ss") $cmd $cmd_status $seconds)
+    end
+
+    set -q fish_notify_method[1]
+    or set -l fish_notify_method bell
+    for method in $fish_notify_method
+        set -l seq
+        switch $method
+            case bell
+                printf \a
+            case osc777
+                set seq \e"]777;notify;fish;$body"\e\\
+            case osc9
+                set seq \e"]9;$body"\e\\
+            case event
+                emit fish_notify $cmdline $duration $cmd_status
+            case '*'
+                printf (_ "%s: Unknown notification method '%s'\n") fish_notify_method $method >&2
+        end
+        if set -q seq[1]
+            # tmux only passes them on to the terminal inside its own sequence.
+            if set -q TMUX
+                set seq \ePtmux\;(string replace -a \e \e\e -- $seq)\e\\
+            end
+            printf %s $seq
+        end
+    end
+end
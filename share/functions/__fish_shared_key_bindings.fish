@@ -118,12 +118,11 @@ function __fish_shared_key_bindings -d "Bindings shared between emacs and vi mod
     bind --preset $argv \ee edit_command_buffer
     bind --preset $argv \ev edit_command_buffer
 
-    # Tmux' focus events.
+    # Focus events, which tmux and some terminals report when focus reporting is on.
     # Exclude paste mode because that should get _everything_ literally.
     for mode in (bind --list-modes | string match -v paste)
-        # We only need the in-focus event currently (to redraw the vi-cursor).
         bind --preset -M $mode \e\[I 'emit fish_focus_in'
-        bind --preset -M $mode \e\[O false
+        bind --preset -M $mode \e\[O 'emit fish_focus_out'
         bind --preset -M $mode \e\[\?1004h false
     end
 
@@ -4,7 +4,9 @@
 function cd --description "Change directory"
     set -l MAX_DIR_HIST 25
 
-    if test (count $argv) -gt (test "$argv[1]" = "--" && echo 2 || echo 1)
+    # With --jump, the arguments are patterns for builtin cd.
+    if test "$argv[1]" != --jump
+        and test (count $argv) -gt (test "$argv[1]" = "--" && echo 2 || echo 1)
         printf "%s\n" (_ "Too many args for cd command")
         return 1
     end
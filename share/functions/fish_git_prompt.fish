@@ -341,15 +341,15 @@ function fish_git_prompt --description "Prompt function for Git"
     if not command -sq git
         return 1
     end
-    set -l repo_info (command git rev-parse --git-dir --is-inside-git-dir --is-bare-repository --is-inside-work-tree HEAD 2>/dev/null)
-    test -n "$repo_info"
+    # This reads the repository without running git, which is a lot faster in large ones.
+    set -l repo_info (fish_git_status git-dir inside-git-dir bare inside-work-tree sha operation step total branch)
     or return
 
     set -l git_dir $repo_info[1]
     set -l inside_gitdir $repo_info[2]
     set -l bare_repo $repo_info[3]
     set -l inside_worktree $repo_info[4]
-    set -q repo_info[5]
+    test -n "$repo_info[5]"
     and set -l sha $repo_info[5]
 
     set -l rbc (__fish_git_prompt_operation_branch_bare $repo_info)
@@ -408,13 +408,26 @@ function fish_git_prompt --description "Prompt function for Git"
                 set informative_status "$space$informative_status"
             end
         else
+            # Count the changes once, in the background when interactive.
+            # Conflicts show up in both the index and the work tree.
+            set -l fields staged dirty unmerged
+            test "$untracked" = true
+            and set -a fields untracked
+            set -l changes
+            if test "$dirty" = true
+                or test "$untracked" = true
+                set changes (fish_git_status --background $fields | string replace -r '^$' 0)
+            end
+
             # This has to be set explicitly.
             if test "$dirty" = true
-                set w (__fish_git_prompt_dirty)
-                if test -n "$sha"
-                    set i (__fish_git_prompt_staged)
-                else
+                if test (math $changes[2] + $changes[3]) -gt 0
+                    set w $___fish_git_prompt_char_dirtystate
+                end
+                if test -z "$sha"
                     set i $___fish_git_prompt_char_invalidstate
+                else if test (math $changes[1] + $changes[3]) -gt 0
+                    set i $___fish_git_prompt_char_stagedstate
                 end
             end
 
@@ -424,7 +437,8 @@ function fish_git_prompt --description "Prompt function for Git"
             end
 
             if test "$untracked" = true
-                set u (__fish_git_prompt_untracked)
+                and test $changes[4] -gt 0
+                set u $___fish_git_prompt_char_untrackedfiles
             end
         end
 
@@ -491,39 +505,16 @@ end
 
 ### helper functions
 
-function __fish_git_prompt_staged --description "fish_git_prompt helper, tells whether or not the current branch has staged files"
-    # The "diff" functions all return > 0 if there _is_ a diff,
-    # but we want to return 0 if there are staged changes.
-    # So we invert the status.
-    not command git diff-index --cached --quiet HEAD -- 2>/dev/null
-    and echo $___fish_git_prompt_char_stagedstate
-end
-
-function __fish_git_prompt_untracked --description "fish_git_prompt helper, tells whether or not the current repository has untracked files"
-    command git ls-files --others --exclude-standard --directory --no-empty-directory --error-unmatch -- :/ >/dev/null 2>&1
-    and echo $___fish_git_prompt_char_untrackedfiles
-end
-
-function __fish_git_prompt_dirty --description "fish_git_prompt helper, tells whether or not the current branch has tracked, modified files"
-    # Like staged, invert the status because we want 0 to mean there are dirty files.
-    not command git diff --no-ext-diff --quiet --exit-code 2>/dev/null
-    and echo $___fish_git_prompt_char_dirtystate
-end
-
 set -g ___fish_git_prompt_status_order stagedstate invalidstate dirtystate untrackedfiles stashstate
 
 function __fish_git_prompt_informative_status
-
-    set -l changedFiles (command git diff --name-status 2>/dev/null | string match -r \\w)
-    set -l stagedFiles (command git diff --staged --name-status | string match -r \\w)
-
-    set -l x (count $changedFiles)
-    set -l y (count (string match -r "U" -- $changedFiles))
-    set -l dirtystate (math $x - $y)
-    set -l x (count $stagedFiles)
-    set -l invalidstate (count (string match -r "U" -- $stagedFiles))
-    set -l stagedstate (math $x - $invalidstate)
-    set -l untrackedfiles (command git ls-files --others --exclude-standard :/ | count)
+    # Conflicts are only counted as conflicts, not as staged or dirty.
+    # Counts which aren't known (yet) are empty.
+    set -l changes (fish_git_status --background staged dirty unmerged untracked | string replace -r '^$' 0)
+    set -l stagedstate $changes[1]
+    set -l dirtystate $changes[2]
+    set -l invalidstate $changes[3]
+    set -l untrackedfiles $changes[4]
     set -l stashstate 0
     set -l stashfile "$argv[1]/logs/refs/stash"
     if set -q __fish_git_prompt_showstashstate; and test -e "$stashfile"
@@ -571,46 +562,18 @@ function __fish_git_prompt_operation_branch_bare --description "fish_git_prompt
     set -l git_dir $argv[1]
     set -l inside_gitdir $argv[2]
     set -l bare_repo $argv[3]
-    set -q argv[5]
+    test -n "$argv[5]"
     and set -l sha $argv[5]
 
-    set -l branch
-    set -l operation
+    set -l operation $argv[6]
+    set -l step $argv[7]
+    set -l total $argv[8]
+    set -l branch $argv[9]
     set -l detached no
     set -l bare
-    set -l step
-    set -l total
-
-    if test -d $git_dir/rebase-merge
-        set branch (cat $git_dir/rebase-merge/head-name 2>/dev/null)
-        set step (cat $git_dir/rebase-merge/msgnum 2>/dev/null)
-        set total (cat $git_dir/rebase-merge/end 2>/dev/null)
-        if test -f $git_dir/rebase-merge/interactive
-            set operation "|REBASE-i"
-        else
-            set operation "|REBASE-m"
-        end
-    else
-        if test -d $git_dir/rebase-apply
-            set step (cat $git_dir/rebase-apply/next 2>/dev/null)
-            set total (cat $git_dir/rebase-apply/last 2>/dev/null)
-            if test -f $git_dir/rebase-apply/rebasing
-                set branch (cat $git_dir/rebase-apply/head-name 2>/dev/null)
-                set operation "|REBASE"
-            else if test -f $git_dir/rebase-apply/applying
-                set operation "|AM"
-            else
-                set operation "|AM/REBASE"
-            end
-        else if test -f $git_dir/MERGE_HEAD
-            set operation "|MERGING"
-        else if test -f $git_dir/CHERRY_PICK_HEAD
-            set operation "|CHERRY-PICKING"
-        else if test -f $git_dir/REVERT_HEAD
-            set operation "|REVERTING"
-        else if test -f $git_dir/BISECT_LOG
-            set operation "|BISECTING"
-        end
+
+    if test -n "$operation"
+        set operation "|$operation"
     end
 
     if test -n "$step" -a -n "$total"
@@ -618,9 +581,8 @@ function __fish_git_prompt_operation_branch_bare --description "fish_git_prompt
     end
 
     if test -z "$branch"
-        if not set branch (command git symbolic-ref HEAD 2>/dev/null)
-            set detached yes
-            set branch (switch "$__fish_git_prompt_describe_style"
+        set detached yes
+        set branch (switch "$__fish_git_prompt_describe_style"
 						case contains
 							command git describe --contains HEAD
 						case branch
@@ -630,17 +592,16 @@ function __fish_git_prompt_operation_branch_bare --description "fish_git_prompt
 						case default '*'
 							command git describe --tags --exact-match HEAD
 						end 2>/dev/null)
-            if test $status -ne 0
-                # Shorten the sha ourselves to 8 characters - this should be good for most repositories,
-                # and even for large ones it should be good for most commits
-                if set -q sha
-                    set branch (string match -r '^.{8}' -- $sha)…
-                else
-                    set branch unknown
-                end
+        if test $status -ne 0
+            # Shorten the sha ourselves to 8 characters - this should be good for most repositories,
+            # and even for large ones it should be good for most commits
+            if set -q sha
+                set branch (string match -r '^.{8}' -- $sha)…
+            else
+                set branch unknown
             end
-            set branch "($branch)"
         end
+        set branch "($branch)"
     end
 
     if test true = $inside_gitdir
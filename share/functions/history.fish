@@ -19,6 +19,7 @@ function history --description "display or manipulate interactive command histor
     set -l options --exclusive 'c,e,p' --exclusive 'S,D,M,V,X'
     set -a options h/help c/contains e/exact p/prefix
     set -a options C/case-sensitive R/reverse z/null 't/show-time=?' 'n#max'
+    set -a options strategy=
     # The following options are deprecated and will be removed in the next major release.
     # Note that they do not have usable short flags.
     set -a options S-search D-delete M-merge V-save X-clear
@@ -173,7 +174,10 @@ function history --description "display or manipulate interactive command histor
             __fish_unexpected_hist_args $argv
             and return 1
 
-            builtin history merge -- $argv
+            set -l strategy
+            set -q _flag_strategy
+            and set strategy --strategy=$_flag_strategy
+            builtin history merge $strategy -- $argv
 
         case clear # clear the interactive command history
             __fish_unexpected_hist_args $argv
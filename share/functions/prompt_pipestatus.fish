@@ -0,0 +1,10 @@
+function prompt_pipestatus --description 'Print the statuses of the last pipeline for the prompt if it failed'
+    # This has to come first, before any other command changes $pipestatus.
+    set -l statuses $pipestatus
+    set -q argv[1]
+    and set statuses $argv
+
+    set -l pretty (status pipestatus --pretty $statuses)
+    and return 1
+    printf '[%s]' $pretty
+end
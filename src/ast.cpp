@@ -6,8 +6,10 @@
 
 #include "common.h"
 #include "flog.h"
+#include "json.h"
 #include "parse_constants.h"
 #include "parse_tree.h"
+#include "wcstringutil.h"
 #include "wutil.h"
 
 namespace {
@@ -173,7 +175,9 @@ class token_stream_t {
         result.keyword = keyword_for_token(token.type, text);
         result.has_dash_prefix = !text.empty() && text.at(0) == L'-';
         result.is_help_argument = (text == L"-h" || text == L"--help");
-        result.is_newline = (result.type == parse_token_type_t::end && text == L"\n");
+        // A newline which ends the line of a here-document also holds the body.
+        result.is_newline = (result.type == parse_token_type_t::end && !text.empty() &&
+                             text.front() == L'\n');
         result.may_be_variable_assignment = variable_assignment_equals_pos(text).has_value();
         result.tok_error = token.error;
 
@@ -964,6 +968,11 @@ class ast_t::populator_t {
             // This may happen if we just have a 'time' prefix.
             // Construct a decorated statement, which will be unsourced.
             return this->allocate_visit<decorated_statement_t>();
+        } else if (token1.type == parse_token_type_t::tokenizer_error) {
+            // For example a here-document whose line continues, in `cat <<END |`.
+            parse_error(token1, parse_error_from_tokenizer_error(token1.tok_error), L"%ls",
+                        tokenizer_get_error_message(token1.tok_error));
+            return got_error();
         } else if (token1.type != parse_token_type_t::string) {
             // We may be unwinding already; do not produce another error.
             // For example in `true | and`.
@@ -1111,9 +1120,15 @@ class ast_t::populator_t {
 
         if (!token.allows_token(peek_token().type)) {
             const auto &peek = peek_token();
-            parse_error(peek, parse_error_generic, L"Expected %ls, but found %ls",
-                        token_types_user_presentable_description({TokTypes...}).c_str(),
-                        peek.user_presentable_description().c_str());
+            if (peek.type == parse_token_type_t::tokenizer_error) {
+                // Prefer the tokenizer's own description, e.g. for an unterminated here-document.
+                parse_error(peek, parse_error_from_tokenizer_error(peek.tok_error), L"%ls",
+                            tokenizer_get_error_message(peek.tok_error));
+            } else {
+                parse_error(peek, parse_error_generic, L"Expected %ls, but found %ls",
+                            token_types_user_presentable_description({TokTypes...}).c_str(),
+                            peek.user_presentable_description().c_str());
+            }
             token.unsourced = true;
             return;
         }
@@ -1291,6 +1306,250 @@ ast_t ast_t::parse_argument_list(const wcstring &src, parse_tree_flags_t flags,
     return parse_from_top(src, flags, out_errors, type_t::freestanding_argument_list);
 }
 
+namespace {
+/// Writes nodes in a compact binary form, for ast_t::serialize(). Each leaf is written as whether
+/// it is unsourced, followed by its source range and keyword or token type; lists as their count
+/// followed by their contents; optional fields as whether they are present; and unions as the type
+/// of their contents. Numbers are written seven bits at a time, with the high bit set on all but
+/// the last byte.
+class ast_writer_t {
+   public:
+    explicit ast_writer_t(std::string &out) : out_(out) {}
+
+    void write_number(uint32_t num) {
+        while (num >= 0x80) {
+            out_.push_back(static_cast<char>((num & 0x7F) | 0x80));
+            num >>= 7;
+        }
+        out_.push_back(static_cast<char>(num));
+    }
+
+    void write_range(source_range_t range) {
+        write_number(range.start);
+        write_number(range.length);
+    }
+
+    template <typename Node>
+    enable_if_t<Node::Category == category_t::branch> visit_node_field(const Node &node) {
+        const_cast<Node &>(node).accept(*this);
+    }
+
+    template <typename Node>
+    enable_if_t<Node::Category == category_t::leaf> visit_node_field(const Node &node) {
+        write_number(node.unsourced);
+        if (node.unsourced) return;
+        write_range(node.range);
+        write_leaf_contents(node);
+    }
+
+    void write_leaf_contents(const node_t &) {}
+    void write_leaf_contents(const keyword_base_t &kw) {
+        write_number(static_cast<uint32_t>(kw.kw));
+    }
+    void write_leaf_contents(const token_base_t &tok) {
+        write_number(static_cast<uint32_t>(tok.type));
+    }
+
+    template <type_t ListType, typename ContentsNode>
+    void visit_list_field(const list_t<ListType, ContentsNode> &list) {
+        write_number(list.length);
+        for (const ContentsNode &node : list) visit_node_field(node);
+    }
+
+    template <typename Node>
+    void visit_optional_field(const optional_t<Node> &opt) {
+        write_number(opt.contents != nullptr);
+        if (opt.contents) visit_node_field(*opt.contents);
+    }
+
+    template <typename... Nodes>
+    void visit_union_field(const union_ptr_t<Nodes...> &ptr) {
+        write_number(static_cast<uint32_t>(ptr->type));
+        write_union<Nodes...>(*ptr.get());
+    }
+
+    template <typename Node, typename Next, typename... Rest>
+    void write_union(const node_t &node) {
+        if (node.type != Node::AstType) return write_union<Next, Rest...>(node);
+        visit_node_field(*node.as<Node>());
+    }
+
+    template <typename Node>
+    void write_union(const node_t &node) {
+        visit_node_field(*node.as<Node>());
+    }
+
+    void will_visit_fields_of(const node_t &) {}
+    void did_visit_fields_of(const node_t &) {}
+
+   private:
+    std::string &out_;
+};
+
+/// Reads nodes written by ast_writer_t, for ast_t::deserialize(). Anything which could not have
+/// been written for the source, like a range past its end or a keyword which is not allowed in its
+/// place, makes it fail.
+class ast_reader_t {
+   public:
+    ast_reader_t(const std::string &data, size_t src_length)
+        : data_(data), src_length_(src_length) {}
+
+    /// \return whether the data was malformed.
+    bool failed() const { return failed_; }
+
+    /// \return whether all of the data was read.
+    bool at_end() const { return pos_ == data_.size(); }
+
+    uint32_t read_number() {
+        uint32_t result = 0;
+        for (int shift = 0; shift < 35 && pos_ < data_.size(); shift += 7) {
+            auto byte = static_cast<unsigned char>(data_[pos_++]);
+            result |= static_cast<uint32_t>(byte & 0x7F) << shift;
+            if (!(byte & 0x80)) return result;
+        }
+        failed_ = true;
+        return 0;
+    }
+
+    /// Read a count of things which each take at least a byte, so a bogus count fails before
+    /// anything is allocated.
+    uint32_t read_count() {
+        uint32_t count = read_number();
+        if (count > data_.size() - pos_) {
+            failed_ = true;
+            return 0;
+        }
+        return count;
+    }
+
+    source_range_t read_range() {
+        source_range_t range;
+        range.start = read_number();
+        range.length = read_number();
+        if (static_cast<uint64_t>(range.start) + range.length > src_length_) failed_ = true;
+        return range;
+    }
+
+    template <typename Node>
+    enable_if_t<Node::Category == category_t::branch> visit_node_field(Node &node) {
+        if (!failed_) node.accept(*this);
+    }
+
+    template <typename Node>
+    enable_if_t<Node::Category == category_t::leaf> visit_node_field(Node &node) {
+        if (failed_) return;
+        node.unsourced = read_number() != 0;
+        if (node.unsourced) return;
+        node.range = read_range();
+        read_leaf_contents(node);
+    }
+
+    void read_leaf_contents(node_t &) {}
+
+    template <parse_keyword_t... KWs>
+    void read_leaf_contents(keyword_t<KWs...> &kw) {
+        kw.kw = static_cast<parse_keyword_t>(read_number());
+        if (!kw.allows_keyword(kw.kw)) failed_ = true;
+    }
+
+    template <parse_token_type_t... Toks>
+    void read_leaf_contents(token_t<Toks...> &tok) {
+        tok.type = static_cast<parse_token_type_t>(read_number());
+        if (!tok.allows_token(tok.type)) failed_ = true;
+    }
+
+    template <type_t ListType, typename ContentsNode>
+    void visit_list_field(list_t<ListType, ContentsNode> &list) {
+        if (failed_) return;
+        uint32_t count = read_count();
+        if (count == 0) return;
+        using contents_ptr_t = typename list_t<ListType, ContentsNode>::contents_ptr_t;
+        auto *array = new contents_ptr_t[count];
+        list.length = count;
+        list.contents = array;
+        for (uint32_t i = 0; i < count && !failed_; i++) {
+            auto node = make_unique<ContentsNode>();
+            visit_node_field(*node);
+            array[i] = std::move(node);
+        }
+    }
+
+    template <typename Node>
+    void visit_optional_field(optional_t<Node> &opt) {
+        if (failed_ || !read_number()) return;
+        opt.contents = make_unique<Node>();
+        visit_node_field(*opt.contents);
+    }
+
+    template <typename... Nodes>
+    void visit_union_field(union_ptr_t<Nodes...> &ptr) {
+        if (failed_) return;
+        auto type = static_cast<type_t>(read_number());
+        read_union<union_ptr_t<Nodes...>, Nodes...>(ptr, type);
+    }
+
+    template <typename Union, typename Node, typename Next, typename... Rest>
+    void read_union(Union &ptr, type_t type) {
+        if (type != Node::AstType) return read_union<Union, Next, Rest...>(ptr, type);
+        read_union<Union, Node>(ptr, type);
+    }
+
+    template <typename Union, typename Node>
+    void read_union(Union &ptr, type_t type) {
+        if (type != Node::AstType) {
+            failed_ = true;
+            return;
+        }
+        auto node = make_unique<Node>();
+        visit_node_field(*node);
+        ptr = std::move(node);
+    }
+
+    void will_visit_fields_of(node_t &) {}
+    void did_visit_fields_of(node_t &) {}
+
+   private:
+    const std::string &data_;
+    size_t pos_{0};
+    const size_t src_length_;
+    bool failed_{false};
+};
+}  // namespace
+
+std::string ast_t::serialize() const {
+    assert(top_->type == type_t::job_list && "Only job lists can be serialized");
+    std::string out;
+    ast_writer_t writer(out);
+    writer.write_number(any_error_);
+    for (const source_range_list_t *ranges :
+         {&extras_.comments, &extras_.semis, &extras_.errors}) {
+        writer.write_number(static_cast<uint32_t>(ranges->size()));
+        for (source_range_t range : *ranges) writer.write_range(range);
+    }
+    writer.visit_list_field(*top_->as<job_list_t>());
+    return out;
+}
+
+// static
+maybe_t<ast_t> ast_t::deserialize(const std::string &data, const wcstring &src) {
+    ast_t ast;
+    ast_reader_t reader(data, src.size());
+    ast.any_error_ = reader.read_number() != 0;
+    for (source_range_list_t *ranges :
+         {&ast.extras_.comments, &ast.extras_.semis, &ast.extras_.errors}) {
+        uint32_t count = reader.read_count();
+        for (uint32_t i = 0; i < count && !reader.failed(); i++) {
+            ranges->push_back(reader.read_range());
+        }
+    }
+    auto list = make_unique<job_list_t>();
+    reader.visit_list_field(*list);
+    if (reader.failed() || !reader.at_end()) return none();
+    ast.top_.reset(list.release());
+    set_parents(ast.top());
+    return ast;
+}
+
 // \return the depth of a node, i.e. number of parent links.
 static int get_depth(const node_t *node) {
     int result = 0;
@@ -1358,4 +1617,99 @@ wcstring ast_t::dump(const wcstring &orig) const {
     }
     return result;
 }
+
+/// Append the JSON members for the source range \p range, which may be none if unsourced.
+static void append_json_range(wcstring &out, maybe_t<source_range_t> range) {
+    if (range) {
+        append_format(out, L"\"start\":%u,\"length\":%u", range->start, range->length);
+    } else {
+        out.append(L"\"start\":null,\"length\":null");
+    }
+}
+
+namespace {
+/// Visitor which appends each node and its children as a JSON object.
+struct json_dumper_t {
+    json_dumper_t(const wcstring &orig, wcstring &out) : orig(orig), out(out) {}
+
+    const wcstring &orig;
+    wcstring &out;
+
+    void visit(const node_t &node) {
+        out.append(L"{\"type\":");
+        out.append(json_string(ast_type_to_string(node.type)));
+        out.push_back(L',');
+        auto range = node.try_source_range();
+        append_json_range(out, range);
+        if (const auto *n = node.try_as<keyword_base_t>()) {
+            out.append(L",\"keyword\":");
+            out.append(json_string(keyword_description(n->kw)));
+        } else if (const auto *n = node.try_as<token_base_t>()) {
+            wcstring desc = token_type_description(n->type);
+            const wcstring prefix = L"parse_token_type_t::";
+            if (string_prefixes_string(prefix, desc)) desc.erase(0, prefix.size());
+            out.append(L",\"token\":");
+            out.append(json_string(desc));
+        }
+
+        if (node.category == category_t::leaf) {
+            if (range) {
+                out.append(L",\"source\":");
+                out.append(json_string(orig.substr(range->start, range->length)));
+            }
+        } else {
+            out.append(L",\"children\":[");
+            needs_comma = false;
+            node_visitor(*this).accept_children_of(&node);
+            out.push_back(L']');
+        }
+        out.push_back(L'}');
+        needs_comma = true;
+    }
+
+    // Callback for node_visitation_t, adding commas between siblings.
+    template <typename Node>
+    void visit(const Node &node) {
+        if (needs_comma) out.push_back(L',');
+        visit(static_cast<const node_t &>(node));
+    }
+
+    bool needs_comma{false};
+};
+}  // namespace
+
+wcstring ast_t::dump_json(const wcstring &orig, const parse_error_list_t &errors) const {
+    wcstring result = L"{\"ast\":";
+    json_dumper_t dumper{orig, result};
+    dumper.visit(*top());
+
+    result.append(L",\"comments\":[");
+    for (size_t i = 0; i < extras_.comments.size(); i++) {
+        if (i > 0) result.push_back(L',');
+        const source_range_t &range = extras_.comments.at(i);
+        result.push_back(L'{');
+        append_json_range(result, range);
+        result.append(L",\"source\":");
+        result.append(json_string(orig.substr(range.start, range.length)));
+        result.push_back(L'}');
+    }
+
+    result.append(L"],\"errors\":[");
+    for (size_t i = 0; i < errors.size(); i++) {
+        if (i > 0) result.push_back(L',');
+        const parse_error_t &err = errors.at(i);
+        result.push_back(L'{');
+        maybe_t<source_range_t> range{};
+        if (err.source_start != SOURCE_LOCATION_UNKNOWN) {
+            range = source_range_t{static_cast<uint32_t>(err.source_start),
+                                   static_cast<uint32_t>(err.source_length)};
+        }
+        append_json_range(result, range);
+        result.append(L",\"message\":");
+        result.append(json_string(err.text));
+        result.push_back(L'}');
+    }
+    result.append(L"]}\n");
+    return result;
+}
 }  // namespace ast
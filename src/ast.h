@@ -987,6 +987,20 @@ class ast_t {
     /// Pass the original source as \p orig.
     wcstring dump(const wcstring &orig) const;
 
+    /// \return a JSON representation of the tree, for use by other tools. Each node has its type
+    /// and source range, in characters. Comments are included if the ast was parsed with
+    /// parse_flag_include_comments. Pass the original source as \p orig, and any parse errors as
+    /// \p errors.
+    wcstring dump_json(const wcstring &orig, const parse_error_list_t &errors) const;
+
+    /// \return the ast, which must be a job list, in a compact binary form. The source is not
+    /// included. This is used to cache the parsed form of files.
+    std::string serialize() const;
+
+    /// \return the ast serialized as \p data, for the source \p src, or none() if the data is
+    /// malformed or does not fit the source.
+    static maybe_t<ast_t> deserialize(const std::string &data, const wcstring &src);
+
     /// Extra source ranges.
     /// These are only generated if the corresponding flags are set.
     struct extras_t {
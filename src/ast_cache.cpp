@@ -0,0 +1,101 @@
+// Caching the parsed form of autoloaded files on disk.
+//
+// Prompts and plugin frameworks can autoload thousands of lines every time fish starts. Instead of
+// parsing and checking them again each time, the ast of an autoloaded file is stored in the cache
+// directory after it is first parsed, and used for as long as neither the file nor fish changes.
+// Each file has one cache file, named after a hash of its path, which starts with a header saying
+// what it was made from.
+#include "config.h"  // IWYU pragma: keep
+
+#include "ast_cache.h"
+
+#include <errno.h>
+#include <stdio.h>
+#include <unistd.h>
+
+#include <string>
+
+#include "fallback.h"  // IWYU pragma: keep
+#include "fish_version.h"
+#include "flog.h"
+#include "future_feature_flags.h"
+#include "path.h"
+#include "wutil.h"  // IWYU pragma: keep
+
+/// \return the path of the cache file for the file at \p path, or none() if there is no cache
+/// directory.
+static maybe_t<wcstring> cache_file_path(const wcstring &path) {
+    wcstring dir;
+    if (!path_get_cache(dir)) return none();
+
+    // FNV-1a, which unlike std::hash is the same for every build.
+    uint64_t hash = 14695981039346656037ULL;
+    for (char c : wcs2string(path)) {
+        hash ^= static_cast<unsigned char>(c);
+        hash *= 1099511628211ULL;
+    }
+    return dir + format_string(L"/ast/%016llx", static_cast<unsigned long long>(hash));
+}
+
+/// \return the header of the cache file for the file at \p path, when it is \p file_id and parsed
+/// with the current feature flags.
+static std::string cache_header(const wcstring &path, const file_id_t &file_id) {
+    std::string header = "fish-ast\n";
+    header.append(get_fish_version());
+    header.push_back('\n');
+    header.append(wcs2string(path));
+    header.push_back('\n');
+    // Flags which can't change at runtime are the ones which change how code is parsed.
+    for (const auto &md : features_t::metadata) {
+        if (md.runtime) continue;
+        header.append(wcs2string(md.name));
+        header.append(feature_test(md.flag) ? "=1 " : "=0 ");
+    }
+    header.back() = '\n';
+    for (long long num : {static_cast<long long>(file_id.device),
+                          static_cast<long long>(file_id.inode),
+                          static_cast<long long>(file_id.size),
+                          static_cast<long long>(file_id.change_seconds),
+                          static_cast<long long>(file_id.change_nanoseconds),
+                          static_cast<long long>(file_id.mod_seconds),
+                          static_cast<long long>(file_id.mod_nanoseconds)}) {
+        header.append(std::to_string(num));
+        header.push_back(' ');
+    }
+    header.back() = '\n';
+    return header;
+}
+
+maybe_t<ast::ast_t> ast_cache_load(const wcstring &path, const file_id_t &file_id,
+                                   const wcstring &src) {
+    if (file_id == kInvalidFileID) return none();
+    auto cache_path = cache_file_path(path);
+    if (!cache_path) return none();
+    maybe_t<std::string> contents = read_file_contents(*cache_path);
+    if (!contents) return none();
+
+    std::string header = cache_header(path, file_id);
+    if (contents->compare(0, header.size(), header) != 0) {
+        FLOGF(ast_cache, L"The cached ast of '%ls' is out of date", path.c_str());
+        return none();
+    }
+    contents->erase(0, header.size());
+    auto ast = ast::ast_t::deserialize(*contents, src);
+    if (!ast) {
+        FLOGF(ast_cache, L"The cached ast of '%ls' is malformed", path.c_str());
+        return none();
+    }
+    FLOGF(ast_cache, L"Loaded the cached ast of '%ls'", path.c_str());
+    return ast;
+}
+
+void ast_cache_store(const wcstring &path, const file_id_t &file_id, const ast::ast_t &ast) {
+    if (file_id == kInvalidFileID) return;
+    auto cache_path = cache_file_path(path);
+    if (!cache_path) return;
+    if (wmkdir(wdirname(*cache_path), 0700) != 0 && errno != EEXIST) return;
+
+    std::string contents = cache_header(path, file_id) + ast.serialize();
+    if (write_file_atomically(wcs2string(*cache_path), contents, 0600) != 0) return;
+    FLOGF(ast_cache, L"Cached the ast of '%ls'", path.c_str());
+}
@@ -0,0 +1,20 @@
+// Caching the parsed form of autoloaded files on disk.
+#ifndef FISH_AST_CACHE_H
+#define FISH_AST_CACHE_H
+
+#include "ast.h"
+#include "common.h"
+#include "maybe.h"
+
+struct file_id_t;
+
+/// \return the cached ast of the file at \p path, whose contents are \p src, if it was cached when
+/// the file was identical to \p file_id, and with this version of fish. Only asts without errors
+/// are cached, so the result has none.
+maybe_t<ast::ast_t> ast_cache_load(const wcstring &path, const file_id_t &file_id,
+                                   const wcstring &src);
+
+/// Cache \p ast, which was parsed from the file at \p path when it was \p file_id.
+void ast_cache_store(const wcstring &path, const file_id_t &file_id, const ast::ast_t &ast);
+
+#endif
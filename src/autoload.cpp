@@ -4,12 +4,16 @@
 #include "autoload.h"
 
 #include <chrono>
+#include <unordered_set>
+#include <vector>
 
 #include "common.h"
+#include "dir_watcher.h"
 #include "env.h"
 #include "exec.h"
-#include "lru.h"
+#include "flog.h"
 #include "parser.h"
+#include "wcstringutil.h"
 #include "wutil.h"  // IWYU pragma: keep
 
 /// The time before we'll recheck an autoloaded file.
@@ -26,6 +30,11 @@ struct autoloadable_file_t {
 
 /// Class representing a cache of files that may be autoloaded.
 /// This is responsible for performing cached accesses to a set of paths.
+///
+/// Rather than looking for cmd.fish in each directory on every lookup, each directory is listed
+/// once and its listing kept. Directories are watched where the system allows it, and listed again
+/// when they change; other directories are listed again when a stat shows they changed, which is
+/// checked at most once every kAutoloadStalenessInterval.
 class autoload_file_cache_t {
     /// A timestamp is a monotonic point in time.
     using timestamp_t = std::chrono::time_point<std::chrono::steady_clock>;
@@ -33,10 +42,24 @@ class autoload_file_cache_t {
     /// The directories from which to load.
     const wcstring_list_t dirs_{};
 
-    /// Our LRU cache of checks that were misses.
-    /// The key is the command, the  value is the time of the check.
-    struct misses_lru_cache_t : public lru_cache_t<misses_lru_cache_t, timestamp_t> {};
-    misses_lru_cache_t misses_cache_;
+    /// The listing of a directory.
+    struct dir_index_t {
+        /// The commands which have a file in the directory.
+        std::unordered_set<wcstring> cmds;
+
+        /// The metadata of the directory when it was listed.
+        file_id_t dir_id{kInvalidFileID};
+
+        /// The time the directory was last checked.
+        timestamp_t last_checked{};
+
+        /// Whether cmds is usable. This is unset if the directory changed.
+        bool listed{false};
+    };
+    std::vector<dir_index_t> indexes_;
+
+    /// Watches dirs_ for changes.
+    dir_watcher_t watcher_;
 
     /// The set of files that we have returned to the caller, along with the time of the check.
     /// The key is the command (not the path).
@@ -52,32 +75,78 @@ class autoload_file_cache_t {
     /// \return whether a timestamp is fresh enough to use.
     static bool is_fresh(timestamp_t then, timestamp_t now);
 
+    /// Make sure the listing of the directory at \p idx is current, listing it again if needed.
+    /// If \p allow_stale is true, an existing listing is used without checking it.
+    void refresh_index(size_t idx, timestamp_t now, bool allow_stale);
+
     /// Attempt to find an autoloadable file by searching our path list for a given comand.
     /// \return the file, or none() if none.
-    maybe_t<autoloadable_file_t> locate_file(const wcstring &cmd) const;
+    maybe_t<autoloadable_file_t> locate_file(const wcstring &cmd, bool allow_stale);
 
    public:
     /// Initialize with a set of directories.
-    explicit autoload_file_cache_t(wcstring_list_t dirs) : dirs_(std::move(dirs)) {}
+    explicit autoload_file_cache_t(wcstring_list_t dirs)
+        : dirs_(std::move(dirs)), indexes_(dirs_.size()), watcher_(dirs_) {}
 
     /// Initialize with empty directories.
-    autoload_file_cache_t() = default;
+    autoload_file_cache_t() : autoload_file_cache_t(wcstring_list_t{}) {}
 
     /// \return the directories.
     const wcstring_list_t &dirs() const { return dirs_; }
 
+    /// \return whether changes to all of the directories are reported by the watcher.
+    bool all_dirs_watched() const {
+        for (size_t i = 0; i < dirs_.size(); i++) {
+            if (!watcher_.is_watched(i)) return false;
+        }
+        return true;
+    }
+
     /// Check if a command \p cmd can be loaded.
     /// If \p allow_stale is true, allow stale entries; otherwise discard them.
     /// This returns an autoloadable file, or none() if there is no such file.
     maybe_t<autoloadable_file_t> check(const wcstring &cmd, bool allow_stale = false);
 };
 
-maybe_t<autoloadable_file_t> autoload_file_cache_t::locate_file(const wcstring &cmd) const {
-    // Re-use the storage for path.
-    wcstring path;
-    for (const wcstring &dir : dirs()) {
+void autoload_file_cache_t::refresh_index(size_t idx, timestamp_t now, bool allow_stale) {
+    dir_index_t &index = indexes_.at(idx);
+    if (index.listed) {
+        // A watched directory is unlisted as soon as it changes.
+        if (allow_stale || watcher_.is_watched(idx)) return;
+        if (is_fresh(index.last_checked, now)) return;
+    }
+
+    const wcstring &dir = dirs_.at(idx);
+    file_id_t dir_id = file_id_for_path(dir);
+    index.last_checked = now;
+    if (index.listed && dir_id == index.dir_id) return;
+
+    index.cmds.clear();
+    index.dir_id = dir_id;
+    index.listed = true;
+    if (dir_id == kInvalidFileID) return;
+    dir_t listing(dir);
+    if (!listing.valid()) return;
+    const wcstring suffix = L".fish";
+    wcstring name;
+    while (listing.read(name)) {
+        if (name.size() > suffix.size() && string_suffixes_string(suffix, name)) {
+            name.resize(name.size() - suffix.size());
+            index.cmds.insert(std::move(name));
+        }
+    }
+    FLOGF(path, L"Listed '%ls' for autoloading", dir.c_str());
+}
+
+maybe_t<autoloadable_file_t> autoload_file_cache_t::locate_file(const wcstring &cmd,
+                                                                bool allow_stale) {
+    timestamp_t now = current_timestamp();
+    for (size_t i = 0; i < dirs_.size(); i++) {
+        refresh_index(i, now, allow_stale);
+        if (!indexes_[i].cmds.count(cmd)) continue;
+
         // Construct the path as dir/cmd.fish
-        path = dir;
+        wcstring path = dirs_[i];
         path += L"/";
         path += cmd;
         path += L".fish";
@@ -100,6 +169,14 @@ bool autoload_file_cache_t::is_fresh(timestamp_t then, timestamp_t now) {
 }
 
 maybe_t<autoloadable_file_t> autoload_file_cache_t::check(const wcstring &cmd, bool allow_stale) {
+    // Forget the listings of directories which changed. A new file in one directory can shadow a
+    // known file in a later one, so forget the known files too.
+    std::vector<size_t> changed = watcher_.take_changed();
+    for (size_t idx : changed) {
+        indexes_.at(idx).listed = false;
+    }
+    if (!changed.empty()) known_files_.clear();
+
     // Check hits.
     auto iter = known_files_.find(cmd);
     if (iter != known_files_.end()) {
@@ -111,26 +188,12 @@ maybe_t<autoloadable_file_t> autoload_file_cache_t::check(const wcstring &cmd, b
         known_files_.erase(iter);
     }
 
-    // Check misses.
-    if (timestamp_t *miss = misses_cache_.get(cmd)) {
-        if (allow_stale || is_fresh(*miss, current_timestamp())) {
-            // Re-use this cached miss.
-            return none();
-        }
-        // The miss is stale, remove it.
-        misses_cache_.evict_node(cmd);
-    }
-
-    // We couldn't satisfy this request from the cache. Hit the disk.
-    maybe_t<autoloadable_file_t> file = locate_file(cmd);
+    // Look in the directory listings.
+    maybe_t<autoloadable_file_t> file = locate_file(cmd, allow_stale);
     if (file.has_value()) {
         auto ins = known_files_.emplace(cmd, known_file_t{*file, current_timestamp()});
         assert(ins.second && "Known files cache should not have contained this cmd");
         (void)ins;
-    } else {
-        bool ins = misses_cache_.insert(cmd, current_timestamp());
-        assert(ins && "Misses cache should not have contained this cmd");
-        (void)ins;
     }
     return file;
 }
@@ -146,6 +209,16 @@ void autoload_t::invalidate_cache() {
     cache_ = std::move(cache);
 }
 
+bool autoload_t::all_dirs_watched() const { return cache_->all_dirs_watched(); }
+
+void autoload_t::update_paths(const environment_t &env) {
+    wcstring_list_t paths;
+    if (maybe_t<env_var_t> mvar = env.get(env_var_name_)) paths = mvar->as_list();
+    if (paths != cache_->dirs()) {
+        cache_ = make_unique<autoload_file_cache_t>(std::move(paths));
+    }
+}
+
 bool autoload_t::can_autoload(const wcstring &cmd) {
     return cache_->check(cmd, true /* allow stale */).has_value();
 }
@@ -199,5 +272,6 @@ maybe_t<wcstring> autoload_t::resolve_command(const wcstring &cmd, const wcstrin
 
 void autoload_t::perform_autoload(const wcstring &path, parser_t &parser) {
     wcstring script_source = L"source " + escape_string(path, ESCAPE_ALL);
+    scoped_push<bool> autoloading_push{&parser.libdata().is_autoloading, true};
     exec_subshell(script_source, parser, false /* do not apply exit status */);
 }
@@ -48,6 +48,10 @@ class autoload_t {
     /// This is exposed for testing.
     maybe_t<wcstring> resolve_command(const wcstring &cmd, const wcstring_list_t &paths);
 
+    /// \return whether all of our directories are watched for changes, so new files are found
+    /// without invalidating the cache. This is exposed for testing.
+    bool all_dirs_watched() const;
+
     friend autoload_tester_t;
 
    public:
@@ -67,6 +71,10 @@ class autoload_t {
     /// code; it is the caller's responsibility to load the file.
     maybe_t<wcstring> resolve_command(const wcstring &cmd, const environment_t &env);
 
+    /// Observe that our environment variable may have changed, and start caching the directories
+    /// it now names, if they are different.
+    void update_paths(const environment_t &env);
+
     /// Helper to actually perform an autoload.
     /// This is a static function because it executes fish script, and so must be called without
     /// holding any particular locks.
@@ -84,6 +92,9 @@ class autoload_t {
         return current_autoloading_.count(cmd) > 0;
     }
 
+    /// \return whether a file for \p cmd has been autoloaded, or is being autoloaded.
+    bool has_autoloaded(const wcstring &cmd) const { return autoloaded_files_.count(cmd) > 0; }
+
     /// \return whether a command could potentially be autoloaded.
     /// This does not actually mark the command as being autoloaded.
     bool can_autoload(const wcstring &cmd);
@@ -40,17 +40,31 @@
 #include "builtin_commandline.h"
 #include "builtin_complete.h"
 #include "builtin_contains.h"
+#include "builtin_coproc.h"
 #include "builtin_disown.h"
 #include "builtin_echo.h"
 #include "builtin_emit.h"
 #include "builtin_eval.h"
 #include "builtin_exit.h"
+#include "builtin_fdclose.h"
+#include "builtin_fdopen.h"
 #include "builtin_fg.h"
+#include "builtin_fish_add_path.h"
+#include "builtin_fish_config.h"
+#include "builtin_fish_envrc.h"
+#include "builtin_fish_git_status.h"
+#include "builtin_fish_reload_term.h"
+#include "builtin_fish_spell_correct.h"
+#include "builtin_funced.h"
+#include "builtin_funcsave.h"
 #include "builtin_functions.h"
 #include "builtin_history.h"
 #include "builtin_jobs.h"
+#include "builtin_kill.h"
 #include "builtin_math.h"
+#include "builtin_path.h"
 #include "builtin_printf.h"
+#include "builtin_prompt_segment.h"
 #include "builtin_pwd.h"
 #include "builtin_random.h"
 #include "builtin_read.h"
@@ -62,6 +76,7 @@
 #include "builtin_status.h"
 #include "builtin_string.h"
 #include "builtin_test.h"
+#include "builtin_timeout.h"
 #include "builtin_type.h"
 #include "builtin_ulimit.h"
 #include "builtin_wait.h"
@@ -72,11 +87,13 @@
 #include "flog.h"
 #include "intern.h"
 #include "io.h"
+#include "output.h"
 #include "parse_constants.h"
 #include "parse_util.h"
 #include "parser.h"
 #include "proc.h"
 #include "reader.h"
+#include "termsize.h"
 #include "wgetopt.h"
 #include "wutil.h"  // IWYU pragma: keep
 
@@ -173,8 +190,7 @@ void builtin_print_help(parser_t &parser, const io_streams_t &streams, const wch
 /// Perform error reporting for encounter with unknown option.
 void builtin_unknown_option(parser_t &parser, io_streams_t &streams, const wchar_t *cmd,
                             const wchar_t *opt) {
-    streams.err.append_format(BUILTIN_ERR_UNKNOWN, cmd, opt);
-    builtin_print_error_trailer(parser, streams.err, cmd);
+    builtin_print_error(parser, streams, cmd, format_string(BUILTIN_ERR_UNKNOWN, cmd, opt));
 }
 
 /// Perform error reporting for encounter with missing argument.
@@ -183,22 +199,64 @@ void builtin_missing_argument(parser_t &parser, io_streams_t &streams, const wch
     if (opt[0] == L'-' && opt[1] != L'-') {
         opt += std::wcslen(opt) - 1;
     }
-    streams.err.append_format(BUILTIN_ERR_MISSING, cmd, opt);
-    if (print_hints) {
-        builtin_print_error_trailer(parser, streams.err, cmd);
-    }
+    builtin_print_error(parser, streams, cmd, format_string(BUILTIN_ERR_MISSING, cmd, opt),
+                        print_hints);
 }
 
-/// Print the backtrace and call for help that we use at the end of error messages.
-void builtin_print_error_trailer(parser_t &parser, output_stream_t &b, const wchar_t *cmd) {
-    b.append(L"\n");
+/// \return the backtrace and call for help that we use at the end of error messages.
+static styled_text_t error_trailer(parser_t &parser, const wchar_t *cmd) {
+    styled_text_t trailer(L"\n");
     const wcstring stacktrace = parser.current_line();
     // Don't print two empty lines if we don't have a stacktrace.
     if (!stacktrace.empty()) {
-        b.append(stacktrace);
-        b.append(L"\n");
+        trailer.append(stacktrace, text_style_t::verbatim);
+        trailer.append(L"\n");
     }
-    b.append_format(_(L"(Type 'help %ls' for related documentation)\n"), cmd);
+    // Style the command to type, which is the part of the hint that matters.
+    const wcstring help_cmd = format_string(L"help %ls", cmd);
+    const wcstring hint = format_string(_(L"(Type '%ls' for related documentation)\n"),
+                                        help_cmd.c_str());
+    size_t pos = hint.find(help_cmd);
+    if (pos == wcstring::npos) {
+        trailer.append(hint);
+    } else {
+        trailer.append(hint.substr(0, pos));
+        trailer.append(help_cmd, text_style_t::emphasis);
+        trailer.append(hint.substr(pos + help_cmd.size()));
+    }
+    return trailer;
+}
+
+/// Print the backtrace and call for help that we use at the end of error messages.
+void builtin_print_error_trailer(parser_t &parser, output_stream_t &b, const wchar_t *cmd) {
+    b.append(error_trailer(parser, cmd).text());
+}
+
+/// Print \p text to the stdout or stderr of \p streams. It is styled and wrapped to the width of
+/// the terminal if it goes to one, and left plain otherwise.
+void builtin_print_styled(parser_t &parser, io_streams_t &streams, bool to_stderr,
+                          const styled_text_t &text) {
+    bool redirected = to_stderr ? streams.err_is_redirected : streams.out_is_redirected;
+    bool is_tty = !redirected && isatty(to_stderr ? STDERR_FILENO : STDOUT_FILENO);
+    wcstring rendered = text.render(parser.vars(), is_tty, is_tty ? termsize_last().width : 0);
+    (to_stderr ? streams.err : streams.out).append(rendered);
+}
+
+/// Print the error \p msg of the builtin \p cmd in the error color, followed by the error
+/// trailer if \p print_trailer is set.
+void builtin_print_error(parser_t &parser, io_streams_t &streams, const wchar_t *cmd,
+                         const wcstring &msg, bool print_trailer) {
+    styled_text_t text;
+    text.append(msg, text_style_t::error);
+    if (print_trailer) text.append(error_trailer(parser, cmd));
+    builtin_print_styled(parser, streams, true, text);
+}
+
+/// Print the warning \p msg in the warning color.
+void builtin_print_warning(parser_t &parser, io_streams_t &streams, const wcstring &msg) {
+    styled_text_t text;
+    text.append(msg, text_style_t::warning);
+    builtin_print_styled(parser, streams, true, text);
 }
 
 /// A generic bultin that only supports showing a help message. This is only a placeholder that
@@ -297,6 +355,12 @@ static maybe_t<int> builtin_breakpoint(parser_t &parser, io_streams_t &streams,
         return STATUS_INVALID_ARGS;
     }
 
+    // An attached debugger stops here, as it would at a breakpoint set in the editor.
+    if (debugger_t *debugger = parser.libdata().debugger) {
+        debugger->breakpoint(parser);
+        return STATUS_CMD_OK;
+    }
+
     // If we're not interactive then we can't enter the debugger. So treat this command as a no-op.
     if (!parser.is_interactive()) {
         return STATUS_CMD_ERROR;
@@ -369,6 +433,7 @@ static const builtin_data_t builtin_datas[] = {
     {L"contains", &builtin_contains, N_(L"Search for a specified string in a list")},
     {L"continue", &builtin_break_continue,
      N_(L"Skip the rest of the current lap of the innermost loop")},
+    {L"coproc", &builtin_coproc, N_(L"Run a command connected to the shell by pipes")},
     {L"count", &builtin_count, N_(L"Count the number of arguments")},
     {L"disown", &builtin_disown, N_(L"Remove job from job list")},
     {L"echo", &builtin_echo, N_(L"Print arguments")},
@@ -379,17 +444,36 @@ static const builtin_data_t builtin_datas[] = {
     {L"exec", &builtin_generic, N_(L"Run command in current process")},
     {L"exit", &builtin_exit, N_(L"Exit the shell")},
     {L"false", &builtin_false, N_(L"Return an unsuccessful result")},
+    {L"fdclose", &builtin_fdclose, N_(L"Close file descriptors opened by scripts")},
+    {L"fdopen", &builtin_fdopen, N_(L"Open a file or socket on a file descriptor")},
     {L"fg", &builtin_fg, N_(L"Send job to foreground")},
+    {L"fish_add_path", &builtin_fish_add_path, N_(L"Add paths to the PATH")},
+    {L"fish_config", &builtin_fish_config,
+     N_(L"Start the web-based configuration, or manage color themes")},
+    {L"fish_envrc", &builtin_fish_envrc, N_(L"Trust the .envrc.fish files of directories")},
+    {L"fish_git_status", &builtin_fish_git_status,
+     N_(L"Print the state of the git repository without running git")},
+    {L"fish_reload_term", &builtin_fish_reload_term,
+     N_(L"Redetect the terminal and what it supports")},
+    {L"fish_spell_correct", &builtin_fish_spell_correct,
+     N_(L"Correct mistyped commands and directories in a command line")},
     {L"for", &builtin_generic, N_(L"Perform a set of commands multiple times")},
+    {L"funced", &builtin_funced, N_(L"Edit function definition")},
+    {L"funcsave", &builtin_funcsave,
+     N_(L"Save the current definition of all specified functions to file")},
     {L"function", &builtin_generic, N_(L"Define a new function")},
     {L"functions", &builtin_functions, N_(L"List or remove functions")},
     {L"history", &builtin_history, N_(L"History of commands executed by user")},
     {L"if", &builtin_generic, N_(L"Evaluate block if condition is true")},
     {L"jobs", &builtin_jobs, N_(L"Print currently running jobs")},
+    {L"kill", &builtin_kill, N_(L"Send a signal to processes or jobs")},
     {L"math", &builtin_math, N_(L"Evaluate math expressions")},
     {L"not", &builtin_generic, N_(L"Negate exit status of job")},
     {L"or", &builtin_generic, N_(L"Execute command if previous command failed")},
+    {L"path", &builtin_path, N_(L"Translate paths between Windows and Unix forms")},
     {L"printf", &builtin_printf, N_(L"Prints formatted text")},
+    {L"prompt_segment", &builtin_prompt_segment,
+     N_(L"Run a command for the prompt in the background")},
     {L"pwd", &builtin_pwd, N_(L"Print the working directory")},
     {L"random", &builtin_random, N_(L"Generate random number")},
     {L"read", &builtin_read, N_(L"Read a line of input into variables")},
@@ -403,6 +487,7 @@ static const builtin_data_t builtin_datas[] = {
     {L"switch", &builtin_generic, N_(L"Conditionally execute a block of commands")},
     {L"test", &builtin_test, N_(L"Test a condition")},
     {L"time", &builtin_generic, N_(L"Measure how long a command or block takes")},
+    {L"timeout", &builtin_timeout, N_(L"Run a command with a time limit")},
     {L"true", &builtin_true, N_(L"Return a successful result")},
     {L"type", &builtin_type, N_(L"Check if a thing is a thing")},
     {L"ulimit", &builtin_ulimit, N_(L"Set or get the shells resource usage limits")},
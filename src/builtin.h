@@ -12,6 +12,7 @@ class completion_t;
 class parser_t;
 class proc_status_t;
 class output_stream_t;
+class styled_text_t;
 struct io_streams_t;
 using completion_list_t = std::vector<completion_t>;
 
@@ -101,6 +102,14 @@ void builtin_missing_argument(parser_t &parser, io_streams_t &streams, const wch
 
 void builtin_print_error_trailer(parser_t &parser, output_stream_t &b, const wchar_t *cmd);
 
+void builtin_print_styled(parser_t &parser, io_streams_t &streams, bool to_stderr,
+                          const styled_text_t &text);
+
+void builtin_print_error(parser_t &parser, io_streams_t &streams, const wchar_t *cmd,
+                         const wcstring &msg, bool print_trailer = true);
+
+void builtin_print_warning(parser_t &parser, io_streams_t &streams, const wcstring &msg);
+
 void builtin_wperror(const wchar_t *s, io_streams_t &streams);
 
 struct help_only_cmd_opts_t {
@@ -7,35 +7,90 @@
 #include <sys/stat.h>
 
 #include <cerrno>
+#include <ctime>
 
 #include "builtin.h"
 #include "common.h"
 #include "env.h"
 #include "fallback.h"  // IWYU pragma: keep
+#include "frecency.h"
+#include "history.h"
 #include "io.h"
 #include "parser.h"
 #include "path.h"
 #include "proc.h"
+#include "wcstringutil.h"
+#include "wgetopt.h"
 #include "wutil.h"  // IWYU pragma: keep
 
+static const wchar_t *const short_options = L"+:h";
+static const struct woption long_options[] = {{L"help", no_argument, nullptr, 'h'},
+                                              {L"jump", no_argument, nullptr, 1},
+                                              {nullptr, 0, nullptr, 0}};
+
+/// \return the directories visited before which match \p patterns, best first, without \p pwd and
+/// directories which no longer exist.
+static wcstring_list_t jump_candidates(const wcstring_list_t &patterns, const wcstring &pwd) {
+    wcstring_list_t result;
+    for (wcstring &dir : frecency_match(frecency_load(), patterns, time(nullptr))) {
+        struct stat buf;
+        if (dir + L"/" == pwd || dir == pwd || wstat(dir, &buf) || !S_ISDIR(buf.st_mode)) continue;
+        result.push_back(std::move(dir));
+    }
+    return result;
+}
+
 /// The cd builtin. Changes the current directory to the one specified or to $HOME if none is
-/// specified. The directory can be relative to any directory in the CDPATH variable.
+/// specified. The directory can be relative to any directory in the CDPATH variable. With --jump,
+/// it changes to the directory visited before which best matches the arguments.
 maybe_t<int> builtin_cd(parser_t &parser, io_streams_t &streams, wchar_t **argv) {
     const wchar_t *cmd = argv[0];
     int argc = builtin_count_args(argv);
-    help_only_cmd_opts_t opts;
-
-    int optind;
-    int retval = parse_help_only_cmd_opts(opts, &optind, argc, argv, parser, streams);
-    if (retval != STATUS_CMD_OK) return retval;
-
-    if (opts.print_help) {
-        builtin_print_help(parser, streams, cmd);
-        return STATUS_CMD_OK;
+    bool jump = false;
+    wgetopter_t w;
+    int opt;
+    while ((opt = w.wgetopt_long(argc, argv, short_options, long_options, nullptr)) != -1) {
+        switch (opt) {
+            case 1: {
+                jump = true;
+                break;
+            }
+            case 'h': {
+                builtin_print_help(parser, streams, cmd);
+                return STATUS_CMD_OK;
+            }
+            case ':': {
+                builtin_missing_argument(parser, streams, cmd, argv[w.woptind - 1]);
+                return STATUS_INVALID_ARGS;
+            }
+            case '?': {
+                builtin_unknown_option(parser, streams, cmd, argv[w.woptind - 1]);
+                return STATUS_INVALID_ARGS;
+            }
+            default: {
+                DIE("unexpected retval from wgetopt_long");
+            }
+        }
     }
+    int optind = w.woptind;
 
+    wcstring pwd = parser.vars().get_pwd_slash();
     wcstring dir_in;
-    if (argv[optind]) {
+    if (jump) {
+        wcstring_list_t patterns(argv + optind, argv + argc);
+        wcstring_list_t candidates = jump_candidates(patterns, pwd);
+        if (patterns.empty()) {
+            // Without patterns, list the candidates, e.g. for completions.
+            for (const wcstring &dir : candidates) streams.out.append(dir + L"\n");
+            return STATUS_CMD_OK;
+        }
+        if (candidates.empty()) {
+            streams.err.append_format(_(L"%ls: No visited directory matches '%ls'\n"), cmd,
+                                      join_strings(patterns, L' ').c_str());
+            return STATUS_CMD_ERROR;
+        }
+        dir_in = candidates.front();
+    } else if (argv[optind]) {
         dir_in = argv[optind];
     } else {
         auto maybe_dir_in = parser.vars().get(L"HOME");
@@ -46,7 +101,6 @@ maybe_t<int> builtin_cd(parser_t &parser, io_streams_t &streams, wchar_t **argv)
         dir_in = maybe_dir_in->as_string();
     }
 
-    wcstring pwd = parser.vars().get_pwd_slash();
     maybe_t<wcstring> mdir = path_get_cdpath(dir_in, pwd, parser.vars());
     if (!mdir) {
         if (errno == ENOTDIR) {
@@ -93,6 +147,10 @@ maybe_t<int> builtin_cd(parser_t &parser, io_streams_t &streams, wchar_t **argv)
 
     parser.libdata().cwd_fd = std::make_shared<const autoclose_fd_t>(std::move(dir_fd));
     std::vector<event_t> evts;
+    // Remember the directory for --jump, unless it was only changed by a script.
+    if (is_interactive_session() && !in_private_mode(parser.vars())) {
+        frecency_add_visit(norm_dir);
+    }
     parser.vars().set_one(L"PWD", ENV_EXPORT | ENV_GLOBAL, std::move(norm_dir), &evts);
     for (const auto &evt : evts) {
         event_fire(parser, evt);
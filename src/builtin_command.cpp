@@ -5,9 +5,11 @@
 
 #include <unistd.h>
 
+#include <cwchar>
 #include <string>
 
 #include "builtin.h"
+#include "builtin_eval.h"
 #include "common.h"
 #include "fallback.h"  // IWYU pragma: keep
 #include "io.h"
@@ -21,12 +23,16 @@ struct command_cmd_opts_t {
     bool find_path = false;
     bool quiet = false;
     bool all_paths = false;
+    const wchar_t *nice = nullptr;
+    const wchar_t *ionice = nullptr;
 };
-static const wchar_t *const short_options = L":ahqsv";
+// Options end at the command name, so it can have its own options with --nice and --ionice.
+static const wchar_t *const short_options = L"+:ahqsv";
 static const struct woption long_options[] = {
-    {L"help", no_argument, nullptr, 'h'},   {L"all", no_argument, nullptr, 'a'},
-    {L"quiet", no_argument, nullptr, 'q'},  {L"query", no_argument, nullptr, 'q'},
-    {L"search", no_argument, nullptr, 's'}, {nullptr, 0, nullptr, 0}};
+    {L"help", no_argument, nullptr, 'h'},      {L"all", no_argument, nullptr, 'a'},
+    {L"quiet", no_argument, nullptr, 'q'},     {L"query", no_argument, nullptr, 'q'},
+    {L"search", no_argument, nullptr, 's'},    {L"nice", required_argument, nullptr, 1},
+    {L"ionice", required_argument, nullptr, 2}, {nullptr, 0, nullptr, 0}};
 
 static int parse_cmd_opts(command_cmd_opts_t &opts, int *optind, int argc, wchar_t **argv,
                           parser_t &parser, io_streams_t &streams) {
@@ -52,6 +58,14 @@ static int parse_cmd_opts(command_cmd_opts_t &opts, int *optind, int argc, wchar
                 opts.find_path = true;
                 break;
             }
+            case 1: {
+                opts.nice = w.woptarg;
+                break;
+            }
+            case 2: {
+                opts.ionice = w.woptarg;
+                break;
+            }
             case ':': {
                 builtin_missing_argument(parser, streams, cmd, argv[w.woptind - 1]);
                 return STATUS_INVALID_ARGS;
@@ -70,6 +84,87 @@ static int parse_cmd_opts(command_cmd_opts_t &opts, int *optind, int argc, wchar
     return STATUS_CMD_OK;
 }
 
+#if defined(__linux__)
+/// Parse an I/O scheduling class like "idle", "best-effort" or "realtime:2" into the format of
+/// ioprio_set(2). The level after the colon goes from 0 (highest priority) to 7.
+/// \return the value, or none() if it is invalid.
+static maybe_t<int> parse_ionice(const wcstring &str) {
+    // These are the IOPRIO_CLASS_* and IOPRIO_CLASS_SHIFT values from the kernel.
+    const int class_shift = 13;
+    const struct {
+        const wchar_t *name;
+        int value;
+        bool has_level;
+    } classes[] = {{L"realtime", 1, true}, {L"best-effort", 2, true}, {L"idle", 3, false}};
+
+    size_t colon = str.find(L':');
+    wcstring name = str.substr(0, colon);
+    for (const auto &cls : classes) {
+        if (name != cls.name) continue;
+        int level = 4;
+        if (colon != wcstring::npos) {
+            if (!cls.has_level) return none();
+            level = fish_wcstoi(str.c_str() + colon + 1);
+            if (errno || level < 0 || level > 7) return none();
+        }
+        return (cls.value << class_shift) | (cls.has_level ? level : 0);
+    }
+    return none();
+}
+#endif
+
+/// Run a command with a scheduling priority, given by --nice and --ionice.
+static int run_with_priority(parser_t &parser, io_streams_t &streams, const wchar_t *cmd,
+                             const command_cmd_opts_t &opts, int argc, wchar_t **argv) {
+    if (opts.find_path || opts.all_paths || opts.quiet) {
+        streams.err.append_format(BUILTIN_ERR_COMBO, cmd);
+        return STATUS_INVALID_ARGS;
+    }
+    if (argc == 0) {
+        streams.err.append_format(BUILTIN_ERR_MIN_ARG_COUNT1, cmd, 1, 0);
+        return STATUS_INVALID_ARGS;
+    }
+
+    // Nested uses add up their niceness, like nested calls to nice(1).
+    auto &current = parser.libdata().spawn_priority;
+    spawn_priority_t priority = current ? *current : spawn_priority_t{};
+    if (opts.nice) {
+        int nice = fish_wcstoi(opts.nice);
+        if (errno) {
+            streams.err.append_format(_(L"%ls: Invalid niceness '%ls'\n"), cmd, opts.nice);
+            return STATUS_INVALID_ARGS;
+        }
+        priority.nice += nice;
+    }
+    if (opts.ionice) {
+#if defined(__linux__)
+        maybe_t<int> ioprio = parse_ionice(opts.ionice);
+        if (!ioprio) {
+            streams.err.append_format(_(L"%ls: Invalid I/O scheduling class '%ls'\n"), cmd,
+                                      opts.ionice);
+            return STATUS_INVALID_ARGS;
+        }
+        priority.ioprio = *ioprio;
+#else
+        streams.err.append_format(_(L"%ls: --ionice is not supported on this system\n"), cmd);
+        return STATUS_CMD_ERROR;
+#endif
+    }
+
+    // Another `command` may give more options; otherwise make sure this runs an external command.
+    wcstring command = std::wcscmp(argv[0], L"command") ? L"command" : L"";
+    for (int i = 0; i < argc; i++) {
+        if (!command.empty()) command.push_back(L' ');
+        command.append(escape_string(argv[i], ESCAPE_ALL));
+    }
+
+    const maybe_t<spawn_priority_t> saved = current;
+    current = priority;
+    int status = builtin_eval_in_streams(parser, streams, command);
+    parser.libdata().spawn_priority = saved;
+    return status;
+}
+
 /// Implementation of the builtin 'command'. Actual command running is handled by the parser, this
 /// just processes the flags.
 maybe_t<int> builtin_command(parser_t &parser, io_streams_t &streams, wchar_t **argv) {
@@ -86,6 +181,10 @@ maybe_t<int> builtin_command(parser_t &parser, io_streams_t &streams, wchar_t **
         return STATUS_CMD_OK;
     }
 
+    if (opts.nice || opts.ionice) {
+        return run_with_priority(parser, streams, cmd, opts, argc - optind, argv + optind);
+    }
+
     // Quiet implies find_path.
     if (!opts.find_path && !opts.all_paths && !opts.quiet) {
         builtin_print_help(parser, streams, cmd);
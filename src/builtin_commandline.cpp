@@ -148,6 +148,8 @@ maybe_t<int> builtin_commandline(parser_t &parser, io_streams_t &streams, wchar_
     bool line_mode = false;
     bool search_mode = false;
     bool paging_mode = false;
+    bool message_mode = false;
+    bool snippet_mode = false;
     const wchar_t *begin = nullptr, *end = nullptr;
 
     const auto &ld = parser.libdata();
@@ -168,13 +170,13 @@ maybe_t<int> builtin_commandline(parser_t &parser, io_streams_t &streams, wchar_
             return STATUS_CMD_ERROR;
         }
 
-        streams.err.append(argv[0]);
-        streams.err.append(L": Can not set commandline in non-interactive mode\n");
-        builtin_print_error_trailer(parser, streams.err, cmd);
+        builtin_print_error(
+            parser, streams, cmd,
+            format_string(L"%ls: Can not set commandline in non-interactive mode\n", argv[0]));
         return STATUS_CMD_ERROR;
     }
 
-    static const wchar_t *const short_options = L":abijpctforhI:CLSsP";
+    static const wchar_t *const short_options = L":abijpctforhI:CLSsPm";
     static const struct woption long_options[] = {{L"append", no_argument, nullptr, 'a'},
                                                   {L"insert", no_argument, nullptr, 'i'},
                                                   {L"replace", no_argument, nullptr, 'r'},
@@ -192,6 +194,8 @@ maybe_t<int> builtin_commandline(parser_t &parser, io_streams_t &streams, wchar_
                                                   {L"line", no_argument, nullptr, 'L'},
                                                   {L"search-mode", no_argument, nullptr, 'S'},
                                                   {L"paging-mode", no_argument, nullptr, 'P'},
+                                                  {L"message", no_argument, nullptr, 'm'},
+                                                  {L"insert-snippet", no_argument, nullptr, 1},
                                                   {nullptr, 0, nullptr, 0}};
 
     int opt;
@@ -263,6 +267,14 @@ maybe_t<int> builtin_commandline(parser_t &parser, io_streams_t &streams, wchar_
                 paging_mode = true;
                 break;
             }
+            case 'm': {
+                message_mode = true;
+                break;
+            }
+            case 1: {
+                snippet_mode = true;
+                break;
+            }
             case 'h': {
                 builtin_print_help(parser, streams, cmd);
                 return STATUS_CMD_OK;
@@ -286,9 +298,8 @@ maybe_t<int> builtin_commandline(parser_t &parser, io_streams_t &streams, wchar_
 
         // Check for invalid switch combinations.
         if (buffer_part || cut_at_cursor || append_mode || tokenize || cursor_mode || line_mode ||
-            search_mode || paging_mode) {
-            streams.err.append_format(BUILTIN_ERR_COMBO, argv[0]);
-            builtin_print_error_trailer(parser, streams.err, cmd);
+            search_mode || paging_mode || message_mode || snippet_mode) {
+            builtin_print_error(parser, streams, cmd, format_string(BUILTIN_ERR_COMBO, argv[0]));
             return STATUS_INVALID_ARGS;
         }
 
@@ -302,7 +313,8 @@ maybe_t<int> builtin_commandline(parser_t &parser, io_streams_t &streams, wchar_
             if (auto mc = input_function_get_code(argv[i])) {
                 // Don't enqueue a repaint if we're currently in the middle of one,
                 // because that's an infinite loop.
-                if (mc == rl::repaint_mode || mc == rl::force_repaint || mc == rl::repaint) {
+                if (mc == rl::repaint_mode || mc == rl::repaint_right_prompt ||
+                    mc == rl::force_repaint || mc == rl::repaint) {
                     if (ld.is_repaint) continue;
                 }
 
@@ -318,8 +330,9 @@ maybe_t<int> builtin_commandline(parser_t &parser, io_streams_t &streams, wchar_
                     reader_queue_ch(*mc);
                 }
             } else {
-                streams.err.append_format(_(L"%ls: Unknown input function '%ls'"), cmd, argv[i]);
-                builtin_print_error_trailer(parser, streams.err, cmd);
+                builtin_print_error(parser, streams, cmd,
+                                    format_string(_(L"%ls: Unknown input function '%ls'"), cmd,
+                                                  argv[i]));
                 return STATUS_INVALID_ARGS;
             }
         }
@@ -327,6 +340,42 @@ maybe_t<int> builtin_commandline(parser_t &parser, io_streams_t &streams, wchar_
         return STATUS_CMD_OK;
     }
 
+    if (message_mode) {
+        if (buffer_part || cut_at_cursor || append_mode || tokenize || cursor_mode || line_mode ||
+            search_mode || paging_mode || selection_mode || snippet_mode) {
+            builtin_print_error(parser, streams, cmd, format_string(BUILTIN_ERR_COMBO, argv[0]));
+            return STATUS_INVALID_ARGS;
+        }
+
+        if (argc == w.woptind) {
+            wcstring message = reader_get_message();
+            if (message.empty()) return STATUS_CMD_ERROR;
+            streams.out.append(message);
+            streams.out.push_back(L'\n');
+        } else {
+            reader_set_message(join_strings(wcstring_list_t(argv + w.woptind, argv + argc), L' '));
+        }
+        return STATUS_CMD_OK;
+    }
+
+    if (snippet_mode) {
+        if (buffer_part || cut_at_cursor || append_mode || tokenize || cursor_mode || line_mode ||
+            search_mode || paging_mode || selection_mode) {
+            builtin_print_error(parser, streams, cmd, format_string(BUILTIN_ERR_COMBO, argv[0]));
+            return STATUS_INVALID_ARGS;
+        }
+
+        if (argc == w.woptind) {
+            builtin_missing_argument(parser, streams, cmd, argv[0]);
+            return STATUS_INVALID_ARGS;
+        }
+
+        // Snippets can only be filled in in the real command line.
+        if (!ld.transient_commandlines.empty()) return STATUS_CMD_ERROR;
+        reader_insert_snippet(join_strings(wcstring_list_t(argv + w.woptind, argv + argc), L'\n'));
+        return STATUS_CMD_OK;
+    }
+
     if (selection_mode) {
         size_t start, len;
         const wchar_t *buffer = reader_get_buffer();
@@ -338,23 +387,23 @@ maybe_t<int> builtin_commandline(parser_t &parser, io_streams_t &streams, wchar_
 
     // Check for invalid switch combinations.
     if ((search_mode || line_mode || cursor_mode || paging_mode) && (argc - w.woptind > 1)) {
-        streams.err.append_format(BUILTIN_ERR_TOO_MANY_ARGUMENTS, argv[0]);
-        builtin_print_error_trailer(parser, streams.err, cmd);
+        builtin_print_error(parser, streams, cmd,
+                            format_string(BUILTIN_ERR_TOO_MANY_ARGUMENTS, argv[0]));
         return STATUS_INVALID_ARGS;
     }
 
     if ((buffer_part || tokenize || cut_at_cursor) &&
         (cursor_mode || line_mode || search_mode || paging_mode)) {
-        streams.err.append_format(BUILTIN_ERR_COMBO, argv[0]);
-        builtin_print_error_trailer(parser, streams.err, cmd);
+        builtin_print_error(parser, streams, cmd, format_string(BUILTIN_ERR_COMBO, argv[0]));
         return STATUS_INVALID_ARGS;
     }
 
     if ((tokenize || cut_at_cursor) && (argc - w.woptind)) {
-        streams.err.append_format(
-            BUILTIN_ERR_COMBO2, cmd,
-            L"--cut-at-cursor and --tokenize can not be used when setting the commandline");
-        builtin_print_error_trailer(parser, streams.err, cmd);
+        builtin_print_error(
+            parser, streams, cmd,
+            format_string(BUILTIN_ERR_COMBO2, cmd,
+                          L"--cut-at-cursor and --tokenize can not be used when setting the "
+                          L"commandline"));
         return STATUS_INVALID_ARGS;
     }
 
@@ -376,8 +425,8 @@ maybe_t<int> builtin_commandline(parser_t &parser, io_streams_t &streams, wchar_
         if (argc - w.woptind) {
             long new_pos = fish_wcstol(argv[w.woptind]);
             if (errno) {
-                streams.err.append_format(BUILTIN_ERR_NOT_NUMBER, cmd, argv[w.woptind]);
-                builtin_print_error_trailer(parser, streams.err, cmd);
+                builtin_print_error(parser, streams, cmd,
+                                    format_string(BUILTIN_ERR_NOT_NUMBER, cmd, argv[w.woptind]));
             }
 
             current_buffer = reader_get_buffer();
@@ -307,8 +307,8 @@ maybe_t<int> builtin_complete(parser_t &parser, io_streams_t &streams, wchar_t *
             // Or use one left-over arg as the command to complete
             cmd_to_complete.push_back(argv[argc - 1]);
         } else {
-            streams.err.append_format(BUILTIN_ERR_TOO_MANY_ARGUMENTS, cmd);
-            builtin_print_error_trailer(parser, streams.err, cmd);
+            builtin_print_error(parser, streams, cmd,
+                                format_string(BUILTIN_ERR_TOO_MANY_ARGUMENTS, cmd));
             return STATUS_INVALID_ARGS;
         }
     }
@@ -0,0 +1,199 @@
+// Implementation of the coproc builtin.
+#include "config.h"  // IWYU pragma: keep
+
+#include "builtin_coproc.h"
+
+#include <unistd.h>
+
+#include <memory>
+#include <set>
+
+#include "builtin.h"
+#include "common.h"
+#include "env.h"
+#include "fallback.h"  // IWYU pragma: keep
+#include "fds.h"
+#include "function.h"
+#include "io.h"
+#include "parser.h"
+#include "proc.h"
+#include "wcstringutil.h"
+#include "wgetopt.h"
+#include "wutil.h"  // IWYU pragma: keep
+
+/// The variable the fds are stored in, unless another one is given.
+#define DEFAULT_COPROC_NAME L"COPROC"
+
+struct coproc_cmd_opts_t {
+    bool print_help = false;
+    bool local = false;
+    bool global = false;
+    const wchar_t *name = DEFAULT_COPROC_NAME;
+};
+
+static const wchar_t *const short_options = L"+:n:lgh";
+static const struct woption long_options[] = {{L"name", required_argument, nullptr, 'n'},
+                                              {L"local", no_argument, nullptr, 'l'},
+                                              {L"global", no_argument, nullptr, 'g'},
+                                              {L"help", no_argument, nullptr, 'h'},
+                                              {nullptr, 0, nullptr, 0}};
+
+static int parse_cmd_opts(coproc_cmd_opts_t &opts, int *optind, int argc, wchar_t **argv,
+                          parser_t &parser, io_streams_t &streams) {
+    const wchar_t *cmd = argv[0];
+    int opt;
+    wgetopter_t w;
+    while ((opt = w.wgetopt_long(argc, argv, short_options, long_options, nullptr)) != -1) {
+        switch (opt) {
+            case 'n': {
+                opts.name = w.woptarg;
+                break;
+            }
+            case 'l': {
+                opts.local = true;
+                break;
+            }
+            case 'g': {
+                opts.global = true;
+                break;
+            }
+            case 'h': {
+                opts.print_help = true;
+                break;
+            }
+            case ':': {
+                builtin_missing_argument(parser, streams, cmd, argv[w.woptind - 1]);
+                return STATUS_INVALID_ARGS;
+            }
+            case '?': {
+                builtin_unknown_option(parser, streams, cmd, argv[w.woptind - 1]);
+                return STATUS_INVALID_ARGS;
+            }
+            default: {
+                DIE("unexpected retval from wgetopt_long");
+            }
+        }
+    }
+
+    *optind = w.woptind;
+    return STATUS_CMD_OK;
+}
+
+/// Run a command in the background, connected to the shell by pipes on its stdin and stdout.
+maybe_t<int> builtin_coproc(parser_t &parser, io_streams_t &streams, wchar_t **argv) {
+    const wchar_t *cmd = argv[0];
+    coproc_cmd_opts_t opts;
+    int argc = builtin_count_args(argv);
+    int optind;
+
+    int retval = parse_cmd_opts(opts, &optind, argc, argv, parser, streams);
+    if (retval != STATUS_CMD_OK) return retval;
+
+    if (opts.print_help) {
+        builtin_print_help(parser, streams, cmd);
+        return STATUS_CMD_OK;
+    }
+
+    if (opts.local && opts.global) {
+        streams.err.append_format(BUILTIN_ERR_GLOCAL, cmd);
+        return STATUS_INVALID_ARGS;
+    }
+    if (optind == argc) {
+        streams.err.append_format(BUILTIN_ERR_MIN_ARG_COUNT1, cmd, 1, 0);
+        return STATUS_INVALID_ARGS;
+    }
+    const wcstring name = opts.name;
+    const wcstring pid_name = name + L"_PID";
+    if (!valid_var_name(name)) {
+        streams.err.append_format(BUILTIN_ERR_VARNAME, cmd, name.c_str());
+        return STATUS_INVALID_ARGS;
+    }
+
+    // Functions and builtins run in fish itself, even with &, so they would wait for the input
+    // that only the shell can send them once they are done.
+    const wcstring program = argv[optind];
+    if (builtin_exists(program) || function_exists(program, parser)) {
+        streams.err.append_format(_(L"%ls: '%ls' is not an external command\n"), cmd,
+                                  program.c_str());
+        return STATUS_INVALID_ARGS;
+    }
+
+    wcstring command;
+    for (int i = optind; i < argc; i++) {
+        if (!command.empty()) command.push_back(L' ');
+        command.append(escape_string(argv[i], ESCAPE_ALL));
+    }
+
+    // One pipe carries input to the command, the other its output back to the shell.
+    auto to_command = make_autoclose_pipes();
+    auto from_command = make_autoclose_pipes();
+    if (!to_command || !from_command) {
+        streams.err.append_format(_(L"%ls: Could not create pipes\n"), cmd);
+        return STATUS_CMD_ERROR;
+    }
+    int read_fd = script_fd_adopt(std::move(from_command->read), L"coproc-read", command);
+    int write_fd = script_fd_adopt(std::move(to_command->write), L"coproc-write", command);
+    if (read_fd < 0 || write_fd < 0) {
+        if (read_fd >= 0) script_fd_close(read_fd);
+        if (write_fd >= 0) script_fd_close(write_fd);
+        streams.err.append_format(_(L"%ls: Could not allocate file descriptors\n"), cmd);
+        return STATUS_CMD_ERROR;
+    }
+    uint64_t read_id = script_fd_get(read_fd)->id;
+    uint64_t write_id = script_fd_get(write_fd)->id;
+
+    std::set<const job_t *> old_jobs;
+    for (const auto &j : parser.jobs()) old_jobs.insert(j.get());
+
+    // The command's ends of the pipes are only open in the command, so it sees the end of its
+    // input when the shell closes the other end.
+    {
+        io_chain_t ios = *streams.io_chain;
+        ios.push_back(
+            std::make_shared<io_pipe_t>(STDIN_FILENO, true, std::move(to_command->read)));
+        ios.push_back(
+            std::make_shared<io_pipe_t>(STDOUT_FILENO, false, std::move(from_command->write)));
+        parser.eval(command + L" &", ios);
+    }
+
+    pid_t pid = 0;
+    for (const auto &j : parser.jobs()) {
+        if (!old_jobs.count(j.get()) && !j->processes.empty()) {
+            pid = j->processes.front()->pid;
+            break;
+        }
+    }
+    if (pid == 0) {
+        // The command could not be launched; the error has been printed already.
+        script_fd_close(read_fd, read_id);
+        script_fd_close(write_fd, write_id);
+        return STATUS_CMD_ERROR;
+    }
+
+    env_mode_flags_t scope = opts.global ? ENV_GLOBAL : ENV_LOCAL;
+    wcstring_list_t fds{to_string(read_fd), to_string(write_fd)};
+    int var_err = parser.set_var_and_fire(name, scope | ENV_USER, std::move(fds));
+    if (var_err == ENV_OK) {
+        var_err = parser.set_var_and_fire(pid_name, scope | ENV_USER, to_string(pid));
+    }
+    if (var_err != ENV_OK) {
+        // The command keeps running, but sees the end of its input.
+        script_fd_close(read_fd, read_id);
+        script_fd_close(write_fd, write_id);
+        streams.err.append_format(_(L"%ls: Could not set the variable '%ls'\n"), cmd,
+                                  name.c_str());
+        return STATUS_CMD_ERROR;
+    }
+
+    // Like fdopen, local fds belong to the innermost block with its own variable scope.
+    if (!opts.global) {
+        for (size_t i = 0; block_t *block = parser.block_at_index(i); i++) {
+            if (block->wants_pop_env) {
+                block->script_fds.emplace_back(read_fd, read_id);
+                block->script_fds.emplace_back(write_fd, write_id);
+                break;
+            }
+        }
+    }
+    return STATUS_CMD_OK;
+}
@@ -18,8 +18,8 @@
 /// Helper for builtin_disown.
 static int disown_job(const wchar_t *cmd, parser_t &parser, io_streams_t &streams, job_t *j) {
     if (j == nullptr) {
-        streams.err.append_format(_(L"%ls: Unknown job '%ls'\n"), L"bg");
-        builtin_print_error_trailer(parser, streams.err, cmd);
+        builtin_print_error(parser, streams, cmd,
+                            format_string(_(L"%ls: Unknown job '%ls'\n"), L"bg"));
         return STATUS_INVALID_ARGS;
     }
 
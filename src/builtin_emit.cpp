@@ -5,19 +5,132 @@
 
 #include "builtin.h"
 #include "common.h"
+#include "env.h"
 #include "event.h"
 #include "fallback.h"  // IWYU pragma: keep
 #include "io.h"
+#include "json.h"
+#include "wgetopt.h"
 #include "wutil.h"  // IWYU pragma: keep
 
+struct emit_cmd_opts_t {
+    bool print_help = false;
+    /// The variables for the handlers, from --var and --json, in the order they were given.
+    event_variables_t variables;
+};
+
+static const wchar_t *const short_options = L"+:hv:";
+static const struct woption long_options[] = {{L"help", no_argument, nullptr, 'h'},
+                                              {L"var", required_argument, nullptr, 'v'},
+                                              {L"json", required_argument, nullptr, 1},
+                                              {nullptr, 0, nullptr, 0}};
+
+/// \return the elements of the variable \p name in \p vars, adding it if it is not there yet.
+static wcstring_list_t &variable_named(event_variables_t &vars, const wcstring &name) {
+    for (auto &var : vars) {
+        if (var.first == name) return var.second;
+    }
+    vars.emplace_back(name, wcstring_list_t{});
+    return vars.back().second;
+}
+
+/// Check that a handler can have a local variable called \p name, and report it if not.
+static bool check_variable_name(const wchar_t *cmd, const wcstring &name, io_streams_t &streams) {
+    if (!valid_var_name(name) || name == L"argv") {
+        streams.err.append_format(BUILTIN_ERR_VARNAME, cmd, name.c_str());
+        return false;
+    }
+    if (env_var_t::flags_for(name.c_str()) & env_var_t::flag_read_only) {
+        streams.err.append_format(_(L"%ls: Tried to change the read-only variable '%ls'\n"), cmd,
+                                  name.c_str());
+        return false;
+    }
+    return true;
+}
+
+/// \return the elements of a variable for the JSON value \p val: a string as it is, the elements
+/// of an array, nothing for null, and anything else as JSON.
+static wcstring_list_t json_to_elements(const json_value_t &val) {
+    using type_t = json_value_t::type_t;
+    wcstring_list_t result;
+    if (val.type == type_t::string) {
+        result.push_back(val.string);
+    } else if (val.type == type_t::array) {
+        for (const json_value_t &elem : val.array) {
+            result.push_back(elem.type == type_t::string ? elem.string : json_dump(elem));
+        }
+    } else if (val.type != type_t::null) {
+        result.push_back(json_dump(val));
+    }
+    return result;
+}
+
+static int parse_cmd_opts(emit_cmd_opts_t &opts, int *optind, int argc, wchar_t **argv,
+                          parser_t &parser, io_streams_t &streams) {
+    const wchar_t *cmd = argv[0];
+    int opt;
+    wgetopter_t w;
+    while ((opt = w.wgetopt_long(argc, argv, short_options, long_options, nullptr)) != -1) {
+        switch (opt) {
+            case 'h': {
+                opts.print_help = true;
+                break;
+            }
+            case 'v': {
+                // Giving a variable several times gives it several elements.
+                const wcstring arg = w.woptarg;
+                size_t eq = arg.find(L'=');
+                if (eq == wcstring::npos) {
+                    streams.err.append_format(_(L"%ls: Expected NAME=VALUE, got '%ls'\n"), cmd,
+                                              w.woptarg);
+                    return STATUS_INVALID_ARGS;
+                }
+                wcstring name = arg.substr(0, eq);
+                if (!check_variable_name(cmd, name, streams)) return STATUS_INVALID_ARGS;
+                variable_named(opts.variables, name).push_back(arg.substr(eq + 1));
+                break;
+            }
+            case 1: {
+                maybe_t<json_value_t> json = json_parse(w.woptarg);
+                if (!json || json->type != json_value_t::type_t::object) {
+                    streams.err.append_format(_(L"%ls: Expected a JSON object, got '%ls'\n"), cmd,
+                                              w.woptarg);
+                    return STATUS_INVALID_ARGS;
+                }
+                for (const auto &member : json->members) {
+                    if (!check_variable_name(cmd, member.first, streams)) {
+                        return STATUS_INVALID_ARGS;
+                    }
+                    variable_named(opts.variables, member.first) = json_to_elements(member.second);
+                }
+                break;
+            }
+            case ':': {
+                builtin_missing_argument(parser, streams, cmd, argv[w.woptind - 1]);
+                return STATUS_INVALID_ARGS;
+            }
+            case '?': {
+                builtin_unknown_option(parser, streams, cmd, argv[w.woptind - 1]);
+                return STATUS_INVALID_ARGS;
+            }
+            default: {
+                DIE("unexpected retval from wgetopt_long");
+            }
+        }
+    }
+
+    *optind = w.woptind;
+    return STATUS_CMD_OK;
+}
+
 /// Implementation of the builtin emit command, used to create events.
 maybe_t<int> builtin_emit(parser_t &parser, io_streams_t &streams, wchar_t **argv) {
     const wchar_t *cmd = argv[0];
     int argc = builtin_count_args(argv);
-    help_only_cmd_opts_t opts;
+    emit_cmd_opts_t opts;
 
     int optind;
-    int retval = parse_help_only_cmd_opts(opts, &optind, argc, argv, parser, streams);
+    int retval = parse_cmd_opts(opts, &optind, argc, argv, parser, streams);
     if (retval != STATUS_CMD_OK) return retval;
 
     if (opts.print_help) {
@@ -32,6 +145,6 @@ maybe_t<int> builtin_emit(parser_t &parser, io_streams_t &streams, wchar_t **arg
 
     const wchar_t *eventname = argv[optind];
     wcstring_list_t args(argv + optind + 1, argv + argc);
-    event_fire_generic(parser, eventname, &args);
+    event_fire_generic(parser, eventname, &args, &opts.variables);
     return STATUS_CMD_OK;
 }
@@ -1,6 +1,8 @@
 // Functions for executing the eval builtin.
 #include "config.h"  // IWYU pragma: keep
 
+#include "builtin_eval.h"
+
 #include <cerrno>
 #include <cstddef>
 
@@ -26,7 +28,10 @@ maybe_t<int> builtin_eval(parser_t &parser, io_streams_t &streams, wchar_t **arg
         if (i > 1) new_cmd += L' ';
         new_cmd += argv[i];
     }
+    return builtin_eval_in_streams(parser, streams, new_cmd);
+}
 
+int builtin_eval_in_streams(parser_t &parser, io_streams_t &streams, const wcstring &new_cmd) {
     // Copy the full io chain; we may append bufferfills.
     io_chain_t ios = *streams.io_chain;
 
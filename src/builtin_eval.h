@@ -2,10 +2,15 @@
 #ifndef FISH_BUILTIN_EVAL_H
 #define FISH_BUILTIN_EVAL_H
 
+#include "common.h"
 #include "maybe.h"
 
 class parser_t;
 struct io_streams_t;
 
 maybe_t<int> builtin_eval(parser_t &parser, io_streams_t &streams, wchar_t **argv);
+
+/// Evaluate \p cmd like the eval builtin, with the output going to \p streams.
+/// \return the status.
+int builtin_eval_in_streams(parser_t &parser, io_streams_t &streams, const wcstring &cmd);
 #endif
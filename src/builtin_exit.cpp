@@ -73,8 +73,8 @@ maybe_t<int> builtin_exit(parser_t &parser, io_streams_t &streams, wchar_t **arg
     }
 
     if (optind + 1 < argc) {
-        streams.err.append_format(BUILTIN_ERR_TOO_MANY_ARGUMENTS, cmd);
-        builtin_print_error_trailer(parser, streams.err, cmd);
+        builtin_print_error(parser, streams, cmd,
+                            format_string(BUILTIN_ERR_TOO_MANY_ARGUMENTS, cmd));
         return STATUS_INVALID_ARGS;
     }
 
@@ -83,8 +83,8 @@ maybe_t<int> builtin_exit(parser_t &parser, io_streams_t &streams, wchar_t **arg
     } else {
         retval = fish_wcstoi(argv[optind]);
         if (errno) {
-            streams.err.append_format(BUILTIN_ERR_NOT_NUMBER, cmd, argv[optind]);
-            builtin_print_error_trailer(parser, streams.err, cmd);
+            builtin_print_error(parser, streams, cmd,
+                                format_string(BUILTIN_ERR_NOT_NUMBER, cmd, argv[optind]));
             return STATUS_INVALID_ARGS;
         }
     }
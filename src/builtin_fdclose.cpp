@@ -0,0 +1,81 @@
+// Implementation of the fdclose builtin.
+#include "config.h"  // IWYU pragma: keep
+
+#include "builtin_fdclose.h"
+
+#include <errno.h>
+
+#include "builtin.h"
+#include "common.h"
+#include "fallback.h"  // IWYU pragma: keep
+#include "fds.h"
+#include "io.h"
+#include "wgetopt.h"
+#include "wutil.h"  // IWYU pragma: keep
+
+struct fdclose_cmd_opts_t {
+    bool print_help = false;
+};
+
+static const wchar_t *const short_options = L":h";
+static const struct woption long_options[] = {{L"help", no_argument, nullptr, 'h'},
+                                              {nullptr, 0, nullptr, 0}};
+
+static int parse_cmd_opts(fdclose_cmd_opts_t &opts, int *optind, int argc, wchar_t **argv,
+                          parser_t &parser, io_streams_t &streams) {
+    const wchar_t *cmd = argv[0];
+    int opt;
+    wgetopter_t w;
+    while ((opt = w.wgetopt_long(argc, argv, short_options, long_options, nullptr)) != -1) {
+        switch (opt) {
+            case 'h': {
+                opts.print_help = true;
+                break;
+            }
+            case ':': {
+                builtin_missing_argument(parser, streams, cmd, argv[w.woptind - 1]);
+                return STATUS_INVALID_ARGS;
+            }
+            case '?': {
+                builtin_unknown_option(parser, streams, cmd, argv[w.woptind - 1]);
+                return STATUS_INVALID_ARGS;
+            }
+            default: {
+                DIE("unexpected retval from wgetopt_long");
+            }
+        }
+    }
+
+    *optind = w.woptind;
+    return STATUS_CMD_OK;
+}
+
+/// Close fds opened by fdopen or named fd redirections.
+maybe_t<int> builtin_fdclose(parser_t &parser, io_streams_t &streams, wchar_t **argv) {
+    const wchar_t *cmd = argv[0];
+    fdclose_cmd_opts_t opts;
+    int argc = builtin_count_args(argv);
+    int optind;
+
+    int retval = parse_cmd_opts(opts, &optind, argc, argv, parser, streams);
+    if (retval != STATUS_CMD_OK) return retval;
+
+    if (opts.print_help) {
+        builtin_print_help(parser, streams, cmd);
+        return STATUS_CMD_OK;
+    }
+
+    for (int i = optind; i < argc; i++) {
+        int fd = fish_wcstoi(argv[i]);
+        if (errno) {
+            streams.err.append_format(BUILTIN_ERR_NOT_NUMBER, cmd, argv[i]);
+            return STATUS_INVALID_ARGS;
+        }
+        // Only fds the shell opened for scripts may be closed, not those it uses itself.
+        if (!script_fd_close(fd)) {
+            streams.err.append_format(_(L"%ls: %d is not an open file descriptor\n"), cmd, fd);
+            retval = STATUS_CMD_ERROR;
+        }
+    }
+    return retval;
+}
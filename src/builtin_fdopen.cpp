@@ -0,0 +1,280 @@
+// Implementation of the fdopen builtin.
+#include "config.h"  // IWYU pragma: keep
+
+#include "builtin_fdopen.h"
+
+#include <errno.h>
+#include <fcntl.h>
+#include <sys/socket.h>
+#include <sys/un.h>
+#include <unistd.h>
+
+#include <cstring>
+#include <string>
+
+#include "builtin.h"
+#include "common.h"
+#include "env.h"
+#include "fallback.h"  // IWYU pragma: keep
+#include "fds.h"
+#include "io.h"
+#include "parser.h"
+#include "path.h"
+#include "wcstringutil.h"
+#include "wgetopt.h"
+#include "wutil.h"  // IWYU pragma: keep
+
+enum class fdopen_mode_t { read, write, append, read_write, socket };
+
+struct fdopen_cmd_opts_t {
+    bool print_help = false;
+    bool query = false;
+    bool local = false;
+    bool global = false;
+    maybe_t<fdopen_mode_t> mode{};
+};
+
+static const wchar_t *const short_options = L":rwasqlgh";
+static const struct woption long_options[] = {{L"read", no_argument, nullptr, 'r'},
+                                              {L"write", no_argument, nullptr, 'w'},
+                                              {L"append", no_argument, nullptr, 'a'},
+                                              {L"read-write", no_argument, nullptr, 1},
+                                              {L"socket", no_argument, nullptr, 's'},
+                                              {L"query", no_argument, nullptr, 'q'},
+                                              {L"local", no_argument, nullptr, 'l'},
+                                              {L"global", no_argument, nullptr, 'g'},
+                                              {L"help", no_argument, nullptr, 'h'},
+                                              {nullptr, 0, nullptr, 0}};
+
+static int parse_cmd_opts(fdopen_cmd_opts_t &opts, int *optind, int argc, wchar_t **argv,
+                          parser_t &parser, io_streams_t &streams) {
+    const wchar_t *cmd = argv[0];
+    int opt;
+    wgetopter_t w;
+    while ((opt = w.wgetopt_long(argc, argv, short_options, long_options, nullptr)) != -1) {
+        maybe_t<fdopen_mode_t> mode{};
+        switch (opt) {
+            case 'r': {
+                mode = fdopen_mode_t::read;
+                break;
+            }
+            case 'w': {
+                mode = fdopen_mode_t::write;
+                break;
+            }
+            case 'a': {
+                mode = fdopen_mode_t::append;
+                break;
+            }
+            case 1: {
+                mode = fdopen_mode_t::read_write;
+                break;
+            }
+            case 's': {
+                mode = fdopen_mode_t::socket;
+                break;
+            }
+            case 'q': {
+                opts.query = true;
+                break;
+            }
+            case 'l': {
+                opts.local = true;
+                break;
+            }
+            case 'g': {
+                opts.global = true;
+                break;
+            }
+            case 'h': {
+                opts.print_help = true;
+                break;
+            }
+            case ':': {
+                builtin_missing_argument(parser, streams, cmd, argv[w.woptind - 1]);
+                return STATUS_INVALID_ARGS;
+            }
+            case '?': {
+                builtin_unknown_option(parser, streams, cmd, argv[w.woptind - 1]);
+                return STATUS_INVALID_ARGS;
+            }
+            default: {
+                DIE("unexpected retval from wgetopt_long");
+            }
+        }
+        if (mode) {
+            if (opts.mode && *opts.mode != *mode) {
+                streams.err.append_format(BUILTIN_ERR_COMBO2, cmd,
+                                          _(L"only one of the open modes may be given"));
+                return STATUS_INVALID_ARGS;
+            }
+            opts.mode = mode;
+        }
+    }
+
+    *optind = w.woptind;
+    return STATUS_CMD_OK;
+}
+
+/// \return the name of \p mode, as it is listed.
+static const wchar_t *mode_name(fdopen_mode_t mode) {
+    switch (mode) {
+        case fdopen_mode_t::read:
+            return L"read";
+        case fdopen_mode_t::write:
+            return L"write";
+        case fdopen_mode_t::append:
+            return L"append";
+        case fdopen_mode_t::read_write:
+            return L"read-write";
+        case fdopen_mode_t::socket:
+            return L"socket";
+    }
+    DIE("unexpected fdopen mode");
+}
+
+/// Connect to the Unix domain socket at \p path.
+/// \return the connected socket, or an invalid fd with errno set.
+static autoclose_fd_t connect_socket(const wcstring &path) {
+    std::string narrow = wcs2string(path);
+    struct sockaddr_un addr {};
+    if (narrow.size() >= sizeof addr.sun_path) {
+        errno = ENAMETOOLONG;
+        return autoclose_fd_t{};
+    }
+    addr.sun_family = AF_UNIX;
+    std::memcpy(addr.sun_path, narrow.c_str(), narrow.size() + 1);
+
+    autoclose_fd_t sock{socket(AF_UNIX, SOCK_STREAM, 0)};
+    if (!sock.valid()) return sock;
+    int res;
+    do {
+        res = connect(sock.fd(), reinterpret_cast<struct sockaddr *>(&addr), sizeof addr);
+    } while (res < 0 && errno == EINTR);
+    if (res < 0) {
+        int saved_errno = errno;
+        sock.close();
+        errno = saved_errno;
+    }
+    return sock;
+}
+
+/// List the open script fds, or with --query, check if the given ones are open.
+static int list_fds(const wchar_t *cmd, const fdopen_cmd_opts_t &opts, int argc, wchar_t **argv,
+                    io_streams_t &streams) {
+    if (!opts.query) {
+        for (const script_fd_t &fd : script_fd_list()) {
+            streams.out.append_format(L"%d\t%ls\t%ls\n", fd.fd, fd.mode.c_str(),
+                                      fd.target.c_str());
+        }
+        return STATUS_CMD_OK;
+    }
+    if (argc == 0) return script_fd_list().empty() ? STATUS_CMD_ERROR : STATUS_CMD_OK;
+
+    int retval = STATUS_CMD_OK;
+    for (int i = 0; i < argc; i++) {
+        int fd = fish_wcstoi(argv[i]);
+        if (errno) {
+            streams.err.append_format(BUILTIN_ERR_NOT_NUMBER, cmd, argv[i]);
+            return STATUS_INVALID_ARGS;
+        }
+        if (!script_fd_get(fd)) retval = STATUS_CMD_ERROR;
+    }
+    return retval;
+}
+
+/// Open files and sockets on fds for scripts, or list the open ones.
+maybe_t<int> builtin_fdopen(parser_t &parser, io_streams_t &streams, wchar_t **argv) {
+    const wchar_t *cmd = argv[0];
+    fdopen_cmd_opts_t opts;
+    int argc = builtin_count_args(argv);
+    int optind;
+
+    int retval = parse_cmd_opts(opts, &optind, argc, argv, parser, streams);
+    if (retval != STATUS_CMD_OK) return retval;
+
+    if (opts.print_help) {
+        builtin_print_help(parser, streams, cmd);
+        return STATUS_CMD_OK;
+    }
+
+    if (opts.local && opts.global) {
+        streams.err.append_format(BUILTIN_ERR_GLOCAL, cmd);
+        return STATUS_INVALID_ARGS;
+    }
+
+    // Without arguments, list the fds.
+    if (opts.query || optind == argc) {
+        if (opts.mode || opts.local || opts.global) {
+            streams.err.append_format(BUILTIN_ERR_COMBO, cmd);
+            return STATUS_INVALID_ARGS;
+        }
+        return list_fds(cmd, opts, argc - optind, argv + optind, streams);
+    }
+
+    if (argc - optind != 2) {
+        streams.err.append_format(BUILTIN_ERR_ARG_COUNT1, cmd, 2, argc - optind);
+        return STATUS_INVALID_ARGS;
+    }
+    const wcstring var_name = argv[optind];
+    const wcstring target = argv[optind + 1];
+    if (!valid_var_name(var_name)) {
+        streams.err.append_format(BUILTIN_ERR_VARNAME, cmd, var_name.c_str());
+        return STATUS_INVALID_ARGS;
+    }
+    if (target.empty()) {
+        streams.err.append_format(_(L"%ls: The path must not be empty\n"), cmd);
+        return STATUS_INVALID_ARGS;
+    }
+
+    fdopen_mode_t mode = opts.mode ? *opts.mode : fdopen_mode_t::read;
+    wcstring path = path_apply_working_directory(target, parser.vars().get_pwd_slash());
+    autoclose_fd_t fd;
+    if (mode == fdopen_mode_t::socket) {
+        fd = connect_socket(path);
+    } else {
+        int oflags = O_RDONLY;
+        if (mode == fdopen_mode_t::write) oflags = O_WRONLY | O_CREAT | O_TRUNC;
+        if (mode == fdopen_mode_t::append) oflags = O_WRONLY | O_CREAT | O_APPEND;
+        if (mode == fdopen_mode_t::read_write) oflags = O_RDWR | O_CREAT;
+        fd.reset(wopen_cloexec(path, oflags, 0666));
+    }
+    if (!fd.valid()) {
+        streams.err.append_format(_(L"%ls: Could not open '%ls': %s\n"), cmd, target.c_str(),
+                                  std::strerror(errno));
+        return STATUS_CMD_ERROR;
+    }
+
+    int newfd = script_fd_adopt(std::move(fd), mode_name(mode), path);
+    if (newfd < 0) {
+        streams.err.append_format(_(L"%ls: Could not allocate a file descriptor for '%ls'\n"),
+                                  cmd, target.c_str());
+        return STATUS_CMD_ERROR;
+    }
+    uint64_t id = script_fd_get(newfd)->id;
+
+    // A local fd belongs to the innermost block with its own variable scope, and is closed with
+    // it. At the top level, or with --global, it stays open until it is closed explicitly.
+    env_mode_flags_t scope = opts.global ? ENV_GLOBAL : ENV_LOCAL;
+    int var_err = parser.set_var_and_fire(var_name, scope | ENV_USER, to_string(newfd));
+    if (var_err != ENV_OK) {
+        script_fd_close(newfd, id);
+        if (var_err == ENV_PERM) {
+            streams.err.append_format(_(L"%ls: Tried to change the read-only variable '%ls'\n"),
+                                      cmd, var_name.c_str());
+        } else {
+            streams.err.append_format(_(L"%ls: Could not set the variable '%ls'\n"), cmd,
+                                      var_name.c_str());
+        }
+        return STATUS_CMD_ERROR;
+    }
+    if (!opts.global) {
+        for (size_t i = 0; block_t *block = parser.block_at_index(i); i++) {
+            if (block->wants_pop_env) {
+                block->script_fds.emplace_back(newfd, id);
+                break;
+            }
+        }
+    }
+    return STATUS_CMD_OK;
+}
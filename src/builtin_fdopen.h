@@ -0,0 +1,11 @@
+// Prototypes for executing builtin_fdopen function.
+#ifndef FISH_BUILTIN_FDOPEN_H
+#define FISH_BUILTIN_FDOPEN_H
+
+#include "maybe.h"
+
+class parser_t;
+struct io_streams_t;
+
+maybe_t<int> builtin_fdopen(parser_t &parser, io_streams_t &streams, wchar_t **argv);
+#endif
@@ -71,8 +71,8 @@ maybe_t<int> builtin_fg(parser_t &parser, io_streams_t &streams, wchar_t **argv)
     } else {
         int pid = abs(fish_wcstoi(argv[optind]));
         if (errno) {
-            streams.err.append_format(BUILTIN_ERR_NOT_NUMBER, cmd, argv[optind]);
-            builtin_print_error_trailer(parser, streams.err, cmd);
+            builtin_print_error(parser, streams, cmd,
+                                format_string(BUILTIN_ERR_NOT_NUMBER, cmd, argv[optind]));
         } else {
             job = parser.job_get_from_pid(pid);
             if (!job || !job->is_constructed() || job->is_completed()) {
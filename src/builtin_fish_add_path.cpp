@@ -0,0 +1,277 @@
+// Implementation of the fish_add_path builtin.
+#include "config.h"  // IWYU pragma: keep
+
+#include "builtin_fish_add_path.h"
+
+#include <sys/stat.h>
+
+#include <algorithm>
+#include <string>
+#include <vector>
+
+#include "builtin.h"
+#include "common.h"
+#include "env.h"
+#include "event.h"
+#include "fallback.h"  // IWYU pragma: keep
+#include "io.h"
+#include "parser.h"
+#include "wgetopt.h"
+#include "wutil.h"  // IWYU pragma: keep
+
+struct fish_add_path_cmd_opts_t {
+    bool print_help = false;
+    bool global = false;
+    bool universal = false;
+    bool prepend = false;
+    bool append = false;
+    bool move = false;
+    bool dedup = false;
+    bool verbose = false;
+    bool dry_run = false;
+    const wchar_t *variable = nullptr;
+};
+
+static const wchar_t *const short_options = L":agUPpmdvnh";
+static const struct woption long_options[] = {{L"append", no_argument, nullptr, 'a'},
+                                              {L"global", no_argument, nullptr, 'g'},
+                                              {L"universal", no_argument, nullptr, 'U'},
+                                              {L"path", no_argument, nullptr, 'P'},
+                                              {L"variable", required_argument, nullptr, 1},
+                                              {L"prepend", no_argument, nullptr, 'p'},
+                                              {L"move", no_argument, nullptr, 'm'},
+                                              {L"dedup", no_argument, nullptr, 'd'},
+                                              {L"verbose", no_argument, nullptr, 'v'},
+                                              {L"dry-run", no_argument, nullptr, 'n'},
+                                              {L"help", no_argument, nullptr, 'h'},
+                                              {nullptr, 0, nullptr, 0}};
+
+static int parse_cmd_opts(fish_add_path_cmd_opts_t &opts, int *optind, int argc, wchar_t **argv,
+                          parser_t &parser, io_streams_t &streams) {
+    const wchar_t *cmd = argv[0];
+    int opt;
+    wgetopter_t w;
+    while ((opt = w.wgetopt_long(argc, argv, short_options, long_options, nullptr)) != -1) {
+        switch (opt) {
+            case 'a': {
+                opts.append = true;
+                break;
+            }
+            case 'g': {
+                opts.global = true;
+                break;
+            }
+            case 'U': {
+                opts.universal = true;
+                break;
+            }
+            case 'P': {
+                opts.variable = L"PATH";
+                break;
+            }
+            case 1: {
+                opts.variable = w.woptarg;
+                break;
+            }
+            case 'p': {
+                opts.prepend = true;
+                break;
+            }
+            case 'm': {
+                opts.move = true;
+                break;
+            }
+            case 'd': {
+                opts.dedup = true;
+                break;
+            }
+            case 'v': {
+                opts.verbose = true;
+                break;
+            }
+            case 'n': {
+                opts.dry_run = true;
+                break;
+            }
+            case 'h': {
+                opts.print_help = true;
+                break;
+            }
+            case ':': {
+                builtin_missing_argument(parser, streams, cmd, argv[w.woptind - 1]);
+                return STATUS_INVALID_ARGS;
+            }
+            case '?': {
+                builtin_unknown_option(parser, streams, cmd, argv[w.woptind - 1]);
+                return STATUS_INVALID_ARGS;
+            }
+            default: {
+                DIE("unexpected retval from wgetopt_long");
+            }
+        }
+    }
+
+    *optind = w.woptind;
+    return STATUS_CMD_OK;
+}
+
+/// \return \p path made absolute relative to \p pwd and normalized, without resolving symlinks,
+/// like `realpath -s`, so entries can be compared.
+static wcstring canonical_path(const wcstring &path, const wcstring &pwd) {
+    wcstring absolute = path.front() == L'/' ? path : pwd + path;
+    return normalize_path(absolute, false);
+}
+
+/// Add directories to $fish_user_paths, $PATH or another path variable, without duplicates.
+maybe_t<int> builtin_fish_add_path(parser_t &parser, io_streams_t &streams, wchar_t **argv) {
+    const wchar_t *cmd = argv[0];
+    fish_add_path_cmd_opts_t opts;
+    int argc = builtin_count_args(argv);
+    int optind;
+
+    int retval = parse_cmd_opts(opts, &optind, argc, argv, parser, streams);
+    if (retval != STATUS_CMD_OK) return retval;
+
+    if (opts.print_help) {
+        builtin_print_help(parser, streams, cmd);
+        return STATUS_CMD_OK;
+    }
+
+    if (opts.global && opts.universal) {
+        streams.err.append_format(BUILTIN_ERR_GLOCAL, cmd);
+        return STATUS_INVALID_ARGS;
+    }
+    if (opts.prepend && opts.append) {
+        streams.err.append_format(BUILTIN_ERR_COMBO2, cmd,
+                                  L"--prepend and --append are mutually exclusive");
+        return STATUS_INVALID_ARGS;
+    }
+
+    const wcstring var_name = opts.variable ? opts.variable : L"fish_user_paths";
+    if (!valid_var_name(var_name)) {
+        streams.err.append_format(BUILTIN_ERR_VARNAME, cmd, var_name.c_str());
+        return STATUS_INVALID_ARGS;
+    }
+    // $PATH is set up anew by every shell, so a universal one would never be used.
+    if (opts.universal && var_name == L"PATH") {
+        streams.err.append_format(_(L"%ls: $PATH can't be made universal\n"), cmd);
+        return STATUS_INVALID_ARGS;
+    }
+
+    // Modify the variable in the given scope, or else where it is defined. A new $fish_user_paths
+    // is universal, so it is kept, and any other variable is global.
+    auto &vars = parser.vars();
+    maybe_t<env_var_t> existing;
+    const wchar_t *scope_flag = nullptr;
+    env_mode_flags_t scope = ENV_USER;
+    if (opts.global || opts.universal) {
+        scope_flag = opts.global ? L"-g" : L"-U";
+        scope |= opts.global ? ENV_GLOBAL : ENV_UNIVERSAL;
+        existing = vars.get(var_name, opts.global ? ENV_GLOBAL : ENV_UNIVERSAL);
+        // A new global would hide the paths in the universal variable, so it starts out with them.
+        if (!existing && opts.global) existing = vars.get(var_name, ENV_UNIVERSAL);
+    } else {
+        existing = vars.get(var_name);
+        if (!existing) {
+            scope_flag = var_name == L"fish_user_paths" ? L"-U" : L"-g";
+            scope |= var_name == L"fish_user_paths" ? ENV_UNIVERSAL : ENV_GLOBAL;
+        }
+    }
+    if (opts.universal && vars.get(var_name, ENV_GLOBAL)) {
+        streams.err.append_format(
+            _(L"%ls: Universal variable '%ls' is shadowed by the global variable of the same "
+              L"name.\n"),
+            cmd, var_name.c_str());
+    }
+
+    const wcstring_list_t old_paths = existing ? existing->as_list() : wcstring_list_t{};
+    const wcstring pwd = vars.get_pwd_slash();
+    wcstring_list_t canonical_old;
+    for (const wcstring &path : old_paths) {
+        canonical_old.push_back(path.empty() ? path : canonical_path(path, pwd));
+    }
+
+    // Entries are removed from the old value if they are moved, or are duplicates and --dedup is
+    // given.
+    std::vector<bool> removed(old_paths.size(), false);
+    if (opts.dedup) {
+        for (size_t i = 0; i < old_paths.size(); i++) {
+            auto first = std::find(canonical_old.begin(), canonical_old.end(), canonical_old[i]);
+            removed[i] = static_cast<size_t>(first - canonical_old.begin()) < i;
+        }
+    }
+
+    wcstring_list_t new_paths;
+    for (int i = optind; i < argc; i++) {
+        const wcstring arg = argv[i];
+        if (arg.empty()) continue;
+        wcstring path = canonical_path(arg, pwd);
+
+        struct stat buf;
+        const wchar_t *problem = nullptr;
+        if (wstat(path, &buf) == -1) {
+            problem = _(L"it does not exist");
+        } else if (!S_ISDIR(buf.st_mode)) {
+            problem = _(L"it is not a directory");
+        }
+        if (problem) {
+            if (opts.verbose || opts.dry_run) {
+                streams.err.append_format(_(L"%ls: Skipping '%ls' because %ls\n"), cmd,
+                                          arg.c_str(), problem);
+            }
+            continue;
+        }
+        if (contains(new_paths, path)) continue;
+
+        bool found = false;
+        for (size_t j = 0; j < canonical_old.size(); j++) {
+            if (canonical_old[j] != path) continue;
+            found = true;
+            if (opts.move) removed[j] = true;
+        }
+        if (!found || opts.move) new_paths.push_back(std::move(path));
+    }
+
+    wcstring_list_t result;
+    for (size_t i = 0; i < old_paths.size(); i++) {
+        if (!removed[i]) result.push_back(old_paths[i]);
+    }
+    result.insert(opts.append ? result.end() : result.begin(), new_paths.begin(),
+                  new_paths.end());
+
+    // If nothing changes, the variable is not set again, so its handlers don't run.
+    if (existing && result == old_paths) return STATUS_CMD_ERROR;
+    if (!existing && result.empty()) return STATUS_CMD_ERROR;
+
+    if (opts.verbose || opts.dry_run) {
+        wcstring command = L"set";
+        if (scope_flag) command.append(L" ").append(scope_flag);
+        command.append(L" ").append(escape_string(var_name, ESCAPE_ALL));
+        for (const wcstring &path : result) {
+            command.append(L" ").append(escape_string(path, ESCAPE_ALL));
+        }
+        streams.out.append(command + L"\n");
+    }
+    if (opts.dry_run) return STATUS_CMD_OK;
+
+    std::vector<event_t> evts;
+    switch (vars.set(var_name, scope, std::move(result), &evts)) {
+        case ENV_OK: {
+            break;
+        }
+        case ENV_PERM: {
+            streams.err.append_format(_(L"%ls: Tried to change the read-only variable '%ls'\n"),
+                                      cmd, var_name.c_str());
+            return STATUS_CMD_ERROR;
+        }
+        default: {
+            streams.err.append_format(_(L"%ls: Could not set the variable '%ls'\n"), cmd,
+                                      var_name.c_str());
+            return STATUS_CMD_ERROR;
+        }
+    }
+    for (const auto &evt : evts) {
+        event_fire(parser, evt);
+    }
+    return STATUS_CMD_OK;
+}
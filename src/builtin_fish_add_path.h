@@ -0,0 +1,11 @@
+// Prototypes for executing builtin_fish_add_path function.
+#ifndef FISH_BUILTIN_FISH_ADD_PATH_H
+#define FISH_BUILTIN_FISH_ADD_PATH_H
+
+#include "maybe.h"
+
+class parser_t;
+struct io_streams_t;
+
+maybe_t<int> builtin_fish_add_path(parser_t &parser, io_streams_t &streams, wchar_t **argv);
+#endif
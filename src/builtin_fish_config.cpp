@@ -0,0 +1,285 @@
+// Implementation of the fish_config builtin, which starts the web-based or terminal configuration
+// and manages color themes.
+#include "config.h"  // IWYU pragma: keep
+
+#include "builtin_fish_config.h"
+
+#include <cwchar>
+#include <string>
+#include <vector>
+
+#include "builtin.h"
+#include "common.h"
+#include "config_tui.h"
+#include "env.h"
+#include "fallback.h"  // IWYU pragma: keep
+#include "io.h"
+#include "parser.h"
+#include "sample_prompt.h"
+#include "termsize.h"
+#include "theme.h"
+#include "web_config.h"
+#include "wgetopt.h"
+#include "wutil.h"  // IWYU pragma: keep
+
+enum theme_cmd_t { THEME_CHOOSE = 1, THEME_DUMP, THEME_LIST, THEME_SAVE, THEME_SHOW, THEME_UNDEF };
+
+// Must be sorted by string, not enum or random.
+static const enum_map<theme_cmd_t> theme_enum_map[] = {
+    {THEME_CHOOSE, L"choose"}, {THEME_DUMP, L"dump"}, {THEME_LIST, L"list"},
+    {THEME_SAVE, L"save"},     {THEME_SHOW, L"show"}, {THEME_UNDEF, nullptr}};
+#define theme_enum_map_len (sizeof theme_enum_map / sizeof *theme_enum_map)
+
+enum prompt_cmd_t { PROMPT_CHOOSE = 1, PROMPT_LIST, PROMPT_SHOW, PROMPT_UNDEF };
+
+// Must be sorted by string, not enum or random.
+static const enum_map<prompt_cmd_t> prompt_enum_map[] = {{PROMPT_CHOOSE, L"choose"},
+                                                         {PROMPT_LIST, L"list"},
+                                                         {PROMPT_SHOW, L"show"},
+                                                         {PROMPT_UNDEF, nullptr}};
+#define prompt_enum_map_len (sizeof prompt_enum_map / sizeof *prompt_enum_map)
+
+static const wchar_t *const short_options = L":h";
+static const struct woption long_options[] = {{L"help", no_argument, nullptr, 'h'},
+                                              {nullptr, 0, nullptr, 0}};
+
+/// Check that the subcommand \p subcmd_str has between \p min and \p max arguments, and print an
+/// error if not.
+static bool fish_config_check_arg_count(io_streams_t &streams, const wchar_t *cmd,
+                                        const wcstring &subcmd_str, const wcstring_list_t &args,
+                                        size_t min, size_t max) {
+    if (args.size() >= min && args.size() <= max) return true;
+    streams.err.append_format(BUILTIN_ERR_ARG_COUNT2, cmd, subcmd_str.c_str(),
+                              static_cast<int>(args.size() < min ? min : max),
+                              static_cast<int>(args.size()));
+    return false;
+}
+
+/// Run `fish_config theme`, with the \p argc arguments \p argv after "theme".
+static int fish_config_theme(parser_t &parser, io_streams_t &streams, const wchar_t *cmd, int argc,
+                             wchar_t **argv) {
+    theme_cmd_t subcmd = THEME_UNDEF;
+    int optind = 0;
+    if (optind < argc) {
+        subcmd = str_to_enum(argv[optind], theme_enum_map, theme_enum_map_len);
+        if (subcmd == THEME_UNDEF) {
+            builtin_print_error(parser, streams, cmd,
+                                format_string(BUILTIN_ERR_INVALID_SUBCMD, cmd, argv[optind]));
+            return STATUS_INVALID_ARGS;
+        }
+        optind++;
+    } else {
+        subcmd = THEME_LIST;
+    }
+    const wcstring subcmd_str = wcstring(L"theme ") + enum_to_str(subcmd, theme_enum_map);
+    const wcstring_list_t args(argv + optind, argv + argc);
+
+    auto check_arg_count = [&](size_t min, size_t max) {
+        return fish_config_check_arg_count(streams, cmd, subcmd_str, args, min, max);
+    };
+    auto load = [&](const wcstring &name) {
+        wcstring err;
+        auto theme = theme_load(name, parser.vars(), &err);
+        if (!theme) streams.err.append_format(L"%ls: %ls\n", cmd, err.c_str());
+        return theme;
+    };
+
+    switch (subcmd) {
+        case THEME_LIST: {
+            if (!check_arg_count(0, 0)) return STATUS_INVALID_ARGS;
+            for (const wcstring &name : theme_list(parser.vars())) {
+                streams.out.append(name);
+                streams.out.push_back(L'\n');
+            }
+            return STATUS_CMD_OK;
+        }
+        case THEME_SHOW: {
+            const wcstring_list_t names = args.empty() ? theme_list(parser.vars()) : args;
+            int status = STATUS_CMD_OK;
+            bool first = true;
+            if (args.empty()) {
+                streams.out.append(_(L"Current\n"));
+                streams.out.append(theme_sample(nullptr, parser.vars()));
+                first = false;
+            }
+            for (const wcstring &name : names) {
+                auto theme = load(name);
+                if (!theme) {
+                    status = STATUS_CMD_ERROR;
+                    continue;
+                }
+                if (!first) streams.out.push_back(L'\n');
+                first = false;
+                streams.out.append(name);
+                streams.out.push_back(L'\n');
+                streams.out.append(theme_sample(&*theme, parser.vars()));
+            }
+            return status;
+        }
+        case THEME_CHOOSE: {
+            if (!check_arg_count(1, 1)) return STATUS_INVALID_ARGS;
+            auto theme = load(args.front());
+            if (!theme) return STATUS_CMD_ERROR;
+            theme_use(parser, *theme, ENV_GLOBAL);
+            return STATUS_CMD_OK;
+        }
+        case THEME_SAVE: {
+            if (!check_arg_count(0, 1)) return STATUS_INVALID_ARGS;
+            maybe_t<theme_t> theme;
+            if (args.empty()) {
+                // Save the current colors.
+                theme_t current;
+                for (const auto &kv : theme_color_variables(parser.vars())) {
+                    current.colors.emplace_back(kv.first, kv.second.as_list());
+                }
+                theme = std::move(current);
+            } else {
+                theme = load(args.front());
+                if (!theme) return STATUS_CMD_ERROR;
+            }
+            theme_use(parser, *theme, ENV_UNIVERSAL);
+            return STATUS_CMD_OK;
+        }
+        case THEME_DUMP: {
+            if (!check_arg_count(0, 0)) return STATUS_INVALID_ARGS;
+            streams.out.append(theme_dump(parser.vars()));
+            return STATUS_CMD_OK;
+        }
+        case THEME_UNDEF: {
+            DIE("Unexpected THEME_UNDEF seen");
+        }
+    }
+    DIE("unexpected theme subcommand");
+}
+
+/// Run `fish_config prompt`, with the \p argc arguments \p argv after "prompt".
+static int fish_config_prompt(parser_t &parser, io_streams_t &streams, const wchar_t *cmd, int argc,
+                              wchar_t **argv) {
+    prompt_cmd_t subcmd = str_to_enum(argv[0], prompt_enum_map, prompt_enum_map_len);
+    if (subcmd == PROMPT_UNDEF) {
+        builtin_print_error(parser, streams, cmd,
+                            format_string(BUILTIN_ERR_INVALID_SUBCMD, cmd, argv[0]));
+        return STATUS_INVALID_ARGS;
+    }
+    const wcstring subcmd_str = wcstring(L"prompt ") + enum_to_str(subcmd, prompt_enum_map);
+    const wcstring_list_t args(argv + 1, argv + argc);
+
+    auto check_arg_count = [&](size_t min, size_t max) {
+        return fish_config_check_arg_count(streams, cmd, subcmd_str, args, min, max);
+    };
+    auto load = [&](const wcstring &name) {
+        auto prompt = sample_prompt_load(name);
+        if (!prompt) {
+            streams.err.append_format(_(L"%ls: No such prompt: '%ls'\n"), cmd, name.c_str());
+        }
+        return prompt;
+    };
+    // Show prompts as they would be on this terminal.
+    const size_t width = termsize_last().width;
+
+    switch (subcmd) {
+        case PROMPT_LIST: {
+            if (!check_arg_count(0, 0)) return STATUS_INVALID_ARGS;
+            for (const wcstring &name : sample_prompt_list()) {
+                streams.out.append(name);
+                streams.out.push_back(L'\n');
+            }
+            return STATUS_CMD_OK;
+        }
+        case PROMPT_SHOW: {
+            const wcstring_list_t names = args.empty() ? sample_prompt_list() : args;
+            int status = STATUS_CMD_OK;
+            bool first = true;
+            if (args.empty()) {
+                streams.out.append(_(L"Current\n"));
+                streams.out.append(sample_prompt_render(sample_prompt_run_current(parser), width));
+                streams.out.push_back(L'\n');
+                first = false;
+            }
+            for (const wcstring &name : names) {
+                auto prompt = load(name);
+                if (!prompt) {
+                    status = STATUS_CMD_ERROR;
+                    continue;
+                }
+                if (!first) streams.out.push_back(L'\n');
+                first = false;
+                streams.out.append(name);
+                streams.out.push_back(L'\n');
+                streams.out.append(
+                    sample_prompt_render(sample_prompt_run(parser, prompt->function), width));
+                streams.out.push_back(L'\n');
+            }
+            return status;
+        }
+        case PROMPT_CHOOSE: {
+            if (!check_arg_count(1, 1)) return STATUS_INVALID_ARGS;
+            auto prompt = load(args.front());
+            if (!prompt) return STATUS_CMD_ERROR;
+            streams.out.append(
+                sample_prompt_render(sample_prompt_run(parser, prompt->function), width));
+            streams.out.push_back(L'\n');
+            if (!sample_prompt_use(parser, prompt->function)) {
+                streams.err.append_format(_(L"%ls: Unable to save the prompt '%ls'\n"), cmd,
+                                          args.front().c_str());
+                return STATUS_CMD_ERROR;
+            }
+            return STATUS_CMD_OK;
+        }
+        case PROMPT_UNDEF: {
+            DIE("Unexpected PROMPT_UNDEF seen");
+        }
+    }
+    DIE("unexpected prompt subcommand");
+}
+
+/// The fish_config builtin.
+maybe_t<int> builtin_fish_config(parser_t &parser, io_streams_t &streams, wchar_t **argv) {
+    const wchar_t *cmd = argv[0];
+    int argc = builtin_count_args(argv);
+
+    int opt;
+    wgetopter_t w;
+    while ((opt = w.wgetopt_long(argc, argv, short_options, long_options, nullptr)) != -1) {
+        switch (opt) {
+            case 'h': {
+                builtin_print_help(parser, streams, cmd);
+                return STATUS_CMD_OK;
+            }
+            case ':': {
+                builtin_missing_argument(parser, streams, cmd, argv[w.woptind - 1]);
+                return STATUS_INVALID_ARGS;
+            }
+            case '?': {
+                builtin_unknown_option(parser, streams, cmd, argv[w.woptind - 1]);
+                return STATUS_INVALID_ARGS;
+            }
+            default: {
+                DIE("unexpected retval from wgetopt_long");
+            }
+        }
+    }
+
+    int optind = w.woptind;
+    if (optind < argc && std::wcscmp(argv[optind], L"tui") == 0) {
+        if (argc - optind > 2) {
+            streams.err.append_format(BUILTIN_ERR_ARG_COUNT2, cmd, L"tui", 1, argc - optind - 1);
+            return STATUS_INVALID_ARGS;
+        }
+        return config_tui_run(parser, streams, optind + 1 < argc ? argv[optind + 1] : L"");
+    }
+    if (optind + 1 < argc && std::wcscmp(argv[optind], L"prompt") == 0) {
+        // Without a subcommand, "prompt" is the tab of the web-based configuration.
+        return fish_config_prompt(parser, streams, cmd, argc - optind - 1, argv + optind + 1);
+    }
+    if (optind == argc || std::wcscmp(argv[optind], L"theme") != 0) {
+        // Anything else is the tab the web-based configuration starts with.
+        if (argc - optind > 1) {
+            builtin_print_error(parser, streams, cmd,
+                                format_string(BUILTIN_ERR_MAX_ARG_COUNT1, cmd, 1, argc - optind));
+            return STATUS_INVALID_ARGS;
+        }
+        return web_config_run(parser, streams, optind < argc ? argv[optind] : L"");
+    }
+    return fish_config_theme(parser, streams, cmd, argc - optind - 1, argv + optind + 1);
+}
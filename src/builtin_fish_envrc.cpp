@@ -0,0 +1,155 @@
+// Implementation of the fish_envrc builtin, which trusts the .envrc.fish files of directories.
+#include "config.h"  // IWYU pragma: keep
+
+#include "builtin_fish_envrc.h"
+
+#include <algorithm>
+#include <string>
+#include <vector>
+
+#include "builtin.h"
+#include "common.h"
+#include "dir_env.h"
+#include "env.h"
+#include "fallback.h"  // IWYU pragma: keep
+#include "io.h"
+#include "parser.h"
+#include "path.h"
+#include "wcstringutil.h"
+#include "wgetopt.h"
+#include "wutil.h"  // IWYU pragma: keep
+
+enum envrc_cmd_t { ENVRC_ALLOW = 1, ENVRC_DENY, ENVRC_STATUS, ENVRC_UNDEF };
+
+// Must be sorted by string, not enum or random.
+static const enum_map<envrc_cmd_t> envrc_enum_map[] = {{ENVRC_ALLOW, L"allow"},
+                                                       {ENVRC_DENY, L"deny"},
+                                                       {ENVRC_STATUS, L"status"},
+                                                       {ENVRC_UNDEF, nullptr}};
+#define envrc_enum_map_len (sizeof envrc_enum_map / sizeof *envrc_enum_map)
+
+static const wchar_t *const short_options = L":h";
+static const struct woption long_options[] = {{L"help", no_argument, nullptr, 'h'},
+                                              {nullptr, 0, nullptr, 0}};
+
+/// The length of the hash at the start of the elements of fish_envrc_allowed, with its space.
+static constexpr size_t hash_prefix_len = 65;
+
+/// Trust the file in \p dir as it is now if \p allow is set, or stop trusting it otherwise.
+static int allow_or_deny(parser_t &parser, io_streams_t &streams, const wchar_t *cmd,
+                         const wcstring &dir, bool allow) {
+    const wcstring pwd = parser.vars().get_pwd_slash();
+    wcstring path = normalize_path(path_apply_working_directory(dir, pwd));
+    if (!string_suffixes_string(L"/", path)) path.push_back(L'/');
+    path.append(DIR_ENV_FILE);
+
+    wcstring_list_t allowed;
+    if (auto var = parser.vars().get(DIR_ENV_ALLOWED_VAR, ENV_UNIVERSAL)) {
+        allowed = var->as_list();
+    }
+    // Only the current contents of a file are trusted.
+    auto is_for_path = [&](const wcstring &entry) {
+        return entry.size() > hash_prefix_len &&
+               entry.compare(hash_prefix_len, wcstring::npos, path) == 0;
+    };
+    size_t count = allowed.size();
+    allowed.erase(std::remove_if(allowed.begin(), allowed.end(), is_for_path), allowed.end());
+    if (allow) {
+        maybe_t<wcstring> entry = dir_env_allowed_entry(path);
+        if (!entry) {
+            streams.err.append_format(_(L"%ls: Could not read '%ls'\n"), cmd, path.c_str());
+            return STATUS_CMD_ERROR;
+        }
+        allowed.push_back(entry.acquire());
+    } else if (allowed.size() == count) {
+        streams.err.append_format(_(L"%ls: '%ls' is not trusted\n"), cmd, path.c_str());
+        return STATUS_CMD_ERROR;
+    }
+
+    parser.set_var_and_fire(DIR_ENV_ALLOWED_VAR, ENV_UNIVERSAL, std::move(allowed));
+    dir_env_reload(parser);
+    return STATUS_CMD_OK;
+}
+
+/// Print the files of the directories which were entered, and whether they are loaded.
+static int print_status(parser_t &parser, io_streams_t &streams) {
+    std::vector<dir_env_file_t> files = dir_env_files();
+    for (const dir_env_file_t &file : files) {
+        wcstring state;
+        if (!file.loaded) {
+            state = _(L"not trusted");
+        } else if (!dir_env_is_allowed(parser.vars(), file.path)) {
+            state = _(L"loaded, but changed since it was trusted");
+        } else {
+            state = _(L"loaded");
+        }
+        streams.out.append_format(L"%ls: %ls\n", file.path.c_str(), state.c_str());
+        if (!file.changed_vars.empty()) {
+            streams.out.append_format(_(L"  changed variables: %ls\n"),
+                                      join_strings(file.changed_vars, L' ').c_str());
+        }
+    }
+    return files.empty() ? STATUS_CMD_ERROR : STATUS_CMD_OK;
+}
+
+/// The fish_envrc builtin.
+maybe_t<int> builtin_fish_envrc(parser_t &parser, io_streams_t &streams, wchar_t **argv) {
+    const wchar_t *cmd = argv[0];
+    int argc = builtin_count_args(argv);
+
+    int opt;
+    wgetopter_t w;
+    while ((opt = w.wgetopt_long(argc, argv, short_options, long_options, nullptr)) != -1) {
+        switch (opt) {
+            case 'h': {
+                builtin_print_help(parser, streams, cmd);
+                return STATUS_CMD_OK;
+            }
+            case ':': {
+                builtin_missing_argument(parser, streams, cmd, argv[w.woptind - 1]);
+                return STATUS_INVALID_ARGS;
+            }
+            case '?': {
+                builtin_unknown_option(parser, streams, cmd, argv[w.woptind - 1]);
+                return STATUS_INVALID_ARGS;
+            }
+            default: {
+                DIE("unexpected retval from wgetopt_long");
+            }
+        }
+    }
+
+    int optind = w.woptind;
+    envrc_cmd_t subcmd = ENVRC_STATUS;
+    if (optind < argc) {
+        subcmd = str_to_enum(argv[optind], envrc_enum_map, envrc_enum_map_len);
+        if (subcmd == ENVRC_UNDEF) {
+            builtin_print_error(parser, streams, cmd,
+                                format_string(BUILTIN_ERR_INVALID_SUBCMD, cmd, argv[optind]));
+            return STATUS_INVALID_ARGS;
+        }
+        optind++;
+    }
+    const wchar_t *subcmd_str = enum_to_str(subcmd, envrc_enum_map);
+    size_t max_args = subcmd == ENVRC_STATUS ? 0 : 1;
+    if (static_cast<size_t>(argc - optind) > max_args) {
+        streams.err.append_format(BUILTIN_ERR_ARG_COUNT2, cmd, subcmd_str,
+                                  static_cast<int>(max_args), argc - optind);
+        return STATUS_INVALID_ARGS;
+    }
+
+    switch (subcmd) {
+        case ENVRC_ALLOW:
+        case ENVRC_DENY: {
+            const wcstring dir = optind < argc ? argv[optind] : L".";
+            return allow_or_deny(parser, streams, cmd, dir, subcmd == ENVRC_ALLOW);
+        }
+        case ENVRC_STATUS: {
+            return print_status(parser, streams);
+        }
+        case ENVRC_UNDEF: {
+            DIE("unexpected subcommand");
+        }
+    }
+    DIE("unexpected subcommand");
+}
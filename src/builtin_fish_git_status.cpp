@@ -0,0 +1,243 @@
+// Implementation of the fish_git_status builtin.
+#include "config.h"  // IWYU pragma: keep
+
+#include "builtin_fish_git_status.h"
+
+#include <cstddef>
+#include <string>
+#include <unordered_map>
+
+#include "builtin.h"
+#include "common.h"
+#include "env.h"
+#include "fallback.h"  // IWYU pragma: keep
+#include "git_status.h"
+#include "io.h"
+#include "iothread.h"
+#include "parser.h"
+#include "proc.h"
+#include "reader.h"
+#include "wcstringutil.h"
+#include "wgetopt.h"
+#include "wutil.h"  // IWYU pragma: keep
+
+namespace {
+/// The changes last counted in a repository. This is only accessed on the main thread.
+struct git_changes_cache_t {
+    /// git_state_key() when the changes were counted.
+    std::string changes_key;
+    /// The changes of the last count which finished, or none() if it failed.
+    maybe_t<git_changes_t> changes;
+    /// Whether untracked files are counted.
+    bool untracked{false};
+    /// The key of the last count which was started.
+    std::string started_key;
+    /// The value of reader_run_count() when the last count was started.
+    uint64_t started_at{0};
+    /// Identifies the last count which was started, so results from earlier ones can be dropped.
+    uint64_t generation{0};
+    /// Whether the last count which was started has not finished.
+    bool running{false};
+};
+}  // namespace
+
+/// The cached changes, by git directory.
+static std::unordered_map<std::string, git_changes_cache_t> &changes_caches() {
+    ASSERT_IS_MAIN_THREAD();
+    static std::unordered_map<std::string, git_changes_cache_t> s_caches;
+    return s_caches;
+}
+
+/// The fields which can be printed. The last four need the work tree to be read.
+static const wchar_t *const field_names[] = {
+    L"git-dir",   L"work-tree", L"inside-git-dir", L"inside-work-tree", L"bare",
+    L"branch",    L"sha",       L"operation",      L"step",             L"total",
+    L"staged",    L"dirty",     L"unmerged",       L"untracked"};
+
+static bool is_changes_field(const wcstring &field) {
+    return field == L"staged" || field == L"dirty" || field == L"unmerged" ||
+           field == L"untracked";
+}
+
+/// \return the value of \p field, or an empty string if it is not known.
+static wcstring field_value(const wcstring &field, const git_repo_t &repo, const git_head_t &head,
+                            const maybe_t<git_changes_t> &changes) {
+    auto count = [&](size_t git_changes_t::*member) {
+        return changes ? to_string((*changes).*member) : wcstring();
+    };
+    auto boolean = [](bool value) { return wcstring(value ? L"true" : L"false"); };
+    if (field == L"git-dir") return str2wcstring(repo.git_dir);
+    if (field == L"work-tree") return str2wcstring(repo.work_tree);
+    if (field == L"inside-git-dir") return boolean(repo.inside_git_dir);
+    if (field == L"inside-work-tree") return boolean(!repo.inside_git_dir && !repo.bare);
+    if (field == L"bare") return boolean(repo.bare);
+    if (field == L"branch") return str2wcstring(head.branch);
+    if (field == L"sha") return str2wcstring(head.sha);
+    if (field == L"operation") return str2wcstring(head.operation);
+    if (field == L"step") return str2wcstring(head.step);
+    if (field == L"total") return str2wcstring(head.total);
+    if (field == L"staged") return count(&git_changes_t::staged);
+    if (field == L"dirty") return count(&git_changes_t::dirty);
+    if (field == L"unmerged") return count(&git_changes_t::unmerged);
+    if (field == L"untracked" && changes && changes->untracked) {
+        return to_string(*changes->untracked);
+    }
+    return wcstring();
+}
+
+/// \return the changes last counted in \p repo, and count them again in the background if HEAD or
+/// the index changed or a command was executed since. Once they are counted, the prompt is
+/// repainted if they changed. Only the first time they are counted right away.
+static maybe_t<git_changes_t> background_changes(const git_repo_t &repo, const git_head_t &head,
+                                                 bool count_untracked) {
+    const std::string key = git_state_key(repo);
+    auto found = changes_caches().find(repo.git_dir);
+    if (found == changes_caches().end() || found->second.untracked != count_untracked) {
+        // Nothing to show yet.
+        git_changes_cache_t &cache = changes_caches()[repo.git_dir];
+        cache = git_changes_cache_t{};
+        cache.changes = git_read_changes(repo, head, count_untracked);
+        cache.changes_key = key;
+        cache.untracked = count_untracked;
+        cache.started_key = key;
+        cache.started_at = reader_run_count();
+        return cache.changes;
+    }
+
+    git_changes_cache_t &cache = found->second;
+    if (cache.started_key != key || (!cache.running && cache.started_at != reader_run_count())) {
+        static uint64_t s_generation = 0;
+        const uint64_t generation = ++s_generation;
+        cache.generation = generation;
+        cache.started_key = key;
+        cache.started_at = reader_run_count();
+        cache.running = true;
+        const std::string git_dir = repo.git_dir;
+        iothread_perform([=]() { return git_read_changes(repo, head, count_untracked); },
+                         [=](maybe_t<git_changes_t> changes) {
+                             git_changes_cache_t &finished = changes_caches()[git_dir];
+                             if (finished.generation != generation) return;
+                             finished.running = false;
+                             if (finished.changes_key == key && finished.changes == changes) {
+                                 return;
+                             }
+                             finished.changes_key = key;
+                             finished.changes = changes;
+                             reader_schedule_prompt_repaint();
+                         });
+    }
+    return cache.changes;
+}
+
+struct fish_git_status_cmd_opts_t {
+    bool print_help = false;
+    bool background = false;
+    bool untracked = false;
+};
+
+static const wchar_t *const short_options = L"+:bhu";
+static const struct woption long_options[] = {{L"background", no_argument, nullptr, 'b'},
+                                              {L"untracked", no_argument, nullptr, 'u'},
+                                              {L"help", no_argument, nullptr, 'h'},
+                                              {nullptr, 0, nullptr, 0}};
+
+static int parse_cmd_opts(fish_git_status_cmd_opts_t &opts, int *optind, int argc, wchar_t **argv,
+                          parser_t &parser, io_streams_t &streams) {
+    const wchar_t *cmd = argv[0];
+    int opt;
+    wgetopter_t w;
+    while ((opt = w.wgetopt_long(argc, argv, short_options, long_options, nullptr)) != -1) {
+        switch (opt) {
+            case 'b': {
+                opts.background = true;
+                break;
+            }
+            case 'u': {
+                opts.untracked = true;
+                break;
+            }
+            case 'h': {
+                opts.print_help = true;
+                break;
+            }
+            case ':': {
+                builtin_missing_argument(parser, streams, cmd, argv[w.woptind - 1]);
+                return STATUS_INVALID_ARGS;
+            }
+            case '?': {
+                builtin_unknown_option(parser, streams, cmd, argv[w.woptind - 1]);
+                return STATUS_INVALID_ARGS;
+            }
+            default: {
+                DIE("unexpected retval from wgetopt_long");
+            }
+        }
+    }
+
+    *optind = w.woptind;
+    return STATUS_CMD_OK;
+}
+
+/// Print the state of the git repository the current directory is in, without running git.
+maybe_t<int> builtin_fish_git_status(parser_t &parser, io_streams_t &streams, wchar_t **argv) {
+    const wchar_t *cmd = argv[0];
+    fish_git_status_cmd_opts_t opts;
+    int argc = builtin_count_args(argv);
+    int optind;
+
+    int retval = parse_cmd_opts(opts, &optind, argc, argv, parser, streams);
+    if (retval != STATUS_CMD_OK) return retval;
+
+    if (opts.print_help) {
+        builtin_print_help(parser, streams, cmd);
+        return STATUS_CMD_OK;
+    }
+
+    wcstring_list_t fields(argv + optind, argv + argc);
+    bool count_untracked = opts.untracked || contains(fields, wcstring(L"untracked"));
+    for (const wcstring &field : fields) {
+        if (!contains(field_names, field)) {
+            builtin_print_error(
+                parser, streams, cmd,
+                format_string(_(L"%ls: Unknown field '%ls'\n"), cmd, field.c_str()));
+            return STATUS_INVALID_ARGS;
+        }
+    }
+    if (fields.empty()) fields.assign(std::begin(field_names), std::end(field_names));
+
+    const auto &vars = parser.vars();
+    auto get_var = [&](const wchar_t *name) {
+        auto var = vars.get(name);
+        return var ? wcs2string(var->as_string()) : std::string();
+    };
+    git_user_dirs_t user_dirs{get_var(L"HOME"), get_var(L"XDG_CONFIG_HOME")};
+    std::string pwd = wcs2string(vars.get_pwd_slash());
+    if (pwd.size() > 1 && pwd.back() == '/') pwd.pop_back();
+    auto repo = git_find_repo(pwd, get_var(L"GIT_DIR"), get_var(L"GIT_WORK_TREE"), user_dirs);
+    if (!repo) return STATUS_CMD_ERROR;
+    git_head_t head = git_read_head(*repo);
+
+    maybe_t<git_changes_t> changes;
+    bool need_changes = false;
+    for (const wcstring &field : fields) need_changes = need_changes || is_changes_field(field);
+    if (need_changes && !repo->work_tree.empty()) {
+        // Outside of interactive sessions nobody would see the prompt being repainted.
+        if (opts.background && is_interactive_session()) {
+            changes = background_changes(*repo, head, count_untracked);
+        } else {
+            changes = git_read_changes(*repo, head, count_untracked);
+        }
+    }
+
+    bool print_names = argc == optind;
+    for (const wcstring &field : fields) {
+        wcstring value = field_value(field, *repo, head, changes);
+        if (print_names) {
+            if (value.empty()) continue;
+            streams.out.append(field + L" " + value + L"\n");
+        } else {
+            streams.out.append(value + L"\n");
+        }
+    }
+    return STATUS_CMD_OK;
+}
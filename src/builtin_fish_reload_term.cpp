@@ -0,0 +1,53 @@
+// Implementation of the fish_reload_term builtin, which redetects the terminal.
+#include "config.h"  // IWYU pragma: keep
+
+#include "builtin_fish_reload_term.h"
+
+#include "builtin.h"
+#include "common.h"
+#include "env_dispatch.h"
+#include "fallback.h"  // IWYU pragma: keep
+#include "io.h"
+#include "parser.h"
+#include "wgetopt.h"
+#include "wutil.h"  // IWYU pragma: keep
+
+static const wchar_t *const short_options = L":h";
+static const struct woption long_options[] = {{L"help", no_argument, nullptr, 'h'},
+                                              {nullptr, 0, nullptr, 0}};
+
+/// The fish_reload_term builtin.
+maybe_t<int> builtin_fish_reload_term(parser_t &parser, io_streams_t &streams, wchar_t **argv) {
+    const wchar_t *cmd = argv[0];
+    int argc = builtin_count_args(argv);
+
+    int opt;
+    wgetopter_t w;
+    while ((opt = w.wgetopt_long(argc, argv, short_options, long_options, nullptr)) != -1) {
+        switch (opt) {
+            case 'h': {
+                builtin_print_help(parser, streams, cmd);
+                return STATUS_CMD_OK;
+            }
+            case ':': {
+                builtin_missing_argument(parser, streams, cmd, argv[w.woptind - 1]);
+                return STATUS_INVALID_ARGS;
+            }
+            case '?': {
+                builtin_unknown_option(parser, streams, cmd, argv[w.woptind - 1]);
+                return STATUS_INVALID_ARGS;
+            }
+            default: {
+                DIE("unexpected retval from wgetopt_long");
+            }
+        }
+    }
+
+    if (w.woptind != argc) {
+        streams.err.append_format(BUILTIN_ERR_ARG_COUNT1, cmd, 0, argc - w.woptind);
+        return STATUS_INVALID_ARGS;
+    }
+
+    env_dispatch_reload_term(parser.vars());
+    return STATUS_CMD_OK;
+}
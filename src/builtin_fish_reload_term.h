@@ -0,0 +1,11 @@
+// Prototypes for executing builtin_fish_reload_term function.
+#ifndef FISH_BUILTIN_FISH_RELOAD_TERM_H
+#define FISH_BUILTIN_FISH_RELOAD_TERM_H
+
+#include "maybe.h"
+
+class parser_t;
+struct io_streams_t;
+
+maybe_t<int> builtin_fish_reload_term(parser_t &parser, io_streams_t &streams, wchar_t **argv);
+#endif
@@ -0,0 +1,275 @@
+// Implementation of the fish_spell_correct builtin.
+#include "config.h"  // IWYU pragma: keep
+
+#include "builtin_fish_spell_correct.h"
+
+#include <dirent.h>
+#include <sys/stat.h>
+#include <unistd.h>
+
+#include <algorithm>
+#include <cwchar>
+#include <string>
+#include <tuple>
+#include <utility>
+#include <vector>
+
+#include "ast.h"
+#include "builtin.h"
+#include "common.h"
+#include "env.h"
+#include "expand.h"
+#include "fallback.h"  // IWYU pragma: keep
+#include "function.h"
+#include "io.h"
+#include "parser.h"
+#include "path.h"
+#include "wcstringutil.h"
+#include "wgetopt.h"
+#include "wutil.h"  // IWYU pragma: keep
+
+/// Characters which mean a word is expanded, so what was typed is not what was run.
+static const wchar_t *const expansion_chars = L"$*?{}()%";
+
+namespace {
+/// Finds the closest of the names offered to a mistyped name.
+class closest_name_t {
+    const wcstring typed_;
+    // Short names may only have one typo, or nearly any other name would match.
+    const size_t max_distance_;
+    size_t best_distance_{0};
+
+   public:
+    /// The closest name so far.
+    maybe_t<wcstring> best{};
+
+    explicit closest_name_t(wcstring typed)
+        : typed_(std::move(typed)), max_distance_(typed_.size() < 5 ? 1 : 2) {}
+
+    /// Consider \p name, if \p is_valid accepts it. That is only asked for close names.
+    template <typename Func>
+    void offer(const wcstring &name, const Func &is_valid) {
+        auto distance = string_edit_distance(typed_, name, max_distance_);
+        if (!distance || *distance == 0) return;
+        if (best) {
+            // Prefer names with the same letters in another order, since swapping letters is
+            // the most common typo, then ones with the same first letter, and then the first in
+            // order, so the result does not depend on the order of the directories.
+            auto rank = [&](const wcstring &n, size_t d) {
+                bool swapped = n.size() == typed_.size() &&
+                               std::is_permutation(n.begin(), n.end(), typed_.begin());
+                return std::make_tuple(d, !swapped, n.front() != typed_.front(), n);
+            };
+            if (rank(name, *distance) >= rank(*best, best_distance_)) return;
+        }
+        if (!is_valid(name)) return;
+        best = name;
+        best_distance_ = *distance;
+    }
+
+    void offer(const wcstring &name) {
+        offer(name, [](const wcstring &) { return true; });
+    }
+};
+}  // namespace
+
+/// \return whether \p path is a directory.
+static bool is_directory(const wcstring &path) {
+    struct stat buf;
+    return !wstat(path, &buf) && S_ISDIR(buf.st_mode);
+}
+
+/// \return the command closest to \p cmd, which does not exist.
+static maybe_t<wcstring> correct_command(const wcstring &cmd, parser_t &parser) {
+    closest_name_t closest(cmd);
+    for (const wcstring &name : builtin_get_names()) closest.offer(name);
+    for (const wcstring &name : function_get_names(false)) closest.offer(name);
+
+    if (auto path_var = parser.vars().get(L"PATH")) {
+        for (wcstring dir : path_var->as_list()) {
+            if (dir.empty()) continue;
+            if (dir.back() != L'/') dir.push_back(L'/');
+            DIR *dirp = wopendir(dir);
+            if (!dirp) continue;
+            wcstring name;
+            while (wreaddir(dirp, name)) {
+                closest.offer(name, [&](const wcstring &n) {
+                    return !waccess(dir + n, X_OK) && !is_directory(dir + n);
+                });
+            }
+            closedir(dirp);
+        }
+    }
+    return closest.best;
+}
+
+/// \return the subdirectory of \p dir closest to \p name, which does not exist.
+static maybe_t<wcstring> correct_subdirectory(const wcstring &dir, const wcstring &name) {
+    closest_name_t closest(name);
+    DIR *dirp = wopendir(dir);
+    if (!dirp) return none();
+    wcstring entry;
+    bool entry_is_dir;
+    while (wreaddir_resolving(dirp, dir, entry, &entry_is_dir)) {
+        // Hidden directories are only suggested for hidden names.
+        if (!entry_is_dir || entry == L"." || entry == L"..") continue;
+        if (entry.front() == L'.' && name.front() != L'.') continue;
+        closest.offer(entry);
+    }
+    closedir(dirp);
+    return closest.best;
+}
+
+/// \return \p typed, the argument to cd, with each directory which does not exist replaced by the
+/// closest one, or none() if there is nothing to correct.
+static maybe_t<wcstring> correct_directory(const wcstring &typed, const environment_t &vars) {
+    wcstring_list_t components = split_string(typed, L'/');
+    wcstring dir;
+    size_t first = 0;
+    if (typed.front() == L'/') {
+        dir = L"/";
+        first = 1;
+    } else if (typed.front() == L'~') {
+        dir = components.front();
+        expand_tilde(dir, vars);
+        first = 1;
+    } else {
+        // Directories in $CDPATH are not corrected, only those relative to $PWD.
+        dir = vars.get_pwd_slash();
+    }
+
+    bool corrected = false;
+    for (size_t i = first; i < components.size(); i++) {
+        wcstring &component = components.at(i);
+        if (!dir.empty() && dir.back() != L'/') dir.push_back(L'/');
+        if (!component.empty() && component != L"." && component != L".." &&
+            !is_directory(dir + component)) {
+            auto closest = correct_subdirectory(dir, component);
+            if (!closest) return none();
+            component = *closest;
+            corrected = true;
+        }
+        dir.append(component);
+    }
+    if (!corrected) return none();
+    return join_strings(components, L'/');
+}
+
+/// \return the corrected command line \p src, or none() if nothing could be corrected.
+static maybe_t<wcstring> correct_commandline(const wcstring &src, parser_t &parser) {
+    using namespace ast;
+    auto ast = ast_t::parse(src);
+    if (ast.errored()) return none();
+
+    const environment_t &vars = parser.vars();
+    std::vector<std::pair<source_range_t, wcstring>> replacements;
+    for (const node_t &node : ast) {
+        const auto *stmt = node.try_as<decorated_statement_t>();
+        if (!stmt) continue;
+        wcstring raw_cmd = stmt->command.source(src);
+        wcstring cmd;
+        if (raw_cmd.empty() || raw_cmd.front() == L'~' ||
+            raw_cmd.find_first_of(expansion_chars) != wcstring::npos ||
+            !unescape_string(raw_cmd, &cmd, UNESCAPE_DEFAULT)) {
+            continue;
+        }
+
+        if (cmd == L"cd") {
+            const argument_t *arg = nullptr;
+            size_t arg_count = 0;
+            for (const argument_or_redirection_t &v : stmt->args_or_redirs) {
+                if (v.is_argument() && arg_count++ == 0) arg = &v.argument();
+            }
+            if (arg_count != 1) continue;
+            wcstring raw_dir = arg->source(src);
+            wcstring dir;
+            if (raw_dir.find_first_of(expansion_chars) != wcstring::npos ||
+                !unescape_string(raw_dir, &dir, UNESCAPE_DEFAULT) || dir.empty() ||
+                dir.front() == L'-' || path_get_cdpath(dir, vars.get_pwd_slash(), vars)) {
+                continue;
+            }
+            if (auto corrected = correct_directory(dir, vars)) {
+                escape_flags_t flags = ESCAPE_ALL;
+                if (corrected->front() == L'~') flags |= ESCAPE_NO_TILDE;
+                replacements.emplace_back(arg->source_range(), escape_string(*corrected, flags));
+            }
+        } else if (cmd.size() > 1 && cmd.find(L'/') == wcstring::npos && !builtin_exists(cmd) &&
+                   !function_exists(cmd, parser) && !path_get_path(cmd, nullptr, vars)) {
+            if (auto corrected = correct_command(cmd, parser)) {
+                replacements.emplace_back(stmt->command.source_range(),
+                                          escape_string(*corrected, ESCAPE_ALL));
+            }
+        }
+    }
+    if (replacements.empty()) return none();
+
+    // Replace from the end, so the earlier ranges stay valid.
+    wcstring result = src;
+    for (auto it = replacements.rbegin(); it != replacements.rend(); ++it) {
+        result.replace(it->first.start, it->first.length, it->second);
+    }
+    return result;
+}
+
+struct fish_spell_correct_cmd_opts_t {
+    bool print_help = false;
+};
+
+static const wchar_t *const short_options = L"+:h";
+static const struct woption long_options[] = {{L"help", no_argument, nullptr, 'h'},
+                                              {nullptr, 0, nullptr, 0}};
+
+static int parse_cmd_opts(fish_spell_correct_cmd_opts_t &opts, int *optind, int argc,
+                          wchar_t **argv, parser_t &parser, io_streams_t &streams) {
+    const wchar_t *cmd = argv[0];
+    int opt;
+    wgetopter_t w;
+    while ((opt = w.wgetopt_long(argc, argv, short_options, long_options, nullptr)) != -1) {
+        switch (opt) {
+            case 'h': {
+                opts.print_help = true;
+                break;
+            }
+            case ':': {
+                builtin_missing_argument(parser, streams, cmd, argv[w.woptind - 1]);
+                return STATUS_INVALID_ARGS;
+            }
+            case '?': {
+                builtin_unknown_option(parser, streams, cmd, argv[w.woptind - 1]);
+                return STATUS_INVALID_ARGS;
+            }
+            default: {
+                DIE("unexpected retval from wgetopt_long");
+            }
+        }
+    }
+
+    *optind = w.woptind;
+    return STATUS_CMD_OK;
+}
+
+/// Print a command line with mistyped commands and cd targets replaced by the closest match.
+maybe_t<int> builtin_fish_spell_correct(parser_t &parser, io_streams_t &streams, wchar_t **argv) {
+    const wchar_t *cmd = argv[0];
+    fish_spell_correct_cmd_opts_t opts;
+    int argc = builtin_count_args(argv);
+    int optind;
+
+    int retval = parse_cmd_opts(opts, &optind, argc, argv, parser, streams);
+    if (retval != STATUS_CMD_OK) return retval;
+
+    if (opts.print_help) {
+        builtin_print_help(parser, streams, cmd);
+        return STATUS_CMD_OK;
+    }
+
+    if (argc - optind != 1) {
+        streams.err.append_format(BUILTIN_ERR_ARG_COUNT1, cmd, 1, argc - optind);
+        return STATUS_INVALID_ARGS;
+    }
+
+    auto corrected = correct_commandline(argv[optind], parser);
+    if (!corrected) return STATUS_CMD_ERROR;
+    streams.out.append(*corrected + L"\n");
+    return STATUS_CMD_OK;
+}
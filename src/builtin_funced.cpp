@@ -0,0 +1,453 @@
+// Implementation of the funced builtin.
+#include "config.h"  // IWYU pragma: keep
+
+#include "builtin_funced.h"
+
+#include <fcntl.h>
+#include <unistd.h>
+
+#include <algorithm>
+#include <cerrno>
+#include <cstdlib>
+#include <cstring>
+#include <cwctype>
+#include <string>
+#include <vector>
+
+#include "ast.h"
+#include "builtin.h"
+#include "builtin_funcsave.h"
+#include "color.h"
+#include "common.h"
+#include "env.h"
+#include "fallback.h"  // IWYU pragma: keep
+#include "fds.h"
+#include "fish_indent_common.h"
+#include "function.h"
+#include "io.h"
+#include "output.h"
+#include "parse_constants.h"
+#include "parse_util.h"
+#include "parser.h"
+#include "path.h"
+#include "reader.h"
+#include "tokenizer.h"
+#include "wcstringutil.h"
+#include "wgetopt.h"
+#include "wutil.h"  // IWYU pragma: keep
+
+/// The number of unchanged lines shown around each change in the diff.
+static constexpr size_t k_diff_context = 3;
+
+namespace {
+/// A line of a diff. The kind is ' ' for a line in both texts, '-' for a line only in the old one
+/// and '+' for a line only in the new one.
+struct diff_line_t {
+    wchar_t kind;
+    wcstring text;
+};
+
+/// The file the function is edited in by an external editor. The file and its directory are
+/// removed again when this goes out of scope.
+class edit_file_t {
+    std::string dir_;
+
+   public:
+    wcstring path;
+
+    /// Create the file for function \p name with the contents \p text, in a new directory, so it
+    /// can be given a name which makes editors recognize it as fish code.
+    /// \return 0 on success, or the errno of the failure.
+    int create(const wcstring &name, const wcstring &text, const environment_t &vars) {
+        auto tmpdir_var = vars.get(L"TMPDIR");
+        wcstring tmpdir = tmpdir_var && !tmpdir_var->empty() ? tmpdir_var->as_string() : L"/tmp";
+        std::string dir = wcs2string(tmpdir) + "/fish.XXXXXX";
+        if (!mkdtemp(&dir[0])) return errno;
+        dir_ = dir;
+
+        wcstring file = str2wcstring(dir_) + L"/" + name + L".fish";
+        autoclose_fd_t fd{wopen_cloexec(file, O_WRONLY | O_CREAT | O_EXCL, 0600)};
+        if (!fd.valid()) return errno;
+        path = file;
+        std::string contents = wcs2string(text);
+        if (write_loop(fd.fd(), contents.data(), contents.size()) < 0) return errno;
+        return 0;
+    }
+
+    /// Read the file back into \p text. \return whether that worked.
+    bool read(wcstring *text) const {
+        autoclose_fd_t fd{wopen_cloexec(path, O_RDONLY)};
+        if (!fd.valid()) return false;
+        std::string contents;
+        char buff[4096];
+        ssize_t amt;
+        while ((amt = read_loop(fd.fd(), buff, sizeof buff)) > 0) contents.append(buff, amt);
+        *text = str2wcstring(contents);
+        return amt == 0;
+    }
+
+    ~edit_file_t() {
+        if (!path.empty()) wunlink(path);
+        if (!dir_.empty()) rmdir(dir_.c_str());
+    }
+};
+}  // namespace
+
+static wcstring_list_t split_lines(const wcstring &text) {
+    wcstring_list_t lines = split_string(text, L'\n');
+    if (!lines.empty() && lines.back().empty()) lines.pop_back();
+    return lines;
+}
+
+/// \return the lines of \p before and \p after, marked as described for diff_line_t, keeping their
+/// longest common subsequence.
+static std::vector<diff_line_t> diff_lines(const wcstring_list_t &before,
+                                           const wcstring_list_t &after) {
+    // lcs[i][j] is the length of the longest common subsequence of before[i..] and after[j..].
+    std::vector<std::vector<size_t>> lcs(before.size() + 1,
+                                         std::vector<size_t>(after.size() + 1, 0));
+    for (size_t i = before.size(); i-- > 0;) {
+        for (size_t j = after.size(); j-- > 0;) {
+            lcs[i][j] = before[i] == after[j] ? lcs[i + 1][j + 1] + 1
+                                              : std::max(lcs[i + 1][j], lcs[i][j + 1]);
+        }
+    }
+
+    std::vector<diff_line_t> result;
+    size_t i = 0, j = 0;
+    while (i < before.size() || j < after.size()) {
+        if (i < before.size() && j < after.size() && before[i] == after[j]) {
+            result.push_back({L' ', before[i]});
+            i++;
+            j++;
+        } else if (j == after.size() || (i < before.size() && lcs[i + 1][j] >= lcs[i][j + 1])) {
+            result.push_back({L'-', before[i++]});
+        } else {
+            result.push_back({L'+', after[j++]});
+        }
+    }
+    return result;
+}
+
+/// \return the changes from \p before to \p after as a unified diff of \p file, colored if
+/// \p colorize is set.
+static wcstring unified_diff(const wcstring &before, const wcstring &after, const wcstring &file,
+                             bool colorize) {
+    std::vector<diff_line_t> lines = diff_lines(split_lines(before), split_lines(after));
+    size_t count = lines.size();
+    // The number of lines of each text before each line of the diff.
+    std::vector<size_t> old_before(count + 1, 0), new_before(count + 1, 0);
+    for (size_t k = 0; k < count; k++) {
+        old_before[k + 1] = old_before[k] + (lines[k].kind != L'+');
+        new_before[k + 1] = new_before[k] + (lines[k].kind != L'-');
+    }
+
+    outputter_t outp;
+    auto write_line = [&](const wchar_t *color, const wcstring &line) {
+        if (colorize && color) outp.set_color(rgb_color_t(color), rgb_color_t::normal());
+        outp.writestr(line);
+        if (colorize && color) outp.set_color(rgb_color_t::normal(), rgb_color_t::normal());
+        outp.writestr(L"\n");
+    };
+    write_line(nullptr, L"--- a/" + file);
+    write_line(nullptr, L"+++ b/" + file);
+
+    size_t pos = 0;
+    while (pos < count) {
+        while (pos < count && lines[pos].kind == L' ') pos++;
+        if (pos == count) break;
+
+        // Changes which are close together share a hunk.
+        size_t start = pos >= k_diff_context ? pos - k_diff_context : 0;
+        size_t end = pos;
+        for (;;) {
+            while (end < count && lines[end].kind != L' ') end++;
+            size_t next = end;
+            while (next < count && lines[next].kind == L' ') next++;
+            if (next == count || next - end > 2 * k_diff_context) break;
+            end = next;
+        }
+        end = std::min(count, end + k_diff_context);
+
+        // An empty range is given as the line before it.
+        size_t old_count = old_before[end] - old_before[start];
+        size_t new_count = new_before[end] - new_before[start];
+        size_t old_start = old_before[start] + (old_count ? 1 : 0);
+        size_t new_start = new_before[start] + (new_count ? 1 : 0);
+        write_line(L"cyan", format_string(L"@@ -%lu,%lu +%lu,%lu @@", old_start, old_count,
+                                          new_start, new_count));
+        for (size_t k = start; k < end; k++) {
+            const wchar_t *color = nullptr;
+            if (lines[k].kind == L'-') color = L"red";
+            if (lines[k].kind == L'+') color = L"green";
+            write_line(color, lines[k].kind + lines[k].text);
+        }
+        pos = end;
+    }
+    return str2wcstring(outp.contents());
+}
+
+/// Read a line with the reader, like `read --shell` does. \return none() if it was cancelled.
+static maybe_t<wcstring> read_line(parser_t &parser, const wcstring &prompt, const wcstring &text,
+                                   bool shell, int in) {
+    reader_config_t conf;
+    conf.complete_ok = shell;
+    conf.highlight_ok = shell;
+    conf.syntax_check_ok = shell;
+    conf.exit_on_interrupt = true;
+    conf.left_prompt_cmd = prompt;
+    conf.in = in;
+
+    // Don't keep history.
+    reader_push(parser, wcstring{}, std::move(conf));
+    reader_set_buffer(text);
+    scoped_push<bool> interactive{&parser.libdata().is_interactive, true};
+    maybe_t<wcstring> result = reader_readline(0);
+    interactive.restore();
+    reader_pop();
+    return result;
+}
+
+/// \return whether \p text defines function \p name without syntax errors. Otherwise the errors
+/// are appended to \p out_errors, described for \p file.
+static bool validate_definition(const wcstring &text, const wcstring &name, const wcstring &file,
+                                wcstring *out_errors) {
+    using namespace ast;
+    parse_error_list_t errors;
+    auto ast = ast_t::parse(text, parse_flag_none, &errors);
+    if (ast.errored() || parse_util_detect_errors(ast, text, &errors)) {
+        for (const parse_error_t &err : errors) {
+            size_t line = 1 + std::count(text.begin(),
+                                         text.begin() + std::min(err.source_start, text.size()),
+                                         L'\n');
+            wcstring prefix = format_string(_(L"%ls (line %lu): "), file.c_str(), line);
+            out_errors->append(err.describe_with_prefix(text, prefix, false, false));
+            out_errors->push_back(L'\n');
+        }
+        return false;
+    }
+
+    for (const node_t &node : ast) {
+        const auto *header = node.try_as<function_header_t>();
+        if (!header) continue;
+        const argument_t *arg = &header->first_arg;
+        if (arg->source(text) == L"--" && !header->args.empty()) arg = header->args.at(0);
+        wcstring defined;
+        if (unescape_string(arg->source(text), &defined, UNESCAPE_DEFAULT) && defined == name) {
+            return true;
+        }
+    }
+    out_errors->append(
+        format_string(_(L"%ls: The function '%ls' is not defined\n"), file.c_str(), name.c_str()));
+    return false;
+}
+
+struct funced_cmd_opts_t {
+    bool print_help = false;
+    bool interactive = false;
+    bool save = false;
+    wcstring editor;
+};
+
+static const wchar_t *const short_options = L":e:his";
+static const struct woption long_options[] = {{L"editor", required_argument, nullptr, 'e'},
+                                              {L"help", no_argument, nullptr, 'h'},
+                                              {L"interactive", no_argument, nullptr, 'i'},
+                                              {L"save", no_argument, nullptr, 's'},
+                                              {nullptr, 0, nullptr, 0}};
+
+static int parse_cmd_opts(funced_cmd_opts_t &opts, int *optind, int argc, wchar_t **argv,
+                          parser_t &parser, io_streams_t &streams) {
+    const wchar_t *cmd = argv[0];
+    int opt;
+    wgetopter_t w;
+    while ((opt = w.wgetopt_long(argc, argv, short_options, long_options, nullptr)) != -1) {
+        switch (opt) {
+            case 'e': {
+                opts.editor = w.woptarg;
+                break;
+            }
+            case 'h': {
+                opts.print_help = true;
+                break;
+            }
+            case 'i': {
+                opts.interactive = true;
+                break;
+            }
+            case 's': {
+                opts.save = true;
+                break;
+            }
+            case ':': {
+                builtin_missing_argument(parser, streams, cmd, argv[w.woptind - 1]);
+                return STATUS_INVALID_ARGS;
+            }
+            case '?': {
+                builtin_unknown_option(parser, streams, cmd, argv[w.woptind - 1]);
+                return STATUS_INVALID_ARGS;
+            }
+            default: {
+                DIE("unexpected retval from wgetopt_long");
+            }
+        }
+    }
+
+    *optind = w.woptind;
+    return STATUS_CMD_OK;
+}
+
+/// \return the command line of the editor to run, or an empty string to edit in the shell.
+static wcstring get_editor(const funced_cmd_opts_t &opts, parser_t &parser,
+                           io_streams_t &streams, const wchar_t *cmd) {
+    if (opts.interactive) return {};
+    wcstring editor = opts.editor;
+    // $VISUAL is checked first, since $EDITOR could be a line editor like ed.
+    for (const wchar_t *var_name : {L"VISUAL", L"EDITOR"}) {
+        if (!editor.empty()) break;
+        if (auto var = parser.vars().get(var_name)) editor = var->as_string();
+    }
+    if (editor.empty() || editor == L"fish") return {};
+
+    // The editor may be given with arguments, only the command has to exist.
+    tokenizer_t tok(editor.c_str(), 0);
+    wcstring editor_cmd;
+    if (auto token = tok.next()) {
+        if (!unescape_string(tok.text_of(*token), &editor_cmd, UNESCAPE_DEFAULT)) {
+            editor_cmd.clear();
+        }
+    }
+    if (editor_cmd.empty() ||
+        (!builtin_exists(editor_cmd) && !path_get_path(editor_cmd, nullptr, parser.vars()))) {
+        streams.err.append_format(_(L"%ls: The value for $EDITOR '%ls' could not be used because "
+                                    L"the command '%ls' could not be found\n"),
+                                  cmd, editor.c_str(), editor_cmd.c_str());
+        return {};
+    }
+    return editor;
+}
+
+/// Ask whether to edit the function again after it could not be loaded. \return the answer.
+static bool ask_edit_again(parser_t &parser, io_streams_t &streams) {
+    if (!isatty(streams.stdin_fd)) return false;
+    wcstring prompt = L"echo " + escape_string(_(L"Edit the file again? [Y/n] "), ESCAPE_ALL);
+    maybe_t<wcstring> response = read_line(parser, prompt, wcstring{}, false, streams.stdin_fd);
+    if (!response) return false;
+    wcstring answer = trim(*response);
+    std::transform(answer.begin(), answer.end(), answer.begin(), towlower);
+    if (answer == L"n" || answer == L"no") return false;
+    if (!answer.empty() && answer != L"y" && answer != L"yes") {
+        streams.err.append_format(_(L"I don't understand '%ls', assuming 'Yes'\n"),
+                                  response->c_str());
+    }
+    return true;
+}
+
+/// Edit a function, in an editor or in the shell, and load it once it is free of syntax errors.
+maybe_t<int> builtin_funced(parser_t &parser, io_streams_t &streams, wchar_t **argv) {
+    const wchar_t *cmd = argv[0];
+    funced_cmd_opts_t opts;
+    int argc = builtin_count_args(argv);
+    int optind;
+
+    int retval = parse_cmd_opts(opts, &optind, argc, argv, parser, streams);
+    if (retval != STATUS_CMD_OK) return retval;
+
+    if (opts.print_help) {
+        builtin_print_help(parser, streams, cmd);
+        return STATUS_CMD_OK;
+    }
+
+    if (argc - optind != 1) {
+        streams.err.append_format(BUILTIN_ERR_ARG_COUNT1, cmd, 1, argc - optind);
+        return STATUS_INVALID_ARGS;
+    }
+
+    const wcstring name = argv[optind];
+    const wcstring file_name = name + L".fish";
+    const bool exists = function_exists(name, parser);
+    wcstring before;
+    if (exists) {
+        before = functions_def(name);
+    } else {
+        const wchar_t *separator = name.front() == L'-' ? L"-- " : L"";
+        before = format_string(L"function %ls%ls\n\nend\n", separator,
+                               escape_string(name, ESCAPE_ALL).c_str());
+    }
+
+    wcstring editor = get_editor(opts, parser, streams, cmd);
+    edit_file_t edit_file;
+    if (!editor.empty()) {
+        if (int err = edit_file.create(name, before, parser.vars())) {
+            streams.err.append_format(_(L"%ls: Could not create a temporary file: %s\n"), cmd,
+                                      std::strerror(err));
+            return STATUS_CMD_ERROR;
+        }
+    } else if (!isatty(streams.stdin_fd)) {
+        streams.err.append_format(_(L"%ls: Editing in the shell requires a terminal\n"), cmd);
+        return STATUS_CMD_ERROR;
+    }
+
+    // In the shell, the definition is edited unindented, like a command line.
+    indent_options_t unindented;
+    unindented.do_indent = false;
+    wcstring text = before;
+    if (editor.empty()) {
+        text = prettify(before, unindented);
+        while (!text.empty() && text.back() == L'\n') text.pop_back();
+        before = prettify(before, indent_options_t{});
+    }
+    for (;;) {
+        if (editor.empty()) {
+            wcstring prompt = format_string(L"printf '%%s%%s%%s> ' (set_color green) %ls "
+                                            L"(set_color normal)",
+                                            escape_string(name, ESCAPE_ALL).c_str());
+            maybe_t<wcstring> line = read_line(parser, prompt, text, true, streams.stdin_fd);
+            // Clearing the definition cancels editing.
+            if (!line || trim(*line).empty()) {
+                streams.err.append(_(L"Cancelled function editing\n"));
+                return STATUS_CMD_ERROR;
+            }
+            text = prettify(*line, indent_options_t{});
+        } else {
+            auto res = parser.eval(editor + L" " + escape_string(edit_file.path, ESCAPE_ALL),
+                                   *streams.io_chain, streams.job_group);
+            if (res.status.status_value() != 0 || !edit_file.read(&text)) {
+                streams.err.append(_(L"Editing failed or was cancelled\n"));
+                return STATUS_CMD_ERROR;
+            }
+        }
+
+        if (text == before && exists) {
+            streams.err.append(_(L"Editor exited but the function was not modified\n"));
+            break;
+        }
+
+        wcstring errors;
+        bool loaded = validate_definition(text, name, file_name, &errors);
+        if (loaded) {
+            auto res = parser.eval(text, *streams.io_chain, streams.job_group);
+            loaded = res.status.status_value() == 0;
+        } else {
+            streams.err.append(errors);
+        }
+        if (loaded) {
+            if (text != before) {
+                bool colorize = !streams.out_is_redirected && isatty(STDOUT_FILENO);
+                streams.out.append(unified_diff(before, text, file_name, colorize));
+            }
+            break;
+        }
+
+        // In the shell, the text is simply edited again, until it is cancelled.
+        if (!editor.empty() && !ask_edit_again(parser, streams)) {
+            streams.err.append(_(L"Cancelled function editing\n"));
+            return STATUS_CMD_ERROR;
+        }
+    }
+
+    if (opts.save && !funcsave_function(parser, streams, cmd, name, wcstring{})) {
+        return STATUS_CMD_ERROR;
+    }
+    return STATUS_CMD_OK;
+}
@@ -0,0 +1,144 @@
+// Implementation of the funcsave builtin.
+#include "config.h"  // IWYU pragma: keep
+
+#include "builtin_funcsave.h"
+
+#include <unistd.h>
+
+#include <cerrno>
+#include <cstring>
+#include <string>
+
+#include "builtin.h"
+#include "common.h"
+#include "env.h"
+#include "fallback.h"  // IWYU pragma: keep
+#include "function.h"
+#include "io.h"
+#include "parse_util.h"
+#include "parser.h"
+#include "path.h"
+#include "wcstringutil.h"
+#include "wgetopt.h"
+#include "wutil.h"  // IWYU pragma: keep
+
+/// \return the directory function \p name should be saved to: the one it was autoloaded from, if
+/// that is one of the user's, or else the functions directory in the user's configuration.
+static wcstring default_function_dir(const wcstring &name, const environment_t &vars) {
+    if (const wchar_t *file = function_get_definition_file(name)) {
+        wcstring dir = wdirname(file);
+        auto data_dir = vars.get(L"__fish_data_dir");
+        auto function_path = vars.get(L"fish_function_path");
+        bool is_shipped =
+            data_dir && string_prefixes_string(data_dir->as_string() + L"/", dir + L"/");
+        if (function_is_autoloaded(name) && !is_shipped && function_path &&
+            contains(function_path->as_list(), dir) && waccess(dir, W_OK) == 0) {
+            return dir;
+        }
+    }
+    auto config_dir = vars.get(L"__fish_config_dir");
+    return (config_dir ? config_dir->as_string() : wcstring{}) + L"/functions";
+}
+
+bool funcsave_function(parser_t &parser, io_streams_t &streams, const wchar_t *cmd,
+                       const wcstring &name, const wcstring &dir) {
+    if (!function_exists(name, parser)) {
+        streams.err.append_format(_(L"%ls: Unknown function '%ls'\n"), cmd, name.c_str());
+        return false;
+    }
+
+    // Never save a file which could not be loaded again.
+    wcstring def = functions_def(name);
+    if (parse_util_detect_errors(def)) {
+        streams.err.append_format(_(L"%ls: The definition of '%ls' could not be parsed\n"), cmd,
+                                  name.c_str());
+        return false;
+    }
+
+    wcstring funcdir = dir.empty() ? default_function_dir(name, parser.vars()) : dir;
+    if (create_directory(funcdir) == -1) {
+        streams.err.append_format(_(L"%ls: Could not create configuration directory '%ls'\n"),
+                                  cmd, funcdir.c_str());
+        return false;
+    }
+
+    wcstring path = funcdir + L"/" + name + L".fish";
+    if (int err = write_file_atomically(wcs2string(path), wcs2string(def))) {
+        streams.err.append_format(_(L"%ls: Could not write '%ls': %s\n"), cmd, path.c_str(),
+                                  std::strerror(err));
+        return false;
+    }
+    return true;
+}
+
+struct funcsave_cmd_opts_t {
+    bool print_help = false;
+    wcstring directory;
+};
+
+static const wchar_t *const short_options = L":d:h";
+static const struct woption long_options[] = {{L"directory", required_argument, nullptr, 'd'},
+                                              {L"help", no_argument, nullptr, 'h'},
+                                              {nullptr, 0, nullptr, 0}};
+
+static int parse_cmd_opts(funcsave_cmd_opts_t &opts, int *optind, int argc, wchar_t **argv,
+                          parser_t &parser, io_streams_t &streams) {
+    const wchar_t *cmd = argv[0];
+    int opt;
+    wgetopter_t w;
+    while ((opt = w.wgetopt_long(argc, argv, short_options, long_options, nullptr)) != -1) {
+        switch (opt) {
+            case 'd': {
+                opts.directory = w.woptarg;
+                break;
+            }
+            case 'h': {
+                opts.print_help = true;
+                break;
+            }
+            case ':': {
+                builtin_missing_argument(parser, streams, cmd, argv[w.woptind - 1]);
+                return STATUS_INVALID_ARGS;
+            }
+            case '?': {
+                builtin_unknown_option(parser, streams, cmd, argv[w.woptind - 1]);
+                return STATUS_INVALID_ARGS;
+            }
+            default: {
+                DIE("unexpected retval from wgetopt_long");
+            }
+        }
+    }
+
+    *optind = w.woptind;
+    return STATUS_CMD_OK;
+}
+
+/// Save the current definition of functions, so they are autoloaded in future sessions.
+maybe_t<int> builtin_funcsave(parser_t &parser, io_streams_t &streams, wchar_t **argv) {
+    const wchar_t *cmd = argv[0];
+    funcsave_cmd_opts_t opts;
+    int argc = builtin_count_args(argv);
+    int optind;
+
+    int retval = parse_cmd_opts(opts, &optind, argc, argv, parser, streams);
+    if (retval != STATUS_CMD_OK) return retval;
+
+    if (opts.print_help) {
+        builtin_print_help(parser, streams, cmd);
+        return STATUS_CMD_OK;
+    }
+
+    if (optind == argc) {
+        streams.err.append_format(BUILTIN_ERR_MIN_ARG_COUNT1, cmd, 1, 0);
+        return STATUS_INVALID_ARGS;
+    }
+
+    retval = STATUS_CMD_OK;
+    for (int i = optind; i < argc; i++) {
+        if (!funcsave_function(parser, streams, cmd, argv[i], opts.directory)) {
+            retval = STATUS_CMD_ERROR;
+        }
+    }
+    return retval;
+}
@@ -0,0 +1,18 @@
+// Prototypes for executing builtin_funcsave function.
+#ifndef FISH_BUILTIN_FUNCSAVE_H
+#define FISH_BUILTIN_FUNCSAVE_H
+
+#include "common.h"
+#include "maybe.h"
+
+class parser_t;
+struct io_streams_t;
+
+/// Save the function \p name to a file in \p dir, or if that is empty, to the user's directory it
+/// was autoloaded from or the functions directory in the user's configuration. Errors are printed
+/// for \p cmd. \return whether it was saved.
+bool funcsave_function(parser_t &parser, io_streams_t &streams, const wchar_t *cmd,
+                       const wcstring &name, const wcstring &dir);
+
+maybe_t<int> builtin_funcsave(parser_t &parser, io_streams_t &streams, wchar_t **argv);
+#endif
@@ -21,6 +21,7 @@
 #include "io.h"
 #include "parser.h"
 #include "parser_keywords.h"
+#include "path.h"
 #include "proc.h"
 #include "signal.h"
 #include "wgetopt.h"
@@ -29,6 +30,7 @@
 struct function_cmd_opts_t {
     bool print_help = false;
     bool shadow_scope = true;
+    bool run_async = false;
     wcstring description;
     std::vector<event_description_t> events;
     wcstring_list_t named_arguments;
@@ -46,6 +48,9 @@ static const struct woption long_options[] = {
     {L"on-process-exit", required_argument, nullptr, 'p'},
     {L"on-variable", required_argument, nullptr, 'v'},
     {L"on-event", required_argument, nullptr, 'e'},
+    {L"on-interval", required_argument, nullptr, 2},
+    {L"on-file-change", required_argument, nullptr, 3},
+    {L"async", no_argument, nullptr, 4},
     {L"wraps", required_argument, nullptr, 'w'},
     {L"help", no_argument, nullptr, 'h'},
     {L"argument-names", required_argument, nullptr, 'a'},
@@ -98,6 +103,34 @@ static int parse_cmd_opts(function_cmd_opts_t &opts, int *optind,  //!OCLINT(hig
                 opts.events.push_back(event_description_t::generic(w.woptarg));
                 break;
             }
+            case 2: {
+                // Intervals are kept in milliseconds, which must leave at least one.
+                wchar_t *end = nullptr;
+                errno = 0;
+                double secs = fish_wcstod(w.woptarg, &end);
+                if (errno || end == w.woptarg || *end || !(secs >= 0.001 && secs <= 1e12)) {
+                    streams.err.append_format(_(L"%ls: Invalid interval '%ls'"), cmd, w.woptarg);
+                    return STATUS_INVALID_ARGS;
+                }
+                opts.events.push_back(
+                    event_description_t::interval(static_cast<uint64_t>(secs * 1000 + 0.5)));
+                break;
+            }
+            case 3: {
+                if (!*w.woptarg) {
+                    streams.err.append_format(_(L"%ls: Expected a path"), cmd);
+                    return STATUS_INVALID_ARGS;
+                }
+                // Relative paths are relative to where the function is defined.
+                const wcstring pwd = parser.vars().get_pwd_slash();
+                wcstring path = normalize_path(path_apply_working_directory(w.woptarg, pwd));
+                opts.events.push_back(event_description_t::file_change(std::move(path)));
+                break;
+            }
+            case 4: {
+                opts.run_async = true;
+                break;
+            }
             case 'j':
             case 'p': {
                 event_description_t e(event_type_t::any);
@@ -250,6 +283,7 @@ maybe_t<int> builtin_function(parser_t &parser, io_streams_t &streams,
     // We have what we need to actually define the function.
     auto props = std::make_shared<function_properties_t>();
     props->shadow_scope = opts.shadow_scope;
+    props->run_async = opts.run_async;
     props->named_arguments = std::move(opts.named_arguments);
     props->parsed_source = source;
     props->func_node = &func_node;
@@ -201,8 +201,7 @@ maybe_t<int> builtin_functions(parser_t &parser, io_streams_t &streams, wchar_t
     // Erase, desc, query, copy and list are mutually exclusive.
     bool describe = opts.description != nullptr;
     if (describe + opts.erase + opts.list + opts.query + opts.copy > 1) {
-        streams.err.append_format(BUILTIN_ERR_COMBO, cmd);
-        builtin_print_error_trailer(parser, streams.err, cmd);
+        builtin_print_error(parser, streams, cmd, format_string(BUILTIN_ERR_COMBO, cmd));
         return STATUS_INVALID_ARGS;
     }
 
@@ -215,15 +214,17 @@ maybe_t<int> builtin_functions(parser_t &parser, io_streams_t &streams, wchar_t
         wchar_t *func;
 
         if (argc - optind != 1) {
-            streams.err.append_format(_(L"%ls: Expected exactly one function name\n"), cmd);
-            builtin_print_error_trailer(parser, streams.err, cmd);
+            builtin_print_error(parser, streams, cmd,
+                                format_string(_(L"%ls: Expected exactly one function name\n"),
+                                              cmd));
             return STATUS_INVALID_ARGS;
         }
 
         func = argv[optind];
         if (!function_exists(func, parser)) {
-            streams.err.append_format(_(L"%ls: Function '%ls' does not exist\n"), cmd, func);
-            builtin_print_error_trailer(parser, streams.err, cmd);
+            builtin_print_error(parser, streams, cmd,
+                                format_string(_(L"%ls: Function '%ls' does not exist\n"), cmd,
+                                              func));
             return STATUS_CMD_ERROR;
         }
 
@@ -293,35 +294,35 @@ maybe_t<int> builtin_functions(parser_t &parser, io_streams_t &streams, wchar_t
         wcstring new_func;
 
         if (argc - optind != 2) {
-            streams.err.append_format(_(L"%ls: Expected exactly two names (current function name, "
-                                        L"and new function name)\n"),
-                                      cmd);
-            builtin_print_error_trailer(parser, streams.err, cmd);
+            builtin_print_error(parser, streams, cmd,
+                                format_string(_(L"%ls: Expected exactly two names (current "
+                                                L"function name, and new function name)\n"),
+                                              cmd));
             return STATUS_INVALID_ARGS;
         }
         current_func = argv[optind];
         new_func = argv[optind + 1];
 
         if (!function_exists(current_func, parser)) {
-            streams.err.append_format(_(L"%ls: Function '%ls' does not exist\n"), cmd,
-                                      current_func.c_str());
-            builtin_print_error_trailer(parser, streams.err, cmd);
+            builtin_print_error(parser, streams, cmd,
+                                format_string(_(L"%ls: Function '%ls' does not exist\n"), cmd,
+                                              current_func.c_str()));
             return STATUS_CMD_ERROR;
         }
 
         if (!valid_func_name(new_func) || parser_keywords_is_reserved(new_func)) {
-            streams.err.append_format(_(L"%ls: Illegal function name '%ls'\n"), cmd,
-                                      new_func.c_str());
-            builtin_print_error_trailer(parser, streams.err, cmd);
+            builtin_print_error(parser, streams, cmd,
+                                format_string(_(L"%ls: Illegal function name '%ls'\n"), cmd,
+                                              new_func.c_str()));
             return STATUS_INVALID_ARGS;
         }
 
         // Keep things simple: don't allow existing names to be copy targets.
         if (function_exists(new_func, parser)) {
-            streams.err.append_format(
-                _(L"%ls: Function '%ls' already exists. Cannot create copy '%ls'\n"), cmd,
-                new_func.c_str(), current_func.c_str());
-            builtin_print_error_trailer(parser, streams.err, cmd);
+            builtin_print_error(
+                parser, streams, cmd,
+                format_string(_(L"%ls: Function '%ls' already exists. Cannot create copy '%ls'\n"),
+                              cmd, new_func.c_str(), current_func.c_str()));
             return STATUS_CMD_ERROR;
         }
 
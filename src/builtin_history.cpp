@@ -15,6 +15,7 @@
 #include "fallback.h"  // IWYU pragma: keep
 #include "history.h"
 #include "io.h"
+#include "maybe.h"
 #include "parser.h"
 #include "reader.h"
 #include "wgetopt.h"
@@ -27,10 +28,19 @@ static const enum_map<hist_cmd_t> hist_enum_map[] = {
     {HIST_CLEAR, L"clear"}, {HIST_DELETE, L"delete"}, {HIST_MERGE, L"merge"},
     {HIST_SAVE, L"save"},   {HIST_SEARCH, L"search"}, {HIST_UNDEF, nullptr}};
 
+/// \return the history merge strategy with the given name, or none() if there is none.
+static maybe_t<history_merge_strategy_t> merge_strategy_from_name(const wcstring &name) {
+    if (name == L"interleave") return history_merge_strategy_t::interleave;
+    if (name == L"session") return history_merge_strategy_t::session;
+    if (name == L"last-writer-wins") return history_merge_strategy_t::last_writer_wins;
+    return none();
+}
+
 struct history_cmd_opts_t {
     hist_cmd_t hist_cmd = HIST_UNDEF;
     history_search_type_t search_type = static_cast<history_search_type_t>(-1);
     const wchar_t *show_time_format = nullptr;
+    const wchar_t *merge_strategy = nullptr;
     size_t max_items = SIZE_MAX;
     bool print_help = false;
     bool history_search_type_defined = false;
@@ -58,6 +68,7 @@ static const struct woption long_options[] = {{L"prefix", no_argument, nullptr,
                                               {L"clear", no_argument, nullptr, 4},
                                               {L"merge", no_argument, nullptr, 5},
                                               {L"reverse", no_argument, nullptr, 'R'},
+                                              {L"strategy", required_argument, nullptr, 6},
                                               {nullptr, 0, nullptr, 0}};
 
 /// Remember the history subcommand and disallow selecting more than one history subcommand.
@@ -131,6 +142,10 @@ static int parse_cmd_opts(history_cmd_opts_t &opts, int *optind,  //!OCLINT(high
                 }
                 break;
             }
+            case 6: {
+                opts.merge_strategy = w.woptarg;
+                break;
+            }
             case 'C': {
                 opts.case_sensitive = true;
                 break;
@@ -215,7 +230,7 @@ maybe_t<int> builtin_history(parser_t &parser, io_streams_t &streams, wchar_t **
     }
 
     // Use the default history if we have none (which happens if invoked non-interactively, e.g.
-    // from webconfig.py.
+    // from the web server of fish_config).
     std::shared_ptr<history_t> history = reader_get_history();
     if (!history) history = history_t::with_name(history_session_id(parser.vars()));
 
@@ -244,6 +259,13 @@ maybe_t<int> builtin_history(parser_t &parser, io_streams_t &streams, wchar_t **
         if (opts.hist_cmd == HIST_DELETE) opts.search_type = history_search_type_t::exact;
     }
 
+    if (opts.merge_strategy && opts.hist_cmd != HIST_MERGE) {
+        const wchar_t *subcmd_str = enum_to_str(opts.hist_cmd, hist_enum_map);
+        streams.err.append_format(_(L"%ls: you cannot use --strategy with the %ls command\n"), cmd,
+                                  subcmd_str);
+        return STATUS_INVALID_ARGS;
+    }
+
     int status = STATUS_CMD_OK;
     switch (opts.hist_cmd) {
         case HIST_SEARCH: {
@@ -292,7 +314,28 @@ maybe_t<int> builtin_history(parser_t &parser, io_streams_t &streams, wchar_t **
                 break;
             }
 
-            history->incorporate_external_changes();
+            // The strategy defaults to $fish_history_merge_strategy, and then to interleave.
+            wcstring strategy_name = L"interleave";
+            if (opts.merge_strategy) {
+                strategy_name = opts.merge_strategy;
+            } else if (auto var = parser.vars().get(L"fish_history_merge_strategy")) {
+                if (!var->empty()) strategy_name = var->as_string();
+            }
+            maybe_t<history_merge_strategy_t> strategy = merge_strategy_from_name(strategy_name);
+            if (!strategy) {
+                streams.err.append_format(_(L"%ls: Invalid merge strategy '%ls'\n"), cmd,
+                                          strategy_name.c_str());
+                status = STATUS_INVALID_ARGS;
+                break;
+            }
+
+            history_merge_result_t result = history->incorporate_external_changes(*strategy);
+            streams.out.append_format(_(L"Merged %lu items from other sessions\n"),
+                                      static_cast<unsigned long>(result.merged));
+            if (result.dropped > 0) {
+                streams.out.append_format(_(L"Dropped %lu older duplicates\n"),
+                                          static_cast<unsigned long>(result.dropped));
+            }
             break;
         }
         case HIST_SAVE: {
@@ -7,11 +7,13 @@
 #include <cstddef>
 
 #include "builtin.h"
+#include "cgroup.h"
 #include "common.h"
 #include "fallback.h"  // IWYU pragma: keep
 #include "io.h"
 #include "parser.h"
 #include "proc.h"
+#include "proc_info.h"
 #include "wgetopt.h"
 #include "wutil.h"  // IWYU pragma: keep
 
@@ -31,28 +33,71 @@ static int cpu_use(const job_t *j) {
     double u = 0;
 
     for (const process_ptr_t &p : j->processes) {
+        if (p->pid <= 0) continue;
         struct timeval t;
-        unsigned long jiffies;
         gettimeofday(&t, nullptr);
-        jiffies = proc_get_jiffies(p.get());
+        maybe_t<proc_info_t> info = proc_info_get(p->pid);
+        if (!info || !info->cpu_micros) continue;
+        uint64_t cpu_micros = *info->cpu_micros;
 
         double t1 = 1000000.0 * p->last_time.tv_sec + p->last_time.tv_usec;
         double t2 = 1000000.0 * t.tv_sec + t.tv_usec;
 
         // Check for a race condition that can cause negative CPU usage to be reported (#7066)
-        unsigned long cached_last_jiffies = p->last_jiffies;
-        if (t2 < t1 || jiffies < cached_last_jiffies) {
+        uint64_t cached_last_cpu_micros = p->last_cpu_micros;
+        if (t2 <= t1 || cpu_micros < cached_last_cpu_micros) {
             continue;
         }
 
-        // std::fwprintf( stderr, L"t1 %f t2 %f p1 %d p2 %d\n", t1, t2, jiffies, p->last_jiffies );
-        u += (static_cast<double>(jiffies - cached_last_jiffies)) / (t2 - t1);
+        u += (static_cast<double>(cpu_micros - cached_last_cpu_micros)) / (t2 - t1);
+    }
+    return u * 100;
+}
+
+/// Print a line for each external process of \p j with what the system knows about it: its pid,
+/// the name of the program it runs, the CPU time and memory it used and its working directory.
+static void print_process_details(const job_t *j, io_streams_t &streams) {
+    for (const process_ptr_t &p : j->processes) {
+        if (p->pid <= 0) continue;
+        maybe_t<proc_info_t> info = proc_info_get(p->pid);
+        streams.out.append_format(L"\t%d\t", p->pid);
+        streams.out.append(info && !info->name.empty() ? info->name : wcstring(p->argv0()));
+        if (info && info->cpu_micros) {
+            streams.out.append_format(L"\t%.2fs", *info->cpu_micros / 1E6);
+        } else {
+            streams.out.append(L"\t-");
+        }
+        if (info && info->rss_bytes) {
+            streams.out.append_format(L"\t%llu kB",
+                                      static_cast<unsigned long long>(*info->rss_bytes / 1024));
+        } else {
+            streams.out.append(L"\t-");
+        }
+        streams.out.push_back(L'\t');
+        streams.out.append(info && !info->cwd.empty() ? info->cwd : wcstring(L"-"));
+        streams.out.push_back(L'\n');
+    }
+
+    // The cgroup also accounts for processes that left the job, so it gets a line of its own.
+    maybe_t<cgroup_usage_t> usage = j->cgroup ? j->cgroup->usage() : none();
+    if (usage) {
+        streams.out.append_format(L"\t-\tcgroup\t%.2fs\t",
+                                  (usage->usr_micros + usage->sys_micros) / 1E6);
+        if (usage->memory_bytes) {
+            streams.out.append_format(
+                L"%llu kB", static_cast<unsigned long long>(*usage->memory_bytes / 1024));
+        } else {
+            streams.out.push_back(L'-');
+        }
+        streams.out.push_back(L'\t');
+        streams.out.append(j->cgroup->path());
+        streams.out.push_back(L'\n');
     }
-    return u * 1000000;
 }
 
 /// Print information about the specified job.
-static void builtin_jobs_print(const job_t *j, int mode, int header, io_streams_t &streams) {
+static void builtin_jobs_print(const job_t *j, int mode, int header, bool verbose,
+                               io_streams_t &streams) {
     int pgid = INVALID_PID;
     if (auto job_pgid = j->get_pgid()) {
         pgid = *job_pgid;
@@ -66,7 +111,7 @@ static void builtin_jobs_print(const job_t *j, int mode, int header, io_streams_
             if (header) {
                 // Print table header before first job.
                 streams.out.append(_(L"Job\tGroup\t"));
-                if (have_proc_stat()) {
+                if (proc_info_available()) {
                     streams.out.append(_(L"CPU\t"));
                 }
                 streams.out.append(_(L"State\tCommand\n"));
@@ -74,7 +119,7 @@ static void builtin_jobs_print(const job_t *j, int mode, int header, io_streams_
 
             streams.out.append_format(L"%d\t%d\t", j->job_id(), pgid);
 
-            if (have_proc_stat()) {
+            if (proc_info_available()) {
                 streams.out.append_format(L"%d%%\t", cpu_use(j));
             }
 
@@ -82,6 +127,7 @@ static void builtin_jobs_print(const job_t *j, int mode, int header, io_streams_
             streams.out.append(L"\t");
             streams.out.append(j->command_wcstr());
             streams.out.append(L"\n");
+            if (verbose) print_process_details(j, streams);
             break;
         }
         case JOBS_PRINT_GROUP: {
@@ -127,13 +173,15 @@ maybe_t<int> builtin_jobs(parser_t &parser, io_streams_t &streams, wchar_t **arg
     bool found = false;
     int mode = JOBS_DEFAULT;
     bool print_last = false;
+    bool verbose = false;
 
-    static const wchar_t *const short_options = L":cghlpq";
+    static const wchar_t *const short_options = L":cghlpqv";
     static const struct woption long_options[] = {
         {L"command", no_argument, nullptr, 'c'}, {L"group", no_argument, nullptr, 'g'},
         {L"help", no_argument, nullptr, 'h'},    {L"last", no_argument, nullptr, 'l'},
         {L"pid", no_argument, nullptr, 'p'},     {L"quiet", no_argument, nullptr, 'q'},
-        {L"query", no_argument, nullptr, 'q'},   {nullptr, 0, nullptr, 0}};
+        {L"query", no_argument, nullptr, 'q'},   {L"verbose", no_argument, nullptr, 'v'},
+        {nullptr, 0, nullptr, 0}};
 
     int opt;
     wgetopter_t w;
@@ -159,6 +207,10 @@ maybe_t<int> builtin_jobs(parser_t &parser, io_streams_t &streams, wchar_t **arg
                 print_last = true;
                 break;
             }
+            case 'v': {
+                verbose = true;
+                break;
+            }
             case 'h': {
                 builtin_print_help(parser, streams, cmd);
                 return STATUS_CMD_OK;
@@ -181,7 +233,7 @@ maybe_t<int> builtin_jobs(parser_t &parser, io_streams_t &streams, wchar_t **arg
         // Ignore unconstructed jobs, i.e. ourself.
         for (const auto &j : parser.jobs()) {
             if (j->is_visible()) {
-                builtin_jobs_print(j.get(), mode, !streams.out_is_redirected, streams);
+                builtin_jobs_print(j.get(), mode, !streams.out_is_redirected, verbose, streams);
                 return STATUS_CMD_OK;
             }
         }
@@ -213,7 +265,7 @@ maybe_t<int> builtin_jobs(parser_t &parser, io_streams_t &streams, wchar_t **arg
                 }
 
                 if (j && !j->is_completed() && j->is_constructed()) {
-                    builtin_jobs_print(j, mode, false, streams);
+                    builtin_jobs_print(j, mode, false, verbose, streams);
                     found = true;
                 } else {
                     if (mode != JOBS_PRINT_NOTHING) {
@@ -227,7 +279,7 @@ maybe_t<int> builtin_jobs(parser_t &parser, io_streams_t &streams, wchar_t **arg
                 // Ignore unconstructed jobs, i.e. ourself.
                 if (j->is_visible()) {
                     builtin_jobs_print(j.get(), mode, !found && !streams.out_is_redirected,
-                                       streams);
+                                       verbose, streams);
                     found = true;
                 }
             }
@@ -0,0 +1,301 @@
+// Implementation of the kill builtin.
+#include "config.h"  // IWYU pragma: keep
+
+#include "builtin_kill.h"
+
+#include <errno.h>
+#include <signal.h>
+
+#include <algorithm>
+#include <cstring>
+#include <cwchar>
+#include <vector>
+
+#include "builtin.h"
+#include "common.h"
+#include "fallback.h"  // IWYU pragma: keep
+#include "io.h"
+#include "parser.h"
+#include "proc.h"
+#include "signal.h"
+#include "wcstringutil.h"
+#include "wgetopt.h"
+#include "wutil.h"  // IWYU pragma: keep
+
+struct kill_cmd_opts_t {
+    bool print_help = false;
+    bool list = false;
+    bool table = false;
+    bool verbose = false;
+    int signal = SIGTERM;
+    bool have_signal = false;
+};
+
+static const wchar_t *const short_options = L":s:lLh";
+static const struct woption long_options[] = {{L"signal", required_argument, nullptr, 's'},
+                                              {L"list", no_argument, nullptr, 'l'},
+                                              {L"table", no_argument, nullptr, 'L'},
+                                              {L"verbose", no_argument, nullptr, 1},
+                                              {L"help", no_argument, nullptr, 'h'},
+                                              {nullptr, 0, nullptr, 0}};
+
+/// Parse a signal name or number, like "TERM", "SIGTERM", "RTMIN+1" or "15".
+/// \return the signal, or -1 if there is no such signal.
+static int parse_signal(const wchar_t *str) {
+    int sig = wcs2sig(str);
+    if (sig <= 0) return sig;
+    // wcs2sig accepts any number, but only known signals can be sent.
+    std::vector<int> signals = signal_list();
+    if (std::find(signals.begin(), signals.end(), sig) == signals.end()) return -1;
+    return sig;
+}
+
+/// \return the name of \p sig without the "SIG" prefix, like kill -l prints it.
+static const wchar_t *short_signal_name(int sig) {
+    const wchar_t *name = sig2wcs(sig);
+    return std::wcsncmp(name, L"SIG", 3) == 0 ? name + 3 : name;
+}
+
+static int parse_cmd_opts(kill_cmd_opts_t &opts, int *optind, int argc, wchar_t **argv,
+                          const wchar_t *cmd, parser_t &parser, io_streams_t &streams) {
+    int opt;
+    wgetopter_t w;
+    while ((opt = w.wgetopt_long(argc, argv, short_options, long_options, nullptr)) != -1) {
+        switch (opt) {
+            case 's': {
+                opts.signal = parse_signal(w.woptarg);
+                if (opts.signal < 0) {
+                    streams.err.append_format(_(L"%ls: Unknown signal '%ls'\n"), cmd, w.woptarg);
+                    return STATUS_INVALID_ARGS;
+                }
+                opts.have_signal = true;
+                break;
+            }
+            case 'l': {
+                opts.list = true;
+                break;
+            }
+            case 'L': {
+                opts.table = true;
+                break;
+            }
+            case 1: {
+                opts.verbose = true;
+                break;
+            }
+            case 'h': {
+                opts.print_help = true;
+                break;
+            }
+            case ':': {
+                builtin_missing_argument(parser, streams, cmd, argv[w.woptind - 1]);
+                return STATUS_INVALID_ARGS;
+            }
+            case '?': {
+                builtin_unknown_option(parser, streams, cmd, argv[w.woptind - 1]);
+                return STATUS_INVALID_ARGS;
+            }
+            default: {
+                DIE("unexpected retval from wgetopt_long");
+            }
+        }
+    }
+
+    *optind = w.woptind;
+    return STATUS_CMD_OK;
+}
+
+/// Print the signal names, or translate the given signals between names and numbers.
+static int list_signals(const wchar_t *cmd, int argc, wchar_t **argv, io_streams_t &streams) {
+    if (argc == 0) {
+        for (int sig : signal_list()) {
+            streams.out.append(short_signal_name(sig));
+            streams.out.push_back(L'\n');
+        }
+        return STATUS_CMD_OK;
+    }
+
+    int retval = STATUS_CMD_OK;
+    for (int i = 0; i < argc; i++) {
+        const wchar_t *arg = argv[i];
+        int num = fish_wcstoi(arg);
+        if (!errno) {
+            // Like in other shells, the exit status of a job killed by a signal gives the signal.
+            if (num > 128) num -= 128;
+            int sig = num > 0 ? parse_signal(to_string(num).c_str()) : -1;
+            if (sig > 0) {
+                streams.out.append_format(L"%ls\n", short_signal_name(sig));
+                continue;
+            }
+        } else {
+            int sig = parse_signal(arg);
+            if (sig > 0) {
+                streams.out.append_format(L"%d\n", sig);
+                continue;
+            }
+        }
+        streams.err.append_format(_(L"%ls: Unknown signal '%ls'\n"), cmd, arg);
+        retval = STATUS_CMD_ERROR;
+    }
+    return retval;
+}
+
+/// Print the signal numbers, names and descriptions.
+static void print_signal_table(io_streams_t &streams) {
+    for (int sig : signal_list()) {
+        streams.out.append_format(L"%2d %-12ls %ls\n", sig, short_signal_name(sig),
+                                  signal_get_desc(sig));
+    }
+}
+
+/// Find the job a job specifier like "%2" refers to. The part after the percent sign may be
+/// a job id, "%" or "+" for the most recent job, "-" for the one before it, a prefix of the
+/// job's command, or "?" and a part of it.
+/// \return the job, or null with an error printed.
+static job_t *find_job(const wchar_t *cmd, parser_t &parser, const wchar_t *spec,
+                       io_streams_t &streams) {
+    const wchar_t *what = spec + 1;
+    std::vector<job_t *> matches;
+    for (const auto &j : parser.jobs()) {
+        if (j->is_visible()) matches.push_back(j.get());
+    }
+
+    if (!*what || !std::wcscmp(what, L"%") || !std::wcscmp(what, L"+") ||
+        !std::wcscmp(what, L"-")) {
+        size_t idx = *what == L'-' ? 1 : 0;
+        if (idx < matches.size()) return matches.at(idx);
+        streams.err.append_format(_(L"%ls: No suitable job: %ls\n"), cmd, spec);
+        return nullptr;
+    }
+
+    int job_id = fish_wcstoi(what);
+    if (!errno) {
+        job_t *j = job_id > 0 ? parser.job_get(job_id) : nullptr;
+        if (j && j->is_visible()) return j;
+        streams.err.append_format(_(L"%ls: No suitable job: %ls\n"), cmd, spec);
+        return nullptr;
+    }
+
+    // Match on the command, either a prefix or with "?" anywhere.
+    bool anywhere = *what == L'?';
+    wcstring needle = anywhere ? what + 1 : what;
+    auto no_match = [&](const job_t *j) {
+        const wcstring &command = j->command();
+        return anywhere ? command.find(needle) == wcstring::npos
+                        : !string_prefixes_string(needle, command);
+    };
+    matches.erase(std::remove_if(matches.begin(), matches.end(), no_match), matches.end());
+    if (matches.size() == 1) return matches.front();
+    if (matches.empty()) {
+        streams.err.append_format(_(L"%ls: No suitable job: %ls\n"), cmd, spec);
+    } else {
+        streams.err.append_format(_(L"%ls: Ambiguous job specification: %ls\n"), cmd, spec);
+    }
+    return nullptr;
+}
+
+/// Send \p sig to all processes of \p j, via its process group if it has its own.
+static bool signal_job(job_t *j, int sig) {
+    if (!j->signal(sig)) return false;
+    // A stopped job can't act on a request to quit, so continue it, like other shells do.
+    if (j->is_stopped() && (sig == SIGTERM || sig == SIGHUP)) j->signal(SIGCONT);
+    return true;
+}
+
+/// Send a signal to processes or jobs.
+maybe_t<int> builtin_kill(parser_t &parser, io_streams_t &streams, wchar_t **argv) {
+    const wchar_t *cmd = argv[0];
+    kill_cmd_opts_t opts;
+    int argc = builtin_count_args(argv);
+
+    // The signal may be given as the first argument, like "-9" or "-HUP". Parse the options after
+    // it, treating it as the command name.
+    int shift = 0;
+    if (argc > 1 && argv[1][0] == L'-' && argv[1][1] != L'-') {
+        int sig = parse_signal(argv[1] + 1);
+        if (sig >= 0 && argv[1][1]) {
+            opts.signal = sig;
+            opts.have_signal = true;
+            shift = 1;
+        }
+    }
+
+    int optind;
+    int retval =
+        parse_cmd_opts(opts, &optind, argc - shift, argv + shift, cmd, parser, streams);
+    if (retval != STATUS_CMD_OK) return retval;
+    optind += shift;
+
+    if (opts.print_help) {
+        builtin_print_help(parser, streams, cmd);
+        return STATUS_CMD_OK;
+    }
+
+    if ((opts.list || opts.table) && (opts.have_signal || opts.verbose)) {
+        streams.err.append_format(BUILTIN_ERR_COMBO, cmd);
+        return STATUS_INVALID_ARGS;
+    }
+    if (opts.list && opts.table) {
+        streams.err.append_format(BUILTIN_ERR_COMBO2, cmd, _(L"--list and --table are exclusive"));
+        return STATUS_INVALID_ARGS;
+    }
+    if (opts.list) return list_signals(cmd, argc - optind, argv + optind, streams);
+    if (opts.table) {
+        if (optind != argc) {
+            streams.err.append_format(BUILTIN_ERR_ARG_COUNT1, cmd, 0, argc - optind);
+            return STATUS_INVALID_ARGS;
+        }
+        print_signal_table(streams);
+        return STATUS_CMD_OK;
+    }
+
+    if (optind == argc) {
+        streams.err.append_format(BUILTIN_ERR_MIN_ARG_COUNT1, cmd, 1, 0);
+        return STATUS_INVALID_ARGS;
+    }
+
+    // Resolve all arguments first, so nothing is signalled if one of them is invalid.
+    struct target_t {
+        job_t *job;
+        pid_t pid;
+    };
+    std::vector<target_t> targets;
+    for (int i = optind; i < argc; i++) {
+        if (argv[i][0] == L'%') {
+            job_t *j = find_job(cmd, parser, argv[i], streams);
+            if (!j) return STATUS_CMD_ERROR;
+            targets.push_back({j, 0});
+            continue;
+        }
+        // Negative numbers are process groups, as with kill(2).
+        pid_t pid = fish_wcstoi(argv[i]);
+        if (errno) {
+            streams.err.append_format(_(L"%ls: '%ls' is not a valid process id or job\n"), cmd,
+                                      argv[i]);
+            return STATUS_INVALID_ARGS;
+        }
+        targets.push_back({nullptr, pid});
+    }
+
+    const wchar_t *sig_name = opts.signal ? sig2wcs(opts.signal) : L"0";
+    for (const target_t &target : targets) {
+        if (target.job) {
+            job_t *j = target.job;
+            if (!signal_job(j, opts.signal)) {
+                streams.err.append_format(_(L"%ls: Could not signal job %d, '%ls': %s\n"), cmd,
+                                          j->job_id(), j->command_wcstr(), std::strerror(errno));
+                retval = STATUS_CMD_ERROR;
+            } else if (opts.verbose) {
+                streams.out.append_format(_(L"Sent %ls to job %d, '%ls'\n"), sig_name,
+                                          j->job_id(), j->command_wcstr());
+            }
+        } else if (kill(target.pid, opts.signal) < 0) {
+            streams.err.append_format(_(L"%ls: Could not signal process %d: %s\n"), cmd,
+                                      target.pid, std::strerror(errno));
+            retval = STATUS_CMD_ERROR;
+        } else if (opts.verbose) {
+            streams.out.append_format(_(L"Sent %ls to process %d\n"), sig_name, target.pid);
+        }
+    }
+    return retval;
+}
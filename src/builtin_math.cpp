@@ -71,7 +71,7 @@ static int parse_cmd_opts(math_cmd_opts_t &opts, int *optind,  //!OCLINT(high nc
                     opts.base = 8;
                 } else {
                     opts.base = fish_wcstoi(w.woptarg);
-                    if (errno || (opts.base != 8 && opts.base != 16)) {
+                    if (errno || opts.base < 2 || opts.base > 36) {
                         streams.err.append_format(_(L"%ls: '%ls' is not a valid base value\n"), cmd,
                                                   w.woptarg);
                         return STATUS_INVALID_ARGS;
@@ -180,14 +180,37 @@ static const wchar_t *math_describe_error(const te_error_t &error) {
     }
 }
 
+/// Return the integer \p v formatted in the given \p base. Hexadecimal numbers get a "0x" prefix,
+/// binary ones "0b" and octal ones "0".
+static wcstring format_integer(long long v, int base) {
+    if (base == 10) return format_string(L"%lld", v);
+
+    const wchar_t *const digits = L"0123456789abcdefghijklmnopqrstuvwxyz";
+    // Negate as unsigned so LLONG_MIN works.
+    unsigned long long magnitude = v < 0 ? 0ULL - static_cast<unsigned long long>(v) : v;
+    wcstring ret;
+    do {
+        ret.push_back(digits[magnitude % base]);
+        magnitude /= base;
+    } while (magnitude);
+
+    if (base == 16) {
+        ret.append(L"x0");
+    } else if (base == 2) {
+        ret.append(L"b0");
+    } else if (base == 8) {
+        ret.push_back(L'0');
+    }
+    if (v < 0) ret.push_back(L'-');
+    std::reverse(ret.begin(), ret.end());
+    return ret;
+}
+
 /// Return a formatted version of the value \p v respecting the given \p opts.
 static wcstring format_double(double v, const math_cmd_opts_t &opts) {
-    if (opts.base == 16) {
-        v = trunc(v);
-        return format_string(L"0x%x", (long)v);
-    } else if (opts.base == 8) {
-        v = trunc(v);
-        return format_string(L"0%o", (long)v);
+    if (opts.base != 10) {
+        // The magnitude has already been checked to fit.
+        return format_integer(static_cast<long long>(trunc(v)), opts.base);
     }
 
     // As a special-case, a scale of 0 means to truncate to an integer
@@ -223,9 +246,14 @@ static int evaluate_expression(const wchar_t *cmd, const parser_t &parser, io_st
 
     int retval = STATUS_CMD_OK;
     te_error_t error;
-    double v = te_interp(expression.c_str(), &error);
-
-    if (error.position == 0) {
+    te_integer_t integer;
+    double v = te_interp(expression.c_str(), &error, &integer);
+
+    if (error.position == 0 && integer.exact) {
+        // Integer results are exact, no matter their magnitude.
+        streams.out.append(format_integer(integer.value, opts.base));
+        streams.out.push_back(L'\n');
+    } else if (error.position == 0) {
         // Check some runtime errors after the fact.
         // TODO: Really, this should be done in tinyexpr
         // (e.g. infinite is the result of "x / 0"),
@@ -0,0 +1,136 @@
+// Implementation of the path builtin.
+#include "config.h"  // IWYU pragma: keep
+
+#include "builtin_path.h"
+
+#include <algorithm>
+#include <cwchar>
+#include <iterator>
+
+#include "builtin.h"
+#include "common.h"
+#include "fallback.h"  // IWYU pragma: keep
+#include "io.h"
+#include "parser.h"
+#include "path.h"
+#include "wgetopt.h"
+#include "wutil.h"  // IWYU pragma: keep
+
+struct convert_cmd_opts_t {
+    bool to_windows = false;
+    bool to_unix = false;
+    maybe_t<windows_path_style_t> style{};
+};
+
+static const wchar_t *const convert_short_options = L":wus:";
+static const struct woption convert_long_options[] = {{L"windows", no_argument, nullptr, 'w'},
+                                                      {L"unix", no_argument, nullptr, 'u'},
+                                                      {L"style", required_argument, nullptr, 's'},
+                                                      {nullptr, 0, nullptr, 0}};
+
+/// Translate paths between their Windows form, like C:\Users, and the one used by WSL or MSYS2,
+/// like /mnt/c/Users or /c/Users.
+static int path_convert(parser_t &parser, io_streams_t &streams, int argc, wchar_t **argv) {
+    const wchar_t *cmd = L"path convert";
+    convert_cmd_opts_t opts;
+    int opt;
+    wgetopter_t w;
+    while ((opt = w.wgetopt_long(argc, argv, convert_short_options, convert_long_options,
+                                 nullptr)) != -1) {
+        switch (opt) {
+            case 'w': {
+                opts.to_windows = true;
+                break;
+            }
+            case 'u': {
+                opts.to_unix = true;
+                break;
+            }
+            case 's': {
+                if (!std::wcscmp(w.woptarg, L"wsl")) {
+                    opts.style = windows_path_style_t::wsl;
+                } else if (!std::wcscmp(w.woptarg, L"msys")) {
+                    opts.style = windows_path_style_t::msys;
+                } else {
+                    streams.err.append_format(_(L"%ls: Invalid style '%ls'\n"), cmd, w.woptarg);
+                    return STATUS_INVALID_ARGS;
+                }
+                break;
+            }
+            case ':': {
+                builtin_missing_argument(parser, streams, cmd, argv[w.woptind - 1]);
+                return STATUS_INVALID_ARGS;
+            }
+            case '?': {
+                builtin_unknown_option(parser, streams, cmd, argv[w.woptind - 1]);
+                return STATUS_INVALID_ARGS;
+            }
+            default: {
+                DIE("unexpected retval from wgetopt_long");
+            }
+        }
+    }
+
+    if (opts.to_windows == opts.to_unix) {
+        streams.err.append_format(BUILTIN_ERR_COMBO2, cmd,
+                                  _(L"Exactly one of --windows and --unix must be given"));
+        return STATUS_INVALID_ARGS;
+    }
+    if (!opts.style) opts.style = path_get_windows_style();
+    if (!opts.style) {
+        streams.err.append_format(
+            _(L"%ls: Not running on Windows, give the path style with --style\n"), cmd);
+        return STATUS_INVALID_ARGS;
+    }
+
+    int retval = STATUS_CMD_OK;
+    for (int i = w.woptind; i < argc; i++) {
+        maybe_t<wcstring> converted = opts.to_windows ? path_to_windows(argv[i], *opts.style)
+                                                      : path_from_windows(argv[i], *opts.style);
+        if (!converted) {
+            streams.err.append_format(_(L"%ls: '%ls' cannot be translated\n"), cmd, argv[i]);
+            retval = STATUS_CMD_ERROR;
+            continue;
+        }
+        streams.out.append(*converted);
+        streams.out.push_back(L'\n');
+    }
+    return retval;
+}
+
+static const struct path_subcommand {
+    const wchar_t *name;
+    int (*handler)(parser_t &, io_streams_t &, int argc,  //!OCLINT(unused param)
+                   wchar_t **argv);                       //!OCLINT(unused param)
+} path_subcommands[] = {
+    {L"convert", &path_convert},
+};
+
+/// The path builtin, for manipulating paths.
+maybe_t<int> builtin_path(parser_t &parser, io_streams_t &streams, wchar_t **argv) {
+    const wchar_t *cmd = argv[0];
+    int argc = builtin_count_args(argv);
+    if (argc <= 1) {
+        builtin_print_error(parser, streams, L"path",
+                            format_string(BUILTIN_ERR_MISSING_SUBCMD, cmd));
+        return STATUS_INVALID_ARGS;
+    }
+
+    if (!std::wcscmp(argv[1], L"-h") || !std::wcscmp(argv[1], L"--help") ||
+        (argc >= 3 && (!std::wcscmp(argv[2], L"-h") || !std::wcscmp(argv[2], L"--help")))) {
+        builtin_print_help(parser, streams, L"path");
+        return STATUS_CMD_OK;
+    }
+
+    const wchar_t *subcmd_name = argv[1];
+    auto subcmd = std::find_if(std::begin(path_subcommands), std::end(path_subcommands),
+                               [&](const path_subcommand &sub) {
+                                   return !std::wcscmp(sub.name, subcmd_name);
+                               });
+    if (subcmd == std::end(path_subcommands)) {
+        builtin_print_error(parser, streams, L"path",
+                            format_string(BUILTIN_ERR_INVALID_SUBCMD, cmd, subcmd_name));
+        return STATUS_INVALID_ARGS;
+    }
+    return subcmd->handler(parser, streams, argc - 1, argv + 1);
+}
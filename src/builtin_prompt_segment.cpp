@@ -0,0 +1,162 @@
+// Implementation of the prompt_segment builtin.
+#include "config.h"  // IWYU pragma: keep
+
+#include "builtin_prompt_segment.h"
+
+#include <unistd.h>
+
+#include <memory>
+#include <string>
+#include <unordered_map>
+#include <vector>
+
+#include "builtin.h"
+#include "common.h"
+#include "env.h"
+#include "exec.h"
+#include "fallback.h"  // IWYU pragma: keep
+#include "io.h"
+#include "iothread.h"
+#include "null_terminated_array.h"
+#include "parser.h"
+#include "path.h"
+#include "postfork.h"
+#include "reader.h"
+#include "wgetopt.h"
+#include "wutil.h"  // IWYU pragma: keep
+
+namespace {
+/// The state of one named segment. This is only accessed on the main thread.
+struct prompt_segment_t {
+    /// The command line and directory the output belongs to.
+    wcstring output_key;
+    /// The output of the last run which finished.
+    wcstring output;
+    /// The command line and directory of the last run which was started.
+    wcstring started_key;
+    /// The value of reader_run_count() when the last run was started.
+    uint64_t started_at{0};
+    /// Identifies the last run which was started, so results from earlier ones can be dropped.
+    uint64_t generation{0};
+    /// Whether the last run which was started has not finished.
+    bool running{false};
+};
+}  // namespace
+
+static std::unordered_map<wcstring, prompt_segment_t> &segments() {
+    ASSERT_IS_MAIN_THREAD();
+    static std::unordered_map<wcstring, prompt_segment_t> s_segments;
+    return s_segments;
+}
+
+struct prompt_segment_cmd_opts_t {
+    bool print_help = false;
+};
+
+static const wchar_t *const short_options = L"+:h";
+static const struct woption long_options[] = {{L"help", no_argument, nullptr, 'h'},
+                                              {nullptr, 0, nullptr, 0}};
+
+static int parse_cmd_opts(prompt_segment_cmd_opts_t &opts, int *optind, int argc, wchar_t **argv,
+                          parser_t &parser, io_streams_t &streams) {
+    const wchar_t *cmd = argv[0];
+    int opt;
+    wgetopter_t w;
+    while ((opt = w.wgetopt_long(argc, argv, short_options, long_options, nullptr)) != -1) {
+        switch (opt) {
+            case 'h': {
+                opts.print_help = true;
+                break;
+            }
+            case ':': {
+                builtin_missing_argument(parser, streams, cmd, argv[w.woptind - 1]);
+                return STATUS_INVALID_ARGS;
+            }
+            case '?': {
+                builtin_unknown_option(parser, streams, cmd, argv[w.woptind - 1]);
+                return STATUS_INVALID_ARGS;
+            }
+            default: {
+                DIE("unexpected retval from wgetopt_long");
+            }
+        }
+    }
+
+    *optind = w.woptind;
+    return STATUS_CMD_OK;
+}
+
+/// Print the output a command had the last time it finished in the current directory, and run it
+/// again in the background. When the output changes, the right prompt is repainted.
+maybe_t<int> builtin_prompt_segment(parser_t &parser, io_streams_t &streams, wchar_t **argv) {
+    const wchar_t *cmd = argv[0];
+    prompt_segment_cmd_opts_t opts;
+    int argc = builtin_count_args(argv);
+    int optind;
+
+    int retval = parse_cmd_opts(opts, &optind, argc, argv, parser, streams);
+    if (retval != STATUS_CMD_OK) return retval;
+
+    if (opts.print_help) {
+        builtin_print_help(parser, streams, cmd);
+        return STATUS_CMD_OK;
+    }
+
+    if (argc - optind < 2) {
+        builtin_print_error(parser, streams, cmd,
+                            format_string(BUILTIN_ERR_MIN_ARG_COUNT1, cmd, 2, argc - optind));
+        return STATUS_INVALID_ARGS;
+    }
+
+    const wcstring name = argv[optind];
+    wcstring_list_t args(argv + optind + 1, argv + argc);
+    wcstring path;
+    if (!path_get_path(args.front(), &path, parser.vars())) {
+        builtin_print_error(
+            parser, streams, cmd,
+            format_string(_(L"%ls: Unknown command '%ls'\n"), cmd, args.front().c_str()));
+        return STATUS_CMD_UNKNOWN;
+    }
+
+    // The output of a command is only valid for the directory it ran in.
+    const wcstring key = join_strings(args, L'\0') + L'\0' + parser.vars().get_pwd_slash();
+    prompt_segment_t &segment = segments()[name];
+
+    // Run the command at most once for every command line that is executed, unless it changed.
+    if (segment.started_key != key ||
+        (!segment.running && segment.started_at != reader_run_count())) {
+#if FISH_USE_POSIX_SPAWN
+        static uint64_t s_generation = 0;
+        const uint64_t generation = ++s_generation;
+        segment.started_key = key;
+        segment.started_at = reader_run_count();
+        segment.generation = generation;
+        segment.running = true;
+
+        std::string narrow_path = wcs2string(path);
+        std::vector<std::string> narrow_args;
+        for (const wcstring &arg : args) narrow_args.push_back(wcs2string(arg));
+        auto env = parser.vars().export_arr();
+        iothread_perform(
+            [=]() { return exec_detached_output(narrow_path, narrow_args, env, false); },
+            [=](std::string output) {
+                prompt_segment_t &finished = segments()[name];
+                if (finished.generation != generation) return;
+                finished.running = false;
+                wcstring text = str2wcstring(output);
+                if (finished.output_key == key && finished.output == text) return;
+                finished.output_key = key;
+                finished.output = std::move(text);
+                reader_schedule_right_prompt_repaint();
+            });
+#else
+        builtin_print_error(parser, streams, cmd,
+                            format_string(_(L"%ls: Not supported on this platform\n"), cmd));
+        return STATUS_CMD_ERROR;
+#endif
+    }
+
+    if (segment.output_key != key) return STATUS_CMD_ERROR;
+    streams.out.append(segment.output);
+    return STATUS_CMD_OK;
+}
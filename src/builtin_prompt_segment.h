@@ -0,0 +1,11 @@
+// Prototypes for executing builtin_prompt_segment function.
+#ifndef FISH_BUILTIN_PROMPT_SEGMENT_H
+#define FISH_BUILTIN_PROMPT_SEGMENT_H
+
+#include "maybe.h"
+
+class parser_t;
+struct io_streams_t;
+
+maybe_t<int> builtin_prompt_segment(parser_t &parser, io_streams_t &streams, wchar_t **argv);
+#endif
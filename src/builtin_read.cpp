@@ -51,12 +51,19 @@ struct read_cmd_opts_t {
     bool shell = false;
     bool array = false;
     bool silent = false;
+    // The character shown for each one typed in silent mode, or empty to show nothing.
+    const wchar_t *mask = nullptr;
     bool split_null = false;
     bool to_stdout = false;
     int nchars = 0;
     bool one_line = false;
 };
 
+/// Values used for long-only options.
+enum {
+    opt_mask = 1,
+};
+
 static const wchar_t *const short_options = L":ac:d:ghiLln:p:sStuxzP:UR:L";
 static const struct woption long_options[] = {{L"array", no_argument, nullptr, 'a'},
                                               {L"command", required_argument, nullptr, 'c'},
@@ -67,6 +74,7 @@ static const struct woption long_options[] = {{L"array", no_argument, nullptr, '
                                               {L"line", no_argument, nullptr, 'L'},
                                               {L"list", no_argument, nullptr, 'a'},
                                               {L"local", no_argument, nullptr, 'l'},
+                                              {L"mask", required_argument, nullptr, opt_mask},
                                               {L"nchars", required_argument, nullptr, 'n'},
                                               {L"null", no_argument, nullptr, 'z'},
                                               {L"prompt", required_argument, nullptr, 'p'},
@@ -125,14 +133,15 @@ static int parse_cmd_opts(read_cmd_opts_t &opts, int *optind,  //!OCLINT(high nc
                 opts.nchars = fish_wcstoi(w.woptarg);
                 if (errno) {
                     if (errno == ERANGE) {
-                        streams.err.append_format(_(L"%ls: Argument '%ls' is out of range\n"), cmd,
-                                                  w.woptarg);
-                        builtin_print_error_trailer(parser, streams.err, cmd);
+                        builtin_print_error(
+                            parser, streams, cmd,
+                            format_string(_(L"%ls: Argument '%ls' is out of range\n"), cmd,
+                                          w.woptarg));
                         return STATUS_INVALID_ARGS;
                     }
 
-                    streams.err.append_format(BUILTIN_ERR_NOT_NUMBER, cmd, w.woptarg);
-                    builtin_print_error_trailer(parser, streams.err, cmd);
+                    builtin_print_error(parser, streams, cmd,
+                                        format_string(BUILTIN_ERR_NOT_NUMBER, cmd, w.woptarg));
                     return STATUS_INVALID_ARGS;
                 }
                 break;
@@ -153,6 +162,11 @@ static int parse_cmd_opts(read_cmd_opts_t &opts, int *optind,  //!OCLINT(high nc
                 opts.silent = true;
                 break;
             }
+            case opt_mask: {
+                opts.silent = true;
+                opts.mask = w.woptarg;
+                break;
+            }
             case L'S': {
                 opts.shell = true;
                 break;
@@ -198,8 +212,8 @@ static int parse_cmd_opts(read_cmd_opts_t &opts, int *optind,  //!OCLINT(high nc
 /// Read from the tty. This is only valid when the stream is stdin and it is attached to a tty and
 /// we weren't asked to split on null characters.
 static int read_interactive(parser_t &parser, wcstring &buff, int nchars, bool shell, bool silent,
-                            const wchar_t *prompt, const wchar_t *right_prompt,
-                            const wchar_t *commandline, int in) {
+                            const wchar_t *mask, const wchar_t *prompt,
+                            const wchar_t *right_prompt, const wchar_t *commandline, int in) {
     int exit_res = STATUS_CMD_OK;
 
     // Construct a configuration.
@@ -214,6 +228,7 @@ static int read_interactive(parser_t &parser, wcstring &buff, int nchars, bool s
 
     conf.exit_on_interrupt = true;
     conf.in_silent_mode = silent;
+    conf.silent_mask = mask ? mask : wcstring(1, get_obfuscation_read_char());
 
     conf.left_prompt_cmd = prompt;
     conf.right_prompt_cmd = right_prompt;
@@ -356,9 +371,9 @@ static int read_one_char_at_a_time(int fd, wcstring &buff, int nchars, bool spli
 static int validate_read_args(const wchar_t *cmd, read_cmd_opts_t &opts, int argc,
                               const wchar_t *const *argv, parser_t &parser, io_streams_t &streams) {
     if (opts.prompt && opts.prompt_str) {
-        streams.err.append_format(_(L"%ls: Options %ls and %ls cannot be used together\n"), cmd,
-                                  L"-p", L"-P");
-        builtin_print_error_trailer(parser, streams.err, cmd);
+        builtin_print_error(parser, streams, cmd,
+                            format_string(_(L"%ls: Options %ls and %ls cannot be used together\n"),
+                                          cmd, L"-p", L"-P"));
         return STATUS_INVALID_ARGS;
     }
 
@@ -373,6 +388,12 @@ static int validate_read_args(const wchar_t *cmd, read_cmd_opts_t &opts, int arg
         return STATUS_INVALID_ARGS;
     }
 
+    if (opts.mask && std::wcslen(opts.mask) > 1) {
+        streams.err.append_format(_(L"%ls: Mask must be a single character or empty, not '%ls'\n"),
+                                  cmd, opts.mask);
+        return STATUS_INVALID_ARGS;
+    }
+
     if (opts.prompt_str) {
         opts.prompt_cmd = L"echo " + escape_string(opts.prompt_str, ESCAPE_ALL);
         opts.prompt = opts.prompt_cmd.c_str();
@@ -381,16 +402,14 @@ static int validate_read_args(const wchar_t *cmd, read_cmd_opts_t &opts, int arg
     }
 
     if ((opts.place & ENV_UNEXPORT) && (opts.place & ENV_EXPORT)) {
-        streams.err.append_format(BUILTIN_ERR_EXPUNEXP, cmd);
-        builtin_print_error_trailer(parser, streams.err, cmd);
+        builtin_print_error(parser, streams, cmd, format_string(BUILTIN_ERR_EXPUNEXP, cmd));
         return STATUS_INVALID_ARGS;
     }
 
     if ((opts.place & ENV_LOCAL ? 1 : 0) + (opts.place & ENV_GLOBAL ? 1 : 0) +
             (opts.place & ENV_UNIVERSAL ? 1 : 0) >
         1) {
-        streams.err.append_format(BUILTIN_ERR_GLOCAL, cmd);
-        builtin_print_error_trailer(parser, streams.err, cmd);
+        builtin_print_error(parser, streams, cmd, format_string(BUILTIN_ERR_GLOCAL, cmd));
         return STATUS_INVALID_ARGS;
     }
 
@@ -424,8 +443,8 @@ static int validate_read_args(const wchar_t *cmd, read_cmd_opts_t &opts, int arg
     // Verify all variable names.
     for (int i = 0; i < argc; i++) {
         if (!valid_var_name(argv[i])) {
-            streams.err.append_format(BUILTIN_ERR_VARNAME, cmd, argv[i]);
-            builtin_print_error_trailer(parser, streams.err, cmd);
+            builtin_print_error(parser, streams, cmd,
+                                format_string(BUILTIN_ERR_VARNAME, cmd, argv[i]));
             return STATUS_INVALID_ARGS;
         }
     }
@@ -490,8 +509,9 @@ maybe_t<int> builtin_read(parser_t &parser, io_streams_t &streams, wchar_t **arg
         if (stream_stdin_is_a_tty && !opts.split_null) {
             // Read interactively using reader_readline(). This does not support splitting on null.
             exit_res =
-                read_interactive(parser, buff, opts.nchars, opts.shell, opts.silent, opts.prompt,
-                                 opts.right_prompt, opts.commandline, streams.stdin_fd);
+                read_interactive(parser, buff, opts.nchars, opts.shell, opts.silent, opts.mask,
+                                 opts.prompt, opts.right_prompt, opts.commandline,
+                                 streams.stdin_fd);
         } else if (!opts.nchars && !stream_stdin_is_a_tty &&
                    lseek(streams.stdin_fd, 0, SEEK_CUR) != -1) {
             exit_res = read_in_chunks(streams.stdin_fd, buff, opts.split_null);
@@ -72,8 +72,8 @@ maybe_t<int> builtin_return(parser_t &parser, io_streams_t &streams, wchar_t **a
     }
 
     if (optind + 1 < argc) {
-        streams.err.append_format(BUILTIN_ERR_TOO_MANY_ARGUMENTS, cmd);
-        builtin_print_error_trailer(parser, streams.err, cmd);
+        builtin_print_error(parser, streams, cmd,
+                            format_string(BUILTIN_ERR_TOO_MANY_ARGUMENTS, cmd));
         return STATUS_INVALID_ARGS;
     }
 
@@ -82,8 +82,8 @@ maybe_t<int> builtin_return(parser_t &parser, io_streams_t &streams, wchar_t **a
     } else {
         retval = fish_wcstoi(argv[1]);
         if (errno) {
-            streams.err.append_format(BUILTIN_ERR_NOT_NUMBER, cmd, argv[1]);
-            builtin_print_error_trailer(parser, streams.err, cmd);
+            builtin_print_error(parser, streams, cmd,
+                                format_string(BUILTIN_ERR_NOT_NUMBER, cmd, argv[1]));
             return STATUS_INVALID_ARGS;
         }
         retval &= 0xFF;
@@ -99,8 +99,8 @@ maybe_t<int> builtin_return(parser_t &parser, io_streams_t &streams, wchar_t **a
     }
 
     if (!has_function_block) {
-        streams.err.append_format(_(L"%ls: Not inside of function\n"), cmd);
-        builtin_print_error_trailer(parser, streams.err, cmd);
+        builtin_print_error(parser, streams, cmd,
+                            format_string(_(L"%ls: Not inside of function\n"), cmd));
         return STATUS_CMD_ERROR;
     }
 
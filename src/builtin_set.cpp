@@ -1,6 +1,7 @@
 // Functions used for implementing the set builtin.
 #include "config.h"  // IWYU pragma: keep
 
+#include <fcntl.h>
 #include <sys/stat.h>
 #include <unistd.h>
 
@@ -19,10 +20,13 @@
 #include "builtin.h"
 #include "common.h"
 #include "env.h"
+#include "env_universal_common.h"
 #include "expand.h"
 #include "fallback.h"  // IWYU pragma: keep
+#include "fds.h"
 #include "history.h"
 #include "io.h"
+#include "output.h"
 #include "parser.h"
 #include "proc.h"
 #include "wcstringutil.h"
@@ -41,10 +45,16 @@ struct set_cmd_opts_t {
     bool pathvar = false;
     bool unpathvar = false;
     bool universal = false;
+    bool host = false;
+    const wchar_t *export_file = nullptr;
+    const wchar_t *import_file = nullptr;
+    bool overwrite = false;
+    bool dry_run = false;
     bool query = false;
     bool shorten_ok = true;
     bool append = false;
     bool prepend = false;
+    bool unique = false;
     bool preserve_failure_exit_status = true;
 };
 
@@ -52,6 +62,12 @@ struct set_cmd_opts_t {
 enum {
     opt_path = 1,
     opt_unpath = 2,
+    opt_host = 3,
+    opt_export_file = 4,
+    opt_import_file = 5,
+    opt_overwrite = 6,
+    opt_dry_run = 7,
+    opt_unique = 8,
 };
 
 // Variables used for parsing the argument list. This command is atypical in using the "+"
@@ -66,7 +82,13 @@ static const struct woption long_options[] = {
     {L"query", no_argument, nullptr, 'q'},     {L"show", no_argument, nullptr, 'S'},
     {L"append", no_argument, nullptr, 'a'},    {L"prepend", no_argument, nullptr, 'p'},
     {L"path", no_argument, nullptr, opt_path}, {L"unpath", no_argument, nullptr, opt_unpath},
-    {L"help", no_argument, nullptr, 'h'},      {nullptr, 0, nullptr, 0}};
+    {L"host", no_argument, nullptr, opt_host}, {L"help", no_argument, nullptr, 'h'},
+    {L"export-file", required_argument, nullptr, opt_export_file},
+    {L"import-file", required_argument, nullptr, opt_import_file},
+    {L"overwrite", no_argument, nullptr, opt_overwrite},
+    {L"dry-run", no_argument, nullptr, opt_dry_run},
+    {L"unique", no_argument, nullptr, opt_unique},
+    {nullptr, 0, nullptr, 0}};
 
 // Hint for invalid path operation with a colon.
 #define BUILTIN_SET_PATH_ERROR _(L"%ls: Warning: $%ls entry \"%ls\" is not valid (%s)\n")
@@ -140,6 +162,32 @@ static int parse_cmd_opts(set_cmd_opts_t &opts, int *optind,  //!OCLINT(high ncs
                 opts.unpathvar = true;
                 break;
             }
+            case opt_host: {
+                opts.host = true;
+                break;
+            }
+            case opt_export_file: {
+                opts.export_file = w.woptarg;
+                opts.preserve_failure_exit_status = false;
+                break;
+            }
+            case opt_import_file: {
+                opts.import_file = w.woptarg;
+                opts.preserve_failure_exit_status = false;
+                break;
+            }
+            case opt_overwrite: {
+                opts.overwrite = true;
+                break;
+            }
+            case opt_dry_run: {
+                opts.dry_run = true;
+                break;
+            }
+            case opt_unique: {
+                opts.unique = true;
+                break;
+            }
             case 'U': {
                 opts.universal = true;
                 break;
@@ -176,57 +224,84 @@ static int validate_cmd_opts(const wchar_t *cmd,
                              int argc, parser_t &parser, io_streams_t &streams) {
     // Can't query and erase or list.
     if (opts.query && (opts.erase || opts.list)) {
-        streams.err.append_format(BUILTIN_ERR_COMBO, cmd);
-        builtin_print_error_trailer(parser, streams.err, cmd);
+        builtin_print_error(parser, streams, cmd, format_string(BUILTIN_ERR_COMBO, cmd));
         return STATUS_INVALID_ARGS;
     }
 
     // We can't both list and erase variables.
     if (opts.erase && opts.list) {
-        streams.err.append_format(BUILTIN_ERR_COMBO, cmd);
-        builtin_print_error_trailer(parser, streams.err, cmd);
+        builtin_print_error(parser, streams, cmd, format_string(BUILTIN_ERR_COMBO, cmd));
         return STATUS_INVALID_ARGS;
     }
 
     // Variables can only have one scope.
     if (opts.local + opts.global + opts.universal > 1) {
-        streams.err.append_format(BUILTIN_ERR_GLOCAL, cmd);
-        builtin_print_error_trailer(parser, streams.err, cmd);
+        builtin_print_error(parser, streams, cmd, format_string(BUILTIN_ERR_GLOCAL, cmd));
         return STATUS_INVALID_ARGS;
     }
 
     // Variables can only have one export status.
     if (opts.exportv && opts.unexport) {
-        streams.err.append_format(BUILTIN_ERR_EXPUNEXP, cmd);
-        builtin_print_error_trailer(parser, streams.err, cmd);
+        builtin_print_error(parser, streams, cmd, format_string(BUILTIN_ERR_EXPUNEXP, cmd));
         return STATUS_INVALID_ARGS;
     }
 
     // Variables can only have one path status.
     if (opts.pathvar && opts.unpathvar) {
-        streams.err.append_format(BUILTIN_ERR_EXPUNEXP, cmd);
-        builtin_print_error_trailer(parser, streams.err, cmd);
+        builtin_print_error(parser, streams, cmd, format_string(BUILTIN_ERR_EXPUNEXP, cmd));
+        return STATUS_INVALID_ARGS;
+    }
+
+    // Only universal variables can be for this host, or exported to and imported from files.
+    const wchar_t *universal_opt = opts.host          ? L"--host"
+                                   : opts.export_file ? L"--export-file"
+                                   : opts.import_file ? L"--import-file"
+                                                      : nullptr;
+    if (universal_opt && !opts.universal) {
+        wcstring msg = format_string(_(L"%ls requires --universal"), universal_opt);
+        builtin_print_error(parser, streams, cmd,
+                            format_string(BUILTIN_ERR_COMBO2, cmd, msg.c_str()));
+        return STATUS_INVALID_ARGS;
+    }
+
+    // Exporting and importing files don't go with the other modes.
+    if ((opts.export_file || opts.import_file) &&
+        ((opts.export_file && opts.import_file) || opts.query || opts.erase || opts.list ||
+         opts.append || opts.prepend || opts.host || opts.exportv || opts.unexport ||
+         opts.pathvar || opts.unpathvar)) {
+        builtin_print_error(parser, streams, cmd, format_string(BUILTIN_ERR_COMBO, cmd));
+        return STATUS_INVALID_ARGS;
+    }
+
+    if ((opts.overwrite || opts.dry_run) && !opts.import_file) {
+        builtin_print_error(parser, streams, cmd,
+                            format_string(BUILTIN_ERR_COMBO2, cmd,
+                                          _(L"--overwrite and --dry-run require --import-file")));
+        return STATUS_INVALID_ARGS;
+    }
+
+    if (opts.unique && !(opts.append || opts.prepend)) {
+        builtin_print_error(parser, streams, cmd,
+                            format_string(BUILTIN_ERR_COMBO2, cmd,
+                                          _(L"--unique requires --append or --prepend")));
         return STATUS_INVALID_ARGS;
     }
 
     // Trying to erase and (un)export at the same time doesn't make sense.
     if (opts.erase && (opts.exportv || opts.unexport)) {
-        streams.err.append_format(BUILTIN_ERR_COMBO, cmd);
-        builtin_print_error_trailer(parser, streams.err, cmd);
+        builtin_print_error(parser, streams, cmd, format_string(BUILTIN_ERR_COMBO, cmd));
         return STATUS_INVALID_ARGS;
     }
 
     // The --show flag cannot be combined with any other flag.
     if (opts.show &&
         (opts.local || opts.global || opts.erase || opts.list || opts.exportv || opts.universal)) {
-        streams.err.append_format(BUILTIN_ERR_COMBO, cmd);
-        builtin_print_error_trailer(parser, streams.err, cmd);
+        builtin_print_error(parser, streams, cmd, format_string(BUILTIN_ERR_COMBO, cmd));
         return STATUS_INVALID_ARGS;
     }
 
     if (argc == 0 && opts.erase) {
-        streams.err.append_format(BUILTIN_SET_ERASE_NO_VAR, cmd);
-        builtin_print_error_trailer(parser, streams.err, cmd);
+        builtin_print_error(parser, streams, cmd, format_string(BUILTIN_SET_ERASE_NO_VAR, cmd));
         return STATUS_INVALID_ARGS;
     }
 
@@ -252,8 +327,8 @@ static int check_global_scope_exists(const wchar_t *cmd, const set_cmd_opts_t &o
 // contain a colon, then complain. Return true if any path element was valid, false if not.
 static bool validate_path_warning_on_colons(const wchar_t *cmd,
                                             const wchar_t *key,  //!OCLINT(npath complexity)
-                                            const wcstring_list_t &list, io_streams_t &streams,
-                                            const environment_t &vars) {
+                                            const wcstring_list_t &list, parser_t &parser,
+                                            io_streams_t &streams) {
     // Always allow setting an empty value.
     if (list.empty()) return true;
 
@@ -268,7 +343,7 @@ static bool validate_path_warning_on_colons(const wchar_t *cmd,
     // where we are temporarily shadowing a variable, we want to compare against the shadowed value,
     // not the (missing) local value. Also don't bother to complain about relative paths, which
     // don't start with /.
-    const auto existing_variable = vars.get(key, ENV_DEFAULT);
+    const auto existing_variable = parser.vars().get(key, ENV_DEFAULT);
     const wcstring_list_t &existing_values =
         existing_variable ? existing_variable->as_list() : wcstring_list_t{};
 
@@ -297,10 +372,11 @@ static bool validate_path_warning_on_colons(const wchar_t *cmd,
         if (valid) {
             any_success = true;
         } else if (looks_like_colon_sep) {
-            streams.err.append_format(BUILTIN_SET_PATH_ERROR, cmd, key, dir.c_str(),
-                                      std::strerror(errno));
-            streams.err.append_format(BUILTIN_SET_PATH_HINT, cmd, key, key,
-                                      std::wcschr(dir.c_str(), L':') + 1);
+            wcstring warning = format_string(BUILTIN_SET_PATH_ERROR, cmd, key, dir.c_str(),
+                                             std::strerror(errno));
+            warning.append(format_string(BUILTIN_SET_PATH_HINT, cmd, key, key,
+                                         std::wcschr(dir.c_str(), L':') + 1));
+            builtin_print_warning(parser, streams, warning);
         }
     }
     return any_success;
@@ -342,13 +418,13 @@ static void handle_env_return(int retval, const wchar_t *cmd, const wchar_t *key
 /// Call vars.set. If this is a path variable, e.g. PATH, validate the elements. On error, print a
 /// description of the problem to stderr.
 static int env_set_reporting_errors(const wchar_t *cmd, const wchar_t *key, int scope,
-                                    wcstring_list_t list, io_streams_t &streams, env_stack_t &vars,
+                                    wcstring_list_t list, parser_t &parser, io_streams_t &streams,
                                     std::vector<event_t> *evts) {
-    if (is_path_variable(key) && !validate_path_warning_on_colons(cmd, key, list, streams, vars)) {
+    if (is_path_variable(key) && !validate_path_warning_on_colons(cmd, key, list, parser, streams)) {
         return STATUS_CMD_ERROR;
     }
 
-    int retval = vars.set(key, scope | ENV_USER, std::move(list), evts);
+    int retval = parser.vars().set(key, scope | ENV_USER, std::move(list), evts);
     handle_env_return(retval, cmd, key, streams);
 
     return retval;
@@ -476,6 +552,7 @@ static env_mode_flags_t compute_scope(const set_cmd_opts_t &opts) {
     if (opts.universal) scope |= ENV_UNIVERSAL;
     if (opts.pathvar) scope |= ENV_PATHVAR;
     if (opts.unpathvar) scope |= ENV_UNPATHVAR;
+    if (opts.host) scope |= ENV_HOST;
     return scope;
 }
 
@@ -568,8 +645,8 @@ static int builtin_set_query(const wchar_t *cmd, set_cmd_opts_t &opts, int argc,
     return retval;
 }
 
-static void show_scope(const wchar_t *var_name, int scope, io_streams_t &streams,
-                       const environment_t &vars) {
+static void show_scope(const wchar_t *var_name, int scope, parser_t &parser,
+                       io_streams_t &streams) {
     const wchar_t *scope_name;
     switch (scope) {
         case ENV_LOCAL: {
@@ -589,45 +666,52 @@ static void show_scope(const wchar_t *var_name, int scope, io_streams_t &streams
         }
     }
 
-    const auto var = vars.get(var_name, scope);
+    const auto var = parser.vars().get(var_name, scope);
     if (!var) {
         return;
     }
 
     const wchar_t *exportv = var->exports() ? _(L"exported") : _(L"unexported");
     const wchar_t *pathvarv = var->is_pathvar() ? _(L" a path variable") : L"";
+    const wchar_t *hostv = var->is_host_scoped() ? _(L" for this host") : L"";
     wcstring_list_t vals = var->as_list();
-    streams.out.append_format(_(L"$%ls: set in %ls scope, %ls,%ls with %d elements\n"), var_name,
-                              scope_name, exportv, pathvarv, vals.size());
+    unsigned long count = vals.size();
+    styled_text_t text;
+    text.append_format(text_style_t::emphasis,
+                       P_(L"$%ls: set in %ls scope%ls, %ls,%ls with %lu element\n",
+                          L"$%ls: set in %ls scope%ls, %ls,%ls with %lu elements\n", count),
+                       var_name, scope_name, hostv, exportv, pathvarv, count);
 
     for (size_t i = 0; i < vals.size(); i++) {
         if (vals.size() > 100) {
             if (i == 50) {
                 // try to print a mid-line ellipsis because we are eliding lines not words
-                streams.out.append(get_ellipsis_char() > 256 ? L"\u22EF" : get_ellipsis_str());
-                streams.out.push_back(L'\n');
+                text.append(get_ellipsis_char() > 256 ? L"\u22EF" : get_ellipsis_str());
+                text.append(L"\n");
             }
             if (i >= 50 && i < vals.size() - 50) continue;
         }
         const wcstring value = vals[i];
         const wcstring escaped_val = escape_string(value, ESCAPE_NO_QUOTED, STRING_STYLE_SCRIPT);
-        streams.out.append_format(_(L"$%ls[%d]: |%ls|\n"), var_name, i + 1, escaped_val.c_str());
+        text.append_format(text_style_t::normal, L"$%ls[%d]: |", var_name, static_cast<int>(i + 1));
+        text.append(escaped_val, text_style_t::value);
+        text.append(L"|\n");
     }
+    builtin_print_styled(parser, streams, false, text);
 }
 
 /// Show mode. Show information about the named variable(s).
 static int builtin_set_show(const wchar_t *cmd, const set_cmd_opts_t &opts, int argc,
                             wchar_t **argv, parser_t &parser, io_streams_t &streams) {
     UNUSED(opts);
-    const auto &vars = parser.vars();
     if (argc == 0) {  // show all vars
         wcstring_list_t names = parser.vars().get_names(ENV_USER);
         sort(names.begin(), names.end());
         for (const auto &name : names) {
             if (name == L"history") continue;
-            show_scope(name.c_str(), ENV_LOCAL, streams, vars);
-            show_scope(name.c_str(), ENV_GLOBAL, streams, vars);
-            show_scope(name.c_str(), ENV_UNIVERSAL, streams, vars);
+            show_scope(name.c_str(), ENV_LOCAL, parser, streams);
+            show_scope(name.c_str(), ENV_GLOBAL, parser, streams);
+            show_scope(name.c_str(), ENV_UNIVERSAL, parser, streams);
         }
     } else {
         for (int i = 0; i < argc; i++) {
@@ -639,15 +723,16 @@ static int builtin_set_show(const wchar_t *cmd, const set_cmd_opts_t &opts, int
             }
 
             if (std::wcschr(arg, L'[')) {
-                streams.err.append_format(
-                    _(L"%ls: `set --show` does not allow slices with the var names\n"), cmd);
-                builtin_print_error_trailer(parser, streams.err, cmd);
+                builtin_print_error(
+                    parser, streams, cmd,
+                    format_string(
+                        _(L"%ls: `set --show` does not allow slices with the var names\n"), cmd));
                 return STATUS_CMD_ERROR;
             }
 
-            show_scope(arg, ENV_LOCAL, streams, vars);
-            show_scope(arg, ENV_GLOBAL, streams, vars);
-            show_scope(arg, ENV_UNIVERSAL, streams, vars);
+            show_scope(arg, ENV_LOCAL, parser, streams);
+            show_scope(arg, ENV_GLOBAL, parser, streams);
+            show_scope(arg, ENV_UNIVERSAL, parser, streams);
         }
     }
 
@@ -672,8 +757,8 @@ static int builtin_set_erase(const wchar_t *cmd, set_cmd_opts_t &opts, int argc,
 
         int retval;
         if (!valid_var_name(dest)) {
-            streams.err.append_format(BUILTIN_ERR_VARNAME, cmd, dest);
-            builtin_print_error_trailer(parser, streams.err, cmd);
+            builtin_print_error(parser, streams, cmd,
+                                format_string(BUILTIN_ERR_VARNAME, cmd, dest));
             return STATUS_INVALID_ARGS;
         }
 
@@ -692,8 +777,8 @@ static int builtin_set_erase(const wchar_t *cmd, set_cmd_opts_t &opts, int argc,
             wcstring_list_t result;
             dest_var->to_list(result);
             erase_values(result, indexes);
-            retval = env_set_reporting_errors(cmd, dest, scope, std::move(result), streams,
-                                              parser.vars(), &evts);
+            retval = env_set_reporting_errors(cmd, dest, scope, std::move(result), parser,
+                                              streams, &evts);
         }
 
         // Fire any events.
@@ -721,18 +806,45 @@ static int set_var_array(const wchar_t *cmd, const set_cmd_opts_t &opts, const w
     UNUSED(streams);
 
     if (opts.prepend || opts.append) {
+        auto var_str = parser.vars().get(varname, ENV_DEFAULT);
+        wcstring_list_t added;
+        for (int i = 0; i < argc; i++) added.push_back(argv[i]);
+        if (opts.unique) {
+            // Skip the values which are already there, or given twice. Paths are compared
+            // normalized, so "/usr/bin/" is the same as "/usr/bin".
+            bool pathvar = opts.pathvar;
+            if (!opts.pathvar && !opts.unpathvar) {
+                pathvar =
+                    var_str ? var_str->is_pathvar() : string_suffixes_string(L"PATH", varname);
+            }
+            auto key = [=](const wcstring &val) {
+                return pathvar && !val.empty() ? normalize_path(val, false) : val;
+            };
+            wcstring_list_t seen;
+            if (var_str) {
+                for (const wcstring &val : var_str->as_list()) seen.push_back(key(val));
+            }
+            wcstring_list_t unique;
+            for (wcstring &val : added) {
+                wcstring k = key(val);
+                if (contains(seen, k)) continue;
+                seen.push_back(std::move(k));
+                unique.push_back(std::move(val));
+            }
+            added = std::move(unique);
+        }
+
         if (opts.prepend) {
-            for (int i = 0; i < argc; i++) new_values.push_back(argv[i]);
+            new_values.insert(new_values.end(), added.begin(), added.end());
         }
 
-        auto var_str = parser.vars().get(varname, ENV_DEFAULT);
         if (var_str) {
             const auto &var_array = var_str->as_list();
             new_values.insert(new_values.end(), var_array.begin(), var_array.end());
         }
 
         if (opts.append) {
-            for (int i = 0; i < argc; i++) new_values.push_back(argv[i]);
+            new_values.insert(new_values.end(), added.begin(), added.end());
         }
     } else {
         for (int i = 0; i < argc; i++) new_values.push_back(argv[i]);
@@ -748,9 +860,10 @@ static int set_var_slices(const wchar_t *cmd, set_cmd_opts_t &opts, const wchar_
     UNUSED(parser);
 
     if (opts.append || opts.prepend) {
-        streams.err.append_format(
-            L"%ls: Cannot use --append or --prepend when assigning to a slice", cmd);
-        builtin_print_error_trailer(parser, streams.err, cmd);
+        builtin_print_error(
+            parser, streams, cmd,
+            format_string(L"%ls: Cannot use --append or --prepend when assigning to a slice",
+                          cmd));
         return STATUS_INVALID_ARGS;
     }
 
@@ -780,8 +893,8 @@ static int set_var_slices(const wchar_t *cmd, set_cmd_opts_t &opts, const wchar_
 static int builtin_set_set(const wchar_t *cmd, set_cmd_opts_t &opts, int argc, wchar_t **argv,
                            parser_t &parser, io_streams_t &streams) {
     if (argc == 0) {
-        streams.err.append_format(BUILTIN_ERR_MIN_ARG_COUNT1, cmd, 1);
-        builtin_print_error_trailer(parser, streams.err, cmd);
+        builtin_print_error(parser, streams, cmd,
+                            format_string(BUILTIN_ERR_MIN_ARG_COUNT1, cmd, 1));
         return STATUS_INVALID_ARGS;
     }
 
@@ -798,8 +911,7 @@ static int builtin_set_set(const wchar_t *cmd, set_cmd_opts_t &opts, int argc, w
     }
 
     if (!valid_var_name(varname)) {
-        streams.err.append_format(BUILTIN_ERR_VARNAME, cmd, varname);
-        builtin_print_error_trailer(parser, streams.err, cmd);
+        builtin_print_error(parser, streams, cmd, format_string(BUILTIN_ERR_VARNAME, cmd, varname));
         return STATUS_INVALID_ARGS;
     }
 
@@ -816,8 +928,8 @@ static int builtin_set_set(const wchar_t *cmd, set_cmd_opts_t &opts, int argc, w
     if (retval != STATUS_CMD_OK) return retval;
 
     std::vector<event_t> evts;
-    retval = env_set_reporting_errors(cmd, varname, scope, std::move(new_values), streams,
-                                      parser.vars(), &evts);
+    retval = env_set_reporting_errors(cmd, varname, scope, std::move(new_values), parser,
+                                      streams, &evts);
     // Fire any events.
     for (const auto &evt : evts) {
         event_fire(parser, evt);
@@ -827,6 +939,110 @@ static int builtin_set_set(const wchar_t *cmd, set_cmd_opts_t &opts, int argc, w
     return check_global_scope_exists(cmd, opts, varname, streams, parser);
 }
 
+/// Write the universal variables, or the named ones, to a file in the format of fish_variables.
+static int builtin_set_export_file(const wchar_t *cmd, const set_cmd_opts_t &opts, int argc,
+                                   wchar_t **argv, parser_t &parser, io_streams_t &streams) {
+    wcstring_list_t names(argv, argv + argc);
+    if (names.empty()) names = parser.vars().get_names(ENV_UNIVERSAL);
+
+    var_table_t vars;
+    for (const wcstring &name : names) {
+        auto var = parser.vars().get(name, ENV_UNIVERSAL);
+        if (!var) {
+            streams.err.append_format(_(L"%ls: Universal variable '%ls' does not exist\n"), cmd,
+                                      name.c_str());
+            return STATUS_CMD_ERROR;
+        }
+        // Values for this host are written as shared ones, so they can be imported elsewhere.
+        vars[name] = env_var_t(var->as_list(), var->get_flags() & ~env_var_t::flag_host);
+    }
+
+    std::string contents = env_universal_t::serialize_with_vars(vars);
+    autoclose_fd_t fd{wopen_cloexec(opts.export_file, O_WRONLY | O_CREAT | O_TRUNC, 0644)};
+    if (!fd.valid() || write_loop(fd.fd(), contents.data(), contents.size()) < 0) {
+        streams.err.append_format(_(L"%ls: Could not write '%ls': %s\n"), cmd, opts.export_file,
+                                  std::strerror(errno));
+        return STATUS_CMD_ERROR;
+    }
+    return STATUS_CMD_OK;
+}
+
+/// Set universal variables, or the named ones, from a file in the format of fish_variables.
+/// Variables which are already set keep their values, unless --overwrite was given. With
+/// --dry-run, the changes are printed like a diff instead.
+static int builtin_set_import_file(const wchar_t *cmd, const set_cmd_opts_t &opts, int argc,
+                                   wchar_t **argv, parser_t &parser, io_streams_t &streams) {
+    std::string contents;
+    autoclose_fd_t fd{wopen_cloexec(opts.import_file, O_RDONLY)};
+    ssize_t amt = -1;
+    if (fd.valid()) {
+        char buff[4096];
+        while ((amt = read_loop(fd.fd(), buff, sizeof buff)) > 0) contents.append(buff, amt);
+    }
+    if (amt < 0) {
+        streams.err.append_format(_(L"%ls: Could not read '%ls': %s\n"), cmd, opts.import_file,
+                                  std::strerror(errno));
+        return STATUS_CMD_ERROR;
+    }
+
+    var_table_t vars;
+    std::vector<size_t> bad_lines;
+    env_universal_t::populate_variables(contents, &vars, &bad_lines);
+    for (size_t line : bad_lines) {
+        streams.err.append_format(_(L"%ls: Line %lu of '%ls' could not be read\n"), cmd,
+                                  static_cast<unsigned long>(line), opts.import_file);
+    }
+
+    wcstring_list_t names(argv, argv + argc);
+    for (const wcstring &name : names) {
+        if (!vars.count(name)) {
+            streams.err.append_format(_(L"%ls: Variable '%ls' is not in '%ls'\n"), cmd,
+                                      name.c_str(), opts.import_file);
+            return STATUS_CMD_ERROR;
+        }
+    }
+    if (names.empty()) {
+        for (const auto &kv : vars) names.push_back(kv.first);
+        std::sort(names.begin(), names.end());
+    }
+
+    int retval = STATUS_CMD_OK;
+    std::vector<event_t> evts;
+    const auto flags_mask = env_var_t::flag_export | env_var_t::flag_pathvar;
+    for (const wcstring &name : names) {
+        const env_var_t &var = vars.at(name);
+        auto old = parser.vars().get(name, ENV_UNIVERSAL);
+        if (old && (!opts.overwrite || (old->as_list() == var.as_list() &&
+                                        (old->get_flags() & flags_mask) ==
+                                            (var.get_flags() & flags_mask)))) {
+            continue;
+        }
+
+        if (opts.dry_run) {
+            if (old) {
+                streams.out.append_format(L"- %ls %ls\n", name.c_str(),
+                                          expand_escape_variable(*old).c_str());
+            }
+            streams.out.append_format(L"+ %ls %ls\n", name.c_str(),
+                                      expand_escape_variable(var).c_str());
+            continue;
+        }
+
+        int scope = ENV_UNIVERSAL;
+        scope |= var.exports() ? ENV_EXPORT : ENV_UNEXPORT;
+        scope |= var.is_pathvar() ? ENV_PATHVAR : ENV_UNPATHVAR;
+        if (var.is_host_scoped()) scope |= ENV_HOST;
+        if (env_set_reporting_errors(cmd, name.c_str(), scope, var.as_list(), parser, streams,
+                                     &evts) != ENV_OK) {
+            retval = STATUS_CMD_ERROR;
+        }
+    }
+    for (const auto &evt : evts) {
+        event_fire(parser, evt);
+    }
+    return retval;
+}
+
 /// The set builtin creates, updates, and erases (removes, deletes) variables.
 maybe_t<int> builtin_set(parser_t &parser, io_streams_t &streams, wchar_t **argv) {
     wchar_t *cmd = argv[0];
@@ -855,6 +1071,10 @@ maybe_t<int> builtin_set(parser_t &parser, io_streams_t &streams, wchar_t **argv
         retval = builtin_set_list(cmd, opts, argc, argv, parser, streams);
     } else if (opts.show) {
         retval = builtin_set_show(cmd, opts, argc, argv, parser, streams);
+    } else if (opts.export_file) {
+        retval = builtin_set_export_file(cmd, opts, argc, argv, parser, streams);
+    } else if (opts.import_file) {
+        retval = builtin_set_import_file(cmd, opts, argc, argv, parser, streams);
     } else if (argc == 0) {  // implicit list the vars we know about
         retval = builtin_set_list(cmd, opts, argc, argv, parser, streams);
     } else {
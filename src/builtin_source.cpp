@@ -18,18 +18,61 @@
 #include "parser.h"
 #include "proc.h"
 #include "reader.h"
+#include "wgetopt.h"
 #include "wutil.h"  // IWYU pragma: keep
 
+struct source_cmd_opts_t {
+    bool print_help = false;
+    bool keep_going = false;
+};
+static const wchar_t *const short_options = L"+:hk";
+static const struct woption long_options[] = {{L"help", no_argument, nullptr, 'h'},
+                                              {L"keep-going", no_argument, nullptr, 'k'},
+                                              {nullptr, 0, nullptr, 0}};
+
+static int parse_cmd_opts(source_cmd_opts_t &opts, int *optind,  //!OCLINT(high ncss method)
+                          int argc, wchar_t **argv, parser_t &parser, io_streams_t &streams) {
+    wchar_t *cmd = argv[0];
+    int opt;
+    wgetopter_t w;
+    while ((opt = w.wgetopt_long(argc, argv, short_options, long_options, nullptr)) != -1) {
+        switch (opt) {
+            case 'h': {
+                opts.print_help = true;
+                break;
+            }
+            case 'k': {
+                opts.keep_going = true;
+                break;
+            }
+            case ':': {
+                builtin_missing_argument(parser, streams, cmd, argv[w.woptind - 1]);
+                return STATUS_INVALID_ARGS;
+            }
+            case '?': {
+                builtin_unknown_option(parser, streams, cmd, argv[w.woptind - 1]);
+                return STATUS_INVALID_ARGS;
+            }
+            default: {
+                DIE("unexpected retval from wgetopt_long");
+            }
+        }
+    }
+
+    *optind = w.woptind;
+    return STATUS_CMD_OK;
+}
+
 /// The  source builtin, sometimes called `.`. Evaluates the contents of a file in the current
 /// context.
 maybe_t<int> builtin_source(parser_t &parser, io_streams_t &streams, wchar_t **argv) {
     ASSERT_IS_MAIN_THREAD();
     const wchar_t *cmd = argv[0];
     int argc = builtin_count_args(argv);
-    help_only_cmd_opts_t opts;
+    source_cmd_opts_t opts;
 
     int optind;
-    int retval = parse_help_only_cmd_opts(opts, &optind, argc, argv, parser, streams);
+    int retval = parse_cmd_opts(opts, &optind, argc, argv, parser, streams);
     if (retval != STATUS_CMD_OK) return retval;
 
     if (opts.print_help) {
@@ -46,6 +89,12 @@ maybe_t<int> builtin_source(parser_t &parser, io_streams_t &streams, wchar_t **a
     struct stat buf;
     const wchar_t *fn, *fn_intern;
 
+    // Autoloaded files have their parsed form cached. Files they source are not autoloaded.
+    auto &ld = parser.libdata();
+    bool autoloading = ld.is_autoloading;
+    const wchar_t *cache_path = nullptr;
+    scoped_push<bool> autoloading_push{&ld.is_autoloading, false};
+
     if (argc == optind || std::wcscmp(argv[optind], L"-") == 0) {
         // Either a bare `source` which means to implicitly read from stdin or an explicit `-`.
         if (argc == optind && isatty(streams.stdin_fd)) {
@@ -72,18 +121,23 @@ maybe_t<int> builtin_source(parser_t &parser, io_streams_t &streams, wchar_t **a
             return STATUS_CMD_ERROR;
         }
 
-        if (!S_ISREG(buf.st_mode)) {
+        // Allow pipes, so that process substitutions like `source <(cmd)` work.
+        if (!S_ISREG(buf.st_mode) && !S_ISFIFO(buf.st_mode)) {
             streams.err.append_format(_(L"%ls: '%ls' is not a file\n"), cmd, argv[optind]);
             return STATUS_CMD_ERROR;
         }
 
         fn_intern = intern(argv[optind]);
+        if (autoloading && S_ISREG(buf.st_mode)) cache_path = fn_intern;
     }
     assert(fd >= 0 && "Should have a valid fd");
 
     const block_t *sb = parser.push_block(block_t::source_block(fn_intern));
-    auto &ld = parser.libdata();
     scoped_push<const wchar_t *> filename_push{&ld.current_filename, fn_intern};
+    // Files sourced from a file with --keep-going also keep going.
+    bool outermost_keep_going = opts.keep_going && !ld.keep_going_on_errors;
+    scoped_push<bool> keep_going_push{&ld.keep_going_on_errors,
+                                      ld.keep_going_on_errors || opts.keep_going};
 
     // This is slightly subtle. If this is a bare `source` with no args then `argv + optind` already
     // points to the end of argv. Otherwise we want to skip the file name to get to the args if any.
@@ -91,10 +145,17 @@ maybe_t<int> builtin_source(parser_t &parser, io_streams_t &streams, wchar_t **a
         null_terminated_array_t<wchar_t>::to_list(argv + optind + (argc == optind ? 0 : 1));
     parser.vars().set_argv(std::move(argv_list));
 
-    retval = reader_read(parser, fd, streams.io_chain ? *streams.io_chain : io_chain_t());
+    retval =
+        reader_read(parser, fd, streams.io_chain ? *streams.io_chain : io_chain_t(), cache_path);
 
     parser.pop_block(sb);
 
+    // Report the errors we kept going after. During startup, this waits until all config files are
+    // read.
+    if (outermost_keep_going && !is_within_fish_initialization()) {
+        reader_report_deferred_errors(parser);
+    }
+
     if (retval != STATUS_CMD_OK) {
         streams.err.append_format(_(L"%ls: Error while reading file '%ls'\n"), cmd,
                                   fn_intern == intern_static(L"-") ? L"<stdin>" : fn_intern);
@@ -3,17 +3,34 @@
 
 #include "builtin_status.h"
 
+#include <fcntl.h>
+#include <stddef.h>
+#include <stdio.h>
+#include <sys/socket.h>
+#include <sys/un.h>
+#include <unistd.h>
+
+#include <cerrno>
 #include <cstddef>
+#include <cstring>
 #include <cwchar>
 #include <string>
 
 #include "builtin.h"
+#include "color.h"
 #include "common.h"
+#include "exec.h"
 #include "fallback.h"  // IWYU pragma: keep
+#include "fds.h"
+#include "flog.h"
 #include "future_feature_flags.h"
 #include "io.h"
+#include "json.h"
+#include "output.h"
 #include "parser.h"
 #include "proc.h"
+#include "signal.h"
+#include "wcstringutil.h"
 #include "wgetopt.h"
 #include "wutil.h"  // IWYU pragma: keep
 
@@ -21,6 +38,8 @@ enum status_cmd_t {
     STATUS_CURRENT_CMD = 1,
     STATUS_BASENAME,
     STATUS_DIRNAME,
+    STATUS_EVENTS,
+    STATUS_FDS,
     STATUS_FEATURES,
     STATUS_FILENAME,
     STATUS_FISH_PATH,
@@ -34,7 +53,12 @@ enum status_cmd_t {
     STATUS_IS_LOGIN,
     STATUS_IS_NO_JOB_CTRL,
     STATUS_LINE_NUMBER,
+    STATUS_LOG,
+    STATUS_NOTIFY_READY,
+    STATUS_NOTIFY_WATCHDOG,
+    STATUS_PIPESTATUS,
     STATUS_SET_JOB_CONTROL,
+    STATUS_SPAWN_STATS,
     STATUS_STACK_TRACE,
     STATUS_TEST_FEATURE,
     STATUS_UNDEF
@@ -50,6 +74,8 @@ const enum_map<status_cmd_t> status_enum_map[] = {
     {STATUS_FUNCTION, L"current-function"},
     {STATUS_LINE_NUMBER, L"current-line-number"},
     {STATUS_DIRNAME, L"dirname"},
+    {STATUS_EVENTS, L"events"},
+    {STATUS_FDS, L"fds"},
     {STATUS_FEATURES, L"features"},
     {STATUS_FILENAME, L"filename"},
     {STATUS_FISH_PATH, L"fish-path"},
@@ -64,7 +90,12 @@ const enum_map<status_cmd_t> status_enum_map[] = {
     {STATUS_IS_NO_JOB_CTRL, L"is-no-job-control"},
     {STATUS_SET_JOB_CONTROL, L"job-control"},
     {STATUS_LINE_NUMBER, L"line-number"},
+    {STATUS_LOG, L"log"},
+    {STATUS_NOTIFY_READY, L"notify-ready"},
+    {STATUS_NOTIFY_WATCHDOG, L"notify-watchdog"},
+    {STATUS_PIPESTATUS, L"pipestatus"},
     {STATUS_STACK_TRACE, L"print-stack-trace"},
+    {STATUS_SPAWN_STATS, L"spawn-stats"},
     {STATUS_STACK_TRACE, L"stack-trace"},
     {STATUS_TEST_FEATURE, L"test-feature"},
     {STATUS_UNDEF, nullptr}};
@@ -101,6 +132,9 @@ struct status_cmd_opts_t {
     const wchar_t *feature_name{};
     status_cmd_t status_cmd{STATUS_UNDEF};
     bool print_help{false};
+    bool json{false};
+    bool pretty{false};
+    maybe_t<bool> follow_events{};
 };
 
 /// Note: Do not add new flags that represent subcommands. We're encouraging people to switch to
@@ -114,6 +148,7 @@ static const struct woption long_options[] = {
     {L"current-line-number", no_argument, nullptr, 'n'},
     {L"filename", no_argument, nullptr, 'f'},
     {L"fish-path", no_argument, nullptr, STATUS_FISH_PATH},
+    {L"follow", no_argument, nullptr, 'F'},
     {L"is-block", no_argument, nullptr, 'b'},
     {L"is-command-substitution", no_argument, nullptr, 'c'},
     {L"is-full-job-control", no_argument, nullptr, STATUS_IS_FULL_JOB_CTRL},
@@ -121,10 +156,13 @@ static const struct woption long_options[] = {
     {L"is-interactive-job-control", no_argument, nullptr, STATUS_IS_INTERACTIVE_JOB_CTRL},
     {L"is-login", no_argument, nullptr, 'l'},
     {L"is-no-job-control", no_argument, nullptr, STATUS_IS_NO_JOB_CTRL},
+    {L"json", no_argument, nullptr, 'J'},
     {L"job-control", required_argument, nullptr, 'j'},
     {L"level", required_argument, nullptr, 'L'},
     {L"line", no_argument, nullptr, 'n'},
     {L"line-number", no_argument, nullptr, 'n'},
+    {L"no-follow", no_argument, nullptr, 'N'},
+    {L"pretty", no_argument, nullptr, 'P'},
     {L"print-stack-trace", no_argument, nullptr, 't'},
     {nullptr, 0, nullptr, 0}};
 
@@ -155,6 +193,265 @@ static void print_features(io_streams_t &streams) {
     }
 }
 
+/// Print the features as a JSON array of objects, for tools.
+static void print_features_json(io_streams_t &streams) {
+    wcstring out = L"[";
+    for (const auto &md : features_t::metadata) {
+        if (out.size() > 1) out.push_back(L',');
+        out.append(L"{\"name\":");
+        out.append(json_string(md.name));
+        out.append(feature_test(md.flag) ? L",\"enabled\":true" : L",\"enabled\":false");
+        out.append(L",\"groups\":[");
+        bool first = true;
+        for (const wcstring &group : split_string(md.groups, L',')) {
+            if (!first) out.push_back(L',');
+            first = false;
+            out.append(json_string(group));
+        }
+        out.append(L"],\"description\":");
+        out.append(json_string(md.description));
+        out.append(md.runtime ? L",\"runtime\":true}" : L",\"runtime\":false}");
+    }
+    out.append(L"]\n");
+    streams.out.append(out);
+}
+
+/// Print the fds fish has open, with what they are used for, for finding leaked ones.
+static void print_fds(io_streams_t &streams, bool json) {
+    wcstring out = json ? L"[" : L"";
+    for (const open_fd_t &fd : fd_list_open()) {
+        if (!json) {
+            append_format(out, L"%d\t%ls\t%ls\t%ls\t%ls\n", fd.fd, fd.type,
+                          fd.cloexec ? L"cloexec" : L"inherited", fd.purpose.c_str(),
+                          fd.target.c_str());
+            continue;
+        }
+        if (out.size() > 1) out.push_back(L',');
+        append_format(out, L"{\"fd\":%d,\"type\":%ls,\"inherited\":%ls,\"purpose\":", fd.fd,
+                      json_string(fd.type).c_str(), fd.cloexec ? L"false" : L"true");
+        out.append(fd.purpose.empty() ? L"null" : json_string(fd.purpose));
+        out.append(L",\"target\":");
+        out.append(fd.target.empty() ? L"null" : json_string(fd.target));
+        out.push_back(L'}');
+    }
+    if (json) out.append(L"]\n");
+    streams.out.append(out);
+}
+
+/// Handle `status features enable` and `disable`, which change flags for the rest of the session.
+static int set_features(const wchar_t *cmd, const wcstring_list_t &args, io_streams_t &streams) {
+    const wcstring &action = args.front();
+    if (action != L"enable" && action != L"disable") {
+        streams.err.append_format(_(L"%ls: Invalid features action '%ls'\n"), cmd,
+                                  action.c_str());
+        return STATUS_INVALID_ARGS;
+    }
+    if (args.size() < 2) {
+        streams.err.append_format(BUILTIN_ERR_MIN_ARG_COUNT1, cmd, 2,
+                                  static_cast<int>(args.size()));
+        return STATUS_INVALID_ARGS;
+    }
+    int retval = STATUS_CMD_OK;
+    for (size_t i = 1; i < args.size(); i++) {
+        const wchar_t *name = args.at(i).c_str();
+        if (mutable_fish_features().set_at_runtime(name, action == L"enable")) continue;
+        if (!features_t::metadata_for(name)) {
+            streams.err.append_format(_(L"%ls: Unknown feature '%ls'\n"), cmd, name);
+        } else {
+            streams.err.append_format(
+                _(L"%ls: Feature '%ls' can only be set at startup, with fish --features or "
+                  L"$fish_features\n"),
+                cmd, name);
+        }
+        retval = STATUS_CMD_ERROR;
+    }
+    return retval;
+}
+
+/// Send \p message to the service manager which started fish, using the sd_notify protocol: a
+/// datagram to the Unix-domain socket named by $NOTIFY_SOCKET.
+/// \return 0 if it was sent, 1 if there is no service manager to notify, or 2 on error.
+static int notify_service_manager(const wchar_t *cmd, const parser_t &parser,
+                                  const wcstring &message, io_streams_t &streams) {
+    auto socket_var = parser.vars().get(L"NOTIFY_SOCKET");
+    if (socket_var.missing_or_empty()) return 1;
+    std::string path = wcs2string(socket_var->as_string());
+
+    struct sockaddr_un addr = {};
+    addr.sun_family = AF_UNIX;
+    if ((path[0] != '/' && path[0] != '@') || path.size() > sizeof addr.sun_path) {
+        streams.err.append_format(_(L"%ls: Invalid NOTIFY_SOCKET '%ls'\n"), cmd,
+                                  socket_var->as_string().c_str());
+        return 2;
+    }
+    std::memcpy(addr.sun_path, path.data(), path.size());
+    // A leading @ means the socket is in the abstract namespace.
+    if (path[0] == '@') addr.sun_path[0] = '\0';
+    socklen_t addr_len = offsetof(struct sockaddr_un, sun_path) + path.size();
+
+    autoclose_fd_t fd(socket(AF_UNIX, SOCK_DGRAM, 0));
+    std::string narrow = wcs2string(message);
+    if (!fd.valid() || sendto(fd.fd(), narrow.data(), narrow.size(), 0,
+                              reinterpret_cast<struct sockaddr *>(&addr), addr_len) < 0) {
+        streams.err.append_format(_(L"%ls: Could not notify the service manager: %s\n"), cmd,
+                                  std::strerror(errno));
+        return 2;
+    }
+    return 0;
+}
+
+/// Print how many external commands were launched via posix_spawn and fork, and why.
+static void print_spawn_stats(io_streams_t &streams) {
+    spawn_stats_t stats = get_spawn_stats();
+    streams.out.append_format(_(L"posix_spawn: %lu\n"), stats.posix_spawn);
+    streams.out.append_format(_(L"fork: %lu\n"), stats.fork);
+    streams.out.append_format(_(L"  posix_spawn disabled: %lu\n"), stats.fork_disabled);
+    streams.out.append_format(_(L"  self-fd redirection: %lu\n"), stats.fork_self_fd_redirect);
+    streams.out.append_format(_(L"  foreground job control: %lu\n"),
+                              stats.fork_claims_terminal);
+    streams.out.append_format(_(L"  scheduling priority: %lu\n"), stats.fork_priority);
+    streams.out.append_format(_(L"  job cgroup: %lu\n"), stats.fork_cgroup);
+}
+
+/// The file that `status log output` or `status events --follow` last sent the debug output to.
+static FILE *s_log_file = nullptr;
+
+/// Send the debug output to \p target, which is "syslog", "stderr" or a file to append to.
+static int set_log_output(const wchar_t *cmd, const wcstring &target, io_streams_t &streams) {
+    FILE *new_file = nullptr;
+    if (target == L"syslog") {
+        set_flog_output_syslog();
+    } else if (target == L"stderr") {
+        set_flog_output_file(stderr);
+    } else {
+        int fd = wopen_cloexec(target, O_WRONLY | O_CREAT | O_APPEND, 0666);
+        if (fd < 0 || !(new_file = fdopen(fd, "a"))) {
+            streams.err.append_format(_(L"%ls: Could not open '%ls': %s\n"), cmd, target.c_str(),
+                                      std::strerror(errno));
+            if (fd >= 0) close(fd);
+            return STATUS_CMD_ERROR;
+        }
+        set_flog_output_file(new_file);
+    }
+    // Nothing logs to the old file once it has been replaced.
+    if (s_log_file) fclose(s_log_file);
+    s_log_file = new_file;
+    return STATUS_CMD_OK;
+}
+
+/// Handle `status log`: list the debug categories, turn them on and off, or choose where their
+/// messages go.
+static int status_log(const wchar_t *cmd, const wcstring_list_t &args, io_streams_t &streams) {
+    if (args.empty()) {
+        for (const auto *cat : get_flog_categories()) {
+            streams.out.append_format(L"%ls\t%s\t%ls\n", cat->name, cat->enabled ? "on" : "off",
+                                      cat->description);
+        }
+        return STATUS_CMD_OK;
+    }
+
+    const wcstring &action = args.front();
+    if (action == L"enable" || action == L"disable") {
+        if (args.size() < 2) {
+            streams.err.append_format(BUILTIN_ERR_ARG_COUNT2, cmd, L"log", 2,
+                                      static_cast<int>(args.size()));
+            return STATUS_INVALID_ARGS;
+        }
+        wcstring_list_t unmatched;
+        for (size_t i = 1; i < args.size(); i++) {
+            for (const wcstring &pattern : split_string(args.at(i), L',')) {
+                activate_flog_categories_by_pattern(
+                    action == L"disable" ? L"-" + pattern : pattern, &unmatched);
+            }
+        }
+        for (const wcstring &name : unmatched) {
+            streams.err.append_format(_(L"%ls: Unknown debug category '%ls'\n"), cmd,
+                                      name.c_str());
+        }
+        return unmatched.empty() ? STATUS_CMD_OK : STATUS_CMD_ERROR;
+    } else if (action == L"output") {
+        if (args.size() != 2) {
+            streams.err.append_format(BUILTIN_ERR_ARG_COUNT2, cmd, L"log", 2,
+                                      static_cast<int>(args.size()));
+            return STATUS_INVALID_ARGS;
+        }
+        return set_log_output(cmd, args.at(1), streams);
+    }
+
+    streams.err.append_format(_(L"%ls: Invalid log action '%ls'\n"), cmd, action.c_str());
+    return STATUS_INVALID_ARGS;
+}
+
+/// Handle `status events`: start or stop tracing every event dispatch, or report whether that is
+/// happening.
+static int status_events(const wchar_t *cmd, maybe_t<bool> follow, const wcstring_list_t &args,
+                         io_streams_t &streams) {
+    auto &trace = flog_details::category_list_t::g_instance->event_trace;
+    size_t max_args = follow && *follow ? 1 : 0;
+    if (args.size() > max_args) {
+        streams.err.append_format(BUILTIN_ERR_ARG_COUNT2, cmd, L"events",
+                                  static_cast<int>(max_args), static_cast<int>(args.size()));
+        return STATUS_INVALID_ARGS;
+    }
+    if (!follow) return trace.enabled ? STATUS_CMD_OK : STATUS_CMD_ERROR;
+
+    if (*follow && !args.empty()) {
+        int ret = set_log_output(cmd, args.front(), streams);
+        if (ret != STATUS_CMD_OK) return ret;
+    }
+    trace.enabled = *follow;
+    return STATUS_CMD_OK;
+}
+
+/// \return how a stage of a pipeline which returned \p status is shown: the name of the signal that
+/// killed it, like SIGPIPE for 141, or else the number.
+static wcstring pipestatus_stage_name(int status) {
+    int sig = status - 128;
+    if (sig > 0 && contains(signal_list(), sig)) return sig2wcs(sig);
+    return to_string(status);
+}
+
+/// Handle `status pipestatus`: print the statuses of the stages of the last pipeline, or the given
+/// ones, separated by "|". With \p pretty, the failed stages are colored with $fish_color_status,
+/// and those killed by a signal are bold. SIGPIPE is usually not a failure (#6375), so a stage
+/// killed by it is not colored. \return whether no stage failed.
+static int status_pipestatus(const wchar_t *cmd, const wcstring_list_t &args, bool pretty,
+                             const parser_t &parser, io_streams_t &streams) {
+    std::vector<int> statuses;
+    if (args.empty()) {
+        statuses = parser.get_last_statuses().pipestatus;
+    }
+    for (const wcstring &arg : args) {
+        int status = fish_wcstoi(arg.c_str());
+        if (errno || status < 0) {
+            streams.err.append_format(BUILTIN_ERR_NOT_NUMBER, cmd, arg.c_str());
+            return STATUS_INVALID_ARGS;
+        }
+        statuses.push_back(status);
+    }
+
+    rgb_color_t fail_color{L"red"};
+    if (auto var = parser.vars().get(L"fish_color_status")) fail_color = parse_color(*var, false);
+    outputter_t outp;
+    bool failed = false;
+    for (size_t i = 0; i < statuses.size(); i++) {
+        int status = statuses.at(i);
+        bool stage_failed = status != 0 && status != 128 + SIGPIPE;
+        failed = failed || stage_failed;
+        if (i > 0) outp.writestr(L"|");
+        if (pretty && stage_failed) {
+            rgb_color_t color = fail_color;
+            if (status > 128 && contains(signal_list(), status - 128)) color.set_bold(true);
+            outp.set_color(color, rgb_color_t::normal());
+        }
+        outp.writestr(pipestatus_stage_name(status));
+        if (pretty && stage_failed) outp.set_color(rgb_color_t::normal(), rgb_color_t::normal());
+    }
+    outp.writestr(L"\n");
+    streams.out.append(str2wcstring(outp.contents()));
+    return failed ? STATUS_CMD_ERROR : STATUS_CMD_OK;
+}
+
 static int parse_cmd_opts(status_cmd_opts_t &opts, int *optind,  //!OCLINT(high ncss method)
                           int argc, wchar_t **argv, parser_t &parser, io_streams_t &streams) {
     wchar_t *cmd = argv[0];
@@ -255,6 +552,22 @@ static int parse_cmd_opts(status_cmd_opts_t &opts, int *optind,  //!OCLINT(high
                 opts.print_help = true;
                 break;
             }
+            case 'J': {
+                opts.json = true;
+                break;
+            }
+            case 'P': {
+                opts.pretty = true;
+                break;
+            }
+            case 'F': {
+                opts.follow_events = true;
+                break;
+            }
+            case 'N': {
+                opts.follow_events = false;
+                break;
+            }
             case ':': {
                 builtin_missing_argument(parser, streams, cmd, argv[w.woptind - 1]);
                 return STATUS_INVALID_ARGS;
@@ -306,6 +619,26 @@ maybe_t<int> builtin_status(parser_t &parser, io_streams_t &streams, wchar_t **a
     // Every argument that we haven't consumed already is an argument for a subcommand.
     const wcstring_list_t args(argv + optind, argv + argc);
 
+    if (opts.json && opts.status_cmd != STATUS_FEATURES && opts.status_cmd != STATUS_STACK_TRACE &&
+        opts.status_cmd != STATUS_FDS) {
+        streams.err.append_format(
+            BUILTIN_ERR_COMBO2, cmd,
+            _(L"--json can only be used with 'features', 'stack-trace' and 'fds'"));
+        return STATUS_INVALID_ARGS;
+    }
+
+    if (opts.pretty && opts.status_cmd != STATUS_PIPESTATUS) {
+        streams.err.append_format(BUILTIN_ERR_COMBO2, cmd,
+                                  _(L"--pretty can only be used with 'pipestatus'"));
+        return STATUS_INVALID_ARGS;
+    }
+
+    if (opts.follow_events && opts.status_cmd != STATUS_EVENTS) {
+        streams.err.append_format(BUILTIN_ERR_COMBO2, cmd,
+                                  _(L"--follow and --no-follow can only be used with 'events'"));
+        return STATUS_INVALID_ARGS;
+    }
+
     switch (opts.status_cmd) {
         case STATUS_UNDEF: {
             CHECK_FOR_UNEXPECTED_STATUS_ARGS(opts.status_cmd)
@@ -346,7 +679,56 @@ maybe_t<int> builtin_status(parser_t &parser, io_streams_t &streams, wchar_t **a
             break;
         }
         case STATUS_FEATURES: {
-            print_features(streams);
+            if (!args.empty()) {
+                retval = set_features(cmd, args, streams);
+            } else if (opts.json) {
+                print_features_json(streams);
+            } else {
+                print_features(streams);
+            }
+            break;
+        }
+        case STATUS_FDS: {
+            CHECK_FOR_UNEXPECTED_STATUS_ARGS(opts.status_cmd)
+            print_fds(streams, opts.json);
+            break;
+        }
+        case STATUS_EVENTS: {
+            retval = status_events(cmd, opts.follow_events, args, streams);
+            break;
+        }
+        case STATUS_LOG: {
+            retval = status_log(cmd, args, streams);
+            break;
+        }
+        case STATUS_NOTIFY_READY: {
+            if (args.size() > 1) {
+                const wchar_t *subcmd_str = enum_to_str(opts.status_cmd, status_enum_map);
+                streams.err.append_format(BUILTIN_ERR_ARG_COUNT2, cmd, subcmd_str, 1,
+                                          static_cast<int>(args.size()));
+                return STATUS_INVALID_ARGS;
+            }
+            wcstring message = L"READY=1";
+            // The service manager shows the status text, which must be a single line.
+            if (!args.empty()) {
+                const wcstring &text = args.front();
+                message += L"\nSTATUS=" + text.substr(0, text.find(L'\n'));
+            }
+            retval = notify_service_manager(cmd, parser, message, streams);
+            break;
+        }
+        case STATUS_NOTIFY_WATCHDOG: {
+            CHECK_FOR_UNEXPECTED_STATUS_ARGS(opts.status_cmd)
+            retval = notify_service_manager(cmd, parser, L"WATCHDOG=1", streams);
+            break;
+        }
+        case STATUS_PIPESTATUS: {
+            retval = status_pipestatus(cmd, args, opts.pretty, parser, streams);
+            break;
+        }
+        case STATUS_SPAWN_STATS: {
+            CHECK_FOR_UNEXPECTED_STATUS_ARGS(opts.status_cmd)
+            print_spawn_stats(streams);
             break;
         }
         case STATUS_TEST_FEATURE: {
@@ -437,7 +819,7 @@ maybe_t<int> builtin_status(parser_t &parser, io_streams_t &streams, wchar_t **a
         }
         case STATUS_STACK_TRACE: {
             CHECK_FOR_UNEXPECTED_STATUS_ARGS(opts.status_cmd)
-            streams.out.append(parser.stack_trace());
+            streams.out.append(opts.json ? parser.stack_trace_json() : parser.stack_trace());
             break;
         }
         case STATUS_CURRENT_CMD: {
@@ -1,10 +1,6 @@
 // Implementation of the string builtin.
 #include "config.h"  // IWYU pragma: keep
 
-#define PCRE2_CODE_UNIT_WIDTH WCHAR_T_BITS
-#ifdef _WIN32
-#define PCRE2_STATIC
-#endif
 #include <algorithm>
 #include <cerrno>
 #include <climits>
@@ -25,12 +21,14 @@
 #include "builtin.h"
 #include "common.h"
 #include "env.h"
+#include "exec.h"
 #include "fallback.h"  // IWYU pragma: keep
+#include "function.h"
 #include "future_feature_flags.h"
 #include "io.h"
 #include "parse_util.h"
 #include "parser.h"
-#include "pcre2.h"
+#include "regex_cache.h"
 #include "wcstringutil.h"
 #include "wgetopt.h"
 #include "wildcard.h"
@@ -51,8 +49,8 @@ static void string_error(io_streams_t &streams, const wchar_t *fmt, ...) {
 
 static void string_unknown_option(parser_t &parser, io_streams_t &streams, const wchar_t *subcmd,
                                   const wchar_t *opt) {
-    string_error(streams, BUILTIN_ERR_UNKNOWN, subcmd, opt);
-    builtin_print_error_trailer(parser, streams.err, L"string");
+    builtin_print_error(parser, streams, L"string",
+                        L"string " + format_string(BUILTIN_ERR_UNKNOWN, subcmd, opt));
 }
 
 // We read from stdin if we are the second or later process in a pipeline.
@@ -160,6 +158,7 @@ struct options_t {  //!OCLINT(too many fields)
     bool fields_valid = false;
     bool allow_empty_valid = false;
     bool width_valid = false;
+    bool function_valid = false;
 
     bool all = false;
     bool entire = false;
@@ -191,6 +190,7 @@ struct options_t {  //!OCLINT(too many fields)
     const wchar_t *chars_to_trim = L" \f\n\r\t\v";
     const wchar_t *arg1 = nullptr;
     const wchar_t *arg2 = nullptr;
+    const wchar_t *function = nullptr;
 
     escape_string_style_t escape_style = STRING_STYLE_SCRIPT;
 };
@@ -220,6 +220,17 @@ static int handle_flag_1(wchar_t **argv, parser_t &parser, io_streams_t &streams
     return STATUS_INVALID_ARGS;
 }
 
+/// This handles the `--function=xxx` flag.
+static int handle_flag_2(wchar_t **argv, parser_t &parser, io_streams_t &streams,
+                         const wgetopter_t &w, options_t *opts) {
+    if (opts->function_valid) {
+        opts->function = w.woptarg;
+        return STATUS_CMD_OK;
+    }
+    string_unknown_option(parser, streams, argv[0], argv[w.woptind - 1]);
+    return STATUS_INVALID_ARGS;
+}
+
 static int handle_flag_N(wchar_t **argv, parser_t &parser, io_streams_t &streams,
                          const wgetopter_t &w, options_t *opts) {
     if (opts->no_newline_valid) {
@@ -525,6 +536,7 @@ static const struct woption long_options[] = {{L"all", no_argument, nullptr, 'a'
                                               {L"entire", no_argument, nullptr, 'e'},
                                               {L"end", required_argument, nullptr, 'e'},
                                               {L"filter", no_argument, nullptr, 'f'},
+                                              {L"function", required_argument, nullptr, 2},
                                               {L"ignore-case", no_argument, nullptr, 'i'},
                                               {L"index", no_argument, nullptr, 'n'},
                                               {L"invert", no_argument, nullptr, 'v'},
@@ -549,7 +561,7 @@ static const std::unordered_map<char, decltype(*handle_flag_N)> flag_to_function
     {'N', handle_flag_N}, {'a', handle_flag_a}, {'c', handle_flag_c}, {'e', handle_flag_e},
     {'f', handle_flag_f}, {'i', handle_flag_i}, {'l', handle_flag_l}, {'m', handle_flag_m},
     {'n', handle_flag_n}, {'q', handle_flag_q}, {'r', handle_flag_r}, {'s', handle_flag_s},
-    {'v', handle_flag_v}, {'w', handle_flag_w}, {1, handle_flag_1}};
+    {'v', handle_flag_v}, {'w', handle_flag_w}, {1, handle_flag_1}, {2, handle_flag_2}};
 
 /// Parse the arguments for flags recognized by a specific string subcommand.
 static int parse_opts(options_t *opts, int *optind, int n_req_args, int argc, wchar_t **argv,
@@ -579,6 +591,9 @@ static int parse_opts(options_t *opts, int *optind, int n_req_args, int argc, wc
 
     *optind = w.woptind;
 
+    // The function of `string replace --function` takes the place of the replacement.
+    if (opts->function) n_req_args--;
+
     // If the caller requires one or two mandatory args deal with that here.
     if (n_req_args) {
         opts->arg1 = string_get_arg_argv(optind, argv);
@@ -793,7 +808,8 @@ static wcstring pcre2_strerror(int err_code) {
 }
 
 struct compiled_regex_t {
-    pcre2_code *code;
+    regex_code_ref_t code_ref;
+    const pcre2_code *code;
     pcre2_match_data *match;
 
     compiled_regex_t(const wchar_t *argv0, const wcstring &pattern, bool ignore_case,
@@ -805,17 +821,15 @@ struct compiled_regex_t {
         options |= PCRE2_NEVER_BACKSLASH_C;
 #endif
 
-        int err_code = 0;
-        PCRE2_SIZE err_offset = 0;
-
-        code = pcre2_compile(PCRE2_SPTR(pattern.c_str()), pattern.length(),
-                             options | (ignore_case ? PCRE2_CASELESS : 0), &err_code, &err_offset,
-                             nullptr);
+        regex_compile_error_t error;
+        code_ref = regex_compile_cached(pattern, options | (ignore_case ? PCRE2_CASELESS : 0),
+                                        &error);
+        code = code_ref.get();
         if (code == nullptr) {
             string_error(streams, _(L"%ls: Regular expression compile error: %ls\n"), argv0,
-                         pcre2_strerror(err_code).c_str());
+                         pcre2_strerror(error.code).c_str());
             string_error(streams, L"%ls: %ls\n", argv0, pattern.c_str());
-            string_error(streams, L"%ls: %*ls\n", argv0, err_offset, L"^");
+            string_error(streams, L"%ls: %*ls\n", argv0, error.offset, L"^");
             return;
         }
 
@@ -823,10 +837,7 @@ struct compiled_regex_t {
         assert(match);
     }
 
-    ~compiled_regex_t() {
-        pcre2_match_data_free(match);
-        pcre2_code_free(code);
-    }
+    ~compiled_regex_t() { pcre2_match_data_free(match); }
 };
 
 class pcre2_matcher_t : public string_matcher_t {
@@ -1281,6 +1292,130 @@ class regex_replacer_t : public string_replacer_t {
     bool replace_matches(const wcstring &arg) override;
 };
 
+/// Replaces matches with the output of a function, which gets the match and its capturing groups
+/// as arguments.
+class function_replacer_t : public string_replacer_t {
+    const wcstring pattern;
+    const wcstring function;
+    parser_t &parser;
+    // Only compiled with --regex.
+    std::unique_ptr<compiled_regex_t> regex;
+
+    /// Find the first match in \p arg at or after \p start. On success, set \p begin and \p end to
+    /// where it is, and \p groups to the match and its capturing groups.
+    /// \return whether a match was found, or none on error.
+    maybe_t<bool> find_match(const wcstring &arg, size_t start, size_t *begin, size_t *end,
+                             wcstring_list_t *groups);
+
+   public:
+    function_replacer_t(const wchar_t *argv0, wcstring pattern_, const wchar_t *function_,
+                        const options_t &opts, io_streams_t &streams, parser_t &parser_)
+        : string_replacer_t(argv0, opts, streams),
+          pattern(std::move(pattern_)),
+          function(function_),
+          parser(parser_) {
+        if (opts.regex) {
+            regex = make_unique<compiled_regex_t>(argv0, pattern, opts.ignore_case, streams);
+        }
+    }
+
+    bool replace_matches(const wcstring &arg) override;
+};
+
+maybe_t<bool> function_replacer_t::find_match(const wcstring &arg, size_t start, size_t *begin,
+                                              size_t *end, wcstring_list_t *groups) {
+    groups->clear();
+    if (!regex) {
+        auto &cmp_func = opts.ignore_case ? wcsncasecmp : std::wcsncmp;
+        for (size_t pos = start; pos + pattern.size() <= arg.size(); pos++) {
+            if (cmp_func(arg.c_str() + pos, pattern.c_str(), pattern.size()) == 0) {
+                *begin = pos;
+                *end = pos + pattern.size();
+                groups->push_back(arg.substr(pos, pattern.size()));
+                return true;
+            }
+        }
+        return false;
+    }
+
+    int pcre2_rc = pcre2_match(regex->code, PCRE2_SPTR(arg.c_str()), arg.size(), start, 0,
+                               regex->match, nullptr);
+    if (pcre2_rc == PCRE2_ERROR_NOMATCH) return false;
+    if (pcre2_rc < 0) {
+        string_error(streams, _(L"%ls: Regular expression match error: %ls\n"), argv0,
+                     pcre2_strerror(pcre2_rc).c_str());
+        return none();
+    }
+    // Groups which did not take part in the match are passed as empty arguments, so each group is
+    // always at the same index of $argv.
+    PCRE2_SIZE *ovector = pcre2_get_ovector_pointer(regex->match);
+    uint32_t count = pcre2_get_ovector_count(regex->match);
+    for (uint32_t j = 0; j < count; j++) {
+        PCRE2_SIZE group_begin = ovector[2 * j];
+        PCRE2_SIZE group_end = ovector[2 * j + 1];
+        bool is_set = static_cast<int>(j) < pcre2_rc && group_begin != PCRE2_UNSET;
+        if (is_set && group_end > group_begin) {
+            groups->push_back(arg.substr(group_begin, group_end - group_begin));
+        } else {
+            groups->push_back(wcstring{});
+        }
+    }
+    *begin = ovector[0];
+    // May have end < begin if \K is used.
+    *end = std::max(ovector[0], ovector[1]);
+    return true;
+}
+
+/// A return value of true means all is well (even if no replacements were performed), false
+/// indicates an unrecoverable error.
+bool function_replacer_t::replace_matches(const wcstring &arg) {
+    if (regex && !regex->code) return false;  // pcre2_compile() failed
+
+    wcstring result;
+    bool replacement_occurred = false;
+    if (!regex && pattern.empty()) {
+        // Like a literal replacement, an empty pattern leaves the string alone.
+        replacement_occurred = true;
+        result = arg;
+    } else {
+        // The part of arg which was copied to the result so far, and where to look for matches.
+        size_t copied = 0;
+        size_t start = 0;
+        size_t begin, end;
+        wcstring_list_t groups;
+        while ((opts.all || !replacement_occurred) && start <= arg.size()) {
+            maybe_t<bool> found = find_match(arg, start, &begin, &end, &groups);
+            if (!found) return false;
+            if (!*found) break;
+
+            wcstring cmd = escape_string(function, ESCAPE_ALL);
+            for (const wcstring &group : groups) {
+                cmd.push_back(L' ');
+                cmd.append(escape_string(group, ESCAPE_ALL));
+            }
+            wcstring_list_t outputs;
+            exec_subshell(cmd, parser, outputs, false);
+
+            result.append(arg, copied, begin - copied);
+            result.append(join_strings(outputs, L'\n'));
+            copied = end;
+            replacement_occurred = true;
+            total_replaced++;
+
+            // Step over an empty match, so the next one is not at the same place.
+            start = end > begin ? end : end + 1;
+        }
+        result.append(arg, copied, wcstring::npos);
+    }
+
+    if (!opts.quiet && (!opts.filter || replacement_occurred)) {
+        streams.out.append(result);
+        streams.out.append(L'\n');
+    }
+
+    return true;
+}
+
 /// A return value of true means all is well (even if no replacements were performed), false
 /// indicates an unrecoverable error.
 bool literal_replacer_t::replace_matches(const wcstring &arg) {
@@ -1382,6 +1517,7 @@ static int string_replace(parser_t &parser, io_streams_t &streams, int argc, wch
     opts.ignore_case_valid = true;
     opts.quiet_valid = true;
     opts.regex_valid = true;
+    opts.function_valid = true;
     int optind;
     int retval = parse_opts(&opts, &optind, 2, argc, argv, parser, streams);
     if (retval != STATUS_CMD_OK) return retval;
@@ -1390,7 +1526,14 @@ static int string_replace(parser_t &parser, io_streams_t &streams, int argc, wch
     const wchar_t *replacement = opts.arg2;
 
     std::unique_ptr<string_replacer_t> replacer;
-    if (opts.regex) {
+    if (opts.function) {
+        if (!function_exists(opts.function, parser)) {
+            string_error(streams, _(L"%ls: Unknown function '%ls'\n"), argv[0], opts.function);
+            return STATUS_INVALID_ARGS;
+        }
+        replacer = make_unique<function_replacer_t>(argv[0], pattern, opts.function, opts,
+                                                    streams, parser);
+    } else if (opts.regex) {
         replacer = make_unique<regex_replacer_t>(argv[0], pattern, replacement, opts, streams);
     } else {
         replacer = make_unique<literal_replacer_t>(argv[0], pattern, replacement, opts, streams);
@@ -1756,8 +1899,8 @@ maybe_t<int> builtin_string(parser_t &parser, io_streams_t &streams, wchar_t **a
     wchar_t *cmd = argv[0];
     int argc = builtin_count_args(argv);
     if (argc <= 1) {
-        streams.err.append_format(BUILTIN_ERR_MISSING_SUBCMD, cmd);
-        builtin_print_error_trailer(parser, streams.err, L"string");
+        builtin_print_error(parser, streams, L"string",
+                            format_string(BUILTIN_ERR_MISSING_SUBCMD, cmd));
         return STATUS_INVALID_ARGS;
     }
 
@@ -1779,8 +1922,8 @@ maybe_t<int> builtin_string(parser_t &parser, io_streams_t &streams, wchar_t **a
     if (binsearch != end && wcscmp(subcmd_name, binsearch->name) == 0) subcmd = &*binsearch;
 
     if (subcmd == nullptr) {
-        streams.err.append_format(BUILTIN_ERR_INVALID_SUBCMD, cmd, subcmd_name);
-        builtin_print_error_trailer(parser, streams.err, L"string");
+        builtin_print_error(parser, streams, L"string",
+                            format_string(BUILTIN_ERR_INVALID_SUBCMD, cmd, subcmd_name));
         return STATUS_INVALID_ARGS;
     }
 
@@ -875,8 +875,8 @@ maybe_t<int> builtin_test(parser_t &parser, io_streams_t &streams, wchar_t **arg
             // Ignore the closing bracket from now on.
             argc--;
         } else {
-            streams.err.append(L"[: the last argument must be ']'\n");
-            builtin_print_error_trailer(parser, streams.err, program_name);
+            builtin_print_error(parser, streams, program_name,
+                                L"[: the last argument must be ']'\n");
             return STATUS_INVALID_ARGS;
         }
     }
@@ -0,0 +1,167 @@
+// Implementation of the timeout builtin.
+#include "config.h"  // IWYU pragma: keep
+
+#include "builtin_timeout.h"
+
+#include <errno.h>
+#include <signal.h>
+
+#include <cmath>
+#include <memory>
+
+#include "builtin.h"
+#include "builtin_eval.h"
+#include "common.h"
+#include "fallback.h"  // IWYU pragma: keep
+#include "io.h"
+#include "parser.h"
+#include "proc.h"
+#include "signal.h"
+#include "wgetopt.h"
+#include "wutil.h"  // IWYU pragma: keep
+
+/// The status of a command which timed out, as with GNU timeout.
+#define STATUS_TIMED_OUT 124
+
+struct timeout_cmd_opts_t {
+    bool print_help = false;
+    bool preserve_status = false;
+    int signal = SIGTERM;
+    const wchar_t *kill_after = nullptr;
+};
+
+static const wchar_t *const short_options = L"+:s:k:h";
+static const struct woption long_options[] = {{L"signal", required_argument, nullptr, 's'},
+                                              {L"kill-after", required_argument, nullptr, 'k'},
+                                              {L"preserve-status", no_argument, nullptr, 1},
+                                              {L"help", no_argument, nullptr, 'h'},
+                                              {nullptr, 0, nullptr, 0}};
+
+static int parse_cmd_opts(timeout_cmd_opts_t &opts, int *optind, int argc, wchar_t **argv,
+                          parser_t &parser, io_streams_t &streams) {
+    const wchar_t *cmd = argv[0];
+    int opt;
+    wgetopter_t w;
+    while ((opt = w.wgetopt_long(argc, argv, short_options, long_options, nullptr)) != -1) {
+        switch (opt) {
+            case 's': {
+                opts.signal = wcs2sig(w.woptarg);
+                if (opts.signal <= 0) {
+                    streams.err.append_format(_(L"%ls: Unknown signal '%ls'\n"), cmd, w.woptarg);
+                    return STATUS_INVALID_ARGS;
+                }
+                break;
+            }
+            case 'k': {
+                opts.kill_after = w.woptarg;
+                break;
+            }
+            case 1: {
+                opts.preserve_status = true;
+                break;
+            }
+            case 'h': {
+                opts.print_help = true;
+                break;
+            }
+            case ':': {
+                builtin_missing_argument(parser, streams, cmd, argv[w.woptind - 1]);
+                return STATUS_INVALID_ARGS;
+            }
+            case '?': {
+                builtin_unknown_option(parser, streams, cmd, argv[w.woptind - 1]);
+                return STATUS_INVALID_ARGS;
+            }
+            default: {
+                DIE("unexpected retval from wgetopt_long");
+            }
+        }
+    }
+
+    *optind = w.woptind;
+    return STATUS_CMD_OK;
+}
+
+/// Parse a duration like "10", "1.5s", "2m", "1h" or "1d", in seconds.
+/// \return the duration, or none if it is invalid.
+static maybe_t<double> parse_duration(const wchar_t *str) {
+    wchar_t *end = nullptr;
+    errno = 0;
+    double result = fish_wcstod(str, &end);
+    if (errno || end == str || !std::isfinite(result) || result < 0) return none();
+    switch (*end) {
+        case L'\0':
+        case L's':
+            break;
+        case L'm':
+            result *= 60;
+            break;
+        case L'h':
+            result *= 60 * 60;
+            break;
+        case L'd':
+            result *= 24 * 60 * 60;
+            break;
+        default:
+            return none();
+    }
+    if (*end && end[1]) return none();
+    return result;
+}
+
+/// Run a command, and signal it if it is still running after a given time.
+maybe_t<int> builtin_timeout(parser_t &parser, io_streams_t &streams, wchar_t **argv) {
+    const wchar_t *cmd = argv[0];
+    timeout_cmd_opts_t opts;
+    int argc = builtin_count_args(argv);
+    int optind;
+
+    int retval = parse_cmd_opts(opts, &optind, argc, argv, parser, streams);
+    if (retval != STATUS_CMD_OK) return retval;
+
+    if (opts.print_help) {
+        builtin_print_help(parser, streams, cmd);
+        return STATUS_CMD_OK;
+    }
+
+    if (argc - optind < 2) {
+        streams.err.append_format(BUILTIN_ERR_MIN_ARG_COUNT1, cmd, 2, argc - optind);
+        return STATUS_INVALID_ARGS;
+    }
+    auto duration = parse_duration(argv[optind]);
+    if (!duration) {
+        streams.err.append_format(_(L"%ls: Invalid duration '%ls'\n"), cmd, argv[optind]);
+        return STATUS_INVALID_ARGS;
+    }
+    maybe_t<double> kill_after = 0.0;
+    if (opts.kill_after) kill_after = parse_duration(opts.kill_after);
+    if (!kill_after) {
+        streams.err.append_format(_(L"%ls: Invalid duration '%ls'\n"), cmd, opts.kill_after);
+        return STATUS_INVALID_ARGS;
+    }
+
+    wcstring command;
+    for (int i = optind + 1; i < argc; i++) {
+        if (!command.empty()) command.push_back(L' ');
+        command.append(escape_string(argv[i], ESCAPE_ALL));
+    }
+
+    // A duration of 0 means there is no timeout.
+    if (*duration == 0) return builtin_eval_in_streams(parser, streams, command);
+
+    auto timeout = job_timeout_t::start(job_timeout_t::duration_t(*duration), opts.signal,
+                                        job_timeout_t::duration_t(*kill_after));
+    if (!timeout) {
+        streams.err.append_format(_(L"%ls: Could not start the timer\n"), cmd);
+        return STATUS_CMD_ERROR;
+    }
+    auto &timeouts = parser.libdata().timeouts;
+    timeouts.push_back(timeout);
+    int status = builtin_eval_in_streams(parser, streams, command);
+    timeout->cancel();
+    assert(timeouts.back() == timeout && "timeouts should be removed in reverse order");
+    timeouts.pop_back();
+
+    if (timeout->expired() && !opts.preserve_status) return STATUS_TIMED_OUT;
+    return status;
+}
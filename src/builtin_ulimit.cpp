@@ -268,8 +268,8 @@ maybe_t<int> builtin_ulimit(parser_t &parser, io_streams_t &streams, wchar_t **a
         print(what, hard, streams);
         return STATUS_CMD_OK;
     } else if (arg_count != 1) {
-        streams.err.append_format(BUILTIN_ERR_TOO_MANY_ARGUMENTS, cmd);
-        builtin_print_error_trailer(parser, streams.err, cmd);
+        builtin_print_error(parser, streams, cmd,
+                            format_string(BUILTIN_ERR_TOO_MANY_ARGUMENTS, cmd));
         return STATUS_INVALID_ARGS;
     }
 
@@ -281,8 +281,8 @@ maybe_t<int> builtin_ulimit(parser_t &parser, io_streams_t &streams, wchar_t **a
 
     rlim_t new_limit;
     if (*argv[w.woptind] == L'\0') {
-        streams.err.append_format(_(L"%ls: New limit cannot be an empty string\n"), cmd);
-        builtin_print_error_trailer(parser, streams.err, cmd);
+        builtin_print_error(parser, streams, cmd,
+                            format_string(_(L"%ls: New limit cannot be an empty string\n"), cmd));
         return STATUS_INVALID_ARGS;
     } else if (wcscasecmp(argv[w.woptind], L"unlimited") == 0) {
         new_limit = RLIM_INFINITY;
@@ -293,8 +293,9 @@ maybe_t<int> builtin_ulimit(parser_t &parser, io_streams_t &streams, wchar_t **a
     } else {
         new_limit = fish_wcstol(argv[w.woptind]);
         if (errno) {
-            streams.err.append_format(_(L"%ls: Invalid limit '%ls'\n"), cmd, argv[w.woptind]);
-            builtin_print_error_trailer(parser, streams.err, cmd);
+            builtin_print_error(parser, streams, cmd,
+                                format_string(_(L"%ls: Invalid limit '%ls'\n"), cmd,
+                                              argv[w.woptind]));
             return STATUS_INVALID_ARGS;
         }
         new_limit *= get_multiplier(what);
@@ -0,0 +1,161 @@
+// Resource accounting for jobs via Linux control groups (cgroups v2).
+#include "config.h"  // IWYU pragma: keep
+
+#include "cgroup.h"
+
+#include <errno.h>
+#include <fcntl.h>
+#include <sys/stat.h>
+#include <unistd.h>
+
+#include <cstdlib>
+#include <cstring>
+
+#include "flog.h"
+#include "wcstringutil.h"
+#include "wutil.h"  // IWYU pragma: keep
+
+job_cgroup_t::job_cgroup_t(std::string dir, wcstring path, autoclose_fd_t procs_fd)
+    : dir_(std::move(dir)), path_(std::move(path)), procs_fd_(std::move(procs_fd)) {}
+
+#ifdef __linux__
+
+static bool write_cgroup_file(const std::string &path, const std::string &contents) {
+    autoclose_fd_t fd{open_cloexec(path, O_WRONLY)};
+    return fd.valid() && write_loop(fd.fd(), contents.data(), contents.size()) >= 0;
+}
+
+/// \return the value of the line starting with \p key in the contents of a file like cpu.stat,
+/// which has a key and a number on each line, or 0 if there is no such line.
+static uint64_t find_stat(const std::string &contents, const char *key) {
+    const std::string prefix = std::string(key) + " ";
+    for (size_t pos = 0; pos < contents.size();) {
+        size_t end = contents.find('\n', pos);
+        if (end == std::string::npos) end = contents.size();
+        if (contents.compare(pos, prefix.size(), prefix) == 0) {
+            return std::strtoull(contents.c_str() + pos + prefix.size(), nullptr, 10);
+        }
+        pos = end + 1;
+    }
+    return 0;
+}
+
+/// \return the value in a file with a single number, like memory.current.
+static maybe_t<uint64_t> read_number(const std::string &path) {
+    maybe_t<std::string> contents = read_file_contents(path);
+    if (!contents || contents->empty()) return none();
+    return std::strtoull(contents->c_str(), nullptr, 10);
+}
+
+/// Where in the cgroup hierarchy the cgroups for jobs are created.
+struct cgroup_parent_t {
+    /// The directory the cgroup2 hierarchy is mounted on.
+    std::string mount;
+    /// The path of the parent cgroup in the hierarchy, which is empty for its root.
+    std::string path;
+};
+
+/// Find fish's own cgroup, which the job cgroups are created next to.
+/// \return none if there is no cgroup2 hierarchy or fish may not create cgroups in it.
+static maybe_t<cgroup_parent_t> init_cgroup_parent() {
+    // Systems with both cgroup versions mount the cgroup2 hierarchy somewhere other than
+    // /sys/fs/cgroup, so look for it.
+    maybe_t<std::string> mounts = read_file_contents("/proc/self/mounts");
+    maybe_t<std::string> self = read_file_contents("/proc/self/cgroup");
+    if (!mounts || !self) return none();
+    cgroup_parent_t result;
+    for (const wcstring &line : split_string(str2wcstring(*mounts), L'\n')) {
+        wcstring_list_t fields = split_string(line, L' ');
+        if (fields.size() > 2 && fields.at(2) == L"cgroup2") {
+            result.mount = wcs2string(fields.at(1));
+            break;
+        }
+    }
+    // The line for cgroup2 is "0::" followed by the path.
+    bool found = false;
+    for (const wcstring &line : split_string(str2wcstring(*self), L'\n')) {
+        if (string_prefixes_string(L"0::", line)) {
+            result.path = wcs2string(line.substr(3));
+            found = true;
+            break;
+        }
+    }
+    if (result.mount.empty() || !found) return none();
+    if (result.path == "/") result.path.clear();
+
+    const std::string dir = result.mount + result.path;
+    if (access(dir.c_str(), W_OK) != 0) {
+        FLOGF(proc_cgroup, L"Cannot create cgroups in '%s'", dir.c_str());
+        return none();
+    }
+
+    // The memory controller can only be enabled for the children of a cgroup which has no
+    // processes of its own, so move fish into a cgroup of its own first. If this fails, only the
+    // CPU time of jobs is accounted for.
+    const std::string own = dir + "/fish-" + std::to_string(getpid());
+    if (mkdir(own.c_str(), 0755) == 0 || errno == EEXIST) {
+        if (!write_cgroup_file(own + "/cgroup.procs", "0")) rmdir(own.c_str());
+    }
+    maybe_t<std::string> controllers = read_file_contents(dir + "/cgroup.controllers");
+    if (controllers && controllers->find("memory") != std::string::npos &&
+        !write_cgroup_file(dir + "/cgroup.subtree_control", "+memory")) {
+        FLOGF(proc_cgroup, L"Could not enable the memory controller in '%s': %s", dir.c_str(),
+              std::strerror(errno));
+    }
+    return result;
+}
+
+std::shared_ptr<job_cgroup_t> job_cgroup_t::create() {
+    ASSERT_IS_MAIN_THREAD();
+    static const maybe_t<cgroup_parent_t> parent = init_cgroup_parent();
+    static unsigned long s_last_id = 0;
+    if (!parent) return nullptr;
+
+    std::string path = parent->path + "/fish-" + std::to_string(getpid()) + "-job-" +
+                       std::to_string(++s_last_id);
+    std::string dir = parent->mount + path;
+    if (mkdir(dir.c_str(), 0755) != 0) {
+        FLOGF(proc_cgroup, L"Could not create cgroup '%s': %s", dir.c_str(), std::strerror(errno));
+        return nullptr;
+    }
+    autoclose_fd_t procs_fd{open_cloexec(dir + "/cgroup.procs", O_WRONLY)};
+    if (procs_fd.valid()) procs_fd = heightenize_fd(std::move(procs_fd), true);
+    if (!procs_fd.valid()) {
+        rmdir(dir.c_str());
+        return nullptr;
+    }
+    fd_set_purpose(procs_fd.fd(), N_(L"job cgroup"));
+    FLOGF(proc_cgroup, L"Created cgroup '%s'", dir.c_str());
+    return std::shared_ptr<job_cgroup_t>(
+        new job_cgroup_t(std::move(dir), str2wcstring(path), std::move(procs_fd)));
+}
+
+maybe_t<cgroup_usage_t> job_cgroup_t::usage() const {
+    maybe_t<std::string> cpu_stat = read_file_contents(dir_ + "/cpu.stat");
+    if (!cpu_stat) return none();
+    cgroup_usage_t result;
+    result.usr_micros = find_stat(*cpu_stat, "user_usec");
+    result.sys_micros = find_stat(*cpu_stat, "system_usec");
+    result.memory_bytes = read_number(dir_ + "/memory.current");
+    result.memory_peak_bytes = read_number(dir_ + "/memory.peak");
+    return result;
+}
+
+job_cgroup_t::~job_cgroup_t() {
+    // This fails if processes of the job are still running, like daemons it started.
+    procs_fd_.close();
+    if (rmdir(dir_.c_str()) != 0) {
+        FLOGF(proc_cgroup, L"Could not remove cgroup '%s': %s", dir_.c_str(),
+              std::strerror(errno));
+    }
+}
+
+#else
+
+std::shared_ptr<job_cgroup_t> job_cgroup_t::create() { return nullptr; }
+
+maybe_t<cgroup_usage_t> job_cgroup_t::usage() const { return none(); }
+
+job_cgroup_t::~job_cgroup_t() = default;
+
+#endif
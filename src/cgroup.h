@@ -0,0 +1,61 @@
+// Resource accounting for jobs via Linux control groups (cgroups v2).
+#ifndef FISH_CGROUP_H
+#define FISH_CGROUP_H
+
+#include <stdint.h>
+
+#include <memory>
+#include <string>
+
+#include "common.h"
+#include "fds.h"
+#include "maybe.h"
+
+/// The resources used by the processes in a cgroup.
+struct cgroup_usage_t {
+    /// The CPU time spent in user and system mode, in microseconds.
+    uint64_t usr_micros{0};
+    uint64_t sys_micros{0};
+
+    /// The memory in use now, and the most that was in use at any time, in bytes. These are only
+    /// known if the memory controller is enabled for the cgroup, and the peak only on Linux 5.19
+    /// and later.
+    maybe_t<uint64_t> memory_bytes{};
+    maybe_t<uint64_t> memory_peak_bytes{};
+};
+
+/// A cgroup created for a job, so the resources used by all of its processes can be reported,
+/// including those of processes which are never waited for. The cgroup is removed when this is
+/// destroyed, unless processes are left in it.
+class job_cgroup_t {
+   public:
+    /// Create a cgroup for a new job.
+    /// \return null if cgroups v2 are not available, or fish is not allowed to create them.
+    static std::shared_ptr<job_cgroup_t> create();
+
+    /// \return the path of the cgroup in the hierarchy, as shown in /proc/PID/cgroup.
+    const wcstring &path() const { return path_; }
+
+    /// \return the fd of the cgroup's cgroup.procs file. A forked child joins the cgroup by writing
+    /// "0" to it.
+    int procs_fd() const { return procs_fd_.fd(); }
+
+    /// \return the resources used by the processes in the cgroup so far, or none if they can't be
+    /// read.
+    maybe_t<cgroup_usage_t> usage() const;
+
+    ~job_cgroup_t();
+
+    job_cgroup_t(const job_cgroup_t &) = delete;
+    void operator=(const job_cgroup_t &) = delete;
+
+   private:
+    job_cgroup_t(std::string dir, wcstring path, autoclose_fd_t procs_fd);
+
+    /// The directory of the cgroup in the mounted hierarchy.
+    const std::string dir_;
+    const wcstring path_;
+    autoclose_fd_t procs_fd_;
+};
+
+#endif
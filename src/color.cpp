@@ -4,14 +4,18 @@
 #include "color.h"
 
 #include <algorithm>
+#include <array>
+#include <cmath>
 #include <cstddef>
 #include <cstdint>
 #include <cstdlib>
 #include <cwchar>  // IWYU pragma: keep
 #include <cwctype>
+#include <limits>
 
 #include "common.h"
 #include "fallback.h"  // IWYU pragma: keep
+#include "wcstringutil.h"
 
 bool rgb_color_t::try_parse_special(const wcstring &special) {
     std::memset(&data, 0, sizeof data);
@@ -101,23 +105,47 @@ static int parse_hex_digit(wchar_t x) {
     }
 }
 
-static unsigned long squared_difference(long p1, long p2) {
-    auto diff = static_cast<unsigned long>(labs(p1 - p2));
-    return diff * diff;
+namespace {
+/// A color in the OKLab color space, where the distance between colors is close to how different
+/// they look. See https://bottosson.github.io/posts/oklab/
+struct oklab_t {
+    float l, a, b;
+};
+}  // namespace
+
+static float srgb_to_linear(unsigned char c) {
+    float x = c / 255.0f;
+    return x <= 0.04045f ? x / 12.92f : std::pow((x + 0.055f) / 1.055f, 2.4f);
+}
+
+static oklab_t oklab_from_rgb(uint32_t rgb) {
+    float r = srgb_to_linear((rgb >> 16) & 0xFF), g = srgb_to_linear((rgb >> 8) & 0xFF),
+          b = srgb_to_linear(rgb & 0xFF);
+    float l = std::cbrt(0.4122214708f * r + 0.5363325363f * g + 0.0514459929f * b);
+    float m = std::cbrt(0.2119034982f * r + 0.6806995451f * g + 0.1073969566f * b);
+    float s = std::cbrt(0.0883024619f * r + 0.2817188376f * g + 0.6299787005f * b);
+    return {0.2104542553f * l + 0.7936177850f * m - 0.0040720468f * s,
+            1.9779984951f * l - 2.4285922050f * m + 0.4505937099f * s,
+            0.0259040371f * l + 0.7827717662f * m - 0.8086757660f * s};
+}
+
+static uint32_t rgb_to_uint32(const unsigned char rgb[3]) {
+    return (static_cast<uint32_t>(rgb[0]) << 16) | (static_cast<uint32_t>(rgb[1]) << 8) | rgb[2];
 }
 
-static unsigned char convert_color(const unsigned char rgb[3], const uint32_t *colors,
+/// \return the index of the color in \p colors which looks the most like \p rgb.
+static unsigned char convert_color(const unsigned char rgb[3], const oklab_t *colors,
                                    size_t color_count) {
-    long r = rgb[0], g = rgb[1], b = rgb[2];
-    auto best_distance = static_cast<unsigned long>(-1);
+    const oklab_t lab = oklab_from_rgb(rgb_to_uint32(rgb));
+    float best_distance = std::numeric_limits<float>::max();
     auto best_index = static_cast<unsigned char>(-1);
     for (size_t idx = 0; idx < color_count; idx++) {
-        uint32_t color = colors[idx];
-        long test_r = (color >> 16) & 0xFF, test_g = (color >> 8) & 0xFF,
-             test_b = (color >> 0) & 0xFF;
-        unsigned long distance = squared_difference(r, test_r) + squared_difference(g, test_g) +
-                                 squared_difference(b, test_b);
-        if (distance <= best_distance) {
+        const oklab_t &test = colors[idx];
+        float dl = lab.l - test.l, da = lab.a - test.a, db = lab.b - test.b;
+        // Weigh differences in hue and colorfulness more than in lightness: a gray shown in a
+        // color is more jarring than one which is a bit too light or dark.
+        float distance = dl * dl + 2 * (da * da + db * db);
+        if (distance < best_distance) {
             best_index = idx;
             best_distance = distance;
         }
@@ -271,30 +299,51 @@ rgb_color_t rgb_color_t::white() { return rgb_color_t(type_named, 7); }
 
 rgb_color_t rgb_color_t::black() { return rgb_color_t(type_named, 0); }
 
+/// The colors which terminals commonly show for the 16 named colors.
+static const uint32_t kTerm16Palettes[][16] = {
+    // The default, which is roughly what most terminals use.
+    {0x000000, 0x800000, 0x008000, 0x808000, 0x000080, 0x800080, 0x008080, 0xc0c0c0, 0x808080,
+     0xff0000, 0x00ff00, 0xffff00, 0x0000ff, 0xff00ff, 0x00ffff, 0xffffff},
+    // xterm.
+    {0x000000, 0xcd0000, 0x00cd00, 0xcdcd00, 0x0000ee, 0xcd00cd, 0x00cdcd, 0xe5e5e5, 0x7f7f7f,
+     0xff0000, 0x00ff00, 0xffff00, 0x5c5cff, 0xff00ff, 0x00ffff, 0xffffff},
+    // The Linux console, which uses the VGA colors.
+    {0x000000, 0xaa0000, 0x00aa00, 0xaa5500, 0x0000aa, 0xaa00aa, 0x00aaaa, 0xaaaaaa, 0x555555,
+     0xff5555, 0x55ff55, 0xffff55, 0x5555ff, 0xff55ff, 0x55ffff, 0xffffff},
+};
+
+/// The 16 named colors of the terminal, as set by set_term16_palette().
+static owning_lock<std::array<oklab_t, 16>> s_term16_palette{[] {
+    std::array<oklab_t, 16> result;
+    for (size_t i = 0; i < 16; i++) result[i] = oklab_from_rgb(kTerm16Palettes[0][i]);
+    return result;
+}()};
+
+void set_term16_palette(const wcstring &term, const std::vector<color24_t> &colors) {
+    std::array<oklab_t, 16> palette;
+    if (colors.size() == palette.size()) {
+        for (size_t i = 0; i < palette.size(); i++) {
+            palette[i] = oklab_from_rgb(rgb_to_uint32(colors[i].rgb));
+        }
+    } else {
+        const uint32_t *known = kTerm16Palettes[0];
+        if (string_prefixes_string(L"xterm", term)) {
+            known = kTerm16Palettes[1];
+        } else if (term == L"linux") {
+            known = kTerm16Palettes[2];
+        }
+        for (size_t i = 0; i < palette.size(); i++) palette[i] = oklab_from_rgb(known[i]);
+    }
+    *s_term16_palette.acquire() = palette;
+}
+
 static unsigned char term16_color_for_rgb(const unsigned char rgb[3]) {
-    const uint32_t kColors[] = {
-        0x000000,  // Black
-        0x800000,  // Red
-        0x008000,  // Green
-        0x808000,  // Yellow
-        0x000080,  // Blue
-        0x800080,  // Magenta
-        0x008080,  // Cyan
-        0xc0c0c0,  // White
-        0x808080,  // Bright Black
-        0xFF0000,  // Bright Red
-        0x00FF00,  // Bright Green
-        0xFFFF00,  // Bright Yellow
-        0x0000FF,  // Bright Blue
-        0xFF00FF,  // Bright Magenta
-        0x00FFFF,  // Bright Cyan
-        0xFFFFFF   // Bright White
-    };
-    return convert_color(rgb, kColors, sizeof kColors / sizeof *kColors);
+    auto palette = s_term16_palette.acquire();
+    return convert_color(rgb, palette->data(), palette->size());
 }
 
 static unsigned char term256_color_for_rgb(const unsigned char rgb[3]) {
-    const uint32_t kColors[240] = {
+    static const uint32_t kColors[240] = {
         0x000000, 0x00005f, 0x000087, 0x0000af, 0x0000d7, 0x0000ff, 0x005f00, 0x005f5f, 0x005f87,
         0x005faf, 0x005fd7, 0x005fff, 0x008700, 0x00875f, 0x008787, 0x0087af, 0x0087d7, 0x0087ff,
         0x00af00, 0x00af5f, 0x00af87, 0x00afaf, 0x00afd7, 0x00afff, 0x00d700, 0x00d75f, 0x00d787,
@@ -322,7 +371,12 @@ static unsigned char term256_color_for_rgb(const unsigned char rgb[3]) {
         0x080808, 0x121212, 0x1c1c1c, 0x262626, 0x303030, 0x3a3a3a, 0x444444, 0x4e4e4e, 0x585858,
         0x626262, 0x6c6c6c, 0x767676, 0x808080, 0x8a8a8a, 0x949494, 0x9e9e9e, 0xa8a8a8, 0xb2b2b2,
         0xbcbcbc, 0xc6c6c6, 0xd0d0d0, 0xdadada, 0xe4e4e4, 0xeeeeee};
-    return 16 + convert_color(rgb, kColors, sizeof kColors / sizeof *kColors);
+    static const std::array<oklab_t, 240> kLabColors = [] {
+        std::array<oklab_t, 240> result;
+        for (size_t i = 0; i < result.size(); i++) result[i] = oklab_from_rgb(kColors[i]);
+        return result;
+    }();
+    return 16 + convert_color(rgb, kLabColors.data(), kLabColors.size());
 }
 
 unsigned char rgb_color_t::to_term256_index() const {
@@ -4,6 +4,7 @@
 
 #include <cstring>
 #include <string>
+#include <vector>
 
 #include "common.h"
 
@@ -171,4 +172,9 @@ class rgb_color_t {
 
 static_assert(sizeof(rgb_color_t) <= 4, "rgb_color_t is too big");
 
+/// Set the colors of the 16 named colors, which RGB colors are mapped to the nearest of when the
+/// terminal supports no more. \p colors are the colors in order, or empty to use the colors the
+/// terminal \p term is known to use.
+void set_term16_palette(const wcstring &term, const std::vector<color24_t> &colors);
+
 #endif
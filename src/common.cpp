@@ -19,6 +19,7 @@
 #include <stdlib.h>
 #include <sys/stat.h>
 #include <sys/time.h>
+#include <sys/uio.h>
 #include <termios.h>
 #include <unistd.h>
 #include <wctype.h>
@@ -188,6 +189,20 @@ bool is_windows_subsystem_for_linux() {
 #endif
 }
 
+bool is_termux() {
+#if !defined(__linux__)
+    return false;
+#else
+    // Termux sets $TERMUX_VERSION in newer versions, and its $PREFIX is in the app's data.
+    static const bool termux_state = [] {
+        if (getenv("TERMUX_VERSION")) return true;
+        const char *prefix = getenv("PREFIX");
+        return prefix && std::strstr(prefix, "/com.termux/") != nullptr;
+    }();
+    return termux_state;
+#endif
+}
+
 #ifdef HAVE_BACKTRACE_SYMBOLS
 // This function produces a stack backtrace with demangled function & method names. It is based on
 // https://gist.github.com/fmela/591333 but adapted to the style of the fish project.
@@ -577,6 +592,43 @@ ssize_t write_loop(int fd, const char *buff, size_t count) {
     return static_cast<ssize_t>(out_cum);
 }
 
+#ifndef IOV_MAX
+#define IOV_MAX 16
+#endif
+
+ssize_t writev_loop(int fd, struct iovec *iov, int iovcnt) {
+    size_t out_cum = 0;
+    while (iovcnt > 0) {
+        // Skip any buffers which are done, so we never pass writev an empty one.
+        if (iov->iov_len == 0) {
+            iov++;
+            iovcnt--;
+            continue;
+        }
+        ssize_t out = writev(fd, iov, std::min(iovcnt, IOV_MAX));
+        if (out < 0) {
+            if (errno != EAGAIN && errno != EINTR) {
+                return -1;
+            }
+            continue;
+        }
+        out_cum += static_cast<size_t>(out);
+        // Advance past what was written, which may end in the middle of a buffer.
+        auto remaining = static_cast<size_t>(out);
+        while (remaining > 0) {
+            size_t amt = std::min(remaining, iov->iov_len);
+            iov->iov_base = static_cast<char *>(iov->iov_base) + amt;
+            iov->iov_len -= amt;
+            remaining -= amt;
+            if (iov->iov_len == 0) {
+                iov++;
+                iovcnt--;
+            }
+        }
+    }
+    return static_cast<ssize_t>(out_cum);
+}
+
 ssize_t read_loop(int fd, void *buff, size_t count) {
     ssize_t result;
     do {
@@ -1320,6 +1372,52 @@ maybe_t<size_t> read_unquoted_escape(const wchar_t *input, wcstring *result, boo
     return in_pos;
 }
 
+/// Check if the brace contents in \p result starting at \p start form a numeric range like 1..10 or
+/// 10..1..2. If so, replace each ".." with BRACE_RANGE and return true.
+static bool mark_brace_range(wcstring *result, size_t start) {
+    // Match up to three integers separated by "..". Limit the number of digits so they can't
+    // overflow when parsed.
+    const size_t max_digits = 18;
+    std::vector<size_t> dots;
+    size_t pos = start;
+    for (;;) {
+        if (pos < result->size() && result->at(pos) == L'-') pos++;
+        size_t digits = 0;
+        while (pos < result->size() && result->at(pos) >= L'0' && result->at(pos) <= L'9') {
+            pos++;
+            digits++;
+        }
+        if (digits == 0 || digits > max_digits) return false;
+        if (pos == result->size()) break;
+        if (dots.size() == 2 || result->compare(pos, 2, L"..") != 0) return false;
+        dots.push_back(pos);
+        pos += 2;
+    }
+    if (dots.empty()) return false;
+
+    // Replace from the back, so the earlier positions stay valid.
+    for (auto iter = dots.rbegin(); iter != dots.rend(); ++iter) {
+        result->replace(*iter, 2, 1, BRACE_RANGE);
+    }
+    return true;
+}
+
+/// Check if the \p len characters at \p str are glob qualifiers like "[type=dir,limit=5]". These
+/// must end the token and contain only plain characters, wildcards and at least one '='.
+static bool is_glob_qualifier(const wchar_t *str, size_t len) {
+    if (len < 3 || str[0] != L'[' || str[len - 1] != L']') return false;
+    bool have_eq = false;
+    for (size_t i = 1; i + 1 < len; i++) {
+        wchar_t c = str[i];
+        if (c == L'=') {
+            have_eq = true;
+        } else if (!iswalnum(c) && !std::wcschr(L",-_.*?/", c)) {
+            return false;
+        }
+    }
+    return have_eq;
+}
+
 /// Returns the unescaped version of input_str into output_str (by reference). Returns true if
 /// successful. If false, the contents of output_str are undefined (!).
 static bool unescape_string_internal(const wchar_t *const input, const size_t input_len,
@@ -1437,8 +1535,9 @@ static bool unescape_string_internal(const wchar_t *const input, const size_t in
                         to_append_or_none = BRACE_END;
                         if (!braces.empty()) {
                             // If we didn't have a var or separator since the last '{',
-                            // put the literal back.
-                            if (vars_or_seps.empty() || vars_or_seps.back() < braces.back()) {
+                            // put the literal back, unless it's a numeric range like {1..10}.
+                            if ((vars_or_seps.empty() || vars_or_seps.back() < braces.back()) &&
+                                !mark_brace_range(&result, braces.back() + 1)) {
                                 result[braces.back()] = L'{';
                                 // We also need to turn all spaces back.
                                 for (size_t i = braces.back() + 1; i < result.size(); i++) {
@@ -1458,6 +1557,17 @@ static bool unescape_string_internal(const wchar_t *const input, const size_t in
                     }
                     break;
                 }
+                case L'[': {
+                    // Glob qualifiers like *[type=dir] end a wildcard. Keep their text as is.
+                    if (unescape_special && wildcard_has(result, true) &&
+                        is_glob_qualifier(input + input_position, input_len - input_position)) {
+                        result.push_back(GLOB_QUALIFIER);
+                        result.append(input + input_position + 1, input_len - input_position - 2);
+                        input_position = input_len - 1;
+                        to_append_or_none = none();
+                    }
+                    break;
+                }
                 case L',': {
                     if (unescape_special && brace_count > 0) {
                         to_append_or_none = BRACE_SEP;
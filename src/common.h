@@ -244,6 +244,10 @@ extern const wcstring g_empty_string;
 /// std::fwprintf()).
 #define _(wstr) wgettext(wstr).c_str()
 
+/// Shorthand for wngettext call, for messages about a number of things. The number must still be
+/// passed to the format function, e.g. format_string(P_(L"%lu job", L"%lu jobs", n), n).
+#define P_(singular, plural, n) wngettext(singular, plural, n).c_str()
+
 /// Noop, used to tell xgettext that a string should be translated. Use this when a string cannot be
 /// passed through wgettext() at the point where it is used. For example, when initializing a
 /// static array or structure. You must pass the string through wgettext() when it is used.
@@ -474,6 +478,10 @@ long read_blocked(int fd, void *buf, size_t count);
 /// error.
 ssize_t write_loop(int fd, const char *buff, size_t count);
 
+/// Like write_loop, but write the \p iovcnt buffers of \p iov in order, with as few write calls as
+/// possible. \p iov is modified. Return -1 and set errno in case of critical error.
+ssize_t writev_loop(int fd, struct iovec *iov, int iovcnt);
+
 /// Loop a read request while failure is non-critical. Return -1 and set errno in case of critical
 /// error.
 ssize_t read_loop(int fd, void *buff, size_t count);
@@ -550,6 +558,10 @@ void assert_is_not_forked_child(const char *who);
 /// See https://github.com/Microsoft/WSL/issues/423 and Microsoft/WSL#2997
 bool is_windows_subsystem_for_linux();
 
+/// Detect if we are running in Termux, the terminal emulator and Linux environment for Android.
+/// Android has no locales to speak of, and Termux keeps its terminfo database under $PREFIX.
+bool is_termux();
+
 /// Detect if we are running under Cygwin or Cgywin64
 constexpr bool is_cygwin() {
 #ifdef __CYGWIN__
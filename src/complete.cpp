@@ -36,6 +36,7 @@
 #include "expand.h"
 #include "fallback.h"  // IWYU pragma: keep
 #include "function.h"
+#include "future_feature_flags.h"
 #include "history.h"
 #include "iothread.h"
 #include "parse_constants.h"
@@ -744,14 +745,18 @@ void completer_t::complete_cmd(const wcstring &str_cmd) {
         this->complete_cmd_desc(str_cmd);
     }
 
+    // Append all matching directories. With the auto-cd feature, these are found like cd finds
+    // them, since that is where the directory will be looked for when run as a command.
+    expand_flags_t dir_flags =
+        this->expand_flags() | expand_flag::for_completions | expand_flag::directories_only;
+    if (feature_test(features_t::auto_cd)) {
+        dir_flags |= expand_flag::special_for_cd;
+        dir_flags.clear(expand_flag::gen_descriptions);
+    }
+
     // We don't really care if this succeeds or fails. If it succeeds this->completions will be
     // updated with choices for the user.
-    expand_result_t ignore =
-        // Append all matching directories
-        expand_string(
-            str_cmd, &this->completions,
-            this->expand_flags() | expand_flag::for_completions | expand_flag::directories_only,
-            ctx);
+    expand_result_t ignore = expand_string(str_cmd, &this->completions, dir_flags, ctx);
     UNUSED(ignore);
 
     if (str_cmd.empty() || (str_cmd.find(L'/') == wcstring::npos && str_cmd.at(0) != L'~')) {
@@ -919,9 +924,19 @@ static void complete_load(const wcstring &name) {
     // We need to take the lock to decide what to load, drop it to perform the load, then reacquire
     // it.
     // Note we only look at the global fish_function_path and fish_complete_path.
-    maybe_t<wcstring> path_to_load =
-        completion_autoloader.acquire()->resolve_command(name, env_stack_t::globals());
+    // The autoloader only returns a path if the file is new or changed since it was loaded, so
+    // repeated completions of the same command do not source it again.
+    bool reloading;
+    maybe_t<wcstring> path_to_load;
+    {
+        auto autoloader = completion_autoloader.acquire();
+        reloading = autoloader->has_autoloaded(name);
+        path_to_load = autoloader->resolve_command(name, env_stack_t::globals());
+    }
     if (path_to_load) {
+        // The file changed. Forget what it defined before, so sourcing it again doesn't add its
+        // completions twice.
+        if (reloading) complete_remove_all(name, false /* not a path */);
         autoload_t::perform_autoload(*path_to_load, parser);
         completion_autoloader.acquire()->mark_autoload_finished(name);
     }
@@ -1158,6 +1173,17 @@ bool completer_t::complete_param_for_command(const wcstring &cmd_orig, const wcs
 }
 
 /// Perform generic (not command-specific) expansions on the specified string.
+/// If \p str is an unquoted path in Windows form, like C:\Users\me, as pasted from a Windows
+/// program, \return the path that names the file on WSL or MSYS2. The backslashes in such a token
+/// are separators, not escapes.
+static maybe_t<wcstring> unix_path_for_windows_token(const wcstring &str) {
+    if (str.size() < 3 || str[1] != L':' || str[2] != L'\\') return none();
+    if (str.find_first_of(L"'\"$*?{}()~") != wcstring::npos) return none();
+    maybe_t<windows_path_style_t> style = path_get_windows_style();
+    if (!style) return none();
+    return path_from_windows(str, *style);
+}
+
 void completer_t::complete_param_expand(const wcstring &str, bool do_file,
                                         bool handle_as_special_cd) {
     if (ctx.check_cancel()) return;
@@ -1177,6 +1203,24 @@ void completer_t::complete_param_expand(const wcstring &str, bool do_file,
     // Squelch file descriptions per issue #254.
     if (this->type() == COMPLETE_AUTOSUGGEST || do_file) flags.clear(expand_flag::gen_descriptions);
 
+    // Complete the Unix form of a path pasted in Windows form, replacing the whole token.
+    maybe_t<wcstring> unix_path = do_file ? unix_path_for_windows_token(str) : none();
+    if (unix_path) {
+        completion_list_t local_completions;
+        const wcstring escaped = escape_string(*unix_path, ESCAPE_ALL | ESCAPE_NO_QUOTED);
+        if (expand_string(escaped, &local_completions, flags, ctx) == expand_result_t::error) {
+            FLOGF(complete, L"Error while expanding string '%ls'", escaped.c_str());
+        }
+        for (completion_t &comp : local_completions) {
+            if (!(comp.flags & COMPLETE_REPLACES_TOKEN)) {
+                comp.completion.insert(0, *unix_path);
+                comp.flags |= COMPLETE_REPLACES_TOKEN;
+            }
+        }
+        ignore_result(this->completions.add_list(std::move(local_completions)));
+        return;
+    }
+
     // We have the following cases:
     //
     // --foo=bar => expand just bar
@@ -1773,6 +1817,66 @@ completion_list_t complete(const wcstring &cmd_with_subcmds, completion_request_
     return completer.acquire_completions();
 }
 
+wcstring complete_get_argument_hint(const wcstring &cmd_with_subcmds,
+                                    const operation_context_t &ctx) {
+    const wchar_t *cmdsubst_begin, *cmdsubst_end;
+    parse_util_cmdsubst_extent(cmd_with_subcmds.c_str(), cmd_with_subcmds.size(), &cmdsubst_begin,
+                               &cmdsubst_end);
+    assert(cmdsubst_begin != nullptr && cmdsubst_end != nullptr && cmdsubst_end >= cmdsubst_begin);
+    const wcstring cmdline(cmdsubst_begin, cmdsubst_end - cmdsubst_begin);
+
+    // Only hint at an argument that has not been started yet.
+    if (cmdline.empty() || cmdline.back() != L' ') return wcstring{};
+    size_t position_in_statement = cmdline.size();
+    while (position_in_statement > 0 && cmdline.at(position_in_statement - 1) == L' ') {
+        position_in_statement--;
+    }
+
+    std::vector<tok_t> tokens;
+    parse_util_process_extent(cmdline.c_str(), position_in_statement, nullptr, nullptr, &tokens);
+    while (!tokens.empty() && (parser_keywords_is_subcommand(tokens.front().get_source(cmdline)) ||
+                               variable_assignment_equals_pos(tokens.front().get_source(cmdline)))) {
+        tokens.erase(tokens.begin());
+    }
+    if (tokens.empty()) return wcstring{};
+
+    // The trailing space must not be part of the last token, like in "foo\ ".
+    const tok_t &cmd_tok = tokens.front();
+    const tok_t &last_tok = tokens.back();
+    if (cmd_tok.type != token_type_t::string || last_tok.type != token_type_t::string ||
+        last_tok.offset + last_tok.length >= cmdline.size()) {
+        return wcstring{};
+    }
+
+    wcstring cmd_orig, popt;
+    if (!unescape_string(cmd_tok.get_source(cmdline), &cmd_orig, UNESCAPE_DEFAULT) ||
+        !unescape_string(last_tok.get_source(cmdline), &popt, UNESCAPE_DEFAULT)) {
+        return wcstring{};
+    }
+    wcstring cmd, path;
+    parse_cmd_string(cmd_orig, &path, &cmd, ctx.vars);
+    const bool after_command = tokens.size() == 1;
+
+    auto completion_set = s_completion_set.acquire();
+    for (const completion_entry_t &entry : *completion_set) {
+        if (!wildcard_match(entry.cmd_is_path ? path : cmd, entry.cmd)) continue;
+        for (const complete_entry_opt_t &o : entry.get_options()) {
+            // Conditions can only be tested by running them, so ignore options that have one.
+            if (!o.result_mode.requires_param || o.desc.empty() || !o.condition.empty()) continue;
+            bool match;
+            if (o.type == option_type_args_only) {
+                match = after_command;
+            } else if (o.type == option_type_short) {
+                match = popt.size() == 2 && popt.at(0) == L'-' && popt.at(1) == o.option.at(0);
+            } else {
+                match = param_match(&o, popt.c_str());
+            }
+            if (match) return o.localized_desc();
+        }
+    }
+    return wcstring{};
+}
+
 /// Print the short switch \c opt, and the argument \c arg to the specified
 /// wcstring, but only if \c argument isn't an empty string.
 static void append_switch(wcstring &out, wchar_t opt, const wcstring &arg) {
@@ -1869,11 +1973,18 @@ wcstring complete_print(const wcstring &cmd) {
     return out;
 }
 
-void complete_invalidate_path() {
+void complete_invalidate_path(const environment_t &vars) {
     // TODO: here we unload all completions for commands that are loaded by the autoloader. We also
     // unload any completions that the user may specified on the command line. We should in
     // principle track those completions loaded by the autoloader alone.
-    wcstring_list_t cmds = completion_autoloader.acquire()->get_autoloaded_commands();
+    wcstring_list_t cmds;
+    {
+        auto autoloader = completion_autoloader.acquire();
+        cmds = autoloader->get_autoloaded_commands();
+        // Forget the loaded files too, so the completions are loaded again when needed.
+        autoloader->clear();
+        autoloader->update_paths(vars);
+    }
     for (const wcstring &cmd : cmds) {
         complete_remove_all(cmd, false /* not a path */);
     }
@@ -248,6 +248,11 @@ class operation_context_t;
 completion_list_t complete(const wcstring &cmd, completion_request_flags_t flags,
                            const operation_context_t &ctx);
 
+/// \return a hint for the argument expected at the end of \p cmd, which must end in a space. This
+/// is the description of the option before it, if that option requires a parameter, or of the
+/// arguments to the command itself if they do. Returns an empty string if there is no such hint.
+wcstring complete_get_argument_hint(const wcstring &cmd, const operation_context_t &ctx);
+
 /// Return a list of all current completions.
 wcstring complete_print(const wcstring &cmd = L"");
 
@@ -275,7 +280,7 @@ bool complete_remove_wrapper(const wcstring &command, const wcstring &target_to_
 /// Returns a list of wrap targets for a given command.
 wcstring_list_t complete_get_wrap_targets(const wcstring &command);
 
-// Observes that fish_complete_path has changed.
-void complete_invalidate_path();
+// Observes that fish_complete_path has changed, to the value in \p vars.
+void complete_invalidate_path(const environment_t &vars);
 
 #endif
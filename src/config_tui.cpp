@@ -0,0 +1,640 @@
+// The terminal interface of `fish_config tui`.
+//
+// This lets the user choose a sample prompt and a color theme, with a preview of them, and edit
+// variables, without a web browser. Each page is a list in the pager, which can be searched by
+// typing. The preview is drawn as the prompt, above the pager.
+#include "config.h"  // IWYU pragma: keep
+
+#include "config_tui.h"
+
+#include <termios.h>
+#include <unistd.h>
+
+#include <map>
+#include <string>
+#include <vector>
+
+#include "builtin.h"
+#include "color.h"
+#include "common.h"
+#include "complete.h"
+#include "env.h"
+#include "exec.h"
+#include "expand.h"
+#include "fallback.h"  // IWYU pragma: keep
+#include "highlight.h"
+#include "input.h"
+#include "input_common.h"
+#include "io.h"
+#include "output.h"
+#include "pager.h"
+#include "parser.h"
+#include "reader.h"
+#include "sample_prompt.h"
+#include "screen.h"
+#include "signal.h"
+#include "termsize.h"
+#include "theme.h"
+#include "wcstringutil.h"
+#include "wutil.h"  // IWYU pragma: keep
+
+namespace {
+/// The pages of the interface.
+enum class tui_page_t { menu, prompt, theme, variables };
+
+/// The keys the interface knows, as decoded from the terminal's input.
+enum class tui_key_t {
+    none,  // nothing to do, except maybe a repaint
+    text,  // a character to type
+    up,
+    down,
+    left,
+    right,
+    page_up,
+    page_down,
+    home,
+    end,
+    next,
+    prev,
+    enter,
+    escape,
+    backspace,
+    del,
+    clear,
+    quit,
+};
+}  // namespace
+
+/// The pages of the menu.
+static const struct {
+    tui_page_t page;
+    const wchar_t *name;
+    const wchar_t *description;
+} menu_pages[] = {
+    {tui_page_t::prompt, L"prompt", N_(L"Choose a prompt")},
+    {tui_page_t::theme, L"theme", N_(L"Choose a color theme")},
+    {tui_page_t::variables, L"variables", N_(L"Edit variables")},
+};
+
+/// The escape sequences of the keys which terminals send as CSI or SS3 sequences, without the
+/// introducer.
+static const struct {
+    const wchar_t *seq;
+    tui_key_t key;
+} key_sequences[] = {
+    {L"A", tui_key_t::up},       {L"B", tui_key_t::down},       {L"C", tui_key_t::right},
+    {L"D", tui_key_t::left},     {L"H", tui_key_t::home},       {L"F", tui_key_t::end},
+    {L"1~", tui_key_t::home},    {L"7~", tui_key_t::home},      {L"4~", tui_key_t::end},
+    {L"8~", tui_key_t::end},     {L"5~", tui_key_t::page_up},   {L"6~", tui_key_t::page_down},
+    {L"3~", tui_key_t::del},     {L"Z", tui_key_t::prev},
+};
+
+namespace {
+class config_tui_t {
+   public:
+    config_tui_t(parser_t &parser, tui_page_t page)
+        : parser_(parser), menu_shown_(page == tui_page_t::menu) {
+        enter_page(page);
+    }
+
+    /// Show the interface and handle keys until the user quits.
+    void run();
+
+   private:
+    parser_t &parser_;
+    input_event_queue_t queue_{STDIN_FILENO};
+    screen_t screen_{};
+    pager_t pager_{};
+    page_rendering_t rendering_{};
+
+    /// The current page.
+    tui_page_t page_{tui_page_t::menu};
+    /// Whether we started on the menu, so escape goes back to it rather than quitting.
+    bool menu_shown_;
+    /// What is shown as the prompt: a preview of the selected prompt or theme, or a title.
+    wcstring preview_;
+    /// The line below the preview: what the keys do, or the result of the last action.
+    wcstring message_;
+    /// The name of the variable which is being edited, if any, and its value.
+    maybe_t<wcstring> editing_{};
+    editable_line_t edit_line_{};
+    /// The search on the page of variables when editing started, to restore it afterwards.
+    wcstring search_before_editing_{};
+    /// The names of the items on the page.
+    wcstring_list_t items_{};
+    /// The output of the sample prompts which were previewed, by name.
+    std::map<wcstring, wcstring> prompt_previews_{};
+    bool finished_{false};
+
+    /// Read a key. If it is a character to type, store it in \p out_char.
+    tui_key_t read_key(wchar_t *out_char);
+
+    /// Draw the preview, the message and the pager or the variable being edited.
+    void repaint();
+
+    /// Show the page \p page, with nothing selected and no search.
+    void enter_page(tui_page_t page);
+
+    /// \return what the keys do on the current page.
+    wcstring help() const;
+
+    /// \return the name of the selected item, or of the first one if none is selected.
+    maybe_t<wcstring> chosen_item();
+
+    /// Update the preview for the selected item.
+    void update_preview();
+
+    void handle_key(tui_key_t key, wchar_t c);
+    void handle_edit_key(tui_key_t key, wchar_t c);
+
+    /// Do what enter does on the current page, with the item \p name.
+    void choose(const wcstring &name);
+
+    /// Start editing the variable \p name.
+    void start_editing(const wcstring &name);
+
+    /// Set the edited variable to the value being edited. \return whether that worked.
+    bool finish_editing();
+
+    /// Go back to the page of variables.
+    void stop_editing();
+};
+}  // namespace
+
+tui_key_t config_tui_t::read_key(wchar_t *out_char) {
+    char_event_t evt = queue_.readch();
+    if (evt.is_eof()) return tui_key_t::quit;
+    if (evt.is_check_exit()) {
+        // A signal, like the terminal being resized.
+        return signal_check_cancel() ? tui_key_t::quit : tui_key_t::none;
+    }
+    if (!evt.is_char()) return tui_key_t::none;
+
+    wchar_t c = evt.get_char();
+    if (c == shell_modes.c_cc[VINTR] || c == shell_modes.c_cc[VEOF]) return tui_key_t::quit;
+    switch (c) {
+        case L'\r':
+        case L'\n':
+            return tui_key_t::enter;
+        case L'\t':
+            return tui_key_t::next;
+        case L'\x7F':
+        case L'\b':
+            return tui_key_t::backspace;
+        case L'\x01':
+            return tui_key_t::home;
+        case L'\x05':
+            return tui_key_t::end;
+        case L'\x02':
+            return tui_key_t::left;
+        case L'\x06':
+            return tui_key_t::right;
+        case L'\x0E':
+            return tui_key_t::down;
+        case L'\x10':
+            return tui_key_t::up;
+        case L'\x15':
+            return tui_key_t::clear;
+        case L'\x1B': {
+            // An escape on its own, or the start of a sequence for a special key.
+            char_event_t next = queue_.readch_timed(true /* dequeue timeouts */);
+            if (!next.is_char()) return tui_key_t::escape;
+            if (next.get_char() != L'[' && next.get_char() != L'O') return tui_key_t::none;
+            wcstring seq;
+            for (;;) {
+                char_event_t seq_evt = queue_.readch_timed(true /* dequeue timeouts */);
+                if (!seq_evt.is_char()) return tui_key_t::none;
+                wchar_t seq_char = seq_evt.get_char();
+                seq.push_back(seq_char);
+                if (seq_char >= L'@' && seq_char <= L'~') break;
+            }
+            for (const auto &key_seq : key_sequences) {
+                if (seq == key_seq.seq) return key_seq.key;
+            }
+            return tui_key_t::none;
+        }
+        default:
+            break;
+    }
+    if (c < L' ') return tui_key_t::none;
+    *out_char = c;
+    return tui_key_t::text;
+}
+
+wcstring config_tui_t::help() const {
+    if (editing_) return _(L"Enter: set the variable, Escape: cancel");
+    const wchar_t *back = menu_shown_ ? _(L"Escape: back") : _(L"Escape: quit");
+    switch (page_) {
+        case tui_page_t::menu:
+            return _(L"Enter: open, Escape: quit, type to search");
+        case tui_page_t::prompt:
+            return format_string(_(L"Enter: use this prompt, %ls, type to search"), back);
+        case tui_page_t::theme:
+            return format_string(_(L"Enter: use this theme, %ls, type to search"), back);
+        case tui_page_t::variables:
+            return format_string(_(L"Enter: edit, %ls, type to search"), back);
+    }
+    DIE("unexpected page");
+}
+
+void config_tui_t::enter_page(tui_page_t page) {
+    page_ = page;
+    editing_ = none();
+    completion_list_t items;
+    const environment_t &vars = parser_.vars();
+    switch (page) {
+        case tui_page_t::menu: {
+            for (const auto &menu_page : menu_pages) {
+                items.emplace_back(menu_page.name, _(menu_page.description));
+            }
+            break;
+        }
+        case tui_page_t::prompt: {
+            for (const wcstring &name : sample_prompt_list()) {
+                auto prompt = sample_prompt_load(name);
+                if (prompt) items.emplace_back(name, prompt->get(L"name"));
+            }
+            break;
+        }
+        case tui_page_t::theme: {
+            for (const wcstring &name : theme_list(vars)) {
+                items.emplace_back(name);
+            }
+            break;
+        }
+        case tui_page_t::variables: {
+            for (const wcstring &name : vars.get_names(ENV_GLOBAL | ENV_UNIVERSAL)) {
+                auto var = vars.get(name);
+                if (var) items.emplace_back(name, expand_escape_variable(*var));
+            }
+            break;
+        }
+    }
+    items_.clear();
+    for (const completion_t &item : items) items_.push_back(item.completion);
+    pager_.clear();
+    pager_.set_search_field_shown(true);
+    pager_.set_fully_disclosed(true);
+    pager_.set_completions(items);
+    rendering_ = page_rendering_t();
+    message_ = help();
+    update_preview();
+}
+
+maybe_t<wcstring> config_tui_t::chosen_item() {
+    if (const completion_t *selected = pager_.selected_completion(rendering_)) {
+        return selected->completion;
+    }
+    // Searching for the exact name of an item chooses it, even if others match too.
+    const wcstring &search = pager_.search_field_line.text();
+    if (contains(items_, search)) return search;
+    if (pager_.select_next_completion_in_direction(selection_motion_t::next, rendering_)) {
+        rendering_ = pager_.render();
+        if (const completion_t *first = pager_.selected_completion(rendering_)) {
+            return first->completion;
+        }
+    }
+    return none();
+}
+
+void config_tui_t::update_preview() {
+    wcstring preview;
+    const completion_t *selected = pager_.selected_completion(rendering_);
+    switch (page_) {
+        case tui_page_t::menu: {
+            preview = L"fish_config";
+            break;
+        }
+        case tui_page_t::prompt: {
+            if (!selected) {
+                preview = sample_prompt_render(sample_prompt_run_current(parser_),
+                                               termsize_last().width);
+                break;
+            }
+            auto cached = prompt_previews_.find(selected->completion);
+            if (cached != prompt_previews_.end()) {
+                preview = cached->second;
+                break;
+            }
+            if (auto prompt = sample_prompt_load(selected->completion)) {
+                preview = sample_prompt_render(sample_prompt_run(parser_, prompt->function),
+                                               termsize_last().width);
+            }
+            prompt_previews_[selected->completion] = preview;
+            break;
+        }
+        case tui_page_t::theme: {
+            maybe_t<theme_t> theme;
+            if (selected) {
+                wcstring err;
+                theme = theme_load(selected->completion, parser_.vars(), &err);
+                if (!theme) message_ = err;
+            }
+            preview = theme_sample(theme ? &*theme : nullptr, parser_.vars());
+            break;
+        }
+        case tui_page_t::variables: {
+            if (editing_) {
+                bool universal = !parser_.vars().get(*editing_, ENV_GLOBAL) &&
+                                 parser_.vars().get(*editing_, ENV_UNIVERSAL);
+                preview = wcstring(universal ? L"set -U " : L"set -g ") +
+                          escape_string(*editing_, ESCAPE_ALL) + L" ";
+            } else {
+                preview = L"fish_config variables";
+            }
+            break;
+        }
+    }
+    // Drop the newline at the end of the output, so the search field follows it directly.
+    while (!preview.empty() && preview.back() == L'\n') preview.pop_back();
+    if (preview != preview_) {
+        preview_ = std::move(preview);
+        // The preview may have had more lines, or have been in different colors.
+        s_reset_line(&screen_, true /* repaint prompt */);
+    }
+}
+
+void config_tui_t::repaint() {
+    termsize_container_t::shared().updating(parser_);
+    wcstring text;
+    std::vector<highlight_spec_t> colors;
+    if (editing_) {
+        // Highlight the value as the arguments of the command in the preview.
+        text = edit_line_.text();
+        wcstring command = preview_ + text;
+        highlight_shell(command, colors, parser_.context());
+        colors.erase(colors.begin(), colors.begin() + preview_.size());
+    }
+    std::vector<int> indents(text.size(), 0);
+    bool in_pager = !editing_ && !pager_.empty();
+    size_t cursor = in_pager ? pager_.cursor_position() : editing_ ? edit_line_.position() : 0;
+    s_write(&screen_, preview_, wcstring(), text, text.size(), colors, indents, cursor, message_,
+            pager_, rendering_, in_pager);
+}
+
+void config_tui_t::choose(const wcstring &name) {
+    switch (page_) {
+        case tui_page_t::menu: {
+            for (const auto &menu_page : menu_pages) {
+                if (name == menu_page.name) enter_page(menu_page.page);
+            }
+            break;
+        }
+        case tui_page_t::prompt: {
+            auto prompt = sample_prompt_load(name);
+            if (prompt && sample_prompt_use(parser_, prompt->function)) {
+                message_ = format_string(_(L"Using the prompt '%ls'"), name.c_str());
+            } else {
+                message_ = format_string(_(L"Unable to set the prompt '%ls'"), name.c_str());
+            }
+            break;
+        }
+        case tui_page_t::theme: {
+            wcstring err;
+            auto theme = theme_load(name, parser_.vars(), &err);
+            if (!theme) {
+                message_ = err;
+                break;
+            }
+            theme_use(parser_, *theme, ENV_UNIVERSAL);
+            message_ = format_string(_(L"Using the theme '%ls'"), name.c_str());
+            // Everything is drawn in the new colors.
+            s_reset_line(&screen_, true /* repaint prompt */);
+            break;
+        }
+        case tui_page_t::variables: {
+            start_editing(name);
+            break;
+        }
+    }
+}
+
+void config_tui_t::start_editing(const wcstring &name) {
+    auto var = parser_.vars().get(name);
+    if (!var) return;
+    wcstring value;
+    for (const wcstring &elem : var->as_list()) {
+        if (!value.empty()) value.push_back(L' ');
+        value.append(expand_escape_string(elem));
+    }
+    editing_ = name;
+    edit_line_.clear();
+    edit_line_.push_edit(edit_t(0, 0, value));
+    search_before_editing_ = pager_.search_field_line.text();
+    pager_.clear();
+    rendering_ = page_rendering_t();
+    message_ = help();
+    update_preview();
+}
+
+void config_tui_t::stop_editing() {
+    enter_page(tui_page_t::variables);
+    if (!search_before_editing_.empty()) {
+        pager_.search_field_line.push_edit(edit_t(0, 0, std::move(search_before_editing_)));
+        pager_.refilter_completions();
+    }
+}
+
+bool config_tui_t::finish_editing() {
+    // The value is given to set as it was typed, so it may use quotes, variables and so on.
+    wcstring command = preview_ + edit_line_.text() + L" 2>&1";
+    wcstring_list_t output;
+    exec_subshell(command, parser_, output, false /* apply_exit_status */);
+    if (!output.empty()) {
+        message_ = output.front();
+        return false;
+    }
+    message_ = format_string(_(L"Set '%ls'"), editing_->c_str());
+    return true;
+}
+
+void config_tui_t::handle_edit_key(tui_key_t key, wchar_t c) {
+    size_t pos = edit_line_.position();
+    switch (key) {
+        case tui_key_t::text:
+            edit_line_.push_edit(edit_t(pos, 0, wcstring(1, c)));
+            break;
+        case tui_key_t::backspace:
+            if (pos > 0) edit_line_.push_edit(edit_t(pos - 1, 1, L""));
+            break;
+        case tui_key_t::del:
+            if (pos < edit_line_.size()) edit_line_.push_edit(edit_t(pos, 1, L""));
+            break;
+        case tui_key_t::left:
+            if (pos > 0) edit_line_.set_position(pos - 1);
+            break;
+        case tui_key_t::right:
+            if (pos < edit_line_.size()) edit_line_.set_position(pos + 1);
+            break;
+        case tui_key_t::home:
+            edit_line_.set_position(0);
+            break;
+        case tui_key_t::end:
+            edit_line_.set_position(edit_line_.size());
+            break;
+        case tui_key_t::clear:
+            edit_line_.push_edit(edit_t(0, pos, L""));
+            break;
+        case tui_key_t::enter: {
+            if (!finish_editing()) break;
+            wcstring message = std::move(message_);
+            stop_editing();
+            message_ = std::move(message);
+            break;
+        }
+        case tui_key_t::escape:
+            stop_editing();
+            break;
+        case tui_key_t::quit:
+            finished_ = true;
+            break;
+        default:
+            break;
+    }
+}
+
+void config_tui_t::handle_key(tui_key_t key, wchar_t c) {
+    auto select = [&](selection_motion_t direction) {
+        if (pager_.select_next_completion_in_direction(direction, rendering_)) {
+            // Render now, so the preview is of the newly selected item.
+            pager_.update_rendering(&rendering_);
+            update_preview();
+        }
+    };
+    auto search_changed = [&] {
+        pager_.refilter_completions();
+        pager_.select_next_completion_in_direction(selection_motion_t::deselect, rendering_);
+        pager_.update_rendering(&rendering_);
+        update_preview();
+    };
+    editable_line_t &search = pager_.search_field_line;
+    switch (key) {
+        case tui_key_t::text:
+            search.push_edit(edit_t(search.size(), 0, wcstring(1, c)));
+            search_changed();
+            break;
+        case tui_key_t::backspace:
+            if (!search.empty()) {
+                search.push_edit(edit_t(search.size() - 1, 1, L""));
+                search_changed();
+            }
+            break;
+        case tui_key_t::clear:
+            if (!search.empty()) {
+                search.push_edit(edit_t(0, search.size(), L""));
+                search_changed();
+            }
+            break;
+        case tui_key_t::up:
+            select(selection_motion_t::north);
+            break;
+        case tui_key_t::down:
+            select(selection_motion_t::south);
+            break;
+        case tui_key_t::left:
+            select(selection_motion_t::west);
+            break;
+        case tui_key_t::right:
+            select(selection_motion_t::east);
+            break;
+        case tui_key_t::page_up:
+            select(selection_motion_t::page_north);
+            break;
+        case tui_key_t::page_down:
+            select(selection_motion_t::page_south);
+            break;
+        case tui_key_t::next:
+            select(selection_motion_t::next);
+            break;
+        case tui_key_t::prev:
+            select(selection_motion_t::prev);
+            break;
+        case tui_key_t::enter:
+            if (auto name = chosen_item()) {
+                update_preview();
+                choose(*name);
+            }
+            break;
+        case tui_key_t::escape:
+            if (page_ != tui_page_t::menu && menu_shown_) {
+                enter_page(tui_page_t::menu);
+            } else {
+                finished_ = true;
+            }
+            break;
+        case tui_key_t::quit:
+            finished_ = true;
+            break;
+        default:
+            break;
+    }
+}
+
+void config_tui_t::run() {
+    s_reset_abandoning_line(&screen_, termsize_last().width);
+    while (!finished_) {
+        repaint();
+        wchar_t c = 0;
+        tui_key_t key = read_key(&c);
+        if (key == tui_key_t::none) continue;
+        message_ = help();
+        if (editing_) {
+            handle_edit_key(key, c);
+        } else {
+            handle_key(key, c);
+        }
+    }
+
+    // Erase everything, leaving the cursor where we started.
+    pager_.clear();
+    preview_.clear();
+    message_.clear();
+    editing_ = none();
+    s_reset_line(&screen_, true /* repaint prompt */);
+    repaint();
+}
+
+int config_tui_run(parser_t &parser, io_streams_t &streams, const wcstring &initial_page) {
+    const wchar_t *cmd = L"fish_config";
+    tui_page_t page = tui_page_t::menu;
+    if (!initial_page.empty()) {
+        bool found = false;
+        for (const auto &menu_page : menu_pages) {
+            if (initial_page == menu_page.name) {
+                page = menu_page.page;
+                found = true;
+            }
+        }
+        if (!found) {
+            streams.err.append_format(_(L"%ls tui: Unknown page '%ls'\n"), cmd,
+                                      initial_page.c_str());
+            return STATUS_INVALID_ARGS;
+        }
+    }
+    if (!isatty(STDIN_FILENO) || !isatty(STDOUT_FILENO) || streams.out_is_redirected ||
+        streams.stdin_is_directly_redirected || screen_is_dumb()) {
+        streams.err.append_format(_(L"%ls tui: Needs a terminal which can move the cursor\n"),
+                                  cmd);
+        return STATUS_CMD_ERROR;
+    }
+
+    // Read keys one at a time, without echoing them, until we are done.
+    struct termios old_modes {};
+    if (tcgetattr(STDIN_FILENO, &old_modes) == -1) {
+        wperror(L"tcgetattr");
+        return STATUS_CMD_ERROR;
+    }
+    if (tcsetattr(STDIN_FILENO, TCSANOW, &shell_modes) == -1) {
+        wperror(L"tcsetattr");
+        return STATUS_CMD_ERROR;
+    }
+    cleanup_t restore_modes([&] {
+        if (tcsetattr(STDIN_FILENO, TCSANOW, &old_modes) == -1) wperror(L"tcsetattr");
+        outputter_t::stdoutput().set_color(rgb_color_t::reset(), rgb_color_t::reset());
+    });
+
+    init_input();
+    config_tui_t tui(parser, page);
+    tui.run();
+    return STATUS_CMD_OK;
+}
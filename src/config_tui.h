@@ -0,0 +1,16 @@
+// The terminal interface of `fish_config tui`.
+#ifndef FISH_CONFIG_TUI_H
+#define FISH_CONFIG_TUI_H
+
+#include "common.h"
+
+class parser_t;
+struct io_streams_t;
+
+/// Let the user choose a prompt and a color theme and edit variables in the terminal, using the
+/// pager. Start on the page \p initial_page, which is "prompt", "theme" or "variables", or on a
+/// menu of them if it is empty.
+/// \return a status for the fish_config builtin.
+int config_tui_run(parser_t &parser, io_streams_t &streams, const wcstring &initial_page);
+
+#endif
@@ -0,0 +1,195 @@
+// A socket on which other programs can query and control an interactive session, for
+// `fish --listen`.
+//
+// Clients send one JSON object per line, like {"id":1,"request":"variable","name":"PWD"}, and get
+// one JSON object per line back, with the same "id" and either the result or an "error". Each
+// connection is read on its own thread, but requests are answered on the main thread while the
+// reader waits for input, so they see the session as the user does.
+#include "config.h"  // IWYU pragma: keep
+
+#include "control_socket.h"
+
+#include <errno.h>
+#include <sys/socket.h>
+#include <sys/stat.h>
+#include <sys/un.h>
+#include <unistd.h>
+
+#include <cstring>
+#include <string>
+
+#include "common.h"
+#include "env.h"
+#include "exec.h"
+#include "fallback.h"  // IWYU pragma: keep
+#include "fds.h"
+#include "input.h"
+#include "iothread.h"
+#include "json.h"
+#include "parser.h"
+#include "proc.h"
+#include "reader.h"
+#include "wutil.h"  // IWYU pragma: keep
+
+/// The path of our socket. This is only accessed on the main thread.
+static wcstring s_socket_path;
+
+/// \return the members of the response to \p req, which is a request named \p name, without the
+/// surrounding braces.
+static wcstring handle_request(parser_t &parser, const wcstring &name, const json_value_t &req) {
+    ASSERT_IS_MAIN_THREAD();
+    if (name == L"variable") {
+        const wcstring *var_name = req.get_string(L"name");
+        if (!var_name) return L"\"error\":\"Missing variable name\"";
+        auto var = parser.vars().get(*var_name);
+        return L"\"value\":" + (var ? json_string_list(var->as_list()) : L"null");
+    } else if (name == L"cwd") {
+        return L"\"cwd\":" + json_string(parser.vars().get_pwd_slash());
+    } else if (name == L"jobs") {
+        wcstring jobs;
+        for (const auto &j : parser.jobs()) {
+            if (!j->is_visible()) continue;
+            if (!jobs.empty()) jobs.push_back(L',');
+            append_format(jobs, L"{\"id\":%d", j->job_id());
+            if (auto pgid = j->get_pgid()) append_format(jobs, L",\"pgid\":%d", *pgid);
+            jobs.append(j->is_stopped() ? L",\"stopped\":true" : L",\"stopped\":false");
+            jobs.append(L",\"command\":" + json_string(j->command()) + L"}");
+        }
+        return L"\"jobs\":[" + jobs + L"]";
+    } else if (name == L"eval") {
+        const wcstring *command = req.get_string(L"command");
+        if (!command) return L"\"error\":\"Missing command\"";
+        wcstring_list_t output;
+        int status = exec_subshell(*command, parser, output, false /* apply_exit_status */);
+        // The command may have changed what the prompt shows.
+        reader_schedule_prompt_repaint();
+        return format_string(L"\"status\":%d,\"output\":", status) + json_string_list(output);
+    } else if (name == L"input") {
+        // Text is queued as if it was typed, so it goes through the key bindings, and input
+        // functions are queued as if a binding invoked them.
+        std::vector<readline_cmd_t> functions;
+        if (const json_value_t *names = req.get(L"functions")) {
+            for (const json_value_t &func : names->array) {
+                auto code = input_function_get_code(func.string);
+                if (func.type != json_value_t::type_t::string || !code) {
+                    return L"\"error\":" +
+                           json_string(format_string(L"Unknown input function '%ls'",
+                                                     func.string.c_str()));
+                }
+                functions.push_back(*code);
+            }
+        }
+        if (const wcstring *text = req.get_string(L"text")) {
+            for (wchar_t c : *text) reader_queue_ch(c);
+        }
+        for (readline_cmd_t code : functions) reader_queue_ch(code);
+        return wcstring{};
+    }
+    return L"\"error\":" + json_string(format_string(L"Unknown request '%ls'", name.c_str()));
+}
+
+/// \return the line of JSON answering the request \p line from a client.
+static wcstring respond(const wcstring &line) {
+    wcstring id;
+    wcstring body;
+    auto req = json_parse(line);
+    if (!req || req->type != json_value_t::type_t::object) {
+        body = L"\"error\":\"Invalid JSON\"";
+    } else {
+        if (const json_value_t *id_val = req->get(L"id")) id = json_dump(*id_val);
+        const wcstring *name = req->get_string(L"request");
+        if (!name) {
+            body = L"\"error\":\"Missing request\"";
+        } else {
+            iothread_perform_on_main([&] {
+                body = handle_request(parser_t::principal_parser(), *name, *req);
+            });
+        }
+    }
+
+    wcstring result = L"{";
+    if (!id.empty()) result.append(L"\"id\":" + id);
+    if (!id.empty() && !body.empty()) result.push_back(L',');
+    result.append(body);
+    result.append(L"}\n");
+    return result;
+}
+
+/// Answer each line from the client connected on \p fd until it disconnects.
+static void serve_client(int fd) {
+    autoclose_fd_t conn(fd);
+    std::string buffer;
+    char buff[4096];
+    ssize_t amt;
+    while ((amt = read_loop(fd, buff, sizeof buff)) > 0) {
+        buffer.append(buff, amt);
+        size_t newline;
+        while ((newline = buffer.find('\n')) != std::string::npos) {
+            std::string response = wcs2string(respond(str2wcstring(buffer.substr(0, newline))));
+            buffer.erase(0, newline + 1);
+            if (write_loop(fd, response.data(), response.size()) < 0) return;
+        }
+    }
+}
+
+bool control_socket_listen(const wcstring &path) {
+    ASSERT_IS_MAIN_THREAD();
+    std::string narrow = wcs2string(path);
+    struct sockaddr_un addr = {};
+    addr.sun_family = AF_UNIX;
+    if (narrow.size() >= sizeof addr.sun_path) {
+        FLOGF(error, _(L"Socket path is too long: %ls"), path.c_str());
+        return false;
+    }
+    std::strcpy(addr.sun_path, narrow.c_str());
+
+    // Replace a socket left behind by an earlier session, but nothing else.
+    struct stat buf;
+    if (lstat(narrow.c_str(), &buf) == 0) {
+        if (!S_ISSOCK(buf.st_mode)) {
+            FLOGF(error, _(L"Could not listen on '%ls': %s"), path.c_str(),
+                  std::strerror(EEXIST));
+            return false;
+        }
+        unlink(narrow.c_str());
+    }
+
+    autoclose_fd_t fd(socket(AF_UNIX, SOCK_STREAM, 0));
+    if (!fd.valid() || set_cloexec(fd.fd())) {
+        wperror(L"socket");
+        return false;
+    }
+    // The permissions of the socket are all that keeps other users out, so create it without
+    // any for the group and others.
+    mode_t old_mask = umask(0177);
+    int bound = bind(fd.fd(), reinterpret_cast<struct sockaddr *>(&addr), sizeof addr);
+    umask(old_mask);
+    if (bound < 0 || listen(fd.fd(), 8) < 0) {
+        FLOGF(error, _(L"Could not listen on '%ls': %s"), path.c_str(), std::strerror(errno));
+        return false;
+    }
+    s_socket_path = path;
+
+    fd_set_purpose(fd.fd(), N_(L"--listen socket"));
+    int listen_fd = fd.acquire();
+    make_detached_pthread([listen_fd] {
+        for (;;) {
+            int conn = accept(listen_fd, nullptr, nullptr);
+            if (conn < 0) {
+                if (errno == EINTR || errno == ECONNABORTED) continue;
+                wperror(L"accept");
+                return;
+            }
+            set_cloexec(conn);
+            if (!make_detached_pthread([conn] { serve_client(conn); })) close(conn);
+        }
+    });
+    return true;
+}
+
+void control_socket_remove() {
+    ASSERT_IS_MAIN_THREAD();
+    if (s_socket_path.empty()) return;
+    wunlink(s_socket_path);
+    s_socket_path.clear();
+}
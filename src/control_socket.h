@@ -0,0 +1,17 @@
+// A socket on which other programs can query and control an interactive session, for
+// `fish --listen`.
+#ifndef FISH_CONTROL_SOCKET_H
+#define FISH_CONTROL_SOCKET_H
+
+#include "common.h"
+
+/// Listen on a Unix-domain socket at \p path, which only the user may connect to. Each line a
+/// client sends is a JSON request, answered with a line of JSON once the session is waiting for
+/// input. A stale socket at \p path is replaced.
+/// \return false and print an error if the socket could not be created.
+bool control_socket_listen(const wcstring &path);
+
+/// Remove the socket created by control_socket_listen, if any.
+void control_socket_remove();
+
+#endif
@@ -0,0 +1,140 @@
+// Writing a report when fish crashes.
+//
+// The signal handler may only call async-signal-safe functions, so everything it needs is kept
+// ready in fixed-size buffers, and it only uses open(), write() and friends.
+#include "config.h"  // IWYU pragma: keep
+
+#include "crash.h"
+
+#include <fcntl.h>
+#include <limits.h>
+#include <signal.h>
+#include <stdlib.h>
+#include <unistd.h>
+#ifdef HAVE_EXECINFO_H
+#include <execinfo.h>
+#endif
+
+#include <algorithm>
+#include <cstring>
+#include <string>
+
+#include "common.h"
+#include "fish_version.h"
+#include "flog.h"
+#include "path.h"
+
+/// The signals for which we write a report, with their names.
+static const struct {
+    int signal;
+    const char *name;
+} s_fatal_signals[] = {
+    {SIGSEGV, "SIGSEGV"}, {SIGBUS, "SIGBUS"}, {SIGILL, "SIGILL"},
+    {SIGFPE, "SIGFPE"},   {SIGABRT, "SIGABRT"},
+};
+
+/// The path of the report.
+static char s_report_path[PATH_MAX];
+
+/// The command being run, truncated to fit.
+static char s_command[1024];
+
+/// A stack for the handler, so a report can be written even if we ran out of stack.
+static char s_signal_stack[64 * 1024];
+
+/// Write the string \p str to \p fd.
+static void write_str(int fd, const char *str) { write_loop(fd, str, std::strlen(str)); }
+
+/// Write the number \p num to \p fd.
+static void write_num(int fd, unsigned long num) {
+    char buff[32];
+    char *end = buff + sizeof buff;
+    char *cursor = end;
+    do {
+        *--cursor = static_cast<char>('0' + num % 10);
+        num /= 10;
+    } while (num > 0);
+    write_loop(fd, cursor, end - cursor);
+}
+
+static void crash_handler(int sig) {
+    const char *name = "an unknown signal";
+    for (const auto &data : s_fatal_signals) {
+        if (data.signal == sig) name = data.name;
+    }
+
+    int fd = open(s_report_path, O_WRONLY | O_CREAT | O_TRUNC | O_CLOEXEC, 0600);
+    if (fd >= 0) {
+        write_str(fd, "fish ");
+        write_num(fd, static_cast<unsigned long>(getpid()));
+        write_str(fd, " crashed with ");
+        write_str(fd, name);
+        write_str(fd, "\nVersion: ");
+        write_str(fd, get_fish_version());
+        // init_curses exports TERM, so the environment has the current value.
+        const char *term = getenv("TERM");
+        write_str(fd, "\nTERM: ");
+        write_str(fd, term ? term : "");
+        write_str(fd, "\nCommand: ");
+        write_str(fd, s_command);
+        write_str(fd, "\n\nBacktrace:\n");
+#ifdef HAVE_BACKTRACE_SYMBOLS
+        void *callstack[128];
+        int n_frames = backtrace(callstack, sizeof callstack / sizeof callstack[0]);
+        backtrace_symbols_fd(callstack, n_frames, fd);
+#else
+        write_str(fd, "Sorry, but your system does not support backtraces\n");
+#endif
+        write_str(fd, "\nRecent debug output:\n");
+        flog_write_recent_lines(fd);
+        close(fd);
+
+        write_str(STDERR_FILENO, "fish: crashed with ");
+        write_str(STDERR_FILENO, name);
+        write_str(STDERR_FILENO, ". A report was written to ");
+        write_str(STDERR_FILENO, s_report_path);
+        write_str(STDERR_FILENO,
+                  "\nPlease attach it to a bug report at "
+                  "https://github.com/fish-shell/fish-shell/issues\n");
+    }
+
+    // The handler was reset to the default when it was called, so this kills us with the same
+    // signal once we return, and our parent sees what actually happened.
+    raise(sig);
+}
+
+void crash_report_init() {
+    wcstring dir;
+    if (!path_get_data(dir)) return;
+    std::string path = wcs2string(dir) + "/fish-crash-" + std::to_string(getpid()) + ".txt";
+    if (path.size() >= sizeof s_report_path) return;
+    std::strcpy(s_report_path, path.c_str());
+
+#ifdef HAVE_BACKTRACE_SYMBOLS
+    // The first call to backtrace() may load a library and allocate, which is not safe in the
+    // handler, so get it out of the way.
+    void *callstack[1];
+    backtrace(callstack, 1);
+#endif
+
+    stack_t stack = {};
+    stack.ss_sp = s_signal_stack;
+    stack.ss_size = sizeof s_signal_stack;
+    sigaltstack(&stack, nullptr);
+
+    struct sigaction act = {};
+    sigemptyset(&act.sa_mask);
+    act.sa_handler = crash_handler;
+    act.sa_flags = SA_RESETHAND | SA_ONSTACK;
+    for (const auto &data : s_fatal_signals) {
+        sigaction(data.signal, &act, nullptr);
+    }
+}
+
+void crash_report_set_command(const wcstring &cmd) {
+    ASSERT_IS_MAIN_THREAD();
+    std::string narrow = wcs2string(cmd);
+    size_t len = std::min(narrow.size(), sizeof s_command - 1);
+    std::memcpy(s_command, narrow.data(), len);
+    s_command[len] = '\0';
+}
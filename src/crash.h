@@ -0,0 +1,17 @@
+// Writing a report when fish crashes, so there is something to attach to a bug report.
+#ifndef FISH_CRASH_H
+#define FISH_CRASH_H
+
+#include "common.h"
+
+/// Install handlers for fatal signals like SIGSEGV and SIGABRT, which write a report with a
+/// backtrace, the fish version, $TERM, the command being run and the most recent debug output to
+/// a file in the data directory, print its path, and then let the signal kill fish as usual.
+/// This does nothing if there is no data directory.
+void crash_report_init();
+
+/// Remember \p cmd as the command being run, for the crash report.
+/// This should only be called on the main thread.
+void crash_report_set_command(const wcstring &cmd);
+
+#endif
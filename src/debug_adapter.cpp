@@ -0,0 +1,613 @@
+// A debugger for fish scripts, run by `fish --debug-adapter`.
+//
+// This speaks the Debug Adapter Protocol over a pair of file descriptors. The script runs on the
+// main thread as usual; the parser tells us before each job runs, and when we decide to stop we
+// handle the client's requests right there until it resumes execution. There is only ever the one
+// thread as far as the client is concerned.
+#include "config.h"  // IWYU pragma: keep
+
+#include "debug_adapter.h"
+
+#include <fcntl.h>
+#include <sys/select.h>
+#include <unistd.h>
+
+#include <algorithm>
+#include <chrono>
+#include <condition_variable>
+#include <map>
+#include <memory>
+#include <mutex>
+#include <set>
+#include <string>
+#include <vector>
+
+#include "common.h"
+#include "env.h"
+#include "expand.h"
+#include "fallback.h"  // IWYU pragma: keep
+#include "fds.h"
+#include "flog.h"
+#include "intern.h"
+#include "io.h"
+#include "iothread.h"
+#include "json.h"
+#include "parser.h"
+#include "proc.h"
+#include "reader.h"
+#include "wutil.h"  // IWYU pragma: keep
+
+namespace {
+/// The connection to the client. This is shared with the threads which forward the script's
+/// output, so sending is serialized.
+class channel_t {
+   public:
+    explicit channel_t(int out_fd) : out_fd(out_fd) {}
+
+    /// Send a message made of the JSON object members \p members, plus a sequence number.
+    void send(const wcstring &members) {
+        std::lock_guard<std::mutex> locker(lock);
+        json_write_message(out_fd, format_string(L"{\"seq\":%ld,", seq++) + members + L"}");
+    }
+
+    /// Send the event \p name with the JSON object \p body.
+    void event(const wchar_t *name, const wcstring &body) {
+        send(L"\"type\":\"event\",\"event\":" + json_string(name) + L",\"body\":" + body);
+    }
+
+   private:
+    const int out_fd;
+    std::mutex lock;
+    long seq{1};
+};
+
+/// Tracks the threads forwarding the script's output, so we can wait for them to finish.
+class forwarders_t {
+   public:
+    void started() {
+        std::lock_guard<std::mutex> locker(lock);
+        running++;
+    }
+
+    void finished() {
+        {
+            std::lock_guard<std::mutex> locker(lock);
+            running--;
+        }
+        cond.notify_all();
+    }
+
+    /// Wait for all output to be forwarded. Background processes may keep the pipes open, so don't
+    /// wait forever.
+    void wait() {
+        std::unique_lock<std::mutex> locker(lock);
+        cond.wait_for(locker, std::chrono::seconds(1), [this] { return running == 0; });
+    }
+
+   private:
+    std::mutex lock;
+    std::condition_variable cond;
+    int running{0};
+};
+
+/// \return the length of the longest prefix of \p str which does not end in the middle of a UTF-8
+/// sequence.
+size_t utf8_complete_length(const std::string &str) {
+    size_t len = str.size();
+    for (size_t back = 1; back <= 4 && back <= len; back++) {
+        auto c = static_cast<unsigned char>(str.at(len - back));
+        if ((c & 0xC0) == 0x80) continue;  // a continuation byte
+        size_t needed = c >= 0xF0 ? 4 : c >= 0xE0 ? 3 : c >= 0xC0 ? 2 : 1;
+        return needed > back ? len - back : len;
+    }
+    return len;
+}
+
+/// Create a pipe and a thread which sends everything written to it to the client as output events
+/// of type \p category. \return the write end of the pipe.
+autoclose_fd_t forward_output(const std::shared_ptr<channel_t> &channel,
+                              const std::shared_ptr<forwarders_t> &forwarders,
+                              const wchar_t *category) {
+    auto pipes = make_autoclose_pipes();
+    if (!pipes) return autoclose_fd_t{};
+    int read_fd = pipes->read.acquire();
+    forwarders->started();
+    bool spawned = make_detached_pthread([=] {
+        autoclose_fd_t read_end(read_fd);
+        std::string pending;
+        char buff[4096];
+        ssize_t amt;
+        while ((amt = read_loop(read_fd, buff, sizeof buff)) > 0) {
+            pending.append(buff, amt);
+            size_t len = utf8_complete_length(pending);
+            if (len == 0) continue;
+            channel->event(L"output", L"{\"category\":" + json_string(category) +
+                                          L",\"output\":" +
+                                          json_string(str2wcstring(pending.substr(0, len))) + L"}");
+            pending.erase(0, len);
+        }
+        if (!pending.empty()) {
+            channel->event(L"output", L"{\"category\":" + json_string(category) +
+                                          L",\"output\":" + json_string(str2wcstring(pending)) +
+                                          L"}");
+        }
+        forwarders->finished();
+    });
+    if (!spawned) {
+        close(read_fd);
+        forwarders->finished();
+        return autoclose_fd_t{};
+    }
+    return std::move(pipes->write);
+}
+
+/// The variables reference of each scope. Only the innermost frame has local variables, since
+/// those of the others are hidden by their callees.
+enum { scope_local = 1, scope_global, scope_universal };
+
+class debug_adapter_t final : public debugger_t {
+   public:
+    debug_adapter_t(parser_t &parser, int in_fd, int out_fd)
+        : parser(parser), in_fd(in_fd), channel(std::make_shared<channel_t>(out_fd)) {}
+
+    int run();
+
+    void will_run_job(parser_t &) override;
+    void breakpoint(parser_t &) override;
+
+   private:
+    /// How execution continues until it next stops.
+    enum class run_mode_t { run, entry, pause, step_in, step_over, step_out };
+
+    /// Where a job runs.
+    struct location_t {
+        /// The file, which is intern'd and may be null.
+        const wchar_t *filename{};
+        int lineno{-1};
+        /// The offset of the job in its source.
+        int offset{-1};
+        /// The number of function calls and sourced files above the top level.
+        size_t depth{0};
+    };
+
+    /// A frame of the call stack, innermost first.
+    struct frame_t {
+        wcstring name;
+        const wchar_t *filename;
+        int lineno;
+    };
+
+    parser_t &parser;
+    const int in_fd;
+    const std::shared_ptr<channel_t> channel;
+
+    /// Input which has been read but not yet handled.
+    std::string input;
+
+    /// The launch configuration.
+    wcstring program;
+    wcstring_list_t args;
+    wcstring cwd;
+    bool launched{false};
+    bool configured{false};
+
+    /// Line breakpoints, by resolved path.
+    std::map<wcstring, std::set<int>> breakpoints;
+
+    /// The resolved paths of files the parser has reported, by their intern'd names.
+    std::map<const wchar_t *, wcstring> resolved_paths;
+
+    run_mode_t mode{run_mode_t::run};
+    /// The depth at which the current step started.
+    size_t step_depth{0};
+    /// Where the previous job ran.
+    location_t last_location;
+
+    /// Whether we are stopped and handling requests, and the stack while we are.
+    bool stopped{false};
+    std::vector<frame_t> frames;
+
+    /// Whether the client has gone away or asked to end the script.
+    bool disconnected{false};
+
+    bool read_request(json_value_t *out);
+    void poll_requests();
+    void handle_request(const json_value_t &request);
+    void respond(const json_value_t &request, const wcstring &body = L"{}");
+    void respond_error(const json_value_t &request, const wcstring &message);
+
+    const wcstring &resolve_path(const wchar_t *filename);
+    location_t current_location() const;
+    std::vector<frame_t> get_frames() const;
+    void stop(const wchar_t *reason);
+
+    wcstring set_breakpoints(const json_value_t *arguments);
+    wcstring stack_trace();
+    wcstring scopes(const json_value_t *arguments) const;
+    wcstring variables(const json_value_t *arguments) const;
+
+    int run_program();
+};
+
+/// Read the next request into \p out. \return false at the end of input.
+bool debug_adapter_t::read_request(json_value_t *out) {
+    wcstring body;
+    while (json_read_message(in_fd, &input, &body)) {
+        auto message = json_parse(body);
+        if (message && message->type == json_value_t::type_t::object) {
+            const json_value_t *type = message->get(L"type");
+            if (type && type->type == json_value_t::type_t::string && type->string == L"request") {
+                *out = message.acquire();
+                return true;
+            }
+        }
+        FLOGF(warning, L"Ignoring malformed debug adapter message");
+    }
+    return false;
+}
+
+/// Handle any requests which have arrived while the script is running, without waiting for more.
+void debug_adapter_t::poll_requests() {
+    while (!disconnected) {
+        if (input.empty()) {
+            fd_set fds;
+            FD_ZERO(&fds);
+            FD_SET(in_fd, &fds);
+            struct timeval timeout = {};
+            if (select(in_fd + 1, &fds, nullptr, nullptr, &timeout) <= 0) return;
+        }
+        json_value_t request;
+        if (!read_request(&request)) {
+            disconnected = true;
+            return;
+        }
+        handle_request(request);
+    }
+}
+
+void debug_adapter_t::respond(const json_value_t &request, const wcstring &body) {
+    const json_value_t *seq = request.get(L"seq");
+    const json_value_t *command = request.get(L"command");
+    channel->send(L"\"type\":\"response\",\"request_seq\":" + json_dump(seq ? *seq : json_value_t{}) +
+                  L",\"success\":true,\"command\":" +
+                  json_dump(command ? *command : json_value_t{}) + L",\"body\":" + body);
+}
+
+void debug_adapter_t::respond_error(const json_value_t &request, const wcstring &message) {
+    const json_value_t *seq = request.get(L"seq");
+    const json_value_t *command = request.get(L"command");
+    channel->send(L"\"type\":\"response\",\"request_seq\":" + json_dump(seq ? *seq : json_value_t{}) +
+                  L",\"success\":false,\"command\":" +
+                  json_dump(command ? *command : json_value_t{}) +
+                  L",\"message\":" + json_string(message));
+}
+
+/// \return the absolute path of \p filename as reported by the parser, resolving symlinks so it
+/// can be compared with the paths of breakpoints.
+const wcstring &debug_adapter_t::resolve_path(const wchar_t *filename) {
+    auto iter = resolved_paths.find(filename);
+    if (iter == resolved_paths.end()) {
+        maybe_t<wcstring> real = wrealpath(filename);
+        iter = resolved_paths.emplace(filename, real ? real.acquire() : wcstring(filename)).first;
+    }
+    return iter->second;
+}
+
+debug_adapter_t::location_t debug_adapter_t::current_location() const {
+    location_t loc;
+    loc.filename = parser.current_filename();
+    loc.lineno = parser.get_lineno();
+    loc.offset = parser.get_source_offset();
+    for (const block_t &b : parser.blocks()) {
+        if (b.is_function_call() || b.type() == block_type_t::source) loc.depth++;
+    }
+    return loc;
+}
+
+std::vector<debug_adapter_t::frame_t> debug_adapter_t::get_frames() const {
+    std::vector<frame_t> result;
+    // Each function or source block records where it was called from, which is the location of
+    // the frame outside it.
+    const wchar_t *filename = parser.current_filename();
+    int lineno = parser.get_lineno();
+    for (const block_t &b : parser.blocks()) {
+        wcstring name;
+        if (b.is_function_call()) {
+            name = b.function_name;
+        } else if (b.type() == block_type_t::source) {
+            name = L"source " + (b.sourced_file ? wbasename(b.sourced_file) : wcstring());
+        } else {
+            continue;
+        }
+        result.push_back(frame_t{std::move(name), filename, lineno});
+        filename = b.src_filename;
+        lineno = b.src_lineno;
+    }
+    result.push_back(frame_t{wbasename(program), filename, lineno});
+    return result;
+}
+
+/// Stop the script, telling the client why, and handle requests until it resumes.
+void debug_adapter_t::stop(const wchar_t *reason) {
+    frames = get_frames();
+    stopped = true;
+    mode = run_mode_t::run;
+    channel->event(L"stopped", L"{\"reason\":" + json_string(reason) +
+                                   L",\"threadId\":1,\"allThreadsStopped\":true}");
+    while (stopped && !disconnected) {
+        json_value_t request;
+        if (!read_request(&request)) {
+            disconnected = true;
+            break;
+        }
+        handle_request(request);
+    }
+    stopped = false;
+    frames.clear();
+    if (disconnected) parser.libdata().exit_current_script = true;
+}
+
+void debug_adapter_t::will_run_job(parser_t &) {
+    poll_requests();
+    if (disconnected) {
+        parser.libdata().exit_current_script = true;
+        return;
+    }
+
+    // Jobs directly inside a command substitution have line numbers relative to the substitution,
+    // not to the file, so we can't tell where they are.
+    for (const block_t &b : parser.blocks()) {
+        if (b.type() == block_type_t::subst) return;
+        if (b.is_function_call() || b.type() == block_type_t::source) break;
+    }
+
+    // Several jobs on one line only count once, but a loop coming back around to a line does count.
+    location_t loc = current_location();
+    bool new_line = loc.filename != last_location.filename || loc.lineno != last_location.lineno ||
+                    loc.depth != last_location.depth || loc.offset <= last_location.offset;
+    last_location = loc;
+
+    const wchar_t *reason = nullptr;
+    switch (mode) {
+        case run_mode_t::run:
+            break;
+        case run_mode_t::entry:
+            reason = L"entry";
+            break;
+        case run_mode_t::pause:
+            reason = L"pause";
+            break;
+        case run_mode_t::step_in:
+            if (new_line) reason = L"step";
+            break;
+        case run_mode_t::step_over:
+            if (new_line && loc.depth <= step_depth) reason = L"step";
+            break;
+        case run_mode_t::step_out:
+            if (new_line && loc.depth < step_depth) reason = L"step";
+            break;
+    }
+    if (!reason && new_line && loc.filename) {
+        auto iter = breakpoints.find(resolve_path(loc.filename));
+        if (iter != breakpoints.end() && iter->second.count(loc.lineno)) reason = L"breakpoint";
+    }
+    if (reason) stop(reason);
+}
+
+void debug_adapter_t::breakpoint(parser_t &) {
+    if (!disconnected) stop(L"breakpoint");
+}
+
+wcstring debug_adapter_t::set_breakpoints(const json_value_t *arguments) {
+    const json_value_t *source = arguments ? arguments->get(L"source") : nullptr;
+    const json_value_t *path = source ? source->get(L"path") : nullptr;
+    if (!path || path->type != json_value_t::type_t::string) return L"{\"breakpoints\":[]}";
+
+    maybe_t<wcstring> real = wrealpath(path->string);
+    std::set<int> &lines = breakpoints[real ? *real : path->string];
+    lines.clear();
+    wcstring result = L"{\"breakpoints\":[";
+    const json_value_t *list = arguments->get(L"breakpoints");
+    if (list && list->type == json_value_t::type_t::array) {
+        for (const json_value_t &bp : list->array) {
+            const json_value_t *line = bp.get(L"line");
+            if (!line || line->type != json_value_t::type_t::number) continue;
+            int lineno = static_cast<int>(line->number);
+            lines.insert(lineno);
+            if (result.back() != L'[') result.push_back(L',');
+            append_format(result, L"{\"verified\":true,\"line\":%d}", lineno);
+        }
+    }
+    result.append(L"]}");
+    return result;
+}
+
+wcstring debug_adapter_t::stack_trace() {
+    wcstring result = L"{\"stackFrames\":[";
+    for (size_t i = 0; i < frames.size(); i++) {
+        const frame_t &frame = frames.at(i);
+        if (i > 0) result.push_back(L',');
+        append_format(result, L"{\"id\":%lu,\"name\":%ls,\"line\":%d,\"column\":1",
+                      static_cast<unsigned long>(i), json_string(frame.name).c_str(),
+                      std::max(frame.lineno, 0));
+        if (frame.filename) {
+            const wcstring &path = resolve_path(frame.filename);
+            result.append(L",\"source\":{\"name\":" + json_string(wbasename(path)) +
+                          L",\"path\":" + json_string(path) + L"}");
+        }
+        result.push_back(L'}');
+    }
+    append_format(result, L"],\"totalFrames\":%lu}", static_cast<unsigned long>(frames.size()));
+    return result;
+}
+
+wcstring debug_adapter_t::scopes(const json_value_t *arguments) const {
+    const json_value_t *frame_id = arguments ? arguments->get(L"frameId") : nullptr;
+    wcstring result = L"{\"scopes\":[";
+    if (frame_id && frame_id->number == 0) {
+        append_format(result, L"{\"name\":\"Local\",\"variablesReference\":%d,\"expensive\":false},",
+                      scope_local);
+    }
+    append_format(result,
+                  L"{\"name\":\"Global\",\"variablesReference\":%d,\"expensive\":false},"
+                  L"{\"name\":\"Universal\",\"variablesReference\":%d,\"expensive\":false}]}",
+                  scope_global, scope_universal);
+    return result;
+}
+
+wcstring debug_adapter_t::variables(const json_value_t *arguments) const {
+    const json_value_t *ref = arguments ? arguments->get(L"variablesReference") : nullptr;
+    int flags = 0;
+    switch (ref ? static_cast<int>(ref->number) : 0) {
+        case scope_local:
+            flags = ENV_LOCAL;
+            break;
+        case scope_global:
+            flags = ENV_GLOBAL;
+            break;
+        case scope_universal:
+            flags = ENV_UNIVERSAL;
+            break;
+        default:
+            return L"{\"variables\":[]}";
+    }
+    wcstring_list_t names = parser.vars().get_names(flags);
+    std::sort(names.begin(), names.end());
+    wcstring result = L"{\"variables\":[";
+    for (const wcstring &name : names) {
+        // The history is huge and not interesting here.
+        if (name == L"history") continue;
+        auto var = parser.vars().get(name, flags);
+        if (!var) continue;
+        if (result.back() != L'[') result.push_back(L',');
+        result.append(L"{\"name\":" + json_string(name) +
+                      L",\"value\":" + json_string(expand_escape_variable(*var)) +
+                      L",\"variablesReference\":0}");
+    }
+    result.append(L"]}");
+    return result;
+}
+
+void debug_adapter_t::handle_request(const json_value_t &request) {
+    const json_value_t *command_val = request.get(L"command");
+    const wcstring command = command_val ? command_val->string : wcstring();
+    const json_value_t *arguments = request.get(L"arguments");
+    auto get_arg = [&](const wchar_t *key) { return arguments ? arguments->get(key) : nullptr; };
+
+    if (command == L"initialize") {
+        respond(request,
+                L"{\"supportsConfigurationDoneRequest\":true,\"supportsTerminateRequest\":true}");
+        channel->event(L"initialized", L"{}");
+    } else if (command == L"launch") {
+        const json_value_t *prog = get_arg(L"program");
+        if (!prog || prog->type != json_value_t::type_t::string || prog->string.empty()) {
+            respond_error(request, _(L"No program to launch"));
+            return;
+        }
+        program = prog->string;
+        args.clear();
+        if (const json_value_t *list = get_arg(L"args")) {
+            for (const json_value_t &arg : list->array) args.push_back(arg.string);
+        }
+        if (const json_value_t *dir = get_arg(L"cwd")) cwd = dir->string;
+        const json_value_t *stop_on_entry = get_arg(L"stopOnEntry");
+        if (stop_on_entry && stop_on_entry->boolean) mode = run_mode_t::entry;
+        launched = true;
+        respond(request);
+    } else if (command == L"setBreakpoints") {
+        respond(request, set_breakpoints(arguments));
+    } else if (command == L"setExceptionBreakpoints") {
+        respond(request);
+    } else if (command == L"configurationDone") {
+        configured = true;
+        respond(request);
+    } else if (command == L"threads") {
+        respond(request, L"{\"threads\":[{\"id\":1,\"name\":\"fish\"}]}");
+    } else if (command == L"stackTrace") {
+        respond(request, stack_trace());
+    } else if (command == L"scopes") {
+        respond(request, scopes(arguments));
+    } else if (command == L"variables") {
+        respond(request, variables(arguments));
+    } else if (command == L"continue") {
+        mode = run_mode_t::run;
+        stopped = false;
+        respond(request, L"{\"allThreadsContinued\":true}");
+    } else if (command == L"next" || command == L"stepIn" || command == L"stepOut") {
+        mode = command == L"next"     ? run_mode_t::step_over
+               : command == L"stepIn" ? run_mode_t::step_in
+                                      : run_mode_t::step_out;
+        step_depth = current_location().depth;
+        stopped = false;
+        respond(request);
+    } else if (command == L"pause") {
+        if (!stopped) mode = run_mode_t::pause;
+        respond(request);
+    } else if (command == L"disconnect" || command == L"terminate") {
+        disconnected = true;
+        respond(request);
+    } else {
+        respond_error(request, format_string(_(L"Unsupported request '%ls'"), command.c_str()));
+    }
+}
+
+/// Run the launched program, forwarding its output. \return its exit status.
+int debug_adapter_t::run_program() {
+    // The script's stdout and stderr, including fish's own error messages, go to the client.
+    auto forwarders = std::make_shared<forwarders_t>();
+    autoclose_fd_t saved_stdout(fcntl(STDOUT_FILENO, F_DUPFD_CLOEXEC, k_first_high_fd));
+    autoclose_fd_t saved_stderr(fcntl(STDERR_FILENO, F_DUPFD_CLOEXEC, k_first_high_fd));
+    {
+        autoclose_fd_t out = forward_output(channel, forwarders, L"stdout");
+        autoclose_fd_t err = forward_output(channel, forwarders, L"stderr");
+        if (out.valid()) dup2(out.fd(), STDOUT_FILENO);
+        if (err.valid()) dup2(err.fd(), STDERR_FILENO);
+    }
+    cleanup_t restore_output([&] {
+        // This closes the last write ends of the pipes, unless a background process has them.
+        dup2(saved_stdout.fd(), STDOUT_FILENO);
+        dup2(saved_stderr.fd(), STDERR_FILENO);
+        forwarders->wait();
+    });
+
+    if (!cwd.empty()) {
+        parser.eval(L"builtin cd -- " + escape_string(cwd, ESCAPE_ALL), io_chain_t());
+    }
+    autoclose_fd_t fd(wopen_cloexec(program, O_RDONLY));
+    if (!fd.valid()) {
+        wperror(program.c_str());
+        return STATUS_CMD_UNKNOWN;
+    }
+    parser.vars().set(L"argv", ENV_DEFAULT, args);
+
+    auto &ld = parser.libdata();
+    scoped_push<const wchar_t *> filename_push{&ld.current_filename, intern(program.c_str())};
+    scoped_push<debugger_t *> debugger_push{&ld.debugger, this};
+    int res = reader_read(parser, fd.fd(), {});
+    ld.exit_current_script = false;
+    return res ? STATUS_CMD_UNKNOWN : parser.get_last_status();
+}
+
+int debug_adapter_t::run() {
+    json_value_t request;
+    while (!launched || !configured) {
+        if (!read_request(&request)) return 1;
+        handle_request(request);
+        if (disconnected) return 1;
+    }
+
+    int status = run_program();
+    channel->event(L"exited", format_string(L"{\"exitCode\":%d}", status));
+    channel->event(L"terminated", L"{}");
+
+    while (!disconnected && read_request(&request)) {
+        handle_request(request);
+    }
+    return status;
+}
+}  // namespace
+
+int debug_adapter_run(parser_t &parser, int in_fd, int out_fd) {
+    debug_adapter_t adapter(parser, in_fd, out_fd);
+    return adapter.run();
+}
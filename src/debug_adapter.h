@@ -0,0 +1,14 @@
+// A debugger for fish scripts, run by `fish --debug-adapter`.
+#ifndef FISH_DEBUG_ADAPTER_H
+#define FISH_DEBUG_ADAPTER_H
+
+class parser_t;
+
+/// Serve the Debug Adapter Protocol, reading requests from \p in_fd and writing responses and
+/// events to \p out_fd. The script named by the client's launch request is run with \p parser,
+/// stopping at breakpoints, `breakpoint` commands and steps, and its output is forwarded to the
+/// client. This returns once the client disconnects or closes the input.
+/// \return the exit status of the script, or 1 if it was never launched.
+int debug_adapter_run(parser_t &parser, int in_fd, int out_fd);
+
+#endif
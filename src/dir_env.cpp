@@ -0,0 +1,188 @@
+// Events for entering and leaving directories, and per-directory environments.
+#include "config.h"  // IWYU pragma: keep
+
+#include "dir_env.h"
+
+#include <fcntl.h>
+#include <unistd.h>
+
+#include <algorithm>
+#include <cwchar>
+#include <string>
+#include <utility>
+#include <vector>
+
+#include "common.h"
+#include "env.h"
+#include "event.h"
+#include "fallback.h"  // IWYU pragma: keep
+#include "global_safety.h"
+#include "io.h"
+#include "parser.h"
+#include "sha256.h"
+#include "wcstringutil.h"
+#include "wutil.h"  // IWYU pragma: keep
+
+namespace {
+/// A directory which was entered.
+struct entered_dir_t {
+    wcstring dir;
+
+    /// Whether its file is loaded.
+    bool loaded{false};
+
+    /// The global variables which loading the file changed, with their values from before, or
+    /// none if they did not exist.
+    std::vector<std::pair<wcstring, maybe_t<env_var_t>>> saved{};
+
+    explicit entered_dir_t(wcstring d) : dir(std::move(d)) {}
+
+    /// \return the path of the directory's file.
+    wcstring file() const { return dir == L"/" ? L"/" DIR_ENV_FILE : dir + L"/" DIR_ENV_FILE; }
+};
+}  // namespace
+
+/// The directories which were entered, from the root to the working directory at the last update.
+static mainthread_t<std::vector<entered_dir_t>> s_entered_dirs;
+
+maybe_t<wcstring> dir_env_allowed_entry(const wcstring &path) {
+    maybe_t<std::string> contents = read_file_contents(path);
+    if (!contents) return none();
+    return str2wcstring(sha256_hex(*contents)) + L" " + path;
+}
+
+bool dir_env_is_allowed(const environment_t &vars, const wcstring &path) {
+    auto allowed = vars.get(DIR_ENV_ALLOWED_VAR);
+    if (!allowed) return false;
+    maybe_t<wcstring> entry = dir_env_allowed_entry(path);
+    return entry && contains(allowed->as_list(), *entry);
+}
+
+/// \return the global variables which may be reverted, with their values.
+static var_table_t snapshot_globals(const environment_t &vars) {
+    var_table_t result;
+    for (const wcstring &name : vars.get_names(ENV_GLOBAL)) {
+        if (env_var_t::flags_for(name.c_str()) & env_var_t::flag_read_only) continue;
+        if (auto var = vars.get(name, ENV_GLOBAL)) result.emplace(name, *var);
+    }
+    return result;
+}
+
+/// Load the file of \p ed, if it is trusted. If it is not, say so if \p complain is set.
+static void load(parser_t &parser, entered_dir_t &ed, bool complain) {
+    wcstring path = ed.file();
+    if (waccess(path, R_OK) != 0) return;
+    if (!dir_env_is_allowed(parser.vars(), path)) {
+        if (complain) {
+            const wchar_t *fmt =
+                _(L"fish: '%ls' is not trusted. Run 'fish_envrc allow %ls' to load it.\n");
+            std::fwprintf(stderr, fmt, path.c_str(), escape_string(ed.dir, ESCAPE_ALL).c_str());
+        }
+        return;
+    }
+
+    var_table_t before = snapshot_globals(parser.vars());
+    parser.eval(L"source " + escape_string(path, ESCAPE_ALL), io_chain_t{});
+    var_table_t after = snapshot_globals(parser.vars());
+
+    ed.loaded = true;
+    ed.saved.clear();
+    for (const auto &kv : after) {
+        auto old = before.find(kv.first);
+        if (old == before.end()) {
+            ed.saved.emplace_back(kv.first, none());
+        } else if (old->second != kv.second) {
+            ed.saved.emplace_back(kv.first, old->second);
+        }
+    }
+    for (const auto &kv : before) {
+        if (!after.count(kv.first)) ed.saved.emplace_back(kv.first, kv.second);
+    }
+}
+
+/// Revert the variables which loading the file of \p ed changed.
+static void revert(parser_t &parser, entered_dir_t &ed) {
+    for (auto iter = ed.saved.rbegin(); iter != ed.saved.rend(); ++iter) {
+        const wcstring &name = iter->first;
+        const maybe_t<env_var_t> &old = iter->second;
+        if (old) {
+            env_mode_flags_t mode = ENV_GLOBAL;
+            mode |= old->exports() ? ENV_EXPORT : ENV_UNEXPORT;
+            mode |= old->is_pathvar() ? ENV_PATHVAR : ENV_UNPATHVAR;
+            parser.set_var_and_fire(name, mode, old->as_list());
+        } else {
+            std::vector<event_t> events;
+            parser.vars().remove(name, ENV_GLOBAL, &events);
+            for (const event_t &evt : events) event_fire(parser, evt);
+        }
+    }
+    ed.loaded = false;
+    ed.saved.clear();
+}
+
+/// \return the directories from the root to the working directory.
+static wcstring_list_t working_dirs(const environment_t &vars) {
+    wcstring_list_t result{L"/"};
+    wcstring dir;
+    for (const wcstring &component : split_string(vars.get_pwd_slash(), L'/')) {
+        if (component.empty()) continue;
+        dir += L"/" + component;
+        result.push_back(dir);
+    }
+    return result;
+}
+
+void dir_env_update(parser_t &parser) {
+    std::vector<entered_dir_t> &entered = s_entered_dirs;
+    wcstring_list_t dirs = working_dirs(parser.vars());
+    size_t common = 0;
+    while (common < entered.size() && common < dirs.size() && entered[common].dir == dirs[common]) {
+        common++;
+    }
+
+    // Leave the innermost directories first, and enter the outermost first, so each directory's
+    // file is loaded in the environment of those around it. Handlers see the variables from the
+    // files of the directories they are called for.
+    while (entered.size() > common) {
+        wcstring_list_t args{entered.back().dir};
+        event_fire_generic(parser, L"fish_dir_leave", &args);
+        revert(parser, entered.back());
+        entered.pop_back();
+    }
+    for (size_t i = common; i < dirs.size(); i++) {
+        entered.emplace_back(dirs[i]);
+        load(parser, entered.back(), true);
+        wcstring_list_t args{dirs[i]};
+        event_fire_generic(parser, L"fish_dir_enter", &args);
+    }
+}
+
+void dir_env_reload(parser_t &parser) {
+    std::vector<entered_dir_t> &entered = s_entered_dirs;
+    size_t first = entered.size();
+    for (size_t i = 0; i < entered.size(); i++) {
+        wcstring path = entered[i].file();
+        bool trusted = waccess(path, R_OK) == 0 && dir_env_is_allowed(parser.vars(), path);
+        if (trusted != entered[i].loaded) {
+            first = i;
+            break;
+        }
+    }
+
+    // The files of the directories inside are loaded again too, as they were loaded after it.
+    for (size_t i = entered.size(); i > first; i--) revert(parser, entered[i - 1]);
+    for (size_t i = first; i < entered.size(); i++) load(parser, entered[i], false);
+}
+
+std::vector<dir_env_file_t> dir_env_files() {
+    std::vector<dir_env_file_t> result;
+    for (const entered_dir_t &ed : static_cast<std::vector<entered_dir_t> &>(s_entered_dirs)) {
+        wcstring path = ed.file();
+        if (!ed.loaded && waccess(path, F_OK) != 0) continue;
+        wcstring_list_t changed;
+        for (const auto &kv : ed.saved) changed.push_back(kv.first);
+        std::sort(changed.begin(), changed.end());
+        result.push_back(dir_env_file_t{std::move(path), ed.loaded, std::move(changed)});
+    }
+    return result;
+}
@@ -0,0 +1,53 @@
+// Events for entering and leaving directories, and per-directory environments.
+//
+// When the working directory changes, fish_dir_leave is emitted for each directory which was left
+// and fish_dir_enter for each one which was entered, with the directory as the argument. The
+// .envrc.fish file of an entered directory is loaded if it is trusted: its SHA-256 hash and path
+// are in the universal fish_envrc_allowed variable. The global variables which loading it
+// changed are reverted when the directory is left.
+#ifndef FISH_DIR_ENV_H
+#define FISH_DIR_ENV_H
+
+#include <vector>
+
+#include "common.h"
+#include "maybe.h"
+
+class environment_t;
+class parser_t;
+
+/// The name of the file which is loaded when its directory is entered.
+#define DIR_ENV_FILE L".envrc.fish"
+
+/// The universal variable with the trusted files, with elements like "HASH PATH".
+#define DIR_ENV_ALLOWED_VAR L"fish_envrc_allowed"
+
+/// Emit the events for the directories which were left and entered since the last call, and
+/// revert or load their files. This is called before the prompt.
+void dir_env_update(parser_t &parser);
+
+/// Load or revert the files of the directories which were entered again, if it changed whether
+/// they are trusted, like after `fish_envrc allow`.
+void dir_env_reload(parser_t &parser);
+
+/// \return the element of fish_envrc_allowed which trusts the file at \p path as it is now, or
+/// none if it can't be read.
+maybe_t<wcstring> dir_env_allowed_entry(const wcstring &path);
+
+/// \return whether the file at \p path is trusted in \p vars.
+bool dir_env_is_allowed(const environment_t &vars, const wcstring &path);
+
+/// A directory which was entered and has a file.
+struct dir_env_file_t {
+    /// The path of the file.
+    wcstring path;
+    /// Whether it is loaded.
+    bool loaded;
+    /// The variables loading it changed, which are reverted when its directory is left.
+    wcstring_list_t changed_vars;
+};
+
+/// \return the files of the directories which were entered, the outermost first.
+std::vector<dir_env_file_t> dir_env_files();
+
+#endif
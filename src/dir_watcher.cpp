@@ -0,0 +1,145 @@
+// Watching directories for changes, so their listings can be cached.
+#include "config.h"  // IWYU pragma: keep
+
+#include "dir_watcher.h"
+
+#include <errno.h>
+#include <fcntl.h>
+#include <unistd.h>
+#if defined(HAVE_INOTIFY_INIT1)
+#include <sys/inotify.h>
+#elif defined(HAVE_KQUEUE)
+#include <sys/event.h>
+#include <sys/time.h>
+#include <sys/types.h>
+#endif
+
+#include <algorithm>
+
+#include "fds.h"
+#include "flog.h"
+#include "wutil.h"  // IWYU pragma: keep
+
+#if defined(HAVE_INOTIFY_INIT1)
+
+dir_watcher_t::dir_watcher_t(const wcstring_list_t &dirs)
+    : watched_(dirs.size(), false), watch_descriptors_(dirs.size(), -1) {
+    inotify_fd_ = heightenize_fd(autoclose_fd_t{inotify_init1(IN_NONBLOCK | IN_CLOEXEC)}, true);
+    if (!inotify_fd_.valid()) return;
+    fd_set_purpose(inotify_fd_.fd(), N_(L"directory watcher"));
+    const uint32_t mask = IN_CREATE | IN_DELETE | IN_MOVED_FROM | IN_MOVED_TO | IN_MODIFY |
+                          IN_ATTRIB | IN_DELETE_SELF | IN_MOVE_SELF | IN_ONLYDIR;
+    for (size_t i = 0; i < dirs.size(); i++) {
+        int wd = inotify_add_watch(inotify_fd_.fd(), wcs2string(dirs[i]).c_str(), mask);
+        watch_descriptors_[i] = wd;
+        watched_[i] = wd >= 0;
+        FLOGF(path, L"Watching '%ls' with inotify: %s", dirs[i].c_str(), wd >= 0 ? "yes" : "no");
+    }
+}
+
+int dir_watcher_t::fd() const { return inotify_fd_.fd(); }
+
+std::vector<size_t> dir_watcher_t::take_changed() {
+    std::vector<size_t> changed;
+    if (!inotify_fd_.valid()) return changed;
+    // The same directory may be in the list several times, and then they share a descriptor.
+    auto mark_changed = [&](int wd, bool removed) {
+        for (size_t i = 0; i < watch_descriptors_.size(); i++) {
+            if (watch_descriptors_[i] != wd) continue;
+            changed.push_back(i);
+            if (removed) {
+                watch_descriptors_[i] = -1;
+                watched_[i] = false;
+            }
+        }
+    };
+
+    alignas(struct inotify_event) char buff[4096];
+    for (;;) {
+        ssize_t amt = read(inotify_fd_.fd(), buff, sizeof buff);
+        if (amt < 0 && errno == EINTR) continue;
+        if (amt <= 0) break;
+        for (ssize_t offset = 0; offset < amt;) {
+            const auto *event = reinterpret_cast<const struct inotify_event *>(buff + offset);
+            offset += sizeof(struct inotify_event) + event->len;
+            if (event->mask & IN_Q_OVERFLOW) {
+                // Events were lost, so anything could have changed.
+                for (size_t i = 0; i < watched_.size(); i++) {
+                    if (watched_[i]) changed.push_back(i);
+                }
+            } else {
+                mark_changed(event->wd, event->mask & IN_IGNORED);
+            }
+        }
+    }
+    std::sort(changed.begin(), changed.end());
+    changed.erase(std::unique(changed.begin(), changed.end()), changed.end());
+    return changed;
+}
+
+#elif defined(HAVE_KQUEUE)
+
+#ifndef O_EVTONLY
+#define O_EVTONLY O_RDONLY
+#endif
+
+dir_watcher_t::dir_watcher_t(const wcstring_list_t &dirs) : watched_(dirs.size(), false) {
+    kqueue_fd_ = heightenize_fd(autoclose_fd_t{kqueue()}, false);
+    if (!kqueue_fd_.valid()) return;
+    fd_set_purpose(kqueue_fd_.fd(), N_(L"directory watcher"));
+    for (size_t i = 0; i < dirs.size(); i++) {
+        autoclose_fd_t fd{wopen_cloexec(dirs[i], O_EVTONLY | O_DIRECTORY)};
+        fd = heightenize_fd(std::move(fd), true);
+        if (fd.valid()) {
+            struct kevent change;
+            EV_SET(&change, fd.fd(), EVFILT_VNODE, EV_ADD | EV_CLEAR,
+                   NOTE_WRITE | NOTE_EXTEND | NOTE_ATTRIB | NOTE_DELETE | NOTE_RENAME | NOTE_REVOKE,
+                   0, 0);
+            watched_[i] = kevent(kqueue_fd_.fd(), &change, 1, nullptr, 0, nullptr) == 0;
+        }
+        if (!watched_[i]) fd.close();
+        dir_fds_.push_back(std::move(fd));
+        FLOGF(path, L"Watching '%ls' with kqueue: %s", dirs[i].c_str(),
+              watched_[i] ? "yes" : "no");
+    }
+}
+
+int dir_watcher_t::fd() const { return kqueue_fd_.fd(); }
+
+std::vector<size_t> dir_watcher_t::take_changed() {
+    std::vector<size_t> changed;
+    if (!kqueue_fd_.valid()) return changed;
+    const struct timespec no_wait = {0, 0};
+    struct kevent events[16];
+    int count;
+    while ((count = kevent(kqueue_fd_.fd(), nullptr, 0, events, 16, &no_wait)) > 0) {
+        for (int e = 0; e < count; e++) {
+            for (size_t i = 0; i < dir_fds_.size(); i++) {
+                if (!watched_[i] || static_cast<uintptr_t>(dir_fds_[i].fd()) != events[e].ident) {
+                    continue;
+                }
+                changed.push_back(i);
+                if (events[e].fflags & (NOTE_DELETE | NOTE_RENAME | NOTE_REVOKE)) {
+                    // The directory is gone, or somewhere else now.
+                    watched_[i] = false;
+                    dir_fds_[i].close();
+                }
+            }
+        }
+    }
+    std::sort(changed.begin(), changed.end());
+    changed.erase(std::unique(changed.begin(), changed.end()), changed.end());
+    return changed;
+}
+
+#else
+
+dir_watcher_t::dir_watcher_t(const wcstring_list_t &dirs) : watched_(dirs.size(), false) {}
+
+int dir_watcher_t::fd() const { return -1; }
+
+std::vector<size_t> dir_watcher_t::take_changed() { return {}; }
+
+#endif
+
+dir_watcher_t::~dir_watcher_t() = default;
@@ -0,0 +1,53 @@
+// Watching directories for changes, so their listings can be cached.
+#ifndef FISH_DIR_WATCHER_H
+#define FISH_DIR_WATCHER_H
+
+#include "config.h"  // IWYU pragma: keep
+
+#include <vector>
+
+#include "common.h"
+#include "fds.h"
+
+/// dir_watcher_t watches a list of directories for entries being created, removed, renamed or
+/// changed, using inotify or kqueue where available. kqueue does not report changes to the
+/// contents of files, only to the entries.
+///
+/// Directories which can't be watched, because there is no way to on this system or because they
+/// don't exist, are not reported; the caller has to check them itself.
+class dir_watcher_t {
+   public:
+    /// Start watching \p dirs.
+    explicit dir_watcher_t(const wcstring_list_t &dirs);
+    ~dir_watcher_t();
+
+    dir_watcher_t(const dir_watcher_t &) = delete;
+    void operator=(const dir_watcher_t &) = delete;
+
+    /// \return an fd which becomes readable when there are changes to take, or -1 if there is
+    /// none, so the caller can wait for changes with select().
+    int fd() const;
+
+    /// \return whether changes to the directory at index \p idx are reported.
+    bool is_watched(size_t idx) const { return watched_.at(idx); }
+
+    /// \return the indexes of the directories which changed since the last call, without
+    /// blocking. A directory which was removed is reported once, and is not watched after that.
+    std::vector<size_t> take_changed();
+
+   private:
+    /// Whether each directory is watched.
+    std::vector<bool> watched_;
+
+#if defined(HAVE_INOTIFY_INIT1)
+    /// The inotify instance, and the watch descriptor of each directory.
+    autoclose_fd_t inotify_fd_;
+    std::vector<int> watch_descriptors_;
+#elif defined(HAVE_KQUEUE)
+    /// The kqueue, and an fd for each directory which is registered with it.
+    autoclose_fd_t kqueue_fd_;
+    std::vector<autoclose_fd_t> dir_fds_;
+#endif
+};
+
+#endif
@@ -0,0 +1,291 @@
+// Checking the environment fish runs in for common problems, for `fish --doctor`.
+//
+// Each check looks at one part of the environment and adds what it finds wrong to a list, along
+// with a suggestion how to fix it. The checks run after the config files are read, so they see
+// what an interactive session would.
+#include "config.h"  // IWYU pragma: keep
+
+#include "doctor.h"
+
+#include <errno.h>
+#include <locale.h>
+#include <stdlib.h>
+#include <sys/stat.h>
+#include <unistd.h>
+
+#include <algorithm>
+#include <cstring>
+#include <cwchar>
+#include <string>
+#include <unordered_map>
+#include <unordered_set>
+#include <vector>
+
+#include "common.h"
+#include "env.h"
+#include "env_dispatch.h"
+#include "env_universal_common.h"
+#include "fallback.h"  // IWYU pragma: keep
+#include "flog.h"
+#include "parser.h"
+#include "path.h"
+#include "wcstringutil.h"
+#include "wutil.h"  // IWYU pragma: keep
+
+namespace {
+/// Something wrong found by a check, and how to fix it.
+struct problem_t {
+    wcstring description;
+    wcstring fix;
+};
+using problem_list_t = std::vector<problem_t>;
+}  // namespace
+
+static void check_terminal(const environment_t &vars, problem_list_t &problems) {
+    auto term = vars.get(L"TERM");
+    if (term.missing_or_empty()) {
+        problems.push_back({_(L"$TERM is not set, so fish does not know what the terminal can do."),
+                            _(L"Make sure the terminal emulator sets TERM.")});
+        return;
+    }
+
+    // This tells us what happened when curses was set up, including whether the fallbacks for
+    // unknown terminals were needed.
+    auto fallback = env_dispatch_get_term_fallback();
+    if (!fallback) return;
+    wcstring description;
+    if (fallback->empty()) {
+        description = format_string(_(L"The terminal type '%ls' from $TERM is unknown, and the "
+                                       L"fallback types did not work either."),
+                                     term->as_string().c_str());
+    } else {
+        description = format_string(
+            _(L"The terminal type '%ls' from $TERM is unknown, so fish uses '%ls', which has "
+              L"fewer features."),
+            term->as_string().c_str(), fallback->c_str());
+    }
+    problems.push_back({description,
+                        _(L"Install the terminfo entry for the terminal, which is often in a "
+                          L"package like ncurses-term, or set TERM to a known type like "
+                          L"xterm-256color.")});
+}
+
+static void check_locale(const environment_t &vars, problem_list_t &problems) {
+    for (const wchar_t *name : {L"LC_ALL", L"LC_CTYPE", L"LC_MESSAGES", L"LANG"}) {
+        auto var = vars.get(name, ENV_EXPORT);
+        if (var.missing_or_empty()) continue;
+        std::string value = wcs2string(var->as_string());
+        if (locale_t loc = newlocale(LC_ALL_MASK, value.c_str(), nullptr)) {
+            freelocale(loc);
+            continue;
+        }
+        problems.push_back(
+            {format_string(_(L"The locale '%ls' in $%ls is not available on this system."),
+                           var->as_string().c_str(), name),
+             format_string(_(L"Set $%ls to a locale listed by 'locale -a', or generate it, for "
+                             L"example with locale-gen."),
+                           name)});
+    }
+
+    if (MB_CUR_MAX == 1) {
+        problems.push_back({_(L"The locale does not use UTF-8, so fish can only show ASCII "
+                              L"characters."),
+                            _(L"Set $LANG to a UTF-8 locale, like en_US.UTF-8.")});
+    }
+}
+
+/// Check the \p which directory \p dir, which can be chosen with the variable \p xdg_var.
+static void check_directory(const base_directory_t &dir, const wchar_t *which,
+                            const wchar_t *xdg_var, problem_list_t &problems) {
+    wcstring fix = format_string(_(L"Set $%ls to a directory where you have write access."),
+                                 xdg_var);
+    if (dir.path.empty()) {
+        problems.push_back(
+            {format_string(_(L"There is no %ls directory, because neither $%ls nor $HOME is set."),
+                           which, xdg_var),
+             fix});
+        return;
+    }
+
+    int err = dir.success ? 0 : dir.err;
+    if (dir.success && waccess(dir.path, W_OK) != 0) err = errno;
+    if (!err) return;
+    problems.push_back({format_string(_(L"The %ls directory '%ls' is not usable: %s"), which,
+                                      dir.path.c_str(), std::strerror(err)),
+                        fix});
+}
+
+static void check_directories(const environment_t &, problem_list_t &problems) {
+    check_directory(get_config_directory(), L"config", L"XDG_CONFIG_HOME", problems);
+    check_directory(get_data_directory(), L"data", L"XDG_DATA_HOME", problems);
+}
+
+static void check_universal_variables(const environment_t &, problem_list_t &problems) {
+    const auto &config = get_config_directory();
+    if (!config.success) return;
+    wcstring path = config.path + L"/fish_variables";
+    maybe_t<std::string> contents = read_file_contents(path);
+    if (!contents) {
+        // There is no file until the first universal variable is set.
+        if (errno == ENOENT) return;
+        problems.push_back(
+            {format_string(_(L"The universal variable file '%ls' can not be read: %s"),
+                           path.c_str(), std::strerror(errno)),
+             _(L"Make sure it belongs to you and is readable.")});
+        return;
+    }
+
+    var_table_t vars;
+    std::vector<size_t> bad_lines;
+    uvar_format_t format = env_universal_t::populate_variables(*contents, &vars, &bad_lines);
+    if (format == uvar_format_t::future) {
+        problems.push_back(
+            {format_string(_(L"The universal variable file '%ls' was written by a newer version "
+                             L"of fish."),
+                           path.c_str()),
+             _(L"Use the same version of fish everywhere the file is shared.")});
+    }
+    if (!bad_lines.empty()) {
+        wcstring numbers;
+        for (size_t line : bad_lines) {
+            if (!numbers.empty()) numbers.append(L", ");
+            numbers.append(to_string(line));
+        }
+        problems.push_back(
+            {format_string(_(L"The universal variable file '%ls' has lines that can not be "
+                             L"read: %ls"),
+                           path.c_str(), numbers.c_str()),
+             _(L"Fix or remove those lines. fish drops them the next time it saves the file.")});
+    }
+}
+
+static void check_conf_dirs(const environment_t &vars, problem_list_t &problems) {
+    // config.fish sources the first snippet with each name, in the order of these directories.
+    auto dirs = vars.get(L"__fish_conf_dirs");
+    if (dirs.missing_or_empty()) return;
+    std::unordered_map<wcstring, wcstring> loaded;
+    for (const wcstring &dir : dirs->as_list()) {
+        DIR *d = wopendir(dir);
+        if (!d) continue;
+        wcstring_list_t names;
+        wcstring entry;
+        while (wreaddir(d, entry)) {
+            if (string_suffixes_string(L".fish", entry)) names.push_back(entry);
+        }
+        closedir(d);
+        std::sort(names.begin(), names.end());
+
+        for (const wcstring &name : names) {
+            wcstring path = dir + L'/' + name;
+            auto first = loaded.emplace(name, path);
+            if (first.second) continue;
+            // A snippet which isn't a readable file is how one masks a snippet of the same name
+            // on purpose, so it isn't a problem.
+            struct stat buf;
+            const wcstring &other = first.first->second;
+            if (wstat(other, &buf) != 0 || !S_ISREG(buf.st_mode) || waccess(other, R_OK) != 0) {
+                continue;
+            }
+            problems.push_back(
+                {format_string(_(L"The conf.d snippet '%ls' is not loaded, because '%ls' has the "
+                                 L"same name."),
+                               path.c_str(), other.c_str()),
+                 _(L"Rename one of them if both should run, or remove the one that isn't "
+                   L"needed.")});
+        }
+    }
+}
+
+static void check_path(const environment_t &vars, problem_list_t &problems) {
+    auto path = vars.get(L"PATH");
+    if (path.missing_or_empty()) {
+        problems.push_back({_(L"$PATH is empty, so fish can not find any commands."),
+                            _(L"Check the config files for a line that clears PATH.")});
+        return;
+    }
+
+    std::unordered_set<wcstring> seen;
+    for (const wcstring &dir : path->as_list()) {
+        if (dir.empty() || dir.front() != L'/') {
+            problems.push_back(
+                {format_string(_(L"$PATH contains the relative directory '%ls', so which "
+                                 L"commands are found depends on the current directory."),
+                               dir.c_str()),
+                 _(L"Remove it from $PATH, or use an absolute path instead.")});
+        } else if (!seen.insert(dir).second) {
+            problems.push_back(
+                {format_string(_(L"$PATH contains '%ls' more than once."), dir.c_str()),
+                 _(L"Check that the config files add it only once, including via "
+                   L"$fish_user_paths.")});
+        } else {
+            struct stat buf;
+            if (wstat(dir, &buf) != 0) {
+                problems.push_back(
+                    {format_string(_(L"$PATH contains '%ls', which does not exist."), dir.c_str()),
+                     _(L"Remove it from $PATH.")});
+            } else if (!S_ISDIR(buf.st_mode)) {
+                problems.push_back({format_string(_(L"$PATH contains '%ls', which is not a "
+                                                    L"directory."),
+                                                  dir.c_str()),
+                                    _(L"Remove it from $PATH.")});
+            }
+        }
+    }
+}
+
+/// Report the errors and warnings which were logged recently, e.g. while reading the config.
+static void check_logged_warnings(const environment_t &, problem_list_t &problems) {
+    const auto &cats = *flog_details::category_list_t::g_instance;
+    for (const flog_recent_line_t &line : flog_get_recent_lines()) {
+        if (line.category != &cats.error && line.category != &cats.warning &&
+            line.category != &cats.warning_path) {
+            continue;
+        }
+        problems.push_back({line.text, format_string(_(L"Fix what the message describes. It was "
+                                                       L"logged to the '%ls' debug category."),
+                                                     line.category->name)});
+    }
+}
+
+/// The checks, in the order they are run.
+static const struct {
+    const wchar_t *name;
+    void (*check)(const environment_t &vars, problem_list_t &problems);
+} s_checks[] = {
+    {N_(L"terminal"), check_terminal},
+    {N_(L"locale"), check_locale},
+    {N_(L"config and data directories"), check_directories},
+    {N_(L"universal variables"), check_universal_variables},
+    {N_(L"conf.d snippets"), check_conf_dirs},
+    {N_(L"PATH"), check_path},
+    {N_(L"logged errors and warnings"), check_logged_warnings},
+};
+
+int doctor_run(parser_t &parser) {
+    size_t total = 0;
+    for (const auto &check : s_checks) {
+        problem_list_t problems;
+        check.check(parser.vars(), problems);
+        std::fwprintf(stdout, _(L"Checking %ls: "), _(check.name));
+        if (problems.empty()) {
+            std::fwprintf(stdout, _(L"ok\n"));
+            continue;
+        }
+        unsigned long count = problems.size();
+        std::fwprintf(stdout, P_(L"%lu problem\n", L"%lu problems\n", count), count);
+        for (const problem_t &problem : problems) {
+            std::fwprintf(stdout, L"    %ls\n", problem.description.c_str());
+            std::fwprintf(stdout, _(L"    Fix: %ls\n"), problem.fix.c_str());
+        }
+        total += problems.size();
+    }
+
+    if (total == 0) {
+        std::fwprintf(stdout, _(L"No problems found.\n"));
+    } else {
+        unsigned long count = total;
+        std::fwprintf(stdout, P_(L"Found %lu problem.\n", L"Found %lu problems.\n", count), count);
+    }
+    std::fflush(stdout);
+    return total == 0 ? 0 : 1;
+}
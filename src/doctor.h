@@ -0,0 +1,12 @@
+// Checking the environment fish runs in for common problems, for `fish --doctor`.
+#ifndef FISH_DOCTOR_H
+#define FISH_DOCTOR_H
+
+class parser_t;
+
+/// Check the terminal, locale, config and data directories, universal variable file, conf.d
+/// snippets and $PATH, and print each problem with a suggested fix.
+/// \return 0 if there were no problems, and 1 otherwise.
+int doctor_run(parser_t &parser);
+
+#endif
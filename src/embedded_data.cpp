@@ -0,0 +1,76 @@
+// Fish's data files built into fish, for a self-contained binary.
+#include "config.h"  // IWYU pragma: keep
+
+#include "embedded_data.h"
+
+#include <errno.h>
+#include <unistd.h>
+
+#include <cstdio>
+#include <cstdlib>
+#include <cstring>
+#include <string>
+#include <vector>
+
+#include "env.h"
+#include "fish_version.h"
+#include "flog.h"
+#include "path.h"
+#include "wutil.h"  // IWYU pragma: keep
+
+#if FISH_EMBED_DATA
+#include "embedded_data_files.h"
+
+/// The file in an install of the embedded data which holds the version of fish that wrote it.
+static const char *const version_file = ".fish-version";
+
+/// Write the embedded files to \p dir, unless they are there already. \return whether that worked.
+static bool install_to(const std::string &dir) {
+    const std::string version = get_fish_version();
+    maybe_t<std::string> installed = read_file_contents(dir + "/" + version_file);
+    if (installed && *installed == version) return true;
+
+    FLOGF(config, L"Writing the embedded data files to '%s'", dir.c_str());
+    for (const embedded_file_t &file : embedded_files) {
+        std::string path = dir + "/" + file.name;
+        int err = create_directory(str2wcstring(path.substr(0, path.rfind('/')))) != 0
+                      ? errno
+                      : write_file_atomically(path, std::string(file.contents, file.size), 0644);
+        if (err) {
+            FLOGF(config, L"Could not write '%s': %s", path.c_str(), std::strerror(err));
+            return false;
+        }
+    }
+    // This is written last, so an install which was interrupted is done again.
+    return write_file_atomically(dir + "/" + version_file, version, 0644) == 0;
+}
+
+bool embedded_data_available() { return true; }
+
+maybe_t<wcstring> embedded_data_install() {
+    // Prefer the user's data directory, so the files are only written once. The runtime directory
+    // is for when there is no writable home, like on rescue systems.
+    std::vector<std::string> dirs;
+    const char *xdg_data_home = getenv("XDG_DATA_HOME");
+    const char *home = getenv("HOME");
+    if (xdg_data_home && xdg_data_home[0]) {
+        dirs.push_back(std::string(xdg_data_home) + "/fish/embedded");
+    } else if (home && home[0]) {
+        dirs.push_back(std::string(home) + "/.local/share/fish/embedded");
+    }
+    wcstring runtime = env_get_runtime_path();
+    if (!runtime.empty()) dirs.push_back(wcs2string(runtime) + "/fish-embedded");
+
+    for (const std::string &dir : dirs) {
+        if (install_to(dir)) return str2wcstring(dir);
+    }
+    return none();
+}
+
+#else
+
+bool embedded_data_available() { return false; }
+
+maybe_t<wcstring> embedded_data_install() { return none(); }
+
+#endif
@@ -0,0 +1,19 @@
+// Fish's data files built into fish, for a self-contained binary.
+#ifndef FISH_EMBEDDED_DATA_H
+#define FISH_EMBEDDED_DATA_H
+
+#include "common.h"
+#include "maybe.h"
+
+/// \return whether fish was built with its data files embedded, with the FISH_EMBED_DATA CMake
+/// option.
+bool embedded_data_available();
+
+/// Write the embedded data files to a directory of the user's, unless they are there already for
+/// this version of fish. This is for when fish's data directory is missing, and uses the
+/// environment directly since it runs before env_init().
+/// \return the directory, which is laid out like the data directory, or none if fish has no
+/// embedded data or it could not be written.
+maybe_t<wcstring> embedded_data_install();
+
+#endif
@@ -0,0 +1,96 @@
+// Generates a header which embeds files in fish, as a table of embedded_file_t. This runs at build
+// time with the name of the table, followed by arguments like
+// "functions/ls.fish=/path/to/share/functions/ls.fish" giving the name of each file and where to
+// read it. It makes embedded_data_files.h, with fish's data files when it is built with
+// FISH_EMBED_DATA (see cmake/EmbedData.cmake), and sample_prompt_sources.h, with the sample prompts
+// for fish_config (see CMakeLists.txt).
+#include <algorithm>
+#include <cctype>
+#include <cerrno>
+#include <cstdio>
+#include <cstring>
+#include <string>
+#include <utility>
+#include <vector>
+
+/// Read the file \p path into \p out. \return whether that worked.
+static bool read_file(const char *path, std::string *out) {
+    FILE *f = std::fopen(path, "rb");
+    if (!f) return false;
+    char buff[4096];
+    size_t amt;
+    while ((amt = std::fread(buff, 1, sizeof buff, f)) > 0) out->append(buff, amt);
+    bool ok = !std::ferror(f);
+    std::fclose(f);
+    return ok;
+}
+
+/// Print \p str as a C string literal, one literal per line of it. Bytes which are not printable
+/// ASCII are written as octal escapes, so this works for binary files like message catalogs too.
+static void print_literal(const std::string &str) {
+    std::printf("\n        \"");
+    for (size_t i = 0; i < str.size(); i++) {
+        unsigned char c = str[i];
+        if (c == '\n') {
+            std::printf("\\n\"");
+            if (i + 1 < str.size()) std::printf("\n        \"");
+            continue;
+        }
+        if (c == '"' || c == '\\') {
+            std::printf("\\%c", c);
+        } else if (c == '?') {
+            // Avoid trigraphs.
+            std::printf("\\?");
+        } else if (c >= ' ' && c <= '~') {
+            std::putchar(c);
+        } else {
+            std::printf("\\%03o", c);
+        }
+    }
+    if (str.empty() || str.back() != '\n') std::putchar('"');
+}
+
+int main(int argc, char **argv) {
+    if (argc < 2) {
+        std::fprintf(stderr, "%s: Expected a table name\n", argv[0]);
+        return 1;
+    }
+    std::string table = argv[1];
+    std::vector<std::pair<std::string, std::string>> files;
+    for (int i = 2; i < argc; i++) {
+        const char *eq = std::strchr(argv[i], '=');
+        if (!eq || eq == argv[i] || argv[i][0] == '/') {
+            std::fprintf(stderr, "%s: Expected NAME=PATH: %s\n", argv[0], argv[i]);
+            return 1;
+        }
+        std::string contents;
+        if (!read_file(eq + 1, &contents)) {
+            std::fprintf(stderr, "%s: %s: %s\n", argv[0], eq + 1, std::strerror(errno));
+            return 1;
+        }
+        files.emplace_back(std::string(argv[i], eq - argv[i]), contents);
+    }
+    std::sort(files.begin(), files.end());
+
+    std::string guard = "FISH_EMBEDDED_" + table + "_H";
+    std::transform(guard.begin(), guard.end(), guard.begin(),
+                   [](unsigned char c) { return std::toupper(c); });
+    std::printf("// Generated by embedded_data_gen. Do not edit.\n");
+    std::printf("#ifndef %s\n#define %s\n\n", guard.c_str(), guard.c_str());
+    std::printf("#include <stddef.h>\n\n");
+    // Several of these headers may be included together.
+    std::printf("#ifndef FISH_EMBEDDED_FILE_T\n#define FISH_EMBEDDED_FILE_T\n");
+    std::printf("struct embedded_file_t {\n");
+    std::printf("    const char *name;\n");
+    std::printf("    const char *contents;\n");
+    std::printf("    size_t size;\n");
+    std::printf("};\n#endif\n\n");
+    std::printf("static const embedded_file_t %s[] = {\n", table.c_str());
+    for (const auto &file : files) {
+        std::printf("    {\"%s\",", file.first.c_str());
+        print_literal(file.second);
+        std::printf(",\n        %lu},\n", static_cast<unsigned long>(file.second.size()));
+    }
+    std::printf("};\n\n#endif\n");
+    return 0;
+}
@@ -29,11 +29,13 @@
 #include "global_safety.h"
 #include "history.h"
 #include "input.h"
+#include "parse_util.h"
 #include "path.h"
 #include "proc.h"
 #include "reader.h"
 #include "termsize.h"
 #include "wcstringutil.h"
+#include "wildcard.h"
 #include "wutil.h"  // IWYU pragma: keep
 
 /// Some configuration path environment variables.
@@ -166,6 +168,44 @@ std::shared_ptr<const wcstring_list_t> env_var_t::empty_list() {
 
 environment_t::~environment_t() = default;
 
+/// Get the variables named \p keys from \p vars, which is an environment_t or an implementation
+/// of one which is already locked.
+template <typename Env>
+static std::vector<maybe_t<env_var_t>> get_many_from(const Env &vars, const wcstring_list_t &keys,
+                                                      env_mode_flags_t mode) {
+    std::vector<maybe_t<env_var_t>> result;
+    result.reserve(keys.size());
+    for (const wcstring &key : keys) result.push_back(vars.get(key, mode));
+    return result;
+}
+
+/// Get the variables in \p vars whose names match \p pattern.
+template <typename Env>
+static std::vector<std::pair<wcstring, env_var_t>> get_matching_from(const Env &vars,
+                                                                     const wcstring &pattern,
+                                                                     env_mode_flags_t mode) {
+    wcstring wc = parse_util_unescape_wildcards(pattern);
+    std::vector<std::pair<wcstring, env_var_t>> result;
+    for (wcstring &name : vars.get_names(mode)) {
+        if (!wildcard_match(name, wc)) continue;
+        if (auto var = vars.get(name, mode)) result.emplace_back(std::move(name), var.acquire());
+    }
+    std::sort(result.begin(), result.end(),
+              [](const std::pair<wcstring, env_var_t> &a,
+                 const std::pair<wcstring, env_var_t> &b) { return a.first < b.first; });
+    return result;
+}
+
+std::vector<maybe_t<env_var_t>> environment_t::get_many(const wcstring_list_t &keys,
+                                                        env_mode_flags_t mode) const {
+    return get_many_from(*this, keys, mode);
+}
+
+std::vector<std::pair<wcstring, env_var_t>> environment_t::get_matching(
+    const wcstring &pattern, env_mode_flags_t mode) const {
+    return get_matching_from(*this, pattern, mode);
+}
+
 wcstring environment_t::get_pwd_slash() const {
     // Return "/" if PWD is missing.
     // See https://github.com/fish-shell/fish-shell/issues/5080
@@ -374,12 +414,18 @@ void env_init(const struct config_paths_t *paths /* or NULL */) {
         vars.set_pwd_from_getcwd();
     }
 
-    // Initialize termsize variables.
-    auto termsize = termsize_container_t::shared().initialize(vars);
-    if (vars.get(L"COLUMNS").missing_or_empty())
-        vars.set_one(L"COLUMNS", ENV_GLOBAL, to_string(termsize.width));
-    if (vars.get(L"LINES").missing_or_empty())
-        vars.set_one(L"LINES", ENV_GLOBAL, to_string(termsize.height));
+    // Initialize termsize variables. Both are exported if one of them was, or if commands can't get
+    // the size from the tty.
+    auto &termsizes = termsize_container_t::shared();
+    auto termsize = termsizes.initialize(vars);
+    env_mode_flags_t termsize_mode = ENV_GLOBAL;
+    if (termsizes.should_export_columns_lines(vars)) termsize_mode |= ENV_EXPORT;
+    for (const auto &name_value : {std::make_pair(L"COLUMNS", termsize.width),
+                                   std::make_pair(L"LINES", termsize.height)}) {
+        auto var = vars.get(name_value.first);
+        vars.set_one(name_value.first, termsize_mode,
+                     var.missing_or_empty() ? to_string(name_value.second) : var->as_string());
+    }
 
     // Set fish_bind_mode to "default".
     vars.set_one(FISH_BIND_MODE_VAR, ENV_GLOBAL, DEFAULT_BIND_MODE);
@@ -449,6 +495,9 @@ struct query_t {
     // Whether this is a "user" set.
     bool user;
 
+    // Whether to set a universal variable for this host only.
+    bool host;
+
     explicit query_t(env_mode_flags_t mode) {
         has_scope = mode & (ENV_LOCAL | ENV_GLOBAL | ENV_UNIVERSAL);
         local = !has_scope || (mode & ENV_LOCAL);
@@ -465,6 +514,7 @@ struct query_t {
         unpathvar = mode & ENV_UNPATHVAR;
 
         user = mode & ENV_USER;
+        host = mode & ENV_HOST;
     }
 
     bool export_matches(const env_var_t &var) const {
@@ -490,6 +540,9 @@ class env_node_t {
     export_generation_t export_gen = 0;
     /// Pointer to next level.
     const std::shared_ptr<env_node_t> next;
+    /// A copy of this node which is never modified, shared by snapshots so they can be read
+    /// without the env_lock. It is made when first needed and dropped whenever this node changes.
+    std::shared_ptr<env_node_t> frozen{};
 
     env_node_t(bool is_new_scope, std::shared_ptr<env_node_t> next_scope)
         : new_scope(is_new_scope), next(std::move(next_scope)) {}
@@ -502,7 +555,12 @@ class env_node_t {
 
     bool exports() const { return export_gen > 0; }
 
-    void changed_exported() { export_gen = next_export_generation(); }
+    void changed() { frozen.reset(); }
+
+    void changed_exported() {
+        export_gen = next_export_generation();
+        changed();
+    }
 };
 }  // namespace
 
@@ -548,6 +606,10 @@ class env_scoped_impl_t : public environment_t {
     // Per process data.
     perproc_data_t perproc_data_{};
 
+    // For snapshots, the universal variables when the snapshot was taken. Otherwise null, and the
+    // live universal variables are used.
+    std::shared_ptr<const var_table_t> frozen_uvars_{};
+
     // Exported variable array used by execv.
     std::shared_ptr<const null_terminated_array_t<char>> export_array_{};
 
@@ -750,6 +812,11 @@ maybe_t<env_var_t> env_scoped_impl_t::try_get_global(const wcstring &key) const
 }
 
 maybe_t<env_var_t> env_scoped_impl_t::try_get_universal(const wcstring &key) const {
+    if (frozen_uvars_) {
+        auto where = frozen_uvars_->find(key);
+        if (where != frozen_uvars_->end()) return where->second;
+        return none();
+    }
     if (!uvars()) return none();
     auto var = uvars()->get(key);
     if (var) {
@@ -813,7 +880,11 @@ wcstring_list_t env_scoped_impl_t::get_names(int flags) const {
         }
     }
 
-    if (query.universal && uvars()) {
+    if (query.universal && frozen_uvars_) {
+        for (const auto &kv : *frozen_uvars_) {
+            if (query.export_matches(kv.second)) names.insert(kv.first);
+        }
+    } else if (query.universal && uvars()) {
         const wcstring_list_t uni_list = uvars()->get_names(query.exports, query.unexports);
         names.insert(uni_list.begin(), uni_list.end());
     }
@@ -837,8 +908,13 @@ static env_node_ref_t copy_node_chain(const env_node_ref_t &node) {
 }
 
 std::shared_ptr<environment_t> env_scoped_impl_t::snapshot() const {
-    auto ret = std::make_shared<env_scoped_impl_t>(copy_node_chain(locals_), globals_);
+    // Snapshots are read on other threads without the env_lock, so they must not share anything
+    // which is modified later. The globals and universal variables are only copied again after
+    // they change, so taking a snapshot is cheap while the user is typing.
+    if (!globals_->frozen) globals_->frozen = copy_node_chain(globals_);
+    auto ret = std::make_shared<env_scoped_impl_t>(copy_node_chain(locals_), globals_->frozen);
     ret->perproc_data_ = this->perproc_data_;
+    if (uvars()) ret->frozen_uvars_ = uvars()->get_frozen_table();
     return ret;
 }
 
@@ -922,6 +998,7 @@ class env_stack_impl_t final : public env_scoped_impl_t {
                     node->changed_exported();
                 }
                 cursor->env.erase(iter);
+                cursor->changed();
                 return true;
             }
         }
@@ -1009,6 +1086,7 @@ static wcstring_list_t colon_split(const wcstring_list_t &val) {
 void env_stack_impl_t::set_in_node(const env_node_ref_t &node, const wcstring &key,
                                    wcstring_list_t &&val, const var_flags_t &flags) {
     env_var_t &var = node->env[key];
+    node->changed();
 
     // Use an explicit exports, or inherit from the existing variable.
     bool res_exports = flags.exports.has_value() ? *flags.exports : var.exports();
@@ -1112,10 +1190,14 @@ void env_stack_impl_t::set_universal(const wcstring &key, wcstring_list_t val,
         val = std::move(split_val);
     }
 
+    // A variable for this host stays one until it is erased.
+    bool host = query.host || (oldvar && oldvar->is_host_scoped());
+
     // Construct and set the new variable.
     env_var_t::env_var_flags_t varflags = 0;
     if (exports) varflags |= env_var_t::flag_export;
     if (pathvar) varflags |= env_var_t::flag_pathvar;
+    if (host) varflags |= env_var_t::flag_host;
     env_var_t new_var{val, varflags};
 
     uvars()->set(key, new_var);
@@ -1275,6 +1357,30 @@ maybe_t<env_var_t> env_stack_t::get(const wcstring &key, env_mode_flags_t mode)
 
 wcstring_list_t env_stack_t::get_names(int flags) const { return acquire_impl()->get_names(flags); }
 
+std::vector<maybe_t<env_var_t>> env_stack_t::get_many(const wcstring_list_t &keys,
+                                                      env_mode_flags_t mode) const {
+    return get_many_from(*acquire_impl(), keys, mode);
+}
+
+std::vector<std::pair<wcstring, env_var_t>> env_stack_t::get_matching(
+    const wcstring &pattern, env_mode_flags_t mode) const {
+    return get_matching_from(*acquire_impl(), pattern, mode);
+}
+
+/// \return the arguments of the event for setting or erasing \p key. Changes of universal variables
+/// get this session as their origin and \p old_uvar as the old value, like those from other
+/// sessions.
+static wcstring_list_t var_event_args(const wcstring &key, bool erase, const mod_result_t &ret,
+                                      const maybe_t<env_var_t> &old_uvar) {
+    if (!ret.uvar_modified) return {L"VARIABLE", erase ? L"ERASE" : L"SET", key};
+    maybe_t<wcstring_list_t> old_val;
+    if (old_uvar) old_val = old_uvar->as_list();
+    maybe_t<wcstring> val;
+    if (!erase) val = wcstring();
+    return callback_data_t(key, std::move(val), std::move(old_val), uvar_session_origin())
+        .event_args();
+}
+
 int env_stack_t::set(const wcstring &key, env_mode_flags_t mode, wcstring_list_t vals,
                      std::vector<event_t> *out_events) {
     // Historical behavior.
@@ -1291,6 +1397,13 @@ int env_stack_t::set(const wcstring &key, env_mode_flags_t mode, wcstring_list_t
         vals = std::move(munged_vals);
     }
 
+    // The old value of a universal variable goes in the event.
+    maybe_t<env_var_t> old_uvar;
+    if (out_events && uvars()) {
+        auto impl = acquire_impl();  // this protects the universal variables too
+        old_uvar = uvars()->get(key);
+    }
+
     mod_result_t ret = acquire_impl()->set(key, mode, std::move(vals));
     if (ret.status == ENV_OK) {
         // If we modified the global state, or we are principal, then dispatch changes.
@@ -1299,7 +1412,8 @@ int env_stack_t::set(const wcstring &key, env_mode_flags_t mode, wcstring_list_t
             env_dispatch_var_change(key, *this);
         }
         if (out_events) {
-            out_events->push_back(event_t::variable(key, {L"VARIABLE", L"SET", key}));
+            out_events->push_back(
+                event_t::variable(key, var_event_args(key, false, ret, old_uvar)));
         }
     }
     // If the principal stack modified universal variables, then post a barrier.
@@ -1322,6 +1436,12 @@ int env_stack_t::set_empty(const wcstring &key, env_mode_flags_t mode,
 }
 
 int env_stack_t::remove(const wcstring &key, int mode, std::vector<event_t> *out_events) {
+    maybe_t<env_var_t> old_uvar;
+    if (out_events && uvars()) {
+        auto impl = acquire_impl();
+        old_uvar = uvars()->get(key);
+    }
+
     mod_result_t ret = acquire_impl()->remove(key, mode);
     if (ret.status == ENV_OK) {
         if (ret.global_modified || is_principal()) {
@@ -1329,7 +1449,14 @@ int env_stack_t::remove(const wcstring &key, int mode, std::vector<event_t> *out
             env_dispatch_var_change(key, *this);
         }
         if (out_events) {
-            out_events->push_back(event_t::variable(key, {L"VARIABLE", L"ERASE", key}));
+            // Erasing the value of a universal variable for this host reveals the shared one.
+            bool erase = true;
+            if (ret.uvar_modified && uvars()) {
+                auto impl = acquire_impl();
+                erase = !uvars()->get(key);
+            }
+            out_events->push_back(
+                event_t::variable(key, var_event_args(key, erase, ret, old_uvar)));
         }
     }
     if (ret.uvar_modified && is_principal()) {
@@ -1367,8 +1494,8 @@ void env_stack_t::pop() {
     auto popped = acquire_impl()->pop();
     // Only dispatch variable changes if we are the principal environment.
     if (this == principal_ref().get()) {
-        // TODO: we would like to coalesce locale / curses changes, so that we only re-initialize
-        // once.
+        // Locale and curses changes are coalesced by env_dispatch, so this only reinitializes
+        // them once.
         for (const auto &kv : popped->env) {
             env_dispatch_var_change(kv.first, *this);
         }
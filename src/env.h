@@ -43,6 +43,8 @@ enum {
     /// serves one purpose: to indicate that an error should be returned if the user is attempting
     /// to modify a var that should not be modified by direct user action; e.g., a read-only var.
     ENV_USER = 1 << 7,
+    /// Flag to set a universal variable for this host only.
+    ENV_HOST = 1 << 8,
 };
 typedef uint32_t env_mode_flags_t;
 
@@ -70,6 +72,14 @@ struct statuses_t {
     /// Pipestatus value.
     std::vector<int> pipestatus{};
 
+    /// The wall time of each process of the last job in milliseconds, like pipestatus. Empty if
+    /// the status did not come from a job.
+    std::vector<long> durations{};
+
+    /// The expanded arguments of each process of the last job, like pipestatus. Empty if the
+    /// status did not come from a job.
+    std::vector<wcstring_list_t> argvs{};
+
     /// Return a statuses for a single process status.
     static statuses_t just(int s) {
         statuses_t result{};
@@ -108,6 +118,7 @@ class env_var_t {
         flag_export = 1 << 0,     // whether the variable is exported
         flag_read_only = 1 << 1,  // whether the variable is read only
         flag_pathvar = 1 << 2,    // whether the variable is a path variable
+        flag_host = 1 << 3,       // whether the universal variable is for this host only
     };
 
     // Constructors.
@@ -131,6 +142,7 @@ class env_var_t {
     bool read_only() const { return flags_ & flag_read_only; }
     bool exports() const { return flags_ & flag_export; }
     bool is_pathvar() const { return flags_ & flag_pathvar; }
+    bool is_host_scoped() const { return flags_ & flag_host; }
     env_var_flags_t get_flags() const { return flags_; }
 
     wcstring as_string() const;
@@ -199,6 +211,15 @@ class environment_t {
     virtual wcstring_list_t get_names(int flags) const = 0;
     virtual ~environment_t();
 
+    /// Get the variables named \p keys, in the same order. This is cheaper than calling get() for
+    /// each, since a locked environment only takes its lock once.
+    virtual std::vector<maybe_t<env_var_t>> get_many(const wcstring_list_t &keys,
+                                                     env_mode_flags_t mode = ENV_DEFAULT) const;
+
+    /// Get the variables whose names match the wildcard \p pattern, sorted by name.
+    virtual std::vector<std::pair<wcstring, env_var_t>> get_matching(
+        const wcstring &pattern, env_mode_flags_t mode = ENV_DEFAULT) const;
+
     /// Returns the PWD with a terminating slash.
     virtual wcstring get_pwd_slash() const;
 };
@@ -240,6 +261,14 @@ class env_stack_t final : public environment_t {
     /// Implementation of environment_t.
     wcstring_list_t get_names(int flags) const override;
 
+    /// Implementation of environment_t.
+    std::vector<maybe_t<env_var_t>> get_many(const wcstring_list_t &keys,
+                                             env_mode_flags_t mode = ENV_DEFAULT) const override;
+
+    /// Implementation of environment_t.
+    std::vector<std::pair<wcstring, env_var_t>> get_matching(
+        const wcstring &pattern, env_mode_flags_t mode = ENV_DEFAULT) const override;
+
     /// Sets the variable with the specified name to the given values.
     /// If \p out_events is supplied, populate it with any events generated through setting the
     /// variable.
@@ -282,10 +311,11 @@ class env_stack_t final : public environment_t {
     /// Returns an array containing all exported variables in a format suitable for execv.
     std::shared_ptr<const null_terminated_array_t<char>> export_arr();
 
-    /// Snapshot this environment. This means returning a read-only copy. Local variables are copied
-    /// but globals are shared (i.e. changes to global will be visible to this snapshot). This
-    /// returns a shared_ptr for convenience, since the most common reason to snapshot is because
-    /// you want to read from another thread.
+    /// Snapshot this environment. This means returning a read-only copy, which does not see later
+    /// changes to any variable and can be read without locking. The globals and universal variables
+    /// are shared with other snapshots until they change. This returns a shared_ptr for
+    /// convenience, since the most common reason to snapshot is because you want to read from
+    /// another thread.
     std::shared_ptr<environment_t> snapshot() const;
 
     /// Helpers to get and set the proc statuses.
@@ -311,6 +341,9 @@ class env_stack_t final : public environment_t {
 
 extern bool g_use_posix_spawn;
 
+/// Whether jobs are put in cgroups of their own, via $fish_job_cgroups.
+extern bool g_use_job_cgroups;
+
 extern bool term_has_xn;  // does the terminal have the "eat_newline_glitch"
 
 /// Synchronizes all universal variable changes: writes everything out, reads stuff in.
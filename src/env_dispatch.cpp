@@ -6,6 +6,7 @@
 #include <locale.h>
 #include <stddef.h>
 #include <stdlib.h>
+#include <sys/stat.h>
 #include <time.h>
 #include <unistd.h>
 
@@ -29,11 +30,13 @@
 
 #include <algorithm>
 #include <functional>
+#include <iterator>
 #include <memory>
 #include <string>
 #include <unordered_map>
 #include <utility>
 
+#include "color.h"
 #include "common.h"
 #include "complete.h"
 #include "env.h"
@@ -116,6 +119,14 @@ static void update_fish_color_support(const environment_t &vars);
 /// True if we think we can set the terminal title.
 static relaxed_atomic_bool_t can_set_term_title{false};
 
+/// The fallback terminal type we set up curses with, if $TERM did not work.
+static maybe_t<wcstring> s_term_fallback{};
+
+/// Whether a locale or curses variable changed since the locale or curses was last initialized.
+/// These are only touched on the main thread.
+static bool s_locale_dirty{false};
+static bool s_curses_dirty{false};
+
 // Run those dispatch functions which want to be run at startup.
 static void run_inits(const environment_t &vars);
 
@@ -200,12 +211,10 @@ void env_dispatch_var_change(const wcstring &key, env_stack_t &vars) {
 /// Universal variable callback function. This function makes sure the proper events are triggered
 /// when an event occurs.
 static void universal_callback(env_stack_t *stack, const callback_data_t &cb) {
-    const wchar_t *op = cb.is_erase() ? L"ERASE" : L"SET";
-
     env_dispatch_var_change(cb.key, *stack);
 
     // TODO: eliminate this principal_parser. Need to rationalize how multiple threads work here.
-    event_fire(parser_t::principal_parser(), event_t::variable(cb.key, {L"VARIABLE", op, cb.key}));
+    event_fire(parser_t::principal_parser(), event_t::variable(cb.key, cb.event_args()));
 }
 
 void env_universal_callbacks(env_stack_t *stack, const callback_data_list_t &callbacks) {
@@ -219,6 +228,12 @@ static void handle_fish_term_change(const env_stack_t &vars) {
     reader_schedule_prompt_repaint();
 }
 
+// The terminal program also decides the emoji width we guess.
+static void handle_term_program_change(const env_stack_t &vars) {
+    guess_emoji_width(vars);
+    handle_fish_term_change(vars);
+}
+
 static void handle_change_ambiguous_width(const env_stack_t &vars) {
     int new_width = 1;
     if (auto width_str = vars.get(L"fish_ambiguous_width")) {
@@ -227,6 +242,45 @@ static void handle_change_ambiguous_width(const env_stack_t &vars) {
     g_fish_ambiguous_width = std::max(0, new_width);
 }
 
+static void handle_right_prompt_overflow_change(const env_stack_t &vars) {
+    auto overflow = vars.get(L"fish_right_prompt_overflow");
+    wcstring value = overflow ? overflow->as_string() : L"";
+    if (value == L"truncate") {
+        g_right_prompt_overflow = right_prompt_overflow_t::truncate;
+    } else if (value == L"newline") {
+        g_right_prompt_overflow = right_prompt_overflow_t::own_line;
+    } else {
+        g_right_prompt_overflow = right_prompt_overflow_t::hide;
+    }
+    reader_schedule_prompt_repaint();
+}
+
+static void handle_wide_char_wrap_change(const env_stack_t &vars) {
+    auto wrap = vars.get(L"fish_wide_char_wrap");
+    wcstring value = wrap ? wrap->as_string() : L"";
+    if (value == L"pad") {
+        g_wide_char_wrap = wide_char_wrap_t::pad;
+    } else if (value == L"wrap") {
+        g_wide_char_wrap = wide_char_wrap_t::wrap;
+    } else {
+        g_wide_char_wrap = none();
+    }
+    reader_schedule_prompt_repaint();
+}
+
+static void handle_invisible_style_change(const env_stack_t &vars) {
+    auto style = vars.get(L"fish_invisible_style");
+    wcstring value = style ? style->as_string() : L"";
+    if (value == L"escape") {
+        g_invisible_style = invisible_style_t::escape;
+    } else if (value == L"none") {
+        g_invisible_style = invisible_style_t::none;
+    } else {
+        g_invisible_style = invisible_style_t::caret;
+    }
+    reader_schedule_prompt_repaint();
+}
+
 static void handle_term_size_change(const env_stack_t &vars) {
     termsize_container_t::shared().handle_columns_lines_var_change(vars);
 }
@@ -236,28 +290,53 @@ static void handle_fish_history_change(const env_stack_t &vars) {
 }
 
 static void handle_function_path_change(const env_stack_t &vars) {
-    UNUSED(vars);
-    function_invalidate_path();
+    function_invalidate_path(vars);
 }
 
 static void handle_complete_path_change(const env_stack_t &vars) {
-    UNUSED(vars);
-    complete_invalidate_path();
+    complete_invalidate_path(vars);
 }
 
 static void handle_tz_change(const wcstring &var_name, const env_stack_t &vars) {
     handle_timezone(var_name.c_str(), vars);
 }
 
+// Reinitializing the locale and curses is expensive, and scripts often change several of their
+// variables in a row, so the changes are only noted here and applied by env_dispatch_flush().
 static void handle_locale_change(const environment_t &vars) {
-    init_locale(vars);
-    // We need to re-guess emoji width because the locale might have changed to a multibyte one.
-    guess_emoji_width(vars);
+    UNUSED(vars);
+    s_locale_dirty = true;
 }
 
 static void handle_curses_change(const environment_t &vars) {
+    UNUSED(vars);
+    s_curses_dirty = true;
+}
+
+void env_dispatch_flush(const environment_t &vars) {
+    ASSERT_IS_MAIN_THREAD();
+    if (!s_locale_dirty && !s_curses_dirty) return;
+    FLOGF(env_dispatch, L"Reinitializing%s%s", s_locale_dirty ? " locale" : "",
+          s_curses_dirty ? " curses" : "");
+    if (s_locale_dirty) {
+        s_locale_dirty = false;
+        init_locale(vars);
+    }
+    if (s_curses_dirty) {
+        s_curses_dirty = false;
+        init_curses(vars);
+    }
+    // We need to re-guess emoji width because the locale might have changed to a multibyte one.
     guess_emoji_width(vars);
+}
+
+void env_dispatch_reload_term(const environment_t &vars) {
+    ASSERT_IS_MAIN_THREAD();
+    FLOGF(env_dispatch, L"Reinitializing curses on request");
+    s_curses_dirty = false;
     init_curses(vars);
+    guess_emoji_width(vars);
+    reader_schedule_prompt_repaint();
 }
 
 static void handle_fish_use_posix_spawn_change(const environment_t &vars) {
@@ -267,6 +346,13 @@ static void handle_fish_use_posix_spawn_change(const environment_t &vars) {
         use_posix_spawn.missing_or_empty() ? true : bool_from_string(use_posix_spawn->as_string());
 }
 
+static void handle_fish_job_cgroups_change(const environment_t &vars) {
+    // note this defaults to false
+    auto job_cgroups = vars.get(L"fish_job_cgroups");
+    g_use_job_cgroups =
+        job_cgroups.missing_or_empty() ? false : bool_from_string(job_cgroups->as_string());
+}
+
 /// Allow the user to override the limit on how much data the `read` command will process.
 /// This is primarily for testing but could be used by users in special situations.
 static void handle_read_limit_change(const environment_t &vars) {
@@ -296,9 +382,16 @@ static std::unique_ptr<const var_dispatch_table_t> create_dispatch_table() {
 
     var_dispatch_table->add(L"fish_term256", handle_fish_term_change);
     var_dispatch_table->add(L"fish_term24bit", handle_fish_term_change);
+    var_dispatch_table->add(L"fish_term_palette", handle_fish_term_change);
+    var_dispatch_table->add(L"COLORTERM", handle_fish_term_change);
+    var_dispatch_table->add(L"TERM_PROGRAM", handle_term_program_change);
+    var_dispatch_table->add(L"TERM_PROGRAM_VERSION", handle_term_program_change);
     var_dispatch_table->add(L"fish_escape_delay_ms", update_wait_on_escape_ms);
     var_dispatch_table->add(L"fish_emoji_width", guess_emoji_width);
     var_dispatch_table->add(L"fish_ambiguous_width", handle_change_ambiguous_width);
+    var_dispatch_table->add(L"fish_right_prompt_overflow", handle_right_prompt_overflow_change);
+    var_dispatch_table->add(L"fish_wide_char_wrap", handle_wide_char_wrap_change);
+    var_dispatch_table->add(L"fish_invisible_style", handle_invisible_style_change);
     var_dispatch_table->add(L"LINES", handle_term_size_change);
     var_dispatch_table->add(L"COLUMNS", handle_term_size_change);
     var_dispatch_table->add(L"fish_complete_path", handle_complete_path_change);
@@ -307,6 +400,7 @@ static std::unique_ptr<const var_dispatch_table_t> create_dispatch_table() {
     var_dispatch_table->add(L"fish_history", handle_fish_history_change);
     var_dispatch_table->add(L"TZ", handle_tz_change);
     var_dispatch_table->add(L"fish_use_posix_spawn", handle_fish_use_posix_spawn_change);
+    var_dispatch_table->add(L"fish_job_cgroups", handle_fish_job_cgroups_change);
 
     // This std::move is required to avoid a build error on old versions of libc++ (#5801)
     return std::move(var_dispatch_table);
@@ -320,6 +414,7 @@ static void run_inits(const environment_t &vars) {
     update_wait_on_escape_ms(vars);
     handle_read_limit_change(vars);
     handle_fish_use_posix_spawn_change(vars);
+    handle_fish_job_cgroups_change(vars);
 }
 
 /// Updates our idea of whether we support term256 and term24bit (see issue #10222).
@@ -330,9 +425,54 @@ static void update_fish_color_support(const environment_t &vars) {
     bool support_term256 = false;
     bool support_term24bit = false;
 
-    if (auto term_var = vars.get(L"TERM")) term = term_var->as_string();
-
-    if (auto fish_term256 = vars.get(L"fish_term256")) {
+    // Get all the variables at once, since this runs whenever one of them changes.
+    // The names are listed in the order of this enum, which is how we find each one's value.
+    enum {
+        var_TERM,
+        var_fish_term256,
+        var_TERM_PROGRAM,
+        var_TERM_PROGRAM_VERSION,
+        var_fish_term24bit,
+        var_STY,
+        var_COLORTERM,
+        var_KONSOLE_VERSION,
+        var_KONSOLE_PROFILE_NAME,
+        var_ITERM_SESSION_ID,
+        var_VTE_VERSION,
+        var_fish_term_palette,
+        var_count
+    };
+    static const wchar_t *const names[var_count] = {
+        L"TERM",
+        L"fish_term256",
+        L"TERM_PROGRAM",
+        L"TERM_PROGRAM_VERSION",
+        L"fish_term24bit",
+        L"STY",
+        L"COLORTERM",
+        L"KONSOLE_VERSION",
+        L"KONSOLE_PROFILE_NAME",
+        L"ITERM_SESSION_ID",
+        L"VTE_VERSION",
+        L"fish_term_palette",
+    };
+    const auto termvars = vars.get_many(wcstring_list_t(std::begin(names), std::end(names)));
+    const maybe_t<env_var_t> &term_var = termvars[var_TERM];
+    const maybe_t<env_var_t> &fish_term256 = termvars[var_fish_term256];
+    const maybe_t<env_var_t> &tp = termvars[var_TERM_PROGRAM];
+    const maybe_t<env_var_t> &tpv = termvars[var_TERM_PROGRAM_VERSION];
+    const maybe_t<env_var_t> &fish_term24bit = termvars[var_fish_term24bit];
+    const maybe_t<env_var_t> &sty = termvars[var_STY];
+    const maybe_t<env_var_t> &ct = termvars[var_COLORTERM];
+    const maybe_t<env_var_t> &konsole_version = termvars[var_KONSOLE_VERSION];
+    const maybe_t<env_var_t> &konsole_profile = termvars[var_KONSOLE_PROFILE_NAME];
+    const maybe_t<env_var_t> &it = termvars[var_ITERM_SESSION_ID];
+    const maybe_t<env_var_t> &vte = termvars[var_VTE_VERSION];
+    const maybe_t<env_var_t> &fish_term_palette = termvars[var_fish_term_palette];
+
+    if (term_var) term = term_var->as_string();
+
+    if (fish_term256) {
         // $fish_term256
         support_term256 = bool_from_string(fish_term256->as_string());
         FLOGF(term_support, L"256 color support determined by '$fish_term256'");
@@ -343,9 +483,8 @@ static void update_fish_color_support(const environment_t &vars) {
     } else if (term.find(L"xterm") != wcstring::npos) {
         // Assume that all 'xterm's can handle 256, except for Terminal.app from Snow Leopard
         wcstring term_program;
-        if (auto tp = vars.get(L"TERM_PROGRAM")) term_program = tp->as_string();
+        if (tp) term_program = tp->as_string();
         if (term_program == L"Apple_Terminal") {
-            auto tpv = vars.get(L"TERM_PROGRAM_VERSION");
             if (tpv && fish_wcstod(tpv->as_string().c_str(), nullptr) > 299) {
                 // OS X Lion is version 299+, it has 256 color support (see github Wiki)
                 support_term256 = true;
@@ -364,12 +503,12 @@ static void update_fish_color_support(const environment_t &vars) {
     }
 
     // Handle $fish_term24bit
-    if (auto fish_term24bit = vars.get(L"fish_term24bit")) {
+    if (fish_term24bit) {
         support_term24bit = bool_from_string(fish_term24bit->as_string());
         FLOGF(term_support, L"'fish_term24bit' preference: 24-bit color %ls",
               support_term24bit ? L"enabled" : L"disabled");
     } else {
-        if (vars.get(L"STY") || string_prefixes_string(L"eterm", term)) {
+        if (sty || string_prefixes_string(L"eterm", term)) {
             // Screen and emacs' ansi-term swallow truecolor sequences,
             // so we ignore them unless force-enabled.
             FLOGF(term_support, L"Truecolor support: disabling for eterm/screen");
@@ -381,19 +520,19 @@ static void update_fish_color_support(const environment_t &vars) {
                   term.c_str(), max_colors);
             support_term24bit = true;
         } else {
-            if (auto ct = vars.get(L"COLORTERM")) {
+            if (ct) {
                 // If someone set $COLORTERM, that's the sort of color they want.
                 if (ct->as_string() == L"truecolor" || ct->as_string() == L"24bit") {
                     FLOGF(term_support, L"Truecolor support: Enabling per $COLORTERM='%ls'",
                           ct->as_string().c_str());
                     support_term24bit = true;
                 }
-            } else if (vars.get(L"KONSOLE_VERSION") || vars.get(L"KONSOLE_PROFILE_NAME")) {
+            } else if (konsole_version || konsole_profile) {
                 // All konsole versions that use $KONSOLE_VERSION are new enough to support this,
                 // so no check is necessary.
                 FLOGF(term_support, L"Truecolor support: Enabling for Konsole");
                 support_term24bit = true;
-            } else if (auto it = vars.get(L"ITERM_SESSION_ID")) {
+            } else if (it) {
                 // Supporting versions of iTerm include a colon here.
                 // We assume that if this is iTerm, it can't also be st, so having this check
                 // inside is okay.
@@ -401,10 +540,14 @@ static void update_fish_color_support(const environment_t &vars) {
                     FLOGF(term_support, L"Truecolor support: Enabling for ITERM");
                     support_term24bit = true;
                 }
+            } else if (is_termux()) {
+                // Older versions of Termux do not set $COLORTERM, but all support truecolor.
+                FLOGF(term_support, L"Truecolor support: Enabling for Termux");
+                support_term24bit = true;
             } else if (string_prefixes_string(L"st-", term)) {
                 FLOGF(term_support, L"Truecolor support: Enabling for st");
                 support_term24bit = true;
-            } else if (auto vte = vars.get(L"VTE_VERSION")) {
+            } else if (vte) {
                 if (fish_wcstod(vte->as_string().c_str(), nullptr) > 3600) {
                     FLOGF(term_support, L"Truecolor support: Enabling for VTE version %ls",
                           vte->as_string().c_str());
@@ -416,6 +559,22 @@ static void update_fish_color_support(const environment_t &vars) {
     color_support_t support = (support_term256 ? color_support_term256 : 0) |
                               (support_term24bit ? color_support_term24bit : 0);
     output_set_color_support(support);
+
+    // Handle $fish_term_palette, the 16 colors of the terminal, which we map RGB colors to if it
+    // supports no more.
+    std::vector<color24_t> palette;
+    if (fish_term_palette) {
+        for (const wcstring &str : fish_term_palette->as_list()) {
+            rgb_color_t color(str);
+            if (!color.is_rgb()) break;
+            palette.push_back(color.to_color24());
+        }
+        if (palette.size() != 16) {
+            FLOGF(warning, L"Ignoring fish_term_palette since it is not 16 RGB colors");
+            palette.clear();
+        }
+    }
+    set_term16_palette(term, palette);
 }
 
 // Try to initialize the terminfo/curses subsystem using our fallback terminal name. Do not set
@@ -494,6 +653,34 @@ static void init_curses(const environment_t &vars) {
         }
     }
 
+    // Termux keeps its terminfo database under $PREFIX, where a curses which was not built for
+    // Termux does not look. Use it unless the user said where to look. This is only for fish
+    // itself, commands from Termux find it anyway.
+    if (is_termux() && vars.get(L"TERMINFO", ENV_EXPORT).missing_or_empty() &&
+        vars.get(L"TERMINFO_DIRS", ENV_EXPORT).missing_or_empty()) {
+        const char *prefix = getenv("PREFIX");
+        std::string dir = std::string(prefix ? prefix : "") + "/share/terminfo";
+        struct stat buf;
+        if (prefix && stat(dir.c_str(), &buf) == 0 && S_ISDIR(buf.st_mode)) {
+            FLOGF(term_support, L"Using the terminfo database in '%s' for Termux", dir.c_str());
+            setenv_lock("TERMINFO_DIRS", dir.c_str(), 1);
+        }
+    }
+
+    // Fish's data directory may have terminfo entries of its own, when they are embedded in a
+    // self-contained fish. Look there after the system's database, which is the empty entry.
+    auto data_dir = vars.get(L"__fish_data_dir");
+    if (!data_dir.missing_or_empty()) {
+        std::string dir = wcs2string(data_dir->as_string()) + "/terminfo";
+        struct stat buf;
+        if (stat(dir.c_str(), &buf) == 0 && S_ISDIR(buf.st_mode)) {
+            const char *dirs = getenv("TERMINFO_DIRS");
+            std::string value = std::string(dirs ? dirs : "") + ":" + dir;
+            FLOGF(term_support, L"Using the terminfo entries in '%s'", dir.c_str());
+            setenv_lock("TERMINFO_DIRS", value.c_str(), 1);
+        }
+    }
+
     int err_ret;
     if (setupterm(nullptr, STDOUT_FILENO, &err_ret) == ERR) {
         auto term = vars.get(L"TERM");
@@ -508,9 +695,15 @@ static void init_curses(const environment_t &vars) {
             }
         }
 
-        if (!initialize_curses_using_fallback(DEFAULT_TERM1)) {
-            initialize_curses_using_fallback(DEFAULT_TERM2);
+        if (initialize_curses_using_fallback(DEFAULT_TERM1)) {
+            s_term_fallback = str2wcstring(DEFAULT_TERM1);
+        } else if (initialize_curses_using_fallback(DEFAULT_TERM2)) {
+            s_term_fallback = str2wcstring(DEFAULT_TERM2);
+        } else {
+            s_term_fallback = wcstring{};
         }
+    } else {
+        s_term_fallback = none();
     }
 
     can_set_term_title = does_term_support_setting_title(vars);
@@ -528,6 +721,7 @@ static void init_locale(const environment_t &vars) {
     // invalidate the pointer from the this setlocale() call.
     char *old_msg_locale = strdup(setlocale(LC_MESSAGES, nullptr));
 
+    bool locale_set = false;
     for (const auto &var_name : locale_variables) {
         const auto var = vars.get(var_name, ENV_EXPORT);
         const std::string &name = wcs2string(var_name);
@@ -538,10 +732,20 @@ static void init_locale(const environment_t &vars) {
             const std::string value = wcs2string(var->as_string());
             FLOGF(env_locale, L"locale var %s='%s'", name.c_str(), value.c_str());
             setenv_lock(name.c_str(), value.c_str(), 1);
+            locale_set = true;
         }
     }
 
     char *locale = setlocale(LC_ALL, "");
+    // Android only has the C and C.UTF-8 locales, so others like en_US.UTF-8 can't be used, and
+    // without any locale variables we may get plain C. The Termux terminal is always UTF-8, so use
+    // C.UTF-8 then, unless another locale was asked for and works.
+    if (is_termux() && (!locale || (!locale_set && MB_CUR_MAX == 1))) {
+        if (char *utf8_locale = setlocale(LC_ALL, "C.UTF-8")) {
+            FLOGF(env_locale, L"Using the C.UTF-8 locale for Termux");
+            locale = utf8_locale;
+        }
+    }
     fish_setlocale();
     FLOGF(env_locale, L"init_locale() setlocale(): '%s'", locale);
 
@@ -558,11 +762,17 @@ static void init_locale(const environment_t &vars) {
     free(old_msg_locale);
 }
 
+maybe_t<wcstring> env_dispatch_get_term_fallback() {
+    ASSERT_IS_MAIN_THREAD();
+    return s_term_fallback;
+}
+
 /// Returns true if we think the terminal supports setting its title.
 bool term_supports_setting_title() { return can_set_term_title; }
 
 /// Miscellaneous variables.
 bool g_use_posix_spawn = false;
+bool g_use_job_cgroups = false;
 
 // Limit `read` to 100 MiB (bytes not wide chars) by default. This can be overridden by the
 // fish_read_limit variable.
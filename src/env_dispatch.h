@@ -8,6 +8,7 @@
 
 #include "common.h"
 #include "env_universal_common.h"
+#include "maybe.h"
 
 /// Initialize variable dispatch.
 class environment_t;
@@ -16,6 +17,20 @@ void env_dispatch_init(const environment_t &vars);
 class env_stack_t;
 void env_dispatch_var_change(const wcstring &key, env_stack_t &vars);
 
+/// Reinitialize the locale and curses if any of their variables changed since they were last
+/// initialized. Changing those variables only marks them, so this must be called before anything
+/// that depends on them: running a command and drawing the prompt.
+void env_dispatch_flush(const environment_t &vars);
+
+/// Reinitialize curses and redetect what the terminal supports, like colors, even if none of
+/// their variables changed. This is for when the terminal changed under us, like after attaching
+/// tmux from a different terminal.
+void env_dispatch_reload_term(const environment_t &vars);
+
+/// \return none if curses was set up with $TERM, and otherwise the fallback terminal type that
+/// was used instead, which is empty if none of them worked either.
+maybe_t<wcstring> env_dispatch_get_term_fallback();
+
 void env_universal_callbacks(env_stack_t *stack, const callback_data_list_t &callbacks);
 
 #endif
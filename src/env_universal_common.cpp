@@ -33,15 +33,18 @@
 
 #include <atomic>
 #include <cwchar>
+#include <cwctype>
 #include <map>
 #include <string>
 #include <type_traits>
 #include <utility>
 
 #include "common.h"
+#include "dir_watcher.h"
 #include "env.h"
 #include "env_universal_common.h"
 #include "fallback.h"  // IWYU pragma: keep
+#include "fds.h"
 #include "flog.h"
 #include "path.h"
 #include "signal.h"
@@ -86,6 +89,12 @@ namespace {
 constexpr const char *SETUVAR = "SETUVAR";
 constexpr const char *EXPORT = "--export";
 constexpr const char *PATH = "--path";
+// The session which set the variable follows this, in the same word, so older versions of fish
+// skip it like other unknown flags.
+constexpr const char *ORIGIN = "--origin=";
+// The host a variable is for follows this. Older versions of fish skip it too, so they see the
+// values of all hosts, and the last one wins.
+constexpr const char *HOST = "--host=";
 }  // namespace
 }  // namespace fish3_uvars
 
@@ -172,9 +181,12 @@ static bool append_utf8(const wcstring &input, std::string *receiver, std::strin
 }
 
 /// Creates a file entry like "SET fish_color_cwd:FF0". Appends the result to *result (as UTF8).
+/// The session which set the variable is written too, unless \p origin is empty, and the host it
+/// is for, unless \p host is empty.
 /// Returns true on success. storage may be used for temporary storage, to avoid allocations.
 static bool append_file_entry(env_var_t::env_var_flags_t flags, const wcstring &key_in,
-                              const wcstring &val_in, std::string *result, std::string *storage) {
+                              const wcstring &val_in, const wcstring &origin,
+                              const wcstring &host, std::string *result, std::string *storage) {
     namespace f3 = fish3_uvars;
     assert(storage != nullptr);
     assert(result != nullptr);
@@ -196,6 +208,16 @@ static bool append_file_entry(env_var_t::env_var_flags_t flags, const wcstring &
         result->append(f3::PATH);
         result->push_back(' ');
     }
+    if (!origin.empty() && wchar_to_utf8_string(origin, storage)) {
+        result->append(f3::ORIGIN);
+        result->append(*storage);
+        result->push_back(' ');
+    }
+    if (!host.empty() && wchar_to_utf8_string(host, storage)) {
+        result->append(f3::HOST);
+        result->append(*storage);
+        result->push_back(' ');
+    }
 
     // Append variable name like "fish_color_cwd".
     if (!valid_var_name(key_in)) {
@@ -251,6 +273,29 @@ static wcstring encode_serialized(const wcstring_list_t &vals) {
     return join_strings(vals, UVAR_ARRAY_SEP);
 }
 
+const wcstring &uvar_host() {
+    static const wcstring host = [] {
+        wcstring hostname;
+        get_hostname_identifier(hostname);
+        // The host is a single word in the file.
+        std::replace_if(hostname.begin(), hostname.end(), [](wchar_t c) { return iswspace(c); },
+                        L'_');
+        return hostname;
+    }();
+    return host;
+}
+
+const wcstring &uvar_session_origin() {
+    static const wcstring origin = to_string(getpid()) + L"@" + uvar_host();
+    return origin;
+}
+
+wcstring_list_t callback_data_t::event_args() const {
+    wcstring_list_t args{L"VARIABLE", is_erase() ? L"ERASE" : L"SET", key, origin};
+    if (old_val) args.insert(args.end(), old_val->begin(), old_val->end());
+    return args;
+}
+
 env_universal_t::env_universal_t(wcstring path)
     : narrow_vars_path(wcs2string(path)), explicit_vars_path(std::move(path)) {}
 
@@ -272,9 +317,15 @@ void env_universal_t::set_internal(const wcstring &key, const env_var_t &var) {
     ASSERT_IS_LOCKED(lock);
     bool new_entry = vars.count(key) == 0;
     env_var_t &entry = vars[key];
+    if (!new_entry && var.is_host_scoped() && !entry.is_host_scoped()) {
+        // Keep the shared value, for other hosts and for when this one is erased.
+        hidden.push_back(hidden_uvar_t{key, entry, wcstring(), origins[key]});
+    }
     if (new_entry || entry != var) {
         entry = var;
+        frozen_vars.reset();
         this->modified.insert(key);
+        this->origins[key] = uvar_session_origin();
         if (entry.exports()) export_generation += 1;
     }
 }
@@ -288,8 +339,24 @@ bool env_universal_t::remove_internal(const wcstring &key) {
     ASSERT_IS_LOCKED(lock);
     auto iter = this->vars.find(key);
     if (iter != this->vars.end()) {
+        frozen_vars.reset();
         if (iter->second.exports()) export_generation += 1;
+        if (iter->second.is_host_scoped()) {
+            // The shared value is visible again, if there is one.
+            auto shared = std::find_if(hidden.begin(), hidden.end(), [&](const hidden_uvar_t &h) {
+                return h.key == key && h.host.empty();
+            });
+            if (shared != hidden.end()) {
+                if (shared->var.exports()) export_generation += 1;
+                iter->second = shared->var;
+                this->origins[key] = shared->origin;
+                this->modified.insert(key);
+                hidden.erase(shared);
+                return true;
+            }
+        }
         this->vars.erase(iter);
+        this->origins.erase(key);
         this->modified.insert(key);
         return true;
     }
@@ -301,6 +368,12 @@ bool env_universal_t::remove(const wcstring &key) {
     return this->remove_internal(key);
 }
 
+std::shared_ptr<const var_table_t> env_universal_t::get_frozen_table() const {
+    scoped_lock locker(lock);
+    if (!frozen_vars) frozen_vars = std::make_shared<const var_table_t>(vars);
+    return frozen_vars;
+}
+
 wcstring_list_t env_universal_t::get_names(bool show_exported, bool show_unexported) const {
     wcstring_list_t result;
     scoped_lock locker(lock);
@@ -317,6 +390,7 @@ wcstring_list_t env_universal_t::get_names(bool show_exported, bool show_unexpor
 // Given a variable table, generate callbacks representing the difference between our vars and the
 // new vars. Update our exports generation.
 void env_universal_t::generate_callbacks_and_update_exports(const var_table_t &new_vars,
+                                                            const uvar_origin_table_t &new_origins,
                                                             callback_data_list_t &callbacks) {
     // Construct callbacks for erased values.
     for (const auto &kv : this->vars) {
@@ -328,7 +402,7 @@ void env_universal_t::generate_callbacks_and_update_exports(const var_table_t &n
 
         // If the value is not present in new_vars, it has been erased.
         if (new_vars.count(key) == 0) {
-            callbacks.push_back(callback_data_t(key, none()));
+            callbacks.push_back(callback_data_t(key, none(), kv.second.as_list()));
             if (kv.second.exports()) export_generation += 1;
         }
     }
@@ -354,29 +428,57 @@ void env_universal_t::generate_callbacks_and_update_exports(const var_table_t &n
         }
         if (existing == this->vars.end() || export_changed || value_changed) {
             // Value is set for the first time, or has changed.
-            callbacks.push_back(callback_data_t(key, new_entry.as_string()));
+            maybe_t<wcstring_list_t> old_val;
+            if (existing != this->vars.end()) old_val = existing->second.as_list();
+            auto origin = new_origins.find(key);
+            callbacks.push_back(
+                callback_data_t(key, new_entry.as_string(), std::move(old_val),
+                                origin != new_origins.end() ? origin->second : wcstring()));
         }
     }
 }
 
-void env_universal_t::acquire_variables(var_table_t &vars_to_acquire) {
+void env_universal_t::acquire_variables(var_table_t &vars_to_acquire,
+                                        uvar_origin_table_t &origins_to_acquire,
+                                        hidden_uvar_list_t &hidden_to_acquire) {
     // Copy modified values from existing vars to vars_to_acquire.
     for (const auto &key : this->modified) {
         auto src_iter = this->vars.find(key);
         if (src_iter == this->vars.end()) {
             /* The value has been deleted. */
             vars_to_acquire.erase(key);
+            origins_to_acquire.erase(key);
         } else {
             // The value has been modified. Copy it over. Note we can destructively modify the
             // source entry in vars since we are about to get rid of this->vars entirely.
             env_var_t &src = src_iter->second;
+            auto dst_iter = vars_to_acquire.find(key);
+            if (src.is_host_scoped()) {
+                // Keep the shared value which this one now shadows.
+                if (dst_iter != vars_to_acquire.end() && !dst_iter->second.is_host_scoped()) {
+                    hidden_to_acquire.push_back(
+                        hidden_uvar_t{key, dst_iter->second, wcstring(), origins_to_acquire[key]});
+                }
+            } else {
+                // This is the shared value now.
+                hidden_to_acquire.erase(
+                    std::remove_if(hidden_to_acquire.begin(), hidden_to_acquire.end(),
+                                   [&](const hidden_uvar_t &h) {
+                                       return h.key == key && h.host.empty();
+                                   }),
+                    hidden_to_acquire.end());
+            }
             env_var_t &dst = vars_to_acquire[key];
             dst = src;
+            origins_to_acquire[key] = uvar_session_origin();
         }
     }
 
     // We have constructed all the callbacks and updated vars_to_acquire. Acquire it!
     this->vars = std::move(vars_to_acquire);
+    frozen_vars.reset();
+    this->origins = std::move(origins_to_acquire);
+    this->hidden = std::move(hidden_to_acquire);
 }
 
 void env_universal_t::load_from_fd(int fd, callback_data_list_t &callbacks) {
@@ -389,7 +491,10 @@ void env_universal_t::load_from_fd(int fd, callback_data_list_t &callbacks) {
     } else {
         // Read a variables table from the file.
         var_table_t new_vars;
-        uvar_format_t format = this->read_message_internal(fd, &new_vars);
+        uvar_origin_table_t new_origins;
+        hidden_uvar_list_t new_hidden;
+        uvar_format_t format =
+            this->read_message_internal(fd, &new_vars, &new_origins, &new_hidden);
 
         // Hacky: if the read format is in the future, avoid overwriting the file: never try to
         // save.
@@ -398,10 +503,10 @@ void env_universal_t::load_from_fd(int fd, callback_data_list_t &callbacks) {
         }
 
         // Announce changes and update our exports generation.
-        this->generate_callbacks_and_update_exports(new_vars, callbacks);
+        this->generate_callbacks_and_update_exports(new_vars, new_origins, callbacks);
 
         // Acquire the new variables.
-        this->acquire_variables(new_vars);
+        this->acquire_variables(new_vars, new_origins, new_hidden);
         last_read_file = current_file;
     }
 }
@@ -437,27 +542,39 @@ uint64_t env_universal_t::get_export_generation() const {
 }
 
 /// Serialize the contents to a string.
-std::string env_universal_t::serialize_with_vars(const var_table_t &vars) {
+std::string env_universal_t::serialize_with_vars(const var_table_t &vars,
+                                                 const uvar_origin_table_t *origins,
+                                                 const hidden_uvar_list_t *hidden) {
     std::string storage;
     std::string contents;
     contents.append(SAVE_MSG);
     contents.append("# VERSION: " UVARS_VERSION_3_0 "\n");
 
-    // Preserve legacy behavior by sorting the values first
-    using env_pair_t =
-        std::pair<std::reference_wrapper<const wcstring>, std::reference_wrapper<const env_var_t>>;
-    std::vector<env_pair_t> cloned(vars.begin(), vars.end());
-    std::sort(cloned.begin(), cloned.end(), [](const env_pair_t &p1, const env_pair_t &p2) {
-        return p1.first.get() < p2.first.get();
+    hidden_uvar_list_t entries;
+    for (const auto &kv : vars) {
+        wcstring origin;
+        if (origins) {
+            auto where = origins->find(kv.first);
+            if (where != origins->end()) origin = where->second;
+        }
+        wcstring host = kv.second.is_host_scoped() ? uvar_host() : wcstring();
+        entries.push_back(hidden_uvar_t{kv.first, kv.second, std::move(host), std::move(origin)});
+    }
+    if (hidden) entries.insert(entries.end(), hidden->begin(), hidden->end());
+
+    // Preserve legacy behavior by sorting the values first, with the shared value of a variable
+    // before those for hosts.
+    std::sort(entries.begin(), entries.end(), [](const hidden_uvar_t &e1, const hidden_uvar_t &e2) {
+        if (e1.key != e2.key) return e1.key < e2.key;
+        return e1.host < e2.host;
     });
 
-    for (const auto &kv : cloned) {
+    for (const hidden_uvar_t &entry : entries) {
         // Append the entry. Note that append_file_entry may fail, but that only affects one
         // variable; soldier on.
-        const wcstring &key = kv.first;
-        const env_var_t &var = kv.second;
-        append_file_entry(var.get_flags(), key, encode_serialized(var.as_list()), &contents,
-                          &storage);
+        auto flags = entry.var.get_flags() & ~env_var_t::flag_host;
+        append_file_entry(flags, entry.key, encode_serialized(entry.var.as_list()), entry.origin,
+                          entry.host, &contents, &storage);
     }
     return contents;
 }
@@ -467,7 +584,7 @@ bool env_universal_t::write_to_fd(int fd, const wcstring &path) {
     ASSERT_IS_LOCKED(lock);
     assert(fd >= 0);
     bool success = true;
-    std::string contents = serialize_with_vars(vars);
+    std::string contents = serialize_with_vars(vars, &origins, &hidden);
     if (write_loop(fd, contents.data(), contents.size()) < 0) {
         const char *error = std::strerror(errno);
         FLOGF(error, _(L"Unable to write to universal variables file '%ls': %s"), path.c_str(),
@@ -785,7 +902,9 @@ bool env_universal_t::save(const wcstring &directory, const wcstring &vars_path)
     return success;
 }
 
-uvar_format_t env_universal_t::read_message_internal(int fd, var_table_t *vars) {
+uvar_format_t env_universal_t::read_message_internal(int fd, var_table_t *vars,
+                                                     uvar_origin_table_t *origins,
+                                                     hidden_uvar_list_t *hidden) {
     // Read everything from the fd. Put a sane limit on it.
     std::string contents;
     while (contents.size() < k_max_read_size) {
@@ -805,7 +924,7 @@ uvar_format_t env_universal_t::read_message_internal(int fd, var_table_t *vars)
         contents.resize(newline == wcstring::npos ? 0 : newline);
     }
 
-    return populate_variables(contents, vars);
+    return populate_variables(contents, vars, nullptr, origins, hidden);
 }
 
 /// \return the format corresponding to file contents \p s.
@@ -835,32 +954,48 @@ uvar_format_t env_universal_t::format_for_contents(const std::string &s) {
     return uvar_format_t::fish_2_x;
 }
 
-uvar_format_t env_universal_t::populate_variables(const std::string &s, var_table_t *out_vars) {
+uvar_format_t env_universal_t::populate_variables(const std::string &s, var_table_t *out_vars,
+                                                  std::vector<size_t> *out_bad_lines,
+                                                  uvar_origin_table_t *out_origins,
+                                                  hidden_uvar_list_t *out_hidden) {
     // Decide on the format.
     const uvar_format_t format = format_for_contents(s);
 
     line_iterator_t<std::string> iter{s};
     wcstring wide_line;
     wcstring storage;
+    size_t line_number = 0;
     while (iter.next()) {
+        line_number++;
         const std::string &line = iter.line();
         // Skip empties and constants.
         if (line.empty() || line.front() == L'#') continue;
 
         // Convert to UTF8.
         wide_line.clear();
-        if (!utf8_to_wchar(line.data(), line.size(), &wide_line, 0)) continue;
+        if (!utf8_to_wchar(line.data(), line.size(), &wide_line, 0)) {
+            if (out_bad_lines) out_bad_lines->push_back(line_number);
+            continue;
+        }
 
+        bool parsed = false;
         switch (format) {
             case uvar_format_t::fish_2_x:
-                env_universal_t::parse_message_2x_internal(wide_line, out_vars, &storage);
+                parsed = env_universal_t::parse_message_2x_internal(wide_line, out_vars, &storage);
                 break;
             case uvar_format_t::fish_3_0:
             // For future formats, just try with the most recent one.
             case uvar_format_t::future:
-                env_universal_t::parse_message_30_internal(wide_line, out_vars, &storage);
+                parsed = env_universal_t::parse_message_30_internal(
+                    wide_line, out_vars, out_origins, out_hidden, &storage);
                 break;
         }
+        if (parsed) continue;
+        if (out_bad_lines) {
+            out_bad_lines->push_back(line_number);
+        } else {
+            FLOGF(warning, PARSE_ERR, wide_line.c_str());
+        }
     }
     return format;
 }
@@ -871,7 +1006,7 @@ static const wchar_t *skip_spaces(const wchar_t *str) {
 }
 
 bool env_universal_t::populate_1_variable(const wchar_t *input, env_var_t::env_var_flags_t flags,
-                                          var_table_t *vars, wcstring *storage) {
+                                          env_var_t *out_var, wcstring *storage) {
     const wchar_t *str = skip_spaces(input);
     const wchar_t *colon = std::wcschr(str, L':');
     if (!colon) return false;
@@ -881,29 +1016,28 @@ bool env_universal_t::populate_1_variable(const wchar_t *input, env_var_t::env_v
     if (!unescape_string(colon + 1, storage, 0)) {
         return false;
     }
-    env_var_t var{decode_serialized(*storage), flags};
+    *out_var = env_var_t{decode_serialized(*storage), flags};
 
-    // Parse out the key and write into the map.
+    // Parse out the key into storage.
     storage->assign(str, colon - str);
-    const wcstring &key = *storage;
-    (*vars)[key] = std::move(var);
     return true;
 }
 
 /// Parse message msg per fish 3.0 format.
-void env_universal_t::parse_message_30_internal(const wcstring &msgstr, var_table_t *vars,
-                                                wcstring *storage) {
+/// \return false if it could not be parsed.
+bool env_universal_t::parse_message_30_internal(const wcstring &msgstr, var_table_t *vars,
+                                                uvar_origin_table_t *origins,
+                                                hidden_uvar_list_t *hidden, wcstring *storage) {
     namespace f3 = fish3_uvars;
     const wchar_t *const msg = msgstr.c_str();
-    if (msg[0] == L'#') return;
+    if (msg[0] == L'#') return true;
 
     const wchar_t *cursor = msg;
-    if (!match(&cursor, f3::SETUVAR)) {
-        FLOGF(warning, PARSE_ERR, msg);
-        return;
-    }
+    if (!match(&cursor, f3::SETUVAR)) return false;
     // Parse out flags.
     env_var_t::env_var_flags_t flags = 0;
+    wcstring origin;
+    wcstring host;
     for (;;) {
         cursor = skip_spaces(cursor);
         if (*cursor != L'-') break;
@@ -912,25 +1046,65 @@ void env_universal_t::parse_message_30_internal(const wcstring &msgstr, var_tabl
         } else if (match(&cursor, f3::PATH)) {
             flags |= env_var_t::flag_pathvar;
         } else {
-            // Skip this unknown flag, for future proofing.
+            // Skip this flag, which is unknown unless it is the origin or host, for future
+            // proofing.
+            const wchar_t *start = cursor;
             while (*cursor && *cursor != L' ' && *cursor != L'\t') cursor++;
+            auto parse_field = [&](const char *name, wcstring *out) {
+                const size_t len = std::strlen(name);
+                if (static_cast<size_t>(cursor - start) > len &&
+                    std::equal(name, name + len, start)) {
+                    out->assign(start + len, cursor);
+                }
+            };
+            parse_field(f3::ORIGIN, &origin);
+            parse_field(f3::HOST, &host);
         }
     }
+    if (!host.empty() && host == uvar_host()) flags |= env_var_t::flag_host;
+
+    // Populate the variable with these flags. This leaves its name in storage.
+    env_var_t var;
+    if (!populate_1_variable(cursor, flags, &var, storage)) return false;
+    const wcstring &key = *storage;
 
-    // Populate the variable with these flags.
-    if (!populate_1_variable(cursor, flags, vars, storage)) {
-        FLOGF(warning, PARSE_ERR, msg);
+    // A variable for another host is hidden, as is a shared one which one for this host shadows.
+    auto existing = vars->find(key);
+    if (!host.empty() && !var.is_host_scoped()) {
+        if (hidden) hidden->push_back(hidden_uvar_t{key, std::move(var), host, origin});
+        return true;
+    }
+    if (existing != vars->end() && existing->second.is_host_scoped() && !var.is_host_scoped()) {
+        if (hidden) hidden->push_back(hidden_uvar_t{key, std::move(var), wcstring(), origin});
+        return true;
+    }
+    if (existing != vars->end() && !existing->second.is_host_scoped() && var.is_host_scoped()) {
+        if (hidden) {
+            wcstring shared_origin;
+            if (origins && origins->count(key)) shared_origin = origins->at(key);
+            hidden->push_back(hidden_uvar_t{key, existing->second, wcstring(), shared_origin});
+        }
+    }
+    (*vars)[key] = std::move(var);
+    if (origins) {
+        if (origin.empty()) {
+            origins->erase(key);
+        } else {
+            (*origins)[key] = std::move(origin);
+        }
     }
+    return true;
 }
 
 /// Parse message msg per fish 2.x format.
-void env_universal_t::parse_message_2x_internal(const wcstring &msgstr, var_table_t *vars,
+/// \return false if it could not be parsed.
+bool env_universal_t::parse_message_2x_internal(const wcstring &msgstr, var_table_t *vars,
                                                 wcstring *storage) {
     namespace f2x = fish2x_uvars;
     const wchar_t *const msg = msgstr.c_str();
     const wchar_t *cursor = msg;
 
-    if (cursor[0] == L'#') return;
+    if (cursor[0] == L'#') return true;
 
     env_var_t::env_var_flags_t flags = 0;
     if (match(&cursor, f2x::SET_EXPORT)) {
@@ -938,13 +1112,13 @@ void env_universal_t::parse_message_2x_internal(const wcstring &msgstr, var_tabl
     } else if (match(&cursor, f2x::SET)) {
         flags |= 0;
     } else {
-        FLOGF(warning, PARSE_ERR, msg);
-        return;
+        return false;
     }
 
-    if (!populate_1_variable(cursor, flags, vars, storage)) {
-        FLOGF(warning, PARSE_ERR, msg);
-    }
+    env_var_t var;
+    if (!populate_1_variable(cursor, flags, &var, storage)) return false;
+    (*vars)[*storage] = std::move(var);
+    return true;
 }
 
 /// Maximum length of hostname. Longer hostnames are truncated.
@@ -1205,6 +1379,8 @@ class universal_notifier_notifyd_t final : public universal_notifier_t {
         if (status != NOTIFY_STATUS_OK) {
             FLOGF(warning, "notify_register_file_descriptor() failed with status %u.", status);
             FLOGF(warning, "Universal variable notifications may not be received.");
+        } else {
+            fd_set_purpose(this->notify_fd, N_(L"universal variable notifier"));
         }
         if (notify_fd >= 0) {
             // Mark us for non-blocking reads, and CLO_EXEC.
@@ -1295,6 +1471,8 @@ static autoclose_fd_t make_fifo(const wchar_t *test_path, const wchar_t *suffix)
         const char *error = std::strerror(errno);
         const wchar_t *errmsg = _(L"Unable to open a pipe for universal variables using '%ls': %s");
         FLOGF(error, errmsg, vars_path.c_str(), error);
+    } else {
+        fd_set_purpose(res.fd(), N_(L"universal variable notifier"));
     }
     return res;
 }
@@ -1566,11 +1744,52 @@ class universal_notifier_named_pipe_t final : public universal_notifier_t {
 #endif
 };
 
+// A universal notifier which watches the directory of the variables file, with kqueue or inotify.
+// Saving the variables replaces the file, which changes the directory, so nothing needs to be done
+// to post a notification. Other files in the directory may change too, so a notification is only
+// reported if the variables file is a different one than before.
+class universal_notifier_dir_watch_t final : public universal_notifier_t {
+    wcstring vars_path;
+    std::unique_ptr<dir_watcher_t> watcher;
+    file_id_t last_id{kInvalidFileID};
+
+   public:
+    explicit universal_notifier_dir_watch_t(const wchar_t *test_path) {
+        if (test_path) {
+            vars_path = test_path;
+        } else if (auto path = default_vars_path()) {
+            vars_path = std::move(*path);
+        }
+        if (vars_path.empty()) return;
+        watcher = make_unique<dir_watcher_t>(wcstring_list_t{wdirname(vars_path)});
+        if (!watcher->is_watched(0)) {
+            FLOGF(uvar_file, L"Unable to watch '%ls' for changes to universal variables",
+                  wdirname(vars_path).c_str());
+        }
+        last_id = file_id_for_path(vars_path);
+    }
+
+    int notification_fd() const override { return watcher ? watcher->fd() : -1; }
+
+    bool notification_fd_became_readable(int fd) override {
+        assert(watcher && fd == watcher->fd() && "Wrong fd for the directory watcher");
+        if (watcher->take_changed().empty()) return false;
+        file_id_t id = file_id_for_path(vars_path);
+        if (id == last_id) return false;
+        last_id = id;
+        return true;
+    }
+};
+
 universal_notifier_t::notifier_strategy_t universal_notifier_t::resolve_default_strategy() {
 #ifdef FISH_NOTIFYD_AVAILABLE
     return strategy_notifyd;
 #elif defined(__CYGWIN__)
     return strategy_shmem_polling;
+#elif defined(HAVE_KQUEUE)
+    // SIGIO for pipes works differently on the BSDs, and kqueue tells about the variables file
+    // without a pipe shared by all fish processes.
+    return strategy_dir_watch;
 #elif defined(SIGIO)
     // The SIGIO notifier does not yet work on WSL. See #7429
     if (is_windows_subsystem_for_linux()) {
@@ -1603,6 +1822,9 @@ std::unique_ptr<universal_notifier_t> universal_notifier_t::new_notifier_for_str
         case strategy_named_pipe: {
             return make_unique<universal_notifier_named_pipe_t>(test_path);
         }
+        case strategy_dir_watch: {
+            return make_unique<universal_notifier_dir_watch_t>(test_path);
+        }
     }
     DIE("should never reach this statement");
     return nullptr;
@@ -6,6 +6,7 @@
 #include <stdio.h>
 
 #include <memory>
+#include <unordered_map>
 #include <unordered_set>
 #include <vector>
 
@@ -22,15 +23,50 @@ struct callback_data_t {
     // The value of the variable, or none if it is erased.
     maybe_t<wcstring> val;
 
-    /// Construct from a key and maybe a value.
-    callback_data_t(wcstring k, maybe_t<wcstring> v) : key(std::move(k)), val(std::move(v)) {}
+    // The value of the variable before the change, or none if it did not exist.
+    maybe_t<wcstring_list_t> old_val;
+
+    // The session which made the change, see uvar_session_origin(). This is empty if it is not
+    // known, which it is not for erased variables, or ones set by fish versions before 3.2.
+    wcstring origin;
+
+    /// Construct from a key and maybe a value, and maybe the old value and origin.
+    callback_data_t(wcstring k, maybe_t<wcstring> v, maybe_t<wcstring_list_t> old = none(),
+                    wcstring o = wcstring())
+        : key(std::move(k)), val(std::move(v)), old_val(std::move(old)), origin(std::move(o)) {}
 
     /// \return whether this callback represents an erased variable.
     bool is_erase() const { return !val.has_value(); }
+
+    /// \return the arguments of the variable event for this change: "VARIABLE", "SET" or "ERASE",
+    /// the name, the origin, and the elements of the old value.
+    wcstring_list_t event_args() const;
 };
 
 typedef std::vector<callback_data_t> callback_data_list_t;
 
+/// The sessions which last set universal variables, by the name of the variable.
+typedef std::unordered_map<wcstring, wcstring> uvar_origin_table_t;
+
+/// \return the identifier of this fish session in the origins of universal variables, which is
+/// its pid and host name, like "1234@myhost".
+const wcstring &uvar_session_origin();
+
+/// \return the name of this host for universal variables set with `set --host`, which is a single
+/// word.
+const wcstring &uvar_host();
+
+/// A universal variable which is in the file, but not visible: it was set for another host, or it
+/// is shadowed by one set for this host. These are kept so they can be written back.
+struct hidden_uvar_t {
+    wcstring key;
+    env_var_t var;
+    // The host it was set for, or empty if it is shared.
+    wcstring host;
+    wcstring origin;
+};
+typedef std::vector<hidden_uvar_t> hidden_uvar_list_t;
+
 // List of fish universal variable formats.
 // This is exposed for testing.
 enum class uvar_format_t { fish_2_x, fish_3_0, future };
@@ -46,6 +82,12 @@ class env_universal_t {
     // vars indicates a deleted value.
     std::unordered_set<wcstring> modified;
 
+    // The sessions which last set the variables, where they are known.
+    uvar_origin_table_t origins;
+
+    // The variables in the file which are not visible here.
+    hidden_uvar_list_t hidden;
+
     std::string narrow_vars_path;
     // Path that we save to. If empty, use the default.
     wcstring explicit_vars_path;
@@ -57,6 +99,10 @@ class env_universal_t {
     // fish wrote the uvars contents.
     bool ok_to_save{true};
 
+    // A copy of vars which is never modified, shared by environment snapshots. It is made when
+    // first needed and dropped whenever vars changes.
+    mutable std::shared_ptr<const var_table_t> frozen_vars;
+
     mutable std::mutex lock;
     bool load_from_path(const std::string &path, callback_data_list_t &callbacks);
     bool load_from_path(const wcstring &path, callback_data_list_t &callbacks);
@@ -77,20 +123,25 @@ class env_universal_t {
     // Given a variable table, generate callbacks representing the difference between our vars and
     // the new vars. Also update our exports generation count as necessary.
     void generate_callbacks_and_update_exports(const var_table_t &new_vars,
+                                               const uvar_origin_table_t &new_origins,
                                                callback_data_list_t &callbacks);
 
-    // Given a variable table, copy unmodified values into self. May destructively modify
-    // vars_to_acquire.
-    void acquire_variables(var_table_t &vars_to_acquire);
+    // Given a variable table with their origins and the hidden variables, copy unmodified values
+    // into self. May destructively modify the arguments.
+    void acquire_variables(var_table_t &vars_to_acquire, uvar_origin_table_t &origins_to_acquire,
+                           hidden_uvar_list_t &hidden_to_acquire);
 
     static bool populate_1_variable(const wchar_t *input, env_var_t::env_var_flags_t flags,
-                                    var_table_t *vars, wcstring *storage);
+                                    env_var_t *out_var, wcstring *storage);
 
-    static void parse_message_2x_internal(const wcstring &msg, var_table_t *vars,
+    static bool parse_message_2x_internal(const wcstring &msg, var_table_t *vars,
                                           wcstring *storage);
-    static void parse_message_30_internal(const wcstring &msg, var_table_t *vars,
-                                          wcstring *storage);
-    static uvar_format_t read_message_internal(int fd, var_table_t *vars);
+    static bool parse_message_30_internal(const wcstring &msg, var_table_t *vars,
+                                          uvar_origin_table_t *origins,
+                                          hidden_uvar_list_t *hidden, wcstring *storage);
+    static uvar_format_t read_message_internal(int fd, var_table_t *vars,
+                                               uvar_origin_table_t *origins,
+                                               hidden_uvar_list_t *hidden);
 
     bool save(const wcstring &directory, const wcstring &vars_path);
 
@@ -115,6 +166,10 @@ class env_universal_t {
     /// Get a view on the universal variable table.
     const var_table_t &get_table() const { return vars; }
 
+    /// \return the universal variable table as it is now, in a table which is never modified, so
+    /// other threads can read it without locking.
+    std::shared_ptr<const var_table_t> get_frozen_table() const;
+
     /// Loads variables at the correct path, optionally migrating from a legacy path.
     bool initialize(callback_data_list_t &callbacks);
 
@@ -123,15 +178,24 @@ class env_universal_t {
     bool sync(callback_data_list_t &callbacks);
 
     /// Populate a variable table \p out_vars from a \p s string.
-    /// This is exposed for testing only.
+    /// The numbers of lines which could not be parsed are added to \p out_bad_lines if it is not
+    /// null, and otherwise reported as warnings. The sessions which set the variables are added to
+    /// \p out_origins if it is not null, and the variables which are not visible on this host to
+    /// \p out_hidden.
     /// \return the format of the file that we read.
-    static uvar_format_t populate_variables(const std::string &s, var_table_t *out_vars);
+    static uvar_format_t populate_variables(const std::string &s, var_table_t *out_vars,
+                                            std::vector<size_t> *out_bad_lines = nullptr,
+                                            uvar_origin_table_t *out_origins = nullptr,
+                                            hidden_uvar_list_t *out_hidden = nullptr);
 
     /// Guess a file format. Exposed for testing only.
     static uvar_format_t format_for_contents(const std::string &s);
 
-    /// Serialize a variable list. Exposed for testing only.
-    static std::string serialize_with_vars(const var_table_t &vars);
+    /// Serialize a variable list, with the sessions which set them if \p origins is not null, and
+    /// the hidden variables if \p hidden is not null.
+    static std::string serialize_with_vars(const var_table_t &vars,
+                                           const uvar_origin_table_t *origins = nullptr,
+                                           const hidden_uvar_list_t *hidden = nullptr);
 
     /// Exposed for testing only.
     bool is_ok_to_save() const { return ok_to_save; }
@@ -172,6 +236,9 @@ class universal_notifier_t {
         // Strategy that uses a named pipe. Somewhat complex, but portable and doesn't require
         // polling most of the time.
         strategy_named_pipe,
+
+        // Watch the directory of the variables file with kqueue or inotify, for it being replaced.
+        strategy_dir_watch,
     };
 
    protected:
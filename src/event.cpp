@@ -7,20 +7,32 @@
 #include <stddef.h>
 #include <unistd.h>
 
+#include <sys/stat.h>
+
 #include <algorithm>
 #include <atomic>
 #include <functional>
+#include <map>
 #include <memory>
+#include <set>
 #include <string>
 #include <type_traits>
 
 #include "common.h"
+#include "dir_watcher.h"
+#include "exec.h"
 #include "fallback.h"  // IWYU pragma: keep
+#include "function.h"
+#include "global_safety.h"
 #include "input_common.h"
 #include "io.h"
+#include "iothread.h"
+#include "parse_util.h"
 #include "parser.h"
 #include "proc.h"
 #include "signal.h"
+#include "wcstringutil.h"
+#include "wildcard.h"
 #include "wutil.h"  // IWYU pragma: keep
 
 class pending_signals_t {
@@ -32,6 +44,15 @@ class pending_signals_t {
     /// List of pending signals.
     std::array<std::atomic<bool>, SIGNAL_COUNT> received_{};
 
+    /// The process and user which sent each pending signal, or 0 if it was not sent by a process.
+    /// If a signal is received again before it is handled, this is the last sender.
+    std::array<std::atomic<pid_t>, SIGNAL_COUNT> sender_pid_{};
+    std::array<std::atomic<uid_t>, SIGNAL_COUNT> sender_uid_{};
+
+    /// The value sent along with each pending signal by sigqueue(), if it was.
+    std::array<std::atomic<bool>, SIGNAL_COUNT> has_value_{};
+    std::array<std::atomic<int>, SIGNAL_COUNT> value_{};
+
     /// The last counter visible in acquire_pending().
     /// This is not accessed from a signal handler.
     owning_lock<uint32_t> last_counter_{0};
@@ -46,8 +67,17 @@ class pending_signals_t {
     /// Mark a signal as pending. This may be called from a signal handler.
     /// We expect only one signal handler to execute at once.
     /// Also note that these may be coalesced.
-    void mark(int which) {
+    void mark(int which, const siginfo_t *info) {
         if (which >= 0 && static_cast<size_t>(which) < received_.size()) {
+            // Only signals sent by other processes have a sender. Otherwise these fields may
+            // overlap with others, like the faulting address.
+            bool from_process = info && (info->si_code == SI_USER || info->si_code == SI_QUEUE);
+            sender_pid_[which].store(from_process ? info->si_pid : 0, std::memory_order_relaxed);
+            sender_uid_[which].store(from_process ? info->si_uid : 0, std::memory_order_relaxed);
+            bool queued = info && info->si_code == SI_QUEUE;
+            has_value_[which].store(queued, std::memory_order_relaxed);
+            value_[which].store(queued ? info->si_value.sival_int : 0, std::memory_order_relaxed);
+
             // Must mark our received first, then pending.
             received_[which].store(true, std::memory_order_relaxed);
             uint32_t count = counter_.load(std::memory_order_relaxed);
@@ -80,10 +110,175 @@ class pending_signals_t {
         }
         return result;
     }
+
+    /// \return the variables describing the sender of pending signal \p which, for its handlers.
+    event_variables_t sender_variables(size_t which) const {
+        event_variables_t result;
+        pid_t pid = sender_pid_[which].load(std::memory_order_relaxed);
+        if (pid != 0) {
+            result.emplace_back(L"signal_pid", wcstring_list_t{to_string(pid)});
+            uid_t uid = sender_uid_[which].load(std::memory_order_relaxed);
+            auto uid_val = static_cast<unsigned long long>(uid);
+            result.emplace_back(L"signal_uid", wcstring_list_t{to_string(uid_val)});
+        }
+        if (has_value_[which].load(std::memory_order_relaxed)) {
+            int value = value_[which].load(std::memory_order_relaxed);
+            result.emplace_back(L"signal_value", wcstring_list_t{to_string(value)});
+        }
+        return result;
+    }
 };
 
 static pending_signals_t s_pending_signals;
 
+/// A path watched by file change event handlers, and what it looked like when it was last checked.
+struct watched_path_t {
+    wcstring path;
+    file_id_t id{kInvalidFileID};
+    /// Whether it is a directory, and if so its entries and what they looked like.
+    bool is_dir{false};
+    std::map<wcstring, file_id_t> entries{};
+
+    explicit watched_path_t(wcstring p) : path(std::move(p)) { update(); }
+
+    /// Look at the path again. \return the paths which changed since the last look: the entries
+    /// of a directory which were created, removed or changed, or else the path itself.
+    wcstring_list_t update() {
+        struct stat buf;
+        bool exists = wstat(path, &buf) == 0;
+        file_id_t new_id = exists ? file_id_t::from_stat(buf) : kInvalidFileID;
+        bool new_is_dir = exists && S_ISDIR(buf.st_mode);
+        std::map<wcstring, file_id_t> new_entries;
+        wcstring prefix = string_suffixes_string(L"/", path) ? path : path + L"/";
+        if (new_is_dir) {
+            dir_t dir(path);
+            wcstring name;
+            while (dir.valid() && dir.read(name)) {
+                if (name == L"." || name == L"..") continue;
+                new_entries[name] = file_id_for_path(prefix + name);
+            }
+        }
+
+        wcstring_list_t changed;
+        if (is_dir && new_is_dir && id.device == new_id.device && id.inode == new_id.inode) {
+            // The same directory, whose own times change with its entries.
+            for (const auto &entry : new_entries) {
+                auto old = entries.find(entry.first);
+                if (old == entries.end() || old->second != entry.second) {
+                    changed.push_back(prefix + entry.first);
+                }
+            }
+            for (const auto &entry : entries) {
+                if (!new_entries.count(entry.first)) changed.push_back(prefix + entry.first);
+            }
+            std::sort(changed.begin(), changed.end());
+        } else if (new_id != id) {
+            changed.push_back(path);
+        }
+        id = new_id;
+        is_dir = new_is_dir;
+        entries = std::move(new_entries);
+        return changed;
+    }
+};
+
+/// The paths watched by file change event handlers. Their directories are watched with a
+/// dir_watcher_t, and checked once a second where that is not possible.
+class file_watches_t {
+    /// How often to check the paths when they can't be watched, in seconds.
+    static constexpr double poll_interval = 1;
+
+    std::vector<watched_path_t> paths_;
+
+    /// Watches the parent directory of each path, and the path itself, in that order.
+    std::unique_ptr<dir_watcher_t> watcher_;
+
+    /// Whether the watcher needs to be created again, as the paths or directories changed.
+    bool stale_{false};
+
+    /// Whether to check the paths once a second, because not all can be watched.
+    bool polling_{false};
+
+    /// Whether the watcher reported changes, which were not checked yet.
+    bool pending_{false};
+
+    /// When the paths were last checked, from timef().
+    double last_check_{0};
+
+    void create_watcher() {
+        wcstring_list_t dirs;
+        for (const watched_path_t &wp : paths_) {
+            dirs.push_back(wdirname(wp.path));
+            dirs.push_back(wp.path);
+        }
+        watcher_ = paths_.empty() ? nullptr : make_unique<dir_watcher_t>(dirs);
+        polling_ = false;
+        for (size_t i = 0; i < paths_.size(); i++) {
+            if (!watcher_->is_watched(2 * i) ||
+                (paths_[i].is_dir && !watcher_->is_watched(2 * i + 1))) {
+                polling_ = true;
+            }
+        }
+        stale_ = false;
+    }
+
+   public:
+    /// Watch \p paths, keeping what is known about the ones which are watched already.
+    void set_paths(const wcstring_list_t &paths) {
+        std::vector<watched_path_t> new_paths;
+        for (const wcstring &path : paths) {
+            auto old = std::find_if(paths_.begin(), paths_.end(),
+                                    [&](const watched_path_t &wp) { return wp.path == path; });
+            if (old != paths_.end()) {
+                new_paths.push_back(std::move(*old));
+            } else {
+                new_paths.emplace_back(path);
+            }
+        }
+        paths_ = std::move(new_paths);
+        stale_ = true;
+    }
+
+    int fd() {
+        if (stale_) create_watcher();
+        return watcher_ ? watcher_->fd() : -1;
+    }
+
+    long usec_delay() {
+        if (stale_) create_watcher();
+        if (paths_.empty() || (!polling_ && !pending_)) return -1;
+        double remaining = last_check_ + poll_interval - timef();
+        return remaining > 0 ? static_cast<long>(remaining * 1E6) : 0;
+    }
+
+    /// \return each path which changed, with the paths below it which changed. If \p check is
+    /// false, the changes are kept until the next time.
+    std::vector<std::pair<wcstring, wcstring_list_t>> take_changes(bool check) {
+        std::vector<std::pair<wcstring, wcstring_list_t>> result;
+        if (stale_) create_watcher();
+        if (watcher_ && !watcher_->take_changed().empty()) pending_ = true;
+        if (usec_delay() != 0) return result;
+        last_check_ = timef();
+        // If the changes can't be checked now, this is tried again after the poll interval.
+        if (!check) return result;
+
+        // Directories may have been created or removed, so watch them again before looking, so
+        // nothing is missed in between.
+        create_watcher();
+        pending_ = false;
+        for (watched_path_t &wp : paths_) {
+            wcstring_list_t changed = wp.update();
+            if (!changed.empty()) result.emplace_back(wp.path, std::move(changed));
+        }
+        return result;
+    }
+};
+
+static mainthread_t<file_watches_t> s_file_watches;
+
+/// Whether the paths of the file change event handlers changed since they were last watched.
+static relaxed_atomic_bool_t s_file_watch_handlers_changed{false};
+
 /// List of event handlers.
 static owning_lock<event_handler_list_t> s_event_handlers;
 
@@ -117,6 +312,18 @@ static bool handler_matches(const event_handler_t &classv, const event_t &instan
             return classv.desc.param1.caller_id == instance.desc.param1.caller_id;
         }
         case event_type_t::generic: {
+            // Handlers may use a wildcard like 'plugin_*' to match a family of events.
+            const wcstring &name = classv.desc.str_param1;
+            if (wildcard_has(name, false)) {
+                return wildcard_match(instance.desc.str_param1,
+                                      parse_util_unescape_wildcards(name));
+            }
+            return name == instance.desc.str_param1;
+        }
+        case event_type_t::interval: {
+            return classv.desc.param1.interval_ms == instance.desc.param1.interval_ms;
+        }
+        case event_type_t::file_change: {
             return classv.desc.str_param1 == instance.desc.str_param1;
         }
         case event_type_t::any:
@@ -127,6 +334,19 @@ static bool handler_matches(const event_handler_t &classv, const event_t &instan
     }
 }
 
+/// \return the number of milliseconds since the epoch.
+static uint64_t now_ms() { return static_cast<uint64_t>(timef() * 1000); }
+
+wcstring event_format_interval(uint64_t ms) {
+    wcstring result = to_string(static_cast<unsigned long long>(ms / 1000));
+    if (ms % 1000) {
+        wcstring frac = to_string(static_cast<unsigned long long>(1000 + ms % 1000)).substr(1);
+        while (frac.back() == L'0') frac.pop_back();
+        result += L"." + frac;
+    }
+    return result;
+}
+
 /// Test if specified event is blocked.
 static int event_is_blocked(parser_t &parser, const event_t &e) {
     (void)e;
@@ -174,6 +394,13 @@ wcstring event_get_desc(const parser_t &parser, const event_t &evt) {
         case event_type_t::generic: {
             return format_string(_(L"handler for generic event '%ls'"), ed.str_param1.c_str());
         }
+        case event_type_t::interval: {
+            return format_string(_(L"handler for an interval of %ls seconds"),
+                                 event_format_interval(ed.param1.interval_ms).c_str());
+        }
+        case event_type_t::file_change: {
+            return format_string(_(L"handler for changes to '%ls'"), ed.str_param1.c_str());
+        }
         case event_type_t::any: {
             DIE("Unreachable");
         }
@@ -186,12 +413,19 @@ void event_add_handler(std::shared_ptr<event_handler_t> eh) {
     if (eh->desc.type == event_type_t::signal) {
         signal_handle(eh->desc.param1.signal);
         set_signal_observed(eh->desc.param1.signal, true);
+    } else if (eh->desc.type == event_type_t::interval) {
+        // The first interval starts now, rather than at the epoch.
+        eh->interval_count = now_ms() / eh->desc.param1.interval_ms;
+    } else if (eh->desc.type == event_type_t::file_change) {
+        s_file_watch_handlers_changed = true;
     }
 
     s_event_handlers.acquire()->push_back(std::move(eh));
 }
 
 void event_remove_function_handlers(const wcstring &name) {
+    // The paths of file change handlers are watched again when they are next needed.
+    s_file_watch_handlers_changed = true;
     auto handlers = s_event_handlers.acquire();
     auto begin = handlers->begin(), end = handlers->end();
     handlers->erase(std::remove_if(begin, end,
@@ -222,6 +456,114 @@ bool event_is_signal_observed(int sig) {
     return result;
 }
 
+/// The functions whose handlers are running in the background.
+static mainthread_t<std::set<wcstring>> s_async_running;
+
+/// What the handlers which finished in the background wrote, to be printed before the prompt.
+static mainthread_t<std::string> s_async_output;
+
+/// Run \p handler for \p event by evaluating \p call in another fish process, if its function was
+/// defined with --async and the session is interactive.
+/// \return whether it was run, or skipped because it is still running from before.
+static bool fire_async(parser_t &parser, const event_handler_t &handler, const event_t &event,
+                       const wcstring &call) {
+#if FISH_USE_POSIX_SPAWN
+    auto props = function_get_properties(handler.function_name);
+    if (!props || !props->run_async || !is_interactive_session()) return false;
+
+    // Slow handlers are not started again until they finish, so they don't pile up.
+    std::set<wcstring> &running = s_async_running;
+    if (!running.insert(handler.function_name).second) return true;
+
+    // The other process gets the exported and universal variables, but the function, the event's
+    // variables and where to autoload functions from must be passed along, since it does not read
+    // the configuration files. Those could be slow and would run again for every event.
+    wcstring script;
+    if (auto function_path = parser.vars().get(L"fish_function_path")) {
+        script.append(L"set -g fish_function_path");
+        for (const wcstring &dir : function_path->as_list()) {
+            script.push_back(L' ');
+            script.append(escape_string(dir, ESCAPE_ALL));
+        }
+        script.push_back(L'\n');
+    }
+    for (const auto &kv : event.variables) {
+        script.append(L"set -g ");
+        script.append(kv.first);
+        for (const wcstring &val : kv.second) {
+            script.push_back(L' ');
+            script.append(escape_string(val, ESCAPE_ALL));
+        }
+        script.push_back(L'\n');
+    }
+    script.append(functions_def(handler.function_name));
+    script.push_back(L'\n');
+    script.append(call);
+
+    std::string fish_path = get_executable_path("fish");
+    std::vector<std::string> args{fish_path, "--no-config", "-c", wcs2string(script)};
+    auto env = parser.vars().export_arr();
+    wcstring name = handler.function_name;
+    FLOGF(event, L"Firing event '%ls' in the background", event.desc.str_param1.c_str());
+    iothread_perform([=]() { return exec_detached_output(fish_path, args, env, true); },
+                     [=](std::string output) {
+                         s_async_running->erase(name);
+                         s_async_output->append(output);
+                     });
+    return true;
+#else
+    UNUSED(parser);
+    UNUSED(handler);
+    UNUSED(event);
+    UNUSED(call);
+    return false;
+#endif
+}
+
+void event_print_async_output() {
+    // Collect the handlers which finished while a command ran.
+    if (!s_async_running->empty()) iothread_service_completion_with_timeout(0);
+    std::string &output = s_async_output;
+    if (output.empty()) return;
+    write_loop(STDOUT_FILENO, output.data(), output.size());
+    output.clear();
+}
+
+static const wchar_t *event_name_for_type(event_type_t type);
+
+/// Describe \p event for the event-trace log: its type, what it is about and its arguments.
+static wcstring event_trace_desc(const event_t &event) {
+    const event_description_t &ed = event.desc;
+    wcstring result = event_name_for_type(ed.type);
+    switch (ed.type) {
+        case event_type_t::signal:
+            result.append(L" ");
+            result.append(sig2wcs(ed.param1.signal));
+            break;
+        case event_type_t::exit:
+            result.append(L" ");
+            result.append(to_string(ed.param1.pid));
+            break;
+        case event_type_t::interval:
+            result.append(L" ");
+            result.append(event_format_interval(ed.param1.interval_ms));
+            break;
+        case event_type_t::variable:
+        case event_type_t::generic:
+        case event_type_t::file_change:
+            result.append(L" ");
+            result.append(ed.str_param1);
+            break;
+        default:
+            break;
+    }
+    for (const wcstring &arg : event.arguments) {
+        result.push_back(L' ');
+        result.append(escape_string(arg, ESCAPE_ALL));
+    }
+    return result;
+}
+
 /// Perform the specified event. Since almost all event firings will not be matched by even a single
 /// event handler, we make sure to optimize the 'no matches' path. This means that nothing is
 /// allocated/initialized unless needed.
@@ -242,6 +584,17 @@ static void event_fire_internal(parser_t &parser, const event_t &event) {
         }
     }
 
+    // Only events that run something are traced. Events fired from handlers are indented under the
+    // handler that fired them.
+    const bool tracing =
+        !fire.empty() && flog_details::category_list_t::g_instance->event_trace.enabled;
+    wcstring indent(2 * (ld.is_event - 1), L' ');
+    if (tracing) {
+        FLOGF(event_trace, L"%ls%ls (%lu handler%ls)", indent.c_str(),
+              event_trace_desc(event).c_str(), static_cast<unsigned long>(fire.size()),
+              fire.size() == 1 ? L"" : L"s");
+    }
+
     // Iterate over our list of matching events. Fire the ones that are still present.
     for (const shared_ptr<event_handler_t> &handler : fire) {
         // Only fire if this event is still present.
@@ -260,15 +613,25 @@ static void event_fire_internal(parser_t &parser, const event_t &event) {
             buffer.append(escape_string(arg, ESCAPE_ALL));
         }
 
+        if (fire_async(parser, *handler, event, buffer)) {
+            FLOGF(event_trace, L"%ls  %ls: async", indent.c_str(), handler->function_name.c_str());
+            continue;
+        }
+
         // Event handlers are not part of the main flow of code, so they are marked as
         // non-interactive.
         scoped_push<bool> interactive{&ld.is_interactive, false};
         auto prev_statuses = parser.get_last_statuses();
 
         FLOGF(event, L"Firing event '%ls'", event.desc.str_param1.c_str());
+        double start = tracing ? timef() : 0;
         block_t *b = parser.push_block(block_t::event_block(event));
         parser.eval(buffer, io_chain_t());
         parser.pop_block(b);
+        if (tracing) {
+            FLOGF(event_trace, L"%ls  %ls: %.3f ms", indent.c_str(),
+                  handler->function_name.c_str(), (timef() - start) * 1000);
+        }
         parser.set_last_statuses(std::move(prev_statuses));
     }
 }
@@ -293,6 +656,7 @@ void event_fire_delayed(parser_t &parser) {
                 auto e = std::make_shared<event_t>(event_type_t::signal);
                 e->desc.param1.signal = sig;
                 e->arguments.push_back(sig2wcs(sig));
+                e->variables = s_pending_signals.sender_variables(sig);
                 to_send.push_back(std::move(e));
             }
         }
@@ -308,9 +672,80 @@ void event_fire_delayed(parser_t &parser) {
     }
 }
 
-void event_enqueue_signal(int signal) {
+void event_fire_intervals(parser_t &parser) {
+    // Handlers with the same interval run together, at multiples of it since the epoch. Handlers
+    // which are due but can't run now are skipped rather than run late, so waiting for input does
+    // not spin until they can.
+    uint64_t now = now_ms();
+    std::vector<uint64_t> due;
+    for (const auto &handler : *s_event_handlers.acquire()) {
+        if (handler->desc.type != event_type_t::interval) continue;
+        uint64_t interval = handler->desc.param1.interval_ms;
+        if (handler->interval_count == now / interval) continue;
+        handler->interval_count = now / interval;
+        if (!contains(due, interval)) due.push_back(interval);
+    }
+    if (due.empty()) return;
+
+    // Do not invoke new event handlers from within event handlers, or while unwinding.
+    if (parser.libdata().is_event || signal_check_cancel()) return;
+    for (uint64_t interval : due) {
+        event_t evt(event_type_t::interval);
+        evt.desc.param1.interval_ms = interval;
+        evt.arguments.push_back(event_format_interval(interval));
+        if (!event_is_blocked(parser, evt)) event_fire_internal(parser, evt);
+    }
+}
+
+long event_interval_usec_delay() {
+    uint64_t now = now_ms();
+    long result = -1;
+    for (const auto &handler : *s_event_handlers.acquire()) {
+        if (handler->desc.type != event_type_t::interval) continue;
+        uint64_t interval = handler->desc.param1.interval_ms;
+        if (handler->interval_count != now / interval) return 0;
+        // Wake up at least once a minute, so long intervals fit.
+        uint64_t msecs = std::min<uint64_t>(interval - now % interval, 60 * 1000);
+        long usecs = static_cast<long>(msecs * 1000);
+        if (result < 0 || usecs < result) result = usecs;
+    }
+    return result;
+}
+
+/// \return the file watches, watching the paths of the current file change event handlers.
+static file_watches_t &file_watches() {
+    if (s_file_watch_handlers_changed) {
+        s_file_watch_handlers_changed = false;
+        wcstring_list_t paths;
+        for (const auto &handler : *s_event_handlers.acquire()) {
+            const wcstring &path = handler->desc.str_param1;
+            if (handler->desc.type == event_type_t::file_change && !contains(paths, path)) {
+                paths.push_back(path);
+            }
+        }
+        s_file_watches->set_paths(paths);
+    }
+    return s_file_watches;
+}
+
+void event_fire_file_changes(parser_t &parser) {
+    // Unlike intervals, changes which can't be handled now are kept until they can be.
+    bool can_run = !parser.libdata().is_event && !signal_check_cancel();
+    for (auto &change : file_watches().take_changes(can_run)) {
+        event_t evt(event_type_t::file_change);
+        evt.desc.str_param1 = std::move(change.first);
+        evt.arguments = std::move(change.second);
+        event_fire(parser, evt);
+    }
+}
+
+int event_file_watch_fd() { return file_watches().fd(); }
+
+long event_file_watch_usec_delay() { return file_watches().usec_delay(); }
+
+void event_enqueue_signal(int signal, const siginfo_t *info) {
     // Beware, we are in a signal handler
-    s_pending_signals.mark(signal);
+    s_pending_signals.mark(signal, info);
 }
 
 void event_fire(parser_t &parser, const event_t &event) {
@@ -335,7 +770,9 @@ static const event_type_name_t events_mapping[] = {{event_type_t::signal, L"sign
                                                    {event_type_t::variable, L"variable"},
                                                    {event_type_t::exit, L"exit"},
                                                    {event_type_t::caller_exit, L"caller-exit"},
-                                                   {event_type_t::generic, L"generic"}};
+                                                   {event_type_t::generic, L"generic"},
+                                                   {event_type_t::interval, L"interval"},
+                                                   {event_type_t::file_change, L"file-change"}};
 
 maybe_t<event_type_t> event_type_for_name(const wcstring &name) {
     for (const auto &em : events_mapping) {
@@ -371,7 +808,10 @@ void event_print(io_streams_t &streams, maybe_t<event_type_t> type_filter) {
                           return d1.param1.pid < d2.param1.pid;
                       case event_type_t::caller_exit:
                           return d1.param1.caller_id < d2.param1.caller_id;
+                      case event_type_t::interval:
+                          return d1.param1.interval_ms < d2.param1.interval_ms;
                       case event_type_t::variable:
+                      case event_type_t::file_change:
                       case event_type_t::any:
                       case event_type_t::generic:
                           return d1.str_param1 < d2.str_param1;
@@ -401,8 +841,15 @@ void event_print(io_streams_t &streams, maybe_t<event_type_t> type_filter) {
             case event_type_t::caller_exit:
                 streams.out.append_format(L"caller-exit %ls\n", evt->function_name.c_str());
                 break;
+            case event_type_t::interval:
+                streams.out.append_format(L"%ls %ls\n",
+                                          event_format_interval(evt->desc.param1.interval_ms)
+                                              .c_str(),
+                                          evt->function_name.c_str());
+                break;
             case event_type_t::variable:
             case event_type_t::generic:
+            case event_type_t::file_change:
                 streams.out.append_format(L"%ls %ls\n", evt->desc.str_param1.c_str(),
                                           evt->function_name.c_str());
                 break;
@@ -415,12 +862,14 @@ void event_print(io_streams_t &streams, maybe_t<event_type_t> type_filter) {
     }
 }
 
-void event_fire_generic(parser_t &parser, const wchar_t *name, const wcstring_list_t *args) {
+void event_fire_generic(parser_t &parser, const wchar_t *name, const wcstring_list_t *args,
+                        const event_variables_t *variables) {
     assert(name && "Null name");
 
     event_t ev(event_type_t::generic);
     ev.desc.str_param1 = name;
     if (args) ev.arguments = *args;
+    if (variables) ev.variables = *variables;
     event_fire(parser, ev);
 }
 
@@ -442,6 +891,18 @@ event_description_t event_description_t::generic(wcstring str) {
     return event;
 }
 
+event_description_t event_description_t::interval(uint64_t ms) {
+    event_description_t event(event_type_t::interval);
+    event.param1.interval_ms = ms;
+    return event;
+}
+
+event_description_t event_description_t::file_change(wcstring path) {
+    event_description_t event(event_type_t::file_change);
+    event.str_param1 = std::move(path);
+    return event;
+}
+
 event_t event_t::variable(wcstring name, wcstring_list_t args) {
     event_t evt{event_type_t::variable};
     evt.desc.str_param1 = std::move(name);
@@ -6,10 +6,12 @@
 #ifndef FISH_EVENT_H
 #define FISH_EVENT_H
 
+#include <signal.h>
 #include <unistd.h>
 
 #include <map>
 #include <memory>
+#include <utility>
 #include <vector>
 
 #include "common.h"
@@ -33,6 +35,10 @@ enum class event_type_t {
     caller_exit,
     /// A generic event.
     generic,
+    /// An event triggered periodically while waiting for input.
+    interval,
+    /// An event triggered by a change to a file or directory.
+    file_change,
 };
 
 /// Properties of an event.
@@ -46,22 +52,27 @@ struct event_description_t {
     /// pid: Process id for process-type events. Use EVENT_ANY_PID to match any pid. (Negative
     /// values are used for PGIDs).
     /// caller_id: Internal job id for caller_exit type events
+    /// interval_ms: The interval in milliseconds for interval events.
     union {
         int signal;
         uint64_t caller_id;
         pid_t pid;
+        uint64_t interval_ms;
     } param1{};
 
     /// The string types are one of the following:
     ///
     /// variable: Variable name for variable-type events.
     /// param: The parameter describing this generic event.
+    /// path: The absolute path of the file or directory for file_change events.
     wcstring str_param1{};
 
     explicit event_description_t(event_type_t t) : type(t) {}
     static event_description_t signal(int sig);
     static event_description_t variable(wcstring str);
     static event_description_t generic(wcstring str);
+    static event_description_t interval(uint64_t ms);
+    static event_description_t file_change(wcstring path);
 };
 
 /// Represents a handler for an event.
@@ -72,12 +83,19 @@ struct event_handler_t {
     /// Name of the function to invoke.
     wcstring function_name{};
 
+    /// For interval events, the number of intervals since the epoch when the handler last ran, or
+    /// was added.
+    uint64_t interval_count{0};
+
     explicit event_handler_t(event_type_t t) : desc(t) {}
     event_handler_t(event_description_t d, wcstring name)
         : desc(std::move(d)), function_name(std::move(name)) {}
 };
 using event_handler_list_t = std::vector<std::shared_ptr<event_handler_t>>;
 
+/// Variables set in the local scope of an event handler, with their values.
+using event_variables_t = std::vector<std::pair<wcstring, wcstring_list_t>>;
+
 /// Represents a event that is fired, or capable of being fired.
 struct event_t {
     /// Properties of the event.
@@ -86,6 +104,9 @@ struct event_t {
     /// Arguments to any handler.
     wcstring_list_t arguments{};
 
+    /// Variables set in the local scope of the handler function, along with its arguments.
+    event_variables_t variables{};
+
     event_t(event_type_t t) : desc(t) {}
 
     static event_t variable(wcstring name, wcstring_list_t args);
@@ -112,8 +133,32 @@ void event_fire(parser_t &parser, const event_t &event);
 /// Fire all delayed events attached to the given parser.
 void event_fire_delayed(parser_t &parser);
 
-/// Enqueue a signal event. Invoked from a signal handler.
-void event_enqueue_signal(int signal);
+/// Fire the interval event handlers which are due. Invoked while waiting for input.
+void event_fire_intervals(parser_t &parser);
+
+/// \return the number of microseconds until an interval event handler is due, 0 if one is due now,
+/// or -1 if there are no interval event handlers.
+long event_interval_usec_delay();
+
+/// Fire the file change event handlers for the paths which changed. Invoked while waiting for
+/// input.
+void event_fire_file_changes(parser_t &parser);
+
+/// \return an fd which becomes readable when a path watched by file change event handlers may have
+/// changed, or -1 if there is none.
+int event_file_watch_fd();
+
+/// \return the number of microseconds until the watched paths should be checked without waiting
+/// for the fd, 0 if they should be checked now, or -1 if there is no need.
+long event_file_watch_usec_delay();
+
+/// Print what the handlers of functions defined with --async wrote since the last call. Invoked
+/// before the prompt.
+void event_print_async_output();
+
+/// Enqueue a signal event, with the sender and value from \p info if it is not null. Invoked from a
+/// signal handler.
+void event_enqueue_signal(int signal, const siginfo_t *info);
 
 /// Print all events. If type_filter is not none(), only output events with that type.
 void event_print(io_streams_t &streams, maybe_t<event_type_t> type_filter);
@@ -121,9 +166,13 @@ void event_print(io_streams_t &streams, maybe_t<event_type_t> type_filter);
 /// Returns a string describing the specified event.
 wcstring event_get_desc(const parser_t &parser, const event_t &e);
 
-/// Fire a generic event with the specified name.
+/// \return an interval of \p ms milliseconds as seconds, like "60" or "0.25".
+wcstring event_format_interval(uint64_t ms);
+
+/// Fire a generic event with the specified name, and the variables \p variables for its handlers.
 void event_fire_generic(parser_t &parser, const wchar_t *name,
-                        const wcstring_list_t *args = nullptr);
+                        const wcstring_list_t *args = nullptr,
+                        const event_variables_t *variables = nullptr);
 
 /// Return the event type for a given name, or none.
 maybe_t<event_type_t> event_type_for_name(const wcstring &name);
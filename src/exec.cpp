@@ -19,6 +19,7 @@
 
 #include <algorithm>
 #include <cstring>
+#include <cwchar>
 #include <functional>
 #include <map>
 #include <memory>
@@ -27,9 +28,13 @@
 #include <type_traits>
 #include <vector>
 
+#include "ast.h"
 #include "builtin.h"
+#include "cgroup.h"
 #include "common.h"
+#include "crash.h"
 #include "env.h"
+#include "env_dispatch.h"
 #include "exec.h"
 #include "fallback.h"  // IWYU pragma: keep
 #include "flog.h"
@@ -47,6 +52,7 @@
 #include "redirection.h"
 #include "signal.h"
 #include "timer.h"
+#include "tokenizer.h"
 #include "trace.h"
 #include "wcstringutil.h"
 #include "wutil.h"  // IWYU pragma: keep
@@ -127,13 +133,14 @@ enum class launch_result_t {
     safe_launch_process(p, actual_cmd.c_str(), argv_array.get(), envv);
 }
 
-// Returns whether we can use posix spawn for a given process in a given job.
+// Returns the reason we cannot use posix spawn for a given process in a given job, or none() if
+// we can.
 //
 // To avoid the race between the caller calling tcsetpgrp() and the client checking the
 // foreground process group, we don't use posix_spawn if we're going to foreground the process. (If
 // we use fork(), we can call tcsetpgrp after the fork, before the exec, and avoid the race).
-static bool can_use_posix_spawn_for_job(const std::shared_ptr<job_t> &job,
-                                        const dup2_list_t &dup2s) {
+static maybe_t<spawn_fallback_t> posix_spawn_fallback_for_job(const std::shared_ptr<job_t> &job,
+                                                              const dup2_list_t &dup2s) {
     // Hack - do not use posix_spawn if there are self-fd redirections.
     // For example if you were to write:
     //   cmd 6< /dev/null
@@ -141,7 +148,7 @@ static bool can_use_posix_spawn_for_job(const std::shared_ptr<job_t> &job,
     // to add a dup2 action, it would be ignored and the CLO_EXEC bit would remain. So don't use
     // posix_spawn in this case; instead we'll call fork() and clear the CLO_EXEC bit manually.
     for (const auto &action : dup2s.get_actions()) {
-        if (action.src == action.target) return false;
+        if (action.src == action.target) return spawn_fallback_t::self_fd_redirect;
     }
     if (job->wants_job_control()) {  //!OCLINT(collapsible if statements)
         // We are going to use job control; therefore when we launch this job it will get its own
@@ -149,10 +156,70 @@ static bool can_use_posix_spawn_for_job(const std::shared_ptr<job_t> &job,
         if (job->group->should_claim_terminal()) {
             // It will be foregrounded, so we will call tcsetpgrp(), therefore do not use
             // posix_spawn.
-            return false;
+            return spawn_fallback_t::claims_terminal;
         }
     }
-    return true;
+    return none();
+}
+
+/// Counters backing get_spawn_stats().
+static relaxed_atomic_t<unsigned long> s_spawn_count{0};
+static relaxed_atomic_t<unsigned long> s_fork_disabled_count{0};
+static relaxed_atomic_t<unsigned long> s_fork_self_fd_redirect_count{0};
+static relaxed_atomic_t<unsigned long> s_fork_claims_terminal_count{0};
+static relaxed_atomic_t<unsigned long> s_fork_priority_count{0};
+static relaxed_atomic_t<unsigned long> s_fork_cgroup_count{0};
+
+static const wchar_t *spawn_fallback_description(spawn_fallback_t reason) {
+    switch (reason) {
+        case spawn_fallback_t::disabled:
+            return L"posix_spawn disabled";
+        case spawn_fallback_t::self_fd_redirect:
+            return L"self-fd redirection";
+        case spawn_fallback_t::claims_terminal:
+            return L"foreground job control";
+        case spawn_fallback_t::priority:
+            return L"scheduling priority";
+        case spawn_fallback_t::cgroup:
+            return L"job cgroup";
+    }
+    DIE("unknown spawn fallback reason");
+}
+
+/// Record that an external command is being launched with fork() for the given reason.
+static void note_spawn_fallback(spawn_fallback_t reason, const char *actual_cmd) {
+    switch (reason) {
+        case spawn_fallback_t::disabled:
+            s_fork_disabled_count++;
+            break;
+        case spawn_fallback_t::self_fd_redirect:
+            s_fork_self_fd_redirect_count++;
+            break;
+        case spawn_fallback_t::claims_terminal:
+            s_fork_claims_terminal_count++;
+            break;
+        case spawn_fallback_t::priority:
+            s_fork_priority_count++;
+            break;
+        case spawn_fallback_t::cgroup:
+            s_fork_cgroup_count++;
+            break;
+    }
+    FLOGF(exec_spawn, L"Using fork for '%s': %ls", actual_cmd,
+          spawn_fallback_description(reason));
+}
+
+spawn_stats_t get_spawn_stats() {
+    spawn_stats_t stats;
+    stats.posix_spawn = s_spawn_count;
+    stats.fork_disabled = s_fork_disabled_count;
+    stats.fork_self_fd_redirect = s_fork_self_fd_redirect_count;
+    stats.fork_claims_terminal = s_fork_claims_terminal_count;
+    stats.fork_priority = s_fork_priority_count;
+    stats.fork_cgroup = s_fork_cgroup_count;
+    stats.fork = stats.fork_disabled + stats.fork_self_fd_redirect + stats.fork_claims_terminal +
+                 stats.fork_priority + stats.fork_cgroup;
+    return stats;
 }
 
 static void internal_exec(env_stack_t &vars, job_t *j, const io_chain_t &block_io) {
@@ -341,6 +408,7 @@ static launch_result_t fork_child_for_process(const std::shared_ptr<job_t> &job,
         // This is the child process. Setup redirections, print correct output to
         // stdout and stderr, and then exit.
         p->pid = getpid();
+        if (job->cgroup) child_join_cgroup(job->cgroup->procs_fd());
         pid_t pgid = maybe_assign_pgid_from_child(job, p->pid);
 
         // The child attempts to join the pgroup.
@@ -359,8 +427,8 @@ static launch_result_t fork_child_for_process(const std::shared_ptr<job_t> &job,
     // This is the parent process. Store away information on the child, and
     // possibly give it control over the terminal.
     s_fork_count++;
-    FLOGF(exec_fork, L"Fork #%d, pid %d: %s for '%ls'", int(s_fork_count), pid, fork_type,
-          p->argv0());
+    FLOG(exec_fork, "Fork", flog_kv("count", int(s_fork_count)), flog_kv("pid", pid),
+         flog_kv("type", fork_type), flog_kv("command", p->argv0()));
 
     p->pid = pid;
     pid_t pgid = maybe_assign_pgid_from_child(job, p->pid);
@@ -522,11 +590,19 @@ static launch_result_t exec_external_command(parser_t &parser, const std::shared
     const char *actual_cmd = actual_cmd_str.c_str();
     const wchar_t *file = parser.libdata().current_filename;
 
+    // posix_spawn has no way to change the priority, so the child applies it after fork.
+    const maybe_t<spawn_priority_t> priority = parser.libdata().spawn_priority;
+
+    maybe_t<spawn_fallback_t> spawn_fallback = spawn_fallback_t::disabled;
 #if FISH_USE_POSIX_SPAWN
     // Prefer to use posix_spawn, since it's faster on some systems like OS X.
-    bool use_posix_spawn = g_use_posix_spawn && can_use_posix_spawn_for_job(j, dup2s);
-    if (use_posix_spawn) {
+    if (g_use_posix_spawn) spawn_fallback = posix_spawn_fallback_for_job(j, dup2s);
+    if (!spawn_fallback && priority) spawn_fallback = spawn_fallback_t::priority;
+    if (!spawn_fallback && j->cgroup) spawn_fallback = spawn_fallback_t::cgroup;
+    if (!spawn_fallback) {
         s_fork_count++;  // spawn counts as a fork+exec
+        s_spawn_count++;
+        FLOGF(exec_spawn, L"Using posix_spawn for '%s'", actual_cmd);
 
         posix_spawner_t spawner(j.get(), dup2s);
         maybe_t<pid_t> pid = spawner.spawn(actual_cmd, const_cast<char *const *>(argv),
@@ -541,8 +617,8 @@ static launch_result_t exec_external_command(parser_t &parser, const std::shared
         // (https://github.com/fish-shell/fish-shell/issues/360).
         // usleep(10000);
 
-        FLOGF(exec_fork, L"Fork #%d, pid %d: spawn external command '%s' from '%ls'",
-              int(s_fork_count), *pid, actual_cmd, file ? file : L"<no file>");
+        FLOG(exec_fork, "Spawn", flog_kv("count", int(s_fork_count)), flog_kv("pid", *pid),
+             flog_kv("command", actual_cmd), flog_kv("file", file ? file : L"<no file>"));
 
         // these are all things do_fork() takes care of normally (for forked processes):
         p->pid = *pid;
@@ -558,8 +634,11 @@ static launch_result_t exec_external_command(parser_t &parser, const std::shared
     } else
 #endif
     {
-        return fork_child_for_process(j, p, dup2s, "external command",
-                                      [&] { safe_launch_process(p, actual_cmd, argv, envv); });
+        note_spawn_fallback(*spawn_fallback, actual_cmd);
+        return fork_child_for_process(j, p, dup2s, "external command", [&] {
+            if (priority) child_set_priority(*priority);
+            safe_launch_process(p, actual_cmd, argv, envv);
+        });
     }
 }
 
@@ -574,6 +653,15 @@ static block_t *function_prepare_environment(parser_t &parser, wcstring_list_t a
         func_name = std::move(*argv.begin());
         argv.erase(argv.begin());
     }
+    // An event handler is called directly from the event block, and gets the event's variables.
+    const event_t *event = nullptr;
+    const block_t *scope = parser.block_at_index(0);
+    const block_t *caller = parser.block_at_index(1);
+    if (scope && scope->type() == block_type_t::top && caller &&
+        caller->type() == block_type_t::event && caller->event) {
+        event = &*caller->event;
+    }
+
     block_t *fb = parser.push_block(block_t::function_block(func_name, argv, props.shadow_scope));
     auto &vars = parser.vars();
 
@@ -596,6 +684,12 @@ static block_t *function_prepare_environment(parser_t &parser, wcstring_list_t a
         vars.set(kv.first, ENV_LOCAL | ENV_USER, kv.second);
     }
 
+    if (event) {
+        for (const auto &kv : event->variables) {
+            vars.set(kv.first, ENV_LOCAL | ENV_USER, kv.second);
+        }
+    }
+
     vars.set_argv(std::move(argv));
     return fb;
 }
@@ -790,6 +884,17 @@ static launch_result_t exec_process_in_job(parser_t &parser, process_t *p,
         parser.vars().set(assignment.variable_name, ENV_LOCAL | ENV_EXPORT, assignment.values);
     }
 
+    // Record the start for $CMD_DURATIONS and 'time'. Internal processes run to completion here,
+    // so we measure them directly, while external processes are measured when they are reaped.
+    // The CPU time of internal processes is only measured for 'time'.
+    maybe_t<timer_snapshot_t> internal_timer_start{};
+    if (j->wants_timing() && p->is_internal()) {
+        internal_timer_start = timer_snapshot_t::take();
+        p->timing.start = internal_timer_start->wall;
+    } else {
+        p->timing.start = std::chrono::steady_clock::now();
+    }
+
     // Execute the process.
     p->check_generations_before_launch();
     switch (p->type) {
@@ -835,6 +940,11 @@ static launch_result_t exec_process_in_job(parser_t &parser, process_t *p,
                 "Aborting.");
         }
     }
+    if (internal_timer_start) {
+        timer_record_internal_exit(p, *internal_timer_start);
+    } else if (p->is_internal()) {
+        p->timing.end = std::chrono::steady_clock::now();
+    }
     return launch_result_t::ok;
 }
 
@@ -897,6 +1007,19 @@ static bool allow_exec_with_background_jobs(parser_t &parser) {
     }
 }
 
+/// \return whether the job \p j only runs the set builtin, which does not depend on the locale or
+/// terminal. Such jobs don't need changes to those to be applied first, which lets a script set
+/// several of their variables in a row and only reinitialize once.
+static bool job_only_sets_variables(const job_t *j) {
+    for (const auto &p : j->processes) {
+        const wchar_t *argv0 = p->argv0();
+        if (p->type != process_type_t::builtin || !argv0 || std::wcscmp(argv0, L"set") != 0) {
+            return false;
+        }
+    }
+    return true;
+}
+
 bool exec_job(parser_t &parser, const shared_ptr<job_t> &j, const io_chain_t &block_io) {
     assert(j && "null job_t passed to exec_job!");
 
@@ -904,6 +1027,11 @@ bool exec_job(parser_t &parser, const shared_ptr<job_t> &j, const io_chain_t &bl
     if (no_exec()) {
         return true;
     }
+    crash_report_set_command(j->command());
+
+    if (!job_only_sets_variables(j.get())) {
+        env_dispatch_flush(parser.vars());
+    }
 
     // Handle an exec call.
     if (j->processes.front()->type == process_type_t::exec) {
@@ -927,7 +1055,12 @@ bool exec_job(parser_t &parser, const shared_ptr<job_t> &j, const io_chain_t &bl
         }
         return false;
     }
-    cleanup_t timer = push_timer(j->wants_timing() && !no_exec());
+    cleanup_t timer = push_timer(j->wants_timing() && !no_exec(), j);
+
+    // A cgroup is only worth creating for jobs which may run processes of their own.
+    if (g_use_job_cgroups && j->wants_job_control() && j->has_external_proc()) {
+        j->cgroup = job_cgroup_t::create();
+    }
 
     // Get the deferred process, if any. We will have to remember its pipes.
     autoclose_pipes_t deferred_pipes;
@@ -1012,7 +1145,8 @@ bool exec_job(parser_t &parser, const shared_ptr<job_t> &j, const io_chain_t &bl
     FLOGF(exec_job_exec, L"Executed job %d from command '%ls' with pgrp %d", j->job_id(),
           j->command_wcstr(), j->get_pgid() ? *j->get_pgid() : -2);
 
-    j->mark_constructed();
+    // The job of a process substitution is constructed along with the job it feeds.
+    if (!j->is_procsub()) j->mark_constructed();
 
     // If exec_error then a backgrounded job would have been terminated before it was ever assigned
     // a pgroup, so error out before setting last_pid.
@@ -1021,7 +1155,7 @@ bool exec_job(parser_t &parser, const shared_ptr<job_t> &j, const io_chain_t &bl
         parser.vars().set_one(L"last_pid", ENV_GLOBAL, to_string(*pgid));
     }
 
-    j->continue_job(parser, !j->is_initially_background());
+    j->continue_job(parser, !j->is_initially_background() && !j->is_procsub());
     return true;
 }
 
@@ -1066,6 +1200,91 @@ static void populate_subshell_output(wcstring_list_t *lst, const separated_buffe
     }
 }
 
+/// Builtins which only compute their output, without running other code or needing an actual fd
+/// for their output.
+static const wchar_t *const in_process_builtins[] = {
+    L"[",     L"argparse", L"break",    L"contains", L"continue", L"count", L"echo",
+    L"false", L"math",     L"printf",   L"pwd",      L"random",   L"realpath", L"return",
+    L"set",   L"set_color", L"status",  L"string",   L"test",     L"true",
+};
+
+/// \return whether every command under \p root, including in the functions it calls, is one of
+/// in_process_builtins, so its output can be collected without a pipe. \p src is the source of
+/// \p root, and \p seen holds the functions already checked.
+static bool runs_only_in_process(const ast::node_t &root, const wcstring &src, parser_t &parser,
+                                 std::vector<wcstring> &seen) {
+    ast::traversal_t traversal(&root);
+    while (const ast::node_t *node = traversal.next()) {
+        if (const auto *job = node->try_as<ast::job_t>()) {
+            if (job->bg) return false;
+        } else if (const auto *block = node->try_as<ast::block_statement_t>()) {
+            // Defining a function could change what a later call does.
+            if (block->header->type == ast::type_t::function_header) return false;
+        } else if (const auto *redir = node->try_as<ast::redirection_t>()) {
+            // Redirecting to stdout, like 2>&1, needs its fd.
+            auto oper = pipe_or_redir_t::from_string(redir->oper.source(src));
+            if (!oper || (oper->mode == redirection_mode_t::fd &&
+                          redir->target.source(src) == L"1")) {
+                return false;
+            }
+        } else if (const auto *stmt = node->try_as<ast::decorated_statement_t>()) {
+            auto decoration = stmt->decoration();
+            if (decoration != statement_decoration_t::none &&
+                decoration != statement_decoration_t::builtin) {
+                return false;
+            }
+            wcstring cmd = stmt->command.source(src);
+            if (decoration == statement_decoration_t::none) {
+                // Functions shadow builtins. Don't autoload functions which aren't builtins
+                // here, since they might not be called.
+                bool is_function = contains(in_process_builtins, cmd)
+                                       ? function_exists(cmd, parser)
+                                       : function_exists_no_autoload(cmd);
+                if (is_function) {
+                    if (contains(seen, cmd)) continue;
+                    seen.push_back(cmd);
+                    auto props = function_get_properties(cmd);
+                    if (!props || !runs_only_in_process(props->func_node->jobs,
+                                                        props->parsed_source->src, parser, seen)) {
+                        return false;
+                    }
+                    continue;
+                }
+            }
+            if (!contains(in_process_builtins, cmd)) return false;
+        }
+    }
+    return true;
+}
+
+/// \return whether \p jobs is a single pipeline of external commands, so it can run alongside fish.
+/// \p src is the source of \p jobs.
+static bool is_external_pipeline(const ast::job_list_t &jobs, const wcstring &src,
+                                 parser_t &parser) {
+    if (jobs.count() != 1) return false;
+    const ast::job_conjunction_t &conjunction = *jobs.at(0);
+    const ast::job_t &job = conjunction.job;
+    if (conjunction.decorator || !conjunction.continuations.empty() || job.time || job.bg) {
+        return false;
+    }
+    auto is_external = [&](const ast::statement_t &statement) {
+        const auto *stmt = statement.contents->try_as<ast::decorated_statement_t>();
+        if (!stmt) return false;
+        auto decoration = stmt->decoration();
+        if (decoration == statement_decoration_t::command) return true;
+        if (decoration != statement_decoration_t::none) return false;
+        // A command which is expanded might turn out to be a function or builtin.
+        wcstring cmd = stmt->command.source(src);
+        if (cmd.find_first_of(L"$(*?{~\\'\"") != wcstring::npos) return false;
+        return !function_exists(cmd, parser) && !builtin_exists(cmd);
+    };
+    if (!is_external(job.statement)) return false;
+    for (const ast::job_continuation_t &continuation : job.continuation) {
+        if (!is_external(continuation.statement)) return false;
+    }
+    return true;
+}
+
 /// Execute \p cmd in a subshell in \p parser. If \p lst is not null, populate it with the output.
 /// Return $status in \p out_status.
 /// If \p job_group is set, any spawned commands should join that job group.
@@ -1076,7 +1295,8 @@ static void populate_subshell_output(wcstring_list_t *lst, const separated_buffe
 /// of $status.
 static int exec_subshell_internal(const wcstring &cmd, parser_t &parser,
                                   const job_group_ref_t &job_group, wcstring_list_t *lst,
-                                  bool *break_expand, bool apply_exit_status, bool is_subcmd) {
+                                  bool *break_expand, bool apply_exit_status, bool is_subcmd,
+                                  std::string *out_raw = nullptr) {
     ASSERT_IS_MAIN_THREAD();
     auto &ld = parser.libdata();
 
@@ -1092,14 +1312,24 @@ static int exec_subshell_internal(const wcstring &cmd, parser_t &parser,
 
     const bool split_output = !parser.vars().get(L"IFS").missing_or_empty();
 
+    // Command substitutions which only run builtins, like (string upper $x), are common in
+    // prompts. Their output doesn't need a pipe and a fillthread, so skip them.
+    parsed_source_ref_t ps = parse_source(wcstring{cmd}, parse_flag_none, nullptr);
+    std::vector<wcstring> seen_functions;
+    bool in_process = ps && runs_only_in_process(*ps->ast.top(), ps->src, parser, seen_functions);
+
     // IO buffer creation may fail (e.g. if we have too many open files to make a pipe), so this may
     // be null.
-    auto bufferfill = io_bufferfill_t::create(ld.read_limit);
+    auto bufferfill = in_process ? io_bufferfill_t::create_in_process(ld.read_limit)
+                                 : io_bufferfill_t::create(ld.read_limit);
     if (!bufferfill) {
         *break_expand = true;
         return STATUS_CMD_ERROR;
     }
-    eval_res_t eval_res = parser.eval(cmd, io_chain_t{bufferfill}, job_group, block_type_t::subst);
+    // If the source doesn't parse, eval() reports the error.
+    eval_res_t eval_res =
+        ps ? parser.eval(ps, io_chain_t{bufferfill}, job_group, block_type_t::subst)
+           : parser.eval(cmd, io_chain_t{bufferfill}, job_group, block_type_t::subst);
     separated_buffer_t buffer = io_bufferfill_t::finish(std::move(bufferfill));
     if (buffer.discarded()) {
         *break_expand = true;
@@ -1114,6 +1344,9 @@ static int exec_subshell_internal(const wcstring &cmd, parser_t &parser,
     if (lst) {
         populate_subshell_output(lst, buffer, split_output);
     }
+    if (out_raw) {
+        *out_raw = buffer.newline_serialized();
+    }
     *break_expand = false;
     return eval_res.status.status_value();
 }
@@ -1127,6 +1360,47 @@ int exec_subshell_for_expand(const wcstring &cmd, parser_t &parser,
     return break_expand ? ret : STATUS_CMD_OK;
 }
 
+int exec_subshell_for_procsub(const wcstring &cmd, parser_t &parser,
+                              const job_group_ref_t &job_group, wcstring *out_path) {
+    ASSERT_IS_MAIN_THREAD();
+    auto &ld = parser.libdata();
+    procsubs_t *procsubs = ld.procsubs;
+    assert(procsubs && "Process substitution outside of a job");
+
+    // A pipeline of external commands runs alongside the job, so its output streams. Anything else
+    // runs in fish, and so is run to completion first, with its output collected.
+    parsed_source_ref_t ps = parse_source(wcstring{cmd}, parse_flag_none, nullptr);
+    bool streams =
+        ps && is_external_pipeline(*ps->ast.top()->as<ast::job_list_t>(), ps->src, parser);
+    std::string output;
+    if (!streams) {
+        bool break_expand = false;
+        int ret = exec_subshell_internal(cmd, parser, job_group, nullptr, &break_expand, true,
+                                         true, &output);
+        if (break_expand) return ret;
+    }
+
+    auto pipes = make_autoclose_pipes();
+    if (!pipes) return STATUS_CMD_ERROR;
+    if (streams) {
+        // The job joins the group of the job it feeds, along with any other process substitutions
+        // of it. Our copy of the write end is closed once it has been launched.
+        scoped_push<procsubs_t *> target(&ld.procsub_target, procsubs);
+        auto stdout_io = std::make_shared<io_fd_t>(STDOUT_FILENO, pipes->write.fd());
+        parser.eval(ps, io_chain_t{stdout_io}, procsubs->group ? procsubs->group : job_group,
+                    block_type_t::subst);
+        pipes->write.close();
+    } else {
+        write_to_pipe_and_close(std::move(pipes->write), std::move(output));
+    }
+    // The read end must be inherited by external commands, which open it by path. It is made so
+    // only now, so the job above does not hold it open.
+    set_cloexec(pipes->read.fd(), false);
+    *out_path = format_string(L"/dev/fd/%d", pipes->read.fd());
+    procsubs->fds.push_back(std::move(pipes->read));
+    return STATUS_CMD_OK;
+}
+
 int exec_subshell(const wcstring &cmd, parser_t &parser, bool apply_exit_status) {
     bool break_expand = false;
     return exec_subshell_internal(cmd, parser, nullptr, nullptr, &break_expand, apply_exit_status,
@@ -1139,3 +1413,63 @@ int exec_subshell(const wcstring &cmd, parser_t &parser, wcstring_list_t &output
     return exec_subshell_internal(cmd, parser, nullptr, &outputs, &break_expand, apply_exit_status,
                                   false);
 }
+
+#if FISH_USE_POSIX_SPAWN
+std::string exec_detached_output(const std::string &path, const std::vector<std::string> &args,
+                                 const std::shared_ptr<const null_terminated_array_t<char>> &env,
+                                 bool with_stderr) {
+    std::string result;
+    auto pipes = make_autoclose_pipes();
+    if (!pipes) return result;
+
+    posix_spawnattr_t attr;
+    posix_spawn_file_actions_t actions;
+    if (posix_spawnattr_init(&attr) != 0) return result;
+    if (posix_spawn_file_actions_init(&actions) != 0) {
+        posix_spawnattr_destroy(&attr);
+        return result;
+    }
+    cleanup_t destroy([&] {
+        posix_spawn_file_actions_destroy(&actions);
+        posix_spawnattr_destroy(&attr);
+    });
+
+    // Put the command in its own process group, so it doesn't get the signals from the terminal.
+    sigset_t sigdefault, sigmask;
+    get_signals_with_handlers(&sigdefault);
+    sigemptyset(&sigmask);
+    short flags = POSIX_SPAWN_SETSIGDEF | POSIX_SPAWN_SETSIGMASK | POSIX_SPAWN_SETPGROUP;
+    posix_spawnattr_setflags(&attr, flags);
+    posix_spawnattr_setsigdefault(&attr, &sigdefault);
+    posix_spawnattr_setsigmask(&attr, &sigmask);
+    posix_spawnattr_setpgroup(&attr, 0);
+
+    posix_spawn_file_actions_addopen(&actions, STDIN_FILENO, "/dev/null", O_RDONLY, 0);
+    posix_spawn_file_actions_adddup2(&actions, pipes->write.fd(), STDOUT_FILENO);
+    if (with_stderr) {
+        posix_spawn_file_actions_adddup2(&actions, pipes->write.fd(), STDERR_FILENO);
+    } else {
+        posix_spawn_file_actions_addopen(&actions, STDERR_FILENO, "/dev/null", O_WRONLY, 0);
+    }
+
+    null_terminated_array_t<char> argv(args);
+    pid_t pid;
+    int err = posix_spawn(&pid, path.c_str(), &actions, &attr, argv.get(),
+                          const_cast<char *const *>(env->get()));
+    if (err != 0) {
+        FLOGF(warning, L"Could not run '%s': %s", path.c_str(), std::strerror(err));
+        return result;
+    }
+
+    pipes->write.close();
+    char buff[4096];
+    ssize_t amt;
+    while ((amt = read_loop(pipes->read.fd(), buff, sizeof buff)) > 0) {
+        result.append(buff, amt);
+    }
+    int status;
+    while (waitpid(pid, &status, 0) < 0 && errno == EINTR) {
+    }
+    return result;
+}
+#endif
@@ -7,6 +7,8 @@
 #include <vector>
 
 #include "common.h"
+#include "null_terminated_array.h"
+#include "postfork.h"
 #include "proc.h"
 
 /// Execute the processes specified by \p j in the parser \p.
@@ -35,10 +37,54 @@ int exec_subshell(const wcstring &cmd, parser_t &parser, wcstring_list_t &output
 int exec_subshell_for_expand(const wcstring &cmd, parser_t &parser,
                              const job_group_ref_t &job_group, wcstring_list_t &outputs);
 
+/// Run \p cmd for a process substitution like <(cmd). Its output is provided through a pipe whose
+/// read end stays open until the current job has been launched, and the path by which that pipe
+/// may be opened (like /dev/fd/12) is returned in \p out_path. A pipeline of external commands is
+/// launched as a job which runs alongside the current one, in its group; anything else is run to
+/// completion first. This must only be called while a job is being populated. Like
+/// exec_subshell_for_expand, a non-zero return means that we should halt expansion.
+int exec_subshell_for_procsub(const wcstring &cmd, parser_t &parser,
+                              const job_group_ref_t &job_group, wcstring *out_path);
+
+#if FISH_USE_POSIX_SPAWN
+/// Run the command at \p path with the given arguments and environment in its own process group,
+/// outside of job control, and return what it wrote to stdout, and to stderr if \p with_stderr is
+/// set. Otherwise stderr is /dev/null, as is stdin. This blocks, so it is run on a background
+/// thread.
+std::string exec_detached_output(const std::string &path, const std::vector<std::string> &args,
+                                 const std::shared_ptr<const null_terminated_array_t<char>> &env,
+                                 bool with_stderr);
+#endif
+
 /// Loops over close until the syscall was run without being interrupted.
 void exec_close(int fd);
 
 /// Add signals that should be masked for external processes in this job.
 bool blocked_signals_for_job(const job_t &job, sigset_t *sigmask);
 
+/// The reasons an external command may be launched via fork() instead of posix_spawn().
+enum class spawn_fallback_t {
+    disabled,          // posix_spawn is unavailable or turned off via $fish_use_posix_spawn
+    self_fd_redirect,  // a redirection like `cmd 6< file` may open onto its own target fd
+    claims_terminal,   // the job is foregrounded, so we must tcsetpgrp() before exec
+    priority,          // `command --nice` or `--ionice` set a priority to apply before exec
+    cgroup,            // the job has a cgroup, which the child must join before exec
+};
+
+/// Counts of how external commands were launched, used by `status spawn-stats`.
+struct spawn_stats_t {
+    unsigned long posix_spawn{0};
+    unsigned long fork{0};
+
+    // Breakdown of the forks by the reason posix_spawn was not used.
+    unsigned long fork_disabled{0};
+    unsigned long fork_self_fd_redirect{0};
+    unsigned long fork_claims_terminal{0};
+    unsigned long fork_priority{0};
+    unsigned long fork_cgroup{0};
+};
+
+/// \return a snapshot of the spawn statistics for this process.
+spawn_stats_t get_spawn_stats();
+
 #endif
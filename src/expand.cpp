@@ -499,6 +499,47 @@ static expand_result_t expand_variables(wcstring instr, completion_receiver_t *o
 }
 
 /// Perform brace expansion, placing the expanded strings into \p out.
+static expand_result_t expand_braces(wcstring &&instr, expand_flags_t flags,
+                                     completion_receiver_t *out, parse_error_list_t *errors);
+
+/// Expand a numeric brace range like {1..10} or {10..1..2}, whose contents \p range lie between
+/// \p prefix and \p suffix. The ".."s have been replaced by BRACE_RANGE when unescaping.
+static expand_result_t expand_brace_range(const wcstring &prefix, const wcstring &range,
+                                          const wcstring &suffix, expand_flags_t flags,
+                                          completion_receiver_t *out, parse_error_list_t *errors) {
+    wcstring_list_t parts = split_string(range, BRACE_RANGE);
+    assert((parts.size() == 2 || parts.size() == 3) && "Brace range should have been validated");
+
+    // Zero-pad all values if an endpoint is zero-padded, like {01..10}.
+    bool zero_padded = false;
+    int width = 0;
+    for (size_t i = 0; i < 2; i++) {
+        const wcstring &part = parts.at(i);
+        size_t digits_start = part.at(0) == L'-' ? 1 : 0;
+        if (part.size() > digits_start + 1 && part.at(digits_start) == L'0') zero_padded = true;
+        width = std::max(width, static_cast<int>(part.size()));
+    }
+    if (!zero_padded) width = 0;
+
+    long long start = fish_wcstoll(parts.at(0).c_str());
+    long long end = fish_wcstoll(parts.at(1).c_str());
+    long long step = parts.size() == 3 ? std::llabs(fish_wcstoll(parts.at(2).c_str())) : 1;
+    if (step == 0) step = 1;
+
+    // The values are limited to 18 digits, so this doesn't overflow.
+    long long count = (start <= end ? end - start : start - end) / step + 1;
+    long long value = start;
+    for (long long i = 0; i < count; i++) {
+        wcstring whole_item = prefix;
+        whole_item.append(format_string(L"%0*lld", width, value));
+        whole_item.append(suffix);
+        expand_result_t result = expand_braces(std::move(whole_item), flags, out, errors);
+        if (result == expand_result_t::error) return result;
+        value = start <= end ? value + step : value - step;
+    }
+    return expand_result_t::ok;
+}
+
 static expand_result_t expand_braces(wcstring &&instr, expand_flags_t flags,
                                      completion_receiver_t *out, parse_error_list_t *errors) {
     bool syntax_error = false;
@@ -567,11 +608,18 @@ static expand_result_t expand_braces(wcstring &&instr, expand_flags_t flags,
 
     if (brace_begin == nullptr) {
         if (!out->add(std::move(instr))) {
-            return expand_result_t::error;
+            return append_overflow_error(errors);
         }
         return expand_result_t::ok;
     }
 
+    if (!last_sep && std::find(brace_begin + 1, brace_end, BRACE_BEGIN) == brace_end &&
+        std::find(brace_begin + 1, brace_end, BRACE_RANGE) != brace_end) {
+        // A numeric range, which was validated when unescaping.
+        return expand_brace_range(wcstring(in, brace_begin), wcstring(brace_begin + 1, brace_end),
+                                  wcstring(brace_end + 1), flags, out, errors);
+    }
+
     length_preceding_braces = (brace_begin - in);
     length_following_braces = instr.size() - (brace_end - in) - 1;
     tot_len = length_preceding_braces + length_following_braces;
@@ -610,6 +658,60 @@ static expand_result_t expand_braces(wcstring &&instr, expand_flags_t flags,
     return expand_result_t::ok;
 }
 
+/// \return the error message for a failed command or process substitution with \p status.
+static const wchar_t *subshell_error_message(int status) {
+    // TODO: Ad-hoc switch, how can we enumerate the possible errors more safely?
+    switch (status) {
+        case STATUS_READ_TOO_MUCH:
+            return N_(L"Too much data emitted by command substitution so it was discarded");
+        case STATUS_CMD_ERROR:
+            return N_(L"Too many active file descriptors");
+        default:
+            return N_(L"Unknown error while evaluating command substitution");
+    }
+}
+
+/// Expand a process substitution \p input like <(cmd), executing on \p ctx. The result is a path
+/// from which the command's output may be read.
+static expand_result_t expand_procsub(const wcstring &input, const operation_context_t &ctx,
+                                      completion_receiver_t *out, parse_error_list_t *errors) {
+    assert(ctx.parser && "Cannot expand without a parser");
+    size_t cursor = 0;
+    size_t paren_begin = 0;
+    size_t paren_end = 0;
+    wcstring subcmd;
+    if (parse_util_locate_cmdsubst_range(input, &cursor, &subcmd, &paren_begin, &paren_end,
+                                         false) != 1) {
+        append_syntax_error(errors, SOURCE_LOCATION_UNKNOWN, L"Mismatched parenthesis");
+        return expand_result_t::make_error(STATUS_EXPAND_ERROR);
+    }
+    assert(paren_begin == 1 && "Process substitution should start with <(");
+    if (paren_end + 1 != input.size()) {
+        append_syntax_error(errors, paren_end + 1,
+                            L"A process substitution must be a complete argument");
+        return expand_result_t::make_error(STATUS_EXPAND_ERROR);
+    }
+    // The pipe is only kept open for a job, so there must be one to read it, unlike in e.g. the
+    // arguments of a for loop.
+    if (!ctx.parser->libdata().procsubs) {
+        append_syntax_error(errors, 0,
+                            L"A process substitution may only be used in the arguments or "
+                            L"redirections of a command");
+        return expand_result_t::make_error(STATUS_EXPAND_ERROR);
+    }
+
+    wcstring path;
+    int subshell_status = exec_subshell_for_procsub(subcmd, *ctx.parser, ctx.job_group, &path);
+    if (subshell_status != 0) {
+        append_cmdsub_error(errors, 1, _(subshell_error_message(subshell_status)));
+        return expand_result_t::make_error(subshell_status);
+    }
+    if (!out->add(escape_string(path, ESCAPE_ALL))) {
+        return append_overflow_error(errors);
+    }
+    return expand_result_t::ok;
+}
+
 /// Expand a command substitution \p input, executing on \p ctx, and inserting the results into
 /// \p out_list, or any errors into \p errors. \return an expand result.
 static expand_result_t expand_cmdsubst(wcstring input, const operation_context_t &ctx,
@@ -643,20 +745,7 @@ static expand_result_t expand_cmdsubst(wcstring input, const operation_context_t
     wcstring_list_t sub_res;
     int subshell_status = exec_subshell_for_expand(subcmd, *ctx.parser, ctx.job_group, sub_res);
     if (subshell_status != 0) {
-        // TODO: Ad-hoc switch, how can we enumerate the possible errors more safely?
-        const wchar_t *err;
-        switch (subshell_status) {
-            case STATUS_READ_TOO_MUCH:
-                err = L"Too much data emitted by command substitution so it was discarded";
-                break;
-            case STATUS_CMD_ERROR:
-                err = L"Too many active file descriptors";
-                break;
-            default:
-                err = L"Unknown error while evaluating command substitution";
-                break;
-        }
-        append_cmdsub_error(errors, paren_begin, _(err));
+        append_cmdsub_error(errors, paren_begin, _(subshell_error_message(subshell_status)));
         return expand_result_t::make_error(subshell_status);
     }
 
@@ -858,10 +947,17 @@ static void remove_internal_separator(wcstring *str, bool conv) {
                str->end());
 
     // If conv is true, replace all instances of ANY_STRING with '*',
-    // ANY_STRING_RECURSIVE with '*'.
+    // ANY_STRING_RECURSIVE with '*', and put glob qualifiers back in brackets.
     if (conv) {
+        if (str->find(GLOB_QUALIFIER) != wcstring::npos) {
+            str->push_back(L']');
+        }
         for (auto &idx : *str) {
             switch (idx) {
+                case GLOB_QUALIFIER: {
+                    idx = L'[';
+                    break;
+                }
                 case ANY_CHAR: {
                     idx = L'?';
                     break;
@@ -929,6 +1025,11 @@ expand_result_t expander_t::stage_cmdsubst(wcstring input, completion_receiver_t
         }
     } else {
         assert(ctx.parser && "Must have a parser to expand command substitutions");
+        // The tokenizer only lets an unquoted argument start with <( if it is a process
+        // substitution.
+        if (string_prefixes_string(L"<(", input)) {
+            return expand_procsub(input, ctx, out, errors);
+        }
         return expand_cmdsubst(std::move(input), ctx, out, errors);
     }
 }
@@ -978,6 +1079,21 @@ expand_result_t expander_t::stage_wildcards(wcstring path_to_expand, completion_
     const bool for_completions = flags & expand_flag::for_completions;
     const bool skip_wildcards = flags & expand_flag::skip_wildcards;
 
+    // Split off any glob qualifiers like *[type=dir]. They are not used for completions.
+    maybe_t<glob_qualifiers_t> qualifiers{};
+    size_t qualifier_pos = path_to_expand.find(GLOB_QUALIFIER);
+    if (qualifier_pos != wcstring::npos) {
+        if (!for_completions) {
+            glob_qualifiers_t quals;
+            if (auto err = parse_glob_qualifiers(path_to_expand.substr(qualifier_pos + 1), &quals)) {
+                append_syntax_error(errors, SOURCE_LOCATION_UNKNOWN, L"%ls", err->c_str());
+                return expand_result_t::make_error(STATUS_EXPAND_ERROR);
+            }
+            qualifiers = quals;
+        }
+        path_to_expand.resize(qualifier_pos);
+    }
+
     if (has_wildcard && (flags & expand_flag::executables_only)) {
         // don't do wildcard expansion for executables, see issue #785
     } else if ((for_completions && !skip_wildcards) || has_wildcard) {
@@ -1019,13 +1135,13 @@ expand_result_t expander_t::stage_wildcards(wcstring path_to_expand, completion_
             } else {
                 // Get the PATH/CDPATH and CWD. Perhaps these should be passed in. An empty CDPATH
                 // implies just the current directory, while an empty PATH is left empty.
-                wcstring_list_t paths;
-                if (auto paths_var = ctx.vars.get(for_cd ? L"CDPATH" : L"PATH")) {
-                    paths = paths_var->as_list();
-                }
-                if (paths.empty()) {
-                    paths.emplace_back(for_cd ? L"." : L"");
-                }
+                // The variable's values are shared rather than copied, since this runs for each
+                // completion.
+                const wcstring_list_t dflt_paths{for_cd ? L"." : L""};
+                auto paths_var = ctx.vars.get(for_cd ? L"CDPATH" : L"PATH");
+                const wcstring_list_t &paths = paths_var && !paths_var->as_list().empty()
+                                                   ? paths_var->as_list()
+                                                   : dflt_paths;
                 for (const wcstring &next_path : paths) {
                     effective_working_dirs.push_back(
                         path_apply_working_directory(next_path, working_dir));
@@ -1056,6 +1172,10 @@ expand_result_t expander_t::stage_wildcards(wcstring path_to_expand, completion_
                   [&](const completion_t &a, const completion_t &b) {
                       return wcsfilecmp_glob(a.completion.c_str(), b.completion.c_str()) < 0;
                   });
+        if (qualifiers) {
+            apply_glob_qualifiers(*qualifiers, &expanded, working_dir);
+            if (expanded.empty()) result = expand_result_t::wildcard_no_match;
+        }
         if (!out->add_list(std::move(expanded))) {
             result = expand_result_t::error;
         }
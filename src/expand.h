@@ -93,6 +93,8 @@ enum : wchar_t {
     /// Character representing an empty variable expansion. Only used transitively while expanding
     /// variables.
     VARIABLE_EXPAND_EMPTY,
+    /// Character representing the ".." in a numeric brace range like {1..10}.
+    BRACE_RANGE,
     /// This is a special pseudo-char that is not used other than to mark the end of the the special
     /// characters so we can sanity check the enum range.
     EXPAND_SENTINEL
@@ -42,6 +42,7 @@
 
 #include "common.h"    // IWYU pragma: keep
 #include "fallback.h"  // IWYU pragma: keep
+#include "lru.h"
 
 #if defined(TPARM_SOLARIS_KLUDGE)
 #undef tparm
@@ -192,6 +193,10 @@ int futimes(int fd, const struct timeval *times) {
 #if HAVE_GETTEXT
 char *fish_gettext(const char *msgid) { return gettext(msgid); }
 
+char *fish_ngettext(const char *msgid, const char *msgid_plural, unsigned long n) {
+    return ngettext(msgid, msgid_plural, n);
+}
+
 char *fish_bindtextdomain(const char *domainname, const char *dirname) {
     return bindtextdomain(domainname, dirname);
 }
@@ -199,6 +204,9 @@ char *fish_bindtextdomain(const char *domainname, const char *dirname) {
 char *fish_textdomain(const char *domainname) { return textdomain(domainname); }
 #else
 char *fish_gettext(const char *msgid) { return (char *)msgid; }
+char *fish_ngettext(const char *msgid, const char *msgid_plural, unsigned long n) {
+    return (char *)(n == 1 ? msgid : msgid_plural);
+}
 char *fish_bindtextdomain(const char *domainname, const char *dirname) {
     UNUSED(domainname);
     UNUSED(dirname);
@@ -236,7 +244,17 @@ static int fish_get_emoji_width(wchar_t c) {
 
 // Big hack to use our versions of wcswidth where we know them to be broken, which is
 // EVERYWHERE (https://github.com/fish-shell/fish-shell/issues/2199)
-#include "widecharwidth/widechar_width.h"
+// The table is generated from widechar_width.h at build time by width_table_gen.
+#include "width_table.h"
+
+/// \return the entry for \p wc in the width table: its width, or one of the negative width_*
+/// values if that depends on the settings.
+static int width_table_lookup(wchar_t wc) {
+    auto c = static_cast<uint32_t>(wc);
+    if (c >= width_table_max_char) return width_unassigned;
+    return width_table_blocks[width_table_index[c / width_table_block_size]]
+                             [c % width_table_block_size];
+}
 
 int fish_wcwidth(wchar_t wc) {
     // The system version of wcwidth should accurately reflect the ability to represent characters
@@ -246,50 +264,97 @@ int fish_wcwidth(wchar_t wc) {
         return wcwidth(wc);
     }
 
-    // Check for VS16 which selects emoji presentation. This "promotes" a character like U+2764
-    // (width 1) to an emoji (probably width 2). So treat it as width 1 so the sums work. See #2652.
-    // VS15 selects text presentation.
-    const wchar_t variation_selector_16 = L'\uFE0F', variation_selector_15 = L'\uFE0E';
-    if (wc == variation_selector_16)
-        return 1;
-    else if (wc == variation_selector_15)
-        return 0;
-
-    // Korean Hangul Jamo median vowels and final consonants.
-    // These can either appear in combined form, taking 0 width themselves,
-    // or standalone with a 1 width. Since that's literally not expressible with wcwidth(),
-    // we take the position that the typical way for them to show up is composed.
-    if (wc >= L'\u1160' && wc <= L'\u11FF') return 0;
-    int width = widechar_wcwidth(wc);
-
+    // The table already has our overrides for variation selectors and Hangul Jamo.
+    int width = width_table_lookup(wc);
     switch (width) {
-        case widechar_nonprint:
-        case widechar_combining:
-        case widechar_unassigned:
+        case width_nonprint:
+        case width_combining:
+        case width_unassigned:
             // Fall back to system wcwidth in this case.
             return wcwidth(wc);
-        case widechar_ambiguous:
+        case width_ambiguous:
             return g_fish_ambiguous_width;
-        case widechar_private_use:
+        case width_private_use:
             // TR11: "All private-use characters are by default classified as Ambiguous".
             return g_fish_ambiguous_width;
-        case widechar_widened_in_9:
+        case width_widened_in_9:
             return fish_get_emoji_width(wc);
         default:
-            assert(width > 0 && "Unexpectedly nonpositive width");
+            assert(width >= 0 && "Unexpectedly negative width");
             return width;
     }
 }
 
-int fish_wcswidth(const wchar_t *str, size_t n) {
-    int result = 0;
-    for (size_t i = 0; i < n && str[i] != L'\0'; i++) {
-        int w = fish_wcwidth(str[i]);
+namespace {
+/// The width of a grapheme cluster, with the settings it depends on.
+struct cluster_width_t {
+    int width;
+    int ambiguous_width;
+    int emoji_width;
+    size_t mb_cur_max;
+
+    bool is_current() const {
+        return ambiguous_width == g_fish_ambiguous_width &&
+               emoji_width == fish_get_emoji_width(0) && mb_cur_max == MB_CUR_MAX;
+    }
+};
+
+/// Clusters of more than one character involve combining marks or joiners, whose width comes
+/// from the system wcwidth, so remember the most recent ones.
+class cluster_width_cache_t : public lru_cache_t<cluster_width_cache_t, cluster_width_t> {
+   public:
+    cluster_width_cache_t() : lru_cache_t<cluster_width_cache_t, cluster_width_t>(512) {}
+};
+}  // namespace
+static owning_lock<cluster_width_cache_t> s_cluster_widths;
+
+/// \return whether \p wc, which follows \p prev, continues the grapheme cluster of \p prev. This
+/// approximates the Unicode rules with combining marks, variation selectors and zero width
+/// joiners, which covers the clusters terminals draw as one.
+static bool continues_cluster(wchar_t prev, wchar_t wc) {
+    const wchar_t zero_width_joiner = L'\u200D';
+    if (prev == zero_width_joiner || wc == zero_width_joiner) return true;
+    if (wc == L'\uFE0F' || wc == L'\uFE0E') return true;
+    return width_table_lookup(wc) == width_combining;
+}
+
+/// \return the width of the cluster of \p len characters at \p str, or -1 if a character has none.
+static int cluster_width(const wchar_t *str, size_t len) {
+    if (len == 1) return fish_wcwidth(*str);
+
+    wcstring cluster(str, len);
+    auto cache = s_cluster_widths.acquire();
+    cluster_width_t *cached = cache->get(cluster);
+    if (cached && cached->is_current()) return cached->width;
+
+    int width = 0;
+    for (wchar_t wc : cluster) {
+        int w = fish_wcwidth(wc);
         if (w < 0) {
-            result = -1;
+            width = -1;
             break;
         }
+        width += w;
+    }
+    cluster_width_t entry{width, g_fish_ambiguous_width, fish_get_emoji_width(0), MB_CUR_MAX};
+    if (cached) {
+        *cached = entry;
+    } else {
+        cache->insert(std::move(cluster), entry);
+    }
+    return width;
+}
+
+int fish_wcswidth(const wchar_t *str, size_t n) {
+    int result = 0;
+    size_t start = 0;
+    while (start < n && str[start] != L'\0') {
+        size_t end = start + 1;
+        while (end < n && str[end] != L'\0' && continues_cluster(str[end - 1], str[end])) end++;
+        int w = cluster_width(str + start, end - start);
+        if (w < 0) return -1;
         result += w;
+        start = end;
     }
     return result;
 }
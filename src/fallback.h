@@ -136,6 +136,9 @@ int futimes(int fd, const struct timeval *times);
 /// Cover for gettext().
 char *fish_gettext(const char *msgid);
 
+/// Cover for ngettext().
+char *fish_ngettext(const char *msgid, const char *msgid_plural, unsigned long n);
+
 /// Cover for bindtextdomain().
 char *fish_bindtextdomain(const char *domainname, const char *dirname);
 
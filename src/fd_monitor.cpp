@@ -3,6 +3,7 @@
 
 #include "fd_monitor.h"
 
+#include "fds.h"
 #include "flog.h"
 #include "io.h"
 #include "iothread.h"
@@ -17,6 +18,9 @@ fd_monitor_t::fd_monitor_t() {
         DIE("Unable to create pipes");
     }
 
+    fd_set_purpose(self_pipe->read.fd(), N_(L"fd monitor wakeup pipe"));
+    fd_set_purpose(self_pipe->write.fd(), N_(L"fd monitor wakeup pipe"));
+
     // Ensure the write side is nonblocking to avoid deadlock.
     notify_write_fd_ = std::move(self_pipe->write);
     if (make_fd_nonblocking(notify_write_fd_.fd())) {
@@ -4,10 +4,19 @@
 
 #include "fds.h"
 
+#include <dirent.h>
 #include <errno.h>
 #include <fcntl.h>
+#include <sys/stat.h>
 #include <unistd.h>
 
+#include <algorithm>
+#include <atomic>
+#include <climits>
+#include <cstdlib>
+#include <map>
+#include <string>
+
 #include "common.h"
 #include "flog.h"
 #include "wutil.h"
@@ -28,13 +37,7 @@ void autoclose_fd_t::close() {
     fd_ = -1;
 }
 
-/// If the given fd is in the "user range", move it to a new fd in the "high range".
-/// zsh calls this movefd().
-/// \p input_has_cloexec describes whether the input has CLOEXEC already set, so we can avoid
-/// setting it again.
-/// \return the fd, which always has CLOEXEC set; or an invalid fd on failure, in
-/// which case an error will have been printed, and the input fd closed.
-static autoclose_fd_t heightenize_fd(autoclose_fd_t fd, bool input_has_cloexec) {
+autoclose_fd_t heightenize_fd(autoclose_fd_t fd, bool input_has_cloexec) {
     // Check if the fd is invalid or already in our high range.
     if (!fd.valid()) {
         return fd;
@@ -191,3 +194,144 @@ void exec_close(int fd) {
         }
     }
 }
+
+/// The open script fds, by fd.
+static owning_lock<std::map<int, script_fd_t>> s_script_fds;
+
+int script_fd_adopt(autoclose_fd_t fd, wcstring mode, wcstring target) {
+    static std::atomic<uint64_t> s_last_id{0};
+    fd = heightenize_fd(std::move(fd), false);
+    if (!fd.valid()) return -1;
+    int result = fd.acquire();
+    script_fd_t entry{result, ++s_last_id, std::move(mode), std::move(target)};
+    (*s_script_fds.acquire())[result] = std::move(entry);
+    return result;
+}
+
+maybe_t<script_fd_t> script_fd_get(int fd) {
+    auto fds = s_script_fds.acquire();
+    auto where = fds->find(fd);
+    if (where == fds->end()) return none();
+    return where->second;
+}
+
+bool script_fd_close(int fd, uint64_t id) {
+    {
+        auto fds = s_script_fds.acquire();
+        auto where = fds->find(fd);
+        if (where == fds->end() || (id != 0 && where->second.id != id)) return false;
+        fds->erase(where);
+    }
+    exec_close(fd);
+    return true;
+}
+
+std::vector<script_fd_t> script_fd_list() {
+    std::vector<script_fd_t> result;
+    for (const auto &kv : *s_script_fds.acquire()) {
+        result.push_back(kv.second);
+    }
+    return result;
+}
+
+namespace {
+/// A record of what an fd is used for, and which file it was open on at the time.
+struct fd_purpose_t {
+    dev_t dev;
+    ino_t ino;
+    const wchar_t *purpose;
+};
+}  // namespace
+
+/// \return the purposes of the long-lived fds, by fd. Some are created during static
+/// initialization, so this must be usable then.
+static owning_lock<std::map<int, fd_purpose_t>> &fd_purposes() {
+    static auto *s_purposes = new owning_lock<std::map<int, fd_purpose_t>>();
+    return *s_purposes;
+}
+
+void fd_set_purpose(int fd, const wchar_t *purpose) {
+    struct stat buf;
+    if (fstat(fd, &buf) < 0) return;
+    (*fd_purposes().acquire())[fd] = fd_purpose_t{buf.st_dev, buf.st_ino, purpose};
+}
+
+/// \return the numbers of the open fds, in ascending order.
+static std::vector<int> open_fd_numbers() {
+    std::vector<int> result;
+    // Prefer the list the system keeps, which includes fds in the high range.
+    for (const char *dir_path : {"/proc/self/fd", "/dev/fd"}) {
+        DIR *dir = opendir(dir_path);
+        if (!dir) continue;
+        int own_fd = dirfd(dir);
+        while (struct dirent *entry = readdir(dir)) {
+            char *end = nullptr;
+            errno = 0;
+            long fd = std::strtol(entry->d_name, &end, 10);
+            if (errno || end == entry->d_name || *end || fd == own_fd) continue;
+            result.push_back(static_cast<int>(fd));
+        }
+        closedir(dir);
+        // A /dev/fd which only lists the standard fds is no better than probing.
+        if (std::any_of(result.begin(), result.end(), [](int fd) { return fd > STDERR_FILENO; })) {
+            break;
+        }
+        result.clear();
+    }
+    if (result.empty()) {
+        long max_fd = sysconf(_SC_OPEN_MAX);
+        if (max_fd < 0 || max_fd > 4096) max_fd = 4096;
+        for (int fd = 0; fd < max_fd; fd++) {
+            if (fcntl(fd, F_GETFD) >= 0) result.push_back(fd);
+        }
+    }
+    std::sort(result.begin(), result.end());
+    return result;
+}
+
+/// \return the kind of file described by \p buf, which is open on \p fd.
+static const wchar_t *fd_type(int fd, const struct stat &buf) {
+    if (S_ISREG(buf.st_mode)) return L"file";
+    if (S_ISDIR(buf.st_mode)) return L"directory";
+    if (S_ISFIFO(buf.st_mode)) return L"pipe";
+    if (S_ISSOCK(buf.st_mode)) return L"socket";
+    if (S_ISCHR(buf.st_mode)) return isatty(fd) ? L"tty" : L"device";
+    return L"other";
+}
+
+std::vector<open_fd_t> fd_list_open() {
+    std::vector<open_fd_t> result;
+    std::map<int, script_fd_t> script_fds = *s_script_fds.acquire();
+    auto purposes = fd_purposes().acquire();
+    for (int fd : open_fd_numbers()) {
+        int flags = fcntl(fd, F_GETFD);
+        struct stat buf;
+        if (flags < 0 || fstat(fd, &buf) < 0) continue;
+        open_fd_t entry{fd, fd_type(fd, buf), (flags & FD_CLOEXEC) != 0, {}, {}};
+
+        auto purpose = purposes->find(fd);
+        auto script_fd = script_fds.find(fd);
+        if (purpose != purposes->end() && purpose->second.dev == buf.st_dev &&
+            purpose->second.ino == buf.st_ino) {
+            entry.purpose = _(purpose->second.purpose);
+        } else if (script_fd != script_fds.end()) {
+            entry.purpose = format_string(_(L"script fd (%ls)"), script_fd->second.mode.c_str());
+            entry.target = script_fd->second.target;
+        } else if (fd == STDIN_FILENO) {
+            entry.purpose = _(L"standard input");
+        } else if (fd == STDOUT_FILENO) {
+            entry.purpose = _(L"standard output");
+        } else if (fd == STDERR_FILENO) {
+            entry.purpose = _(L"standard error");
+        }
+
+#ifdef __linux__
+        char link[PATH_MAX];
+        std::string proc_path = "/proc/self/fd/" + std::to_string(fd);
+        ssize_t len = readlink(proc_path.c_str(), link, sizeof link - 1);
+        if (len > 0) entry.target = str2wcstring(link, len);
+#endif
+        result.push_back(std::move(entry));
+    }
+    return result;
+}
@@ -3,9 +3,11 @@
 #ifndef FISH_FDS_H
 #define FISH_FDS_H
 
+#include <stdint.h>
+#include <sys/types.h>
+
 #include <algorithm>
 #include <string>
-#include <sys/types.h>
 #include <vector>
 
 #include "maybe.h"
@@ -72,6 +74,14 @@ struct autoclose_pipes_t {
         : read(std::move(r)), write(std::move(w)) {}
 };
 
+/// If the given fd is in the "user range", move it to a new fd in the "high range".
+/// zsh calls this movefd().
+/// \p input_has_cloexec describes whether the input has CLOEXEC already set, so we can avoid
+/// setting it again.
+/// \return the fd, which always has CLOEXEC set; or an invalid fd on failure, in
+/// which case an error will have been printed, and the input fd closed.
+autoclose_fd_t heightenize_fd(autoclose_fd_t fd, bool input_has_cloexec);
+
 /// Call pipe(), populating autoclose fds.
 /// The pipes are marked CLO_EXEC and are placed in the high fd range.
 /// \return pipes on success, none() on error.
@@ -101,4 +111,55 @@ int fd_check_is_remote(int fd);
 /// Close a file descriptor \p fd, retrying on EINTR.
 void exec_close(int fd);
 
+/// Script fds are opened on behalf of scripts by named fd redirections like `{var}>file`. They live
+/// in the high range with CLO_EXEC set, and stay open in the shell until explicitly closed; commands
+/// reach them through fd redirections like `>&$var`.
+
+/// An open script fd.
+struct script_fd_t {
+    int fd;
+    /// Identifies this opening of the fd, as the fd number may be reused after it is closed.
+    uint64_t id;
+    /// How the fd was opened, like "read" or "write".
+    wcstring mode;
+    /// The file or socket the fd was opened on.
+    wcstring target;
+};
+
+/// Take ownership of \p fd as a script fd, moving it into the high range if necessary.
+/// \p mode and \p target describe it, for listing.
+/// \return the new fd, or -1 on failure (in which case \p fd is closed).
+int script_fd_adopt(autoclose_fd_t fd, wcstring mode = {}, wcstring target = {});
+
+/// \return the script fd \p fd, or none if it is not open.
+maybe_t<script_fd_t> script_fd_get(int fd);
+
+/// Close the script fd \p fd. \return false if \p fd is not an open script fd.
+/// If \p id is not 0, only close it if it is still the same opening of the fd.
+bool script_fd_close(int fd, uint64_t id = 0);
+
+/// \return the open script fds, in ascending order.
+std::vector<script_fd_t> script_fd_list();
+
+/// Record what the long-lived fd \p fd is used for, like "signal wakeup pipe", so it can be told
+/// apart from leaked fds when listing them. \p purpose must be a string literal, marked with N_ for
+/// translation. The record is dropped once \p fd refers to a different file.
+void fd_set_purpose(int fd, const wchar_t *purpose);
+
+/// A file descriptor which is open in the fish process.
+struct open_fd_t {
+    int fd;
+    /// What kind of file it is, like "file", "pipe" or "tty".
+    const wchar_t *type;
+    /// Whether it is closed in commands that fish runs.
+    bool cloexec;
+    /// What fish uses it for, or empty if that is unknown.
+    wcstring purpose;
+    /// The file it is open on, or empty if that is unknown.
+    wcstring target;
+};
+
+/// \return the file descriptors which are open in the fish process, in ascending order.
+std::vector<open_fd_t> fd_list_open();
+
 #endif
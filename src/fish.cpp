@@ -39,10 +39,16 @@ Foundation, Inc., 51 Franklin Street, Fifth Floor, Boston, MA  02110-1301, USA
 
 #include "builtin.h"
 #include "common.h"
+#include "control_socket.h"
+#include "crash.h"
+#include "debug_adapter.h"
+#include "doctor.h"
+#include "embedded_data.h"
 #include "env.h"
 #include "event.h"
 #include "expand.h"
 #include "fallback.h"  // IWYU pragma: keep
+#include "fds.h"
 #include "fish_version.h"
 #include "flog.h"
 #include "function.h"
@@ -50,6 +56,8 @@ Foundation, Inc., 51 Franklin Street, Fifth Floor, Boston, MA  02110-1301, USA
 #include "history.h"
 #include "intern.h"
 #include "io.h"
+#include "lsp.h"
+#include "parse_util.h"
 #include "parser.h"
 #include "path.h"
 #include "proc.h"
@@ -68,6 +76,8 @@ class fish_cmd_opts_t {
     // File path for profiling output, or empty for none.
     std::string profile_output;
     std::string profile_startup_output;
+    // The format of the profiling output.
+    profile_format_t profile_format{profile_format_t::text};
     // Commands to be executed in place of interactive shell.
     std::vector<std::string> batch_cmds;
     // Commands to execute after the shell's config has been read.
@@ -76,6 +86,18 @@ class fish_cmd_opts_t {
     bool print_rusage_self{false};
     /// Whether no-exec is set.
     bool no_exec{false};
+    /// Whether to skip reading the configuration files.
+    bool no_config{false};
+    /// Whether to print the ast of the script as JSON instead of running it.
+    bool print_ast{false};
+    /// Whether to run as a language server instead of a shell.
+    bool lsp{false};
+    /// Whether to run as a debugger for a script launched by the client instead of a shell.
+    bool debug_adapter{false};
+    /// Whether to check the environment for common problems instead of running anything.
+    bool doctor{false};
+    /// The path of the socket to listen on for other programs, or empty.
+    wcstring listen_path;
     /// Whether this is a login shell.
     bool is_login{false};
     /// Whether this is an interactive session.
@@ -203,6 +225,18 @@ static struct config_paths_t determine_config_directory_paths(const char *argv0)
         paths.bin = L"" BINDIR;
     }
 
+    // Without fish's data files, use the ones built into fish, if there are any. Data files on disk
+    // take precedence, so a static fish can still be pointed at others.
+    if (waccess(paths.data + L"/config.fish", R_OK) != 0) {
+        if (auto dir = embedded_data_install()) {
+            FLOGF(config, L"No data files in '%ls', using the embedded ones", paths.data.c_str());
+            paths.data = *dir;
+            if (waccess(*dir + L"/locale", X_OK) == 0) {
+                wgettext_set_localedir(wcs2string(*dir + L"/locale"));
+            }
+        }
+    }
+
     FLOGF(config,
           L"determine_config_directory_paths() results:\npaths.data: %ls\npaths.sysconf: "
           L"%ls\npaths.doc: %ls\npaths.bin: %ls",
@@ -229,7 +263,7 @@ static void source_config_in_directory(parser_t &parser, const wcstring &dir) {
     }
     FLOGF(config, L"sourcing %ls", escaped_pathname.c_str());
 
-    const wcstring cmd = L"builtin source " + escaped_pathname;
+    const wcstring cmd = L"builtin source --keep-going " + escaped_pathname;
     set_is_within_fish_initialization(true);
     parser.eval(cmd, io_chain_t());
     set_is_within_fish_initialization(false);
@@ -247,6 +281,9 @@ static void read_init(parser_t &parser, const struct config_paths_t &paths) {
     if (path_get_config(config_dir)) {
         source_config_in_directory(parser, config_dir);
     }
+
+    // Config files keep going after errors, so report them together.
+    reader_report_deferred_errors(parser);
 }
 
 static int run_command_list(parser_t &parser, std::vector<std::string> *cmds,
@@ -261,21 +298,28 @@ static int run_command_list(parser_t &parser, std::vector<std::string> *cmds,
 
 /// Parse the argument list, return the index of the first non-flag arguments.
 static int fish_parse_opt(int argc, char **argv, fish_cmd_opts_t *opts) {
-    static const char *const short_opts = "+hPilnvc:C:p:d:f:D:o:";
+    static const char *const short_opts = "+hPilNnvc:C:p:d:f:D:o:";
     static const struct option long_opts[] = {
         {"command", required_argument, nullptr, 'c'},
         {"init-command", required_argument, nullptr, 'C'},
         {"features", required_argument, nullptr, 'f'},
         {"debug", required_argument, nullptr, 'd'},
+        {"debug-adapter", no_argument, nullptr, 6},
         {"debug-output", required_argument, nullptr, 'o'},
         {"debug-stack-frames", required_argument, nullptr, 'D'},
+        {"doctor", no_argument, nullptr, 8},
         {"interactive", no_argument, nullptr, 'i'},
         {"login", no_argument, nullptr, 'l'},
+        {"listen", required_argument, nullptr, 7},
+        {"lsp", no_argument, nullptr, 5},
+        {"no-config", no_argument, nullptr, 'N'},
         {"no-execute", no_argument, nullptr, 'n'},
+        {"print-ast", no_argument, nullptr, 4},
         {"print-rusage-self", no_argument, nullptr, 1},
         {"print-debug-categories", no_argument, nullptr, 2},
         {"profile", required_argument, nullptr, 'p'},
         {"profile-startup", required_argument, nullptr, 3},
+        {"profile-format", required_argument, nullptr, 9},
         {"private", no_argument, nullptr, 'P'},
         {"help", no_argument, nullptr, 'h'},
         {"version", no_argument, nullptr, 'v'},
@@ -331,6 +375,10 @@ static int fish_parse_opt(int argc, char **argv, fish_cmd_opts_t *opts) {
                 opts->is_login = true;
                 break;
             }
+            case 'N': {
+                opts->no_config = true;
+                break;
+            }
             case 'n': {
                 opts->no_exec = true;
                 break;
@@ -366,6 +414,42 @@ static int fish_parse_opt(int argc, char **argv, fish_cmd_opts_t *opts) {
                 g_profiling_active = true;
                 break;
             }
+            case 4: {
+                opts->print_ast = true;
+                break;
+            }
+            case 5: {
+                opts->lsp = true;
+                break;
+            }
+            case 6: {
+                opts->debug_adapter = true;
+                break;
+            }
+            case 7: {
+                opts->listen_path = str2wcstring(optarg);
+                break;
+            }
+            case 8: {
+                opts->doctor = true;
+                break;
+            }
+            case 9: {
+                if (!std::strcmp(optarg, "text")) {
+                    opts->profile_format = profile_format_t::text;
+                } else if (!std::strcmp(optarg, "json")) {
+                    opts->profile_format = profile_format_t::json;
+                } else if (!std::strcmp(optarg, "callgrind")) {
+                    opts->profile_format = profile_format_t::callgrind;
+                } else {
+                    std::fwprintf(stderr,
+                                  _(L"fish: Invalid profile format '%s', expected text, json or "
+                                    L"callgrind\n"),
+                                  optarg);
+                    exit(1);
+                }
+                break;
+            }
             case 'P': {
                 opts->enable_private_mode = true;
                 break;
@@ -392,13 +476,56 @@ static int fish_parse_opt(int argc, char **argv, fish_cmd_opts_t *opts) {
     // We are an interactive session if we have not been given an explicit
     // command or file to execute and stdin is a tty. Note that the -i or
     // --interactive options also force interactive mode.
-    if (opts->batch_cmds.empty() && optind == argc && isatty(STDIN_FILENO)) {
+    if (opts->batch_cmds.empty() && optind == argc && isatty(STDIN_FILENO) && !opts->lsp &&
+        !opts->debug_adapter && !opts->doctor) {
         set_interactive_session(true);
     }
 
     return optind;
 }
 
+/// Print the ast of the commands given with -c, or else of the script file or stdin, as JSON.
+/// \return the exit status, which is 1 if there were syntax errors.
+static int print_ast_json(const fish_cmd_opts_t &opts, int argc, char **argv, int optind) {
+    wcstring_list_t sources;
+    if (!opts.batch_cmds.empty()) {
+        for (const auto &cmd : opts.batch_cmds) {
+            sources.push_back(str2wcstring(cmd));
+        }
+    } else {
+        autoclose_fd_t opened_fd;
+        int fd = STDIN_FILENO;
+        if (optind < argc) {
+            opened_fd = autoclose_fd_t(open_cloexec(argv[optind], O_RDONLY));
+            if (!opened_fd.valid()) {
+                perror(argv[optind]);
+                return 1;
+            }
+            fd = opened_fd.fd();
+        }
+        std::string contents;
+        char buff[4096];
+        ssize_t amt;
+        while ((amt = read_loop(fd, buff, sizeof buff)) > 0) {
+            contents.append(buff, amt);
+        }
+        if (amt < 0) {
+            perror("read");
+            return 1;
+        }
+        sources.push_back(str2wcstring(contents));
+    }
+
+    bool any_errored = false;
+    for (const wcstring &src : sources) {
+        bool errored = false;
+        std::fputws(parse_util_ast_json(src, &errored).c_str(), stdout);
+        any_errored |= errored;
+    }
+    std::fflush(stdout);
+    return any_errored ? 1 : 0;
+}
+
 int main(int argc, char **argv) {
     int res = 1;
     int my_optind = 0;
@@ -467,6 +594,7 @@ int main(int argc, char **argv) {
 
     const struct config_paths_t paths = determine_config_directory_paths(argv[0]);
     env_init(&paths);
+    crash_report_init();
 
     // Set features early in case other initialization depends on them.
     // Start with the ones set in the environment, then those set on the command line (so the
@@ -482,16 +610,36 @@ int main(int argc, char **argv) {
     misc_init();
     reader_init();
 
+    // Printing the ast does not run anything, including the config files.
+    if (opts.print_ast) {
+        exit_without_destructors(print_ast_json(opts, argc, argv, my_optind));
+    }
+
     parser_t &parser = parser_t::principal_parser();
 
-    read_init(parser, paths);
+    // The language server and debug adapter talk to their client over stdin and stdout, so keep
+    // those to themselves. Anything else fish would print, including from the config files, goes to
+    // stderr instead.
+    autoclose_fd_t client_in, client_out;
+    if (opts.lsp || opts.debug_adapter) {
+        client_in = autoclose_fd_t(fcntl(STDIN_FILENO, F_DUPFD_CLOEXEC, k_first_high_fd));
+        client_out = autoclose_fd_t(fcntl(STDOUT_FILENO, F_DUPFD_CLOEXEC, k_first_high_fd));
+        autoclose_fd_t devnull(open_cloexec("/dev/null", O_RDONLY));
+        if (!client_in.valid() || !client_out.valid() || !devnull.valid() ||
+            dup2(devnull.fd(), STDIN_FILENO) < 0 || dup2(STDERR_FILENO, STDOUT_FILENO) < 0) {
+            perror("fish");
+            exit_without_destructors(1);
+        }
+    }
+
+    if (!opts.no_config) read_init(parser, paths);
     // Stomp the exit status of any initialization commands (issue #635).
     parser.set_last_statuses(statuses_t::just(STATUS_CMD_OK));
 
     // If we're profiling startup to a separate file, write it now.
     if (!opts.profile_startup_output.empty()
         && opts.profile_startup_output != opts.profile_output) {
-        parser.emit_profiling(opts.profile_startup_output.c_str());
+        parser.emit_profiling(opts.profile_startup_output.c_str(), opts.profile_format);
 
         // If we are profiling both, ensure the startup data only
         // ends up in the startup file.
@@ -505,7 +653,17 @@ int main(int argc, char **argv) {
         res = run_command_list(parser, &opts.postconfig_cmds, {});
     }
 
-    if (!opts.batch_cmds.empty()) {
+    if (opts.lsp) {
+        res = 0;
+        parser.set_last_statuses(statuses_t::just(lsp_run(parser, client_in.fd(), client_out.fd())));
+    } else if (opts.debug_adapter) {
+        res = 0;
+        parser.set_last_statuses(
+            statuses_t::just(debug_adapter_run(parser, client_in.fd(), client_out.fd())));
+    } else if (opts.doctor) {
+        res = 0;
+        parser.set_last_statuses(statuses_t::just(doctor_run(parser)));
+    } else if (!opts.batch_cmds.empty()) {
         // Run the commands specified as arguments, if any.
         if (get_login()) {
             // Do something nasty to support OpenSUSE assuming we're bash. This may modify cmds.
@@ -528,7 +686,15 @@ int main(int argc, char **argv) {
             FLOGF(error, L"no-execute mode enabled and no script given. Exiting");
             return EXIT_FAILURE;  // above line should always exit
         }
+        if (!opts.listen_path.empty()) {
+            if (!is_interactive_session()) {
+                FLOGF(warning, _(L"--listen only works in interactive sessions"));
+            } else if (!control_socket_listen(opts.listen_path)) {
+                return EXIT_FAILURE;
+            }
+        }
         res = reader_read(parser, STDIN_FILENO, {});
+        control_socket_remove();
     } else {
         const char *file = *(argv + (my_optind++));
         autoclose_fd_t fd(open_cloexec(file, O_RDONLY));
@@ -566,7 +732,7 @@ int main(int argc, char **argv) {
     restore_term_foreground_process_group_for_exit();
 
     if (!opts.profile_output.empty()) {
-        parser.emit_profiling(opts.profile_output.c_str());
+        parser.emit_profiling(opts.profile_output.c_str(), opts.profile_format);
     }
 
     history_save_all();
@@ -0,0 +1,878 @@
+// The formatter used by fish_indent.
+#include "config.h"  // IWYU pragma: keep
+
+#include "fish_indent_common.h"
+
+#include <fcntl.h>
+#include <unistd.h>
+
+#include <algorithm>
+#include <string>
+#include <type_traits>
+#include <vector>
+
+#include "ast.h"
+#include "common.h"
+#include "expand.h"
+#include "fallback.h"  // IWYU pragma: keep
+#include "fds.h"
+#include "parse_constants.h"
+#include "parse_util.h"
+#include "tokenizer.h"
+#include "wcstringutil.h"
+#include "wildcard.h"
+#include "wutil.h"  // IWYU pragma: keep
+
+namespace {
+/// From C++14.
+template <bool B, typename T = void>
+using enable_if_t = typename std::enable_if<B, T>::type;
+
+/// \return whether a character at a given index is escaped.
+/// A character is escaped if it has an odd number of backslashes.
+bool char_is_escaped(const wcstring &text, size_t idx) {
+    return count_preceding_backslashes(text, idx) % 2 == 1;
+}
+
+using namespace ast;
+struct pretty_printer_t {
+    // Note: this got somewhat more complicated after introducing the new AST, because that AST no
+    // longer encodes detailed lexical information (e.g. every newline). This feels more complex
+    // than necessary and would probably benefit from a more layered approach where we identify
+    // certain runs, weight line breaks, have a cost model, etc.
+    pretty_printer_t(const wcstring &src, const indent_options_t &opts)
+        : source(src),
+          opts(opts),
+          ast(ast_t::parse(src, parse_flags())),
+          indents(compute_indents()),
+          gaps(compute_gaps()),
+          preferred_semi_locations(compute_preferred_semi_locations()) {
+        assert(indents.size() == source.size() && "indents and source should be same length");
+    }
+
+    // Original source.
+    const wcstring &source;
+
+    // The formatting options.
+    const indent_options_t opts;
+
+    // The parsed ast.
+    const ast_t ast;
+
+    // The indents of our string.
+    // This has the same length as 'source' and describes the indentation level.
+    const std::vector<int> indents;
+
+    // The prettifier output.
+    wcstring output;
+
+    // The indent of the source range which we are currently emitting.
+    int current_indent{0};
+
+    // Whether to split the stages of the pipeline which we are currently emitting onto separate
+    // lines.
+    bool split_pipes{false};
+
+    // Whether the next gap text should hide the first newline.
+    bool gap_text_mask_newline{false};
+
+    // The "gaps": a sorted set of ranges between tokens.
+    // These contain whitespace, comments, semicolons, and other lexical elements which are not
+    // present in the ast.
+    const std::vector<source_range_t> gaps;
+
+    // The sorted set of source offsets of nl_semi_t which should be set as semis, not newlines.
+    // This is computed ahead of time for convenience.
+    const std::vector<uint32_t> preferred_semi_locations;
+
+    // Flags we support.
+    using gap_flags_t = uint32_t;
+    enum {
+        default_flags = 0,
+
+        // Whether to allow line splitting via escaped newlines.
+        // For example, in argument lists:
+        //
+        //   echo a \
+        //   b
+        //
+        // If this is not set, then split-lines will be joined.
+        allow_escaped_newlines = 1 << 0,
+
+        // Whether to require a space before this token.
+        // This is used when emitting semis:
+        //    echo a; echo b;
+        // No space required between 'a' and ';', or 'b' and ';'.
+        skip_space = 1 << 1,
+    };
+
+    // \return gap text flags for the gap text that comes *before* a given node type.
+    static gap_flags_t gap_text_flags_before_node(const node_t &node) {
+        gap_flags_t result = default_flags;
+        switch (node.type) {
+            // Allow escaped newlines in argument and redirection lists.
+            case type_t::argument:
+            case type_t::redirection:
+                result |= allow_escaped_newlines;
+                break;
+
+            case type_t::token_base:
+                // Allow escaped newlines before && and ||, and also pipes.
+                switch (node.as<token_base_t>()->type) {
+                    case parse_token_type_t::andand:
+                    case parse_token_type_t::oror:
+                    case parse_token_type_t::pipe:
+                        result |= allow_escaped_newlines;
+                        break;
+                    default:
+                        break;
+                }
+                break;
+
+            default:
+                break;
+        }
+        return result;
+    }
+
+    // \return whether we are at the start of a new line.
+    bool at_line_start() const { return output.empty() || output.back() == L'\n'; }
+
+    // \return whether we have a space before the output.
+    // This ignores escaped spaces and escaped newlines.
+    bool has_preceding_space() const {
+        long idx = static_cast<long>(output.size()) - 1;
+        // Skip escaped newlines.
+        // This is historical. Example:
+        //
+        // cmd1 \
+        // | cmd2
+        //
+        // we want the pipe to "see" the space after cmd1.
+        // TODO: this is too tricky, we should factor this better.
+        while (idx >= 0 && output.at(idx) == L'\n') {
+            size_t backslashes = count_preceding_backslashes(source, idx);
+            if (backslashes % 2 == 0) {
+                // Not escaped.
+                return false;
+            }
+            idx -= (1 + backslashes);
+        }
+        return idx >= 0 && output.at(idx) == L' ' && !char_is_escaped(output, idx);
+    }
+
+    // Entry point. Prettify our source code and return it.
+    wcstring prettify() {
+        output = wcstring{};
+        node_visitor(*this).accept(ast.top());
+
+        // Trailing gap text.
+        emit_gap_text_before(source_range_t{(uint32_t)source.size(), 0}, default_flags);
+
+        // Replace all trailing newlines with just a single one.
+        while (!output.empty() && at_line_start()) {
+            output.pop_back();
+        }
+        emit_newline();
+
+        wcstring result = std::move(output);
+        return result;
+    }
+
+    // \return a substring of source.
+    wcstring substr(source_range_t r) const { return source.substr(r.start, r.length); }
+
+    // Return the indents of our source.
+    std::vector<int> compute_indents() const {
+        if (!opts.do_indent) return std::vector<int>(source.size(), 0);
+        std::vector<int> result = parse_util_compute_indents(source);
+        if (!opts.indent_case) {
+            // Outdent the cases, so they line up with their switch.
+            for (const node_t &node : ast) {
+                if (node.type != type_t::case_item_list) continue;
+                auto range = node.try_source_range();
+                if (!range) continue;
+                for (uint32_t i = range->start; i < range->end() && i < result.size(); i++) {
+                    if (result.at(i) > 0) result.at(i) -= 1;
+                }
+            }
+        }
+        return result;
+    }
+
+    // Return the gap ranges from our ast.
+    std::vector<source_range_t> compute_gaps() const {
+        auto range_compare = [](source_range_t r1, source_range_t r2) {
+            if (r1.start != r2.start) return r1.start < r2.start;
+            return r1.length < r2.length;
+        };
+        // Collect the token ranges into a list.
+        std::vector<source_range_t> tok_ranges;
+        for (const node_t &node : ast) {
+            if (node.category == category_t::leaf) {
+                auto r = node.source_range();
+                if (r.length > 0) tok_ranges.push_back(r);
+            }
+        }
+        // Place a zero length range at end to aid in our inverting.
+        tok_ranges.push_back(source_range_t{(uint32_t)source.size(), 0});
+
+        // Our tokens should be sorted.
+        assert(std::is_sorted(tok_ranges.begin(), tok_ranges.end(), range_compare));
+
+        // For each range, add a gap range between the previous range and this range.
+        std::vector<source_range_t> gaps;
+        uint32_t prev_end = 0;
+        for (source_range_t tok_range : tok_ranges) {
+            assert(tok_range.start >= prev_end &&
+                   "Token range should not overlap or be out of order");
+            if (tok_range.start >= prev_end) {
+                gaps.push_back(source_range_t{prev_end, tok_range.start - prev_end});
+            }
+            prev_end = tok_range.start + tok_range.length;
+        }
+        return gaps;
+    }
+
+    // Return sorted list of semi-preferring semi_nl nodes.
+    std::vector<uint32_t> compute_preferred_semi_locations() const {
+        std::vector<uint32_t> result;
+        auto mark_semi_from_input = [&](const optional_t<semi_nl_t> &n) {
+            if (n && n->has_source() && substr(n->range) == L";") {
+                result.push_back(n->range.start);
+            }
+        };
+
+        // andor_job_lists get semis if the input uses semis.
+        for (const auto &node : ast) {
+            // See if we have a condition and an andor_job_list.
+            const optional_t<semi_nl_t> *condition = nullptr;
+            const andor_job_list_t *andors = nullptr;
+            if (const auto *ifc = node.try_as<if_clause_t>()) {
+                condition = &ifc->condition.semi_nl;
+                andors = &ifc->andor_tail;
+            } else if (const auto *wc = node.try_as<while_header_t>()) {
+                condition = &wc->condition.semi_nl;
+                andors = &wc->andor_tail;
+            }
+
+            // If there is no and-or tail then we always use a newline.
+            if (andors && andors->count() > 0) {
+                if (condition) mark_semi_from_input(*condition);
+                // Mark all but last of the andor list.
+                for (uint32_t i = 0; i + 1 < andors->count(); i++) {
+                    mark_semi_from_input(andors->at(i)->job.semi_nl);
+                }
+            }
+        }
+
+        // `x ; and y` gets semis if it has them already, and they are on the same line.
+        for (const auto &node : ast) {
+            if (const auto *job_list = node.try_as<job_list_t>()) {
+                const semi_nl_t *prev_job_semi_nl = nullptr;
+                for (const job_conjunction_t &job : *job_list) {
+                    // Set up prev_job_semi_nl for the next iteration to make control flow easier.
+                    const semi_nl_t *prev = prev_job_semi_nl;
+                    prev_job_semi_nl = job.semi_nl.contents.get();
+
+                    // Is this an 'and' or 'or' job?
+                    if (!job.decorator) continue;
+
+                    // Now see if we want to mark 'prev' as allowing a semi.
+                    // Did we have a previous semi_nl which was a newline?
+                    if (!prev || substr(prev->range) != L";") continue;
+
+                    // Is there a newline between them?
+                    assert(prev->range.start <= job.decorator->range.start &&
+                           "Ranges out of order");
+                    auto start = source.begin() + prev->range.start;
+                    auto end = source.begin() + job.decorator->range.end();
+                    if (std::find(start, end, L'\n') == end) {
+                        // We're going to allow the previous semi_nl to be a semi.
+                        result.push_back(prev->range.start);
+                    }
+                }
+            }
+        }
+        std::sort(result.begin(), result.end());
+        return result;
+    }
+
+    // Emit a space or indent as necessary, depending on the previous output.
+    void emit_space_or_indent(gap_flags_t flags = default_flags) {
+        if (at_line_start()) {
+            output.append(opts.indent_width * current_indent, L' ');
+        } else if (!(flags & skip_space) && !has_preceding_space()) {
+            output.append(1, L' ');
+        }
+    }
+
+    // Emit "gap text:" newlines and comments from the original source.
+    // Gap text may be a few things:
+    //
+    // 1. Just a space is common. We will trim the spaces to be empty.
+    //
+    // Here the gap text is the comment, followed by the newline:
+    //
+    //    echo abc # arg
+    //    echo def
+    //
+    // 2. It may also be an escaped newline:
+    // Here the gap text is a space, backslash, newline, space.
+    //
+    //     echo \
+    //       hi
+    //
+    // 3. Lastly it may be an error, if there was an error token. Here the gap text is the pipe:
+    //
+    //   begin | stuff
+    //
+    //  We do not handle errors here - instead our caller does.
+    bool emit_gap_text(const wcstring &gap_text, gap_flags_t flags) {
+        // Common case: if we are only spaces, do nothing.
+        if (gap_text.find_first_not_of(L' ') == wcstring::npos) return false;
+
+        // Look to see if there is an escaped newline.
+        // Emit it if either we allow it, or it comes before the first comment.
+        // Note we do not have to be concerned with escaped backslashes or escaped #s. This is gap
+        // text - we already know it has no semantic significance.
+        size_t escaped_nl = gap_text.find(L"\\\n");
+        bool have_line_continuation = false;
+        if (escaped_nl != wcstring::npos) {
+            size_t comment_idx = gap_text.find(L'#');
+            if ((flags & allow_escaped_newlines) ||
+                (comment_idx != wcstring::npos && escaped_nl < comment_idx)) {
+                // Emit a space before the escaped newline.
+                if (!at_line_start() && !has_preceding_space()) {
+                    output.append(L" ");
+                }
+                output.append(L"\\\n");
+                // Indent the line continuation and any comment before it (#7252).
+                have_line_continuation = true;
+                current_indent += 1;
+                emit_space_or_indent();
+            }
+        }
+
+        // It seems somewhat ambiguous whether we always get a newline after a comment. Ensure we
+        // always emit one.
+        bool needs_nl = false;
+
+        tokenizer_t tokenizer(gap_text.c_str(), TOK_SHOW_COMMENTS | TOK_SHOW_BLANK_LINES);
+        while (maybe_t<tok_t> tok = tokenizer.next()) {
+            wcstring tok_text = tokenizer.text_of(*tok);
+
+            if (needs_nl) {
+                emit_newline();
+                needs_nl = false;
+                if (tok_text == L"\n") continue;
+            } else if (gap_text_mask_newline) {
+                // We only respect mask_newline the first time through the loop.
+                gap_text_mask_newline = false;
+                if (tok_text == L"\n") continue;
+            }
+
+            if (tok->type == token_type_t::comment) {
+                emit_space_or_indent();
+                output.append(tok_text);
+                needs_nl = true;
+            } else if (tok->type == token_type_t::end) {
+                // This may be either a newline or semicolon.
+                // Semicolons found here are not part of the ast and can simply be removed.
+                // Newlines are preserved unless mask_newline is set.
+                if (tok_text == L"\n") {
+                    emit_newline();
+                }
+            } else {
+                fprintf(stderr,
+                        "Gap text should only have comments and newlines - instead found token "
+                        "type %d with text: %ls\n",
+                        (int)tok->type, tok_text.c_str());
+                DIE("Gap text should only have comments and newlines");
+            }
+        }
+        if (needs_nl) emit_newline();
+        if (have_line_continuation) {
+            emit_space_or_indent();
+            current_indent -= 1;
+        }
+        return needs_nl;
+    }
+
+    /// \return the gap text ending at a given index into the string, or empty if none.
+    source_range_t gap_text_to(uint32_t end) const {
+        auto where = std::lower_bound(
+            gaps.begin(), gaps.end(), end,
+            [](source_range_t r, uint32_t end) { return r.start + r.length < end; });
+        if (where == gaps.end() || where->start + where->length != end) {
+            // Not found.
+            return source_range_t{0, 0};
+        } else {
+            return *where;
+        }
+    }
+
+    /// \return whether a range \p r overlaps an error range from our ast.
+    bool range_contained_error(source_range_t r) const {
+        const auto &errs = ast.extras().errors;
+        auto range_is_before = [](source_range_t x, source_range_t y) {
+            return x.start + x.length <= y.start;
+        };
+        assert(std::is_sorted(errs.begin(), errs.end(), range_is_before) &&
+               "Error ranges should be sorted");
+        return std::binary_search(errs.begin(), errs.end(), r, range_is_before);
+    }
+
+    // Emit the gap text before a source range.
+    bool emit_gap_text_before(source_range_t r, gap_flags_t flags) {
+        assert(r.start <= source.size() && "source out of bounds");
+        bool added_newline = false;
+
+        // Find the gap text which ends at start.
+        source_range_t range = gap_text_to(r.start);
+        if (range.length > 0) {
+            // Set the indent from the beginning of this gap text.
+            // For example:
+            // begin
+            //    cmd
+            //    # comment
+            // end
+            // Here the comment is the gap text before the end, but we want the indent from the
+            // command.
+            if (range.start < indents.size()) current_indent = indents.at(range.start);
+
+            // If this range contained an error, append the gap text without modification.
+            // For example in: echo foo "
+            // We don't want to mess with the quote.
+            if (range_contained_error(range)) {
+                output.append(substr(range));
+            } else {
+                added_newline = emit_gap_text(substr(range), flags);
+            }
+        }
+        // Always clear gap_text_mask_newline after emitting even empty gap text.
+        gap_text_mask_newline = false;
+        return added_newline;
+    }
+
+    /// Given a string \p input, remove unnecessary quotes, etc.
+    wcstring clean_text(const wcstring &input) {
+        // Unescape the string - this leaves special markers around if there are any
+        // expansions or anything. We specifically tell it to not compute backslash-escapes
+        // like \U or \x, because we want to leave them intact.
+        wcstring unescaped = input;
+        unescape_string_in_place(&unescaped, UNESCAPE_SPECIAL | UNESCAPE_NO_BACKSLASHES);
+
+        // Remove INTERNAL_SEPARATOR because that's a quote.
+        auto quote = [](wchar_t ch) { return ch == INTERNAL_SEPARATOR; };
+        unescaped.erase(std::remove_if(unescaped.begin(), unescaped.end(), quote), unescaped.end());
+
+        // If no non-"good" char is left, use the unescaped version.
+        // This can be extended to other characters, but giving the precise list is tough,
+        // can change over time (see "^", "%" and "?", in some cases "{}") and it just makes
+        // people feel more at ease.
+        auto goodchars = [](wchar_t ch) {
+            return fish_iswalnum(ch) || ch == L'_' || ch == L'-' || ch == L'/';
+        };
+        if (std::find_if_not(unescaped.begin(), unescaped.end(), goodchars) == unescaped.end() &&
+            !unescaped.empty()) {
+            return unescaped;
+        } else {
+            return input;
+        }
+    }
+
+    // Emit a range of original text. This indents as needed, and also inserts preceding gap text.
+    // If \p tolerate_line_splitting is set, then permit escaped newlines; otherwise collapse such
+    // lines.
+    void emit_text(source_range_t r, gap_flags_t flags) {
+        emit_gap_text_before(r, flags);
+        current_indent = indents.at(r.start);
+        if (r.length > 0) {
+            emit_space_or_indent(flags);
+            output.append(clean_text(substr(r)));
+        }
+    }
+
+    template <type_t Type>
+    void emit_node_text(const leaf_t<Type> &node) {
+        emit_text(node.range, gap_text_flags_before_node(node));
+    }
+
+    // Emit one newline.
+    void emit_newline() { output.push_back(L'\n'); }
+
+    // Emit a semicolon.
+    void emit_semi() { output.push_back(L';'); }
+
+    // For branch and list nodes, default is to visit their children.
+    template <typename Node>
+    enable_if_t<Node::Category == category_t::branch> visit(const Node &node) {
+        node_visitor(*this).accept_children_of(node);
+    }
+
+    template <typename Node>
+    enable_if_t<Node::Category == ast::category_t::list> visit(const Node &node) {
+        node_visitor(*this).accept_children_of(node);
+    }
+
+    // Leaf nodes we just visit their text.
+    void visit(const keyword_base_t &node) { emit_node_text(node); }
+    void visit(const token_base_t &node) { emit_node_text(node); }
+    void visit(const argument_t &node) { emit_node_text(node); }
+    void visit(const variable_assignment_t &node) { emit_node_text(node); }
+
+    void visit(const semi_nl_t &node) {
+        // These are semicolons or newlines which are part of the ast. That means it includes e.g.
+        // ones terminating a job or 'if' header, but not random semis in job lists. We respect
+        // preferred_semi_locations to decide whether or not these should stay as newlines or
+        // become semicolons.
+
+        // Check if we should prefer a semicolon.
+        bool prefer_semi = node.range.length > 0 &&
+                           std::binary_search(preferred_semi_locations.begin(),
+                                              preferred_semi_locations.end(), node.range.start);
+        emit_gap_text_before(node.range, gap_text_flags_before_node(node));
+
+        // Don't emit anything if the gap text put us on a newline (because it had a comment).
+        if (!at_line_start()) {
+            prefer_semi ? emit_semi() : emit_newline();
+
+            // If it was a semi but we emitted a newline, swallow a subsequent newline.
+            if (!prefer_semi && substr(node.range) == L";") {
+                gap_text_mask_newline = true;
+            }
+        }
+
+        // A newline ending the line of a here-document is followed by its body, which is
+        // literal text.
+        if (node.range.length > 1) {
+            output.append(source, node.range.start + 1, node.range.length - 1);
+        }
+    }
+
+    void visit(const redirection_t &node) {
+        // No space between a redirection operator and its target (#2899).
+        emit_text(node.oper.range, default_flags);
+        emit_text(node.target.range, skip_space);
+    }
+
+    void visit(const maybe_newlines_t &node) {
+        // Our newlines may have comments embedded in them, example:
+        //    cmd |
+        //    # something
+        //    cmd2
+        // Treat it as gap text.
+        if (node.range.length > 0) {
+            auto flags = gap_text_flags_before_node(node);
+            current_indent = indents.at(node.range.start);
+            bool added_newline = emit_gap_text_before(node.range, flags);
+            wcstring text = source.substr(node.range.start, node.range.length);
+            if (added_newline && !text.empty() && text.front() == L'\n') {
+                text = text.substr(strlen("\n"));
+            }
+            emit_gap_text(text, flags);
+        }
+    }
+
+    // \return whether the pipeline \p job should be split because it is too long for one line.
+    bool should_split_pipeline(const job_t &job) const {
+        if (opts.max_line_length == 0 || job.continuation.count() == 0) return false;
+        auto range = job.try_source_range();
+        if (!range) return false;
+        // Leave pipelines alone which are already on multiple lines.
+        wcstring text = substr(*range);
+        if (text.find(L'\n') != wcstring::npos) return false;
+
+        size_t column;
+        if (at_line_start()) {
+            column = opts.indent_width * indents.at(range->start);
+        } else {
+            size_t line_start = output.rfind(L'\n');
+            column = output.size() - (line_start == wcstring::npos ? 0 : line_start + 1) + 1;
+        }
+        return column + text.size() > opts.max_line_length;
+    }
+
+    void visit(const job_t &node) {
+        bool saved_split_pipes = split_pipes;
+        split_pipes = should_split_pipeline(node);
+        node_visitor(*this).accept_children_of(node);
+        split_pipes = saved_split_pipes;
+    }
+
+    void visit(const job_continuation_t &node) {
+        // Put each stage of a long pipeline on its own line, unless it already is.
+        if (split_pipes && !at_line_start() &&
+            substr(gap_text_to(node.pipe.range.start)).find(L"\\\n") == wcstring::npos) {
+            if (!has_preceding_space()) output.push_back(L' ');
+            output.append(L"\\\n");
+            output.append(opts.indent_width * (indents.at(node.pipe.range.start) + 1), L' ');
+        }
+        node_visitor(*this).accept_children_of(node);
+    }
+
+    void visit(const begin_header_t &node) {
+        // 'begin' does not require a newline after it, but we insert one.
+        node_visitor(*this).accept_children_of(node);
+        if (!at_line_start()) {
+            emit_newline();
+        }
+    }
+
+    // The flags we use to parse.
+    static parse_tree_flags_t parse_flags() {
+        return parse_flag_continue_after_error | parse_flag_include_comments |
+               parse_flag_leave_unterminated | parse_flag_show_blank_lines;
+    }
+};
+}  // namespace
+
+wcstring prettify(const wcstring &src, const indent_options_t &opts) {
+    pretty_printer_t printer{src, opts};
+    return printer.prettify();
+}
+
+/// Expand the braces in the EditorConfig glob \p glob, like "*.{fish,sh}", appending the results to
+/// \p out.
+static void expand_editorconfig_braces(const wcstring &glob, wcstring_list_t *out) {
+    size_t open = glob.find(L'{');
+    size_t close = open == wcstring::npos ? wcstring::npos : glob.find(L'}', open);
+    if (close == wcstring::npos) {
+        out->push_back(glob);
+        return;
+    }
+    wcstring prefix = glob.substr(0, open);
+    wcstring suffix = glob.substr(close + 1);
+    for (const wcstring &alt : split_string(glob.substr(open + 1, close - open - 1), L',')) {
+        expand_editorconfig_braces(prefix + alt + suffix, out);
+    }
+}
+
+/// \return whether the EditorConfig section glob \p glob matches \p path, which is relative to the
+/// directory of the .editorconfig file.
+static bool editorconfig_glob_matches(const wcstring &glob, const wcstring &path) {
+    wcstring_list_t alternatives;
+    expand_editorconfig_braces(glob, &alternatives);
+    for (wcstring alt : alternatives) {
+        // A glob without a slash may match a file in any directory.
+        const wcstring *subject = &path;
+        wcstring basename;
+        if (alt.find(L'/') == wcstring::npos) {
+            basename = wbasename(path);
+            subject = &basename;
+        } else if (string_prefixes_string(L"/", alt)) {
+            alt.erase(0, 1);
+        }
+        wcstring wc;
+        for (wchar_t c : alt) {
+            if (c == L'*') {
+                if (wc.empty() || wc.back() != ANY_STRING) wc.push_back(ANY_STRING);
+            } else if (c == L'?') {
+                wc.push_back(ANY_CHAR);
+            } else {
+                wc.push_back(c);
+            }
+        }
+        if (wildcard_match(*subject, wc)) return true;
+    }
+    return false;
+}
+
+/// The parsed contents of an .editorconfig file.
+struct editorconfig_t {
+    /// The directory containing the file.
+    wcstring dir;
+    /// Whether this is the root file, so files in parent directories are not read.
+    bool root{false};
+    /// The sections with their glob and their properties as key and value.
+    std::vector<std::pair<wcstring, std::vector<std::pair<wcstring, wcstring>>>> sections;
+};
+
+/// Read the .editorconfig file in \p dir, if any.
+static maybe_t<editorconfig_t> read_editorconfig(const wcstring &dir) {
+    autoclose_fd_t fd{wopen_cloexec(dir + L"/.editorconfig", O_RDONLY)};
+    if (!fd.valid()) return none();
+    std::string contents;
+    char buff[4096];
+    ssize_t amt;
+    while ((amt = read_loop(fd.fd(), buff, sizeof buff)) > 0) {
+        contents.append(buff, amt);
+    }
+
+    editorconfig_t result;
+    result.dir = dir;
+    for (wcstring line : split_string(str2wcstring(contents), L'\n')) {
+        line = trim(line);
+        if (line.empty() || line.front() == L'#' || line.front() == L';') continue;
+        if (line.front() == L'[' && line.back() == L']') {
+            result.sections.emplace_back(line.substr(1, line.size() - 2),
+                                         std::vector<std::pair<wcstring, wcstring>>{});
+            continue;
+        }
+        size_t eq = line.find(L'=');
+        if (eq == wcstring::npos) continue;
+        wcstring key = wcstolower(trim(line.substr(0, eq)));
+        wcstring value = wcstolower(trim(line.substr(eq + 1)));
+        if (result.sections.empty()) {
+            // Properties before the first section are only allowed to be "root".
+            if (key == L"root") result.root = value == L"true";
+        } else {
+            result.sections.back().second.emplace_back(std::move(key), std::move(value));
+        }
+    }
+    return result;
+}
+
+void indent_options_apply_editorconfig(const wcstring &path, indent_options_t *opts) {
+    wcstring abspath = path;
+    if (!string_prefixes_string(L"/", abspath)) {
+        auto cwd = wgetcwd();
+        if (cwd.empty()) return;
+        abspath = cwd + L"/" + abspath;
+    }
+
+    // Collect the files from the nearest one up to the root.
+    std::vector<editorconfig_t> configs;
+    wcstring dir = wdirname(abspath);
+    for (;;) {
+        if (auto config = read_editorconfig(dir)) {
+            bool root = config->root;
+            configs.push_back(config.acquire());
+            if (root) break;
+        }
+        if (dir == L"/") break;
+        dir = wdirname(dir);
+    }
+
+    // Apply them from the root down, so the nearest file takes precedence.
+    for (auto config = configs.rbegin(); config != configs.rend(); ++config) {
+        wcstring relpath = abspath.substr(config->dir.size());
+        if (string_prefixes_string(L"/", relpath)) relpath.erase(0, 1);
+        for (const auto &section : config->sections) {
+            if (!editorconfig_glob_matches(section.first, relpath)) continue;
+            for (const auto &prop : section.second) {
+                const wcstring &key = prop.first;
+                const wcstring &value = prop.second;
+                if (key == L"indent_size") {
+                    int width = fish_wcstoi(value.c_str());
+                    if (!errno && width > 0) opts->indent_width = width;
+                } else if (key == L"max_line_length") {
+                    if (value == L"off") {
+                        opts->max_line_length = 0;
+                    } else {
+                        long length = fish_wcstol(value.c_str());
+                        if (!errno && length > 0) opts->max_line_length = length;
+                    }
+                } else if (key == L"fish_case_style") {
+                    if (value == L"flat") {
+                        opts->indent_case = false;
+                    } else if (value == L"indented") {
+                        opts->indent_case = true;
+                    }
+                }
+            }
+        }
+    }
+}
+
+/// \return the class of the HTML span elements for text with the color \p spec.
+static const wchar_t *html_class_name_for_color(highlight_spec_t spec) {
+#define P(x) L"fish_color_" #x
+    switch (spec.foreground) {
+        case highlight_role_t::normal: {
+            return P(normal);
+        }
+        case highlight_role_t::error: {
+            return P(error);
+        }
+        case highlight_role_t::command: {
+            return P(command);
+        }
+        case highlight_role_t::statement_terminator: {
+            return P(statement_terminator);
+        }
+        case highlight_role_t::param: {
+            return P(param);
+        }
+        case highlight_role_t::comment: {
+            return P(comment);
+        }
+        case highlight_role_t::search_match: {
+            return P(search_match);
+        }
+        case highlight_role_t::operat: {
+            return P(operator);
+        }
+        case highlight_role_t::escape: {
+            return P(escape);
+        }
+        case highlight_role_t::quote: {
+            return P(quote);
+        }
+        case highlight_role_t::redirection: {
+            return P(redirection);
+        }
+        case highlight_role_t::autosuggestion: {
+            return P(autosuggestion);
+        }
+        case highlight_role_t::selection: {
+            return P(selection);
+        }
+        default: {
+            return P(other);
+        }
+    }
+}
+
+wcstring html_colorize(const wcstring &text, const std::vector<highlight_spec_t> &colors) {
+    if (text.empty()) {
+        return wcstring();
+    }
+
+    assert(colors.size() == text.size());
+    wcstring html = L"<pre><code>";
+    highlight_spec_t last_color = highlight_role_t::normal;
+    for (size_t i = 0; i < text.size(); i++) {
+        // Handle colors.
+        highlight_spec_t color = colors.at(i);
+        if (i > 0 && color != last_color) {
+            html.append(L"</span>");
+        }
+        if (i == 0 || color != last_color) {
+            append_format(html, L"<span class=\"%ls\">", html_class_name_for_color(color));
+        }
+        last_color = color;
+
+        // Handle text.
+        wchar_t wc = text.at(i);
+        switch (wc) {
+            case L'&': {
+                html.append(L"&amp;");
+                break;
+            }
+            case L'\'': {
+                html.append(L"&apos;");
+                break;
+            }
+            case L'"': {
+                html.append(L"&quot;");
+                break;
+            }
+            case L'<': {
+                html.append(L"&lt;");
+                break;
+            }
+            case L'>': {
+                html.append(L"&gt;");
+                break;
+            }
+            default: {
+                html.push_back(wc);
+                break;
+            }
+        }
+    }
+    html.append(L"</span></code></pre>");
+    return html;
+}
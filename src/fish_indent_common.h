@@ -0,0 +1,42 @@
+// The formatter used by fish_indent.
+#ifndef FISH_INDENT_COMMON_H
+#define FISH_INDENT_COMMON_H
+
+#include <stddef.h>
+
+#include <vector>
+
+#include "common.h"
+#include "highlight.h"
+
+/// Options controlling how fish code is formatted.
+struct indent_options_t {
+    /// Whether to indent, or only reformat into one job per line.
+    bool do_indent{true};
+
+    /// The number of spaces per indentation level.
+    int indent_width{4};
+
+    /// If nonzero, pipelines which would be longer than this many characters are split onto
+    /// multiple lines, one stage per line.
+    size_t max_line_length{0};
+
+    /// Whether 'case' lines are indented relative to their 'switch', as in the "indented" case
+    /// style, or at the same level, as in the "flat" case style.
+    bool indent_case{true};
+};
+
+/// \return \p src formatted according to \p opts.
+wcstring prettify(const wcstring &src, const indent_options_t &opts);
+
+/// Apply the settings from the .editorconfig files which affect the file at \p path to \p opts.
+/// Files are searched for from the directory of \p path upwards until one has "root = true", and
+/// closer files take precedence. The recognized properties are indent_size, max_line_length and
+/// fish_case_style.
+void indent_options_apply_editorconfig(const wcstring &path, indent_options_t *opts);
+
+/// \return \p text as HTML, with span elements whose classes name the color variables of
+/// \p colors, which has a color for each character.
+wcstring html_colorize(const wcstring &text, const std::vector<highlight_spec_t> &colors);
+
+#endif
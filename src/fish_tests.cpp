@@ -19,6 +19,7 @@
 #include <sys/stat.h>
 #include <sys/time.h>
 #include <sys/types.h>
+#include <sys/uio.h>
 #include <sys/utsname.h>
 #include <sys/wait.h>
 #include <time.h>
@@ -52,17 +53,24 @@
 #include "expand.h"
 #include "fallback.h"  // IWYU pragma: keep
 #include "fd_monitor.h"
+#include "flog.h"
+#include "frecency.h"
 #include "function.h"
 #include "future_feature_flags.h"
+#include "git_status.h"
 #include "highlight.h"
 #include "history.h"
 #include "input.h"
 #include "input_common.h"
+#include "intern.h"
 #include "io.h"
 #include "iothread.h"
+#include "kill.h"
+#include "json.h"
 #include "lru.h"
 #include "maybe.h"
 #include "operation_context.h"
+#include "output.h"
 #include "pager.h"
 #include "parse_constants.h"
 #include "parse_tree.h"
@@ -72,7 +80,9 @@
 #include "proc.h"
 #include "reader.h"
 #include "redirection.h"
+#include "regex_cache.h"
 #include "screen.h"
+#include "sha256.h"
 #include "signal.h"
 #include "termsize.h"
 #include "timer.h"
@@ -482,6 +492,14 @@ static void test_format() {
     format_long_safe(buff1, q);
     sprintf(buff2, "%ld", q);
     do_test(!std::strcmp(buff1, buff2));
+
+    // Translations may reorder the arguments.
+    do_test(format_string(L"%2$ls %1$lu", 3UL, L"jobs") == L"jobs 3");
+
+    // Without a translation, the English form for the number is used.
+    do_test(wngettext(L"%lu job", L"%lu jobs", 0) == L"%lu jobs");
+    do_test(wngettext(L"%lu job", L"%lu jobs", 1) == L"%lu job");
+    do_test(wngettext(L"%lu job", L"%lu jobs", 2) == L"%lu jobs");
 }
 
 /// Helper to convert a narrow string to a sequence of hex digits.
@@ -618,6 +636,49 @@ static void test_tokenizer() {
         do_test(!token.has_value());
     }
 
+    {
+        // A here-document body is part of the newline which ends its line, through the
+        // terminating line.
+        const wchar_t *str = L"cat <<END | grep x\nbody\n  END\necho";
+        tokenizer_t t(str, 0);
+        maybe_t<tok_t> token{};
+        token = t.next();  // cat
+        token = t.next();  // <<
+        do_test(token->type == token_type_t::redirect);
+        token = t.next();  // END
+        do_test(token->type == token_type_t::string);
+        do_test(t.text_of(*token) == L"END");
+        token = t.next();  // |
+        do_test(token->type == token_type_t::pipe);
+        token = t.next();  // grep
+        token = t.next();  // x
+        token = t.next();  // \nbody\n  END
+        do_test(token->type == token_type_t::end);
+        do_test(t.text_of(*token) == L"\nbody\n  END");
+        token = t.next();  // echo
+        do_test(t.text_of(*token) == L"echo");
+
+        do_test(tok_heredoc_source(str, 4) == L"\nbody\n  END");
+        do_test(tok_heredoc_source(L"cat <<END\nbody", 4).empty());
+    }
+
+    {
+        // A process substitution is a string, but a redirection followed by a command
+        // substitution is not.
+        const wchar_t *str = L"cat <(echo foo) < (echo bar)";
+        tokenizer_t t(str, 0);
+        maybe_t<tok_t> token{};
+        token = t.next();  // cat
+        token = t.next();  // <(echo foo)
+        do_test(token->type == token_type_t::string);
+        do_test(t.text_of(*token) == L"<(echo foo)");
+        token = t.next();  // <
+        do_test(token->type == token_type_t::redirect);
+        token = t.next();  // (echo bar)
+        do_test(token->type == token_type_t::string);
+        do_test(t.text_of(*token) == L"(echo bar)");
+    }
+
     const wchar_t *str =
         L"string <redirection  2>&1 'nested \"quoted\" '(string containing subshells "
         L"){and,brackets}$as[$well (as variable arrays)] not_a_redirect^ ^ ^^is_a_redirect "
@@ -726,6 +787,22 @@ static void test_tokenizer() {
     do_test(pipe_or_redir(L"&>>")->stderr_merge);
     do_test(pipe_or_redir(L"&>?")->stderr_merge);
 
+    do_test(pipe_or_redir(L"{fd}>")->fd_var == L"fd");
+    do_test(pipe_or_redir(L"{fd}>&-")->mode == redirection_mode_t::fd);
+    do_test(pipe_or_redir(L"{my_fd}<")->mode == redirection_mode_t::input);
+    do_test(pipe_or_redir(L">")->fd_var.empty());
+    do_test(!pipe_or_redir(L"{fd}>|"));
+    do_test(!pipe_or_redir(L"{}>"));
+    do_test(!pipe_or_redir(L"{a,b}>"));
+    do_test(!pipe_or_redir(L"{fd}"));
+    do_test(!pipe_or_redir(L"{fd}<<<"));
+
+    do_test(pipe_or_redir(L"<<<")->mode == redirection_mode_t::here_string);
+    do_test(pipe_or_redir(L"<<<")->fd == STDIN_FILENO);
+    do_test(pipe_or_redir(L"3<<<")->fd == 3);
+    do_test(pipe_or_redir(L"<<")->mode == redirection_mode_t::here_doc);
+    do_test(pipe_or_redir(L"<<")->consumed == 2);
+
     auto get_redir_mode = [](const wchar_t *s) -> maybe_t<redirection_mode_t> {
         if (auto redir = pipe_or_redir_t::from_string(s)) {
             return redir->mode;
@@ -990,6 +1067,52 @@ static void test_debounce() {
     do_test(total_ran <= 2);
 }
 
+static void test_task_scope() {
+    say(L"Testing task scopes");
+    task_scope_t scope;
+    std::mutex m;
+    std::condition_variable cv;
+    bool ready_to_go = false;
+    std::atomic<int> handlers_ran{0};
+    std::atomic<int> saw_cancel{0};
+    std::array<bool, 3> completion_ran = {};
+
+    // Spawn two tasks which wait until we are ready, and then cancel them.
+    for (size_t idx = 0; idx < 2; idx++) {
+        auto cancelled = scope.cancel_checker();
+        scope.spawn(
+            [&, idx, cancelled] {
+                std::unique_lock<std::mutex> lock(m);
+                cv.wait(lock, [&] { return ready_to_go; });
+                handlers_ran++;
+                if (cancelled()) saw_cancel++;
+                return idx;
+            },
+            [&](size_t idx) { completion_ran[idx] = true; });
+    }
+    do_test(!scope.cancel_checker()());
+    scope.cancel_all();
+
+    // A task spawned after cancelling is not affected.
+    scope.spawn([] { return size_t(2); }, [&](size_t idx) { completion_ran[idx] = true; });
+    {
+        std::unique_lock<std::mutex> lock(m);
+        ready_to_go = true;
+    }
+    cv.notify_all();
+    while (!completion_ran[2]) {
+        iothread_service_completion();
+    }
+    iothread_drain_all();
+    iothread_service_completion_with_timeout(0);
+
+    // A cancelled task may have been skipped, or it ran and saw that it is cancelled. Its
+    // completion never runs.
+    do_test(handlers_ran == saw_cancel);
+    do_test(!completion_ran[0]);
+    do_test(!completion_ran[1]);
+}
+
 static void test_debounce_timeout() {
     using namespace std::chrono;
     say(L"Testing debounce timeout");
@@ -1145,6 +1268,18 @@ static void test_parser() {
         err(L"unterminated multiline subshell not reported properly");
     }
 
+    if (detect_errors(L"cat <<END\nfoo\n") != PARSER_TEST_INCOMPLETE) {
+        err(L"unterminated here-document not reported properly");
+    }
+
+    if (detect_errors(L"cat <<END\nfoo\nEND\n")) {
+        err(L"terminated here-document wrongly reported as error");
+    }
+
+    if (detect_errors(L"cat <<END | grep foo > out\nfoo\nEND\n")) {
+        err(L"here-document followed by a pipe wrongly reported as error");
+    }
+
     if (detect_errors(L"begin ; true ; end | ") != PARSER_TEST_INCOMPLETE) {
         err(L"unterminated pipe not reported properly");
     }
@@ -1337,6 +1472,69 @@ static void test_cancellation() {
     signal_clear_cancel();
 }
 
+static void test_signal_wakeup() {
+    say(L"Testing signal wakeup fd");
+    signal_set_handlers(true);
+    int fd = signal_get_wakeup_fd();
+    do_test(fd >= 0);
+
+    auto is_readable = [fd] {
+        fd_set fds;
+        FD_ZERO(&fds);
+        FD_SET(fd, &fds);
+        struct timeval tv = {0, 0};
+        return select(fd + 1, &fds, nullptr, nullptr, &tv) > 0;
+    };
+    signal_clear_wakeup();
+    do_test(!is_readable());
+
+    // Any signal with a handler wakes up the main loop, once until it is cleared.
+    raise(SIGALRM);
+    raise(SIGALRM);
+    do_test(is_readable());
+    signal_clear_wakeup();
+    do_test(!is_readable());
+
+    signal_reset_handlers();
+}
+
+static void test_stalled_output() {
+    say(L"Testing output to a stalled fd");
+    signal_set_handlers(true);
+    auto pipes = make_autoclose_pipes().acquire();
+    int saved_stdout = dup(STDOUT_FILENO);
+    dup2(pipes.write.fd(), STDOUT_FILENO);
+
+    // Fill the pipe, so writes would block.
+    make_fd_nonblocking(STDOUT_FILENO);
+    char buf[4096] = {};
+    size_t filled = 0;
+    ssize_t amt;
+    while ((amt = write(STDOUT_FILENO, buf, sizeof buf)) > 0) filled += amt;
+    make_fd_blocking(STDOUT_FILENO);
+
+    // With a signal pending, the output is kept until the pipe has room.
+    outputter_t &outp = outputter_t::stdoutput();
+    raise(SIGALRM);
+    outp.writestr("hello");
+    do_test(outp.has_pending_output());
+    outp.flush_pending_output();
+    do_test(outp.has_pending_output());
+
+    make_fd_nonblocking(pipes.read.fd());
+    while (filled > 0 && (amt = read(pipes.read.fd(), buf, std::min(filled, sizeof buf))) > 0) {
+        filled -= amt;
+    }
+    outp.flush_pending_output();
+    do_test(!outp.has_pending_output());
+    do_test(read(pipes.read.fd(), buf, sizeof buf) == 5 && !std::memcmp(buf, "hello", 5));
+
+    dup2(saved_stdout, STDOUT_FILENO);
+    close(saved_stdout);
+    signal_clear_wakeup();
+    signal_reset_handlers();
+}
+
 namespace indent_tests {
 // A struct which is either text or a new indent.
 struct segment_t {
@@ -1667,6 +1865,30 @@ static void test_wchar2utf8(const wchar_t *src, size_t slen, const unsigned char
     return test_wchar2utf8(src, slen, dst, dlen, flags, res, descr);
 }
 
+static void test_wcwidth() {
+    say(L"Testing character widths");
+    scoped_push<int> ambiguous(&g_fish_ambiguous_width, 1);
+    scoped_push<int> emoji(&g_fish_emoji_width, 2);
+    do_test(fish_wcwidth(L'a') == 1);
+    do_test(fish_wcwidth(L'\u4E00') == 2);      // CJK ideograph
+    do_test(fish_wcwidth(L'\u00A1') == 1);      // ambiguous
+    do_test(fish_wcwidth(L'\U0001F600') == 2);  // widened in Unicode 9
+    do_test(fish_wcwidth(L'\uFE0F') == 1);      // VS16
+    do_test(fish_wcwidth(L'\uFE0E') == 0);      // VS15
+    do_test(fish_wcwidth(L'\u1160') == 0);      // Hangul Jamo
+    do_test(fish_wcwidth(L'\uE000') == 1);      // private use
+
+    // A cluster is as wide as its characters, and its cached width follows the settings.
+    const wchar_t *smiley = L"a\U0001F600\uFE0Fb";
+    do_test(fish_wcswidth(smiley, 4) == 5);
+    g_fish_emoji_width = 1;
+    do_test(fish_wcswidth(smiley, 4) == 4);
+    g_fish_ambiguous_width = 2;
+    do_test(fish_wcwidth(L'\u00A1') == 2);
+    do_test(fish_wcswidth(L"\u00A1\uFE0E", 2) == 2);
+    do_test(fish_wcswidth(smiley, 2) == 2);
+}
+
 static void test_utf8() {
     say(L"Testing utf8");
     wchar_t w1[] = {0x54, 0x65, 0x73, 0x74};
@@ -1800,6 +2022,13 @@ static void test_feature_flags() {
     do_test(ft::metadata[ft::stderr_nocaret].name == wcstring(L"stderr-nocaret"));
     do_test(ft::metadata_for(L"stderr-nocaret") == &ft::metadata[ft::stderr_nocaret]);
     do_test(ft::metadata_for(L"not-a-flag") == nullptr);
+
+    // Only flags which don't affect parsing can be changed while running.
+    do_test(f.set_at_runtime(L"regex-easyesc", true));
+    do_test(f.test(ft::string_replace_backslash));
+    do_test(!f.set_at_runtime(L"qmark-noglob", true));
+    do_test(!f.test(ft::qmark_noglob));
+    do_test(!f.set_at_runtime(L"not-a-flag", true));
 }
 
 static void test_escape_sequences() {
@@ -1910,6 +2139,26 @@ static void test_lru() {
     do_test(cache.evicted.size() == size_t(total_nodes));
 }
 
+static void test_regex_cache() {
+    say(L"Testing the regex cache");
+    regex_code_ref_t first = regex_compile_cached(L"^a+b$", 0);
+    do_test(first != nullptr);
+    do_test(regex_compile_cached(L"^a+b$", 0) == first);
+    // The options are part of the key.
+    do_test(regex_compile_cached(L"^a+b$", PCRE2_CASELESS) != first);
+
+    regex_compile_error_t error;
+    do_test(regex_compile_cached(L"a(b", 0, &error) == nullptr);
+    do_test(error.code != 0 && error.offset == 3);
+
+    // The cache is bounded, and evicts the least recently used patterns.
+    for (size_t i = 0; i < 2 * REGEX_CACHE_SIZE; i++) {
+        regex_compile_cached(L"x" + to_string(static_cast<long>(i)), 0);
+    }
+    do_test(regex_cache_count() == REGEX_CACHE_SIZE);
+    do_test(regex_compile_cached(L"^a+b$", 0) != first);
+}
+
 /// An environment built around an std::map.
 struct test_environment_t : public environment_t {
     std::map<wcstring, wcstring> vars;
@@ -2038,6 +2287,10 @@ static void test_expand() {
 
     expand_test(L"foo", noflags, L"foo", 0, L"Strings do not expand to themselves");
     expand_test(L"a{b,c,d}e", noflags, L"abe", L"ace", L"ade", 0, L"Bracket expansion is broken");
+    expand_test(L"a{1..3}e", noflags, L"a1e", L"a2e", L"a3e", 0,
+                L"Numeric brace range expansion is broken");
+    expand_test(L"{09..11..2}", noflags, L"09", L"11", 0,
+                L"Zero-padded brace range expansion is broken");
     expand_test(L"a*", expand_flag::skip_wildcards, L"a*", 0, L"Cannot skip wildcard expansion");
     expand_test(L"/bin/l\\0", expand_flag::for_completions, 0,
                 L"Failed to handle null escape in expansion");
@@ -2197,6 +2450,60 @@ static void test_expand_overflow() {
     parser->vars().pop();
 }
 
+static void test_flog_compiled_out() {
+    say(L"Testing compiled out debug categories");
+    using flog_details::category_compiled_out;
+    static_assert(!category_compiled_out("screen", ""), "empty list compiles out nothing");
+    static_assert(category_compiled_out("screen", "screen"), "single category");
+    static_assert(category_compiled_out("reader_render", "screen,reader-render"),
+                  "underscores match dashes");
+    static_assert(!category_compiled_out("reader", "screen,reader-render"), "no prefix match");
+    static_assert(!category_compiled_out("reader_render", "reader"), "no prefix match");
+    do_test(category_compiled_out(L"env-export", "config,env-export,path"));
+    do_test(!category_compiled_out(L"env", "config,env-export,path"));
+    // Nothing is compiled out by default.
+    do_test(!flog_compiled_out(screen));
+}
+
+static void test_dir_iter() {
+    say(L"Testing directory iteration");
+    if (system("mkdir -p test/dir_iter/subdir")) err(L"mkdir failed");
+    if (system("touch test/dir_iter/file")) err(L"touch failed");
+    if (system("ln -sf subdir test/dir_iter/dirlink")) err(L"ln failed");
+    if (system("ln -sf nowhere test/dir_iter/badlink")) err(L"ln failed");
+
+    dir_iter_t bad(L"test/dir_iter/nonexistent");
+    do_test(!bad.valid());
+    do_test(bad.next() == nullptr);
+
+    dir_iter_t dir(L"test/dir_iter");
+    do_test(dir.valid());
+    std::map<wcstring, maybe_t<dir_entry_type_t>> types;
+    while (const dir_iter_t::entry_t *entry = dir.next()) {
+        types[entry->name] = entry->check_type();
+    }
+    do_test(types.size() == 6);
+    do_test(types[L"."] == dir_entry_type_t::dir);
+    do_test(types[L".."] == dir_entry_type_t::dir);
+    do_test(types[L"subdir"] == dir_entry_type_t::dir);
+    do_test(types[L"file"] == dir_entry_type_t::reg);
+    // Symlinks are followed, and broken ones have no type.
+    do_test(types[L"dirlink"] == dir_entry_type_t::dir);
+    do_test(!types[L"badlink"]);
+
+    // Rewinding sees the same entries again.
+    dir.rewind();
+    size_t count = 0;
+    while (const dir_iter_t::entry_t *entry = dir.next()) {
+        count++;
+        if (entry->name == L"file") {
+            do_test(!entry->is_dir());
+            do_test(entry->stat() && S_ISREG(entry->stat()->st_mode));
+        }
+    }
+    do_test(count == 6);
+}
+
 static void test_fuzzy_match() {
     say(L"Testing fuzzy string matching");
     // Check that a string fuzzy match has the expected type and case folding.
@@ -2244,6 +2551,62 @@ static void test_ifind_fuzzy() {
     do_test(ifind(std::string{"alpha-b"}, std::string{"alpha b"}, true) == std::string::npos);
 }
 
+static void test_edit_distance() {
+    say(L"Testing edit distance");
+    do_test(string_edit_distance(L"git", L"git", 2) == size_t(0));
+    do_test(string_edit_distance(L"gti", L"git", 1) == size_t(1));
+    do_test(string_edit_distance(L"gt", L"git", 1) == size_t(1));
+    do_test(string_edit_distance(L"giit", L"git", 1) == size_t(1));
+    do_test(string_edit_distance(L"gat", L"git", 1) == size_t(1));
+    do_test(string_edit_distance(L"", L"ab", 2) == size_t(2));
+    do_test(string_edit_distance(L"pyhtno", L"python", 2) == size_t(2));
+    do_test(string_edit_distance(L"pyhtno", L"python", 1) == none());
+    do_test(string_edit_distance(L"abc", L"xyz", 2) == none());
+    do_test(string_edit_distance(L"a", L"abcd", 2) == none());
+}
+
+static void test_frecency() {
+    say(L"Testing frecency");
+    const time_t now = 100000000;
+    auto dir = [](const wchar_t *path, unsigned long rank, time_t last_visit) {
+        frecent_dir_t result;
+        result.path = path;
+        result.rank = rank;
+        result.last_visit = last_visit;
+        return result;
+    };
+    const std::vector<frecent_dir_t> dirs = {
+        dir(L"/src/fish", 10, now - 2 * 24 * 60 * 60),  // 5
+        dir(L"/src/fish/doc", 2, now - 60),             // 8
+        dir(L"/home/Fish", 8, now - 10 * 24 * 60 * 60),  // 2
+        dir(L"/tmp", 100, now),
+    };
+    do_test(dirs.at(0).frecency(now) == 5);
+    do_test(dirs.at(1).frecency(now) == 8);
+    do_test(dirs.at(2).frecency(now) == 2);
+
+    do_test(frecency_match(dirs, {}, now) ==
+            wcstring_list_t({L"/tmp", L"/src/fish/doc", L"/src/fish", L"/home/Fish"}));
+    do_test(frecency_match(dirs, {L"fish"}, now) == wcstring_list_t({L"/src/fish", L"/home/Fish"}));
+    do_test(frecency_match(dirs, {L"Fish"}, now) == wcstring_list_t({L"/home/Fish"}));
+    do_test(frecency_match(dirs, {L"fish", L"d"}, now) == wcstring_list_t({L"/src/fish/doc"}));
+    do_test(frecency_match(dirs, {L"d", L"fish"}, now).empty());
+    do_test(frecency_match(dirs, {L"src", L"src"}, now).empty());
+}
+
+static void test_sha256() {
+    say(L"Testing sha256");
+    do_test(sha256_hex("") ==
+            "e3b0c44298fc1c149afbf4c8996fb92427ae41e4649b934ca495991b7852b855");
+    do_test(sha256_hex("abc") ==
+            "ba7816bf8f01cfea414140de5dae2223b00361a396177a9cb410ff61f20015ad");
+    // Two blocks once padded.
+    do_test(sha256_hex("abcdbcdecdefdefgefghfghighijhijkijkljklmklmnlmnomnopnopq") ==
+            "248d6a61d20638b8e5c026930c3e6039a33ce45964ff2167f6ecedd419db06c1");
+    do_test(sha256_hex(std::string(1000, 'a')) ==
+            "41edece42d63e8d9bf515a9ba6932e1c20cbc9f5a5d134645adb5db1b9737ea3");
+}
+
 static void test_abbreviations() {
     say(L"Testing abbreviations");
     auto &vars = parser_t::principal_parser().vars();
@@ -2356,6 +2719,18 @@ static void test_path() {
     do_test(path_apply_working_directory(L"/abc", L"/def/") == L"/abc");
     do_test(path_apply_working_directory(L"", L"/def/") == L"");
     do_test(path_apply_working_directory(L"abc", L"") == L"abc");
+
+    const auto wsl = windows_path_style_t::wsl, msys = windows_path_style_t::msys;
+    do_test(path_from_windows(L"C:\\Users\\me", wsl) == wcstring(L"/mnt/c/Users/me"));
+    do_test(path_from_windows(L"d:/x", msys) == wcstring(L"/d/x"));
+    do_test(path_from_windows(L"a\\b", msys) == wcstring(L"a/b"));
+    do_test(path_from_windows(L"\\\\wsl$\\Ubuntu\\home", wsl) == wcstring(L"/home"));
+    do_test(!path_from_windows(L"\\\\wsl$\\Ubuntu\\home", msys));
+    do_test(!path_from_windows(L"C:foo", wsl));
+    do_test(path_to_windows(L"/mnt/c/Users/me", wsl) == wcstring(L"C:\\Users\\me"));
+    do_test(path_to_windows(L"/c", msys) == wcstring(L"C:\\"));
+    do_test(!path_to_windows(L"/mnt/c/x", msys));
+    do_test(!path_to_windows(L"/usr", wsl));
 }
 
 static void test_pager_navigation() {
@@ -2446,6 +2821,58 @@ static void test_pager_navigation() {
     }
 }
 
+static void test_pager_jump_search() {
+    say(L"Testing pager jump search");
+
+    // Every third completion matches the search.
+    completion_list_t completions;
+    for (size_t i = 0; i < 10; i++) {
+        append_completion(&completions, i % 3 == 2 ? L"match" : L"other");
+    }
+
+    pager_t pager;
+    pager.set_completions(completions);
+    pager.set_term_size(termsize_t::defaults());
+    pager.set_search_field_shown(true);
+    pager.set_search_jumps_to_matches(true);
+    pager.search_field_line.push_edit(edit_t(0, 0, L"mat"));
+    pager.refilter_completions();
+    page_rendering_t render = pager.render();
+
+    // Nothing is filtered.
+    do_test(render.rows * render.cols >= completions.size());
+
+    const struct {
+        selection_motion_t dir;
+        size_t sel;
+    } cmds[] = {
+        // Next and previous skip to matches, wrapping around.
+        {selection_motion_t::next, 2},
+        {selection_motion_t::next, 5},
+        {selection_motion_t::next, 8},
+        {selection_motion_t::next, 2},
+        {selection_motion_t::prev, 8},
+        // Other directions don't skip.
+        {selection_motion_t::south, 9},
+        {selection_motion_t::next, 2},
+    };
+    for (size_t i = 0; i < sizeof cmds / sizeof *cmds; i++) {
+        pager.select_next_completion_in_direction(cmds[i].dir, render);
+        pager.update_rendering(&render);
+        if (cmds[i].sel != render.selected_completion_idx) {
+            err(L"For command %lu, expected selection %lu, but found instead %lu\n", i, cmds[i].sel,
+                render.selected_completion_idx);
+        }
+    }
+    do_test(pager.selection_matches_search());
+
+    // Without any match, the selection stays.
+    pager.search_field_line.push_edit(edit_t(3, 0, L"xyz"));
+    pager.refilter_completions();
+    do_test(!pager.selection_matches_search());
+    do_test(!pager.select_next_completion_in_direction(selection_motion_t::next, render));
+}
+
 struct pager_layout_testcase_t {
     int width;
     const wchar_t *expected;
@@ -2840,6 +3267,18 @@ static void test_wcstod() {
     tod_test(L"nope", "nope");
 }
 
+static void test_writev_loop() {
+    say(L"Testing writev_loop");
+    auto pipes = make_autoclose_pipes().acquire();
+    char a[] = "abc", b[] = "", c[] = "defgh";
+    struct iovec iov[] = {{a, 3}, {b, 0}, {c, 5}, {b, 0}};
+    do_test(writev_loop(pipes.write.fd(), iov, 4) == 8);
+    pipes.write.close();
+    char buff[16] = {};
+    do_test(read_loop(pipes.read.fd(), buff, sizeof buff) == 8);
+    do_test(std::string(buff) == "abcdefgh");
+}
+
 static void test_dup2s() {
     using std::make_shared;
     io_chain_t chain;
@@ -2894,6 +3333,51 @@ static void test_colors() {
     do_test(rgb_color_t(L"magenta").is_named());
     do_test(rgb_color_t(L"MaGeNTa").is_named());
     do_test(rgb_color_t(L"mooganta").is_none());
+
+    // Colors in the palettes map to themselves, and others to the ones which look closest.
+    do_test(rgb_color_t(L"ff0000").to_term256_index() == 196);
+    do_test(rgb_color_t(L"87afd7").to_term256_index() == 110);
+    do_test(rgb_color_t(L"ff8000").to_term256_index() == 208);
+    set_term16_palette(L"xterm", {});
+    do_test(rgb_color_t(L"5c5cff").to_name_index() == 12);
+    do_test(rgb_color_t(L"aaaaaa").to_name_index() == 8);
+    do_test(rgb_color_t(L"a0522d").to_name_index() == 1);
+    set_term16_palette(L"linux", {});
+    do_test(rgb_color_t(L"aa5500").to_name_index() == 3);
+    std::vector<color24_t> palette(16, color24_t{{0, 0, 0}});
+    palette[5] = color24_t{{0x12, 0x34, 0x56}};
+    set_term16_palette(L"xterm", palette);
+    do_test(rgb_color_t(L"123456").to_name_index() == 5);
+    set_term16_palette(L"", {});
+}
+
+static void test_styled_text() {
+    say(L"Testing styled text");
+    styled_text_t text;
+    text.append(L"set: ");
+    text.append_format(text_style_t::error, L"%ls '%ls'", L"Unknown option", L"--foo");
+    text.append(L" (see help)\n", text_style_t::emphasis);
+    do_test(text.text() == L"set: Unknown option '--foo' (see help)\n");
+
+    // Without colors, or wrapping, the text is unchanged.
+    null_environment_t vars;
+    do_test(text.render(vars, false, 0) == text.text());
+
+    // Lines are wrapped at the last space that fits.
+    do_test(text.render(vars, false, 20) == L"set: Unknown option\n'--foo' (see help)\n");
+    do_test(text.render(vars, false, 10) == L"set:\nUnknown\noption\n'--foo'\n(see help)\n");
+
+    // Verbatim text is never wrapped.
+    styled_text_t code;
+    code.append(L"echo foo bar\n     ^\n", text_style_t::verbatim);
+    code.append(L"some more text");
+    do_test(code.render(vars, false, 10) == L"echo foo bar\n     ^\nsome more\ntext");
+
+    // Appending styled text keeps its styles.
+    styled_text_t copy;
+    copy.append(text);
+    do_test(copy.text() == text.text());
+    do_test(copy.render(vars, false, 20) == text.render(vars, false, 20));
 }
 
 // This class allows accessing private bits of autoload_t.
@@ -2964,6 +3448,21 @@ struct autoload_tester_t {
         do_test(autoload.resolve_command(L"file1", paths));
         autoload.mark_autoload_finished(L"file1");
 
+        // Where the directories are watched, new files are found without invalidating the cache.
+        do_test(!autoload.resolve_command(L"file3", paths));
+        touch_file(format_string(L"%ls/file3.fish", p2.c_str()));
+        if (autoload.all_dirs_watched()) {
+            do_test(autoload.resolve_command(L"file3", paths));
+            autoload.mark_autoload_finished(L"file3");
+        }
+
+        // A new file in an earlier directory shadows a known one.
+        touch_file(format_string(L"%ls/file2.fish", p1.c_str()));
+        autoload.invalidate_cache();
+        auto shadowed = autoload.resolve_command(L"file2", paths);
+        do_test(shadowed && string_prefixes_string(p1, *shadowed));
+        if (shadowed) autoload.mark_autoload_finished(L"file2");
+
         run(L"rm -Rf %ls", p1.c_str());
         run(L"rm -Rf %ls", p2.c_str());
     }
@@ -3120,6 +3619,25 @@ static void test_complete() {
     do_test(completions.size() == 1);
     do_test(completions.at(0).completion == L"qux");
 
+    // Argument hints for options that require a parameter.
+    completion_mode_t requires_param{};
+    requires_param.requires_param = true;
+    complete_add(L"foobarbaz", false, L"o", option_type_short, requires_param, NULL, NULL,
+                 L"Output file", 0);
+    complete_add(L"foobarbaz", false, L"output", option_type_double_long, requires_param, NULL,
+                 NULL, L"Output file", 0);
+    complete_add(L"foobarbaz", false, L"level", option_type_double_long, requires_param,
+                 L"false", NULL, L"Level", 0);
+    do_test(complete_get_argument_hint(L"foobarbaz -o ", parser->context()) == L"Output file");
+    do_test(complete_get_argument_hint(L"foobarbaz --output ", parser->context()) ==
+            L"Output file");
+    do_test(complete_get_argument_hint(L"echo (foobarbaz -o ", parser->context()) ==
+            L"Output file");
+    do_test(complete_get_argument_hint(L"foobarbaz -o", parser->context()).empty());
+    do_test(complete_get_argument_hint(L"foobarbaz -o x ", parser->context()).empty());
+    do_test(complete_get_argument_hint(L"foobarbaz ", parser->context()).empty());
+    do_test(complete_get_argument_hint(L"foobarbaz --level ", parser->context()).empty());
+
     // Don't complete variable names in single quotes (#1023).
     completions = do_complete(L"echo '$Foo", {});
     do_test(completions.empty());
@@ -3557,6 +4075,70 @@ static void test_line_iterator() {
     do_test((lines2 == wcstring_list_t{L"", L"", L"Alpha", L"Beta", L"Gamma", L"", L"Delta"}));
 }
 
+static void test_shared_killring() {
+    say(L"Testing the shared killring");
+    // Our own kills are not merged back in.
+    kill_add(L"local");
+    wcstring_list_t shared = kill_update_shared({}, L"", L"local", 3);
+    do_test(shared == wcstring_list_t{L"local"});
+    kill_merge_shared(shared);
+    do_test(kill_yank() == L"local");
+
+    // Appending to a kill replaces it in the shared killring too.
+    kill_replace(L"local", L"local more");
+    shared = kill_update_shared(shared, L"local", L"local more", 3);
+    do_test(shared == wcstring_list_t{L"local more"});
+
+    // Kills from other sessions end up on top, the most recent first.
+    shared = {L"other2", L"other1", L"local more"};
+    kill_merge_shared(shared);
+    do_test(kill_yank() == L"other2");
+    do_test(kill_yank_rotate() == L"other1");
+    do_test(kill_yank_rotate() == L"local more");
+
+    // A kill which is only local stays on top if the shared killring did not change.
+    kill_add(L"secret");
+    kill_merge_shared(shared);
+    do_test(kill_yank() == L"secret");
+
+    // The shared killring is limited in size.
+    shared = kill_update_shared(shared, L"", L"new", 3);
+    do_test(shared == (wcstring_list_t{L"new", L"other2", L"other1"}));
+}
+
+static void test_snippets() {
+    say(L"Testing snippet parsing");
+    std::vector<snippet_placeholder_t> placeholders;
+    auto placeholder_text = [&](const wcstring &text, size_t idx) {
+        return text.substr(placeholders.at(idx).start, placeholders.at(idx).length);
+    };
+
+    wcstring text = parse_snippet(L"ssh ${1:host} -p ${2:22}", &placeholders);
+    do_test(text == L"ssh host -p 22");
+    do_test(placeholders.size() == 2);
+    do_test(placeholder_text(text, 0) == L"host");
+    do_test(placeholder_text(text, 1) == L"22");
+
+    // Placeholders are visited by number, with 0 last.
+    text = parse_snippet(L"$0 ${2:b} ${1} ${1:a}", &placeholders);
+    do_test(text == L" b  a");
+    do_test(placeholders.size() == 4);
+    do_test(placeholders.at(0).start == 3 && placeholders.at(0).length == 0);
+    do_test(placeholder_text(text, 1) == L"a");
+    do_test(placeholder_text(text, 2) == L"b");
+    do_test(placeholders.at(3).start == 0 && placeholders.at(3).length == 0);
+
+    // Other dollar signs, escaped dollar signs and unfinished placeholders are text.
+    text = parse_snippet(L"echo $HOME \\$1 ${1:a\\}b} ${2:x", &placeholders);
+    do_test(text == L"echo $HOME $1 a}b ${2:x");
+    do_test(placeholders.size() == 1);
+    do_test(placeholder_text(text, 0) == L"a}b");
+
+    text = parse_snippet(L"no placeholders", &placeholders);
+    do_test(text == L"no placeholders");
+    do_test(placeholders.empty());
+}
+
 static void test_undo() {
     say(L"Testing undo/redo setting and restoring text and cursor position.");
 
@@ -3701,6 +4283,18 @@ static void test_universal_output() {
         "SETUVAR --export --path varD:ValD1\n"
         "SETUVAR --path varE:ValE1\\x1eValE2\n";
     do_test(text == expected);
+
+    // The sessions which set variables are written where they are known.
+    uvar_origin_table_t origins{{L"varB", L"1234@host"}};
+    text = env_universal_t::serialize_with_vars(vars, &origins);
+    do_test(text.find("SETUVAR --export --origin=1234@host varB:ValB1\n") != std::string::npos);
+    do_test(text.find("SETUVAR varC:ValC1\n") != std::string::npos);
+
+    var_table_t parsed_vars;
+    uvar_origin_table_t parsed_origins;
+    env_universal_t::populate_variables(text, &parsed_vars, nullptr, &parsed_origins);
+    do_test(parsed_vars == vars);
+    do_test(parsed_origins == origins);
 }
 
 static void test_universal_parsing() {
@@ -3745,6 +4339,79 @@ static void test_universal_parsing_legacy() {
     do_test(vars == parsed_vars);
 }
 
+static void test_universal_parsing_bad_lines() {
+    say(L"Testing universal variable parsing with bad lines");
+    const char *input =
+        "# VERSION: 3.0\n"
+        "SETUVAR varA:ValA1\n"
+        "garbage\n"
+        "\n"
+        "SETUVAR no_colon\n"
+        "SETUVAR varB:ValB1\n";
+
+    var_table_t vars;
+    vars[L"varA"] = env_var_t(wcstring_list_t{L"ValA1"}, 0);
+    vars[L"varB"] = env_var_t(wcstring_list_t{L"ValB1"}, 0);
+
+    var_table_t parsed_vars;
+    std::vector<size_t> bad_lines;
+    env_universal_t::populate_variables(input, &parsed_vars, &bad_lines);
+    do_test(vars == parsed_vars);
+    do_test((bad_lines == std::vector<size_t>{3, 5}));
+}
+
+static void test_universal_hosts() {
+    say(L"Testing universal variables for hosts");
+    const env_var_t::env_var_flags_t noflags = 0;
+    const env_var_t::env_var_flags_t flag_host = env_var_t::flag_host;
+    const std::string host = wcs2string(uvar_host());
+    const std::string input =
+        "# VERSION: 3.0\n"
+        "SETUVAR varA:shared\n"
+        "SETUVAR --host=" + host + " varA:mine\n"
+        "SETUVAR --host=" + host + "_other varA:theirs\n"
+        "SETUVAR --host=" + host + "_other varB:theirs\n"
+        "SETUVAR --host=" + host + " varC:mine\n"
+        "SETUVAR varC:shared\n";
+
+    // Only the variables for this host, and the shared ones which they don't shadow, are visible.
+    var_table_t vars;
+    vars[L"varA"] = env_var_t(L"mine", flag_host);
+    vars[L"varC"] = env_var_t(L"mine", flag_host);
+    var_table_t parsed_vars;
+    hidden_uvar_list_t hidden;
+    env_universal_t::populate_variables(input, &parsed_vars, nullptr, nullptr, &hidden);
+    do_test(parsed_vars == vars);
+    do_test(hidden.size() == 4);
+
+    // They are all written back.
+    std::string text = env_universal_t::serialize_with_vars(parsed_vars, nullptr, &hidden);
+    do_test(text.find("SETUVAR varA:shared\nSETUVAR --host=" + host + " varA:mine\n") !=
+            std::string::npos);
+    do_test(text.find("SETUVAR --host=" + host + "_other varB:theirs\n") != std::string::npos);
+    do_test(text.find("SETUVAR varC:shared\n") != std::string::npos);
+
+    // Setting a variable for this host shadows the shared one, and erasing it reveals it again.
+    if (system("mkdir -p test/fish_uvars_test/")) err(L"mkdir failed");
+    callback_data_list_t callbacks;
+    env_universal_t uvars1(UVARS_TEST_PATH);
+    env_universal_t uvars2(UVARS_TEST_PATH);
+    uvars1.set(L"theme", env_var_t{L"shared", noflags});
+    uvars1.sync(callbacks);
+    uvars1.set(L"theme", env_var_t{L"mine", flag_host});
+    uvars1.sync(callbacks);
+    uvars2.sync(callbacks);
+    do_test(uvars2.get(L"theme") == env_var_t(L"mine", flag_host));
+    do_test(uvars2.remove(L"theme"));
+    do_test(uvars2.get(L"theme") == env_var_t(L"shared", noflags));
+    uvars2.sync(callbacks);
+    uvars1.sync(callbacks);
+    do_test(uvars1.get(L"theme") == env_var_t(L"shared", noflags));
+    do_test(uvars1.remove(L"theme"));
+    do_test(!uvars1.get(L"theme"));
+    system_assert("rm -Rf test/fish_uvars_test/");
+}
+
 static bool callback_data_less_than(const callback_data_t &a, const callback_data_t &b) {
     return a.key < b.key;
 }
@@ -3792,10 +4459,15 @@ static void test_universal_callbacks() {
     do_test(callbacks.size() == 3);
     do_test(callbacks.at(0).key == L"alpha");
     do_test(callbacks.at(0).val == wcstring{L"2"});
+    do_test(callbacks.at(0).old_val == wcstring_list_t{L"1"});
+    do_test(callbacks.at(0).origin == uvar_session_origin());
     do_test(callbacks.at(1).key == L"beta");
     do_test(callbacks.at(1).val == wcstring{L"1"});
     do_test(callbacks.at(2).key == L"delta");
     do_test(callbacks.at(2).val == none());
+    do_test(callbacks.at(2).old_val == wcstring_list_t{L"1"});
+    do_test((callbacks.at(0).event_args() ==
+             wcstring_list_t{L"VARIABLE", L"SET", L"alpha", uvar_session_origin(), L"1"}));
     system_assert("rm -Rf test/fish_uvars_test/");
 }
 
@@ -3880,6 +4552,14 @@ static void trigger_or_wait_for_notification(universal_notifier_t::notifier_stra
         case universal_notifier_t::strategy_sigio: {
             break;  // nothing required
         }
+        case universal_notifier_t::strategy_dir_watch: {
+            // Replace the file, like saving the variables does.
+            if (system("echo changed > test/fish_uvars_test/varsfile.tmp && "
+                       "mv test/fish_uvars_test/varsfile.tmp test/fish_uvars_test/varsfile.txt")) {
+                err(L"Replacing the variables file failed");
+            }
+            break;
+        }
     }
 }
 
@@ -3960,12 +4640,18 @@ static void test_universal_notifiers() {
 
     auto strategy = universal_notifier_t::resolve_default_strategy();
     test_notifiers_with_strategy(strategy);
+#if defined(HAVE_INOTIFY_INIT1) || defined(HAVE_KQUEUE)
+    if (strategy != universal_notifier_t::strategy_dir_watch) {
+        test_notifiers_with_strategy(universal_notifier_t::strategy_dir_watch);
+    }
+#endif
 }
 
 class history_tests_t {
    public:
     static void test_history();
     static void test_history_merge();
+    static void test_history_merge_strategies();
     static void test_history_path_detection();
     static void test_history_formats();
     // static void test_history_speed(void);
@@ -4325,6 +5011,71 @@ void history_tests_t::test_history_merge() {
     everything->clear();
 }
 
+void history_tests_t::test_history_merge_strategies() {
+    say(L"Testing history merge strategies");
+    const wcstring name = L"merge_strategy_test";
+    auto first = std::make_shared<history_t>(name);
+    auto second = std::make_shared<history_t>(name);
+    auto third = std::make_shared<history_t>(name);
+    first->clear();
+    time_barrier();
+
+    // Both sessions run a command, then the first runs another, then the second repeats the first
+    // command of the first session.
+    first->add(L"first 1");
+    first->save();
+    second->add(L"second 1");
+    second->save();
+    time_barrier();
+    first->add(L"first 2");
+    first->save();
+    time_barrier();
+    second->add(L"first 1");
+    second->save();
+    time_barrier();
+
+    // With the session strategy, our own items stay the most recent.
+    history_merge_result_t result =
+        first->incorporate_external_changes(history_merge_strategy_t::session);
+    do_test(result.merged == 2);
+    do_test(result.dropped == 0);
+    do_test(first->size() == 4);
+    do_test(first->item_at_index(1).str() == L"first 2");
+    do_test(first->item_at_index(2).str() == L"first 1");
+    do_test(first->item_at_index(3).str() == L"first 1");
+    do_test(first->item_at_index(4).str() == L"second 1");
+
+    // With interleaving, everything is in the order it was run.
+    result = second->incorporate_external_changes(history_merge_strategy_t::interleave);
+    do_test(result.merged == 2);
+    do_test(second->size() == 4);
+    do_test(second->item_at_index(1).str() == L"first 1");
+    do_test(second->item_at_index(2).str() == L"first 2");
+    do_test(second->item_at_index(3).str() == L"second 1");
+    do_test(second->item_at_index(4).str() == L"first 1");
+
+    // With last-writer-wins, each command only appears at its most recent use.
+    result = third->incorporate_external_changes(history_merge_strategy_t::last_writer_wins);
+    do_test(result.merged == 4);
+    do_test(result.dropped == 1);
+    do_test(third->size() == 3);
+    do_test(third->item_at_index(1).str() == L"first 1");
+    do_test(third->item_at_index(2).str() == L"first 2");
+    do_test(third->item_at_index(3).str() == L"second 1");
+
+    // Items added after a session merge are still ours. Merging again only reports what is new.
+    time_barrier();
+    first->add(L"first 3");
+    first->save();
+    do_test(first->item_at_index(1).str() == L"first 3");
+    do_test(first->item_at_index(2).str() == L"first 2");
+    time_barrier();
+    result = third->incorporate_external_changes(history_merge_strategy_t::last_writer_wins);
+    do_test(result.merged == 1);
+    do_test(third->item_at_index(1).str() == L"first 3");
+    first->clear();
+}
+
 void history_tests_t::test_history_path_detection() {
     // Regression test for #7582.
     say(L"Testing history path detection");
@@ -4791,6 +5542,47 @@ static void test_new_parser_ad_hoc() {
     do_test(!ast.errored());
 }
 
+static void test_ast_serialize() {
+    using namespace ast;
+    say(L"Testing serializing asts");
+
+    const wchar_t *srcs[] = {
+        L"",
+        L"echo hello # a comment\n",
+        L"function foo --argument a\n  if test $a = 1; and not false\n    echo one >&2\n"
+        L"  else if true || false\n    command ls | grep x 2>/dev/null &\n  else\n"
+        L"    return 1\n  end\nend\n",
+        L"for i in (seq 3); switch $i; case 1 2; echo $i; case '*'; continue; end; end",
+        L"while true; begin; time FOO=bar builtin echo $FOO; end; break; end",
+        L"echo 'unterminated",
+        L"if true; echo missing end",
+    };
+    for (const wchar_t *src : srcs) {
+        parse_error_list_t errors;
+        auto ast = ast_t::parse(src, parse_flag_include_comments, &errors);
+        std::string data = ast.serialize();
+        auto loaded = ast_t::deserialize(data, src);
+        if (!loaded) {
+            err(L"Could not deserialize the ast of '%ls'", src);
+            continue;
+        }
+        do_test(loaded->errored() == ast.errored());
+        do_test(loaded->extras().comments.size() == ast.extras().comments.size());
+        if (loaded->dump(src) != ast.dump(src)) {
+            err(L"The deserialized ast of '%ls' differs:\n%ls", src, loaded->dump(src).c_str());
+        }
+
+        // Data which is cut short, has extra bytes or doesn't fit the source is rejected.
+        if (!data.empty()) {
+            do_test(!ast_t::deserialize(data.substr(0, data.size() - 1), src));
+        }
+        do_test(!ast_t::deserialize(data + '\0', src));
+        if (std::wcslen(src) > 0) {
+            do_test(!ast_t::deserialize(data, wcstring(src).substr(0, std::wcslen(src) - 1)));
+        }
+    }
+}
+
 static void test_new_parser_errors() {
     say(L"Testing new parser error reporting");
     const struct {
@@ -4800,6 +5592,10 @@ static void test_new_parser_errors() {
         {L"echo 'abc", parse_error_tokenizer_unterminated_quote},
         {L"'", parse_error_tokenizer_unterminated_quote},
         {L"echo (abc", parse_error_tokenizer_unterminated_subshell},
+        {L"cat <<END\nabc", parse_error_tokenizer_unterminated_heredoc},
+        {L"cat <<'END'\nabc\nEND", parse_error_tokenizer_other},
+        {L"cat <<A <<B\na\nA\nb\nB", parse_error_tokenizer_other},
+        {L"cat <<END |\ngrep x\nabc\nEND", parse_error_tokenizer_other},
 
         {L"end", parse_error_unbalancing_end},
         {L"echo hi ; end", parse_error_unbalancing_end},
@@ -5315,6 +6111,9 @@ static void test_wwrite_to_fd() {
         std::string contents(expected_size, '\0');
         ssize_t read_amt = read(fd.fd(), &contents[0], expected_size);
         do_test(read_amt >= 0 && static_cast<size_t>(read_amt) == expected_size);
+
+        maybe_t<std::string> read_back = read_file_contents(std::string(t));
+        do_test(read_back && *read_back == narrow);
     }
     (void)remove(t);
 }
@@ -5739,6 +6538,15 @@ static void test_env_vars() {
     do_test(v1 != v4 && !(v1 == v4));
 }
 
+static void test_intern() {
+    say(L"Testing string interning");
+    wcstring name = L"test_intern_string";
+    const wchar_t *interned = intern(name.c_str());
+    do_test(interned != name.c_str() && name == interned);
+    do_test(intern(wcstring(name).c_str()) == interned);
+    do_test(intern(L"test_intern_other") != interned);
+}
+
 static void test_env_snapshot() {
     if (system("mkdir -p test/fish_env_snapshot_test/")) err(L"mkdir failed");
     bool pushed = pushd("test/fish_env_snapshot_test");
@@ -5762,15 +6570,49 @@ static void test_env_snapshot() {
     do_test(snapshot->get(L"test_env_snapshot_var")->as_string() == L"before");
     do_test(snapshot->get(L"test_env_snapshot_var_2") == none());
 
-    // snapshots see global var changes except for perproc like PWD
+    // snapshots don't see later changes to globals either, since other threads read them
     vars.set(L"test_env_snapshot_var_3", ENV_GLOBAL, {L"reallyglobal"});
     do_test(vars.get(L"test_env_snapshot_var_3")->as_string() == L"reallyglobal");
-    do_test(snapshot->get(L"test_env_snapshot_var_3")->as_string() == L"reallyglobal");
+    do_test(snapshot->get(L"test_env_snapshot_var_3") == none());
+    const auto snapshot2 = vars.snapshot();
+    do_test(snapshot2->get(L"test_env_snapshot_var_3")->as_string() == L"reallyglobal");
+    vars.remove(L"test_env_snapshot_var_3", ENV_GLOBAL);
+    do_test(snapshot2->get(L"test_env_snapshot_var_3")->as_string() == L"reallyglobal");
+    do_test(vars.snapshot()->get(L"test_env_snapshot_var_3") == none());
 
     vars.pop();
     popd();
 }
 
+static void test_env_get_many() {
+    auto &vars = parser_t::principal_parser().vars();
+    vars.push(true);
+    vars.set_one(L"test_get_many_b", ENV_LOCAL, L"b");
+    vars.set_one(L"test_get_many_a", ENV_LOCAL, L"a");
+    vars.set_one(L"test_get_many_c", ENV_LOCAL | ENV_EXPORT, L"c");
+
+    auto many = vars.get_many({L"test_get_many_c", L"test_get_many_missing", L"test_get_many_a"});
+    do_test(many.size() == 3);
+    do_test(many.at(0) && many.at(0)->as_string() == L"c");
+    do_test(!many.at(1));
+    do_test(many.at(2) && many.at(2)->as_string() == L"a");
+
+    // Snapshots use the default implementation, which must give the same results.
+    const std::shared_ptr<const environment_t> snapshot = vars.snapshot();
+    for (const environment_t *env : {static_cast<const environment_t *>(&vars), snapshot.get()}) {
+        auto matching = env->get_matching(L"test_get_many_*");
+        do_test(matching.size() == 3);
+        if (matching.size() == 3) {
+            do_test(matching.at(0).first == L"test_get_many_a");
+            do_test(matching.at(1).first == L"test_get_many_b");
+            do_test(matching.at(2).second.as_string() == L"c");
+        }
+        matching = env->get_matching(L"test_get_many_?", ENV_EXPORT);
+        do_test(matching.size() == 1 && matching.at(0).first == L"test_get_many_c");
+    }
+    vars.pop();
+}
+
 static void test_illegal_command_exit_code() {
     say(L"Testing illegal command exit code");
 
@@ -6150,6 +6992,85 @@ Executed in  500.00 micros    fish         external
     free(saved_locale);
 }
 
+static void test_json() {
+    say(L"Testing JSON");
+    const wchar_t *const valid[] = {
+        L"null",
+        L"[]",
+        L"{}",
+        L"[true,false,null,-12,1.5,\"\"]",
+        L"{\"a\":{\"b\":[1,2,{\"c\":\"\\\"quoted\\\"\\n\"}]}}",
+    };
+    for (const wchar_t *text : valid) {
+        auto val = json_parse(text);
+        if (!val) {
+            err(L"Failed to parse JSON '%ls'", text);
+        } else if (json_dump(*val) != text) {
+            err(L"JSON '%ls' was dumped as '%ls'", text, json_dump(*val).c_str());
+        }
+    }
+
+    const wchar_t *const invalid[] = {
+        L"", L"[", L"[1,]", L"{\"a\"}", L"{\"a\":1,}", L"tru", L"\"\\x\"", L"1 2", L"-", L"[1]]",
+    };
+    for (const wchar_t *text : invalid) {
+        if (json_parse(text)) err(L"Unexpectedly parsed invalid JSON '%ls'", text);
+    }
+
+    auto val = json_parse(L" { \"s\" : \"a\\u00e9\\ud83d\\ude00\\/\" , \"n\": 42 } ");
+    if (!val) {
+        err(L"Failed to parse JSON with escapes and whitespace");
+        return;
+    }
+    do_test(val->type == json_value_t::type_t::object);
+    do_test(val->get(L"s") && val->get(L"s")->string == L"a\u00e9\U0001F600/");
+    do_test(val->get(L"n") && val->get(L"n")->number == 42);
+    do_test(val->get(L"missing") == nullptr);
+    do_test(json_dump(*val) == L"{\"s\":\"a\u00e9\U0001F600/\",\"n\":42}");
+}
+
+static void test_git_status() {
+    say(L"Testing git status helpers");
+    auto sha1_hex = [](const std::string &data) {
+        std::string hex;
+        for (unsigned char c : git_sha1(data)) {
+            const char *digits = "0123456789abcdef";
+            hex.push_back(digits[c >> 4]);
+            hex.push_back(digits[c & 0xf]);
+        }
+        return hex;
+    };
+    do_test(sha1_hex("") == "da39a3ee5e6b4b0d3255bfef95601890afd80709");
+    do_test(sha1_hex("abc") == "a9993e364706816aba3e25717850c26c9cd0d89d");
+    do_test(sha1_hex("abcdbcdecdefdefgefghfghighijhijkijkljklmklmnlmnomnopnopq") ==
+            "84983e441c3bd26ebaae4aa1f95129e5e54670f1");
+    // The id of a blob with "hello\n", like `git hash-object` prints.
+    do_test(sha1_hex(std::string("blob 6\0hello\n", 13)) ==
+            "ce013625030ba8dba906f756967f9e9ca394464a");
+
+    const struct {
+        const char *pattern;
+        const char *str;
+        bool matches;
+    } wildmatch_tests[] = {
+        {"*.o", "foo.o", true},          {"*.o", "dir/foo.o", false},
+        {"foo?", "foo1", true},          {"foo?", "foo/", false},
+        {"[a-c]x", "bx", true},          {"[!a-c]x", "bx", false},
+        {"[]]x", "]x", true},            {"\\*x", "*x", true},
+        {"\\*x", "ax", false},         {"**/foo", "foo", true},
+        {"**/foo", "a/b/foo", true},     {"a/**/b", "a/b", true},
+        {"a/**/b", "a/x/y/b", true},     {"a/**", "a/x/y", true},
+        {"a/*/b", "a/x/y/b", false},     {"build", "build", true},
+        {"build", "builds", false},      {"*", "", true},
+    };
+    for (const auto &test : wildmatch_tests) {
+        if (git_wildmatch(test.pattern, test.str) != test.matches) {
+            err(L"git_wildmatch('%s', '%s') should be %s", test.pattern, test.str,
+                test.matches ? "true" : "false");
+        }
+    }
+}
+
 struct termsize_tester_t {
     static void test();
 };
@@ -6214,6 +7135,24 @@ void termsize_tester_t::test() {
     do_test(ts.last() == termsize_t(83, 38));
     ts2.handle_winch();
     do_test(ts2.updating(parser) == *stubby_termsize);
+
+    // A tty which reports no size falls back to asking the terminal, but only once.
+    static int query_count = 0;
+    termsize_container_t ts3([] { return maybe_t<termsize_t>{}; },
+                             [](bool *in_tty) {
+                                 query_count++;
+                                 *in_tty = false;
+                                 return maybe_t<termsize_t>{termsize_t{132, 43}};
+                             });
+    vars.remove(L"COLUMNS", ENV_GLOBAL);
+    vars.remove(L"LINES", ENV_GLOBAL);
+    do_test(ts3.initialize(vars) == termsize_t(132, 43));
+    ts3.handle_winch();
+    do_test(ts3.updating(parser) == termsize_t(132, 43));
+    do_test(query_count == 1);
+    // Commands can't get that size from the tty, so it is exported.
+    do_test(ts3.should_export_columns_lines(vars));
+    do_test(!ts2.should_export_columns_lines(vars));
 }
 
 /// Main test.
@@ -6268,6 +7207,8 @@ int main(int argc, char **argv) {
     if (should_test_function("wwrite_to_fd")) test_wwrite_to_fd();
     if (should_test_function("env_vars")) test_env_vars();
     if (should_test_function("env")) test_env_snapshot();
+    if (should_test_function("env")) test_env_get_many();
+    if (should_test_function("intern")) test_intern();
     if (should_test_function("str_to_num")) test_str_to_num();
     if (should_test_function("enum")) test_enum_set();
     if (should_test_function("enum")) test_enum_array();
@@ -6277,6 +7218,7 @@ int main(int argc, char **argv) {
         test_new_parser_fuzzing();  // fuzzing is expensive
     if (should_test_function("new_parser_correctness")) test_new_parser_correctness();
     if (should_test_function("new_parser_ad_hoc")) test_new_parser_ad_hoc();
+    if (should_test_function("ast_serialize")) test_ast_serialize();
     if (should_test_function("new_parser_errors")) test_new_parser_errors();
     if (should_test_function("error_messages")) test_error_messages();
     if (should_test_function("escape")) test_unescape_sane();
@@ -6293,39 +7235,54 @@ int main(int argc, char **argv) {
     if (should_test_function("pthread")) test_pthread();
     if (should_test_function("debounce")) test_debounce();
     if (should_test_function("debounce")) test_debounce_timeout();
+    if (should_test_function("task_scope")) test_task_scope();
     if (should_test_function("parser")) test_parser();
     if (should_test_function("cancellation")) test_cancellation();
     if (should_test_function("indents")) test_indents();
+    if (should_test_function("wcwidth")) test_wcwidth();
     if (should_test_function("utf8")) test_utf8();
     if (should_test_function("feature_flags")) test_feature_flags();
     if (should_test_function("escape_sequences")) test_escape_sequences();
     if (should_test_function("pcre2_escape")) test_pcre2_escape();
     if (should_test_function("lru")) test_lru();
+    if (should_test_function("regex_cache")) test_regex_cache();
     if (should_test_function("expand")) test_expand();
     if (should_test_function("expand")) test_expand_overflow();
+    if (should_test_function("dir_iter")) test_dir_iter();
+    if (should_test_function("flog")) test_flog_compiled_out();
     if (should_test_function("fuzzy_match")) test_fuzzy_match();
     if (should_test_function("ifind")) test_ifind();
     if (should_test_function("ifind_fuzzy")) test_ifind_fuzzy();
+    if (should_test_function("edit_distance")) test_edit_distance();
+    if (should_test_function("frecency")) test_frecency();
+    if (should_test_function("sha256")) test_sha256();
     if (should_test_function("abbreviations")) test_abbreviations();
     if (should_test_function("test")) test_test();
     if (should_test_function("wcstod")) test_wcstod();
+    if (should_test_function("writev_loop")) test_writev_loop();
     if (should_test_function("dup2s")) test_dup2s();
     if (should_test_function("dup2s")) test_dup2s_fd_for_target_fd();
     if (should_test_function("path")) test_path();
     if (should_test_function("pager_navigation")) test_pager_navigation();
+    if (should_test_function("pager_jump_search")) test_pager_jump_search();
     if (should_test_function("pager_layout")) test_pager_layout();
     if (should_test_function("word_motion")) test_word_motion();
     if (should_test_function("is_potential_path")) test_is_potential_path();
     if (should_test_function("colors")) test_colors();
+    if (should_test_function("styled_text")) test_styled_text();
     if (should_test_function("complete")) test_complete();
     if (should_test_function("autoload")) test_autoload();
     if (should_test_function("input")) test_input();
     if (should_test_function("line_iterator")) test_line_iterator();
     if (should_test_function("undo")) test_undo();
+    if (should_test_function("snippets")) test_snippets();
+    if (should_test_function("shared_killring")) test_shared_killring();
     if (should_test_function("universal")) test_universal();
     if (should_test_function("universal")) test_universal_output();
     if (should_test_function("universal")) test_universal_parsing();
     if (should_test_function("universal")) test_universal_parsing_legacy();
+    if (should_test_function("universal")) test_universal_parsing_bad_lines();
+    if (should_test_function("universal")) test_universal_hosts();
     if (should_test_function("universal")) test_universal_callbacks();
     if (should_test_function("universal")) test_universal_formats();
     if (should_test_function("universal")) test_universal_ok_to_save();
@@ -6336,6 +7293,9 @@ int main(int argc, char **argv) {
     if (should_test_function("autosuggest_suggest_special")) test_autosuggest_suggest_special();
     if (should_test_function("history")) history_tests_t::test_history();
     if (should_test_function("history_merge")) history_tests_t::test_history_merge();
+    if (should_test_function("history_merge_strategies")) {
+        history_tests_t::test_history_merge_strategies();
+    }
     if (should_test_function("history_paths")) history_tests_t::test_history_path_detection();
     if (!is_windows_subsystem_for_linux()) {
         // this test always fails under WSL
@@ -6348,10 +7308,14 @@ int main(int argc, char **argv) {
     if (should_test_function("layout_cache")) test_layout_cache();
     if (should_test_function("prompt")) test_prompt_truncation();
     if (should_test_function("normalize")) test_normalize_path();
+    if (should_test_function("signal_wakeup")) test_signal_wakeup();
+    if (should_test_function("stalled_output")) test_stalled_output();
     if (should_test_function("topics")) test_topic_monitor();
     if (should_test_function("topics")) test_topic_monitor_torture();
     if (should_test_function("pipes")) test_pipes();
     if (should_test_function("timer_format")) test_timer_format();
+    if (should_test_function("json")) test_json();
+    if (should_test_function("git_status")) test_git_status();
     // history_tests_t::test_history_speed();
 
     if (should_test_function("termsize")) termsize_tester_t::test();
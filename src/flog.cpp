@@ -3,6 +3,10 @@
 
 #include "flog.h"
 
+#include <syslog.h>
+
+#include <algorithm>
+#include <cstring>
 #include <vector>
 
 #include "common.h"
@@ -22,7 +26,10 @@ static std::vector<category_t *> s_all_categories;
 
 /// When a category is instantiated it adds itself to the 'all' list.
 category_t::category_t(const wchar_t *name, const wchar_t *desc, bool enabled)
-    : name(name), description(desc), enabled(enabled) {
+    : name(name),
+      description(desc),
+      compiled_out(category_compiled_out(name)),
+      enabled(enabled && !compiled_out) {
     s_all_categories.push_back(this);
 }
 
@@ -34,28 +41,80 @@ logger_t::logger_t() : file_(stderr) {}
 
 owning_lock<logger_t> g_logger;
 
-void logger_t::log1(const wchar_t *s) { std::fputws(s, file_); }
+void logger_t::set_syslog() {
+    if (!syslog_) openlog("fish", LOG_PID, LOG_USER);
+    syslog_ = true;
+}
+
+/// The number of lines kept for crash reports, and how much of each.
+static constexpr size_t k_recent_line_count = 32;
+static constexpr size_t k_recent_line_length = 256;
+
+/// The most recent lines of output, oldest first starting at s_recent_line_next, and the
+/// categories that logged them. These are plain arrays so they can be read from a signal handler.
+/// They are protected by the g_logger lock.
+static char s_recent_lines[k_recent_line_count][k_recent_line_length];
+static const category_t *s_recent_categories[k_recent_line_count];
+static size_t s_recent_line_next = 0;
+
+/// Write out each complete line in line_, and remember it for crash reports.
+void logger_t::flush_lines() {
+    size_t start = 0, end;
+    while ((end = line_.find(L'\n', start)) != wcstring::npos) {
+        std::string narrow = wcs2string(line_.substr(start, end - start));
+        if (syslog_) {
+            syslog(LOG_INFO, "%s", narrow.c_str());
+        } else {
+            // Note glibc prohibits mixing narrow and wide I/O, so always use wide-printing
+            // functions. See #5900.
+            std::fputws(line_.substr(start, end + 1 - start).c_str(), file_);
+        }
+
+        char *recent = s_recent_lines[s_recent_line_next];
+        size_t len = std::min(narrow.size(), k_recent_line_length - 1);
+        std::memcpy(recent, narrow.data(), len);
+        recent[len] = '\0';
+        s_recent_categories[s_recent_line_next] = category_;
+        s_recent_line_next = (s_recent_line_next + 1) % k_recent_line_count;
+        start = end + 1;
+    }
+    line_.erase(0, start);
+}
+
+void logger_t::log1(const wchar_t *s) {
+    line_.append(s);
+    flush_lines();
+}
 
 void logger_t::log1(const char *s) {
-    // Note glibc prohibits mixing narrow and wide I/O, so always use wide-printing functions.
-    // See #5900.
-    std::fwprintf(file_, L"%s", s);
+    line_.append(str2wcstring(s));
+    flush_lines();
 }
 
-void logger_t::log1(wchar_t c) { std::fputwc(c, file_); }
+void logger_t::log1(wchar_t c) {
+    line_.push_back(c);
+    if (c == L'\n') flush_lines();
+}
 
-void logger_t::log1(char c) { std::fwprintf(file_, L"%c", c); }
+void logger_t::log1(char c) { log1(static_cast<wchar_t>(c)); }
 
-void logger_t::log1(int64_t v) { std::fwprintf(file_, L"%lld", v); }
+void logger_t::log_value(const wchar_t *s) {
+    log1(escape_string(s, ESCAPE_ALL).c_str());
+}
 
-void logger_t::log1(uint64_t v) { std::fwprintf(file_, L"%llu", v); }
+void logger_t::log_value(const char *s) { log_value(str2wcstring(s).c_str()); }
+
+void logger_t::log1(int64_t v) { log1(std::to_wstring(v).c_str()); }
+
+void logger_t::log1(uint64_t v) { log1(std::to_wstring(v).c_str()); }
 
 void logger_t::log_fmt(const category_t &cat, const wchar_t *fmt, ...) {
     va_list va;
     va_start(va, fmt);
+    category_ = &cat;
     log1(cat.name);
     log1(L": ");
-    std::vfwprintf(file_, fmt, va);
+    log1(vformat_string(fmt, va).c_str());
     log1(L'\n');
     va_end(va);
 }
@@ -90,37 +149,62 @@ void logger_t::log_fmt(const category_t &cat, const char *fmt, ...) {
 using namespace flog_details;
 
 /// For each category, if its name matches the wildcard, set its enabled to the given sense.
-static void apply_one_wildcard(const wcstring &wc_esc, bool sense) {
+/// \return whether any category matched.
+static bool apply_one_wildcard(const wcstring &wc_esc, bool sense) {
     wcstring wc = parse_util_unescape_wildcards(wc_esc);
     bool match_found = false;
     for (category_t *cat : s_all_categories) {
-        if (wildcard_match(cat->name, wc)) {
+        if (!cat->compiled_out && wildcard_match(cat->name, wc)) {
             cat->enabled = sense;
             match_found = true;
         }
     }
-    if (!match_found) {
-        fprintf(stderr, "Failed to match debug category: %ls\n", wc_esc.c_str());
-    }
+    return match_found;
 }
 
-void activate_flog_categories_by_pattern(const wcstring &inwc) {
+void activate_flog_categories_by_pattern(const wcstring &inwc, wcstring_list_t *out_unmatched) {
     // Normalize underscores to dashes, allowing the user to be sloppy.
     wcstring wc = inwc;
     std::replace(wc.begin(), wc.end(), L'_', L'-');
     for (const wcstring &s : split_string(wc, L',')) {
-        if (string_prefixes_string(L"-", s)) {
-            apply_one_wildcard(s.substr(1), false);
+        bool sense = !string_prefixes_string(L"-", s);
+        wcstring name = sense ? s : s.substr(1);
+        if (apply_one_wildcard(name, sense)) continue;
+        if (out_unmatched) {
+            out_unmatched->push_back(name);
         } else {
-            apply_one_wildcard(s, true);
+            fprintf(stderr, "Failed to match debug category: %ls\n", name.c_str());
         }
     }
 }
 
 void set_flog_output_file(FILE *f) { g_logger.acquire()->set_file(f); }
 
+void set_flog_output_syslog() { g_logger.acquire()->set_syslog(); }
+
 void log_extra_to_flog_file(const wcstring &s) { g_logger.acquire()->log_extra(s.c_str()); }
 
+void flog_write_recent_lines(int fd) {
+    // Don't take the lock: this is called from a signal handler, perhaps while the lock is held.
+    for (size_t i = 0; i < k_recent_line_count; i++) {
+        const char *line = s_recent_lines[(s_recent_line_next + i) % k_recent_line_count];
+        if (!line[0]) continue;
+        write_loop(fd, line, std::strlen(line));
+        write_loop(fd, "\n", 1);
+    }
+}
+
+std::vector<flog_recent_line_t> flog_get_recent_lines() {
+    auto logger = g_logger.acquire();
+    std::vector<flog_recent_line_t> result;
+    for (size_t i = 0; i < k_recent_line_count; i++) {
+        size_t idx = (s_recent_line_next + i) % k_recent_line_count;
+        if (!s_recent_lines[idx][0]) continue;
+        result.push_back({s_recent_categories[idx], str2wcstring(s_recent_lines[idx])});
+    }
+    return result;
+}
+
 std::vector<const category_t *> get_flog_categories() {
     std::vector<const category_t *> result(s_all_categories.begin(), s_all_categories.end());
     std::sort(result.begin(), result.end(), [](const category_t *a, const category_t *b) {
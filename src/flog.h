@@ -18,8 +18,35 @@ using wcstring_list_t = std::vector<wcstring>;
 template <typename T>
 class owning_lock;
 
+/// A comma-separated list of categories which are left out of the build, set by CMake.
+#ifndef FISH_FLOG_DISABLED_CATEGORIES
+#define FISH_FLOG_DISABLED_CATEGORIES ""
+#endif
+
 namespace flog_details {
 
+/// \return whether \p name is the first item of the comma-separated \p list. Underscores in
+/// \p name match dashes, so both the category's field and its name can be used.
+template <typename Char>
+constexpr bool category_list_starts_with(const Char *name, const char *list) {
+    return *name == 0 ? (*list == '\0' || *list == ',')
+                      : (*name == static_cast<Char>(*list) || (*name == '_' && *list == '-')) &&
+                            category_list_starts_with(name + 1, list + 1);
+}
+
+/// \return \p list after its first item and comma.
+constexpr const char *category_list_rest(const char *list) {
+    return *list == '\0' ? list : *list == ',' ? list + 1 : category_list_rest(list + 1);
+}
+
+/// \return whether the category \p name is compiled out, i.e. in FISH_FLOG_DISABLED_CATEGORIES.
+template <typename Char>
+constexpr bool category_compiled_out(const Char *name,
+                                     const char *list = FISH_FLOG_DISABLED_CATEGORIES) {
+    return *list != '\0' && (category_list_starts_with(name, list) ||
+                              category_compiled_out(name, category_list_rest(list)));
+}
+
 class category_list_t;
 class category_t {
     friend category_list_t;
@@ -32,10 +59,20 @@ class category_t {
     /// A (non-localized) description of the category.
     const wchar_t *const description;
 
+    /// Whether the category is compiled out. If so, it can't be enabled.
+    const bool compiled_out;
+
     /// Whether the category is enabled.
     relaxed_atomic_bool_t enabled;
 };
 
+/// A key=value field of a log message, see flog_kv().
+template <typename T>
+struct kv_t {
+    const char *key;
+    const T &value;
+};
+
 class category_list_t {
     category_list_t() = default;
 
@@ -57,6 +94,7 @@ class category_list_t {
     category_t config{L"config", L"Finding and reading configuration"};
 
     category_t event{L"event", L"Firing events"};
+    category_t event_trace{L"event-trace", L"Every event dispatch and the handlers it runs"};
 
     category_t exec_job_status{L"exec-job-status", L"Jobs changing status"};
 
@@ -64,8 +102,11 @@ class category_list_t {
 
     category_t exec_fork{L"exec-fork", L"Calls to fork()"};
 
+    category_t exec_spawn{L"exec-spawn", L"Choosing between posix_spawn and fork"};
+
     category_t output_invalid{L"output-invalid", L"Trying to print invalid output"};
     category_t ast_construction{L"ast-construction", L"Parsing fish AST"};
+    category_t ast_cache{L"ast-cache", L"Caching the parsed form of autoloaded files"};
 
     category_t proc_job_run{L"proc-job-run", L"Jobs getting started or continued"};
 
@@ -78,6 +119,7 @@ class category_list_t {
 
     category_t proc_reap_external{L"proc-reap-external", L"Reaping external (forked) processes"};
     category_t proc_pgroup{L"proc-pgroup", L"Process groups"};
+    category_t proc_cgroup{L"proc-cgroup", L"Per-job cgroups for resource accounting"};
 
     category_t env_locale{L"env-locale", L"Changes to locale variables"};
 
@@ -113,6 +155,16 @@ class category_list_t {
 class logger_t {
     FILE *file_;
 
+    /// If set, messages go to syslog instead of the file.
+    bool syslog_{false};
+
+    /// The text of the current message, which is output once its line is complete.
+    wcstring line_;
+
+    /// The category of the current message, or null for log_extra().
+    const category_t *category_{nullptr};
+    void flush_lines();
+
     void log1(const wchar_t *);
     void log1(const char *);
     void log1(wchar_t);
@@ -133,6 +185,30 @@ class logger_t {
         }
     }
 
+    /// A callable is called, and what it returns is logged.
+    template <typename F>
+    auto log1(const F &func) -> decltype(func(), void()) {
+        log1(func());
+    }
+
+    /// Values of fields are quoted if needed, so the fields can be told apart.
+    void log_value(const wchar_t *s);
+    void log_value(const char *s);
+    void log_value(const wcstring &s) { log_value(s.c_str()); }
+    void log_value(const std::string &s) { log_value(s.c_str()); }
+
+    template <typename T>
+    void log_value(const T &v) {
+        log1(v);
+    }
+
+    template <typename T>
+    void log1(const kv_t<T> &kv) {
+        log1(kv.key);
+        log1('=');
+        log_value(kv.value);
+    }
+
     template <typename T>
     void log_args_impl(const T &arg) {
         log1(arg);
@@ -146,12 +222,18 @@ class logger_t {
     }
 
    public:
-    void set_file(FILE *f) { file_ = f; }
+    void set_file(FILE *f) {
+        file_ = f;
+        syslog_ = false;
+    }
+
+    void set_syslog();
 
     logger_t();
 
     template <typename... Args>
     void log_args(const category_t &cat, const Args &...args) {
+        category_ = &cat;
         log1(cat.name);
         log1(": ");
         log_args_impl(args...);
@@ -162,20 +244,36 @@ class logger_t {
     void log_fmt(const category_t &cat, const char *fmt, ...);
 
     // Log outside of the usual flog usage.
-    void log_extra(const wchar_t *s) { log1(s); }
+    void log_extra(const wchar_t *s) {
+        category_ = nullptr;
+        log1(s);
+    }
 };
 
 extern owning_lock<logger_t> g_logger;
 
 }  // namespace flog_details
 
+/// \return a key=value field for FLOG, like `FLOG(exec_fork, "Fork", flog_kv("pid", pid))`. The
+/// value may be a callable, which is only called if the message is logged.
+template <typename T>
+flog_details::kv_t<T> flog_kv(const char *key, const T &value) {
+    return flog_details::kv_t<T>{key, value};
+}
+
 /// Set the active flog categories according to the given wildcard \p wc.
-void activate_flog_categories_by_pattern(const wcstring &wc);
+/// Patterns which match no category are added to \p out_unmatched if it is not null, and
+/// otherwise reported on stderr.
+void activate_flog_categories_by_pattern(const wcstring &wc,
+                                         wcstring_list_t *out_unmatched = nullptr);
 
 /// Set the file that flog should output to.
 /// flog does not close this file.
 void set_flog_output_file(FILE *f);
 
+/// Send flog output to syslog, which is the journal on systems with systemd.
+void set_flog_output_syslog();
+
 /// \return a list of all categories, sorted by name.
 std::vector<const flog_details::category_t *> get_flog_categories();
 
@@ -183,11 +281,30 @@ std::vector<const flog_details::category_t *> get_flog_categories();
 /// This is used by the tracing machinery.
 void log_extra_to_flog_file(const wcstring &s);
 
+/// Write the most recent lines of output to \p fd, even if they went to syslog or a file.
+/// This is async-signal safe, for crash reports.
+void flog_write_recent_lines(int fd);
+
+/// A line of recent output, with the category that logged it or null.
+struct flog_recent_line_t {
+    const flog_details::category_t *category;
+    wcstring text;
+};
+
+/// \return the most recent lines of output, oldest first.
+std::vector<flog_recent_line_t> flog_get_recent_lines();
+
+/// \return whether the category \p wht is compiled out. This is a constant expression.
+#define flog_compiled_out(wht) \
+    (std::integral_constant<bool, flog_details::category_compiled_out(#wht)>::value)
+
 /// Output to the fish log a sequence of arguments, separated by spaces, and ending with a newline.
+/// Fields made by flog_kv() are output as key=value. The arguments are only evaluated if the
+/// category is enabled, and the optimizer drops the whole call if the category is compiled out.
 /// We save and restore errno because we don't want this to affect other code.
 #define FLOG(wht, ...)                                                        \
     do {                                                                      \
-        if (flog_details::category_list_t::g_instance->wht.enabled) {         \
+        if (should_flog(wht)) {                                               \
             auto old_errno = errno;                                           \
             flog_details::g_logger.acquire()->log_args(                       \
                 flog_details::category_list_t::g_instance->wht, __VA_ARGS__); \
@@ -198,7 +315,7 @@ void log_extra_to_flog_file(const wcstring &s);
 /// Output to the fish log a printf-style formatted string.
 #define FLOGF(wht, ...)                                                       \
     do {                                                                      \
-        if (flog_details::category_list_t::g_instance->wht.enabled) {         \
+        if (should_flog(wht)) {                                               \
             auto old_errno = errno;                                           \
             flog_details::g_logger.acquire()->log_fmt(                        \
                 flog_details::category_list_t::g_instance->wht, __VA_ARGS__); \
@@ -208,4 +325,5 @@ void log_extra_to_flog_file(const wcstring &s);
 
 #endif
 
-#define should_flog(wht) (flog_details::category_list_t::g_instance->wht.enabled)
+#define should_flog(wht) \
+    (!flog_compiled_out(wht) && flog_details::category_list_t::g_instance->wht.enabled)
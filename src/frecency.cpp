@@ -0,0 +1,173 @@
+// The database of directories which were changed to, for `cd --jump`.
+#include "config.h"  // IWYU pragma: keep
+
+#include "frecency.h"
+
+#include <fcntl.h>
+#include <sys/file.h>
+#include <unistd.h>
+#include <wctype.h>
+
+#include <algorithm>
+#include <cstdlib>
+#include <string>
+#include <vector>
+
+#include "common.h"
+#include "fallback.h"  // IWYU pragma: keep
+#include "fds.h"
+#include "path.h"
+#include "wcstringutil.h"
+#include "wutil.h"  // IWYU pragma: keep
+
+/// Once the ranks of all directories add up to more than this, they are decreased.
+static constexpr unsigned long k_max_total_rank = 9000;
+
+/// The name of the database in the data directory.
+static const wchar_t *const k_frecency_file_name = L"/cd_frecency";
+
+double frecent_dir_t::frecency(time_t now) const {
+    time_t age = now - last_visit;
+    double rank = this->rank;
+    if (age < 60 * 60) return rank * 4;
+    if (age < 24 * 60 * 60) return rank * 2;
+    if (age < 7 * 24 * 60 * 60) return rank / 2;
+    return rank / 4;
+}
+
+/// \return the path of the database, or none() if there is no data directory.
+static maybe_t<wcstring> frecency_file_path() {
+    wcstring path;
+    if (!path_get_data(path)) return none();
+    return path + k_frecency_file_name;
+}
+
+/// Parse the contents of the database. Each line is the rank, the time of the last visit and the
+/// path, separated by tabs. Lines which can't be parsed are skipped.
+static std::vector<frecent_dir_t> parse_frecency_file(const std::string &contents) {
+    std::vector<frecent_dir_t> dirs;
+    size_t line_start = 0;
+    while (line_start < contents.size()) {
+        size_t line_end = contents.find('\n', line_start);
+        if (line_end == std::string::npos) line_end = contents.size();
+        std::string line = contents.substr(line_start, line_end - line_start);
+        line_start = line_end + 1;
+
+        size_t tab1 = line.find('\t');
+        size_t tab2 = tab1 == std::string::npos ? tab1 : line.find('\t', tab1 + 1);
+        if (tab2 == std::string::npos || tab2 + 1 == line.size() || line.at(tab2 + 1) != '/') {
+            continue;
+        }
+        frecent_dir_t dir;
+        dir.rank = std::strtoul(line.c_str(), nullptr, 10);
+        dir.last_visit = static_cast<time_t>(std::strtoll(line.c_str() + tab1 + 1, nullptr, 10));
+        dir.path = str2wcstring(line.substr(tab2 + 1));
+        if (dir.rank > 0) dirs.push_back(std::move(dir));
+    }
+    return dirs;
+}
+
+static std::string serialize_frecency_file(const std::vector<frecent_dir_t> &dirs) {
+    std::string result;
+    for (const frecent_dir_t &dir : dirs) {
+        result.append(std::to_string(dir.rank));
+        result.push_back('\t');
+        result.append(std::to_string(static_cast<long long>(dir.last_visit)));
+        result.push_back('\t');
+        result.append(wcs2string(dir.path));
+        result.push_back('\n');
+    }
+    return result;
+}
+
+/// Read the rest of the file at \p fd into \p out. \return whether that worked.
+static bool read_fd(int fd, std::string *out) {
+    out->clear();
+    char buff[4096];
+    ssize_t amt;
+    while ((amt = read_loop(fd, buff, sizeof buff)) > 0) out->append(buff, amt);
+    return amt == 0;
+}
+
+void frecency_add_visit(const wcstring &dir) {
+    // A path with a newline can't be stored.
+    if (dir.empty() || dir.front() != L'/' || dir.find(L'\n') != wcstring::npos) return;
+    maybe_t<wcstring> path = frecency_file_path();
+    if (!path) return;
+
+    autoclose_fd_t fd{wopen_cloexec(*path, O_RDWR | O_CREAT, 0600)};
+    if (!fd.valid()) return;
+    // Hold the lock while the file is read and rewritten, so other shells don't lose their visits.
+    // If it can't be locked, it is updated anyway.
+    ignore_result(flock(fd.fd(), LOCK_EX));
+
+    std::string contents;
+    if (!read_fd(fd.fd(), &contents)) return;
+    std::vector<frecent_dir_t> dirs = parse_frecency_file(contents);
+
+    auto found = std::find_if(dirs.begin(), dirs.end(),
+                              [&](const frecent_dir_t &d) { return d.path == dir; });
+    if (found == dirs.end()) {
+        frecent_dir_t new_dir;
+        new_dir.path = dir;
+        dirs.push_back(std::move(new_dir));
+        found = dirs.end() - 1;
+    }
+    found->rank++;
+    found->last_visit = time(nullptr);
+
+    unsigned long total_rank = 0;
+    for (const frecent_dir_t &d : dirs) total_rank += d.rank;
+    if (total_rank > k_max_total_rank) {
+        for (frecent_dir_t &d : dirs) d.rank = d.rank * 99 / 100;
+        dirs.erase(std::remove_if(dirs.begin(), dirs.end(),
+                                  [](const frecent_dir_t &d) { return d.rank == 0; }),
+                   dirs.end());
+    }
+
+    std::string new_contents = serialize_frecency_file(dirs);
+    if (lseek(fd.fd(), 0, SEEK_SET) == -1 || ftruncate(fd.fd(), 0) == -1) return;
+    ignore_result(write_loop(fd.fd(), new_contents.data(), new_contents.size()));
+}
+
+std::vector<frecent_dir_t> frecency_load() {
+    maybe_t<wcstring> path = frecency_file_path();
+    if (!path) return {};
+    autoclose_fd_t fd{wopen_cloexec(*path, O_RDONLY)};
+    std::string contents;
+    if (!fd.valid() || !read_fd(fd.fd(), &contents)) return {};
+    return parse_frecency_file(contents);
+}
+
+/// \return whether \p path matches \p patterns, as described for frecency_match().
+static bool path_matches(const wcstring &path, const wcstring_list_t &patterns) {
+    size_t last_component = path.rfind(L'/');
+    last_component = last_component == wcstring::npos ? 0 : last_component + 1;
+    size_t pos = 0;
+    for (size_t i = 0; i < patterns.size(); i++) {
+        const wcstring &pattern = patterns.at(i);
+        if (i + 1 == patterns.size()) pos = std::max(pos, last_component);
+        bool ignore_case = std::none_of(pattern.begin(), pattern.end(),
+                                        [](wchar_t c) { return iswupper(c); });
+        size_t found = ignore_case ? ifind(path.substr(pos), pattern) : path.find(pattern, pos);
+        if (found == wcstring::npos) return false;
+        if (ignore_case) found += pos;
+        pos = found + pattern.size();
+    }
+    return true;
+}
+
+wcstring_list_t frecency_match(const std::vector<frecent_dir_t> &dirs,
+                               const wcstring_list_t &patterns, time_t now) {
+    std::vector<const frecent_dir_t *> matches;
+    for (const frecent_dir_t &dir : dirs) {
+        if (path_matches(dir.path, patterns)) matches.push_back(&dir);
+    }
+    std::stable_sort(matches.begin(), matches.end(),
+                     [=](const frecent_dir_t *a, const frecent_dir_t *b) {
+                         return a->frecency(now) > b->frecency(now);
+                     });
+    wcstring_list_t result;
+    for (const frecent_dir_t *dir : matches) result.push_back(dir->path);
+    return result;
+}
@@ -0,0 +1,44 @@
+// The database of directories which were changed to, for `cd --jump`.
+//
+// Every visit to a directory increases its rank. Once the ranks add up to more than a limit, all
+// of them are decreased, and directories which were rarely visited are forgotten. Directories are
+// ranked by their "frecency": the rank, weighted by how recently they were visited.
+#ifndef FISH_FRECENCY_H
+#define FISH_FRECENCY_H
+
+#include <time.h>
+
+#include <string>
+#include <vector>
+
+#include "common.h"
+
+/// A directory in the database.
+struct frecent_dir_t {
+    /// The absolute path of the directory.
+    wcstring path;
+
+    /// Increased by every visit, and decreased for all directories as the database ages.
+    unsigned long rank{0};
+
+    /// When the directory was last visited.
+    time_t last_visit{0};
+
+    /// \return the rank, weighted by how long before \p now the directory was last visited.
+    double frecency(time_t now) const;
+};
+
+/// Record a visit to the absolute directory \p dir in the database, which is in the data
+/// directory.
+void frecency_add_visit(const wcstring &dir);
+
+/// \return the directories in the database.
+std::vector<frecent_dir_t> frecency_load();
+
+/// \return the paths of \p dirs which match \p patterns, with the highest frecency at \p now
+/// first. The patterns must be found in the path in order, the last one in the last component.
+/// Patterns without uppercase letters match regardless of case. No patterns match any directory.
+wcstring_list_t frecency_match(const std::vector<frecent_dir_t> &dirs,
+                               const wcstring_list_t &patterns, time_t now);
+
+#endif
@@ -319,7 +319,7 @@ int function_get_definition_lineno(const wcstring &name) {
     return 1 + std::count(source.begin(), source.begin() + func_start, L'\n');
 }
 
-void function_invalidate_path() {
+void function_invalidate_path(const environment_t &vars) {
     // Remove all autoloaded functions and update the autoload path.
     // Note we don't want to risk removal during iteration; we expect this to be called
     // infrequently.
@@ -334,6 +334,7 @@ void function_invalidate_path() {
         funcset->remove(name);
     }
     funcset->autoloader.clear();
+    funcset->autoloader.update_paths(vars);
 }
 
 /// Return a definition of the specified function. Used by the functions builtin.
@@ -372,6 +373,10 @@ wcstring functions_def(const wcstring &name) {
         out.append(L" --no-scope-shadowing");
     }
 
+    if (props->run_async) {
+        out.append(L" --async");
+    }
+
     for (const auto &next : ev) {
         const event_description_t &d = next->desc;
         switch (d.type) {
@@ -398,6 +403,16 @@ wcstring functions_def(const wcstring &name) {
                 append_format(out, L" --on-event %ls", d.str_param1.c_str());
                 break;
             }
+            case event_type_t::file_change: {
+                append_format(out, L" --on-file-change %ls",
+                              escape_string(d.str_param1, ESCAPE_ALL).c_str());
+                break;
+            }
+            case event_type_t::interval: {
+                append_format(out, L" --on-interval %ls",
+                              event_format_interval(d.param1.interval_ms).c_str());
+                break;
+            }
             case event_type_t::any:
             default: {
                 DIE("unexpected next->type");
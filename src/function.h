@@ -37,6 +37,10 @@ struct function_properties_t {
 
     /// Set to true if invoking this function shadows the variables of the underlying function.
     bool shadow_scope{true};
+
+    /// Set to true if this function's event handlers run in another fish process, when the shell
+    /// is interactive.
+    bool run_async{false};
 };
 
 using function_properties_ref_t = std::shared_ptr<const function_properties_t>;
@@ -99,8 +103,8 @@ int function_get_definition_lineno(const wcstring &name);
 /// is successful.
 bool function_copy(const wcstring &name, const wcstring &new_name);
 
-/// Observes that fish_function_path has changed.
-void function_invalidate_path();
+/// Observes that fish_function_path has changed, to the value in \p vars.
+void function_invalidate_path(const environment_t &vars);
 
 wcstring functions_def(const wcstring &name);
 #endif
@@ -12,9 +12,11 @@ features_t::features_t() = default;
 features_t features_t::global_features;
 
 const features_t::metadata_t features_t::metadata[features_t::flag_count] = {
-    {stderr_nocaret, L"stderr-nocaret", L"3.0", L"^ no longer redirects stderr"},
-    {qmark_noglob, L"qmark-noglob", L"3.0", L"? no longer globs"},
-    {string_replace_backslash, L"regex-easyesc", L"3.1", L"string replace -r needs fewer \\'s"},
+    {stderr_nocaret, L"stderr-nocaret", L"3.0", L"^ no longer redirects stderr", false},
+    {qmark_noglob, L"qmark-noglob", L"3.0", L"? no longer globs", false},
+    {string_replace_backslash, L"regex-easyesc", L"3.1", L"string replace -r needs fewer \\'s",
+     true},
+    {auto_cd, L"auto-cd", L"3.2", L"a directory name as a command cds to it", true},
 };
 
 const struct features_t::metadata_t *features_t::metadata_for(const wchar_t *name) {
@@ -25,6 +27,13 @@ const struct features_t::metadata_t *features_t::metadata_for(const wchar_t *nam
     return nullptr;
 }
 
+bool features_t::set_at_runtime(const wchar_t *name, bool value) {
+    const metadata_t *md = metadata_for(name);
+    if (!md || !md->runtime) return false;
+    this->set(md->flag, value);
+    return true;
+}
+
 void features_t::set_from_string(const wcstring &str) {
     wcstring_list_t entries = split_string(str, L',');
     const wchar_t *whitespace = L"\t\n\v\f\r ";
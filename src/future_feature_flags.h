@@ -11,7 +11,9 @@
 
 class features_t {
    public:
-    /// The list of flags.
+    /// The list of flags. To add a flag, add it here and its metadata in
+    /// future_feature_flags.cpp; it is then listed by `status features` and can be set with
+    /// `fish --features` and $fish_features.
     enum flag_t {
         /// Whether ^ is supported for stderr redirection.
         stderr_nocaret,
@@ -22,6 +24,9 @@ class features_t {
         /// Whether string replace -r double-unescapes the replacement.
         string_replace_backslash,
 
+        /// Whether a command which is the name of a directory changes to it.
+        auto_cd,
+
         /// The number of flags.
         flag_count
     };
@@ -57,6 +62,10 @@ class features_t {
 
         /// User-presentable description of the feature flag.
         const wchar_t *description;
+
+        /// Whether the flag may be changed while fish is running. Flags which change how code is
+        /// parsed must only be set at startup, so that all code is parsed the same way.
+        bool runtime;
     };
 
     /// The metadata, indexed by flag.
@@ -65,6 +74,10 @@ class features_t {
     /// Return the metadata for a particular name, or nullptr if not found.
     static const struct metadata_t *metadata_for(const wchar_t *name);
 
+    /// Set the flag named \p name while fish is running, e.g. for the rest of a session.
+    /// \return false if there is no such flag, or if it may only be set at startup.
+    bool set_at_runtime(const wchar_t *name, bool value);
+
     /// The singleton shared feature set.
     static features_t global_features;
 
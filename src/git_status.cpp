@@ -0,0 +1,1366 @@
+// Reading the state of a git repository without running git, for fish_git_status.
+//
+// The formats are described in git's Documentation/technical: index-format.txt for the index,
+// pack-format.txt for packs and their indexes, and gitignore(5) for ignored files.
+#include "config.h"  // IWYU pragma: keep
+
+#include "git_status.h"
+
+#include <dirent.h>
+#include <fcntl.h>
+#include <sys/mman.h>
+#include <sys/stat.h>
+#include <unistd.h>
+#include <zlib.h>
+
+#include <algorithm>
+#include <cerrno>
+#include <cstdint>
+#include <cstdlib>
+#include <cstring>
+#include <map>
+#include <memory>
+#include <set>
+#include <utility>
+
+#include "common.h"
+#include "fds.h"
+#include "wutil.h"  // IWYU pragma: keep
+
+/// The size of a SHA-1 object id.
+static const size_t oid_size = 20;
+
+/// The mode of a directory in a tree object.
+static const uint32_t tree_mode = 040000;
+
+/// The mode of a submodule in a tree or the index.
+static const uint32_t gitlink_mode = 0160000;
+
+namespace {
+/// Computes SHA-1 hashes. Git names objects by them.
+class sha1_t {
+   public:
+    void update(const void *data, size_t len) {
+        const auto *bytes = static_cast<const unsigned char *>(data);
+        for (size_t i = 0; i < len; i++) {
+            block_[block_len_++] = bytes[i];
+            if (block_len_ == sizeof block_) {
+                process_block();
+                block_len_ = 0;
+            }
+        }
+        total_len_ += len;
+    }
+
+    std::string digest() {
+        uint64_t bits = total_len_ * 8;
+        unsigned char pad = 0x80;
+        update(&pad, 1);
+        pad = 0;
+        while (block_len_ != 56) update(&pad, 1);
+        unsigned char len_bytes[8];
+        for (int i = 0; i < 8; i++) len_bytes[i] = static_cast<unsigned char>(bits >> (56 - 8 * i));
+        update(len_bytes, sizeof len_bytes);
+        std::string result;
+        for (uint32_t word : state_) {
+            for (int shift = 24; shift >= 0; shift -= 8) {
+                result.push_back(static_cast<char>((word >> shift) & 0xff));
+            }
+        }
+        return result;
+    }
+
+   private:
+    static uint32_t rotl(uint32_t x, int n) { return (x << n) | (x >> (32 - n)); }
+
+    void process_block() {
+        uint32_t w[80];
+        for (int i = 0; i < 16; i++) {
+            w[i] = uint32_t(block_[4 * i]) << 24 | uint32_t(block_[4 * i + 1]) << 16 |
+                   uint32_t(block_[4 * i + 2]) << 8 | uint32_t(block_[4 * i + 3]);
+        }
+        for (int i = 16; i < 80; i++) w[i] = rotl(w[i - 3] ^ w[i - 8] ^ w[i - 14] ^ w[i - 16], 1);
+        uint32_t a = state_[0], b = state_[1], c = state_[2], d = state_[3], e = state_[4];
+        for (int i = 0; i < 80; i++) {
+            uint32_t f, k;
+            if (i < 20) {
+                f = (b & c) | (~b & d);
+                k = 0x5A827999;
+            } else if (i < 40) {
+                f = b ^ c ^ d;
+                k = 0x6ED9EBA1;
+            } else if (i < 60) {
+                f = (b & c) | (b & d) | (c & d);
+                k = 0x8F1BBCDC;
+            } else {
+                f = b ^ c ^ d;
+                k = 0xCA62C1D6;
+            }
+            uint32_t temp = rotl(a, 5) + f + e + k + w[i];
+            e = d;
+            d = c;
+            c = rotl(b, 30);
+            b = a;
+            a = temp;
+        }
+        state_[0] += a;
+        state_[1] += b;
+        state_[2] += c;
+        state_[3] += d;
+        state_[4] += e;
+    }
+
+    uint32_t state_[5] = {0x67452301, 0xEFCDAB89, 0x98BADCFE, 0x10325476, 0xC3D2E1F0};
+    unsigned char block_[64];
+    size_t block_len_{0};
+    uint64_t total_len_{0};
+};
+}  // namespace
+
+std::string git_sha1(const std::string &data) {
+    sha1_t sha1;
+    sha1.update(data.data(), data.size());
+    return sha1.digest();
+}
+
+/// \return the raw object id \p oid in hex.
+static std::string oid_to_hex(const std::string &oid) {
+    static const char digits[] = "0123456789abcdef";
+    std::string result;
+    for (unsigned char c : oid) {
+        result.push_back(digits[c >> 4]);
+        result.push_back(digits[c & 0xf]);
+    }
+    return result;
+}
+
+/// \return whether \p str starts with \p prefix.
+static bool has_prefix(const std::string &prefix, const std::string &str) {
+    return str.compare(0, prefix.size(), prefix) == 0;
+}
+
+/// \return whether \p str ends with \p suffix.
+static bool has_suffix(const std::string &suffix, const std::string &str) {
+    return str.size() >= suffix.size() &&
+           str.compare(str.size() - suffix.size(), suffix.size(), suffix) == 0;
+}
+
+/// \return the value of the hex digit \p c, or -1 if it is not one.
+static int hex_digit_value(char c) {
+    if (c >= '0' && c <= '9') return c - '0';
+    if (c >= 'a' && c <= 'f') return c - 'a' + 10;
+    if (c >= 'A' && c <= 'F') return c - 'A' + 10;
+    return -1;
+}
+
+/// \return the raw object id of the hex string \p hex, or none() if it is not one.
+static maybe_t<std::string> oid_from_hex(const std::string &hex) {
+    if (hex.size() != 2 * oid_size) return none();
+    std::string result;
+    for (size_t i = 0; i < hex.size(); i += 2) {
+        int hi = hex_digit_value(hex[i]), lo = hex_digit_value(hex[i + 1]);
+        if (hi < 0 || lo < 0) return none();
+        result.push_back(static_cast<char>(hi << 4 | lo));
+    }
+    return result;
+}
+
+/// \return the first line of the file at \p path without surrounding whitespace, or none() if it
+/// can't be read.
+static maybe_t<std::string> read_first_line(const std::string &path) {
+    maybe_t<std::string> file = read_file_contents(path);
+    if (!file) return none();
+    std::string contents = file->substr(0, file->find('\n'));
+    size_t start = contents.find_first_not_of(" \t\r");
+    if (start == std::string::npos) return std::string();
+    size_t end = contents.find_last_not_of(" \t\r");
+    return contents.substr(start, end - start + 1);
+}
+
+/// \return the first line of the file at \p path, or an empty string if it can't be read.
+static std::string first_line_or_empty(const std::string &path) {
+    auto line = read_first_line(path);
+    return line ? *line : std::string();
+}
+
+static bool path_is_dir(const std::string &path) {
+    struct stat buf;
+    return stat(path.c_str(), &buf) == 0 && S_ISDIR(buf.st_mode);
+}
+
+static bool path_is_file(const std::string &path) {
+    struct stat buf;
+    return stat(path.c_str(), &buf) == 0 && S_ISREG(buf.st_mode);
+}
+
+static bool path_exists(const std::string &path) {
+    struct stat buf;
+    return lstat(path.c_str(), &buf) == 0;
+}
+
+/// \return \p path, relative to \p base if it is not absolute, without "." and ".." components.
+static std::string resolve_path(const std::string &base, const std::string &path) {
+    std::string joined = !path.empty() && path.front() == '/' ? path : base + "/" + path;
+    std::vector<std::string> components;
+    size_t start = 0;
+    while (start <= joined.size()) {
+        size_t end = joined.find('/', start);
+        if (end == std::string::npos) end = joined.size();
+        std::string component = joined.substr(start, end - start);
+        if (component == "..") {
+            if (!components.empty()) components.pop_back();
+        } else if (!component.empty() && component != ".") {
+            components.push_back(std::move(component));
+        }
+        start = end + 1;
+    }
+    std::string result;
+    for (const std::string &component : components) result += "/" + component;
+    return result.empty() ? "/" : result;
+}
+
+/// \return whether \p path looks like a git directory.
+static bool is_git_dir(const std::string &path) {
+    return path_is_file(path + "/HEAD") &&
+           (path_is_dir(path + "/objects") || path_is_file(path + "/commondir"));
+}
+
+/// \return the git directory a .git file at \p path points to, as submodules and linked work
+/// trees have them, or none() if it is not one.
+static maybe_t<std::string> read_gitdir_file(const std::string &path) {
+    auto line = read_first_line(path);
+    const char *prefix = "gitdir: ";
+    if (!line || line->compare(0, std::strlen(prefix), prefix) != 0) return none();
+    std::string dir = resolve_path(path.substr(0, path.rfind('/')), line->substr(8));
+    if (!is_git_dir(dir)) return none();
+    return dir;
+}
+
+/// \return the value of the config variable \p name, like "core.bare", from the config file
+/// \p path, or \p value if it is not set there.
+static maybe_t<std::string> read_config(const std::string &path, const std::string &name,
+                                        maybe_t<std::string> value) {
+    maybe_t<std::string> file = read_file_contents(path);
+    if (!file) return value;
+    const std::string &contents = *file;
+    std::string section;
+    size_t pos = 0;
+    while (pos < contents.size()) {
+        size_t end = contents.find('\n', pos);
+        if (end == std::string::npos) end = contents.size();
+        std::string line = contents.substr(pos, end - pos);
+        pos = end + 1;
+
+        size_t start = line.find_first_not_of(" \t\r");
+        if (start == std::string::npos || line[start] == '#' || line[start] == ';') continue;
+        if (line[start] == '[') {
+            // [section], [section "subsection"] or the old [section.subsection].
+            size_t close = line.rfind(']');
+            if (close == std::string::npos || close < start) continue;
+            std::string header = line.substr(start + 1, close - start - 1);
+            size_t quote = header.find('"');
+            std::string sub;
+            if (quote != std::string::npos) {
+                size_t quote_end = header.rfind('"');
+                if (quote_end > quote) sub = "." + header.substr(quote + 1, quote_end - quote - 1);
+                header = header.substr(0, quote);
+            }
+            while (!header.empty() && (header.back() == ' ' || header.back() == '\t')) {
+                header.pop_back();
+            }
+            std::transform(header.begin(), header.end(), header.begin(), ::tolower);
+            section = header + sub;
+            continue;
+        }
+
+        size_t key_end = line.find_first_of("= \t\r", start);
+        std::string key = line.substr(start, key_end - start);
+        std::transform(key.begin(), key.end(), key.begin(), ::tolower);
+        if (section + "." + key != name) continue;
+
+        size_t equals = line.find('=', start);
+        if (equals == std::string::npos) {
+            // A key without a value is a true boolean.
+            value = std::string("true");
+            continue;
+        }
+        std::string val;
+        bool quoted = false;
+        for (size_t i = equals + 1; i < line.size(); i++) {
+            char c = line[i];
+            if (c == '"') {
+                quoted = !quoted;
+            } else if (c == '\\' && i + 1 < line.size()) {
+                char next = line[++i];
+                val.push_back(next == 'n' ? '\n' : next == 't' ? '\t' : next);
+            } else if ((c == '#' || c == ';') && !quoted) {
+                break;
+            } else {
+                val.push_back(c);
+            }
+        }
+        size_t val_start = val.find_first_not_of(" \t\r");
+        size_t val_end = val.find_last_not_of(" \t\r");
+        value = val_start == std::string::npos ? std::string()
+                                               : val.substr(val_start, val_end - val_start + 1);
+    }
+    return value;
+}
+
+/// \return the value of the config variable \p name of \p repo, or none() if it is not set.
+static maybe_t<std::string> get_config(const git_repo_t &repo, const std::string &name) {
+    maybe_t<std::string> value;
+    for (const std::string &path : repo.user_config_files) value = read_config(path, name, value);
+    return read_config(repo.common_dir + "/config", name, value);
+}
+
+/// \return the boolean config variable \p name of \p repo, or \p def if it is not set.
+static bool get_config_bool(const git_repo_t &repo, const std::string &name, bool def) {
+    auto value = get_config(repo, name);
+    if (!value) return def;
+    std::string lower = *value;
+    std::transform(lower.begin(), lower.end(), lower.begin(), ::tolower);
+    if (lower == "true" || lower == "yes" || lower == "on") return true;
+    if (lower == "false" || lower == "no" || lower == "off" || lower.empty()) return false;
+    return std::strtol(lower.c_str(), nullptr, 10) != 0;
+}
+
+maybe_t<git_repo_t> git_find_repo(const std::string &dir, const std::string &git_dir,
+                                  const std::string &work_tree, const git_user_dirs_t &user_dirs) {
+    git_repo_t repo;
+    std::string config_home = user_dirs.xdg_config_home;
+    if (config_home.empty() && !user_dirs.home.empty()) config_home = user_dirs.home + "/.config";
+    repo.user_config_files.push_back("/etc/gitconfig");
+    if (!config_home.empty()) {
+        repo.user_config_files.push_back(config_home + "/git/config");
+        repo.user_excludes_file = config_home + "/git/ignore";
+    }
+    if (!user_dirs.home.empty()) repo.user_config_files.push_back(user_dirs.home + "/.gitconfig");
+
+    if (!git_dir.empty()) {
+        repo.git_dir = resolve_path(dir, git_dir);
+        if (!is_git_dir(repo.git_dir)) return none();
+    } else {
+        // Look for a .git in the directory and its parents, or a git directory itself.
+        std::string candidate = dir;
+        while (true) {
+            std::string dot_git = candidate == "/" ? "/.git" : candidate + "/.git";
+            if (path_is_dir(dot_git) && is_git_dir(dot_git)) {
+                repo.git_dir = dot_git;
+                repo.work_tree = candidate;
+                break;
+            }
+            if (path_is_file(dot_git)) {
+                if (auto linked = read_gitdir_file(dot_git)) {
+                    repo.git_dir = *linked;
+                    repo.work_tree = candidate;
+                    break;
+                }
+            }
+            if (is_git_dir(candidate)) {
+                repo.git_dir = candidate;
+                repo.inside_git_dir = true;
+                break;
+            }
+            if (candidate == "/") return none();
+            size_t slash = candidate.rfind('/');
+            candidate = slash == 0 ? "/" : candidate.substr(0, slash);
+        }
+    }
+
+    repo.common_dir = repo.git_dir;
+    if (auto common = read_first_line(repo.git_dir + "/commondir")) {
+        repo.common_dir = resolve_path(repo.git_dir, *common);
+    }
+    repo.bare = get_config_bool(repo, "core.bare", false);
+    if (auto excludes = get_config(repo, "core.excludesfile")) {
+        repo.user_excludes_file = *excludes;
+        if (has_prefix("~/", *excludes)) {
+            repo.user_excludes_file = user_dirs.home + excludes->substr(1);
+        }
+    }
+
+    if (!git_dir.empty()) {
+        // Like git, the directory is the top of the work tree unless one is given.
+        repo.inside_git_dir = has_prefix(repo.git_dir + "/", dir + "/");
+        if (!work_tree.empty()) {
+            repo.work_tree = resolve_path(dir, work_tree);
+        } else if (!repo.bare && !repo.inside_git_dir) {
+            repo.work_tree = dir;
+        }
+    } else if (repo.inside_git_dir) {
+        // The work tree of a .git directory is its parent, unless the repository is bare.
+        size_t slash = repo.git_dir.rfind('/');
+        if (!repo.bare && repo.git_dir.compare(slash, std::string::npos, "/.git") == 0) {
+            repo.work_tree = slash == 0 ? "/" : repo.git_dir.substr(0, slash);
+        }
+    }
+    if (repo.bare) repo.work_tree.clear();
+    return repo;
+}
+
+/// \return the commit which the ref \p name of \p repo points to, following symbolic refs, in
+/// hex, or an empty string if it does not exist. \p symbolic_target is set to the ref the first
+/// symbolic ref points to.
+static std::string resolve_ref(const git_repo_t &repo, std::string name,
+                               std::string *symbolic_target = nullptr) {
+    // Don't loop forever on refs pointing to each other.
+    for (int depth = 0; depth < 10; depth++) {
+        maybe_t<std::string> contents = read_first_line(repo.git_dir + "/" + name);
+        if (!contents && repo.common_dir != repo.git_dir) {
+            contents = read_first_line(repo.common_dir + "/" + name);
+        }
+        if (!contents) {
+            // Look in packed-refs, which has lines like "<sha> refs/heads/main".
+            maybe_t<std::string> packed_file = read_file_contents(repo.common_dir + "/packed-refs");
+            if (!packed_file) return std::string();
+            const std::string &packed = *packed_file;
+            std::string suffix = " " + name;
+            size_t pos = 0;
+            while (pos < packed.size()) {
+                size_t end = packed.find('\n', pos);
+                if (end == std::string::npos) end = packed.size();
+                std::string line = packed.substr(pos, end - pos);
+                pos = end + 1;
+                if (!line.empty() && line.back() == '\r') line.pop_back();
+                if (line.size() == 2 * oid_size + suffix.size() &&
+                    has_suffix(suffix, line)) {
+                    return line.substr(0, 2 * oid_size);
+                }
+            }
+            return std::string();
+        }
+        if (has_prefix("ref: ", *contents)) {
+            name = contents->substr(5);
+            if (symbolic_target && depth == 0) *symbolic_target = name;
+            continue;
+        }
+        return oid_from_hex(*contents) ? *contents : std::string();
+    }
+    return std::string();
+}
+
+git_head_t git_read_head(const git_repo_t &repo) {
+    git_head_t head;
+    const std::string &dir = repo.git_dir;
+    std::string head_name;
+    if (path_is_dir(dir + "/rebase-merge")) {
+        head_name = first_line_or_empty(dir + "/rebase-merge/head-name");
+        head.step = first_line_or_empty(dir + "/rebase-merge/msgnum");
+        head.total = first_line_or_empty(dir + "/rebase-merge/end");
+        head.operation = path_exists(dir + "/rebase-merge/interactive") ? "REBASE-i" : "REBASE-m";
+    } else if (path_is_dir(dir + "/rebase-apply")) {
+        head.step = first_line_or_empty(dir + "/rebase-apply/next");
+        head.total = first_line_or_empty(dir + "/rebase-apply/last");
+        if (path_exists(dir + "/rebase-apply/rebasing")) {
+            head_name = first_line_or_empty(dir + "/rebase-apply/head-name");
+            head.operation = "REBASE";
+        } else if (path_exists(dir + "/rebase-apply/applying")) {
+            head.operation = "AM";
+        } else {
+            head.operation = "AM/REBASE";
+        }
+    } else if (path_exists(dir + "/MERGE_HEAD")) {
+        head.operation = "MERGING";
+    } else if (path_exists(dir + "/CHERRY_PICK_HEAD")) {
+        head.operation = "CHERRY-PICKING";
+    } else if (path_exists(dir + "/REVERT_HEAD")) {
+        head.operation = "REVERTING";
+    } else if (path_exists(dir + "/BISECT_LOG")) {
+        head.operation = "BISECTING";
+    }
+
+    std::string target;
+    head.sha = resolve_ref(repo, "HEAD", &target);
+    // During a rebase, HEAD is detached, but the branch being rebased is what matters.
+    if (head_name.empty() || head_name == "detached HEAD") head_name = target;
+    const char *heads = "refs/heads/";
+    if (has_prefix(heads, head_name)) head_name.erase(0, std::strlen(heads));
+    head.branch = std::move(head_name);
+    return head;
+}
+
+namespace {
+/// Reads objects from the object database of a repository.
+class object_reader_t {
+   public:
+    explicit object_reader_t(const std::string &objects_dir) {
+        object_dirs_.push_back(objects_dir);
+        // Objects may be borrowed from other repositories, one per line.
+        if (auto file = read_file_contents(objects_dir + "/info/alternates")) {
+            const std::string &alternates = *file;
+            size_t pos = 0;
+            while (pos < alternates.size()) {
+                size_t end = alternates.find('\n', pos);
+                if (end == std::string::npos) end = alternates.size();
+                std::string line = alternates.substr(pos, end - pos);
+                pos = end + 1;
+                if (!line.empty() && line.front() != '#') {
+                    object_dirs_.push_back(resolve_path(objects_dir, line));
+                }
+            }
+        }
+    }
+
+    /// Read the object with the raw id \p oid into \p contents, and its type, like "tree", into
+    /// \p type. \return whether that worked.
+    bool read(const std::string &oid, std::string *type, std::string *contents) {
+        return read(oid, type, contents, 0);
+    }
+
+   private:
+    /// A pack file and its index, which maps object ids to offsets in it.
+    struct pack_t {
+        std::string path;
+        void *idx{MAP_FAILED};
+        size_t idx_size{0};
+        uint32_t count{0};
+        autoclose_fd_t fd;
+
+        ~pack_t() {
+            if (idx != MAP_FAILED) munmap(idx, idx_size);
+        }
+    };
+
+    static const int max_delta_depth = 64;
+
+    static uint32_t read_be32(const unsigned char *p) {
+        return uint32_t(p[0]) << 24 | uint32_t(p[1]) << 16 | uint32_t(p[2]) << 8 | uint32_t(p[3]);
+    }
+
+    /// Open the packs of all object directories, once.
+    void load_packs() {
+        if (packs_loaded_) return;
+        packs_loaded_ = true;
+        for (const std::string &dir : object_dirs_) {
+            std::string pack_dir = dir + "/pack";
+            DIR *d = opendir(pack_dir.c_str());
+            if (!d) continue;
+            while (struct dirent *ent = readdir(d)) {
+                std::string name = ent->d_name;
+                if (!has_suffix(".idx", name)) continue;
+                std::unique_ptr<pack_t> pack(new pack_t());
+                std::string base = pack_dir + "/" + name.substr(0, name.size() - 4);
+                pack->path = base + ".pack";
+                if (map_index(base + ".idx", pack.get())) packs_.push_back(std::move(pack));
+            }
+            closedir(d);
+        }
+    }
+
+    /// Map the version 2 pack index at \p path into \p pack. \return whether that worked.
+    static bool map_index(const std::string &path, pack_t *pack) {
+        autoclose_fd_t fd{open_cloexec(path, O_RDONLY)};
+        struct stat buf;
+        if (!fd.valid() || fstat(fd.fd(), &buf) != 0) return false;
+        // The header, the fan-out table and the checksums of the index and the pack.
+        const size_t min_size = 8 + 256 * 4 + 2 * oid_size;
+        if (static_cast<size_t>(buf.st_size) < min_size) return false;
+        pack->idx_size = buf.st_size;
+        pack->idx = mmap(nullptr, pack->idx_size, PROT_READ, MAP_PRIVATE, fd.fd(), 0);
+        if (pack->idx == MAP_FAILED) return false;
+        const auto *idx = static_cast<const unsigned char *>(pack->idx);
+        if (std::memcmp(idx, "\377tOc", 4) != 0 || read_be32(idx + 4) != 2) return false;
+        pack->count = read_be32(idx + 8 + 255 * 4);
+        // The object ids, their CRCs and their offsets.
+        return pack->idx_size >= min_size + size_t(pack->count) * (oid_size + 4 + 4);
+    }
+
+    /// Find \p oid in the index of \p pack. \return its offset in the pack, or none().
+    static maybe_t<uint64_t> find_in_pack(const pack_t &pack, const std::string &oid) {
+        const auto *idx = static_cast<const unsigned char *>(pack.idx);
+        const unsigned char first = oid[0];
+        const unsigned char *fanout = idx + 8;
+        uint32_t lo = first == 0 ? 0 : read_be32(fanout + (first - 1) * 4);
+        uint32_t hi = read_be32(fanout + first * 4);
+        const unsigned char *oids = fanout + 256 * 4;
+        while (lo < hi) {
+            uint32_t mid = lo + (hi - lo) / 2;
+            int cmp = std::memcmp(oids + size_t(mid) * oid_size, oid.data(), oid_size);
+            if (cmp == 0) {
+                const unsigned char *offsets = oids + size_t(pack.count) * (oid_size + 4);
+                uint32_t offset = read_be32(offsets + size_t(mid) * 4);
+                if (!(offset & 0x80000000)) return uint64_t(offset);
+                // Offsets of 2 GiB or more are in another table.
+                const unsigned char *large = offsets + size_t(pack.count) * 4 +
+                                             size_t(offset & 0x7fffffff) * 8;
+                if (large + 8 > idx + pack.idx_size) return none();
+                return uint64_t(read_be32(large)) << 32 | read_be32(large + 4);
+            }
+            if (cmp < 0) {
+                lo = mid + 1;
+            } else {
+                hi = mid;
+            }
+        }
+        return none();
+    }
+
+    bool read(const std::string &oid, std::string *type, std::string *contents, int depth) {
+        if (oid.size() != oid_size || depth > max_delta_depth) return false;
+        std::string hex = oid_to_hex(oid);
+        for (const std::string &dir : object_dirs_) {
+            maybe_t<std::string> loose =
+                read_file_contents(dir + "/" + hex.substr(0, 2) + "/" + hex.substr(2));
+            if (!loose) continue;
+            // A loose object is compressed, with a header like "blob 1234\0".
+            std::string data;
+            if (!inflate_all(*loose, &data)) return false;
+            size_t space = data.find(' ');
+            size_t nul = data.find('\0');
+            if (space == std::string::npos || nul == std::string::npos || space > nul) {
+                return false;
+            }
+            *type = data.substr(0, space);
+            *contents = data.substr(nul + 1);
+            return true;
+        }
+        load_packs();
+        for (const auto &pack : packs_) {
+            if (auto offset = find_in_pack(*pack, oid)) {
+                return read_packed(*pack, *offset, type, contents, depth);
+            }
+        }
+        return false;
+    }
+
+    /// Read the object at \p offset of \p pack. \return whether that worked.
+    bool read_packed(pack_t &pack, uint64_t offset, std::string *type, std::string *contents,
+                     int depth) {
+        if (depth > max_delta_depth) return false;
+        if (!pack.fd.valid()) {
+            pack.fd.reset(open_cloexec(pack.path, O_RDONLY));
+            if (!pack.fd.valid()) return false;
+        }
+        // The header is the type and the size, in a variable number of bytes, and for deltas
+        // where to find their base.
+        unsigned char header[32];
+        ssize_t amt = pread(pack.fd.fd(), header, sizeof header, offset);
+        if (amt <= 0) return false;
+        size_t pos = 0;
+        unsigned char c = header[pos++];
+        int obj_type = (c >> 4) & 7;
+        uint64_t size = c & 15;
+        int shift = 4;
+        while (c & 0x80) {
+            if (pos >= size_t(amt) || shift > 57) return false;
+            c = header[pos++];
+            size |= uint64_t(c & 0x7f) << shift;
+            shift += 7;
+        }
+
+        enum { obj_commit = 1, obj_tree = 2, obj_blob = 3, obj_tag = 4, ofs_delta = 6, ref_delta };
+        std::string base_type, base;
+        if (obj_type == ofs_delta) {
+            // The base is at a negative offset from this object.
+            if (pos >= size_t(amt)) return false;
+            c = header[pos++];
+            uint64_t back = c & 0x7f;
+            while (c & 0x80) {
+                if (pos >= size_t(amt)) return false;
+                c = header[pos++];
+                back = ((back + 1) << 7) | (c & 0x7f);
+            }
+            if (back > offset ||
+                !read_packed(pack, offset - back, &base_type, &base, depth + 1)) {
+                return false;
+            }
+        } else if (obj_type == ref_delta) {
+            if (pos + oid_size > size_t(amt)) return false;
+            std::string base_oid(reinterpret_cast<const char *>(header + pos), oid_size);
+            pos += oid_size;
+            if (!read(base_oid, &base_type, &base, depth + 1)) return false;
+        } else {
+            static const char *const type_names[] = {nullptr, "commit", "tree", "blob", "tag"};
+            if (obj_type < obj_commit || obj_type > obj_tag) return false;
+            base_type = type_names[obj_type];
+        }
+
+        std::string data;
+        if (!inflate_at(pack.fd.fd(), offset + pos, size, &data)) return false;
+        *type = std::move(base_type);
+        if (obj_type == ofs_delta || obj_type == ref_delta) {
+            return apply_delta(base, data, contents);
+        }
+        *contents = std::move(data);
+        return true;
+    }
+
+    /// Inflate all of \p in into \p out. \return whether that worked.
+    static bool inflate_all(const std::string &in, std::string *out) {
+        z_stream zs{};
+        if (inflateInit(&zs) != Z_OK) return false;
+        zs.next_in = reinterpret_cast<Bytef *>(const_cast<char *>(in.data()));
+        zs.avail_in = static_cast<uInt>(in.size());
+        out->clear();
+        int ret;
+        do {
+            char buff[16384];
+            zs.next_out = reinterpret_cast<Bytef *>(buff);
+            zs.avail_out = sizeof buff;
+            ret = inflate(&zs, Z_NO_FLUSH);
+            out->append(buff, sizeof buff - zs.avail_out);
+        } while (ret == Z_OK);
+        inflateEnd(&zs);
+        return ret == Z_STREAM_END;
+    }
+
+    /// Inflate the data at \p offset of the file \p fd, which is \p size bytes once inflated, into
+    /// \p out. \return whether that worked.
+    static bool inflate_at(int fd, uint64_t offset, uint64_t size, std::string *out) {
+        z_stream zs{};
+        if (inflateInit(&zs) != Z_OK) return false;
+        out->assign(size, '\0');
+        zs.next_out = reinterpret_cast<Bytef *>(&(*out)[0]);
+        zs.avail_out = static_cast<uInt>(size);
+        int ret = Z_OK;
+        unsigned char buff[16384];
+        while (ret == Z_OK) {
+            ssize_t amt = pread(fd, buff, sizeof buff, offset);
+            if (amt <= 0) break;
+            offset += amt;
+            zs.next_in = buff;
+            zs.avail_in = static_cast<uInt>(amt);
+            while (zs.avail_in > 0 && ret == Z_OK) {
+                ret = inflate(&zs, Z_NO_FLUSH);
+                if (ret == Z_OK && zs.avail_out == 0 && zs.avail_in > 0) {
+                    // Only the end of the stream may be left.
+                    unsigned char extra;
+                    zs.next_out = &extra;
+                    zs.avail_out = 1;
+                    ret = inflate(&zs, Z_NO_FLUSH);
+                    if (zs.avail_out == 0) ret = Z_DATA_ERROR;
+                }
+            }
+        }
+        inflateEnd(&zs);
+        return ret == Z_STREAM_END && zs.total_out == size;
+    }
+
+    /// Read a size in the header of a delta.
+    static bool read_delta_size(const std::string &delta, size_t *pos, uint64_t *size) {
+        *size = 0;
+        int shift = 0;
+        unsigned char c;
+        do {
+            if (*pos >= delta.size() || shift > 57) return false;
+            c = delta[(*pos)++];
+            *size |= uint64_t(c & 0x7f) << shift;
+            shift += 7;
+        } while (c & 0x80);
+        return true;
+    }
+
+    /// Apply \p delta, which copies parts of \p base and inserts new data, into \p out.
+    static bool apply_delta(const std::string &base, const std::string &delta, std::string *out) {
+        size_t pos = 0;
+        uint64_t base_size, result_size;
+        if (!read_delta_size(delta, &pos, &base_size) || base_size != base.size() ||
+            !read_delta_size(delta, &pos, &result_size)) {
+            return false;
+        }
+        out->clear();
+        out->reserve(result_size);
+        while (pos < delta.size()) {
+            unsigned char op = delta[pos++];
+            if (op & 0x80) {
+                // Copy from the base. The bits say which bytes of the offset and size follow.
+                uint64_t copy_offset = 0, copy_size = 0;
+                for (int i = 0; i < 4; i++) {
+                    if (!(op & (1 << i))) continue;
+                    if (pos >= delta.size()) return false;
+                    copy_offset |= uint64_t(static_cast<unsigned char>(delta[pos++])) << (8 * i);
+                }
+                for (int i = 0; i < 3; i++) {
+                    if (!(op & (0x10 << i))) continue;
+                    if (pos >= delta.size()) return false;
+                    copy_size |= uint64_t(static_cast<unsigned char>(delta[pos++])) << (8 * i);
+                }
+                if (copy_size == 0) copy_size = 0x10000;
+                if (copy_offset + copy_size > base.size()) return false;
+                out->append(base, copy_offset, copy_size);
+            } else if (op != 0) {
+                // Insert the next op bytes.
+                if (pos + op > delta.size()) return false;
+                out->append(delta, pos, op);
+                pos += op;
+            } else {
+                return false;
+            }
+        }
+        return out->size() == result_size;
+    }
+
+    std::vector<std::string> object_dirs_;
+    std::vector<std::unique_ptr<pack_t>> packs_;
+    bool packs_loaded_{false};
+};
+
+/// An entry of the index.
+struct index_entry_t {
+    std::string path;
+    std::string oid;
+    uint32_t mode;
+    uint32_t size;
+    uint32_t mtime_s, mtime_ns;
+    uint32_t ino;
+    int stage;
+    bool assume_valid;
+    bool skip_worktree;
+    bool intent_to_add;
+};
+
+/// An entry of a tree, with its path from the top of the work tree.
+struct tree_entry_t {
+    std::string path;
+    std::string oid;
+    uint32_t mode;
+};
+
+/// The index of a repository: the files which are tracked, and what is staged.
+struct index_t {
+    std::vector<index_entry_t> entries;
+
+    /// The trees of directories of the index which are known, by path ending in "/", or "" for
+    /// the top directory. They are the same in HEAD if their ids are the same.
+    std::map<std::string, std::string> cache_trees;
+
+    /// When the index was written.
+    file_id_t file_id;
+};
+}  // namespace
+
+static uint32_t read_be32(const unsigned char *p) {
+    return uint32_t(p[0]) << 24 | uint32_t(p[1]) << 16 | uint32_t(p[2]) << 8 | uint32_t(p[3]);
+}
+
+/// Parse the entries of the cache tree extension of the index, for the directory \p prefix, from
+/// \p pos. \return whether that worked.
+static bool parse_cache_tree(const std::string &data, size_t *pos, const std::string &prefix,
+                             std::map<std::string, std::string> *trees, int depth) {
+    if (depth > 1000) return false;
+    // The path, the number of entries (-1 if unknown), the number of subtrees, and the id.
+    size_t nul = data.find('\0', *pos);
+    if (nul == std::string::npos) return false;
+    std::string path = data.substr(*pos, nul - *pos);
+    std::string dir = path.empty() ? prefix : prefix + path + "/";
+    size_t newline = data.find('\n', nul);
+    if (newline == std::string::npos) return false;
+    std::string counts = data.substr(nul + 1, newline - nul - 1);
+    long entry_count = std::strtol(counts.c_str(), nullptr, 10);
+    size_t space = counts.find(' ');
+    if (space == std::string::npos) return false;
+    long subtrees = std::strtol(counts.c_str() + space + 1, nullptr, 10);
+    *pos = newline + 1;
+    if (entry_count >= 0) {
+        if (*pos + oid_size > data.size()) return false;
+        (*trees)[dir] = data.substr(*pos, oid_size);
+        *pos += oid_size;
+    }
+    for (long i = 0; i < subtrees; i++) {
+        if (!parse_cache_tree(data, pos, dir, trees, depth + 1)) return false;
+    }
+    return true;
+}
+
+/// Read the index of \p repo into \p index. \return whether that worked. A repository without an
+/// index has no tracked files.
+static bool read_index(const git_repo_t &repo, index_t *index) {
+    std::string path = repo.git_dir + "/index";
+    struct stat buf;
+    if (stat(path.c_str(), &buf) != 0) return errno == ENOENT;
+    index->file_id = file_id_t::from_stat(buf);
+    maybe_t<std::string> file = read_file_contents(path);
+    if (!file) return false;
+    const std::string &data = *file;
+
+    const auto *bytes = reinterpret_cast<const unsigned char *>(data.data());
+    // The header, and the checksum at the end.
+    if (data.size() < 12 + oid_size || std::memcmp(bytes, "DIRC", 4) != 0) return false;
+    uint32_t version = read_be32(bytes + 4);
+    if (version < 2 || version > 4) return false;
+    uint32_t count = read_be32(bytes + 8);
+    const size_t end = data.size() - oid_size;
+
+    size_t pos = 12;
+    std::string previous_path;
+    for (uint32_t i = 0; i < count; i++) {
+        // ctime, mtime, dev, ino, mode, uid, gid and size are 32 bits each, then the id and
+        // 16 bits of flags.
+        const size_t entry_start = pos;
+        if (pos + 40 + oid_size + 2 > end) return false;
+        index_entry_t entry;
+        entry.mtime_s = read_be32(bytes + pos + 8);
+        entry.mtime_ns = read_be32(bytes + pos + 12);
+        entry.ino = read_be32(bytes + pos + 20);
+        entry.mode = read_be32(bytes + pos + 24);
+        entry.size = read_be32(bytes + pos + 36);
+        entry.oid = data.substr(pos + 40, oid_size);
+        pos += 40 + oid_size;
+        uint16_t flags = uint16_t(bytes[pos]) << 8 | bytes[pos + 1];
+        pos += 2;
+        entry.assume_valid = flags & 0x8000;
+        entry.stage = (flags >> 12) & 3;
+        entry.skip_worktree = false;
+        entry.intent_to_add = false;
+        if (version >= 3 && (flags & 0x4000)) {
+            if (pos + 2 > end) return false;
+            uint16_t extended = uint16_t(bytes[pos]) << 8 | bytes[pos + 1];
+            pos += 2;
+            entry.skip_worktree = extended & 0x4000;
+            entry.intent_to_add = extended & 0x2000;
+        }
+
+        if (version == 4) {
+            // The path is compressed: remove a number of bytes from the end of the previous
+            // path, then append the rest.
+            uint64_t strip = 0;
+            unsigned char c;
+            do {
+                if (pos >= end) return false;
+                c = bytes[pos++];
+                strip = (strip << 7) | (c & 0x7f);
+                if (c & 0x80) strip++;
+            } while (c & 0x80);
+            if (strip > previous_path.size()) return false;
+            size_t nul = data.find('\0', pos);
+            if (nul == std::string::npos || nul >= end) return false;
+            entry.path = previous_path.substr(0, previous_path.size() - strip) +
+                         data.substr(pos, nul - pos);
+            pos = nul + 1;
+        } else {
+            // The path is padded with 1 to 8 NULs to a multiple of 8 bytes.
+            size_t nul = data.find('\0', pos);
+            if (nul == std::string::npos || nul >= end) return false;
+            entry.path = data.substr(pos, nul - pos);
+            pos = entry_start + ((nul + 1 - entry_start + 7) & ~size_t(7));
+            if (pos > end) return false;
+        }
+        previous_path = entry.path;
+        index->entries.push_back(std::move(entry));
+    }
+
+    // Extensions have a signature and a size. Only the cache tree is of interest.
+    while (pos + 8 <= end) {
+        std::string signature = data.substr(pos, 4);
+        uint32_t size = read_be32(bytes + pos + 4);
+        pos += 8;
+        if (pos + size > end) return false;
+        if (signature == "TREE") {
+            std::string tree_data = data.substr(pos, size);
+            size_t tree_pos = 0;
+            if (!parse_cache_tree(tree_data, &tree_pos, "", &index->cache_trees, 0)) {
+                index->cache_trees.clear();
+            }
+        }
+        pos += size;
+    }
+    return true;
+}
+
+/// Add the entries of the tree \p oid for the directory \p prefix to \p entries, except for
+/// directories in \p cache_trees with the same id, which are added to \p same_dirs instead.
+/// \return whether that worked.
+static bool flatten_tree(object_reader_t &reader, const std::string &oid, const std::string &prefix,
+                         const std::map<std::string, std::string> &cache_trees,
+                         std::vector<tree_entry_t> *entries, std::set<std::string> *same_dirs,
+                         int depth) {
+    auto cached = cache_trees.find(prefix);
+    if (cached != cache_trees.end() && cached->second == oid) {
+        same_dirs->insert(prefix);
+        return true;
+    }
+    std::string type, tree;
+    if (depth > 1000 || !reader.read(oid, &type, &tree) || type != "tree") return false;
+    // Each entry is an octal mode, a space, the name, a NUL and the id.
+    size_t pos = 0;
+    while (pos < tree.size()) {
+        size_t space = tree.find(' ', pos);
+        size_t nul = tree.find('\0', pos);
+        if (space == std::string::npos || nul == std::string::npos || space > nul ||
+            nul + 1 + oid_size > tree.size()) {
+            return false;
+        }
+        auto mode = static_cast<uint32_t>(std::strtoul(tree.c_str() + pos, nullptr, 8));
+        std::string path = prefix + tree.substr(space + 1, nul - space - 1);
+        std::string entry_oid = tree.substr(nul + 1, oid_size);
+        pos = nul + 1 + oid_size;
+        if (mode == tree_mode) {
+            if (!flatten_tree(reader, entry_oid, path + "/", cache_trees, entries, same_dirs,
+                              depth + 1)) {
+                return false;
+            }
+        } else {
+            entries->push_back({std::move(path), std::move(entry_oid), mode});
+        }
+    }
+    return true;
+}
+
+/// \return whether \p path is in one of the directories \p dirs, which end in "/".
+static bool in_dirs(const std::string &path, const std::set<std::string> &dirs) {
+    if (dirs.empty()) return false;
+    if (dirs.count("")) return true;
+    for (size_t slash = path.find('/'); slash != std::string::npos;
+         slash = path.find('/', slash + 1)) {
+        if (dirs.count(path.substr(0, slash + 1))) return true;
+    }
+    return false;
+}
+
+/// Count the files whose staged contents in \p index differ from the commit \p head_sha.
+/// \return none() if the objects could not be read.
+static maybe_t<size_t> count_staged(const git_repo_t &repo, const index_t &index,
+                                    const std::string &head_sha,
+                                    const std::set<std::string> &unmerged) {
+    std::vector<tree_entry_t> head_entries;
+    std::set<std::string> same_dirs;
+    if (!head_sha.empty()) {
+        object_reader_t reader(repo.common_dir + "/objects");
+        std::string type, commit;
+        auto commit_oid = oid_from_hex(head_sha);
+        if (!commit_oid || !reader.read(*commit_oid, &type, &commit) || type != "commit" ||
+            !has_prefix("tree ", commit)) {
+            return none();
+        }
+        auto tree_oid = oid_from_hex(commit.substr(5, 2 * oid_size));
+        if (!tree_oid || !flatten_tree(reader, *tree_oid, "", index.cache_trees, &head_entries,
+                                       &same_dirs, 0)) {
+            return none();
+        }
+    }
+
+    std::vector<tree_entry_t> index_entries;
+    for (const index_entry_t &entry : index.entries) {
+        if (entry.stage != 0 || entry.intent_to_add || in_dirs(entry.path, same_dirs)) continue;
+        index_entries.push_back({entry.path, entry.oid, entry.mode});
+    }
+    auto by_path = [](const tree_entry_t &a, const tree_entry_t &b) { return a.path < b.path; };
+    std::sort(head_entries.begin(), head_entries.end(), by_path);
+    std::sort(index_entries.begin(), index_entries.end(), by_path);
+
+    // Files which are added, removed or changed, except for conflicts.
+    size_t staged = 0;
+    auto head = head_entries.begin(), idx = index_entries.begin();
+    while (head != head_entries.end() || idx != index_entries.end()) {
+        const tree_entry_t *changed;
+        if (idx == index_entries.end() || (head != head_entries.end() && head->path < idx->path)) {
+            changed = &*head++;
+        } else if (head == head_entries.end() || idx->path < head->path) {
+            changed = &*idx++;
+        } else {
+            bool same = head->oid == idx->oid && head->mode == idx->mode;
+            changed = same ? nullptr : &*head;
+            ++head;
+            ++idx;
+        }
+        if (changed && !unmerged.count(changed->path)) staged++;
+    }
+    return staged;
+}
+
+/// \return whether the file of \p entry in the work tree \p work_tree differs from the index.
+/// \p filemode says whether the executable bit is tracked, and \p index_id is the index file.
+static bool is_dirty(const std::string &work_tree, const index_entry_t &entry, bool filemode,
+                     const file_id_t &index_id) {
+    std::string path = work_tree + "/" + entry.path;
+    struct stat buf;
+    if (lstat(path.c_str(), &buf) != 0) return true;
+    if ((entry.mode & S_IFMT) == (gitlink_mode & S_IFMT)) {
+        // A submodule is only changed if it is gone.
+        return !S_ISDIR(buf.st_mode);
+    }
+    if (entry.intent_to_add) return true;
+    bool is_link = (entry.mode & S_IFMT) == S_IFLNK;
+    if (is_link ? !S_ISLNK(buf.st_mode) : !S_ISREG(buf.st_mode)) return true;
+    if (!is_link && filemode && ((entry.mode & 0100) != 0) != ((buf.st_mode & S_IXUSR) != 0)) {
+        return true;
+    }
+    // Conflicted entries have no stat data.
+    bool has_stat = entry.stage == 0;
+    if (has_stat && uint32_t(buf.st_size) != entry.size) return true;
+
+    // Like git, trust the modification time, unless the file might have been changed in the
+    // same instant the index was written.
+    file_id_t id = file_id_t::from_stat(buf);
+    bool same_stat = has_stat && uint32_t(id.mod_seconds) == entry.mtime_s &&
+                     uint32_t(id.mod_nanoseconds) == entry.mtime_ns &&
+                     (entry.ino == 0 || uint32_t(buf.st_ino) == entry.ino);
+    bool racy = id.mod_seconds > index_id.mod_seconds ||
+                (id.mod_seconds == index_id.mod_seconds &&
+                 id.mod_nanoseconds >= index_id.mod_nanoseconds);
+    if (same_stat && !racy) return false;
+
+    // Compare the contents.
+    std::string contents;
+    if (is_link) {
+        std::vector<char> target(buf.st_size + 1);
+        ssize_t len = readlink(path.c_str(), target.data(), target.size());
+        if (len < 0 || size_t(len) != size_t(buf.st_size)) return true;
+        contents.assign(target.data(), len);
+    } else if (auto file = read_file_contents(path)) {
+        contents = std::move(*file);
+    } else {
+        return true;
+    }
+    std::string object = "blob " + std::to_string(contents.size());
+    object.push_back('\0');
+    sha1_t sha1;
+    sha1.update(object.data(), object.size());
+    sha1.update(contents.data(), contents.size());
+    return sha1.digest() != entry.oid;
+}
+
+bool git_wildmatch(const std::string &pattern, const std::string &str) {
+    struct matcher_t {
+        const char *start;
+
+        bool match_class(const char **pp, char c) const {
+            // p points after "[".
+            const char *p = *pp;
+            bool negated = *p == '!' || *p == '^';
+            if (negated) p++;
+            bool matched = false;
+            bool first = true;
+            for (; *p && (first || *p != ']'); p++) {
+                first = false;
+                char lo = *p;
+                if (lo == '\\' && p[1]) lo = *++p;
+                char hi = lo;
+                if (p[1] == '-' && p[2] && p[2] != ']') {
+                    hi = p[2];
+                    if (hi == '\\' && p[3]) {
+                        hi = p[3];
+                        p++;
+                    }
+                    p += 2;
+                }
+                if (lo <= c && c <= hi) matched = true;
+            }
+            if (*p != ']') return false;
+            *pp = p + 1;
+            return matched != negated;
+        }
+
+        bool match(const char *p, const char *s) const {
+            while (*p) {
+                if (*p == '*') {
+                    if (p[1] == '*') {
+                        // "**" matches across directories. "**/" also matches none at all.
+                        const char *rest = p + 2;
+                        while (*rest == '*') rest++;
+                        if ((p == start || p[-1] == '/') && *rest == '/' && match(rest + 1, s)) {
+                            return true;
+                        }
+                        for (const char *t = s;; t++) {
+                            if (match(rest, t)) return true;
+                            if (!*t) return false;
+                        }
+                    }
+                    for (const char *t = s;; t++) {
+                        if (match(p + 1, t)) return true;
+                        if (!*t || *t == '/') return false;
+                    }
+                }
+                if (!*s) return false;
+                if (*p == '?') {
+                    if (*s == '/') return false;
+                    p++;
+                } else if (*p == '[') {
+                    p++;
+                    if (*s == '/' || !match_class(&p, *s)) return false;
+                } else {
+                    if (*p == '\\' && p[1]) p++;
+                    if (*p != *s) return false;
+                    p++;
+                }
+                s++;
+            }
+            return !*s;
+        }
+    };
+    matcher_t matcher{pattern.c_str()};
+    return matcher.match(pattern.c_str(), str.c_str());
+}
+
+namespace {
+/// A pattern of ignored files.
+struct ignore_pattern_t {
+    /// The pattern, without "!", or a leading or trailing "/".
+    std::string pattern;
+    /// The directory of the .gitignore, ending in "/", or empty.
+    std::string base;
+    /// Whether the pattern starts with "!", so it un-ignores files.
+    bool negated;
+    /// Whether the pattern ends in "/", so it only matches directories.
+    bool dir_only;
+    /// Whether the pattern contains a "/", so it matches paths from base rather than names.
+    bool anchored;
+};
+
+/// Counts untracked files in a work tree.
+class untracked_counter_t {
+   public:
+    untracked_counter_t(const git_repo_t &repo, const index_t &index) : work_tree_(repo.work_tree) {
+        for (const index_entry_t &entry : index.entries) {
+            tracked_.insert(entry.path);
+            for (size_t slash = entry.path.find('/'); slash != std::string::npos;
+                 slash = entry.path.find('/', slash + 1)) {
+                tracked_dirs_.insert(entry.path.substr(0, slash + 1));
+            }
+        }
+        // The lowest precedence is the user's file, then the repository's.
+        if (!repo.user_excludes_file.empty()) add_patterns(repo.user_excludes_file, "");
+        add_patterns(repo.git_dir + "/info/exclude", "");
+    }
+
+    size_t count() {
+        walk("");
+        return count_;
+    }
+
+   private:
+    /// Add the patterns in the file \p path, whose directory is \p base.
+    void add_patterns(const std::string &path, const std::string &base) {
+        maybe_t<std::string> file = read_file_contents(path);
+        if (!file) return;
+        const std::string &contents = *file;
+        size_t pos = 0;
+        while (pos < contents.size()) {
+            size_t end = contents.find('\n', pos);
+            if (end == std::string::npos) end = contents.size();
+            std::string line = contents.substr(pos, end - pos);
+            pos = end + 1;
+            if (!line.empty() && line.back() == '\r') line.pop_back();
+            // Trailing spaces are ignored unless escaped.
+            while (!line.empty() && line.back() == ' ' &&
+                   !(line.size() > 1 && line[line.size() - 2] == '\\')) {
+                line.pop_back();
+            }
+            if (line.empty() || line.front() == '#') continue;
+            ignore_pattern_t pattern{std::string(), base, false, false, false};
+            if (line.front() == '!') {
+                pattern.negated = true;
+                line.erase(0, 1);
+            } else if (line.front() == '\\' && line.size() > 1 &&
+                       (line[1] == '!' || line[1] == '#')) {
+                line.erase(0, 1);
+            }
+            if (!line.empty() && line.back() == '/') {
+                pattern.dir_only = true;
+                line.pop_back();
+            }
+            pattern.anchored = line.find('/') != std::string::npos;
+            if (!line.empty() && line.front() == '/') line.erase(0, 1);
+            if (line.empty()) continue;
+            pattern.pattern = std::move(line);
+            patterns_.push_back(std::move(pattern));
+        }
+    }
+
+    /// \return whether \p path, relative to the work tree, is ignored.
+    bool is_ignored(const std::string &path, bool is_dir) const {
+        // Later patterns take precedence, and so do those of deeper directories.
+        for (auto it = patterns_.rbegin(); it != patterns_.rend(); ++it) {
+            if (it->dir_only && !is_dir) continue;
+            if (!has_prefix(it->base, path)) continue;
+            std::string rel = path.substr(it->base.size());
+            if (!it->anchored) rel = rel.substr(rel.rfind('/') + 1);
+            if (git_wildmatch(it->pattern, rel)) return !it->negated;
+        }
+        return false;
+    }
+
+    /// Count the untracked files in the directory \p dir, relative to the work tree and ending in
+    /// "/", or empty for its top.
+    void walk(const std::string &dir) {
+        std::string full = dir.empty() ? work_tree_ : work_tree_ + "/" + dir;
+        DIR *d = opendir(full.c_str());
+        if (!d) return;
+        size_t pattern_count = patterns_.size();
+        add_patterns(full + "/.gitignore", dir);
+
+        std::vector<std::pair<std::string, bool>> children;
+        while (struct dirent *ent = readdir(d)) {
+            std::string name = ent->d_name;
+            if (name == "." || name == ".." || name == ".git") continue;
+            std::string path = dir + name;
+            bool is_dir;
+#if HAVE_STRUCT_DIRENT_D_TYPE
+            if (ent->d_type != DT_UNKNOWN) {
+                is_dir = ent->d_type == DT_DIR;
+            } else
+#endif
+            {
+                struct stat buf;
+                if (lstat((full + "/" + name).c_str(), &buf) != 0) continue;
+                is_dir = S_ISDIR(buf.st_mode);
+            }
+            if (tracked_.count(path)) continue;
+            if (is_ignored(path, is_dir)) continue;
+            if (!is_dir) {
+                count_++;
+            } else if (!tracked_dirs_.count(path + "/") &&
+                       path_exists(work_tree_ + "/" + path + "/.git")) {
+                // Another repository counts once.
+                count_++;
+            } else {
+                children.emplace_back(path + "/", true);
+            }
+        }
+        closedir(d);
+        for (const auto &child : children) walk(child.first);
+        patterns_.resize(pattern_count);
+    }
+
+    const std::string work_tree_;
+    std::set<std::string> tracked_;
+    std::set<std::string> tracked_dirs_;
+    std::vector<ignore_pattern_t> patterns_;
+    size_t count_{0};
+};
+}  // namespace
+
+maybe_t<git_changes_t> git_read_changes(const git_repo_t &repo, const git_head_t &head,
+                                        bool count_untracked) {
+    if (repo.work_tree.empty()) return none();
+    // Only SHA-1 repositories are supported.
+    auto format = get_config(repo, "extensions.objectformat");
+    if (format && *format != "sha1") return none();
+
+    index_t index;
+    if (!read_index(repo, &index)) return none();
+
+    git_changes_t changes;
+    std::set<std::string> unmerged;
+    const bool filemode = get_config_bool(repo, "core.filemode", true);
+    for (const index_entry_t &entry : index.entries) {
+        if (entry.stage != 0) unmerged.insert(entry.path);
+        // Like `git diff`, a conflicted file is compared with our side.
+        if (entry.stage != 0 && entry.stage != 2) continue;
+        if (!entry.assume_valid && !entry.skip_worktree &&
+            is_dirty(repo.work_tree, entry, filemode, index.file_id)) {
+            changes.dirty++;
+        }
+    }
+    changes.unmerged = unmerged.size();
+
+    auto staged = count_staged(repo, index, head.sha, unmerged);
+    if (!staged) return none();
+    changes.staged = *staged;
+
+    if (count_untracked) changes.untracked = untracked_counter_t(repo, index).count();
+    return changes;
+}
+
+std::string git_state_key(const git_repo_t &repo) {
+    std::string key = repo.git_dir;
+    for (const char *name : {"/HEAD", "/index"}) {
+        struct stat buf;
+        if (stat((repo.git_dir + name).c_str(), &buf) != 0) continue;
+        file_id_t id = file_id_t::from_stat(buf);
+        key += name + std::to_string(id.mod_seconds) + "." + std::to_string(id.mod_nanoseconds) +
+               "." + std::to_string(id.size);
+    }
+    return key;
+}
@@ -0,0 +1,112 @@
+// Reading the state of a git repository without running git, for fish_git_status.
+//
+// This only reads files: HEAD and the refs, the index, the objects, loose and packed, and the
+// work tree. It is safe to use on a background thread.
+#ifndef FISH_GIT_STATUS_H
+#define FISH_GIT_STATUS_H
+
+#include <cstddef>
+#include <string>
+#include <vector>
+
+#include "maybe.h"
+
+/// A git repository, found from a directory in it.
+struct git_repo_t {
+    /// The git directory, like /src/fish/.git. It has HEAD and the index.
+    std::string git_dir;
+
+    /// The directory with the objects, the refs and the config. It is only different from git_dir
+    /// in a linked work tree.
+    std::string common_dir;
+
+    /// The top directory of the work tree, or empty if there is none.
+    std::string work_tree;
+
+    /// Whether the directory is in the git directory rather than the work tree.
+    bool inside_git_dir{false};
+
+    /// Whether the repository is bare.
+    bool bare{false};
+
+    /// The git config files of the user, in order of precedence, lowest first.
+    std::vector<std::string> user_config_files;
+
+    /// The file with the patterns of ignored files of the user, from core.excludesFile.
+    std::string user_excludes_file;
+};
+
+/// The directories where git looks for the configuration of the user.
+struct git_user_dirs_t {
+    /// $HOME.
+    std::string home;
+
+    /// $XDG_CONFIG_HOME, or empty for ~/.config.
+    std::string xdg_config_home;
+};
+
+/// Find the repository which the absolute path \p dir is in. If \p git_dir is not empty, it is
+/// the git directory, as given by $GIT_DIR, and \p work_tree, as given by $GIT_WORK_TREE, or else
+/// \p dir is the top of the work tree. \return none() if there is no repository.
+maybe_t<git_repo_t> git_find_repo(const std::string &dir, const std::string &git_dir,
+                                  const std::string &work_tree, const git_user_dirs_t &user_dirs);
+
+/// What HEAD points to, and what git is in the middle of.
+struct git_head_t {
+    /// The branch which is checked out, without refs/heads/, or empty if HEAD is detached.
+    std::string branch;
+
+    /// The commit HEAD points to, in hex, or empty if there are no commits on the branch yet.
+    std::string sha;
+
+    /// The operation in progress, like "REBASE-i", "MERGING" or "BISECTING", or empty.
+    std::string operation;
+
+    /// For a rebase or git am, the number of the current patch and of all patches, or empty.
+    std::string step;
+    std::string total;
+};
+
+/// Read HEAD of \p repo, and what it is in the middle of.
+git_head_t git_read_head(const git_repo_t &repo);
+
+/// The number of files with changes.
+struct git_changes_t {
+    /// Files which are staged, so their contents in the index differ from HEAD.
+    size_t staged{0};
+
+    /// Files whose contents in the work tree differ from the index.
+    size_t dirty{0};
+
+    /// Files with merge conflicts.
+    size_t unmerged{0};
+
+    /// Files which are neither tracked nor ignored, or none() if they were not counted.
+    maybe_t<size_t> untracked{};
+
+    bool operator==(const git_changes_t &rhs) const {
+        return staged == rhs.staged && dirty == rhs.dirty && unmerged == rhs.unmerged &&
+               untracked == rhs.untracked;
+    }
+    bool operator!=(const git_changes_t &rhs) const { return !(*this == rhs); }
+};
+
+/// Count the changed files in the work tree of \p repo, whose HEAD is \p head, like `git status`.
+/// Untracked files are only counted if \p count_untracked is set, since that means reading all
+/// directories of the work tree. \return none() if the repository has no work tree, or its index
+/// or objects could not be read.
+maybe_t<git_changes_t> git_read_changes(const git_repo_t &repo, const git_head_t &head,
+                                        bool count_untracked);
+
+/// \return a string which changes when HEAD or the index of \p repo change. Other changes to the
+/// work tree do not change it.
+std::string git_state_key(const git_repo_t &repo);
+
+/// \return the SHA-1 hash of \p data, as 20 bytes.
+std::string git_sha1(const std::string &data);
+
+/// \return whether \p str matches the pattern \p pattern of a .gitignore file, where "*" does not
+/// match "/" but "**" does.
+bool git_wildmatch(const std::string &pattern, const std::string &str);
+
+#endif
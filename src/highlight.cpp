@@ -67,6 +67,8 @@ static const wchar_t *get_highlight_var_name(highlight_role_t role) {
             return L"fish_color_autosuggestion";
         case highlight_role_t::selection:
             return L"fish_color_selection";
+        case highlight_role_t::snippet_placeholder:
+            return L"fish_color_placeholder";
         case highlight_role_t::pager_progress:
             return L"fish_pager_color_progress";
         case highlight_role_t::pager_background:
@@ -127,6 +129,8 @@ static highlight_role_t get_fallback(highlight_role_t role) {
             return highlight_role_t::normal;
         case highlight_role_t::selection:
             return highlight_role_t::normal;
+        case highlight_role_t::snippet_placeholder:
+            return highlight_role_t::selection;
         case highlight_role_t::pager_progress:
             return highlight_role_t::normal;
         case highlight_role_t::pager_background:
@@ -315,9 +319,9 @@ static bool is_potential_cd_path(const wcstring &path, const wcstring &working_d
         directories.push_back(working_directory);
     } else {
         // Get the CDPATH.
+        const wcstring_list_t dflt_pathsv{L"."};
         auto cdpath = ctx.vars.get(L"CDPATH");
-        wcstring_list_t pathsv =
-            cdpath.missing_or_empty() ? wcstring_list_t{L"."} : cdpath->as_list();
+        const wcstring_list_t &pathsv = cdpath.missing_or_empty() ? dflt_pathsv : cdpath->as_list();
 
         for (auto next_path : pathsv) {
             if (next_path.empty()) next_path = L".";
@@ -994,6 +998,11 @@ void highlighter_t::visit(const ast::token_base_t &tok) {
 
 void highlighter_t::visit(const ast::semi_nl_t &semi_nl) {
     color_node(semi_nl, highlight_role_t::statement_terminator);
+    // The newline ending the line of a here-document is followed by its literal body.
+    source_range_t range = semi_nl.source_range();
+    if (range.length > 1) {
+        color_range(source_range_t{range.start + 1, range.length - 1}, highlight_role_t::quote);
+    }
 }
 
 void highlighter_t::visit(const ast::argument_t &arg, bool cmd_is_cd) {
@@ -1094,9 +1103,14 @@ void highlighter_t::visit(const ast::redirection_t &redir) {
     this->color_node(redir.oper, highlight_role_t::redirection);
 
     // Color the target part.
+    // A here-document is literal text, and a here-string is an ordinary argument.
+    if (oper->mode == redirection_mode_t::here_doc) {
+        this->color_node(redir.target, highlight_role_t::quote);
+        return;
+    }
     // Check if the argument contains a command substitution. If so, highlight it as a param
     // even though it's a command redirection, and don't try to do any other validation.
-    if (has_cmdsub(target)) {
+    if (has_cmdsub(target) || oper->mode == redirection_mode_t::here_string) {
         this->color_as_argument(redir.target);
     } else {
         // No command substitution, so we can highlight the target file or fd. For example,
@@ -1180,6 +1194,10 @@ void highlighter_t::visit(const ast::redirection_t &redir) {
                                       !(file_exists && oper->mode == redirection_mode_t::noclob);
                     break;
                 }
+                case redirection_mode_t::here_string:
+                case redirection_mode_t::here_doc: {
+                    DIE("here-string and here-document targets should be handled above");
+                }
             }
         }
         this->color_node(redir.target,
@@ -27,6 +27,7 @@ enum class highlight_role_t : uint8_t {
     redirection,           // redirection
     autosuggestion,        // autosuggestion
     selection,
+    snippet_placeholder,  // placeholder of a snippet inserted with commandline --insert-snippet
 
     // Pager support.
     // NOTE: pager.cpp relies on these being in this order.
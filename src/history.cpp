@@ -26,6 +26,7 @@
 #include <map>
 #include <numeric>
 #include <random>
+#include <set>
 #include <type_traits>
 #include <unordered_set>
 
@@ -288,6 +289,13 @@ struct history_impl_t {
     // List of old items, as offsets into out mmap data.
     std::deque<size_t> old_item_offsets{};
 
+    // How the history of other sessions was last merged into ours. This affects which items from
+    // the file end up in old_item_offsets.
+    history_merge_strategy_t merge_strategy{history_merge_strategy_t::interleave};
+
+    // The number of duplicate items skipped when populating old_item_offsets.
+    size_t dropped_duplicate_count{0};
+
     /// \return a timestamp for new items - see the implementation for a subtlety.
     time_t timestamp_now() const;
 
@@ -297,6 +305,15 @@ struct history_impl_t {
     // Figure out the offsets of our file contents.
     void populate_from_file_contents();
 
+    // \return the offsets of items in the file whose timestamp is newer than \p since but not newer
+    // than boundary_timestamp. Unlike old_item_offsets, this includes items skipped by the merge
+    // strategy.
+    std::vector<size_t> file_item_offsets_newer_than(time_t since) const;
+
+    // \return the (timestamp, contents) pairs of our new items, which identify our own items in
+    // the file.
+    std::set<std::pair<time_t, wcstring>> new_item_keys() const;
+
     // Loads old items if necessary.
     void load_old_if_needed();
 
@@ -357,7 +374,7 @@ struct history_impl_t {
     void populate_from_bash(FILE *stream);
 
     // Incorporates the history of other shells into this history.
-    void incorporate_external_changes();
+    history_merge_result_t incorporate_external_changes(history_merge_strategy_t strategy);
 
     // Gets all the history into a list. This is intended for the $history environment variable.
     // This may be long!
@@ -580,9 +597,66 @@ void history_impl_t::populate_from_file_contents() {
         }
     }
 
+    dropped_duplicate_count = 0;
+    if (file_contents && merge_strategy == history_merge_strategy_t::session &&
+        !new_items.empty()) {
+        // Our own items are returned from new_items, after everything in the file, so skip the
+        // copies we wrote. New items are in chronological order, so the first is the oldest.
+        auto own_keys = new_item_keys();
+        std::unordered_set<size_t> own_offsets;
+        for (size_t offset : file_item_offsets_newer_than(new_items.front().timestamp() - 1)) {
+            history_item_t item = file_contents->decode_item(offset);
+            if (own_keys.count({item.timestamp(), item.str()})) own_offsets.insert(offset);
+        }
+        old_item_offsets.erase(std::remove_if(old_item_offsets.begin(), old_item_offsets.end(),
+                                              [&](size_t offset) {
+                                                  return own_offsets.count(offset) > 0;
+                                              }),
+                               old_item_offsets.end());
+    } else if (file_contents && merge_strategy == history_merge_strategy_t::last_writer_wins) {
+        // Only keep the most recent occurrence of each command. Our new items are the most recent.
+        std::unordered_set<wcstring> seen;
+        for (const auto &item : new_items) seen.insert(item.str());
+        std::deque<size_t> kept;
+        for (auto iter = old_item_offsets.crbegin(); iter != old_item_offsets.crend(); ++iter) {
+            if (seen.insert(file_contents->decode_item(*iter).str()).second) {
+                kept.push_front(*iter);
+            } else {
+                dropped_duplicate_count++;
+            }
+        }
+        old_item_offsets = std::move(kept);
+    }
+
     FLOGF(history, "Loaded %lu old items", old_item_offsets.size());
 }
 
+std::vector<size_t> history_impl_t::file_item_offsets_newer_than(time_t since) const {
+    std::vector<size_t> result;
+    if (!file_contents) return result;
+
+    // The file only tells us which items are not newer than a cutoff, so collect those and return
+    // the rest.
+    std::unordered_set<size_t> older;
+    size_t cursor = 0;
+    while (auto offset = file_contents->offset_of_next_item(&cursor, since)) {
+        older.insert(*offset);
+    }
+    cursor = 0;
+    while (auto offset = file_contents->offset_of_next_item(&cursor, boundary_timestamp)) {
+        if (!older.count(*offset)) result.push_back(*offset);
+    }
+    return result;
+}
+
+std::set<std::pair<time_t, wcstring>> history_impl_t::new_item_keys() const {
+    std::set<std::pair<time_t, wcstring>> result;
+    for (const auto &item : new_items) {
+        result.emplace(item.timestamp(), item.str());
+    }
+    return result;
+}
+
 void history_impl_t::load_old_if_needed() {
     if (loaded_old) return;
     loaded_old = true;
@@ -1197,29 +1271,49 @@ void history_impl_t::populate_from_bash(FILE *stream) {
     this->save_unless_disabled();
 }
 
-void history_impl_t::incorporate_external_changes() {
+history_merge_result_t history_impl_t::incorporate_external_changes(
+    history_merge_strategy_t strategy) {
     // To incorporate new items, we simply update our timestamp to now, so that items from previous
     // instances get added. We then clear the file state so that we remap the file. Note that this
     // is somewhat expensive because we will be going back over old items. An optimization would be
     // to preserve old_item_offsets so that they don't have to be recomputed. (However, then items
     // *deleted* in other instances would not show up here).
+    history_merge_result_t result;
     time_t new_timestamp = time(nullptr);
 
     // If for some reason the clock went backwards, we don't want to start dropping items; therefore
-    // we only do work if time has progressed. This also makes multiple calls cheap.
-    if (new_timestamp > this->boundary_timestamp) {
-        this->boundary_timestamp = new_timestamp;
-        this->clear_file_state();
+    // we only do work if time has progressed or the strategy changed. This also makes multiple
+    // calls cheap.
+    if (new_timestamp <= this->boundary_timestamp && strategy == this->merge_strategy) {
+        return result;
+    }
+    time_t old_boundary = this->boundary_timestamp;
+    this->boundary_timestamp = std::max(new_timestamp, old_boundary);
+    this->merge_strategy = strategy;
+    this->clear_file_state();
+    this->save(false);
 
+    // Remember which items are ours, so we can report only those from other sessions.
+    auto own_keys = this->new_item_keys();
+    if (strategy != history_merge_strategy_t::session) {
         // We also need to erase new items, since we go through those first, and that means we
         // will not properly interleave them with items from other instances.
         // We'll pick them up from the file (#2312)
         // TODO: this will drop items that had no_persist set, how can we avoid that while still
         // properly interleaving?
-        this->save(false);
         this->new_items.clear();
         this->first_unwritten_new_item_index = 0;
     }
+
+    this->load_old_if_needed();
+    if (file_contents) {
+        for (size_t offset : file_item_offsets_newer_than(old_boundary)) {
+            history_item_t item = file_contents->decode_item(offset);
+            if (!own_keys.count({item.timestamp(), item.str()})) result.merged++;
+        }
+    }
+    result.dropped = this->dropped_duplicate_count;
+    return result;
 }
 
 /// Return the prefix for the files to be used for command and read history.
@@ -1473,7 +1567,9 @@ void history_t::populate_from_config_path() { impl()->populate_from_config_path(
 
 void history_t::populate_from_bash(FILE *f) { impl()->populate_from_bash(f); }
 
-void history_t::incorporate_external_changes() { impl()->incorporate_external_changes(); }
+history_merge_result_t history_t::incorporate_external_changes(history_merge_strategy_t strategy) {
+    return impl()->incorporate_external_changes(strategy);
+}
 
 void history_t::get_history(wcstring_list_t &result) { impl()->get_history(result); }
 
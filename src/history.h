@@ -61,6 +61,22 @@ enum class history_search_type_t {
     match_everything,
 };
 
+/// Ways that the history of other sessions may be merged into ours by history merge.
+enum class history_merge_strategy_t {
+    interleave,        // all items are ordered by the time they were run, regardless of session
+    session,           // items from other sessions are inserted before those of this session
+    last_writer_wins,  // like interleave, but a command only appears at its most recent use
+};
+
+/// What history merge did.
+struct history_merge_result_t {
+    // The number of items from other sessions that were merged, including the older duplicates
+    // among them that were then dropped.
+    size_t merged{0};
+    // The number of older duplicates that were dropped (only with last_writer_wins).
+    size_t dropped{0};
+};
+
 typedef uint64_t history_identifier_t;
 
 /// Ways that a history item may be written to disk (or omitted).
@@ -207,8 +223,9 @@ class history_t {
     // Populates from a bash history file.
     void populate_from_bash(FILE *f);
 
-    // Incorporates the history of other shells into this history.
-    void incorporate_external_changes();
+    // Incorporates the history of other shells into this history, using the given strategy.
+    history_merge_result_t incorporate_external_changes(
+        history_merge_strategy_t strategy = history_merge_strategy_t::interleave);
 
     // Gets all the history into a list. This is intended for the $history environment variable.
     // This may be long!
@@ -130,6 +130,7 @@ static const input_function_metadata_t input_function_metadata[] = {
     {readline_cmd_t::beginning_of_buffer, L"beginning-of-buffer"},
     {readline_cmd_t::end_of_buffer, L"end-of-buffer"},
     {readline_cmd_t::repaint_mode, L"repaint-mode"},
+    {readline_cmd_t::repaint_right_prompt, L"repaint-right-prompt"},
     {readline_cmd_t::repaint, L"repaint"},
     {readline_cmd_t::force_repaint, L"force-repaint"},
     {readline_cmd_t::up_line, L"up-line"},
@@ -275,6 +276,8 @@ static maybe_t<char_event_t> interrupt_handler() {
     // TODO: eliminate this principal_parser().
     auto &parser = parser_t::principal_parser();
     event_fire_delayed(parser);
+    event_fire_intervals(parser);
+    event_fire_file_changes(parser);
     // Reap stray processes, including printing exit status messages.
     // TODO: shouldn't need this parser here.
     if (job_reap(parser, true)) reader_schedule_prompt_repaint();
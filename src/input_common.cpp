@@ -23,11 +23,14 @@
 #include "common.h"
 #include "env.h"
 #include "env_universal_common.h"
+#include "event.h"
 #include "fallback.h"  // IWYU pragma: keep
 #include "flog.h"
 #include "global_safety.h"
 #include "input_common.h"
 #include "iothread.h"
+#include "output.h"
+#include "signal.h"
 #include "wutil.h"
 
 /// Time in milliseconds to wait for another byte to be available for reading
@@ -57,6 +60,23 @@ char_event_t input_event_queue_t::readb() {
             fd_max = std::max(fd_max, ioport);
         }
 
+        // Signals are delivered through the wakeup fd.
+        int wakeup_fd = signal_get_wakeup_fd();
+        if (wakeup_fd >= 0) {
+            FD_SET(wakeup_fd, &fdset);
+            fd_max = std::max(fd_max, wakeup_fd);
+        }
+
+        // Output which could not be written before, like while the terminal was stopped, is
+        // written once the terminal can take it.
+        outputter_t &outp = outputter_t::stdoutput();
+        fd_set writefds;
+        FD_ZERO(&writefds);
+        if (outp.has_pending_output()) {
+            FD_SET(outp.fd(), &writefds);
+            fd_max = std::max(fd_max, outp.fd());
+        }
+
         // Get our uvar notifier.
         universal_notifier_t& notifier = universal_notifier_t::default_notifier();
 
@@ -67,18 +87,43 @@ char_event_t input_event_queue_t::readb() {
             fd_max = std::max(fd_max, notifier_fd);
         }
 
-        // Get its suggested delay (possibly none).
+        // Interval and file change event handlers are run by the interrupt handler, so there is
+        // nothing to wake up for without one.
+        long event_delay = -1;
+        int watch_fd = -1;
+        if (interrupt_handler) {
+            event_delay = event_interval_usec_delay();
+            long watch_delay = event_file_watch_usec_delay();
+            if (watch_delay >= 0 && (event_delay < 0 || watch_delay < event_delay)) {
+                event_delay = watch_delay;
+            }
+            watch_fd = event_file_watch_fd();
+            if (watch_fd >= 0) {
+                FD_SET(watch_fd, &fdset);
+                fd_max = std::max(fd_max, watch_fd);
+            }
+        }
+
+        // Get its suggested delay (possibly none), and wake up for event handlers too.
         struct timeval tv = {};
-        const unsigned long usecs_delay = notifier.usec_delay_between_polls();
+        unsigned long usecs_delay = notifier.usec_delay_between_polls();
+        if (event_delay >= 0 &&
+            (usecs_delay == 0 || event_delay < static_cast<long>(usecs_delay))) {
+            // A delay of 0 means to wait forever, so wait for at least a microsecond.
+            usecs_delay = std::max(event_delay, 1L);
+        }
         if (usecs_delay > 0) {
             unsigned long usecs_per_sec = 1000000;
             tv.tv_sec = static_cast<int>(usecs_delay / usecs_per_sec);
             tv.tv_usec = static_cast<int>(usecs_delay % usecs_per_sec);
         }
 
-        res = select(fd_max + 1, &fdset, nullptr, nullptr, usecs_delay > 0 ? &tv : nullptr);
+        res = select(fd_max + 1, &fdset, &writefds, nullptr, usecs_delay > 0 ? &tv : nullptr);
         if (res == -1) {
-            if (errno == EINTR || errno == EAGAIN) {
+            if ((errno == EINTR || errno == EAGAIN) && wakeup_fd >= 0) {
+                // The signal handler has made the wakeup fd readable, so we handle it below.
+                continue;
+            } else if (errno == EINTR || errno == EAGAIN) {
                 // Some uvar notifiers rely on signals - see #7671.
                 if (notifier.poll()) {
                     env_universal_barrier();
@@ -110,6 +155,36 @@ char_event_t input_event_queue_t::readb() {
                 }
             }
 
+            if (outp.has_pending_output() && FD_ISSET(outp.fd(), &writefds)) {
+                outp.flush_pending_output();
+            }
+
+            // Handle signals before reading input, so their handlers run before any keys typed
+            // after the signal arrived.
+            if (wakeup_fd >= 0 && FD_ISSET(wakeup_fd, &fdset)) {
+                signal_clear_wakeup();
+                if (interrupt_handler) {
+                    if (auto interrupt_evt = interrupt_handler()) {
+                        return *interrupt_evt;
+                    } else if (auto mc = pop_discard_timeouts()) {
+                        return *mc;
+                    }
+                }
+            }
+
+            // Run interval and file change event handlers which are due, before reading input like
+            // signals.
+            bool events_due = (watch_fd >= 0 && FD_ISSET(watch_fd, &fdset)) ||
+                              (event_delay >= 0 && (event_interval_usec_delay() == 0 ||
+                                                    event_file_watch_usec_delay() == 0));
+            if (events_due) {
+                if (auto interrupt_evt = interrupt_handler()) {
+                    return *interrupt_evt;
+                } else if (auto mc = pop_discard_timeouts()) {
+                    return *mc;
+                }
+            }
+
             if (FD_ISSET(in_, &fdset)) {
                 unsigned char arr[1];
                 if (read_blocked(in_, arr, 1) != 1) {
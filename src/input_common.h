@@ -55,6 +55,7 @@ enum class readline_cmd_t {
     beginning_of_buffer,
     end_of_buffer,
     repaint_mode,
+    repaint_right_prompt,
     repaint,
     force_repaint,
     up_line,
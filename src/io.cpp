@@ -5,6 +5,7 @@
 
 #include <errno.h>
 #include <fcntl.h>
+#include <limits.h>
 #include <stddef.h>
 #include <stdio.h>
 #include <unistd.h>
@@ -136,17 +137,19 @@ void io_buffer_t::begin_filling(autoclose_fd_t fd) {
 }
 
 separated_buffer_t io_buffer_t::complete_background_fillthread_and_take_buffer() {
-    // Mark that our fillthread is done, then wake it up.
     ASSERT_IS_MAIN_THREAD();
-    assert(fillthread_running() && "Should have a fillthread");
-    assert(this->item_id_ > 0 && "Should have a valid item ID");
-    shutdown_fillthread_ = true;
-    fd_monitor().poke_item(this->item_id_);
-
-    // Wait for the fillthread to fulfill its promise, and then clear the future so we know we no
-    // longer have one.
-    fill_waiter_->get_future().wait();
-    fill_waiter_.reset();
+    // A buffer filled in-process has no fillthread.
+    if (fillthread_running()) {
+        // Mark that our fillthread is done, then wake it up.
+        assert(this->item_id_ > 0 && "Should have a valid item ID");
+        shutdown_fillthread_ = true;
+        fd_monitor().poke_item(this->item_id_);
+
+        // Wait for the fillthread to fulfill its promise, and then clear the future so we know we
+        // no longer have one.
+        fill_waiter_->get_future().wait();
+        fill_waiter_.reset();
+    }
 
     // Return our buffer, transferring ownership.
     auto locked_buff = buffer_.acquire();
@@ -177,6 +180,12 @@ shared_ptr<io_bufferfill_t> io_bufferfill_t::create(size_t buffer_limit, int tar
     return std::make_shared<io_bufferfill_t>(target, std::move(pipes->write), buffer);
 }
 
+shared_ptr<io_bufferfill_t> io_bufferfill_t::create_in_process(size_t buffer_limit, int target) {
+    assert(target >= 0 && "Invalid target fd");
+    auto buffer = std::make_shared<io_buffer_t>(buffer_limit);
+    return std::make_shared<io_bufferfill_t>(target, autoclose_fd_t{}, buffer);
+}
+
 separated_buffer_t io_bufferfill_t::finish(std::shared_ptr<io_bufferfill_t> &&filler) {
     // The io filler is passed in. This typically holds the only instance of the write side of the
     // pipe used by the buffer's fillthread (except for that side held by other processes). Get the
@@ -213,6 +222,23 @@ void io_chain_t::append(const io_chain_t &chain) {
     this->insert(this->end(), chain.begin(), chain.end());
 }
 
+void write_to_pipe_and_close(autoclose_fd_t write_end, std::string data) {
+    if (data.size() <= PIPE_BUF) {
+        if (write_loop(write_end.fd(), data.data(), data.size()) < 0) {
+            wperror(L"write");
+        }
+        return;
+    }
+    // std::function must be copyable, so share the fd.
+    auto fd = std::make_shared<autoclose_fd_t>(std::move(write_end));
+    iothread_perform_cantwait([fd, data] {
+        // The reader may legitimately exit before consuming everything, e.g. with head.
+        if (write_loop(fd->fd(), data.data(), data.size()) < 0 && errno != EPIPE) {
+            wperror(L"write");
+        }
+    });
+}
+
 bool io_chain_t::append_from_specs(const redirection_spec_list_t &specs, const wcstring &pwd) {
     bool have_error = false;
     for (const auto &spec : specs) {
@@ -228,6 +254,20 @@ bool io_chain_t::append_from_specs(const redirection_spec_list_t &specs, const w
                 }
                 break;
             }
+            case redirection_mode_t::here_string:
+            case redirection_mode_t::here_doc: {
+                // Provide the text through a pipe.
+                auto pipes = make_autoclose_pipes();
+                if (!pipes) {
+                    this->push_back(make_unique<io_close_t>(spec.fd));
+                    have_error = true;
+                    break;
+                }
+                write_to_pipe_and_close(std::move(pipes->write), wcs2string(spec.target));
+                this->push_back(
+                    std::make_shared<io_pipe_t>(spec.fd, true /* input */, std::move(pipes->read)));
+                break;
+            }
             default: {
                 // We have a path-based redireciton. Resolve it to a file.
                 // Mark it as CLO_EXEC because we don't want it to be open in any child.
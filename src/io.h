@@ -251,6 +251,7 @@ class io_chain_t;
 /// Represents filling an io_buffer_t. Very similar to io_pipe_t.
 class io_bufferfill_t final : public io_data_t {
     /// Write end. The other end is connected to an io_buffer_t.
+    /// This is invalid if the buffer is only filled in-process; see create_in_process().
     const autoclose_fd_t write_fd_;
 
     /// The receiving buffer.
@@ -264,9 +265,7 @@ class io_bufferfill_t final : public io_data_t {
     io_bufferfill_t(int target, autoclose_fd_t write_fd, std::shared_ptr<io_buffer_t> buffer)
         : io_data_t(io_mode_t::bufferfill, target, write_fd.fd()),
           write_fd_(std::move(write_fd)),
-          buffer_(std::move(buffer)) {
-        assert(write_fd_.valid() && "fd is not valid");
-    }
+          buffer_(std::move(buffer)) {}
 
     ~io_bufferfill_t() override;
 
@@ -278,6 +277,13 @@ class io_bufferfill_t final : public io_data_t {
     /// \param target the fd which this will be dup2'd to - typically stdout.
     static shared_ptr<io_bufferfill_t> create(size_t buffer_limit = 0, int target = STDOUT_FILENO);
 
+    /// Create an io_bufferfill_t which is only filled by builtins writing to the buffer directly,
+    /// without a pipe or a fillthread. This is much cheaper, but anything which needs an actual
+    /// fd, like an external command, will find the target closed; the caller must make sure there
+    /// is no such thing.
+    static shared_ptr<io_bufferfill_t> create_in_process(size_t buffer_limit = 0,
+                                                         int target = STDOUT_FILENO);
+
     /// Reset the receiver (possibly closing the write end of the pipe), and complete the fillthread
     /// of the buffer. \return the buffer.
     static separated_buffer_t finish(std::shared_ptr<io_bufferfill_t> &&filler);
@@ -310,7 +316,8 @@ public:
     /// Begin the fill operation, reading from the given fd in the background.
     void begin_filling(autoclose_fd_t readfd);
 
-    /// End the background fillthread operation, and return the buffer, transferring ownership.
+    /// End the background fillthread operation, if any, and return the buffer, transferring
+    /// ownership.
     separated_buffer_t complete_background_fillthread_and_take_buffer();
 
     /// Helper to return whether the fillthread is running.
@@ -356,6 +363,11 @@ class io_chain_t : public std::vector<io_data_ref_t> {
     void print() const;
 };
 
+/// Write \p data to the write end of a pipe and close it. Small amounts of data fit in the pipe
+/// buffer and are written directly; otherwise we write from a background thread, so that the
+/// reader may start consuming it.
+void write_to_pipe_and_close(autoclose_fd_t write_end, std::string data);
+
 /// Base class representing the output that a builtin can generate.
 /// This has various subclasses depending on the ultimate output destination.
 class output_stream_t {
@@ -161,6 +161,8 @@ static const notify_pipes_t &get_notify_pipes() {
         // Mark both ends as non-blocking.
         if (make_fd_nonblocking(pipes->read.fd())) wperror(L"fcntl");
         if (make_fd_nonblocking(pipes->write.fd())) wperror(L"fcntl");
+        fd_set_purpose(pipes->read.fd(), N_(L"background thread notifier"));
+        fd_set_purpose(pipes->write.fd(), N_(L"background thread notifier"));
         return notify_pipes_t{pipes->read.acquire(), pipes->write.acquire()};
     }();
     return s_notify_pipes;
@@ -593,3 +595,34 @@ uint64_t debounce_t::perform_impl(std::function<void()> handler, std::function<v
 debounce_t::debounce_t(long timeout_msec)
     : timeout_msec_(timeout_msec), impl_(std::make_shared<impl_t>()) {}
 debounce_t::~debounce_t() = default;
+
+task_scope_t::task_scope_t() : generation_(std::make_shared<std::atomic<uint64_t>>(0)) {}
+
+void task_scope_t::cancel_all() { generation_->fetch_add(1, std::memory_order_relaxed); }
+
+std::function<bool()> task_scope_t::cancel_checker() const {
+    auto generation = generation_;
+    uint64_t spawned_in = generation->load(std::memory_order_relaxed);
+    return [=] { return generation->load(std::memory_order_relaxed) != spawned_in; };
+}
+
+void task_scope_t::spawn_impl(std::function<void()> handler, std::function<void()> completion,
+                              debounce_t *debounce) {
+    ASSERT_IS_MAIN_THREAD();
+    // Cancellation is final, so a completion never runs without its handler.
+    auto cancelled = cancel_checker();
+    std::function<void()> wrapped_handler = [=] {
+        if (!cancelled()) handler();
+    };
+    std::function<void()> wrapped_completion;
+    if (completion) {
+        wrapped_completion = [=] {
+            if (!cancelled()) completion();
+        };
+    }
+    if (debounce) {
+        debounce->perform(std::move(wrapped_handler), std::move(wrapped_completion));
+    } else {
+        iothread_perform_impl(std::move(wrapped_handler), std::move(wrapped_completion));
+    }
+}
@@ -4,6 +4,7 @@
 
 #include <pthread.h>
 
+#include <atomic>
 #include <cstdint>  // for uint64_t
 #include <functional>
 #include <memory>
@@ -124,4 +125,40 @@ class debounce_t {
     const std::shared_ptr<impl_t> impl_;
 };
 
+/// A task scope groups background tasks which do work for the same thing, like the autosuggestion
+/// for the current command line. When that thing changes, cancel_all() cancels every task spawned
+/// in the scope so far: a task which has not started yet is skipped, a running one sees it through
+/// its cancel checker, and the completion of each is dropped instead of run on the main thread.
+/// Tasks spawned after cancel_all() are not affected.
+class task_scope_t {
+   public:
+    task_scope_t();
+
+    /// Cancel the tasks spawned so far.
+    void cancel_all();
+
+    /// \return a cancel checker which returns true once the tasks spawned now are cancelled.
+    /// Handlers should poll it while they work.
+    std::function<bool()> cancel_checker() const;
+
+    /// Perform \p handler on a background thread, and then \p completion with its result on the
+    /// main thread, like iothread_perform, unless the task is cancelled first. If \p debounce is
+    /// given, the task goes through it, so it supersedes a task there which has not started yet.
+    template <typename Handler, typename Completion>
+    void spawn(const Handler &handler, const Completion &completion,
+               debounce_t *debounce = nullptr) {
+        iothread_trampoline_t<Handler, Completion> tramp(handler, completion);
+        spawn_impl(std::move(tramp.handler), std::move(tramp.completion), debounce);
+    }
+
+   private:
+    /// Implementation of spawn().
+    void spawn_impl(std::function<void()> handler, std::function<void()> completion,
+                    debounce_t *debounce);
+
+    /// The generation of the scope, which cancel_all() increments. Tasks are cancelled once it
+    /// differs from the one they were spawned with.
+    const std::shared_ptr<std::atomic<uint64_t>> generation_;
+};
+
 #endif
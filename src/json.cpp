@@ -0,0 +1,355 @@
+// A small JSON reader and writer.
+#include "config.h"  // IWYU pragma: keep
+
+#include "json.h"
+
+#include <errno.h>
+#include <stdlib.h>
+#include <strings.h>
+
+#include <cmath>
+#include <cstring>
+#include <cwchar>
+
+#include "common.h"
+#include "flog.h"
+#include "wcstringutil.h"
+#include "wutil.h"  // IWYU pragma: keep
+
+const json_value_t *json_value_t::get(const wchar_t *key) const {
+    if (type != type_t::object) return nullptr;
+    for (const auto &member : members) {
+        if (member.first == key) return &member.second;
+    }
+    return nullptr;
+}
+
+const wcstring *json_value_t::get_string(const wchar_t *key) const {
+    const json_value_t *member = get(key);
+    if (!member || member->type != type_t::string) return nullptr;
+    return &member->string;
+}
+
+namespace {
+/// A recursive descent parser for JSON text.
+class json_parser_t {
+   public:
+    explicit json_parser_t(const wcstring &text) : text(text) {}
+
+    /// Parse the text as a single value.
+    maybe_t<json_value_t> parse_all() {
+        json_value_t result;
+        if (!parse_value(&result, 0)) return none();
+        skip_space();
+        if (pos != text.size()) return none();
+        return result;
+    }
+
+   private:
+    /// Nesting deeper than this is rejected, so hostile input cannot exhaust the stack.
+    static constexpr size_t max_depth = 512;
+
+    const wcstring &text;
+    size_t pos{0};
+
+    wchar_t peek() const { return pos < text.size() ? text.at(pos) : L'\0'; }
+
+    void skip_space() {
+        while (pos < text.size() && std::wcschr(L" \t\r\n", text.at(pos))) pos++;
+    }
+
+    bool consume(const wchar_t *word) {
+        size_t len = std::wcslen(word);
+        if (text.compare(pos, len, word) != 0) return false;
+        pos += len;
+        return true;
+    }
+
+    bool parse_hex4(unsigned *out) {
+        if (pos + 4 > text.size()) return false;
+        unsigned val = 0;
+        for (size_t i = 0; i < 4; i++) {
+            wchar_t c = text.at(pos + i);
+            unsigned digit;
+            if (c >= L'0' && c <= L'9') {
+                digit = c - L'0';
+            } else if (c >= L'a' && c <= L'f') {
+                digit = c - L'a' + 10;
+            } else if (c >= L'A' && c <= L'F') {
+                digit = c - L'A' + 10;
+            } else {
+                return false;
+            }
+            val = val * 16 + digit;
+        }
+        pos += 4;
+        *out = val;
+        return true;
+    }
+
+    bool parse_string(wcstring *out) {
+        if (peek() != L'"') return false;
+        pos++;
+        while (pos < text.size()) {
+            wchar_t c = text.at(pos++);
+            if (c == L'"') return true;
+            if (c < 0x20) return false;
+            if (c != L'\\') {
+                out->push_back(c);
+                continue;
+            }
+            if (pos >= text.size()) return false;
+            c = text.at(pos++);
+            switch (c) {
+                case L'"':
+                case L'\\':
+                case L'/':
+                    out->push_back(c);
+                    break;
+                case L'b':
+                    out->push_back(L'\b');
+                    break;
+                case L'f':
+                    out->push_back(L'\f');
+                    break;
+                case L'n':
+                    out->push_back(L'\n');
+                    break;
+                case L'r':
+                    out->push_back(L'\r');
+                    break;
+                case L't':
+                    out->push_back(L'\t');
+                    break;
+                case L'u': {
+                    unsigned code;
+                    if (!parse_hex4(&code)) return false;
+                    // Combine a UTF-16 surrogate pair into a single character.
+                    if (code >= 0xD800 && code < 0xDC00) {
+                        size_t saved_pos = pos;
+                        unsigned low;
+                        if (consume(L"\\u") && parse_hex4(&low) && low >= 0xDC00 && low < 0xE000) {
+                            code = 0x10000 + ((code - 0xD800) << 10) + (low - 0xDC00);
+                        } else {
+                            pos = saved_pos;
+                        }
+                    }
+                    out->push_back(static_cast<wchar_t>(code));
+                    break;
+                }
+                default:
+                    return false;
+            }
+        }
+        return false;
+    }
+
+    bool parse_number(double *out) {
+        size_t start = pos;
+        if (peek() == L'-') pos++;
+        if (!iswdigit(peek())) return false;
+        while (pos < text.size() && std::wcschr(L"0123456789+-.eE", text.at(pos))) pos++;
+        wcstring num = text.substr(start, pos - start);
+        wchar_t *end = nullptr;
+        *out = fish_wcstod(num.c_str(), &end);
+        return end && *end == L'\0';
+    }
+
+    bool parse_value(json_value_t *out, size_t depth) {
+        if (depth > max_depth) return false;
+        skip_space();
+        using type_t = json_value_t::type_t;
+        switch (peek()) {
+            case L'{': {
+                pos++;
+                out->type = type_t::object;
+                skip_space();
+                if (peek() == L'}') {
+                    pos++;
+                    return true;
+                }
+                for (;;) {
+                    skip_space();
+                    wcstring key;
+                    if (!parse_string(&key)) return false;
+                    skip_space();
+                    if (!consume(L":")) return false;
+                    json_value_t val;
+                    if (!parse_value(&val, depth + 1)) return false;
+                    out->members.emplace_back(std::move(key), std::move(val));
+                    skip_space();
+                    if (consume(L"}")) return true;
+                    if (!consume(L",")) return false;
+                }
+            }
+            case L'[': {
+                pos++;
+                out->type = type_t::array;
+                skip_space();
+                if (peek() == L']') {
+                    pos++;
+                    return true;
+                }
+                for (;;) {
+                    json_value_t val;
+                    if (!parse_value(&val, depth + 1)) return false;
+                    out->array.push_back(std::move(val));
+                    skip_space();
+                    if (consume(L"]")) return true;
+                    if (!consume(L",")) return false;
+                }
+            }
+            case L'"': {
+                out->type = type_t::string;
+                return parse_string(&out->string);
+            }
+            case L't':
+            case L'f': {
+                out->type = type_t::boolean;
+                out->boolean = peek() == L't';
+                return consume(out->boolean ? L"true" : L"false");
+            }
+            case L'n': {
+                out->type = type_t::null;
+                return consume(L"null");
+            }
+            default: {
+                out->type = type_t::number;
+                return parse_number(&out->number);
+            }
+        }
+    }
+};
+}  // namespace
+
+maybe_t<json_value_t> json_parse(const wcstring &text) { return json_parser_t(text).parse_all(); }
+
+wcstring json_string(const wcstring &str) {
+    wcstring result = L"\"";
+    for (wchar_t c : str) {
+        switch (c) {
+            case L'"':
+                result.append(L"\\\"");
+                break;
+            case L'\\':
+                result.append(L"\\\\");
+                break;
+            case L'\n':
+                result.append(L"\\n");
+                break;
+            case L'\t':
+                result.append(L"\\t");
+                break;
+            case L'\r':
+                result.append(L"\\r");
+                break;
+            default:
+                if (c < 0x20) {
+                    append_format(result, L"\\u%04x", static_cast<unsigned>(c));
+                } else {
+                    result.push_back(c);
+                }
+                break;
+        }
+    }
+    result.push_back(L'"');
+    return result;
+}
+
+wcstring json_string_list(const wcstring_list_t &list) {
+    wcstring result = L"[";
+    for (const wcstring &str : list) {
+        if (result.size() > 1) result.push_back(L',');
+        result.append(json_string(str));
+    }
+    result.push_back(L']');
+    return result;
+}
+
+wcstring json_dump(const json_value_t &val) {
+    using type_t = json_value_t::type_t;
+    switch (val.type) {
+        case type_t::null:
+            return L"null";
+        case type_t::boolean:
+            return val.boolean ? L"true" : L"false";
+        case type_t::number: {
+            // Print integers exactly, as they are commonly used as request ids.
+            if (std::isfinite(val.number) && val.number == std::floor(val.number) &&
+                std::fabs(val.number) < 1e15) {
+                return to_string(static_cast<long>(val.number));
+            }
+            return format_string(L"%.17g", val.number);
+        }
+        case type_t::string:
+            return json_string(val.string);
+        case type_t::array: {
+            wcstring result = L"[";
+            for (const json_value_t &elem : val.array) {
+                if (result.size() > 1) result.push_back(L',');
+                result.append(json_dump(elem));
+            }
+            result.push_back(L']');
+            return result;
+        }
+        case type_t::object: {
+            wcstring result = L"{";
+            for (const auto &member : val.members) {
+                if (result.size() > 1) result.push_back(L',');
+                result.append(json_string(member.first));
+                result.push_back(L':');
+                result.append(json_dump(member.second));
+            }
+            result.push_back(L'}');
+            return result;
+        }
+    }
+    DIE("unexpected JSON value type");
+}
+
+bool json_read_message(int fd, std::string *buffer, wcstring *out_body) {
+    std::string &input = *buffer;
+    maybe_t<size_t> content_length;
+    for (;;) {
+        size_t line_end;
+        while ((line_end = input.find("\r\n")) == std::string::npos) {
+            char buff[4096];
+            ssize_t amt = read_loop(fd, buff, sizeof buff);
+            if (amt <= 0) return false;
+            input.append(buff, amt);
+        }
+        std::string line = input.substr(0, line_end);
+        input.erase(0, line_end + 2);
+        if (line.empty()) break;
+
+        static const char content_length_header[] = "content-length:";
+        size_t header_len = std::strlen(content_length_header);
+        if (line.size() > header_len &&
+            strncasecmp(line.c_str(), content_length_header, header_len) == 0) {
+            errno = 0;
+            char *end = nullptr;
+            unsigned long long len = strtoull(line.c_str() + header_len, &end, 10);
+            if (errno || *end != '\0') return false;
+            content_length = static_cast<size_t>(len);
+        }
+    }
+    if (!content_length) {
+        FLOGF(error, L"Message has no Content-Length header");
+        return false;
+    }
+    while (input.size() < *content_length) {
+        char buff[4096];
+        ssize_t amt = read_loop(fd, buff, sizeof buff);
+        if (amt <= 0) return false;
+        input.append(buff, amt);
+    }
+    *out_body = str2wcstring(input.substr(0, *content_length));
+    input.erase(0, *content_length);
+    return true;
+}
+
+bool json_write_message(int fd, const wcstring &body) {
+    std::string narrow = wcs2string(body);
+    std::string message = "Content-Length: " + std::to_string(narrow.size()) + "\r\n\r\n" + narrow;
+    return write_loop(fd, message.data(), message.size()) >= 0;
+}
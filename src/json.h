@@ -0,0 +1,56 @@
+// A small JSON reader and writer.
+#ifndef FISH_JSON_H
+#define FISH_JSON_H
+
+#include <string>
+#include <utility>
+#include <vector>
+
+#include "common.h"
+#include "maybe.h"
+
+/// A JSON value.
+struct json_value_t {
+    enum class type_t { null, boolean, number, string, array, object };
+    type_t type{type_t::null};
+
+    bool boolean{false};
+    double number{0};
+    wcstring string;
+    std::vector<json_value_t> array;
+
+    /// The members of an object, in the order they appeared.
+    std::vector<std::pair<wcstring, json_value_t>> members;
+
+    /// \return the member named \p key, or nullptr if this is not an object or it has no such
+    /// member.
+    const json_value_t *get(const wchar_t *key) const;
+
+    /// \return the string member named \p key, or nullptr if there is no such member or it is not
+    /// a string.
+    const wcstring *get_string(const wchar_t *key) const;
+};
+
+/// Parse \p text as a single JSON value, surrounded by optional whitespace.
+/// \return none() if the text is not valid JSON.
+maybe_t<json_value_t> json_parse(const wcstring &text);
+
+/// \return \p str as a quoted JSON string.
+wcstring json_string(const wcstring &str);
+
+/// \return \p list as a JSON array of strings.
+wcstring json_string_list(const wcstring_list_t &list);
+
+/// \return \p val serialized as JSON, without any whitespace.
+wcstring json_dump(const json_value_t &val);
+
+/// Read a message framed as in the base protocol of the Language Server and Debug Adapter
+/// protocols: headers including Content-Length, a blank line, and then that many bytes of JSON.
+/// \p buffer holds input which has been read from \p fd but not yet returned.
+/// \return false on end of input or a malformed header.
+bool json_read_message(int fd, std::string *buffer, wcstring *out_body);
+
+/// Write \p body to \p fd as a base protocol message. \return false if it could not be written.
+bool json_write_message(int fd, const wcstring &body);
+
+#endif
@@ -55,3 +55,29 @@ wcstring kill_yank() {
     }
     return kill_list.front();
 }
+
+/// The shared killring as we last saw it.
+static wcstring_list_t last_shared_kill_list;
+
+void kill_merge_shared(const wcstring_list_t &shared) {
+    ASSERT_IS_MAIN_THREAD();
+    // Go from the oldest entry to the most recent, so the most recent ends up on top.
+    for (auto iter = shared.rbegin(); iter != shared.rend(); ++iter) {
+        if (contains(last_shared_kill_list, *iter)) continue;
+        kill_remove(*iter);
+        kill_list.push_front(*iter);
+    }
+    last_shared_kill_list = shared;
+}
+
+wcstring_list_t kill_update_shared(const wcstring_list_t &shared, const wcstring &old,
+                                   const wcstring &newv, size_t max_size) {
+    ASSERT_IS_MAIN_THREAD();
+    wcstring_list_t result{newv};
+    for (const wcstring &entry : shared) {
+        if (result.size() >= max_size) break;
+        if (entry != newv && (old.empty() || entry != old)) result.push_back(entry);
+    }
+    last_shared_kill_list = result;
+    return result;
+}
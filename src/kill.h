@@ -19,4 +19,17 @@ wcstring kill_yank_rotate();
 /// Paste from the killring.
 wcstring kill_yank();
 
+/// The killring may be shared with other sessions, through a list of entries with the most recent
+/// first, e.g. in a universal variable.
+
+/// Move the entries of the shared killring \p shared which are new since we last saw it to the top
+/// of our killring.
+void kill_merge_shared(const wcstring_list_t &shared);
+
+/// \return the shared killring \p shared with \p newv added on top, replacing \p old if that is
+/// not empty, and cut down to \p max_size entries. The result is remembered as seen, so our own
+/// entries are not merged back in.
+wcstring_list_t kill_update_shared(const wcstring_list_t &shared, const wcstring &old,
+                                   const wcstring &newv, size_t max_size);
+
 #endif
@@ -0,0 +1,468 @@
+// A language server for fish scripts, run by `fish --lsp`.
+//
+// This speaks the Language Server Protocol as JSON-RPC over a pair of file descriptors. Documents
+// are always synchronized in full, and positions are converted between character offsets and the
+// protocol's lines and UTF-16 columns at the edges.
+#include "config.h"  // IWYU pragma: keep
+
+#include "lsp.h"
+
+#include <cstring>
+#include <map>
+#include <string>
+#include <utility>
+
+#include "ast.h"
+#include "builtin.h"
+#include "common.h"
+#include "complete.h"
+#include "fallback.h"  // IWYU pragma: keep
+#include "fish_indent_common.h"
+#include "fish_version.h"
+#include "function.h"
+#include "json.h"
+#include "operation_context.h"
+#include "parse_constants.h"
+#include "parse_util.h"
+#include "parser.h"
+#include "path.h"
+#include "reader.h"
+#include "wcstringutil.h"
+#include "wutil.h"  // IWYU pragma: keep
+
+namespace {
+/// JSON-RPC error codes.
+enum {
+    lsp_error_parse = -32700,
+    lsp_error_invalid_request = -32600,
+    lsp_error_method_not_found = -32601,
+};
+
+/// LSP diagnostic severities.
+enum { lsp_severity_error = 1 };
+
+/// \return the member \p key of \p val, which may be null, or null if there is no such member.
+const json_value_t *get_member(const json_value_t *val, const wchar_t *key) {
+    return val ? val->get(key) : nullptr;
+}
+
+/// \return the number of UTF-16 code units needed to encode \p c.
+size_t utf16_length(wchar_t c) { return static_cast<unsigned long>(c) > 0xFFFF ? 2 : 1; }
+
+/// \return the LSP position of the character at \p offset in \p text, as JSON.
+wcstring position_json(const wcstring &text, size_t offset) {
+    offset = std::min(offset, text.size());
+    size_t line = 0, column = 0;
+    for (size_t i = 0; i < offset; i++) {
+        if (text.at(i) == L'\n') {
+            line++;
+            column = 0;
+        } else {
+            column += utf16_length(text.at(i));
+        }
+    }
+    return format_string(L"{\"line\":%lu,\"character\":%lu}", static_cast<unsigned long>(line),
+                         static_cast<unsigned long>(column));
+}
+
+/// \return the LSP range from \p start to \p end in \p text, as JSON.
+wcstring range_json(const wcstring &text, size_t start, size_t end) {
+    return L"{\"start\":" + position_json(text, start) + L",\"end\":" + position_json(text, end) +
+           L"}";
+}
+
+/// \return the character offset in \p text of the LSP position \p pos. Positions past the end of a
+/// line are clamped to the end of that line.
+maybe_t<size_t> offset_from_position(const wcstring &text, const json_value_t *pos) {
+    const json_value_t *line = get_member(pos, L"line");
+    const json_value_t *character = get_member(pos, L"character");
+    if (!line || line->type != json_value_t::type_t::number || !character ||
+        character->type != json_value_t::type_t::number) {
+        return none();
+    }
+    size_t offset = 0;
+    for (double i = 0; i < line->number; i++) {
+        size_t newline = text.find(L'\n', offset);
+        if (newline == wcstring::npos) return text.size();
+        offset = newline + 1;
+    }
+    for (double column = 0; offset < text.size() && text.at(offset) != L'\n';) {
+        column += utf16_length(text.at(offset));
+        if (column > character->number) break;
+        offset++;
+    }
+    return offset;
+}
+
+/// Convert a file:// URI to a path, or return none() if it is some other kind of URI.
+maybe_t<wcstring> uri_to_path(const wcstring &uri) {
+    if (!string_prefixes_string(L"file://", uri)) return none();
+    std::string narrow = wcs2string(uri.substr(std::wcslen(L"file://")));
+    std::string decoded;
+    for (size_t i = 0; i < narrow.size(); i++) {
+        if (narrow.at(i) == '%' && i + 2 < narrow.size() && isxdigit(narrow.at(i + 1)) &&
+            isxdigit(narrow.at(i + 2))) {
+            decoded.push_back(static_cast<char>(std::stoi(narrow.substr(i + 1, 2), nullptr, 16)));
+            i += 2;
+        } else {
+            decoded.push_back(narrow.at(i));
+        }
+    }
+    return str2wcstring(decoded);
+}
+
+/// Convert an absolute path to a file:// URI.
+wcstring path_to_uri(const wcstring &path) {
+    wcstring result = L"file://";
+    for (unsigned char c : wcs2string(path)) {
+        if (isalnum(c) || std::strchr("/-._~", c)) {
+            result.push_back(c);
+        } else {
+            append_format(result, L"%%%02X", static_cast<unsigned>(c));
+        }
+    }
+    return result;
+}
+
+/// \return the unescaped name of the command, or of the function being defined, whose source
+/// covers \p offset in \p src. The end of a name counts, so that a cursor just after it matches.
+maybe_t<wcstring> command_at_offset(const ast::ast_t &ast, const wcstring &src, size_t offset) {
+    for (const ast::node_t &node : ast) {
+        const ast::node_t *name_node = nullptr;
+        if (const auto *stmt = node.try_as<ast::decorated_statement_t>()) {
+            name_node = &stmt->command;
+        } else if (const auto *header = node.try_as<ast::function_header_t>()) {
+            name_node = &header->first_arg;
+        }
+        if (!name_node) continue;
+        auto range = name_node->try_source_range();
+        if (!range || offset < range->start || offset > range->end()) continue;
+        wcstring name;
+        if (!unescape_string(name_node->source(src), &name, UNESCAPE_DEFAULT)) return none();
+        return name;
+    }
+    return none();
+}
+
+class lsp_server_t {
+   public:
+    lsp_server_t(parser_t &parser, int in_fd, int out_fd)
+        : parser(parser), in_fd(in_fd), out_fd(out_fd) {}
+
+    int run();
+
+   private:
+    parser_t &parser;
+    const int in_fd;
+    const int out_fd;
+
+    /// Input which has been read but not yet handled.
+    std::string input;
+
+    /// The text of each open document, by URI.
+    std::map<wcstring, wcstring> documents;
+
+    /// Whether the client has sent a "shutdown" request.
+    bool shutdown_requested{false};
+
+    bool read_message(wcstring *out_body);
+    bool send(const wcstring &body);
+    void respond(const json_value_t &id, const wcstring &result);
+    void respond_error(const json_value_t &id, int code, const wcstring &message);
+    void notify(const wchar_t *method, const wcstring &params);
+
+    const wcstring *document(const json_value_t *params, const wcstring **out_uri = nullptr) const;
+    void publish_diagnostics(const wcstring &uri);
+
+    maybe_t<wcstring> handle_request(const wcstring &method, const json_value_t *params);
+    void handle_notification(const wcstring &method, const json_value_t *params);
+
+    wcstring initialize() const;
+    wcstring completion(const json_value_t *params);
+    wcstring hover(const json_value_t *params);
+    wcstring definition(const json_value_t *params);
+    wcstring formatting(const json_value_t *params);
+};
+
+/// Read the next message into \p out_body. \return false on end of input or a malformed header.
+bool lsp_server_t::read_message(wcstring *out_body) {
+    return json_read_message(in_fd, &input, out_body);
+}
+
+/// Write a message with the JSON \p body. \return false if it could not be written.
+bool lsp_server_t::send(const wcstring &body) { return json_write_message(out_fd, body); }
+
+void lsp_server_t::respond(const json_value_t &id, const wcstring &result) {
+    send(L"{\"jsonrpc\":\"2.0\",\"id\":" + json_dump(id) + L",\"result\":" + result + L"}");
+}
+
+void lsp_server_t::respond_error(const json_value_t &id, int code, const wcstring &message) {
+    send(L"{\"jsonrpc\":\"2.0\",\"id\":" + json_dump(id) + L",\"error\":{\"code\":" +
+         to_string(code) + L",\"message\":" + json_string(message) + L"}}");
+}
+
+void lsp_server_t::notify(const wchar_t *method, const wcstring &params) {
+    send(L"{\"jsonrpc\":\"2.0\",\"method\":" + json_string(method) + L",\"params\":" + params +
+         L"}");
+}
+
+/// \return the text of the open document named by the textDocument member of \p params, or null
+/// if it is not open. Set \p out_uri to its URI.
+const wcstring *lsp_server_t::document(const json_value_t *params,
+                                       const wcstring **out_uri) const {
+    const json_value_t *doc = get_member(params, L"textDocument");
+    const wcstring *uri = doc ? doc->get_string(L"uri") : nullptr;
+    if (!uri) return nullptr;
+    auto iter = documents.find(*uri);
+    if (iter == documents.end()) return nullptr;
+    if (out_uri) *out_uri = &iter->first;
+    return &iter->second;
+}
+
+/// Send the syntax errors in the document \p uri to the client. Closed documents have none.
+void lsp_server_t::publish_diagnostics(const wcstring &uri) {
+    wcstring diagnostics = L"[";
+    auto iter = documents.find(uri);
+    if (iter != documents.end()) {
+        const wcstring &text = iter->second;
+        parse_error_list_t errors;
+        parse_util_detect_errors(text, &errors);
+        for (const parse_error_t &err : errors) {
+            size_t start = std::min(err.source_start, text.size());
+            size_t end = std::min(start + err.source_length, text.size());
+            if (diagnostics.size() > 1) diagnostics.push_back(L',');
+            diagnostics.append(L"{\"range\":" + range_json(text, start, end));
+            append_format(diagnostics, L",\"severity\":%d", lsp_severity_error);
+            diagnostics.append(L",\"source\":\"fish\",\"message\":" + json_string(err.text) +
+                               L"}");
+        }
+    }
+    diagnostics.push_back(L']');
+    notify(L"textDocument/publishDiagnostics",
+           L"{\"uri\":" + json_string(uri) + L",\"diagnostics\":" + diagnostics + L"}");
+}
+
+wcstring lsp_server_t::initialize() const {
+    return L"{\"capabilities\":{"
+           L"\"textDocumentSync\":1,"
+           L"\"completionProvider\":{\"triggerCharacters\":[\"-\",\"$\",\"/\"]},"
+           L"\"hoverProvider\":true,"
+           L"\"definitionProvider\":true,"
+           L"\"documentFormattingProvider\":true},"
+           L"\"serverInfo\":{\"name\":\"fish\",\"version\":" +
+           json_string(str2wcstring(get_fish_version())) + L"}}";
+}
+
+/// Complete the token before the cursor, using only the text of its line.
+wcstring lsp_server_t::completion(const json_value_t *params) {
+    const wcstring *text = document(params);
+    if (!text) return L"null";
+    auto cursor = offset_from_position(*text, get_member(params, L"position"));
+    if (!cursor) return L"null";
+
+    size_t line_start = text->rfind(L'\n', *cursor == 0 ? 0 : *cursor - 1);
+    line_start = (line_start == wcstring::npos || line_start >= *cursor) ? 0 : line_start + 1;
+    const wcstring line = text->substr(line_start, *cursor - line_start);
+
+    const wchar_t *token_begin;
+    parse_util_token_extent(line.c_str(), line.size(), &token_begin, nullptr, nullptr, nullptr);
+    const size_t token_start = token_begin - line.c_str();
+    const wcstring token = line.substr(token_start);
+
+    // Completions which ask for the commandline get this line, as with `complete -C`.
+    parser.libdata().transient_commandlines.push_back(line);
+    cleanup_t remove_transient([&] { parser.libdata().transient_commandlines.pop_back(); });
+
+    completion_request_flags_t flags{completion_request_t::descriptions};
+    completion_list_t comps = complete(line, flags, parser.context());
+    completions_sort_and_prioritize(&comps, flags);
+
+    wcstring items = L"[";
+    for (const completion_t &comp : comps) {
+        size_t new_cursor = line.size();
+        wcstring applied =
+            completion_apply_to_command_line(comp.completion, comp.flags, line, &new_cursor, true);
+        wcstring new_text = applied.substr(token_start, new_cursor - token_start);
+        if (!(comp.flags & COMPLETE_NO_SPACE) && string_suffixes_string(L" ", new_text)) {
+            new_text.pop_back();
+        }
+        wcstring label =
+            comp.flags & COMPLETE_REPLACES_TOKEN ? comp.completion : token + comp.completion;
+
+        if (items.size() > 1) items.push_back(L',');
+        items.append(L"{\"label\":" + json_string(label));
+        if (!comp.description.empty()) {
+            items.append(L",\"detail\":" + json_string(comp.description));
+        }
+        items.append(L",\"filterText\":" + json_string(new_text));
+        items.append(L",\"textEdit\":{\"range\":" +
+                     range_json(*text, line_start + token_start, *cursor) +
+                     L",\"newText\":" + json_string(new_text) + L"}}");
+    }
+    items.push_back(L']');
+    return items;
+}
+
+/// Describe the command under the cursor: a builtin, a function or an external command.
+wcstring lsp_server_t::hover(const json_value_t *params) {
+    const wcstring *text = document(params);
+    if (!text) return L"null";
+    auto offset = offset_from_position(*text, get_member(params, L"position"));
+    if (!offset) return L"null";
+    auto ast = ast::ast_t::parse(*text, parse_flag_continue_after_error |
+                                            parse_flag_accept_incomplete_tokens |
+                                            parse_flag_leave_unterminated);
+    maybe_t<wcstring> name = command_at_offset(ast, *text, *offset);
+    if (!name || name->empty()) return L"null";
+
+    wcstring contents;
+    if (builtin_exists(*name)) {
+        contents = format_string(_(L"**%ls** (builtin)\n\n%ls"), name->c_str(),
+                                 builtin_get_desc(*name));
+    } else if (function_exists(*name, parser)) {
+        contents = format_string(_(L"**%ls** (function)"), name->c_str());
+        wcstring desc;
+        if (function_get_desc(*name, desc)) {
+            contents.append(L"\n\n" + desc);
+        }
+        if (const wchar_t *file = function_get_definition_file(*name)) {
+            append_format(contents, _(L"\n\nDefined in %ls line %d"), file,
+                          function_get_definition_lineno(*name));
+        }
+    } else {
+        wcstring path;
+        if (!path_get_path(*name, &path, parser.vars())) return L"null";
+        contents = format_string(_(L"**%ls** (command)\n\n%ls"), name->c_str(), path.c_str());
+    }
+    return L"{\"contents\":{\"kind\":\"markdown\",\"value\":" + json_string(contents) + L"}}";
+}
+
+/// Find the definition of the function under the cursor, first in the document itself and then in
+/// the functions fish knows about, including autoloaded ones.
+wcstring lsp_server_t::definition(const json_value_t *params) {
+    const wcstring *uri;
+    const wcstring *text = document(params, &uri);
+    if (!text) return L"null";
+    auto offset = offset_from_position(*text, get_member(params, L"position"));
+    if (!offset) return L"null";
+    auto ast = ast::ast_t::parse(*text, parse_flag_continue_after_error |
+                                            parse_flag_accept_incomplete_tokens |
+                                            parse_flag_leave_unterminated);
+    maybe_t<wcstring> name = command_at_offset(ast, *text, *offset);
+    if (!name || name->empty()) return L"null";
+
+    for (const ast::node_t &node : ast) {
+        const auto *header = node.try_as<ast::function_header_t>();
+        if (!header) continue;
+        wcstring func_name;
+        if (!unescape_string(header->first_arg.source(*text), &func_name, UNESCAPE_DEFAULT) ||
+            func_name != *name) {
+            continue;
+        }
+        source_range_t range = header->first_arg.source_range();
+        return L"{\"uri\":" + json_string(*uri) +
+               L",\"range\":" + range_json(*text, range.start, range.end()) + L"}";
+    }
+
+    if (builtin_exists(*name) || !function_exists(*name, parser)) return L"null";
+    const wchar_t *file = function_get_definition_file(*name);
+    if (!file || file[0] != L'/') return L"null";
+    int line = std::max(function_get_definition_lineno(*name) - 1, 0);
+    return L"{\"uri\":" + json_string(path_to_uri(file)) +
+           format_string(L",\"range\":{\"start\":{\"line\":%d,\"character\":0},"
+                         L"\"end\":{\"line\":%d,\"character\":0}}}",
+                         line, line);
+}
+
+/// Format the whole document as fish_indent would, including any .editorconfig settings.
+wcstring lsp_server_t::formatting(const json_value_t *params) {
+    const wcstring *uri;
+    const wcstring *text = document(params, &uri);
+    if (!text) return L"null";
+    indent_options_t opts;
+    if (auto path = uri_to_path(*uri)) {
+        indent_options_apply_editorconfig(*path, &opts);
+    }
+    wcstring formatted = prettify(*text, opts);
+    if (formatted == *text) return L"[]";
+    return L"[{\"range\":" + range_json(*text, 0, text->size()) +
+           L",\"newText\":" + json_string(formatted) + L"}]";
+}
+
+/// Handle the request \p method. \return the JSON result, or none() if the method is unknown.
+maybe_t<wcstring> lsp_server_t::handle_request(const wcstring &method,
+                                               const json_value_t *params) {
+    if (method == L"initialize") return initialize();
+    if (method == L"shutdown") {
+        shutdown_requested = true;
+        return wcstring(L"null");
+    }
+    if (method == L"textDocument/completion") return completion(params);
+    if (method == L"textDocument/hover") return hover(params);
+    if (method == L"textDocument/definition") return definition(params);
+    if (method == L"textDocument/formatting") return formatting(params);
+    return none();
+}
+
+void lsp_server_t::handle_notification(const wcstring &method, const json_value_t *params) {
+    const json_value_t *doc = get_member(params, L"textDocument");
+    const wcstring *uri = doc ? doc->get_string(L"uri") : nullptr;
+    if (!uri) return;
+    if (method == L"textDocument/didOpen") {
+        const wcstring *text = doc->get_string(L"text");
+        if (!text) return;
+        documents[*uri] = *text;
+        publish_diagnostics(*uri);
+    } else if (method == L"textDocument/didChange") {
+        // We ask for full synchronization, so the last change has the whole text.
+        const json_value_t *changes = get_member(params, L"contentChanges");
+        if (!changes || changes->type != json_value_t::type_t::array || changes->array.empty()) {
+            return;
+        }
+        const wcstring *text = changes->array.back().get_string(L"text");
+        if (!text) return;
+        documents[*uri] = *text;
+        publish_diagnostics(*uri);
+    } else if (method == L"textDocument/didClose") {
+        documents.erase(*uri);
+        publish_diagnostics(*uri);
+    }
+}
+
+int lsp_server_t::run() {
+    wcstring body;
+    while (read_message(&body)) {
+        maybe_t<json_value_t> message = json_parse(body);
+        if (!message || message->type != json_value_t::type_t::object) {
+            respond_error(json_value_t{}, lsp_error_parse, L"Parse error");
+            continue;
+        }
+        const wcstring *method = message->get_string(L"method");
+        const json_value_t *id = message->get(L"id");
+        const json_value_t *params = message->get(L"params");
+        if (!method) {
+            // A response to a request of ours, but we never make any.
+            if (!id) respond_error(json_value_t{}, lsp_error_invalid_request, L"Invalid request");
+            continue;
+        }
+        if (*method == L"exit") {
+            return shutdown_requested ? 0 : 1;
+        }
+        if (!id) {
+            handle_notification(*method, params);
+        } else if (shutdown_requested) {
+            respond_error(*id, lsp_error_invalid_request, L"Server is shutting down");
+        } else if (auto result = handle_request(*method, params)) {
+            respond(*id, *result);
+        } else {
+            respond_error(*id, lsp_error_method_not_found, L"Method not found: " + *method);
+        }
+    }
+    return 1;
+}
+}  // namespace
+
+int lsp_run(parser_t &parser, int in_fd, int out_fd) {
+    lsp_server_t server(parser, in_fd, out_fd);
+    return server.run();
+}
@@ -0,0 +1,14 @@
+// A language server for fish scripts, run by `fish --lsp`.
+#ifndef FISH_LSP_H
+#define FISH_LSP_H
+
+class parser_t;
+
+/// Serve the Language Server Protocol, reading JSON-RPC messages from \p in_fd and writing
+/// responses and notifications to \p out_fd, until the client sends "exit" or closes the input.
+/// Diagnostics, completions, hover text, function definitions and formatting all come from
+/// \p parser and the rest of fish, as in an interactive session.
+/// \return the exit status, which is 0 if the client asked the server to shut down before exiting.
+int lsp_run(parser_t &parser, int in_fd, int out_fd);
+
+#endif
@@ -1,6 +1,7 @@
 // Generic output functions.
 #include "config.h"
 
+#include <stdarg.h>
 #include <stdio.h>
 #include <stdlib.h>
 
@@ -18,7 +19,13 @@
 #include <ncurses/term.h>
 #endif
 #include <limits.h>
+#ifdef HAVE_SYS_SELECT_H
+#include <sys/select.h>
+#endif
+#include <sys/time.h>
+#include <unistd.h>
 
+#include <algorithm>
 #include <cwchar>
 #include <memory>
 #include <string>
@@ -30,6 +37,7 @@
 #include "fallback.h"  // IWYU pragma: keep
 #include "flog.h"
 #include "output.h"
+#include "signal.h"
 #include "wcstringutil.h"
 #include "wutil.h"  // IWYU pragma: keep
 
@@ -98,11 +106,55 @@ static bool write_background_color(outputter_t &outp, unsigned char idx) {
     return false;
 }
 
-void outputter_t::flush_to(int fd) {
-    if (fd >= 0 && !contents_.empty()) {
-        write_loop(fd, contents_.data(), contents_.size());
-        contents_.clear();
+/// Output is written in chunks of this size, after checking that the fd is writable, so a write
+/// to a stalled terminal blocks at most until there is room for a chunk.
+static constexpr size_t WRITE_CHUNK_SIZE = 512;
+
+/// Wait until \p fd is writable, or \p wakeup_fd is readable because a signal arrived.
+/// \return true if \p fd should be written to, false if a signal arrived first.
+static bool wait_until_writable(int fd, int wakeup_fd) {
+    for (;;) {
+        fd_set writefds;
+        FD_ZERO(&writefds);
+        FD_SET(fd, &writefds);
+        fd_set readfds;
+        FD_ZERO(&readfds);
+        if (wakeup_fd >= 0) FD_SET(wakeup_fd, &readfds);
+        int res = select(std::max(fd, wakeup_fd) + 1, &readfds, &writefds, nullptr, nullptr);
+        if (res < 0) {
+            if (errno == EINTR || errno == EAGAIN) {
+                if (wakeup_fd >= 0) return false;
+                continue;
+            }
+            // Let write() report the error.
+            return true;
+        }
+        if (FD_ISSET(fd, &writefds)) return true;
+        if (wakeup_fd >= 0 && FD_ISSET(wakeup_fd, &readfds)) return false;
+    }
+}
+
+void outputter_t::flush_to(int fd, bool interruptible) {
+    if (fd < 0) return;
+    size_t written = 0;
+    while (written < contents_.size()) {
+        // Only give up for signals if the rest fits in the buffer. This is the backpressure on
+        // whoever keeps writing to a stalled fd.
+        size_t remaining = contents_.size() - written;
+        int wakeup_fd =
+            interruptible && remaining <= max_pending_bytes ? signal_get_wakeup_fd() : -1;
+        if (!wait_until_writable(fd, wakeup_fd)) break;
+
+        ssize_t amt = write(fd, contents_.data() + written, std::min(remaining, WRITE_CHUNK_SIZE));
+        if (amt < 0) {
+            if (errno == EINTR || errno == EAGAIN) continue;
+            // The output can't be written, like when the terminal is gone. Drop it.
+            written = contents_.size();
+            break;
+        }
+        written += static_cast<size_t>(amt);
     }
+    contents_.erase(0, written);
 }
 
 // Exported for builtin_set_color's usage only.
@@ -151,6 +203,10 @@ void outputter_t::set_color(rgb_color_t fg, rgb_color_t bg) {
     // just give up...
     if (!cur_term || !exit_attribute_mode) return;
 
+    // Changing the color may take several escape sequences; write them all at once.
+    begin_buffering();
+    const cleanup_t end_buffering_later([this] { end_buffering(); });
+
     const rgb_color_t normal = rgb_color_t::normal();
     bool bg_set = false, last_bg_set = false;
     bool is_bold = fg.is_bold() || bg.is_bold();
@@ -466,6 +522,119 @@ rgb_color_t parse_color(const env_var_t &var, bool is_background) {
     return result;
 }
 
+styled_text_t &styled_text_t::append(const wcstring &text, text_style_t style) {
+    if (style != text_style_t::normal && !text.empty()) {
+        if (!spans_.empty() && spans_.back().style == style &&
+            spans_.back().start + spans_.back().length == text_.size()) {
+            spans_.back().length += text.size();
+        } else {
+            spans_.push_back(span_t{text_.size(), text.size(), style});
+        }
+    }
+    text_.append(text);
+    return *this;
+}
+
+styled_text_t &styled_text_t::append(const styled_text_t &other) {
+    size_t pos = 0;
+    for (const span_t &span : other.spans_) {
+        append(other.text_.substr(pos, span.start - pos));
+        append(other.text_.substr(span.start, span.length), span.style);
+        pos = span.start + span.length;
+    }
+    return append(other.text_.substr(pos));
+}
+
+styled_text_t &styled_text_t::append_format(text_style_t style, const wchar_t *format, ...) {
+    va_list va;
+    va_start(va, format);
+    wcstring text = vformat_string(format, va);
+    va_end(va);
+    return append(text, style);
+}
+
+/// \return the variable giving the color of \p style, and the color to use if it is not set.
+static std::pair<const wchar_t *, const wchar_t *> style_variable(text_style_t style) {
+    switch (style) {
+        case text_style_t::normal:
+            return {nullptr, L"normal"};
+        case text_style_t::error:
+            return {L"fish_color_error", L"red"};
+        case text_style_t::warning:
+            return {L"fish_color_warning", L"yellow"};
+        case text_style_t::emphasis:
+            return {nullptr, L"--bold"};
+        case text_style_t::value:
+            return {L"fish_color_quote", L"normal"};
+        case text_style_t::verbatim:
+            return {nullptr, L"normal"};
+    }
+    DIE("unknown text style");
+}
+
+/// Break the lines of \p text which are wider than \p width at their last space that fits, except
+/// for spaces for which \p can_break is false. Spaces are replaced by newlines, so the text keeps
+/// its length.
+static void wrap_lines(wcstring &text, int width, const std::vector<bool> &can_break) {
+    size_t line_start = 0;
+    size_t last_space = wcstring::npos;
+    int line_width = 0;
+    for (size_t i = 0; i < text.size(); i++) {
+        wchar_t c = text[i];
+        if (c == L'\n') {
+            line_start = i + 1;
+            last_space = wcstring::npos;
+            line_width = 0;
+            continue;
+        }
+        if (c == L' ' && can_break[i]) last_space = i;
+        line_width += std::max(fish_wcwidth(c), 0);
+        if (line_width > width && last_space != wcstring::npos && last_space > line_start) {
+            text[last_space] = L'\n';
+            line_start = last_space + 1;
+            last_space = wcstring::npos;
+            line_width = 0;
+            for (size_t j = line_start; j <= i; j++) {
+                if (text[j] == L' ' && can_break[j]) last_space = j;
+                line_width += std::max(fish_wcwidth(text[j]), 0);
+            }
+        }
+    }
+}
+
+wcstring styled_text_t::render(const environment_t &vars, bool colorize, int width) const {
+    wcstring text = text_;
+    if (width > 0) {
+        std::vector<bool> can_break(text.size(), true);
+        for (const span_t &span : spans_) {
+            if (span.style != text_style_t::verbatim) continue;
+            std::fill(can_break.begin() + span.start,
+                      can_break.begin() + span.start + span.length, false);
+        }
+        wrap_lines(text, width, can_break);
+    }
+    if (!colorize || spans_.empty()) return text;
+
+    outputter_t outp;
+    size_t pos = 0;
+    for (const span_t &span : spans_) {
+        // Verbatim text is written like unstyled text.
+        if (span.style == text_style_t::verbatim) continue;
+        outp.writestr(text.substr(pos, span.start - pos));
+        auto style = style_variable(span.style);
+        maybe_t<env_var_t> var = style.first ? vars.get(style.first) : none();
+        if (!var) var = env_var_t{L"", wcstring_list_t{style.second}};
+        rgb_color_t fg = parse_color(*var, false);
+        rgb_color_t bg = parse_color(*var, true);
+        outp.set_color(fg, bg);
+        outp.writestr(text.substr(span.start, span.length));
+        outp.set_color(rgb_color_t::normal(), rgb_color_t::normal());
+        pos = span.start + span.length;
+    }
+    outp.writestr(text.substr(pos));
+    return str2wcstring(outp.contents());
+}
+
 /// Write specified multibyte string.
 void writembs_check(outputter_t &outp, const char *mbs, const char *mbs_name, bool critical,
                     const char *file, long line) {
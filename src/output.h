@@ -7,12 +7,14 @@
 
 #include <stddef.h>
 
+#include <utility>
 #include <vector>
 
 #include "color.h"
 #include "fallback.h"  // IWYU pragma: keep
 
 class env_var_t;
+class environment_t;
 
 class outputter_t {
     /// Storage for buffered contents.
@@ -45,9 +47,13 @@ class outputter_t {
 
     /// Flush output, if we have a set fd and our buffering count is 0.
     void maybe_flush() {
-        if (fd_ >= 0 && bufferCount_ == 0) flush_to(fd_);
+        if (fd_ >= 0 && bufferCount_ == 0) flush_to(fd_, true /* interruptible */);
     }
 
+    /// Output buffered data to \p fd. If \p interruptible is set, stop if a signal arrives while
+    /// waiting for the fd to become writable, and keep the rest buffered, unless there is too much.
+    void flush_to(int fd, bool interruptible);
+
    public:
     /// Construct an outputter which outputs to its string.
     outputter_t() = default;
@@ -88,8 +94,28 @@ class outputter_t {
     /// \return the "output" contents.
     const std::string &contents() const { return contents_; }
 
-    /// Output any buffered data to the given \p fd.
-    void flush_to(int fd);
+    /// Output any buffered data to the given \p fd, waiting until it can be written to.
+    void flush_to(int fd) { flush_to(fd, false /* interruptible */); }
+
+    /// The most output which is kept buffered, when the fd can't be written to, like when the
+    /// terminal is stopped with ctrl-s. Beyond this, writes wait until there is room.
+    static constexpr size_t max_pending_bytes = 64 * 1024;
+
+    /// \return the fd to output to, or -1 if this outputs to its string.
+    int fd() const { return fd_; }
+
+    /// \return whether there is output which could not be written yet, because a signal arrived
+    /// while waiting for the fd.
+    bool has_pending_output() const { return fd_ >= 0 && bufferCount_ == 0 && !contents_.empty(); }
+
+    /// Try again to write the pending output, stopping if a signal arrives.
+    void flush_pending_output() { maybe_flush(); }
+
+    /// Write all pending output, waiting until it can be written. This must be done before anyone
+    /// else writes to the fd, like a command run by the shell.
+    void flush_all() {
+        if (fd_ >= 0) flush_to(fd_);
+    }
 
     /// Begins buffering. Output will not be automatically flushed until a corresponding
     /// end_buffering() call.
@@ -118,6 +144,51 @@ void writembs_check(outputter_t &outp, const char *mbs, const char *mbs_name, bo
 
 rgb_color_t parse_color(const env_var_t &var, bool is_background);
 
+/// The kinds of text that builtins style in their output. Each is shown in the color of a
+/// variable, so it follows the theme.
+enum class text_style_t {
+    normal,
+    error,     // fish_color_error
+    warning,   // fish_color_warning
+    emphasis,  // bold
+    value,     // fish_color_quote
+    verbatim,  // unstyled, and never wrapped, like code with a caret under it
+};
+
+/// Text in which some spans have a style. It is rendered with escape sequences only when it is
+/// going to a terminal, so redirected output stays plain.
+class styled_text_t {
+   public:
+    styled_text_t() = default;
+    /* implicit */ styled_text_t(wcstring text) : text_(std::move(text)) {}
+
+    /// Append \p text in the style \p style.
+    styled_text_t &append(const wcstring &text, text_style_t style = text_style_t::normal);
+
+    /// Append \p other, keeping its styles.
+    styled_text_t &append(const styled_text_t &other);
+
+    /// Append formatted text in the style \p style.
+    styled_text_t &append_format(text_style_t style, const wchar_t *format, ...);
+
+    /// \return the text without any styling.
+    const wcstring &text() const { return text_; }
+
+    /// \return the text, wrapped at spaces so lines are no wider than \p width if it is positive.
+    /// If \p colorize is set, the styles are applied with the colors in \p vars.
+    wcstring render(const environment_t &vars, bool colorize, int width) const;
+
+   private:
+    struct span_t {
+        size_t start;
+        size_t length;
+        text_style_t style;
+    };
+
+    wcstring text_;
+    std::vector<span_t> spans_;
+};
+
 /// Sets what colors are supported.
 enum { color_support_term256 = 1 << 0, color_support_term24bit = 1 << 1 };
 typedef unsigned int color_support_t;
@@ -155,6 +155,11 @@ line_t pager_t::completion_print_item(const wcstring &prefix, const comp_t *c, s
     highlight_spec_t bg = {highlight_role_t::normal, bg_role};
     highlight_spec_t prefix_col = {modify_role(highlight_role_t::pager_prefix), bg_role};
     highlight_spec_t comp_col = {modify_role(highlight_role_t::pager_completion), bg_role};
+    // Highlight the completions that a search would jump to.
+    if (search_jumps_to_matches && !selected && !search_field_line.empty() &&
+        completion_info_matches_search(*c)) {
+        comp_col.background = highlight_role_t::search_match;
+    }
     highlight_spec_t desc_col = {modify_role(highlight_role_t::pager_description), bg_role};
 
     // Print the completion part
@@ -343,9 +348,9 @@ void pager_t::measure_completion_infos(comp_info_list_t *infos, const wcstring &
     }
 }
 
-// Indicates if the given completion info passes any filtering we have.
-bool pager_t::completion_info_passes_filter(const comp_t &info) const {
-    // If we have no filter, everything passes.
+// Indicates if the given completion info matches the text of the search field.
+bool pager_t::completion_info_matches_search(const comp_t &info) const {
+    // If we have no search, everything matches.
     if (!search_field_shown || this->search_field_line.empty()) return true;
 
     const wcstring &needle = this->search_field_line.text();
@@ -365,6 +370,30 @@ bool pager_t::completion_info_passes_filter(const comp_t &info) const {
     return false;  // no match
 }
 
+// Indicates if the given completion info passes any filtering we have.
+bool pager_t::completion_info_passes_filter(const comp_t &info) const {
+    // When jumping between matches, we don't filter.
+    if (search_jumps_to_matches) return true;
+    return completion_info_matches_search(info);
+}
+
+// Returns the index of the first completion at or after idx (or before it, if not forward) that
+// matches the search, wrapping around. Returns PAGER_SELECTION_NONE if none match. Without a search
+// to jump between, this is just idx.
+size_t pager_t::search_match_index(size_t idx, bool forward) const {
+    if (!search_jumps_to_matches) return idx;
+    size_t count = completion_infos.size();
+    for (size_t i = 0; i < count; i++) {
+        if (completion_info_matches_search(completion_infos.at(idx))) return idx;
+        if (forward) {
+            idx = (idx + 1 == count) ? 0 : idx + 1;
+        } else {
+            idx = (idx == 0) ? count - 1 : idx - 1;
+        }
+    }
+    return PAGER_SELECTION_NONE;
+}
+
 // Update completion_infos from unfiltered_completion_infos, to reflect the filter.
 void pager_t::refilter_completions() {
     this->completion_infos.clear();
@@ -615,12 +644,18 @@ bool pager_t::select_next_completion_in_direction(selection_motion_t direction,
             case selection_motion_t::north:
             case selection_motion_t::prev: {
                 // These directions do something sane.
+                size_t idx;
                 if (direction == selection_motion_t::prev ||
                     direction == selection_motion_t::north) {
-                    selected_completion_idx = completion_infos.size() - 1;
+                    idx = completion_infos.size() - 1;
                 } else {
-                    selected_completion_idx = 0;
+                    idx = 0;
+                }
+                if (direction == selection_motion_t::next || direction == selection_motion_t::prev) {
+                    idx = search_match_index(idx, direction == selection_motion_t::next);
+                    if (idx == PAGER_SELECTION_NONE) return false;
                 }
+                selected_completion_idx = idx;
                 return true;
             }
             case selection_motion_t::page_north:
@@ -653,6 +688,12 @@ bool pager_t::select_next_completion_in_direction(selection_motion_t direction,
         } else {
             DIE("unknown non-cardinal direction");
         }
+        // Skip to the next or previous search match, if we jump between them.
+        if (new_selected_completion_idx != PAGER_SELECTION_NONE) {
+            new_selected_completion_idx = search_match_index(
+                new_selected_completion_idx, direction == selection_motion_t::next);
+            if (new_selected_completion_idx == PAGER_SELECTION_NONE) return false;
+        }
     } else {
         // Cardinal directions. We have a completion index; we wish to compute its row and column.
         size_t current_row = this->get_selected_row(rendering);
@@ -840,6 +881,7 @@ void pager_t::clear() {
     selected_completion_idx = PAGER_SELECTION_NONE;
     fully_disclosed = false;
     search_field_shown = false;
+    search_jumps_to_matches = false;
     search_field_line.clear();
 }
 
@@ -847,6 +889,14 @@ void pager_t::set_search_field_shown(bool flag) { this->search_field_shown = fla
 
 bool pager_t::is_search_field_shown() const { return this->search_field_shown; }
 
+void pager_t::set_search_jumps_to_matches(bool flag) { this->search_jumps_to_matches = flag; }
+
+bool pager_t::selection_matches_search() const {
+    if (!search_jumps_to_matches) return true;
+    if (selected_completion_idx >= completion_infos.size()) return false;
+    return completion_info_matches_search(completion_infos.at(selected_completion_idx));
+}
+
 size_t pager_t::cursor_position() const {
     size_t result = std::wcslen(SEARCH_FIELD_PROMPT) + this->search_field_line.position();
     // Clamp it to the right edge.
@@ -74,6 +74,10 @@ class pager_t {
     // Whether we show the search field.
     bool search_field_shown{false};
 
+    // Whether the search highlights and jumps between matching completions, instead of filtering
+    // them.
+    bool search_jumps_to_matches{false};
+
     // Returns the index of the completion that should draw selected, using the given number of
     // columns.
     size_t visual_selected_completion_index(size_t rows, size_t cols) const;
@@ -124,7 +128,9 @@ class pager_t {
     void recalc_min_widths(comp_info_list_t *lst) const;
     void measure_completion_infos(std::vector<comp_t> *infos, const wcstring &prefix) const;
 
+    bool completion_info_matches_search(const comp_t &info) const;
     bool completion_info_passes_filter(const comp_t &info) const;
+    size_t search_match_index(size_t idx, bool forward) const;
 
     void completion_print(size_t cols, const size_t *width_by_column, size_t row_start,
                           size_t row_stop, const wcstring &prefix, const comp_info_list_t &lst,
@@ -182,6 +188,12 @@ class pager_t {
     // Gets whether the search field shown.
     bool is_search_field_shown() const;
 
+    // Sets whether the search jumps between matching completions instead of filtering them.
+    void set_search_jumps_to_matches(bool flag);
+
+    // Indicates if the selected completion matches the search. Always true if we filter instead.
+    bool selection_matches_search() const;
+
     // Indicates if we are navigating our contents.
     bool is_navigating_contents() const;
 
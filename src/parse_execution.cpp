@@ -4,6 +4,7 @@
 #include "parse_execution.h"
 
 #include <errno.h>
+#include <fcntl.h>
 #include <stdarg.h>
 #include <stdio.h>
 #include <stdlib.h>
@@ -27,6 +28,7 @@
 #include "event.h"
 #include "exec.h"
 #include "expand.h"
+#include "fds.h"
 #include "flog.h"
 #include "function.h"
 #include "io.h"
@@ -693,8 +695,10 @@ end_execution_reason_t parse_execution_context_t::report_errors(
         wcstring backtrace_and_desc;
         parser->get_backtrace(pstree->src, error_list, backtrace_and_desc);
 
-        // Print it.
-        if (!should_suppress_stderr_for_tests()) {
+        // Print it, or save it for later if we are keeping going.
+        if (parser->libdata().keep_going_on_errors) {
+            parser->libdata().deferred_errors.push_back(std::move(backtrace_and_desc));
+        } else if (!should_suppress_stderr_for_tests()) {
             std::fwprintf(stderr, L"%ls", backtrace_and_desc.c_str());
         }
 
@@ -728,6 +732,10 @@ end_execution_reason_t parse_execution_context_t::handle_command_not_found(
     // We couldn't find the specified command. This is a non-fatal error. We want to set the exit
     // status to 127, which is the standard number used by other shells like bash and zsh.
 
+    // The command-not-found handler prints its message right away, so print the error location
+    // along with it even when keeping going.
+    scoped_push<bool> keep_going_push{&parser->libdata().keep_going_on_errors, false};
+
     const wchar_t *const cmd = cmd_str.c_str();
     if (err_code != ENOENT) {
         return this->report_error(STATUS_NOT_EXECUTABLE, statement,
@@ -979,6 +987,32 @@ end_execution_reason_t parse_execution_context_t::expand_arguments_from_nodes(
     return end_execution_reason_t::ok;
 }
 
+/// Given the source which follows the line of a here-document, which is the newline, the body and
+/// the terminating line, return the body. If the terminating line is indented, that much
+/// indentation is removed from each line of the body.
+static wcstring heredoc_body(const wcstring &src) {
+    size_t body_start = src.find(L'\n');
+    size_t terminator_start = src.rfind(L'\n');
+    if (body_start == wcstring::npos || body_start == terminator_start) return wcstring{};
+    body_start++;
+    terminator_start++;
+    size_t indent = src.find_first_not_of(L" \t", terminator_start) - terminator_start;
+
+    wcstring result;
+    size_t line_start = body_start;
+    while (line_start < terminator_start) {
+        size_t line_end = src.find(L'\n', line_start);
+        size_t skip = 0;
+        while (skip < indent && line_start + skip < line_end &&
+               (src.at(line_start + skip) == L' ' || src.at(line_start + skip) == L'\t')) {
+            skip++;
+        }
+        result.append(src, line_start + skip, line_end + 1 - (line_start + skip));
+        line_start = line_end + 1;
+    }
+    return result;
+}
+
 end_execution_reason_t parse_execution_context_t::determine_redirections(
     const ast::argument_or_redirection_list_t &list, redirection_spec_list_t *out_redirections) {
     // Get all redirection nodes underneath the statement.
@@ -993,6 +1027,42 @@ end_execution_reason_t parse_execution_context_t::determine_redirections(
                                 get_source(redir_node).c_str());
         }
 
+        // Here-documents are literal text, and are not expanded.
+        if (oper->mode == redirection_mode_t::here_doc) {
+            wcstring src = tok_heredoc_source(pstree->src, redir_node.oper.source_range().start);
+            out_redirections->emplace_back(oper->fd, oper->mode, heredoc_body(src));
+            continue;
+        }
+
+        // A here-string provides each element of its expansion as a line of input.
+        if (oper->mode == redirection_mode_t::here_string) {
+            completion_list_t expanded;
+            parse_error_list_t errors;
+            auto expand_ret = expand_string(
+                get_source(redir_node.target), &expanded,
+                no_exec() ? expand_flag::skip_variables : expand_flags_t{}, ctx, &errors);
+            parse_error_offset_source_start(&errors, redir_node.target.range.start);
+            switch (expand_ret.result) {
+                case expand_result_t::error:
+                    return this->report_errors(expand_ret.status, errors);
+                case expand_result_t::cancel:
+                    return end_execution_reason_t::cancelled;
+                case expand_result_t::wildcard_no_match:
+                    if (no_exec()) break;
+                    return report_error(STATUS_UNMATCHED_WILDCARD, redir_node.target,
+                                        WILDCARD_ERR_MSG, get_source(redir_node.target).c_str());
+                case expand_result_t::ok:
+                    break;
+            }
+            wcstring text;
+            for (const completion_t &line : expanded) {
+                text.append(line.completion);
+                text.push_back(L'\n');
+            }
+            out_redirections->emplace_back(oper->fd, oper->mode, std::move(text));
+            continue;
+        }
+
         // PCA: I can't justify this skip_variables flag. It was like this when I got here.
         wcstring target = get_source(redir_node.target);
         bool target_expanded =
@@ -1003,6 +1073,13 @@ end_execution_reason_t parse_execution_context_t::determine_redirections(
                                 _(L"Invalid redirection target: %ls"), target.c_str());
         }
 
+        // Named fd redirections take effect in the shell itself rather than the process.
+        if (!oper->fd_var.empty()) {
+            auto reason = this->apply_named_fd_redirection(redir_node, *oper, target);
+            if (reason != end_execution_reason_t::ok) return reason;
+            continue;
+        }
+
         // Make a redirection spec from the redirect token.
         assert(oper && oper->is_valid() && "expected to have a valid redirection");
         redirection_spec_t spec{oper->fd, oper->mode, std::move(target)};
@@ -1024,6 +1101,76 @@ end_execution_reason_t parse_execution_context_t::determine_redirections(
     return end_execution_reason_t::ok;
 }
 
+end_execution_reason_t parse_execution_context_t::apply_named_fd_redirection(
+    const ast::redirection_t &redir_node, const pipe_or_redir_t &oper, const wcstring &target) {
+    if (no_exec()) return end_execution_reason_t::ok;
+    const wcstring &name = oper.fd_var;
+
+    if (oper.mode == redirection_mode_t::fd && target == L"-") {
+        // Like {fd}>&- : close the fd stored in the variable.
+        auto var = parser->vars().get(name);
+        int fd = -1;
+        if (!var.missing_or_empty()) fd = fish_wcstoi(var->as_string().c_str());
+        if (var.missing_or_empty() || errno || !script_fd_close(fd)) {
+            return report_error(STATUS_INVALID_ARGS, redir_node,
+                                _(L"Variable '%ls' does not name an open file descriptor"),
+                                name.c_str());
+        }
+        return end_execution_reason_t::ok;
+    }
+
+    autoclose_fd_t fd;
+    wcstring description = target;
+    if (oper.mode == redirection_mode_t::fd) {
+        // Like {fd}>&3 : duplicate an existing fd.
+        int target_fd = fish_wcstoi(target.c_str());
+        if (errno || target_fd < 0) {
+            const wchar_t *fmt =
+                _(L"Requested redirection to '%ls', which is not a valid file descriptor");
+            return report_error(STATUS_INVALID_ARGS, redir_node, fmt, target.c_str());
+        }
+        fd.reset(dup(target_fd));
+        if (!fd.valid()) {
+            return report_error(STATUS_INVALID_ARGS, redir_node,
+                                _(L"Requested redirection to '%ls', which is not open"),
+                                target.c_str());
+        }
+    } else {
+        description = path_apply_working_directory(target, parser->vars().get_pwd_slash());
+        int oflags = oper.oflags();
+        fd.reset(wopen_cloexec(description, oflags, 0666));
+        if (!fd.valid()) {
+            if ((oflags & O_EXCL) && errno == EEXIST) {
+                return report_error(STATUS_CMD_ERROR, redir_node,
+                                    _(L"The file '%ls' already exists"), target.c_str());
+            }
+            return report_error(STATUS_CMD_ERROR, redir_node,
+                                _(L"An error occurred while redirecting file '%ls'"),
+                                target.c_str());
+        }
+    }
+
+    const wchar_t *mode = L"write";
+    if (oper.mode == redirection_mode_t::input) {
+        mode = L"read";
+    } else if (oper.mode == redirection_mode_t::append) {
+        mode = L"append";
+    } else if (oper.mode == redirection_mode_t::fd) {
+        mode = L"dup";
+    }
+    int newfd = script_fd_adopt(std::move(fd), mode, description);
+    if (newfd < 0) {
+        return report_error(STATUS_CMD_ERROR, redir_node,
+                            _(L"Could not allocate a file descriptor for '%ls'"), name.c_str());
+    }
+    if (parser->set_var_and_fire(name, ENV_DEFAULT, to_string(newfd)) != ENV_OK) {
+        script_fd_close(newfd);
+        return report_error(STATUS_INVALID_ARGS, redir_node,
+                            _(L"Cannot store a file descriptor in variable '%ls'"), name.c_str());
+    }
+    return end_execution_reason_t::ok;
+}
+
 end_execution_reason_t parse_execution_context_t::populate_not_process(
     job_t *job, process_t *proc, const ast::not_statement_t &not_statement) {
     auto &flags = job->mut_flags();
@@ -1278,6 +1425,34 @@ end_execution_reason_t parse_execution_context_t::run_1_job(const ast::job_t &jo
     // Save the node index.
     scoped_push<const ast::job_t *> saved_node(&executing_job_node, &job_node);
 
+    // Give any debugger a chance to stop here, now that the line number is known.
+    if (debugger_t *debugger = parser->libdata().debugger) {
+        debugger->will_run_job(*parser);
+        if (auto ret = check_end_execution()) {
+            return *ret;
+        }
+    }
+
+    // If this job runs the command of a process substitution, these are the process substitutions
+    // of the job it feeds.
+    procsubs_t *const feeds = parser->libdata().procsub_target;
+    scoped_push<procsubs_t *> saved_procsub_target(&parser->libdata().procsub_target, nullptr);
+
+    // Process substitutions made while populating this job keep their pipes open until we return,
+    // at which point the job has either finished or been launched. The jobs running their commands
+    // are constructed along with it, or with the job it feeds, since they share its group.
+    procsubs_t procsubs;
+    const cleanup_t construct_procsubs([&] {
+        for (const auto &procsub_job : procsubs.jobs) {
+            if (feeds) {
+                feeds->jobs.push_back(procsub_job);
+            } else {
+                procsub_job->mark_constructed();
+            }
+        }
+    });
+    scoped_push<procsubs_t *> saved_procsubs(&parser->libdata().procsubs, &procsubs);
+
     // Profiling support.
     profile_item_t *profile_item = this->parser->create_profile_item();
     const auto start_time = profile_item ? profile_item_t::now() : 0;
@@ -1347,10 +1522,11 @@ end_execution_reason_t parse_execution_context_t::run_1_job(const ast::job_t &jo
     job_t::properties_t props{};
     props.initial_background = job_node.bg.has_value();
     props.skip_notification =
-        ld.is_subshell || ld.is_block || ld.is_event || !parser->is_interactive();
+        ld.is_subshell || ld.is_block || ld.is_event || feeds || !parser->is_interactive();
     props.from_event_handler = ld.is_event;
     props.job_control = wants_job_control;
     props.wants_timing = job_node_wants_timing(job_node);
+    props.is_procsub = feeds != nullptr;
 
     // It's an error to have 'time' in a background job.
     if (props.wants_timing && props.initial_background) {
@@ -1372,10 +1548,13 @@ end_execution_reason_t parse_execution_context_t::run_1_job(const ast::job_t &jo
 
     // Clean up the job on failure or cancellation.
     if (pop_result == end_execution_reason_t::ok) {
-        // Resolve the job's group and mark if this job is the first to get it.
-        job->group = job_group_t::resolve_group_for_job(*job, cancel_group, ctx.job_group);
+        // Resolve the job's group and mark if this job is the first to get it. The job joins the
+        // group of its process substitutions, and shares its own with the job it feeds.
+        job_group_ref_t proposed_group = procsubs.group ? procsubs.group : ctx.job_group;
+        job->group = job_group_t::resolve_group_for_job(*job, cancel_group, proposed_group);
         assert(job->group && "Should not have a null group");
-        job->mut_flags().is_group_root = (job->group != ctx.job_group);
+        job->mut_flags().is_group_root = (job->group != proposed_group);
+        if (feeds) feeds->group = job->group;
 
         // Success. Give the job to the parser - it will clean it up.
         parser->job_add(job);
@@ -1398,6 +1577,8 @@ end_execution_reason_t parse_execution_context_t::run_1_job(const ast::job_t &jo
                 parser->libdata().status_count++;
             }
             remove_job(*this->parser, job.get());
+        } else if (feeds) {
+            feeds->jobs.push_back(job);
         }
 
         // Update universal variables on external conmmands.
@@ -15,6 +15,7 @@ class block_t;
 class cancellation_group_t;
 class operation_context_t;
 class parser_t;
+struct pipe_or_redir_t;
 
 /// An eval_result represents evaluation errors including wildcards which failed to match, syntax
 /// errors, or other expansion errors. It also tracks when evaluation was skipped due to signal
@@ -133,6 +134,12 @@ class parse_execution_context_t {
     end_execution_reason_t determine_redirections(const ast::argument_or_redirection_list_t &list,
                                                   redirection_spec_list_t *out_redirections);
 
+    // Opens or closes the script fd for a named fd redirection like {fd}>file, setting the
+    // variable.
+    end_execution_reason_t apply_named_fd_redirection(const ast::redirection_t &redir_node,
+                                                      const pipe_or_redir_t &oper,
+                                                      const wcstring &target);
+
     end_execution_reason_t run_1_job(const ast::job_t &job, const block_t *associated_block);
     end_execution_reason_t test_and_run_1_job_conjunction(const ast::job_conjunction_t &jc,
                                                           const block_t *associated_block);
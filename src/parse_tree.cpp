@@ -35,6 +35,8 @@ parse_error_code_t parse_error_from_tokenizer_error(tokenizer_error_t err) {
             return parse_error_tokenizer_unterminated_slice;
         case tokenizer_error_t::unterminated_escape:
             return parse_error_tokenizer_unterminated_escape;
+        case tokenizer_error_t::unterminated_heredoc:
+            return parse_error_tokenizer_unterminated_heredoc;
         default:
             return parse_error_tokenizer_other;
     }
@@ -1326,7 +1326,8 @@ parser_test_error_bits_t parse_util_detect_errors(const wcstring &buff_src,
         size_t idx = parse_errors.size();
         while (idx--) {
             if (parse_errors.at(idx).code == parse_error_tokenizer_unterminated_quote ||
-                parse_errors.at(idx).code == parse_error_tokenizer_unterminated_subshell) {
+                parse_errors.at(idx).code == parse_error_tokenizer_unterminated_subshell ||
+                parse_errors.at(idx).code == parse_error_tokenizer_unterminated_heredoc) {
                 // Remove this error, since we don't consider it a real error.
                 has_unclosed_quote_or_subshell = true;
                 parse_errors.erase(parse_errors.begin() + idx);
@@ -1378,3 +1379,14 @@ maybe_t<wcstring> parse_util_detect_errors_in_argument_list(const wcstring &arg_
     }
     return none();
 }
+
+wcstring parse_util_ast_json(const wcstring &src, bool *out_errored) {
+    parse_error_list_t errors;
+    auto ast = ast::ast_t::parse(src, parse_flag_continue_after_error | parse_flag_include_comments,
+                                 &errors);
+    if (!ast.errored()) {
+        parse_util_detect_errors(ast, src, &errors);
+    }
+    if (out_errored) *out_errored = !errors.empty();
+    return ast.dump_json(src, errors);
+}
@@ -158,4 +158,8 @@ parser_test_error_bits_t parse_util_detect_errors_in_argument(
 void parse_util_expand_variable_error(const wcstring &token, size_t global_token_pos,
                                       size_t dollar_pos, parse_error_list_t *out_errors);
 
+/// Parse \p src and return its ast and any errors as JSON, as for `fish --print-ast`. Set
+/// \p out_errored if there were errors.
+wcstring parse_util_ast_json(const wcstring &src, bool *out_errored = nullptr);
+
 #endif
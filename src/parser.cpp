@@ -8,7 +8,9 @@
 
 #include <algorithm>
 #include <cwchar>
+#include <map>
 #include <memory>
+#include <tuple>
 #include <utility>
 
 #include "ast.h"
@@ -17,10 +19,13 @@
 #include "event.h"
 #include "expand.h"
 #include "fallback.h"  // IWYU pragma: keep
+#include "fds.h"
 #include "flog.h"
+#include "fish_version.h"
 #include "function.h"
 #include "intern.h"
 #include "job_group.h"
+#include "json.h"
 #include "parse_constants.h"
 #include "parse_execution.h"
 #include "parse_util.h"
@@ -89,6 +94,7 @@ parser_t::parser_t(std::shared_ptr<env_stack_t> vars) : variables(std::move(vars
         perror("Unable to open the current working directory");
         return;
     }
+    fd_set_purpose(cwd, N_(L"working directory"));
     libdata().cwd_fd = std::make_shared<const autoclose_fd_t>(cwd);
 }
 
@@ -166,6 +172,9 @@ void parser_t::pop_block(const block_t *expected) {
     block_list.pop_front();
 
     if (old.wants_pop_env) vars().pop();
+    for (const auto &fd : old.script_fds) {
+        script_fd_close(fd.first, fd.second);
+    }
 
     // Figure out if `status is-block` should consider us to be in a block now.
     bool new_is_block = false;
@@ -231,6 +240,24 @@ block_t *parser_t::block_at_index(size_t idx) {
 
 block_t *parser_t::current_block() { return block_at_index(0); }
 
+/// \return the self time of the profile item at \p idx: its total time, minus the total time
+/// consumed by subsequent items exactly one eval level deeper.
+static long long profile_self_time(const std::deque<profile_item_t> &items, size_t idx) {
+    const profile_item_t &item = items.at(idx);
+    long long self_time = item.duration;
+    for (size_t i = idx + 1; i < items.size(); i++) {
+        const profile_item_t &nested_item = items.at(i);
+        if (nested_item.skipped) continue;
+
+        // If the eval level is not larger, then we have exhausted nested items.
+        if (nested_item.level <= item.level) break;
+
+        // If the eval level is exactly one more than our level, it is a directly nested item.
+        if (nested_item.level == item.level + 1) self_time -= nested_item.duration;
+    }
+    return self_time;
+}
+
 /// Print profiling information to the specified stream.
 static void print_profile(const std::deque<profile_item_t> &items, FILE *out) {
     for (size_t idx = 0; idx < items.size(); idx++) {
@@ -238,21 +265,7 @@ static void print_profile(const std::deque<profile_item_t> &items, FILE *out) {
         if (item.skipped || item.cmd.empty()) continue;
 
         long long total_time = item.duration;
-
-        // Compute the self time as the total time, minus the total time consumed by subsequent
-        // items exactly one eval level deeper.
-        long long self_time = item.duration;
-        for (size_t i = idx + 1; i < items.size(); i++) {
-            const profile_item_t &nested_item = items.at(i);
-            if (nested_item.skipped) continue;
-
-            // If the eval level is not larger, then we have exhausted nested items.
-            if (nested_item.level <= item.level) break;
-
-            // If the eval level is exactly one more than our level, it is a directly nested item.
-            if (nested_item.level == item.level + 1) self_time -= nested_item.duration;
-        }
-
+        long long self_time = profile_self_time(items, idx);
         if (std::fwprintf(out, L"%lld\t%lld\t", self_time, total_time) < 0) {
             wperror(L"fwprintf");
             return;
@@ -272,21 +285,238 @@ static void print_profile(const std::deque<profile_item_t> &items, FILE *out) {
     }
 }
 
+namespace {
+/// Where a profiled command runs: the file, and the function or else the top level of the file.
+struct profile_context_t {
+    wcstring file;
+    wcstring function;
+
+    explicit profile_context_t(const profile_item_t &item)
+        : file(item.file ? item.file : L""), function(item.function) {}
+
+    bool operator<(const profile_context_t &rhs) const {
+        return std::tie(file, function) < std::tie(rhs.file, rhs.function);
+    }
+    bool operator==(const profile_context_t &rhs) const {
+        return file == rhs.file && function == rhs.function;
+    }
+    bool operator!=(const profile_context_t &rhs) const { return !(*this == rhs); }
+};
+
+/// Calls from one line of a context into another context.
+struct profile_call_t {
+    long long count{};
+    long long time{};
+    int target_line{};
+};
+
+/// The times of the commands in a context, by line, and the calls it makes.
+struct profile_context_times_t {
+    std::map<int, long long> self_by_line;
+    std::map<std::pair<int, profile_context_t>, profile_call_t> calls;
+};
+
+/// The profile items added up by context.
+struct profile_summary_t {
+    std::map<profile_context_t, profile_context_times_t> contexts;
+    std::vector<long long> self_times;
+
+    explicit profile_summary_t(const std::deque<profile_item_t> &items);
+};
+}  // namespace
+
+profile_summary_t::profile_summary_t(const std::deque<profile_item_t> &items) {
+    for (size_t idx = 0; idx < items.size(); idx++) {
+        const profile_item_t &item = items.at(idx);
+        self_times.push_back(profile_self_time(items, idx));
+        if (item.skipped) continue;
+        profile_context_t ctx(item);
+        profile_context_times_t &times = contexts[ctx];
+        times.self_by_line[std::max(item.line, 0)] += self_times.back();
+
+        // Directly nested items in another context were called by this item, e.g. the commands in
+        // a function or a sourced file. Consecutive items in the same context count as one call.
+        int line = std::max(item.line, 0);
+        maybe_t<profile_context_t> last_callee;
+        for (size_t i = idx + 1; i < items.size(); i++) {
+            const profile_item_t &nested_item = items.at(i);
+            if (nested_item.skipped) continue;
+            if (nested_item.level <= item.level) break;
+            if (nested_item.level != item.level + 1) continue;
+            profile_context_t callee(nested_item);
+            if (callee == ctx) {
+                last_callee.reset();
+                continue;
+            }
+            profile_call_t &call = times.calls[{line, callee}];
+            if (!last_callee || *last_callee != callee) {
+                if (call.count++ == 0) call.target_line = std::max(nested_item.line, 0);
+            }
+            call.time += nested_item.duration;
+            last_callee = std::move(callee);
+        }
+    }
+}
+
+/// \return the name of the context \p ctx, as shown in the profile.
+static wcstring profile_context_name(const profile_context_t &ctx) {
+    if (!ctx.function.empty()) return ctx.function;
+    if (!ctx.file.empty()) return ctx.file;
+    return L"(no file)";
+}
+
+/// \return what the file at \p path is for: "config" for config.fish, "conf.d" for conf.d
+/// snippets, "function" and "completion" for autoloaded files, or "script" for others.
+static const wchar_t *profile_file_kind(const wcstring &path, const environment_t &vars) {
+    wcstring dir = wdirname(path);
+    auto in_list = [&](const wchar_t *name) {
+        auto var = vars.get(name);
+        if (!var) return false;
+        const wcstring_list_t &dirs = var->as_list();
+        return std::find(dirs.begin(), dirs.end(), dir) != dirs.end();
+    };
+    if (wbasename(path) == L"config.fish") return L"config";
+    if (in_list(L"__fish_conf_dirs")) return L"conf.d";
+    if (in_list(L"fish_function_path")) return L"function";
+    if (in_list(L"fish_complete_path")) return L"completion";
+    return L"script";
+}
+
+/// \return the profile as JSON: each command, then the totals for each file and function.
+static wcstring profile_to_json(const std::deque<profile_item_t> &items,
+                                const environment_t &vars) {
+    profile_summary_t summary(items);
+    wcstring out = L"{\"commands\":[";
+    bool first = true;
+    for (size_t idx = 0; idx < items.size(); idx++) {
+        const profile_item_t &item = items.at(idx);
+        if (item.skipped || item.cmd.empty()) continue;
+        if (!first) out.push_back(L',');
+        first = false;
+        append_format(out, L"{\"command\":%ls,\"file\":%ls,\"line\":%d,\"function\":%ls,",
+                      json_string(item.cmd).c_str(),
+                      item.file ? json_string(item.file).c_str() : L"null", item.line,
+                      item.function.empty() ? L"null" : json_string(item.function).c_str());
+        append_format(out, L"\"level\":%lu,\"self\":%lld,\"total\":%lld}",
+                      static_cast<unsigned long>(item.level), summary.self_times.at(idx),
+                      item.duration);
+    }
+
+    // Add up the time in each file and function, and the time spent in the calls to them.
+    struct totals_t {
+        long long self{};
+        long long total{};
+        long long calls{};
+    };
+    std::map<wcstring, totals_t> files;
+    std::map<wcstring, totals_t> functions;
+    std::map<wcstring, wcstring> function_files;
+    for (const auto &kv : summary.contexts) {
+        const profile_context_t &ctx = kv.first;
+        long long self = 0;
+        for (const auto &line : kv.second.self_by_line) self += line.second;
+        if (!ctx.file.empty()) files[ctx.file].self += self;
+        if (!ctx.function.empty()) {
+            functions[ctx.function].self += self;
+            function_files[ctx.function] = ctx.file;
+        }
+        for (const auto &call : kv.second.calls) {
+            const profile_context_t &callee = call.first.second;
+            totals_t &totals =
+                callee.function.empty() ? files[callee.file] : functions[callee.function];
+            totals.total += call.second.time;
+            totals.calls += call.second.count;
+        }
+    }
+    files.erase(L"");
+
+    out.append(L"],\"files\":[");
+    first = true;
+    for (const auto &kv : files) {
+        if (!first) out.push_back(L',');
+        first = false;
+        append_format(out, L"{\"file\":%ls,\"kind\":\"%ls\",\"self\":%lld,\"total\":%lld,",
+                      json_string(kv.first).c_str(), profile_file_kind(kv.first, vars),
+                      kv.second.self, kv.second.total);
+        append_format(out, L"\"sourced\":%lld}", kv.second.calls);
+    }
+
+    out.append(L"],\"functions\":[");
+    first = true;
+    for (const auto &kv : functions) {
+        if (!first) out.push_back(L',');
+        first = false;
+        const wcstring &file = function_files[kv.first];
+        append_format(out, L"{\"name\":%ls,\"file\":%ls,\"self\":%lld,\"total\":%lld,",
+                      json_string(kv.first).c_str(),
+                      file.empty() ? L"null" : json_string(file).c_str(), kv.second.self,
+                      kv.second.total);
+        append_format(out, L"\"calls\":%lld}", kv.second.calls);
+    }
+    out.append(L"]}\n");
+    return out;
+}
+
+/// \return the profile in the callgrind format. Each function is a function there, and so is the
+/// top level of each file, named after the file.
+static wcstring profile_to_callgrind(const std::deque<profile_item_t> &items) {
+    profile_summary_t summary(items);
+    wcstring out = L"# callgrind format\nversion: 1\n";
+    append_format(out, L"creator: fish %s\n", get_fish_version());
+    out.append(L"positions: line\nevent: us : Microseconds\nevents: us\n");
+    auto file_name = [](const profile_context_t &ctx) {
+        return ctx.file.empty() ? wcstring(L"(no file)") : ctx.file;
+    };
+    for (const auto &kv : summary.contexts) {
+        const profile_context_t &ctx = kv.first;
+        append_format(out, L"\nfl=%ls\nfn=%ls\n", file_name(ctx).c_str(),
+                      profile_context_name(ctx).c_str());
+        for (const auto &line : kv.second.self_by_line) {
+            append_format(out, L"%d %lld\n", line.first, line.second);
+        }
+        for (const auto &call : kv.second.calls) {
+            const profile_context_t &callee = call.first.second;
+            append_format(out, L"cfl=%ls\ncfn=%ls\ncalls=%lld %d\n%d %lld\n",
+                          file_name(callee).c_str(), profile_context_name(callee).c_str(),
+                          call.second.count, call.second.target_line, call.first.first,
+                          call.second.time);
+        }
+    }
+    return out;
+}
+
 void parser_t::clear_profiling() {
     profile_items.clear();
 }
 
-void parser_t::emit_profiling(const char *path) const {
+void parser_t::emit_profiling(const char *path, profile_format_t format) const {
     // Save profiling information. OK to not use CLO_EXEC here because this is called while fish is
     // exiting (and hence will not fork).
     FILE *f = fopen(path, "w");
     if (!f) {
         FLOGF(warning, _(L"Could not write profiling information to file '%s'"), path);
     } else {
-        if (std::fwprintf(f, _(L"Time\tSum\tCommand\n"), profile_items.size()) < 0) {
-            wperror(L"fwprintf");
-        } else {
-            print_profile(profile_items, f);
+        switch (format) {
+            case profile_format_t::text: {
+                if (std::fwprintf(f, _(L"Time\tSum\tCommand\n"), profile_items.size()) < 0) {
+                    wperror(L"fwprintf");
+                } else {
+                    print_profile(profile_items, f);
+                }
+                break;
+            }
+            case profile_format_t::json: {
+                if (std::fputws(profile_to_json(profile_items, vars()).c_str(), f) < 0) {
+                    wperror(L"fputws");
+                }
+                break;
+            }
+            case profile_format_t::callgrind: {
+                if (std::fputws(profile_to_callgrind(profile_items).c_str(), f) < 0) {
+                    wperror(L"fputws");
+                }
+                break;
+            }
         }
 
         if (fclose(f)) {
@@ -414,6 +644,60 @@ wcstring parser_t::stack_trace() const {
     return trace;
 }
 
+wcstring parser_t::stack_trace_json() const {
+    wcstring result = L"[";
+    for (const auto &b : blocks()) {
+        // The same frames as in the stack trace.
+        wcstring frame;
+        switch (b.type()) {
+            case block_type_t::function_call:
+            case block_type_t::function_call_no_shadow: {
+                frame = L"{\"type\":\"function\",\"function\":" + json_string(b.function_name) +
+                        L",\"arguments\":" + json_string_list(b.function_args);
+                break;
+            }
+            case block_type_t::subst: {
+                frame = L"{\"type\":\"command-substitution\"";
+                break;
+            }
+            case block_type_t::source: {
+                frame = L"{\"type\":\"source\",\"sourced_file\":" + json_string(b.sourced_file);
+                break;
+            }
+            case block_type_t::event: {
+                assert(b.event && "Should have an event");
+                frame = L"{\"type\":\"event\",\"event\":" +
+                        json_string(event_get_desc(*this, *b.event)) +
+                        L",\"arguments\":" + json_string_list(b.event->arguments);
+                break;
+            }
+            case block_type_t::top:
+            case block_type_t::begin:
+            case block_type_t::switch_block:
+            case block_type_t::while_block:
+            case block_type_t::for_block:
+            case block_type_t::if_block:
+            case block_type_t::breakpoint:
+            case block_type_t::variable_assignment:
+                continue;
+        }
+
+        // Where it was called from.
+        if (b.src_filename) {
+            append_format(frame, L",\"file\":%ls,\"line\":%d}", json_string(b.src_filename).c_str(),
+                          b.src_lineno);
+        } else {
+            frame.append(L",\"file\":null,\"line\":null}");
+        }
+        if (result.size() > 1) result.push_back(L',');
+        result.append(frame);
+
+        if (b.type() == block_type_t::event) break;
+    }
+    result.append(L"]\n");
+    return result;
+}
+
 /// Returns the name of the currently evaluated function if we are currently evaluating a function,
 /// NULL otherwise. This is tested by moving down the block-scope-stack, checking every block if it
 /// is of type FUNCTION_CALL. If the caller doesn't specify a starting position in the stack we
@@ -477,6 +761,10 @@ int parser_t::get_lineno() const {
     return lineno;
 }
 
+int parser_t::get_source_offset() const {
+    return execution_context ? execution_context->get_current_source_offset() : -1;
+}
+
 const wchar_t *parser_t::current_filename() const {
     ASSERT_IS_MAIN_THREAD();
 
@@ -596,7 +884,11 @@ job_t *parser_t::job_get_from_pid(pid_t pid) const {
 profile_item_t *parser_t::create_profile_item() {
     if (g_profiling_active) {
         profile_items.emplace_back();
-        return &profile_items.back();
+        profile_item_t *item = &profile_items.back();
+        item->file = current_filename();
+        item->line = get_lineno();
+        if (const wchar_t *func = is_function()) item->function = func;
+        return item;
     }
     return nullptr;
 }
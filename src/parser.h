@@ -87,6 +87,9 @@ class block_t {
     // If this is an event block, the event. Otherwise ignored.
     maybe_t<event_t> event;
 
+    // Script fds opened in this block's scope, with their ids. They are closed when it is popped.
+    std::vector<std::pair<int, uint64_t>> script_fds{};
+
     block_type_t type() const { return this->block_type; }
 
     /// Description of the block, for debugging.
@@ -129,19 +132,69 @@ struct profile_item_t {
     /// The command string.
     wcstring cmd{};
 
+    /// The file the command is in, or nullptr if it is not in a file. This is intern'd.
+    const wchar_t *file{};
+
+    /// The line of the command in its file.
+    int line{};
+
+    /// The name of the function the command is in, or empty if it is not in a function.
+    wcstring function{};
+
     /// \return the current time as a microsecond timestamp since the epoch.
     static microseconds_t now() { return get_time(); }
 };
 
+/// The formats in which profiling information can be written.
+enum class profile_format_t {
+    text,       // a table of the commands and their times
+    json,       // the commands, with totals per file and per function
+    callgrind,  // the format of callgrind, for kcachegrind and flamegraph tools
+};
+
 class parse_execution_context_t;
 class completion_t;
 struct event_t;
+class parser_t;
+
+/// A debugger attached to a parser, as by `fish --debug-adapter`. It is told about each job before
+/// the job runs, and may stop execution there until the user resumes it.
+class debugger_t {
+   public:
+    virtual ~debugger_t() = default;
+
+    /// Called before each job runs. The job's location is available from the parser.
+    virtual void will_run_job(parser_t &parser) = 0;
+
+    /// Called by the `breakpoint` builtin, which stops unconditionally.
+    virtual void breakpoint(parser_t &parser) = 0;
+};
+
+/// The process substitutions like <(cmd) of a job being populated.
+struct procsubs_t {
+    /// The read ends of the pipes feeding them. These are closed once the job has been launched.
+    std::vector<autoclose_fd_t> fds{};
+
+    /// The group of the jobs which run their commands alongside the job, which the job joins. Null
+    /// if there are none.
+    job_group_ref_t group{};
+
+    /// Those jobs. They are marked constructed along with the job, so the leader of their group is
+    /// not reaped before the job can join it.
+    job_list_t jobs{};
+};
 
 /// Miscellaneous data used to avoid recursion and others.
 struct library_data_t {
     /// A counter incremented every time a command executes.
     uint64_t exec_count{0};
 
+    /// The timeouts of the timeout builtins being run, innermost last.
+    std::vector<std::shared_ptr<job_timeout_t>> timeouts{};
+
+    /// The scheduling priority for external commands, if set by `command --nice` or `--ionice`.
+    maybe_t<spawn_priority_t> spawn_priority{};
+
     /// A counter incremented every time a command produces a $status.
     uint64_t status_count{0};
 
@@ -181,6 +234,17 @@ struct library_data_t {
     /// Whether we are currently interactive.
     bool is_interactive{false};
 
+    /// Whether errors should not stop evaluation, as with 'source --keep-going'. This is used for
+    /// config files, so that a typo does not prevent the rest of the configuration from loading.
+    bool keep_going_on_errors{false};
+
+    /// Errors that occurred while keep_going_on_errors was set. These are reported together once
+    /// evaluation is done, by reader_report_deferred_errors().
+    wcstring_list_t deferred_errors{};
+
+    /// Whether the file about to be sourced is being autoloaded, so its parsed form is cached.
+    bool is_autoloading{false};
+
     /// Whether to suppress fish_trace output. This occurs in the prompt, event handlers, and key
     /// bindings.
     bool suppress_fish_trace{false};
@@ -217,6 +281,16 @@ struct library_data_t {
     /// A file descriptor holding the current working directory, for use in openat().
     /// This is never null and never invalid.
     std::shared_ptr<const autoclose_fd_t> cwd_fd{};
+
+    /// The process substitutions of the job being populated, or null if there is none.
+    procsubs_t *procsubs{};
+
+    /// If we are running the command of a process substitution, the process substitutions of the
+    /// job it feeds.
+    procsubs_t *procsub_target{};
+
+    /// The attached debugger, or null for none.
+    debugger_t *debugger{};
 };
 
 class operation_context_t;
@@ -338,6 +412,9 @@ class parser_t : public std::enable_shared_from_this<parser_t> {
     /// Returns the current line number.
     int get_lineno() const;
 
+    /// Returns the offset of the current job within its source, or -1 if there is none.
+    int get_source_offset() const;
+
     /// Returns the block at the given index. 0 corresponds to the innermost block. Returns nullptr
     /// when idx is at or equal to the number of blocks.
     const block_t *block_at_index(size_t idx) const;
@@ -395,7 +472,8 @@ class parser_t : public std::enable_shared_from_this<parser_t> {
     /// Returns the job with the given pid.
     job_t *job_get_from_pid(pid_t pid) const;
 
-    /// Returns a new profile item if profiling is active. The caller should fill it in.
+    /// Returns a new profile item if profiling is active, with the location of the command being
+    /// executed filled in. The caller should fill in the rest.
     /// The parser_t will deallocate it.
     /// If profiling is not active, this returns nullptr.
     profile_item_t *create_profile_item();
@@ -403,8 +481,9 @@ class parser_t : public std::enable_shared_from_this<parser_t> {
     /// Remove the profiling items.
     void clear_profiling();
 
-    /// Output profiling data to the given filename.
-    void emit_profiling(const char *path) const;
+    /// Output profiling data to the given filename, in the given format.
+    void emit_profiling(const char *path,
+                        profile_format_t format = profile_format_t::text) const;
 
     void get_backtrace(const wcstring &src, const parse_error_list_t &errors,
                        wcstring &output) const;
@@ -421,6 +500,10 @@ class parser_t : public std::enable_shared_from_this<parser_t> {
     /// Return a string representing the current stack trace.
     wcstring stack_trace() const;
 
+    /// Return the current stack trace as a JSON array of frames, innermost first, each with its
+    /// type, function and arguments or event, and the file and line it was called from.
+    wcstring stack_trace_json() const;
+
     /// \return whether the number of functions in the stack exceeds our stack depth limit.
     bool function_stack_is_overflowing() const;
 
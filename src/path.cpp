@@ -11,6 +11,7 @@
 
 #include <cstring>
 #include <cwchar>
+#include <cwctype>
 #include <memory>
 #include <string>
 #include <type_traits>
@@ -21,6 +22,7 @@
 #include "expand.h"
 #include "fallback.h"  // IWYU pragma: keep
 #include "flog.h"
+#include "future_feature_flags.h"
 #include "wcstringutil.h"
 #include "wutil.h"  // IWYU pragma: keep
 
@@ -228,6 +230,11 @@ maybe_t<wcstring> path_as_implicit_cd(const wcstring &path, const wcstring &wd,
         // cannot (that's used for sourcing files anyways).
         return path_get_cdpath(exp_path, wd, vars);
     }
+    if (feature_test(features_t::auto_cd) && exp_path != L"." && exp_path != L"-") {
+        // With auto-cd any other directory can be cd'd to as well. We are only called once no
+        // function, builtin or executable of that name was found.
+        return path_get_cdpath(exp_path, wd, vars);
+    }
     return none();
 }
 
@@ -295,7 +302,7 @@ static void maybe_issue_path_warning(const wcstring &which_dir, const wcstring &
 /// existing parent directories, like mkdir -p,.
 ///
 /// \return 0 if, at the time of function return the directory exists, -1 otherwise.
-static int create_directory(const wcstring &d) {
+int create_directory(const wcstring &d) {
     bool ok = false;
     struct stat buf;
     int stat_res = 0;
@@ -314,15 +321,6 @@ static int create_directory(const wcstring &d) {
     return ok ? 0 : -1;
 }
 
-/// The following type wraps up a user's "base" directories, corresponding (conceptually if not
-/// actually) to XDG spec.
-struct base_directory_t {
-    wcstring path{};       /// the path where we attempted to create the directory.
-    bool success{false};   /// whether creating the directory succeeded.
-    int err{0};            /// the error code if creating the directory failed.
-    bool used_xdg{false};  /// whether an XDG variable was used in resolving the directory.
-};
-
 /// Attempt to get a base directory, creating it if necessary. If a variable named \p xdg_var is
 /// set, use that directory; otherwise use the path \p non_xdg_homepath rooted in $HOME. \return the
 /// result; see the base_directory_t fields.
@@ -350,16 +348,21 @@ static base_directory_t make_base_directory(const wcstring &xdg_var,
     return result;
 }
 
-static const base_directory_t &get_data_directory() {
+const base_directory_t &get_data_directory() {
     static base_directory_t s_dir = make_base_directory(L"XDG_DATA_HOME", L"/.local/share/fish");
     return s_dir;
 }
 
-static const base_directory_t &get_config_directory() {
+const base_directory_t &get_config_directory() {
     static base_directory_t s_dir = make_base_directory(L"XDG_CONFIG_HOME", L"/.config/fish");
     return s_dir;
 }
 
+const base_directory_t &get_cache_directory() {
+    static base_directory_t s_dir = make_base_directory(L"XDG_CACHE_HOME", L"/.cache/fish");
+    return s_dir;
+}
+
 void path_emit_config_directory_errors(env_stack_t &vars) {
     const auto &data = get_data_directory();
     if (!data.success) {
@@ -387,6 +390,12 @@ bool path_get_data(wcstring &path) {
     return dir.success;
 }
 
+bool path_get_cache(wcstring &path) {
+    const auto &dir = get_cache_directory();
+    path = dir.success ? dir.path : L"";
+    return dir.success;
+}
+
 void path_make_canonical(wcstring &path) {
     // Ignore trailing slashes, unless it's the first character.
     size_t len = path.size();
@@ -487,3 +496,70 @@ void append_path_component(wcstring &path, const wcstring &component) {
         path.append(component);
     }
 }
+
+maybe_t<windows_path_style_t> path_get_windows_style() {
+    if (is_cygwin()) return windows_path_style_t::msys;
+    if (is_windows_subsystem_for_linux()) return windows_path_style_t::wsl;
+    return none();
+}
+
+/// \return the directory a Windows drive is mounted on, like /mnt/c.
+static wcstring drive_mount_point(wchar_t drive, windows_path_style_t style) {
+    wcstring result = style == windows_path_style_t::wsl ? L"/mnt/" : L"/";
+    result.push_back(std::towlower(drive));
+    return result;
+}
+
+static bool is_drive_letter(wchar_t c) {
+    return (c >= L'a' && c <= L'z') || (c >= L'A' && c <= L'Z');
+}
+
+maybe_t<wcstring> path_from_windows(const wcstring &path, windows_path_style_t style) {
+    wcstring result;
+    size_t rest = 0;
+    if (path.size() >= 2 && is_drive_letter(path[0]) && path[1] == L':') {
+        // A path relative to the working directory of a drive, like C:foo, can't be translated.
+        if (path.size() > 2 && path[2] != L'\\' && path[2] != L'/') return none();
+        result = drive_mount_point(path[0], style);
+        rest = 2;
+    } else if (string_prefixes_string(L"\\\\", path)) {
+        // Only the files of the WSL distribution itself, like \\wsl$\Ubuntu\home, are known.
+        if (style != windows_path_style_t::wsl) return none();
+        size_t host_end = path.find(L'\\', 2);
+        if (host_end == wcstring::npos) return none();
+        wcstring host = path.substr(2, host_end - 2);
+        if (host != L"wsl$" && host != L"wsl.localhost") return none();
+        rest = path.find(L'\\', host_end + 1);
+        if (rest == wcstring::npos) return wcstring{L"/"};
+    } else if (!path.empty() && path[0] == L'\\') {
+        // The root of the current drive depends on the Windows working directory.
+        return none();
+    }
+    for (size_t i = rest; i < path.size(); i++) {
+        result.push_back(path[i] == L'\\' ? L'/' : path[i]);
+    }
+    return result;
+}
+
+maybe_t<wcstring> path_to_windows(const wcstring &path, windows_path_style_t style) {
+    wcstring result;
+    size_t rest = 0;
+    if (!path.empty() && path[0] == L'/') {
+        const wcstring prefix = style == windows_path_style_t::wsl ? L"/mnt/" : L"/";
+        size_t drive = prefix.size();
+        if (!string_prefixes_string(prefix, path) || path.size() <= drive ||
+            !is_drive_letter(path[drive]) ||
+            (path.size() > drive + 1 && path[drive + 1] != L'/')) {
+            return none();
+        }
+        result.push_back(std::towupper(path[drive]));
+        result.push_back(L':');
+        rest = drive + 1;
+        // The drive itself is its root directory.
+        if (rest == path.size()) result.push_back(L'\\');
+    }
+    for (size_t i = rest; i < path.size(); i++) {
+        result.push_back(path[i] == L'/' ? L'\\' : path[i]);
+    }
+    return result;
+}
@@ -29,6 +29,31 @@ bool path_get_config(wcstring &path);
 /// \return whether the directory was returned successfully
 bool path_get_data(wcstring &path);
 
+/// Returns the user cache directory for fish. If the directory or one of its parents doesn't exist,
+/// they are first created.
+///
+/// Files which can be recreated at any time, like the parsed form of autoloaded files, are stored
+/// in this directory.
+///
+/// \param path The directory as an out param
+/// \return whether the directory was returned successfully
+bool path_get_cache(wcstring &path);
+
+/// The following type wraps up a user's "base" directories, corresponding (conceptually if not
+/// actually) to XDG spec.
+struct base_directory_t {
+    wcstring path{};       /// the path where we attempted to create the directory.
+    bool success{false};   /// whether creating the directory succeeded.
+    int err{0};            /// the error code if creating the directory failed.
+    bool used_xdg{false};  /// whether an XDG variable was used in resolving the directory.
+};
+
+/// \return the data, config and cache directories, including why they could not be created if
+/// they couldn't. These are resolved once, the first time they are needed.
+const base_directory_t &get_data_directory();
+const base_directory_t &get_config_directory();
+const base_directory_t &get_cache_directory();
+
 /// Emit any errors if config directories are missing.
 /// Use the given environment stack to ensure this only occurs once.
 class env_stack_t;
@@ -65,7 +90,8 @@ maybe_t<wcstring> path_get_cdpath(const wcstring &dir, const wcstring &wd,
                                   const environment_t &vars);
 
 /// Returns the path resolved as an implicit cd command, or none() if none. This requires it to
-/// start with one of the allowed prefixes (., .., ~) and resolve to a directory.
+/// start with one of the allowed prefixes (., .., ~) and resolve to a directory, unless the auto-cd
+/// feature is enabled, in which case any directory will do.
 maybe_t<wcstring> path_as_implicit_cd(const wcstring &path, const wcstring &wd,
                                       const environment_t &vars);
 
@@ -89,4 +115,28 @@ wcstring path_apply_working_directory(const wcstring &path, const wcstring &work
 /// Appends a path component, with a / if necessary.
 void append_path_component(wcstring &path, const wcstring &component);
 
+/// The ways Unix environments on Windows name the files on Windows drives.
+enum class windows_path_style_t {
+    wsl,   // Windows Subsystem for Linux, with drives like C: mounted on /mnt/c
+    msys,  // MSYS2 and Cygwin, with drives mounted on /c
+};
+
+/// \return the style of the Unix environment on Windows that fish runs in, or none() if it is not
+/// running on Windows.
+maybe_t<windows_path_style_t> path_get_windows_style();
+
+/// Translate a Windows path like C:\Users into the form used by a Unix environment on Windows,
+/// like /mnt/c/Users. Relative paths only have their separators changed.
+/// \return the path, or none() if the Unix environment has no name for it, like \\server\share.
+maybe_t<wcstring> path_from_windows(const wcstring &path, windows_path_style_t style);
+
+/// Translate a path like /mnt/c/Users into its Windows form, like C:\Users. Relative paths only
+/// have their separators changed.
+/// \return the path, or none() if it is not on a Windows drive, like /usr.
+maybe_t<wcstring> path_to_windows(const wcstring &path, windows_path_style_t style);
+
+/// Create the directory \p d and its missing parents, like `mkdir -p`. New directories are only
+/// accessible by the user. \return 0 on success, -1 on failure.
+int create_directory(const wcstring &d);
+
 #endif
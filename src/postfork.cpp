@@ -13,6 +13,9 @@
 #include <spawn.h>
 #endif
 #include <cwchar>
+#ifdef __linux__
+#include <sys/syscall.h>
+#endif
 
 #include "common.h"
 #include "exec.h"
@@ -165,6 +168,27 @@ int child_setup_process(pid_t new_termowner, pid_t fish_pgrp, const job_t &job,
     return 0;
 }
 
+void child_set_priority(const spawn_priority_t &priority) {
+    if (priority.nice != 0) {
+        // nice() may legitimately return -1, so errno tells about failure.
+        errno = 0;
+        if (nice(priority.nice) == -1 && errno != 0) safe_perror("nice");
+    }
+#if defined(__linux__) && defined(SYS_ioprio_set)
+    if (priority.ioprio >= 0) {
+        const int ioprio_who_process = 1;
+        if (syscall(SYS_ioprio_set, ioprio_who_process, 0, priority.ioprio) < 0) {
+            safe_perror("ioprio_set");
+        }
+    }
+#endif
+}
+
+void child_join_cgroup(int procs_fd) {
+    // Writing 0 moves the process that writes.
+    if (write(procs_fd, "0", 1) < 0) safe_perror("cgroup");
+}
+
 /// This function is a wrapper around fork. If the fork calls fails with EAGAIN, it is retried
 /// FORK_LAPS times, with a very slight delay between each lap. If fork fails even then, the process
 /// will exit with an error message.
@@ -19,6 +19,7 @@
 class dup2_list_t;
 class job_t;
 class process_t;
+struct spawn_priority_t;
 
 /// Tell the proc \p pid to join process group \p pgroup.
 /// If \p is_child is true, we are the child process; otherwise we are fish.
@@ -46,6 +47,15 @@ void report_setpgid_error(int err, bool is_parent, pid_t desired_pgid, const job
 int child_setup_process(pid_t new_termowner, pid_t fish_pgrp, const job_t &job, bool is_forked,
                         const dup2_list_t &dup2s);
 
+/// Apply \p priority to the current process, which is a forked child. Failures are reported, but
+/// the command is run anyway, like nice(1) does.
+void child_set_priority(const spawn_priority_t &priority);
+
+/// Move the current process, which is a forked child, into the cgroup whose cgroup.procs file is
+/// open as \p procs_fd. This is done before any redirections are applied, which might replace the
+/// fd.
+void child_join_cgroup(int procs_fd);
+
 /// Call fork(), retrying on failure a few times.
 pid_t execute_fork();
 
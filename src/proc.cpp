@@ -29,6 +29,7 @@
 #ifdef HAVE_SYS_SELECT_H
 #include <sys/select.h>
 #endif
+#include <sys/resource.h>
 #include <sys/time.h>  // IWYU pragma: keep
 #include <sys/types.h>
 
@@ -43,14 +44,17 @@
 #include "flog.h"
 #include "global_safety.h"
 #include "io.h"
+#include "iothread.h"
 #include "job_group.h"
 #include "output.h"
 #include "parse_tree.h"
 #include "parser.h"
 #include "proc.h"
+#include "proc_info.h"
 #include "reader.h"
 #include "sanity.h"
 #include "signal.h"
+#include "timer.h"
 #include "wcstringutil.h"
 #include "wutil.h"  // IWYU pragma: keep
 
@@ -69,12 +73,6 @@ static relaxed_atomic_bool_t s_no_exec{false};
 bool no_exec() { return s_no_exec; }
 void mark_no_exec() { s_no_exec = true; }
 
-bool have_proc_stat() {
-    // Check for /proc/self/stat to see if we are running with Linux-style procfs.
-    static const bool s_result = (access("/proc/self/stat", R_OK) == 0);
-    return s_result;
-}
-
 static relaxed_atomic_t<job_control_t> job_control_mode{job_control_t::interactive};
 
 job_control_t get_job_control_mode() { return job_control_mode; }
@@ -166,12 +164,72 @@ bool job_t::signal(int signal) {
     return true;
 }
 
+std::shared_ptr<job_timeout_t> job_timeout_t::start(duration_t timeout, int signal,
+                                                    duration_t kill_after) {
+    auto result = std::make_shared<job_timeout_t>(signal);
+    auto deadline = std::chrono::steady_clock::now() +
+                    std::chrono::duration_cast<std::chrono::steady_clock::duration>(timeout);
+    auto kill_delay = std::chrono::duration_cast<std::chrono::steady_clock::duration>(kill_after);
+    bool started = make_detached_pthread([=] {
+        if (!result->wait_until(deadline)) return;
+        // Wake up the shell if it is waiting for a job. It will find nothing to reap, but notice
+        // that the timeout has expired.
+        result->expired_ = true;
+        topic_monitor_t::principal().post(topic_t::sigchld);
+        if (kill_delay.count() <= 0 || !result->wait_until(deadline + kill_delay)) return;
+        result->kill_expired_ = true;
+        topic_monitor_t::principal().post(topic_t::sigchld);
+    });
+    if (!started) return nullptr;
+    return result;
+}
+
+bool job_timeout_t::wait_until(std::chrono::steady_clock::time_point deadline) {
+    std::unique_lock<std::mutex> locker(lock_);
+    return !cond_.wait_until(locker, deadline, [this] { return cancelled_; });
+}
+
+void job_timeout_t::cancel() {
+    {
+        std::lock_guard<std::mutex> locker(lock_);
+        cancelled_ = true;
+    }
+    cond_.notify_all();
+}
+
+void job_timeout_t::enforce(job_t *job, int *stage) const {
+    if (*stage < 1 && expired_) {
+        FLOGF(proc_job_run, "Timeout expired, sending %ls to job %d", sig2wcs(signal_),
+              job->job_id());
+        job->mut_flags().timed_out = true;
+        job->signal(signal_);
+        // Stopped processes only get the signal once they are continued.
+        if (signal_ != SIGKILL && signal_ != SIGCONT) job->signal(SIGCONT);
+        *stage = 1;
+    }
+    if (*stage < 2 && kill_expired_) {
+        FLOGF(proc_job_run, "Timeout expired again, sending SIGKILL to job %d", job->job_id());
+        job->signal(SIGKILL);
+        *stage = 2;
+    }
+}
+
 maybe_t<statuses_t> job_t::get_statuses() const {
     statuses_t st{};
     bool has_status = false;
     int laststatus = 0;
     st.pipestatus.reserve(processes.size());
+    st.durations.reserve(processes.size());
+    st.argvs.reserve(processes.size());
     for (const auto &p : processes) {
+        st.argvs.push_back(p->get_argv_array().to_list());
+
+        // A process which did not finish, e.g. because it was stopped, has no duration yet.
+        const process_t::timing_t &timing = p->timing;
+        auto elapsed =
+            std::chrono::duration_cast<std::chrono::milliseconds>(timing.end - timing.start);
+        st.durations.push_back(timing.end > timing.start ? static_cast<long>(elapsed.count()) : 0);
+
         auto status = p->status;
         if (status.is_empty()) {
             // Corner case for if a variable assignment is part of a pipeline.
@@ -245,7 +303,8 @@ static void handle_child_status(const shared_ptr<job_t> &job, process_t *proc,
     }
 
     // If the child was killed by SIGINT or SIGQUIT, then treat it as if we received that signal.
-    if (status.signal_exited()) {
+    // Unless we sent it, because of a timeout.
+    if (status.signal_exited() && !job->flags().timed_out) {
         int sig = status.signal_code();
         if (sig == SIGINT || sig == SIGQUIT) {
             if (is_interactive_session()) {
@@ -421,15 +480,20 @@ static void process_mark_finished_children(parser_t &parser, bool block_ok) {
             if (proc->gens_.sigchld == reapgens.sigchld) continue;
             proc->gens_.sigchld = reapgens.sigchld;
 
-            // Ok, we are reapable. Run waitpid()!
+            // Ok, we are reapable. Run waitpid()! We use wait4() to get the resource usage for
+            // 'time'.
             int statusv = -1;
-            pid_t pid = waitpid(proc->pid, &statusv, WNOHANG | WUNTRACED | WCONTINUED);
+            struct rusage usage;
+            pid_t pid = wait4(proc->pid, &statusv, WNOHANG | WUNTRACED | WCONTINUED, &usage);
             assert((pid <= 0 || pid == proc->pid) && "Unexpcted waitpid() return");
             if (pid <= 0) continue;
 
             // The process has stopped or exited! Update its status.
             proc_status_t status = proc_status_t::from_waitpid(statusv);
             handle_child_status(j, proc.get(), status);
+            if (proc->completed) {
+                timer_record_external_exit(proc.get(), usage);
+            }
             if (status.stopped()) {
                 j->group->set_is_foreground(false);
             }
@@ -437,8 +501,9 @@ static void process_mark_finished_children(parser_t &parser, bool block_ok) {
                 j->mut_flags().notified = false;
             }
             if (status.normal_exited() || status.signal_exited()) {
-                FLOGF(proc_reap_external, "Reaped external process '%ls' (pid %d, status %d)",
-                      proc->argv0(), pid, proc->status.status_value());
+                FLOG(proc_reap_external, "Reaped external process",
+                     flog_kv("command", proc->argv0()), flog_kv("pid", pid),
+                     flog_kv("status", proc->status.status_value()));
             } else {
                 assert(status.stopped() || status.continued());
                 FLOGF(proc_reap_external, "External process '%ls' (pid %d, %s)", proc->argv0(),
@@ -698,51 +763,13 @@ bool job_reap(parser_t &parser, bool allow_interactive) {
     return printed;
 }
 
-/// Get the CPU time for the specified process.
-unsigned long proc_get_jiffies(process_t *p) {
-    if (!have_proc_stat()) return 0;
-    if (p->pid <= 0) return 0;
-
-    char state;
-    int pid, ppid, pgrp, session, tty_nr, tpgid, exit_signal, processor;
-    long int cutime, cstime, priority, nice, placeholder, itrealvalue, rss;
-    unsigned long int flags, minflt, cminflt, majflt, cmajflt, utime, stime, starttime, vsize, rlim,
-        startcode, endcode, startstack, kstkesp, kstkeip, signal, blocked, sigignore, sigcatch,
-        wchan, nswap, cnswap;
-    char comm[1024];
-
-    /// Maximum length of a /proc/[PID]/stat filename.
-    constexpr size_t FN_SIZE = 256;
-    char fn[FN_SIZE];
-    std::snprintf(fn, FN_SIZE, "/proc/%d/stat", p->pid);
-    // Don't use autoclose_fd here, we will fdopen() and then fclose() instead.
-    int fd = open_cloexec(fn, O_RDONLY);
-    if (fd < 0) return 0;
-
-    // TODO: replace the use of fscanf() as it is brittle and should never be used.
-    FILE *f = fdopen(fd, "r");
-    int count = fscanf(f,
-                       "%9d %1023s %c %9d %9d %9d %9d %9d %9lu "
-                       "%9lu %9lu %9lu %9lu %9lu %9lu %9ld %9ld %9ld "
-                       "%9ld %9ld %9ld %9lu %9lu %9ld %9lu %9lu %9lu "
-                       "%9lu %9lu %9lu %9lu %9lu %9lu %9lu %9lu %9lu "
-                       "%9lu %9d %9d ",
-                       &pid, comm, &state, &ppid, &pgrp, &session, &tty_nr, &tpgid, &flags, &minflt,
-                       &cminflt, &majflt, &cmajflt, &utime, &stime, &cutime, &cstime, &priority,
-                       &nice, &placeholder, &itrealvalue, &starttime, &vsize, &rss, &rlim,
-                       &startcode, &endcode, &startstack, &kstkesp, &kstkeip, &signal, &blocked,
-                       &sigignore, &sigcatch, &wchan, &nswap, &cnswap, &exit_signal, &processor);
-    fclose(f);
-    if (count < 17) return 0;
-    return utime + stime + cutime + cstime;
-}
-
 /// Update the CPU time for all jobs.
-void proc_update_jiffies(parser_t &parser) {
+void proc_update_cpu_times(parser_t &parser) {
     for (const auto &job : parser.jobs()) {
         for (process_ptr_t &p : job->processes) {
             gettimeofday(&p->last_time, nullptr);
-            p->last_jiffies = proc_get_jiffies(p.get());
+            maybe_t<proc_info_t> info = p->pid > 0 ? proc_info_get(p->pid) : none();
+            p->last_cpu_micros = info && info->cpu_micros ? *info->cpu_micros : 0;
         }
     }
 }
@@ -985,7 +1012,13 @@ void job_t::continue_job(parser_t &parser, bool in_foreground) {
 
         if (in_foreground) {
             // Wait for the status of our own job to change.
+            // Commands run by the timeout builtin are signalled once it expires.
+            const auto &timeouts = parser.libdata().timeouts;
+            std::vector<int> timeout_stages(timeouts.size(), 0);
             while (!check_cancel_from_fish_signal() && !is_stopped() && !is_completed()) {
+                for (size_t i = 0; i < timeouts.size(); i++) {
+                    timeouts[i]->enforce(this, &timeout_stages[i]);
+                }
                 process_mark_finished_children(parser, true);
             }
         }
@@ -11,8 +11,11 @@
 #include <sys/wait.h>
 #include <unistd.h>
 
+#include <chrono>
+#include <condition_variable>
 #include <deque>
 #include <memory>
+#include <mutex>
 #include <vector>
 
 #include "common.h"
@@ -46,6 +49,7 @@ namespace ast {
 struct statement_t;
 }
 
+class job_cgroup_t;
 class job_group_t;
 using job_group_ref_t = std::shared_ptr<job_group_t>;
 
@@ -293,8 +297,18 @@ class process_t {
     /// Last time of cpu time check.
     struct timeval last_time {};
 
-    /// Number of jiffies spent in process at last cpu time check.
-    unsigned long last_jiffies{0};
+    /// CPU time in microseconds spent in process at last cpu time check.
+    uint64_t last_cpu_micros{0};
+
+    /// Timing of this process for $CMD_DURATIONS and the per-stage report of 'time'. The CPU times
+    /// are only recorded if the job wants timing; an unset end means the process did not finish.
+    struct timing_t {
+        std::chrono::steady_clock::time_point start{};
+        std::chrono::steady_clock::time_point end{};
+        int64_t usr_micros{0};
+        int64_t sys_micros{0};
+    };
+    timing_t timing{};
 };
 
 typedef std::unique_ptr<process_t> process_ptr_t;
@@ -330,6 +344,10 @@ class job_t {
 
         /// Whether the job is under job control, i.e. has its own pgrp.
         bool job_control{};
+
+        /// Whether the job runs the command of a process substitution like <(cmd), alongside the
+        /// job it feeds. It is not waited for.
+        bool is_procsub{};
     };
 
    private:
@@ -389,6 +407,10 @@ class job_t {
     // This is never null and not changed after construction.
     job_group_ref_t group{};
 
+    /// The cgroup the processes of this job are put in to account for their resources, if
+    /// $fish_job_cgroups is set and cgroups are available.
+    std::shared_ptr<job_cgroup_t> cgroup{};
+
     /// \return the pgid for the job, based on the job group.
     /// This may be none if the job consists of just internal fish functions or builtins.
     /// This may also be fish itself.
@@ -419,6 +441,10 @@ class job_t {
         // Indicates that we are the "group root." Any other jobs using this tree are nested.
         bool is_group_root{false};
 
+        /// Whether the job was signalled because a timeout expired, so its processes being killed
+        /// by SIGINT does not mean the user wants to cancel.
+        bool timed_out{false};
+
     } job_flags{};
 
     /// Access the job flags.
@@ -437,6 +463,9 @@ class job_t {
     /// specified.
     bool is_initially_background() const { return properties.initial_background; }
 
+    /// \return whether this job runs the command of a process substitution.
+    bool is_procsub() const { return properties.is_procsub; }
+
     /// Mark this job as constructed. The job must not have previously been marked as constructed.
     void mark_constructed();
 
@@ -455,7 +484,8 @@ class job_t {
     bool is_stopped() const;
     /// The job is OK to be externally visible, e.g. to the user via `jobs`
     bool is_visible() const {
-        return !is_completed() && is_constructed() && !flags().disown_requested;
+        return !is_completed() && is_constructed() && !flags().disown_requested &&
+               !is_procsub();
     }
     bool skip_notification() const { return properties.skip_notification; }
     bool from_event_handler() const { return properties.from_event_handler; }
@@ -484,6 +514,52 @@ class job_t {
     maybe_t<statuses_t> get_statuses() const;
 };
 
+/// A limit on how long the external processes run by a command may take, as set by the timeout
+/// builtin. A thread waits for it to expire and then wakes the shell, which signals the foreground
+/// jobs it waits for until the command finishes.
+class job_timeout_t {
+   public:
+    using duration_t = std::chrono::duration<double>;
+
+    /// Start the timer. When \p timeout expires, jobs are sent \p signal, and if \p kill_after is
+    /// nonzero and they are still running that much later, SIGKILL.
+    static std::shared_ptr<job_timeout_t> start(duration_t timeout, int signal,
+                                                 duration_t kill_after);
+
+    /// Stop the timer, as the command has finished.
+    void cancel();
+
+    /// \return whether the timeout has expired.
+    bool expired() const { return expired_; }
+
+    /// Send \p job the signals which are due. \p stage tracks which were already sent to it, and
+    /// starts at 0.
+    void enforce(job_t *job, int *stage) const;
+
+    job_timeout_t(int signal) : signal_(signal) {}
+
+   private:
+    /// Wait until \p deadline, or until cancelled. \return false if cancelled.
+    bool wait_until(std::chrono::steady_clock::time_point deadline);
+
+    const int signal_;
+    std::mutex lock_;
+    std::condition_variable cond_;
+    bool cancelled_{false};
+    relaxed_atomic_bool_t expired_{false};
+    relaxed_atomic_bool_t kill_expired_{false};
+};
+
+/// The scheduling priority for the external commands run by `command --nice` and `--ionice`,
+/// applied in the child before it executes the command.
+struct spawn_priority_t {
+    /// The amount to add to the niceness, as with nice(1).
+    int nice{0};
+
+    /// The I/O scheduling class and level in the format of ioprio_set(2), or -1 to keep them.
+    int ioprio{-1};
+};
+
 /// Whether this shell is attached to a tty.
 bool is_interactive_session();
 void set_interactive_session(bool flag);
@@ -521,13 +597,8 @@ job_list_t jobs_requiring_warning_on_exit(const parser_t &parser);
 /// jobs_requiring_warning_on_exit().
 void print_exit_warning_for_jobs(const job_list_t &jobs);
 
-/// Use the procfs filesystem to look up how many jiffies of cpu time was used by this process. This
-/// function is only available on systems with the procfs file entry 'stat', i.e. Linux.
-unsigned long proc_get_jiffies(process_t *p);
-
-/// Update process time usage for all processes by calling the proc_get_jiffies function for every
-/// process of every job.
-void proc_update_jiffies(parser_t &parser);
+/// Update process time usage for every process of every job, for the CPU usage shown by `jobs`.
+void proc_update_cpu_times(parser_t &parser);
 
 /// Perform a set of simple sanity checks on the job list. This includes making sure that only one
 /// job is in the foreground, that every process is in a valid state, etc.
@@ -564,6 +635,4 @@ int terminal_maybe_give_to_job_group(const job_group_t *jg, bool continuing_from
 /// jobs. Used to avoid zombie processes after disown.
 void add_disowned_job(job_t *j);
 
-bool have_proc_stat();
-
 #endif
@@ -0,0 +1,97 @@
+// Information about running processes, from the facilities the platform has for it.
+#include "config.h"  // IWYU pragma: keep
+
+#include "proc_info.h"
+
+#include <fcntl.h>
+#include <limits.h>
+#include <unistd.h>
+
+#include <cstdlib>
+#include <string>
+#include <vector>
+
+#if defined(__APPLE__) && defined(HAVE_LIBPROC_H)
+#include <libproc.h>
+#include <mach/mach_time.h>
+#endif
+
+#include "wcstringutil.h"
+#include "wutil.h"  // IWYU pragma: keep
+
+#if defined(__APPLE__) && defined(HAVE_LIBPROC_H)
+
+bool proc_info_available() { return true; }
+
+maybe_t<proc_info_t> proc_info_get(pid_t pid) {
+    struct proc_taskinfo task;
+    if (proc_pidinfo(pid, PROC_PIDTASKINFO, 0, &task, sizeof task) != sizeof task) return none();
+
+    proc_info_t result;
+    // The task times are in Mach absolute time units, which are not nanoseconds on ARM.
+    static const mach_timebase_info_data_t timebase = [] {
+        mach_timebase_info_data_t info;
+        mach_timebase_info(&info);
+        return info;
+    }();
+    uint64_t ticks = task.pti_total_user + task.pti_total_system;
+    result.cpu_micros = ticks * timebase.numer / timebase.denom / 1000;
+    result.rss_bytes = task.pti_resident_size;
+
+    char name[2 * MAXCOMLEN + 1];
+    if (proc_name(pid, name, sizeof name) > 0) result.name = str2wcstring(name);
+
+    struct proc_vnodepathinfo paths;
+    if (proc_pidinfo(pid, PROC_PIDVNODEPATHINFO, 0, &paths, sizeof paths) == sizeof paths) {
+        result.cwd = str2wcstring(paths.pvi_cdir.vip_path);
+    }
+    return result;
+}
+
+#else
+
+bool proc_info_available() {
+    // Check for /proc/self/stat to see if we are running with Linux-style procfs.
+    static const bool s_result = (access("/proc/self/stat", R_OK) == 0);
+    return s_result;
+}
+
+maybe_t<proc_info_t> proc_info_get(pid_t pid) {
+    if (!proc_info_available() || pid <= 0) return none();
+    const std::string dir = "/proc/" + std::to_string(pid);
+    maybe_t<std::string> stat = read_file_contents(dir + "/stat");
+    if (!stat) return none();
+
+    // The fields are separated by spaces, but the second one is the name in parentheses, which may
+    // contain any character itself. The state is the first field after it.
+    proc_info_t result;
+    size_t open = stat->find('(');
+    size_t close = stat->rfind(')');
+    if (open == std::string::npos || close == std::string::npos || close < open) return none();
+    result.name = str2wcstring(stat->substr(open + 1, close - open - 1));
+    std::vector<unsigned long long> fields;
+    const char *cursor = stat->c_str() + close + 1;
+    while (*cursor == ' ') cursor++;
+    cursor++;  // Skip the state.
+    while (*cursor) {
+        char *end;
+        fields.push_back(std::strtoull(cursor, &end, 10));
+        if (end == cursor) break;
+        cursor = end;
+    }
+    // These are utime, stime, cutime and cstime, in clock ticks, and the resident pages.
+    if (fields.size() >= 21) {
+        static const long ticks_per_second = sysconf(_SC_CLK_TCK);
+        static const long page_size = sysconf(_SC_PAGESIZE);
+        unsigned long long ticks = fields[10] + fields[11] + fields[12] + fields[13];
+        if (ticks_per_second > 0) result.cpu_micros = ticks * 1000000 / ticks_per_second;
+        if (page_size > 0) result.rss_bytes = fields[20] * page_size;
+    }
+
+    char cwd[PATH_MAX];
+    ssize_t len = readlink((dir + "/cwd").c_str(), cwd, sizeof cwd - 1);
+    if (len > 0) result.cwd = str2wcstring(cwd, len);
+    return result;
+}
+
+#endif
@@ -0,0 +1,34 @@
+// Information about running processes, from the facilities the platform has for it.
+#ifndef FISH_PROC_INFO_H
+#define FISH_PROC_INFO_H
+
+#include <stdint.h>
+#include <sys/types.h>
+
+#include "common.h"
+#include "maybe.h"
+
+/// What is known about a process. Linux reads it from /proc, macOS asks libproc.
+struct proc_info_t {
+    /// The name of the program the process runs, which may differ from the command that was
+    /// started, like for scripts. Empty if unknown.
+    wcstring name;
+
+    /// The working directory of the process, or empty if unknown.
+    wcstring cwd;
+
+    /// The CPU time the process and its waited-for children used so far, in microseconds.
+    maybe_t<uint64_t> cpu_micros;
+
+    /// The resident memory of the process, in bytes.
+    maybe_t<uint64_t> rss_bytes;
+};
+
+/// \return whether processes can be asked about on this platform.
+bool proc_info_available();
+
+/// \return what is known about the process \p pid, or none if it does not exist or can't be asked
+/// about.
+maybe_t<proc_info_t> proc_info_get(pid_t pid);
+
+#endif
@@ -36,7 +36,10 @@
 #include <wctype.h>
 
 #include <algorithm>
+#include <limits>
 #include <atomic>
+#include <chrono>
+#include <condition_variable>
 #include <csignal>
 #include <cwchar>
 #include <functional>
@@ -45,10 +48,13 @@
 #include <stack>
 
 #include "ast.h"
+#include "ast_cache.h"
 #include "color.h"
 #include "common.h"
 #include "complete.h"
+#include "dir_env.h"
 #include "env.h"
+#include "env_dispatch.h"
 #include "event.h"
 #include "exec.h"
 #include "expand.h"
@@ -70,18 +76,25 @@
 #include "parse_util.h"
 #include "parser.h"
 #include "proc.h"
+#include "proc_info.h"
 #include "reader.h"
 #include "sanity.h"
 #include "screen.h"
 #include "signal.h"
 #include "termsize.h"
 #include "tokenizer.h"
+#include "wcstringutil.h"
+#include "wildcard.h"
 #include "wutil.h"  // IWYU pragma: keep
 
 // Name of the variable that tells how long it took, in milliseconds, for the previous
 // interactive command to complete.
 #define ENV_CMD_DURATION L"CMD_DURATION"
 
+// Name of the variable with the duration of each process of the last job of the previous
+// interactive command, like $pipestatus.
+#define ENV_CMD_DURATIONS L"CMD_DURATIONS"
+
 /// Maximum length of prefix string when printing completion list. Longer prefixes will be
 /// ellipsized.
 #define PREFIX_MAX_LEN 9
@@ -120,24 +133,19 @@ enum class history_search_direction_t { forward, backward };
 enum class jump_direction_t { forward, backward };
 enum class jump_precision_t { till, to };
 
-/// Any time the contents of a buffer changes, we update the generation count. This allows for our
-/// background threads to notice it and skip doing work that they would otherwise have to do.
-static std::atomic<uint32_t> s_generation;
-
-/// Helper to get the generation count
-static inline uint32_t read_generation_count() {
-    return s_generation.load(std::memory_order_relaxed);
+/// The scope of background work for the contents of the command line, like autosuggestions and
+/// highlighting. Any time the contents change, its tasks are cancelled, so our background threads
+/// skip doing work that they would otherwise have to do. Deliberately leaked.
+static task_scope_t &command_line_scope() {
+    static auto res = new task_scope_t();
+    return *res;
 }
 
 /// \return an operation context for a background operation..
 /// Crucially the operation context itself does not contain a parser.
 /// It is the caller's responsibility to ensure the environment lives as long as the result.
 static operation_context_t get_bg_context(const std::shared_ptr<environment_t> &env,
-                                          uint32_t generation_count) {
-    cancel_checker_t cancel_checker = [generation_count] {
-        // Cancel if the generation count changed.
-        return generation_count != read_generation_count();
-    };
+                                          cancel_checker_t cancel_checker) {
     return operation_context_t{nullptr, *env, std::move(cancel_checker), kExpansionLimitBackground};
 }
 
@@ -177,6 +185,91 @@ void apply_edit(wcstring *target, const edit_t &edit) {
     target->replace(edit.offset, edit.length, edit.replacement);
 }
 
+wcstring parse_snippet(const wcstring &snippet,
+                       std::vector<snippet_placeholder_t> *out_placeholders) {
+    // The placeholders with their numbers, in the order they appear.
+    using numbered_placeholder_t = std::pair<unsigned long, snippet_placeholder_t>;
+    std::vector<numbered_placeholder_t> numbered;
+    wcstring result;
+    // Add a placeholder at the end of the result.
+    auto add_placeholder = [&](unsigned long number, size_t length) {
+        snippet_placeholder_t placeholder;
+        placeholder.start = result.size();
+        placeholder.length = length;
+        numbered.emplace_back(number, placeholder);
+    };
+    const size_t len = snippet.size();
+    size_t i = 0;
+    while (i < len) {
+        wchar_t c = snippet.at(i);
+        // A backslash escapes a dollar sign or another backslash.
+        if (c == L'\\' && i + 1 < len &&
+            (snippet.at(i + 1) == L'$' || snippet.at(i + 1) == L'\\')) {
+            result.push_back(snippet.at(i + 1));
+            i += 2;
+            continue;
+        }
+        if (c == L'$') {
+            // A placeholder is $N, ${N} or ${N:default}. Anything else is literal text.
+            size_t j = i + 1;
+            bool braced = j < len && snippet.at(j) == L'{';
+            if (braced) j++;
+            size_t digits_start = j;
+            unsigned long number = 0;
+            while (j < len && snippet.at(j) >= L'0' && snippet.at(j) <= L'9') {
+                if (number < 1000000) number = number * 10 + (snippet.at(j) - L'0');
+                j++;
+            }
+            if (j > digits_start && !braced) {
+                add_placeholder(number, 0);
+                i = j;
+                continue;
+            }
+            if (j > digits_start && j < len) {
+                wcstring default_text;
+                bool closed = snippet.at(j) == L'}';
+                if (snippet.at(j) == L':') {
+                    for (j++; j < len; j++) {
+                        wchar_t d = snippet.at(j);
+                        if (d == L'\\' && j + 1 < len &&
+                            (snippet.at(j + 1) == L'}' || snippet.at(j + 1) == L'$' ||
+                             snippet.at(j + 1) == L'\\')) {
+                            default_text.push_back(snippet.at(++j));
+                        } else if (d == L'}') {
+                            closed = true;
+                            break;
+                        } else {
+                            default_text.push_back(d);
+                        }
+                    }
+                }
+                if (closed) {
+                    add_placeholder(number, default_text.size());
+                    result.append(default_text);
+                    i = j + 1;
+                    continue;
+                }
+            }
+        }
+        result.push_back(c);
+        i++;
+    }
+
+    // Placeholder 0 is the final position, so it comes last.
+    auto order = [](unsigned long number) {
+        return number == 0 ? std::numeric_limits<unsigned long>::max() : number;
+    };
+    std::stable_sort(numbered.begin(), numbered.end(),
+                     [&](const numbered_placeholder_t &a, const numbered_placeholder_t &b) {
+                         return order(a.first) < order(b.first);
+                     });
+    out_placeholders->clear();
+    for (const auto &placeholder : numbered) {
+        out_placeholders->push_back(placeholder.second);
+    }
+    return result;
+}
+
 /// Returns the number of characters left of the cursor that are removed by the
 /// deletion in the given edit.
 static size_t chars_deleted_left_of_cursor(const edit_t &edit) {
@@ -484,10 +577,15 @@ struct autosuggestion_t {
     // This is true for file-generated autosuggestions, but not for history.
     bool icase{false};
 
+    // A description of the argument expected at the end of the command line, shown if there is no
+    // text. Unlike the text, this is never inserted into the command line.
+    wcstring hint{};
+
     // Clear our contents.
     void clear() {
         text.clear();
         search_string.clear();
+        hint.clear();
     }
 
     // \return whether we have empty text.
@@ -544,6 +642,9 @@ struct layout_data_t {
     /// String containing the autosuggestion.
     wcstring autosuggestion{};
 
+    /// String containing the hint for the expected argument, if there is no autosuggestion.
+    wcstring argument_hint{};
+
     /// String containing the history search. If non-empty, then highlight the found range within
     /// the text.
     wcstring history_search_text{};
@@ -553,6 +654,12 @@ struct layout_data_t {
     wcstring left_prompt_buff{};
     wcstring mode_prompt_buff{};
     wcstring right_prompt_buff{};
+
+    /// The message shown below the command line.
+    wcstring message{};
+
+    /// The placeholders of the snippet being filled in.
+    std::vector<snippet_placeholder_t> snippet_placeholders{};
 };
 
 /// A struct describing the state of the interactive reader. These states can be stacked, in case
@@ -598,6 +705,16 @@ class reader_data_t : public std::enable_shared_from_this<reader_data_t> {
     /// The output of the last evaluation of the right prompt command.
     wcstring right_prompt_buff;
 
+    /// A message shown below the command line until the next key is pressed, set with `commandline
+    /// --message`.
+    wcstring message;
+
+    /// The placeholders of a snippet inserted with `commandline --insert-snippet` which have not
+    /// been left with tab yet. The first is the one being filled in.
+    std::vector<snippet_placeholder_t> snippet_placeholders;
+    /// Whether the current placeholder still has its default text, which typing replaces.
+    bool snippet_placeholder_pristine{false};
+
     /// When navigating the pager, we modify the command line.
     /// This is the saved command line before modification.
     wcstring cycle_command_line;
@@ -615,6 +732,9 @@ class reader_data_t : public std::enable_shared_from_this<reader_data_t> {
     /// This may come about when a color like $fish_color... has changed.
     bool force_exec_prompt_and_repaint{false};
 
+    /// A flag which is set when only the right prompt needs to be re-execed.
+    bool force_exec_right_prompt{false};
+
     /// The target character of the last jump command.
     wchar_t last_jump_target{0};
     jump_direction_t last_jump_direction{jump_direction_t::forward};
@@ -690,6 +810,10 @@ class reader_data_t : public std::enable_shared_from_this<reader_data_t> {
     void update_buff_pos(editable_line_t *el, maybe_t<size_t> new_pos = none_t());
 
     void kill(editable_line_t *el, size_t begin_idx, size_t length, int mode, int newv);
+    /// Share a kill with other sessions, if the shared killring is enabled.
+    void share_kill(const wcstring &old, const wcstring &newv);
+    /// Pick up entries other sessions have added to the shared killring.
+    void merge_shared_kills();
     /// Inserts a substring of str given by start, len at the cursor position.
     void insert_string(editable_line_t *el, const wcstring &str);
     /// Erase @length characters starting at @offset.
@@ -698,6 +822,14 @@ class reader_data_t : public std::enable_shared_from_this<reader_data_t> {
     void replace_substring(editable_line_t *el, size_t offset, size_t length, wcstring replacement);
     void push_edit(editable_line_t *el, edit_t &&edit);
 
+    /// Adjust the snippet placeholders for an edit of the command line, which is about to be
+    /// applied. If the edit changes the bounds of a placeholder, forget the snippet.
+    void update_snippet_for_edit(const editable_line_t *el, const edit_t &edit);
+    /// Insert a snippet at the cursor, and go to its first placeholder.
+    void insert_snippet(const wcstring &snippet);
+    /// Go to the next placeholder of the snippet, or past the last one.
+    void next_snippet_placeholder();
+
     /// Insert the character into the command line buffer and print it to the screen using syntax
     /// highlighting, etc.
     void insert_char(editable_line_t *el, wchar_t c) { insert_string(el, wcstring{c}); }
@@ -731,7 +863,9 @@ class reader_data_t : public std::enable_shared_from_this<reader_data_t> {
 
     void highlight_complete(highlight_result_t result);
     void exec_mode_prompt();
+    void exec_right_prompt();
     void exec_prompt();
+    void exec_prompt_with_deadline();
 
     bool jump(jump_direction_t dir, jump_precision_t precision, editable_line_t *el,
               wchar_t target);
@@ -843,6 +977,8 @@ static void redirect_tty_after_sighup() {
 /// Give up control of terminal.
 static void term_donate(outputter_t &outp) {
     outp.set_color(rgb_color_t::normal(), rgb_color_t::normal());
+    // The command must not write to the terminal before everything we have written.
+    outp.flush_all();
 
     while (true) {
         if (tcsetattr(STDIN_FILENO, TCSANOW, &tty_modes_for_external_cmds) == -1) {
@@ -970,9 +1106,12 @@ bool reader_data_t::is_repaint_needed(const std::vector<highlight_spec_t> *mcolo
            check(command_line.position() != last.position, L"position") ||
            check(history_search_text_if_active() != last.history_search_text, L"history search") ||
            check(autosuggestion.text != last.autosuggestion, L"autosuggestion") ||
+           check(autosuggestion.hint != last.argument_hint, L"argument hint") ||
            check(left_prompt_buff != last.left_prompt_buff, L"left_prompt") ||
            check(mode_prompt_buff != last.mode_prompt_buff, L"mode_prompt") ||
            check(right_prompt_buff != last.right_prompt_buff, L"right_prompt") ||
+           check(message != last.message, L"message") ||
+           check(snippet_placeholders != last.snippet_placeholders, L"snippet") ||
            check(pager.rendering_needs_update(current_page_rendering), L"pager");
 }
 
@@ -992,9 +1131,12 @@ layout_data_t reader_data_t::make_layout_data(maybe_t<highlight_list_t> mcolors)
     result.focused_on_pager = (active_edit_line() == &pager.search_field_line);
     result.history_search_text = history_search_text_if_active();
     result.autosuggestion = autosuggestion.text;
+    result.argument_hint = autosuggestion.hint;
     result.left_prompt_buff = left_prompt_buff;
     result.mode_prompt_buff = mode_prompt_buff;
     result.right_prompt_buff = right_prompt_buff;
+    result.message = message;
+    result.snippet_placeholders = snippet_placeholders;
 
     // Ensure our color list has the same length as the command line, by extending it with the last
     // color. This typically reduces redraws; e.g. if the user continues types into an argument, we
@@ -1010,11 +1152,23 @@ void reader_data_t::paint_layout(const wchar_t *reason) {
     const editable_line_t *cmd_line = &command_line;
 
     wcstring full_line;
+    size_t explicit_len = cmd_line->size();
+    size_t cursor_pos = data.position;
     if (conf.in_silent_mode) {
-        full_line = wcstring(cmd_line->text().length(), get_obfuscation_read_char());
+        // Show a mask character for each character, or nothing at all.
+        if (conf.silent_mask.empty()) {
+            explicit_len = 0;
+            cursor_pos = 0;
+        } else {
+            full_line = wcstring(explicit_len, conf.silent_mask.front());
+        }
     } else {
         // Combine the command and autosuggestion into one string.
         full_line = combine_command_and_autosuggestion(cmd_line->text(), autosuggestion.text);
+        // Without an autosuggestion, show what argument is expected after the cursor.
+        if (autosuggestion.empty() && !data.argument_hint.empty() && cursor_pos == explicit_len) {
+            full_line.append(L"<" + data.argument_hint + L">");
+        }
     }
 
     // Copy the colors and extend them with autosuggestion color.
@@ -1032,6 +1186,14 @@ void reader_data_t::paint_layout(const wchar_t *reason) {
         }
     }
 
+    // Highlight the placeholders of a snippet.
+    for (const snippet_placeholder_t &placeholder : data.snippet_placeholders) {
+        size_t end = std::min(placeholder.start + placeholder.length, colors.size());
+        for (size_t i = placeholder.start; i < end; i++) {
+            colors.at(i).background = highlight_role_t::snippet_placeholder;
+        }
+    }
+
     // Apply any selection.
     if (data.selection.has_value()) {
         highlight_spec_t selection_color = {highlight_role_t::normal, highlight_role_t::selection};
@@ -1050,8 +1212,8 @@ void reader_data_t::paint_layout(const wchar_t *reason) {
 
     // Prepend the mode prompt to the left prompt.
     s_write(&screen, mode_prompt_buff + left_prompt_buff, right_prompt_buff, full_line,
-            cmd_line->size(), colors, indents, data.position, pager, current_page_rendering,
-            data.focused_on_pager);
+            explicit_len, colors, indents, cursor_pos, data.message, pager,
+            current_page_rendering, data.focused_on_pager);
 }
 
 /// Internal helper function for handling killing parts of text.
@@ -1060,6 +1222,7 @@ void reader_data_t::kill(editable_line_t *el, size_t begin_idx, size_t length, i
     if (newv) {
         kill_item = wcstring(begin, length);
         kill_add(kill_item);
+        share_kill({}, kill_item);
     } else {
         wcstring old = kill_item;
         if (mode == KILL_APPEND) {
@@ -1070,10 +1233,55 @@ void reader_data_t::kill(editable_line_t *el, size_t begin_idx, size_t length, i
         }
 
         kill_replace(old, kill_item);
+        share_kill(old, kill_item);
     }
     erase_substring(el, begin_idx, length);
 }
 
+/// The universal variable backing the shared killring, most recent entry first.
+static const wchar_t *const SHARED_KILLRING_VAR = L"__fish_shared_killring";
+
+/// \return the maximum number of entries in the shared killring, or 0 if it is not to be used.
+static size_t shared_killring_size(const environment_t &vars) {
+    auto enabled = vars.get(L"fish_shared_killring");
+    if (!enabled || !bool_from_string(enabled->as_string())) return 0;
+    // Text killed in a private session must stay there.
+    if (in_private_mode(vars)) return 0;
+
+    const size_t default_size = 10;
+    auto size_var = vars.get(L"fish_shared_killring_size");
+    if (!size_var) return default_size;
+    long size = fish_wcstol(size_var->as_string().c_str());
+    if (errno || size < 0) {
+        FLOGF(warning, _(L"Ignoring invalid $fish_shared_killring_size value '%ls'"),
+              size_var->as_string().c_str());
+        return default_size;
+    }
+    return static_cast<size_t>(size);
+}
+
+void reader_data_t::share_kill(const wcstring &old, const wcstring &newv) {
+    size_t max_size = shared_killring_size(vars());
+    if (max_size == 0) return;
+    // Let the user keep sensitive text out of the shared killring.
+    if (auto ignore = vars().get(L"fish_shared_killring_ignore")) {
+        for (const wcstring &pattern : ignore->as_list()) {
+            if (wildcard_match(newv, pattern)) return;
+        }
+    }
+
+    wcstring_list_t shared;
+    if (auto var = vars().get(SHARED_KILLRING_VAR, ENV_UNIVERSAL)) shared = var->as_list();
+    vars().set(SHARED_KILLRING_VAR, ENV_UNIVERSAL, kill_update_shared(shared, old, newv, max_size));
+}
+
+void reader_data_t::merge_shared_kills() {
+    if (shared_killring_size(vars()) == 0) return;
+    if (auto var = vars().get(SHARED_KILLRING_VAR, ENV_UNIVERSAL)) {
+        kill_merge_shared(var->as_list());
+    }
+}
+
 // This is called from a signal handler!
 void reader_handle_sigint() { interrupted = SIGINT; }
 
@@ -1081,14 +1289,28 @@ void reader_handle_sigint() { interrupted = SIGINT; }
 void reader_data_t::command_line_changed(const editable_line_t *el) {
     ASSERT_IS_MAIN_THREAD();
     if (el == &this->command_line) {
-        // Update the gen count.
-        s_generation.store(1 + read_generation_count(), std::memory_order_relaxed);
+        // Cancel the work for the old contents. Their results would not be used, so forget that
+        // they are in flight.
+        command_line_scope().cancel_all();
+        in_flight_autosuggest_request.clear();
+        in_flight_highlight_request.clear();
     } else if (el == &this->pager.search_field_line) {
         this->pager.refilter_completions();
+        // If we jump between matches, go to the next one unless the selection still matches.
+        if (!this->pager.selection_matches_search()) {
+            this->select_completion_in_direction(selection_motion_t::next);
+        }
         this->pager_selection_changed();
     }
 }
 
+/// \return whether the pager search should jump between matching completions instead of filtering
+/// them, as set with $fish_pager_search_mode.
+static bool pager_search_jumps_to_matches(const environment_t &vars) {
+    auto mode = vars.get(L"fish_pager_search_mode");
+    return mode && mode->as_string() == L"jump";
+}
+
 void reader_data_t::pager_selection_changed() {
     ASSERT_IS_MAIN_THREAD();
 
@@ -1251,6 +1473,23 @@ void reader_data_t::exec_mode_prompt() {
     }
 }
 
+/// Reexecute the right prompt command. The output is inserted into right_prompt_buff.
+void reader_data_t::exec_right_prompt() {
+    right_prompt_buff.clear();
+    force_exec_right_prompt = false;
+    if (!conf.right_prompt_cmd.empty() && function_exists(conf.right_prompt_cmd, parser())) {
+        scoped_push<bool> noninteractive{&parser().libdata().is_interactive, false};
+        scoped_push<bool> in_prompt(&parser().libdata().suppress_fish_trace, true);
+        // Status is ignored.
+        wcstring_list_t prompt_list;
+        exec_subshell(conf.right_prompt_cmd, parser(), prompt_list, false);
+        // Right prompt does not support multiple lines, so just concatenate all of them.
+        for (const auto &i : prompt_list) {
+            right_prompt_buff += i;
+        }
+    }
+}
+
 /// Reexecute the prompt command. The output is inserted into prompt_buff.
 void reader_data_t::exec_prompt() {
     // Clear existing prompts.
@@ -1260,6 +1499,9 @@ void reader_data_t::exec_prompt() {
     // Suppress fish_trace while in the prompt.
     scoped_push<bool> in_prompt(&parser().libdata().suppress_fish_trace, true);
 
+    // Apply any changes to the locale and terminal variables before drawing.
+    env_dispatch_flush(parser().vars());
+
     // Update the termsize now.
     // This allows prompts to react to $COLUMNS.
     update_termsize();
@@ -1283,17 +1525,7 @@ void reader_data_t::exec_prompt() {
             left_prompt_buff = join_strings(prompt_list, L'\n');
         }
 
-        if (!conf.right_prompt_cmd.empty()) {
-            if (function_exists(conf.right_prompt_cmd, parser())) {
-                // Status is ignored.
-                wcstring_list_t prompt_list;
-                exec_subshell(conf.right_prompt_cmd, parser(), prompt_list, false);
-                // Right prompt does not support multiple lines, so just concatenate all of them.
-                for (const auto &i : prompt_list) {
-                    right_prompt_buff += i;
-                }
-            }
-        }
+        exec_right_prompt();
     }
 
     // Write the screen title. Do not reset the cursor position: exec_prompt is called when there
@@ -1302,6 +1534,62 @@ void reader_data_t::exec_prompt() {
     reader_write_title(L"", parser(), false);
 }
 
+/// Execute the prompt like exec_prompt(). If that takes longer than $fish_prompt_timeout_ms, show
+/// the previous prompt, or a minimal one, in the meantime. Keys pressed while the real prompt is
+/// running are handled once it is done, and it then replaces the stand-in.
+void reader_data_t::exec_prompt_with_deadline() {
+    long timeout_ms = 0;
+    auto timeout_var = vars().get(L"fish_prompt_timeout_ms");
+    if (!timeout_var.missing_or_empty()) {
+        timeout_ms = fish_wcstol(timeout_var->as_string().c_str());
+        if (errno) timeout_ms = 0;
+    }
+    if (timeout_ms <= 0 || conf.in != STDIN_FILENO || !isatty(STDOUT_FILENO)) {
+        exec_prompt();
+        return;
+    }
+
+    // Truncate the stand-in like the screen would, so it knows where the cursor ends up.
+    wcstring stand_in;
+    const wcstring last_prompt = mode_prompt_buff + left_prompt_buff;
+    layout_cache_t::shared.calc_prompt_layout(last_prompt.empty() ? L"> " : last_prompt,
+                                              &stand_in, termsize_last().width);
+    const std::string stand_in_narrow = "\r" + wcs2string(stand_in);
+
+    struct deadline_t {
+        std::mutex lock;
+        std::condition_variable cond;
+        bool done{false};
+        bool shown{false};
+    };
+    auto deadline = std::make_shared<deadline_t>();
+    iothread_perform_cantwait([=] {
+        std::unique_lock<std::mutex> locker(deadline->lock);
+        if (deadline->cond.wait_for(locker, std::chrono::milliseconds(timeout_ms),
+                                    [&] { return deadline->done; })) {
+            return;
+        }
+        // Write while holding the lock, so the stand-in is either all there or not at all once the
+        // prompt is done.
+        write_loop(STDOUT_FILENO, stand_in_narrow.data(), stand_in_narrow.size());
+        deadline->shown = true;
+    });
+
+    exec_prompt();
+
+    bool shown;
+    {
+        scoped_lock locker(deadline->lock);
+        deadline->done = true;
+        shown = deadline->shown;
+    }
+    deadline->cond.notify_one();
+    if (shown) {
+        FLOGF(reader_render, L"Prompt took longer than %ld ms, showed the last one", timeout_ms);
+        s_note_prompt_written(&screen, stand_in);
+    }
+}
+
 void reader_init() {
     parser_t &parser = parser_t::principal_parser();
     auto &vars = parser.vars();
@@ -1310,6 +1598,7 @@ void reader_init() {
     // in a function like `fish_prompt` or `fish_right_prompt` it is defined at the time the first
     // prompt is written.
     vars.set_one(ENV_CMD_DURATION, ENV_UNEXPORT, L"0");
+    vars.set_one(ENV_CMD_DURATIONS, ENV_UNEXPORT, L"0");
 
     // Save the initial terminal mode.
     tcgetattr(STDIN_FILENO, &terminal_mode_on_startup);
@@ -1367,6 +1656,7 @@ static bool command_ends_paging(readline_cmd_t c, bool focused_on_search_field)
         case rl::up_line:
         case rl::down_line:
         case rl::repaint:
+        case rl::repaint_right_prompt:
         case rl::suppress_autosuggestion:
         case rl::beginning_of_history:
         case rl::end_of_history: {
@@ -1426,6 +1716,7 @@ static bool command_ends_history_search(readline_cmd_t c) {
         case readline_cmd_t::beginning_of_history:
         case readline_cmd_t::end_of_history:
         case readline_cmd_t::repaint:
+        case readline_cmd_t::repaint_right_prompt:
         case readline_cmd_t::force_repaint:
             return false;
         default:
@@ -1465,6 +1756,13 @@ void reader_data_t::insert_string(editable_line_t *el, const wcstring &str) {
     if (str.empty()) return;
 
     command_line_has_transient_edit = false;
+    if (el == &command_line && snippet_placeholder_pristine &&
+        !snippet_placeholders.empty() && el->position() == snippet_placeholders.front().start) {
+        // Typing into a placeholder replaces its default text.
+        const snippet_placeholder_t &placeholder = snippet_placeholders.front();
+        erase_substring(el, placeholder.start, placeholder.length);
+    }
+    update_snippet_for_edit(el, edit_t(el->position(), 0, str));
     if (!history_search.active() && want_to_coalesce_insertion_of(*el, str)) {
         el->insert_coalesce(str);
         assert(el->undo_history.may_coalesce);
@@ -1481,6 +1779,7 @@ void reader_data_t::insert_string(editable_line_t *el, const wcstring &str) {
 }
 
 void reader_data_t::push_edit(editable_line_t *el, edit_t &&edit) {
+    update_snippet_for_edit(el, edit);
     el->push_edit(std::move(edit));
     el->undo_history.may_coalesce = false;
     // The pager needs to be refiltered.
@@ -1489,6 +1788,72 @@ void reader_data_t::push_edit(editable_line_t *el, edit_t &&edit) {
     }
 }
 
+void reader_data_t::update_snippet_for_edit(const editable_line_t *el, const edit_t &edit) {
+    if (el != &command_line || snippet_placeholders.empty()) return;
+    snippet_placeholder_pristine = false;
+
+    size_t edit_end = edit.offset + edit.length;
+    bool inserting = edit.length == 0;
+    for (size_t i = 0; i < snippet_placeholders.size(); i++) {
+        snippet_placeholder_t &placeholder = snippet_placeholders.at(i);
+        size_t placeholder_end = placeholder.start + placeholder.length;
+        // Text inserted at the edge of a placeholder belongs to it only if it is being filled in.
+        bool is_current = i == 0;
+        if (edit_end < placeholder.start ||
+            (edit_end == placeholder.start && !(inserting && is_current))) {
+            // The edit is before the placeholder.
+            placeholder.start = placeholder.start - edit.length + edit.replacement.size();
+        } else if (edit.offset > placeholder_end ||
+                   (edit.offset == placeholder_end && !(inserting && is_current))) {
+            // The edit is after the placeholder.
+        } else if (edit.offset >= placeholder.start && edit_end <= placeholder_end) {
+            // The edit is within the placeholder.
+            placeholder.length = placeholder.length - edit.length + edit.replacement.size();
+        } else {
+            // The edit crosses the bounds of the placeholder.
+            snippet_placeholders.clear();
+            return;
+        }
+    }
+}
+
+void reader_data_t::insert_snippet(const wcstring &snippet) {
+    std::vector<snippet_placeholder_t> placeholders;
+    wcstring text = parse_snippet(snippet, &placeholders);
+    editable_line_t *el = &command_line;
+    size_t position = el->position();
+
+    // A new snippet replaces any old one.
+    snippet_placeholders.clear();
+    pager.clear();
+    push_edit(el, edit_t(position, 0, text));
+    command_line_changed(el);
+
+    for (snippet_placeholder_t &placeholder : placeholders) {
+        placeholder.start += position;
+    }
+    snippet_placeholders = std::move(placeholders);
+    snippet_placeholder_pristine = true;
+    if (snippet_placeholders.empty()) {
+        update_buff_pos(el, position + text.size());
+    } else {
+        update_buff_pos(el, snippet_placeholders.front().start);
+    }
+}
+
+void reader_data_t::next_snippet_placeholder() {
+    assert(!snippet_placeholders.empty() && "No snippet placeholder");
+    snippet_placeholder_t left = snippet_placeholders.front();
+    snippet_placeholders.erase(snippet_placeholders.begin());
+    if (snippet_placeholders.empty()) {
+        // We are done with the snippet, so go to the end of its last placeholder.
+        update_buff_pos(&command_line, left.start + left.length);
+    } else {
+        snippet_placeholder_pristine = true;
+        update_buff_pos(&command_line, snippet_placeholders.front().start);
+    }
+}
+
 void reader_data_t::erase_substring(editable_line_t *el, size_t offset, size_t length) {
     push_edit(el, edit_t(offset, length, L""));
 }
@@ -1636,7 +2001,7 @@ void reader_data_t::completion_insert(const wcstring &val, size_t token_end,
 static std::function<autosuggestion_t(void)> get_autosuggestion_performer(
     parser_t &parser, const wcstring &search_string, size_t cursor_pos,
     const std::shared_ptr<history_t> &history) {
-    const uint32_t generation_count = read_generation_count();
+    cancel_checker_t cancel_checker = command_line_scope().cancel_checker();
     auto vars = parser.vars().snapshot();
     const wcstring working_directory = vars->get_pwd_slash();
     // TODO: suspicious use of 'history' here
@@ -1645,7 +2010,7 @@ static std::function<autosuggestion_t(void)> get_autosuggestion_performer(
     return [=]() -> autosuggestion_t {
         ASSERT_IS_BACKGROUND_THREAD();
         autosuggestion_t nothing = {};
-        operation_context_t ctx = get_bg_context(vars, generation_count);
+        operation_context_t ctx = get_bg_context(vars, cancel_checker);
         if (ctx.check_cancel()) {
             return nothing;
         }
@@ -1698,7 +2063,11 @@ static std::function<autosuggestion_t(void)> get_autosuggestion_performer(
             return autosuggestion_t{std::move(suggestion), search_string, true /* icase */};
         }
 
-        return nothing;
+        // Without a suggestion, hint at the argument that is expected next, if the completions know.
+        if (!cursor_at_end || ctx.check_cancel()) return nothing;
+        autosuggestion_t result{wcstring{}, search_string, false /* icase */};
+        result.hint = complete_get_argument_hint(search_string, ctx);
+        return result;
     };
 }
 
@@ -1716,8 +2085,11 @@ void reader_data_t::autosuggest_completed(autosuggestion_t result) {
     ASSERT_IS_MAIN_THREAD();
     if (result.search_string == in_flight_autosuggest_request)
         in_flight_autosuggest_request.clear();
-    if (!result.empty() && can_autosuggest() && result.search_string == command_line.text() &&
-        string_prefixes_string_case_insensitive(result.search_string, result.text)) {
+    // A result without text may still have an argument hint.
+    bool usable = result.empty() ? !result.hint.empty()
+                                 : string_prefixes_string_case_insensitive(result.search_string,
+                                                                           result.text);
+    if (usable && can_autosuggest() && result.search_string == command_line.text()) {
         // Autosuggestion is active and the search term has not changed, so we're good to go.
         autosuggestion = std::move(result);
         if (this->is_repaint_needed()) {
@@ -1756,9 +2128,12 @@ void reader_data_t::update_autosuggestion() {
     autosuggestion.clear();
     auto performer = get_autosuggestion_performer(parser(), el.text(), el.position(), history);
     auto shared_this = this->shared_from_this();
-    debounce_autosuggestions().perform(performer, [shared_this](autosuggestion_t result) {
-        shared_this->autosuggest_completed(std::move(result));
-    });
+    command_line_scope().spawn(
+        performer,
+        [shared_this](autosuggestion_t result) {
+            shared_this->autosuggest_completed(std::move(result));
+        },
+        &debounce_autosuggestions());
 }
 
 // Accept any autosuggestion by replacing the command line with it. If full is true, take the whole
@@ -2344,7 +2719,8 @@ void reader_data_t::set_buffer_maintaining_pager(const wcstring &b, size_t pos,
     history_search.reset();
 }
 
-static void set_env_cmd_duration(struct timeval *after, struct timeval *before, env_stack_t &vars) {
+static void set_env_cmd_duration(struct timeval *after, struct timeval *before,
+                                 const eval_res_t &eval_res, env_stack_t &vars) {
     time_t secs = after->tv_sec - before->tv_sec;
     suseconds_t usecs = after->tv_usec - before->tv_usec;
 
@@ -2353,7 +2729,18 @@ static void set_env_cmd_duration(struct timeval *after, struct timeval *before,
         secs -= 1;
     }
 
-    vars.set_one(ENV_CMD_DURATION, ENV_UNEXPORT, std::to_wstring((secs * 1000) + (usecs / 1000)));
+    wcstring duration = std::to_wstring((secs * 1000) + (usecs / 1000));
+    vars.set_one(ENV_CMD_DURATION, ENV_UNEXPORT, duration);
+
+    // The durations of the processes belong to the same job as $pipestatus. If the command did not
+    // run a job in the foreground, or the status did not come from a job, there is only the
+    // duration of the whole command.
+    wcstring_list_t durations;
+    if (!eval_res.no_status) {
+        for (long ms : vars.get_last_statuses().durations) durations.push_back(to_string(ms));
+    }
+    if (durations.empty()) durations.push_back(std::move(duration));
+    vars.set(ENV_CMD_DURATIONS, ENV_UNEXPORT, std::move(durations));
 }
 
 /// Run the specified command with the correct terminal modes, and while taking care to perform job
@@ -2379,15 +2766,15 @@ static eval_res_t reader_run_command(parser_t &parser, const wcstring &cmd) {
 
     // update the execution duration iff a command is requested for execution
     // issue - #4926
-    if (!ft.empty()) set_env_cmd_duration(&time_after, &time_before, parser.vars());
+    if (!ft.empty()) set_env_cmd_duration(&time_after, &time_before, eval_res, parser.vars());
 
     term_steal();
 
     // For compatibility with fish 2.0's $_, now replaced with `status current-command`
     parser.vars().set_one(L"_", ENV_GLOBAL, program_name);
 
-    if (have_proc_stat()) {
-        proc_update_jiffies(parser);
+    if (proc_info_available()) {
+        proc_update_cpu_times(parser);
     }
 
     return eval_res;
@@ -2442,10 +2829,10 @@ static std::function<highlight_result_t(void)> get_highlight_performer(parser_t
                                                                        const wcstring &text,
                                                                        bool io_ok) {
     auto vars = parser.vars().snapshot();
-    uint32_t generation_count = read_generation_count();
+    cancel_checker_t cancel_checker = command_line_scope().cancel_checker();
     return [=]() -> highlight_result_t {
         if (text.empty()) return {};
-        operation_context_t ctx = get_bg_context(vars, generation_count);
+        operation_context_t ctx = get_bg_context(vars, cancel_checker);
         std::vector<highlight_spec_t> colors(text.size(), highlight_spec_t{});
         highlight_shell(text, colors, ctx, io_ok);
         return highlight_result_t{std::move(colors), text};
@@ -2464,9 +2851,12 @@ void reader_data_t::super_highlight_me_plenty() {
     FLOG(reader_render, L"Highlighting");
     auto highlight_performer = get_highlight_performer(parser(), el->text(), true /* io_ok */);
     auto shared_this = this->shared_from_this();
-    debounce_highlighting().perform(highlight_performer, [shared_this](highlight_result_t result) {
-        shared_this->highlight_complete(std::move(result));
-    });
+    command_line_scope().spawn(
+        highlight_performer,
+        [shared_this](highlight_result_t result) {
+            shared_this->highlight_complete(std::move(result));
+        },
+        &debounce_highlighting());
 }
 
 void reader_data_t::finish_highlighting_before_exec() {
@@ -2631,6 +3021,33 @@ static relaxed_atomic_t<uint64_t> status_count{0};
 /// (e.g. background job, or variable assignment).
 uint64_t reader_status_count() { return status_count; }
 
+/// The variables for the handlers of fish_postexec: the command line, how long it took, and the
+/// status and expanded arguments of each process of its last job, unless it did not set $status.
+static event_variables_t postexec_variables(const parser_t &parser, const wcstring &command,
+                                            const eval_res_t &eval_res) {
+    const auto &vars = parser.vars();
+    event_variables_t result{{L"command_line", {command}}};
+    if (auto duration = vars.get(ENV_CMD_DURATION)) {
+        result.emplace_back(L"command_duration", duration->as_list());
+    }
+    if (auto durations = vars.get(ENV_CMD_DURATIONS)) {
+        result.emplace_back(L"command_durations", durations->as_list());
+    }
+    if (eval_res.no_status) return result;
+
+    statuses_t statuses = parser.get_last_statuses();
+    result.emplace_back(L"command_status", wcstring_list_t{to_string(statuses.status)});
+    wcstring_list_t pipestatus;
+    for (int status : statuses.pipestatus) pipestatus.push_back(to_string(status));
+    result.emplace_back(L"command_pipestatus", std::move(pipestatus));
+    result.emplace_back(L"command_process_count",
+                        wcstring_list_t{to_string(statuses.argvs.size())});
+    for (size_t i = 0; i < statuses.argvs.size(); i++) {
+        result.emplace_back(L"command_argv_" + to_string(i + 1), std::move(statuses.argvs[i]));
+    }
+    return result;
+}
+
 /// Read interactively. Read input from stdin while providing editing facilities.
 static int read_i(parser_t &parser) {
     ASSERT_IS_MAIN_THREAD();
@@ -2656,6 +3073,8 @@ static int read_i(parser_t &parser) {
     while (!check_exit_loop_maybe_warning(data.get())) {
         ++run_count;
 
+        dir_env_update(parser);
+        event_print_async_output();
         maybe_t<wcstring> tmp = data->readline(0);
         if (tmp && !tmp->empty()) {
             const wcstring command = tmp.acquire();
@@ -2663,7 +3082,8 @@ static int read_i(parser_t &parser) {
             data->command_line.clear();
             data->command_line_changed(&data->command_line);
             wcstring_list_t argv{command};
-            event_fire_generic(parser, L"fish_preexec", &argv);
+            event_variables_t preexec_vars{{L"command_line", {command}}};
+            event_fire_generic(parser, L"fish_preexec", &argv, &preexec_vars);
             auto eval_res = reader_run_command(parser, command);
             signal_clear_cancel();
             if (!eval_res.no_status) {
@@ -2674,7 +3094,13 @@ static int read_i(parser_t &parser) {
             data->exit_loop_requested |= parser.libdata().exit_current_script;
             parser.libdata().exit_current_script = false;
 
-            event_fire_generic(parser, L"fish_postexec", &argv);
+            // Pass the duration of each process after the command line.
+            if (auto durations = parser.vars().get(ENV_CMD_DURATIONS)) {
+                const wcstring_list_t &list = durations->as_list();
+                argv.insert(argv.end(), list.begin(), list.end());
+            }
+            event_variables_t postexec_vars = postexec_variables(parser, command, eval_res);
+            event_fire_generic(parser, L"fish_postexec", &argv, &postexec_vars);
             // Allow any pending history items to be returned in the history array.
             if (data->history) {
                 data->history->resolve_pending();
@@ -2809,6 +3235,8 @@ maybe_t<char_event_t> reader_data_t::read_normal_chars(readline_loop_state_t &rl
 
     using command_handler_t = inputter_t::command_handler_t;
     command_handler_t normal_handler = [this](const wcstring_list_t &cmds) {
+        // The key for this binding was pressed, so any message from before goes away.
+        this->message.clear();
         this->run_input_command_scripts(cmds);
     };
     command_handler_t empty_handler = {};
@@ -2816,6 +3244,9 @@ maybe_t<char_event_t> reader_data_t::read_normal_chars(readline_loop_state_t &rl
     while (accumulated_chars.size() < limit) {
         bool allow_commands = (accumulated_chars.empty());
         auto evt = inputter.readch(allow_commands ? normal_handler : empty_handler);
+        // A message is shown until the next key is pressed. Functions queued by a binding with
+        // `commandline -f` have no key sequence, and leave it alone.
+        if (evt.is_char() || (evt.is_readline() && !evt.seq.empty())) message.clear();
         if (!event_is_normal_char(evt) || !can_read(conf.in)) {
             event_needing_handling = std::move(evt);
             break;
@@ -2944,6 +3375,15 @@ void reader_data_t::handle_readline_command(readline_cmd_t c, readline_loop_stat
             // Else we repaint as normal.
             __fallthrough__
         }
+        case rl::repaint_right_prompt: {
+            // The right prompt might have been execed since this was scheduled.
+            if (!force_exec_right_prompt) break;
+            parser().libdata().is_repaint = true;
+            exec_right_prompt();
+            if (this->is_repaint_needed()) this->layout_and_repaint(L"right prompt");
+            parser().libdata().is_repaint = false;
+            break;
+        }
         case rl::force_repaint:
         case rl::repaint: {
             parser().libdata().is_repaint = true;
@@ -2958,6 +3398,13 @@ void reader_data_t::handle_readline_command(readline_cmd_t c, readline_loop_stat
         case rl::complete_and_search: {
             if (!conf.complete_ok) break;
 
+            // While filling in a snippet, tab moves to its next placeholder.
+            if (c == rl::complete && !snippet_placeholders.empty() &&
+                !is_navigating_pager_contents()) {
+                next_snippet_placeholder();
+                break;
+            }
+
             // Use the command line only; it doesn't make sense to complete in any other line.
             editable_line_t *el = &command_line;
             if (is_navigating_pager_contents() ||
@@ -3024,6 +3471,7 @@ void reader_data_t::handle_readline_command(readline_cmd_t c, readline_loop_stat
                 // Show the search field if requested and if we printed a list of completions.
                 if (c == rl::complete_and_search && !rls.complete_did_insert && !pager.empty()) {
                     pager.set_search_field_shown(true);
+                    pager.set_search_jumps_to_matches(pager_search_jumps_to_matches(vars));
                     select_completion_in_direction(selection_motion_t::next);
                 }
             }
@@ -3034,6 +3482,10 @@ void reader_data_t::handle_readline_command(readline_cmd_t c, readline_loop_stat
                 // Toggle search, and begin navigating if we are now searching.
                 bool sfs = pager.is_search_field_shown();
                 pager.set_search_field_shown(!sfs);
+                if (!sfs) {
+                    pager.set_search_jumps_to_matches(pager_search_jumps_to_matches(vars));
+                    pager.refilter_completions();
+                }
                 pager.set_fully_disclosed(true);
                 if (pager.is_search_field_shown() && !is_navigating_pager_contents()) {
                     select_completion_in_direction(selection_motion_t::south);
@@ -3110,6 +3562,7 @@ void reader_data_t::handle_readline_command(readline_cmd_t c, readline_loop_stat
             break;
         }
         case rl::yank: {
+            merge_shared_kills();
             wcstring yank_str = kill_yank();
             insert_string(active_edit_line(), yank_str);
             rls.yank_len = yank_str.size();
@@ -3165,6 +3618,7 @@ void reader_data_t::handle_readline_command(readline_cmd_t c, readline_loop_stat
 
             // Delete any autosuggestion.
             autosuggestion.clear();
+            snippet_placeholders.clear();
 
             // The user may have hit return with pager contents, but while not navigating them.
             // Clear the pager in that event.
@@ -3263,7 +3717,8 @@ void reader_data_t::handle_readline_command(readline_cmd_t c, readline_loop_stat
                 // Result must be some combination including an error. The error message will
                 // already be printed, all we need to do is repaint.
                 wcstring_list_t argv(1, el->text());
-                event_fire_generic(parser(), L"fish_posterror", &argv);
+                event_variables_t posterror_vars{{L"command_line", {el->text()}}};
+                event_fire_generic(parser(), L"fish_posterror", &argv, &posterror_vars);
                 s_reset_abandoning_line(&screen, termsize_last().width);
             }
             break;
@@ -3764,6 +4219,7 @@ void reader_data_t::handle_readline_command(readline_cmd_t c, readline_loop_stat
             if (ok) {
                 if (el == &command_line) {
                     pager.clear();
+                    snippet_placeholders.clear();
                 }
                 update_buff_pos(el);
             } else {
@@ -3814,6 +4270,8 @@ maybe_t<wcstring> reader_data_t::readline(int nchars_or_0) {
     cycle_command_line.clear();
     cycle_cursor_pos = 0;
 
+    snippet_placeholders.clear();
+
     history_search.reset();
 
     // Get the current terminal modes. These will be restored when the function returns.
@@ -3835,7 +4293,7 @@ maybe_t<wcstring> reader_data_t::readline(int nchars_or_0) {
 
     s_reset_abandoning_line(&screen, termsize_last().width);
     event_fire_generic(parser(), L"fish_prompt");
-    exec_prompt();
+    exec_prompt_with_deadline();
 
     /// A helper that kicks off syntax highlighting, autosuggestion computing, and repaints.
     auto color_suggest_repaint_now = [this] {
@@ -3950,6 +4408,9 @@ maybe_t<wcstring> reader_data_t::readline(int nchars_or_0) {
     // Finish any outstanding syntax highlighting (but do not wait forever).
     finish_highlighting_before_exec();
 
+    // Write out anything the terminal could not take yet, as we are about to write directly.
+    outputter_t::stdoutput().flush_all();
+
     // Emit a newline so that the output is on the line after the command.
     // But do not emit a newline if the cursor has wrapped onto a new line all its own - see #6826.
     if (!screen.cursor_is_wrapped_to_own_line()) {
@@ -4057,6 +4518,15 @@ void reader_schedule_prompt_repaint() {
     }
 }
 
+void reader_schedule_right_prompt_repaint() {
+    ASSERT_IS_MAIN_THREAD();
+    reader_data_t *data = current_data_or_null();
+    if (data && !data->force_exec_prompt_and_repaint && !data->force_exec_right_prompt) {
+        data->force_exec_right_prompt = true;
+        data->inputter.queue_ch(readline_cmd_t::repaint_right_prompt);
+    }
+}
+
 void reader_handle_command(readline_cmd_t cmd) {
     if (reader_data_t *data = current_data_or_null()) {
         readline_loop_state_t rls{};
@@ -4091,6 +4561,23 @@ void reader_set_buffer(const wcstring &b, size_t pos) {
     data->set_buffer_maintaining_pager(b, pos);
 }
 
+void reader_set_message(const wcstring &message) {
+    reader_data_t *data = current_data_or_null();
+    if (!data) return;
+    data->message = message;
+}
+
+wcstring reader_get_message() {
+    reader_data_t *data = current_data_or_null();
+    return data ? data->message : wcstring{};
+}
+
+void reader_insert_snippet(const wcstring &snippet) {
+    reader_data_t *data = current_data_or_null();
+    if (!data) return;
+    data->insert_snippet(snippet);
+}
+
 size_t reader_get_cursor_pos() {
     reader_data_t *data = current_data_or_null();
     if (!data) return static_cast<size_t>(-1);
@@ -4109,10 +4596,76 @@ bool reader_get_selection(size_t *start, size_t *len) {
     return result;
 }
 
+/// Replace the top-level statements of \p src that contain syntax errors with whitespace, so that
+/// the remaining statements can be evaluated. Newlines are kept so line numbers stay the same. The
+/// errors are appended to \p out_errors. \return false if the errors could not be isolated.
+static bool blank_erroneous_statements(wcstring *src, parse_error_list_t *out_errors) {
+    for (;;) {
+        parse_error_list_t errors;
+        auto ast = ast::ast_t::parse(*src, parse_flag_none, &errors);
+        if (!ast.errored() && !parse_util_detect_errors(ast, *src, &errors)) return true;
+        if (errors.empty()) return false;
+        const parse_error_t &err = errors.front();
+        if (err.source_start == SOURCE_LOCATION_UNKNOWN || err.source_start > src->size()) {
+            return false;
+        }
+
+        // By default, skip from the error to the end of its line.
+        size_t start = err.source_start;
+        size_t end = src->find(L'\n', std::min(src->size(), start + err.source_length));
+        if (end == wcstring::npos) end = src->size();
+
+        // If the error is part of a top-level statement, skip all of it. Use error recovery to find
+        // the statements.
+        auto recovered = ast::ast_t::parse(*src, parse_flag_continue_after_error);
+        maybe_t<source_range_t> stmt_range{};
+        for (const ast::job_conjunction_t &jc : *recovered.top()->as<ast::job_list_t>()) {
+            auto range = jc.try_source_range();
+            if (!range) continue;
+            if (range->start > err.source_start) break;
+            stmt_range = range;
+        }
+        if (stmt_range) {
+            // The range of a statement includes its terminating newline or semicolon.
+            size_t stmt_end = stmt_range->start + stmt_range->length;
+            bool same_line = src->find(L'\n', stmt_end - 1) >= err.source_start;
+            if (err.source_start < stmt_end || same_line) {
+                start = stmt_range->start;
+                end = std::max(end, stmt_end);
+            }
+        }
+
+        bool changed = false;
+        for (size_t i = start; i < end; i++) {
+            wchar_t &c = src->at(i);
+            if (c != L'\n' && c != L' ') {
+                c = L' ';
+                changed = true;
+            }
+        }
+        out_errors->push_back(err);
+        if (!changed) return false;
+    }
+}
+
+void reader_report_deferred_errors(parser_t &parser) {
+    wcstring_list_t &errors = parser.libdata().deferred_errors;
+    if (errors.empty()) return;
+    std::fwprintf(stderr, _(L"fish: Errors occurred while sourcing files:\n"));
+    for (const wcstring &err : errors) {
+        std::fwprintf(stderr, L"\n%ls", err.c_str());
+    }
+    errors.clear();
+}
+
 /// Read non-interactively.  Read input from stdin without displaying the prompt, using syntax
 /// highlighting. This is used for reading scripts and init files.
 /// The file is not closed.
-static int read_ni(parser_t &parser, int fd, const io_chain_t &io) {
+static int read_ni(parser_t &parser, int fd, const io_chain_t &io, const wchar_t *cache_path) {
+    // Identify the file before reading it, so if it changes meanwhile, its cached form won't be
+    // used the next time.
+    file_id_t file_id = cache_path ? file_id_for_fd(fd) : kInvalidFileID;
+
     // Read all data into a std::string.
     std::string fd_contents;
     for (;;) {
@@ -4151,12 +4704,35 @@ static int read_ni(parser_t &parser, int fd, const io_chain_t &io) {
         str.erase(0, 1);
     }
 
-    // Parse into an ast and detect errors.
+    // Parse into an ast and detect errors. Only asts without errors are cached.
     parse_error_list_t errors;
-    auto ast = ast::ast_t::parse(str, parse_flag_none, &errors);
+    maybe_t<ast::ast_t> cached;
+    if (cache_path) cached = ast_cache_load(cache_path, file_id, str);
+    bool from_cache = cached.has_value();
+    auto ast = from_cache ? cached.acquire() : ast::ast_t::parse(str, parse_flag_none, &errors);
     bool errored = ast.errored();
-    if (!errored) {
+    if (!errored && !from_cache) {
         errored = parse_util_detect_errors(ast, str, &errors);
+        if (!errored && cache_path) ast_cache_store(cache_path, file_id, ast);
+    }
+    if (errored && parser.libdata().keep_going_on_errors) {
+        // Skip the statements with errors and run the rest.
+        wcstring blanked = str;
+        parse_error_list_t skipped_errors;
+        if (blank_erroneous_statements(&blanked, &skipped_errors)) {
+            std::stable_sort(skipped_errors.begin(), skipped_errors.end(),
+                             [](const parse_error_t &a, const parse_error_t &b) {
+                                 return a.source_start < b.source_start;
+                             });
+            for (const parse_error_t &err : skipped_errors) {
+                wcstring sb;
+                parser.get_backtrace(str, {err}, sb);
+                parser.libdata().deferred_errors.push_back(std::move(sb));
+            }
+            str = std::move(blanked);
+            ast = ast::ast_t::parse(str);
+            errored = false;
+        }
     }
     if (!errored) {
         // Construct a parsed source ref.
@@ -4172,7 +4748,7 @@ static int read_ni(parser_t &parser, int fd, const io_chain_t &io) {
     }
 }
 
-int reader_read(parser_t &parser, int fd, const io_chain_t &io) {
+int reader_read(parser_t &parser, int fd, const io_chain_t &io, const wchar_t *cache_path) {
     int res;
 
     // If reader_read is called recursively through the '.' builtin, we need to preserve
@@ -4196,7 +4772,7 @@ int reader_read(parser_t &parser, int fd, const io_chain_t &io) {
     scoped_push<bool> interactive_push{&parser.libdata().is_interactive, interactive};
     signal_set_handlers_once(interactive);
 
-    res = interactive ? read_i(parser) : read_ni(parser, fd, io);
+    res = interactive ? read_i(parser) : read_ni(parser, fd, io, cache_path);
 
     // If the exit command was called in a script, only exit the script, not the program.
     parser.libdata().exit_current_script = false;
@@ -46,6 +46,23 @@ struct edit_t {
 /// Currently exposed for testing only.
 void apply_edit(wcstring *target, const edit_t &edit);
 
+/// A placeholder of a snippet, which tab moves to. This is a range of the command line.
+struct snippet_placeholder_t {
+    size_t start{0};
+    size_t length{0};
+
+    bool operator==(const snippet_placeholder_t &rhs) const {
+        return start == rhs.start && length == rhs.length;
+    }
+    bool operator!=(const snippet_placeholder_t &rhs) const { return !(*this == rhs); }
+};
+
+/// Parse a snippet like 'ssh ${1:host} -p ${2:22}'. \return its text, with each placeholder
+/// replaced by its default text, and set \p out_placeholders to the placeholders in the order they
+/// are visited: by number, with 0 last, and from left to right for the same number.
+/// Currently exposed for testing only.
+wcstring parse_snippet(const wcstring &snippet, std::vector<snippet_placeholder_t> *out_placeholders);
+
 /// The history of all edits to some command line.
 struct undo_history_t {
     /// The stack of edits that can be undone or redone atomically.
@@ -133,8 +150,13 @@ class editable_line_t {
 };
 
 /// Read commands from \c fd until encountering EOF.
-/// The fd is not closed.
-int reader_read(parser_t &parser, int fd, const io_chain_t &io);
+/// The fd is not closed. If \p cache_path is set, \p fd is the file at that path, and its parsed
+/// form is cached on disk.
+int reader_read(parser_t &parser, int fd, const io_chain_t &io,
+                const wchar_t *cache_path = nullptr);
+
+/// Print the errors that were deferred because of 'source --keep-going', and clear them.
+void reader_report_deferred_errors(parser_t &parser);
 
 /// Mark that we encountered SIGHUP and must (soon) exit. This is invoked from a signal handler.
 void reader_sighup();
@@ -160,6 +182,10 @@ void reader_write_title(const wcstring &cmd, parser_t &parser, bool reset_cursor
 /// This may be called in response to e.g. a color variable change.
 void reader_schedule_prompt_repaint();
 
+/// Tell the reader that it needs to re-exec only the right prompt and repaint.
+/// This is used when a prompt segment finished in the background.
+void reader_schedule_right_prompt_repaint();
+
 /// Enqueue an event to the back of the reader's input queue.
 class char_event_t;
 void reader_queue_ch(const char_event_t &ch);
@@ -178,6 +204,17 @@ std::shared_ptr<history_t> reader_get_history();
 /// is placed on the last character.
 void reader_set_buffer(const wcstring &b, size_t p = -1);
 
+/// Show \p message on a line below the command line, until the next key is pressed. An empty message
+/// removes it.
+void reader_set_message(const wcstring &message);
+
+/// Return the message shown below the command line, or an empty string if there is none.
+wcstring reader_get_message();
+
+/// Insert \p snippet at the cursor, and move the cursor to its first placeholder. Tab then moves
+/// between its placeholders.
+void reader_insert_snippet(const wcstring &snippet);
+
 /// Get the current cursor position in the command line. If interactive mode is uninitialized,
 /// return (size_t)-1.
 size_t reader_get_cursor_pos();
@@ -235,6 +272,9 @@ struct reader_config_t {
     /// If set, do not show what is typed.
     bool in_silent_mode{false};
 
+    /// In silent mode, the character shown for each one that is typed, or empty to show nothing.
+    wcstring silent_mask{};
+
     /// The fd for stdin, default to actual stdin.
     int in{0};
 };
@@ -13,7 +13,9 @@ enum class redirection_mode_t {
     append,     // appending redirection: >> file.txt
     input,      // input redirection: < file.txt
     fd,         // fd redirection: 2>&1
-    noclob      // noclobber redirection: >? file.txt
+    noclob,     // noclobber redirection: >? file.txt
+    here_string,  // here-string: <<< text
+    here_doc,     // here-document: <<END, followed by lines up to a line containing just END
 };
 
 class io_chain_t;
@@ -32,6 +34,7 @@ struct redirection_spec_t {
     /// The target of the redirection.
     /// For example in "3>&1", this will be "1".
     /// In "< file.txt" this will be "file.txt".
+    /// For a here-string or here-document, this is the text to provide as input.
     wcstring target{};
 
     /// \return if this is a close-type redirection.
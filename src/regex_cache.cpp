@@ -0,0 +1,47 @@
+// A cache of compiled PCRE2 regular expressions.
+#include "config.h"  // IWYU pragma: keep
+
+#include "regex_cache.h"
+
+#include <utility>
+
+#include "common.h"
+#include "lru.h"
+#include "wcstringutil.h"
+
+namespace {
+/// The recently used patterns, keyed by their options and the pattern.
+class regex_lru_t : public lru_cache_t<regex_lru_t, regex_code_ref_t> {
+   public:
+    regex_lru_t() : lru_cache_t<regex_lru_t, regex_code_ref_t>(REGEX_CACHE_SIZE) {}
+};
+}  // namespace
+static owning_lock<regex_lru_t> s_regex_cache;
+
+regex_code_ref_t regex_compile_cached(const wcstring &pattern, uint32_t options,
+                                      regex_compile_error_t *error) {
+    wcstring key = to_string(static_cast<long>(options));
+    key.push_back(L':');
+    key.append(pattern);
+    if (regex_code_ref_t *cached = s_regex_cache.acquire()->get(key)) return *cached;
+
+    // Compile without holding the lock, since that may take a while.
+    int err_code = 0;
+    PCRE2_SIZE err_offset = 0;
+    pcre2_code *code = pcre2_compile(PCRE2_SPTR(pattern.c_str()), pattern.length(), options,
+                                     &err_code, &err_offset, nullptr);
+    if (!code) {
+        if (error) {
+            error->code = err_code;
+            error->offset = err_offset;
+        }
+        return nullptr;
+    }
+    regex_code_ref_t result(code, [](const pcre2_code *code) {
+        pcre2_code_free(const_cast<pcre2_code *>(code));
+    });
+    s_regex_cache.acquire()->insert(std::move(key), result);
+    return result;
+}
+
+size_t regex_cache_count() { return s_regex_cache.acquire()->size(); }
@@ -0,0 +1,47 @@
+// A cache of compiled PCRE2 regular expressions.
+//
+// Compiling a pattern is much slower than matching a short string with it, and the same few
+// patterns are used over and over, e.g. by `string match -r` in a loop or in functions run for
+// every prompt. So everything that uses PCRE2 compiles its patterns through here.
+#ifndef FISH_REGEX_CACHE_H
+#define FISH_REGEX_CACHE_H
+
+#include "config.h"  // IWYU pragma: keep
+
+#ifndef PCRE2_CODE_UNIT_WIDTH
+#define PCRE2_CODE_UNIT_WIDTH WCHAR_T_BITS
+#endif
+#ifdef _WIN32
+#define PCRE2_STATIC
+#endif
+
+#include <memory>
+
+#include "common.h"
+#include "pcre2.h"
+
+/// A compiled pattern. It is shared by everyone who uses the pattern, which PCRE2 allows as long
+/// as each match has its own match data.
+using regex_code_ref_t = std::shared_ptr<const pcre2_code>;
+
+/// Why a pattern could not be compiled.
+struct regex_compile_error_t {
+    /// The PCRE2 error code.
+    int code{0};
+    /// The offset in the pattern where the error was found.
+    size_t offset{0};
+};
+
+/// The number of compiled patterns that are kept.
+constexpr size_t REGEX_CACHE_SIZE = 64;
+
+/// \return \p pattern compiled with the PCRE2 \p options, reusing the result of a recent
+/// compilation of the same pattern with the same options. On failure, return nullptr and set
+/// \p error if it is not null. Failures are not cached. This may be called from any thread.
+regex_code_ref_t regex_compile_cached(const wcstring &pattern, uint32_t options,
+                                      regex_compile_error_t *error = nullptr);
+
+/// \return the number of compiled patterns in the cache.
+size_t regex_cache_count();
+
+#endif
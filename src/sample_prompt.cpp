@@ -0,0 +1,148 @@
+// Sample prompts: the prompts which come with fish, for fish_config to choose from.
+#include "config.h"  // IWYU pragma: keep
+
+#include "sample_prompt.h"
+
+#include <cwctype>
+
+#include "exec.h"
+#include "function.h"
+#include "parser.h"
+#include "proc.h"
+#include "sample_prompt_sources.h"
+#include "screen.h"
+#include "wcstringutil.h"
+#include "wutil.h"  // IWYU pragma: keep
+
+wcstring sample_prompt_t::get(const wcstring &key) const {
+    for (const auto &extra : extras) {
+        if (extra.first == key) return extra.second;
+    }
+    return wcstring();
+}
+
+sample_prompt_t sample_prompt_parse(const wcstring &name, const wcstring &contents) {
+    sample_prompt_t prompt;
+    prompt.name = name;
+    wcstring_list_t lines = split_string(contents, L'\n');
+    auto line = lines.begin();
+    for (; line != lines.end(); ++line) {
+        if (trim(*line).empty()) continue;
+        if (line->at(0) != L'#') break;
+        size_t key_start = line->find_first_not_of(L" \t", 1);
+        if (key_start == wcstring::npos) continue;
+        size_t key_end = key_start;
+        while (key_end < line->size() &&
+               (iswalnum(line->at(key_end)) || line->at(key_end) == L'_')) {
+            key_end++;
+        }
+        if (key_end > key_start && line->compare(key_end, 2, L": ") == 0) {
+            wcstring value = trim(line->substr(key_end + 2));
+            if (!value.empty()) {
+                prompt.extras.emplace_back(line->substr(key_start, key_end - key_start), value);
+            }
+        }
+    }
+    prompt.function = trim(join_strings(wcstring_list_t(line, lines.end()), L'\n'));
+    return prompt;
+}
+
+wcstring_list_t sample_prompt_list() {
+    wcstring_list_t result;
+    for (const auto &source : sample_prompt_sources) {
+        result.push_back(str2wcstring(source.name));
+    }
+    return result;
+}
+
+maybe_t<sample_prompt_t> sample_prompt_load(const wcstring &name) {
+    for (const auto &source : sample_prompt_sources) {
+        if (name == str2wcstring(source.name)) {
+            return sample_prompt_parse(name, str2wcstring(source.contents));
+        }
+    }
+    return none();
+}
+
+/// The line the new fish prints between the output of the prompt functions.
+static const wchar_t *const prompt_separator = L"\x1e";
+
+/// Set \p output from the output of the prompt functions: \p mode is fish_mode_prompt's, \p left
+/// fish_prompt's and \p right fish_right_prompt's. Like the reader, this joins the lines of the
+/// mode prompt and of the right prompt, and puts the mode prompt in front of the left prompt.
+static void set_prompt_output(prompt_output_t *output, const wcstring_list_t &mode,
+                              wcstring_list_t left, const wcstring_list_t &right) {
+    if (left.empty()) left.emplace_back();
+    for (auto line = mode.rbegin(); line != mode.rend(); ++line) left.front().insert(0, *line);
+    output->left = std::move(left);
+    output->right.clear();
+    for (const wcstring &line : right) output->right.append(line);
+}
+
+prompt_output_t sample_prompt_run(parser_t &parser, const wcstring &function) {
+    // Run all prompt functions in one fish, and separate their output by lines of their own. Each
+    // starts on a new line, which is not part of what it prints.
+    wcstring separate = L"printf '\\n%s\\n' " + wcstring(prompt_separator);
+    wcstring script = function + L"\nfalse; fish_mode_prompt; " + separate +
+                      L"\nfalse; fish_prompt; " + separate +
+                      L"\nif functions -q fish_right_prompt; false; fish_right_prompt; end\n";
+    wcstring fish_path = str2wcstring(get_executable_path("fish"));
+    wcstring_list_t lines;
+    exec_subshell(L"command " + escape_string(fish_path, ESCAPE_ALL) + L" -c " +
+                      escape_string(script, ESCAPE_ALL) + L" 2>/dev/null",
+                  parser, lines, false /* apply_exit_status */);
+
+    std::vector<wcstring_list_t> parts(1);
+    for (wcstring &line : lines) {
+        if (line != prompt_separator || parts.size() == 3) {
+            parts.back().push_back(std::move(line));
+            continue;
+        }
+        // A command substitution drops one newline at the end of the output, and so does this.
+        if (!parts.back().empty() && parts.back().back().empty()) parts.back().pop_back();
+        parts.emplace_back();
+    }
+    parts.resize(3);
+    prompt_output_t result;
+    set_prompt_output(&result, parts.at(0), std::move(parts.at(1)), parts.at(2));
+    return result;
+}
+
+/// \return the lines the prompt function \p name prints after a failed command, if it exists.
+static wcstring_list_t run_prompt_function(parser_t &parser, const wcstring &name) {
+    wcstring_list_t output;
+    if (function_exists(name, parser)) {
+        exec_subshell(L"false; " + name + L" 2>/dev/null", parser, output,
+                      false /* apply_exit_status */);
+    }
+    return output;
+}
+
+prompt_output_t sample_prompt_run_current(parser_t &parser) {
+    prompt_output_t result;
+    set_prompt_output(&result, run_prompt_function(parser, L"fish_mode_prompt"),
+                      run_prompt_function(parser, L"fish_prompt"),
+                      run_prompt_function(parser, L"fish_right_prompt"));
+    return result;
+}
+
+wcstring sample_prompt_render(const prompt_output_t &output, size_t width) {
+    wcstring result = join_strings(output.left, L'\n');
+    if (output.right.empty()) return result;
+    layout_cache_t &layouts = layout_cache_t::shared;
+    size_t left_width = layouts.calc_prompt_layout(result).last_line_width;
+    size_t right_width = layouts.calc_prompt_layout(output.right).max_line_width;
+    // Like the screen, keep a column free for the cursor.
+    if (left_width + right_width < width) {
+        result.append(width - left_width - right_width, L' ');
+        result.append(output.right);
+    }
+    return result;
+}
+
+bool sample_prompt_use(parser_t &parser, const wcstring &function) {
+    wcstring_list_t output;
+    int status = exec_subshell(function + L"\nfuncsave fish_prompt", parser, output,
+                               false /* apply_exit_status */);
+    return status == STATUS_CMD_OK;
+}
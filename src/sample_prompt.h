@@ -0,0 +1,65 @@
+// Sample prompts: the prompts which come with fish, for fish_config to choose from.
+#ifndef FISH_SAMPLE_PROMPT_H
+#define FISH_SAMPLE_PROMPT_H
+
+#include <utility>
+#include <vector>
+
+#include "common.h"
+#include "maybe.h"
+
+class parser_t;
+
+/// A sample prompt.
+///
+/// The sample prompts are the files in src/sample_prompts, which are built into fish. Each defines
+/// fish_prompt, and maybe other functions like fish_right_prompt. It may start with comments like
+/// `# name: Classic`, which describe it.
+struct sample_prompt_t {
+    /// The name of the prompt, which is its file name without the .fish suffix.
+    wcstring name;
+
+    /// The keys and values of the comments at the start of the file, in order.
+    std::vector<std::pair<wcstring, wcstring>> extras;
+
+    /// The rest of the file, which defines the functions.
+    wcstring function;
+
+    /// \return the value of the comment with the key \p key, or an empty string if there is none.
+    wcstring get(const wcstring &key) const;
+};
+
+/// Parse \p contents, the contents of the file of the sample prompt \p name.
+sample_prompt_t sample_prompt_parse(const wcstring &name, const wcstring &contents);
+
+/// \return the names of all sample prompts, sorted.
+wcstring_list_t sample_prompt_list();
+
+/// Load the sample prompt called \p name, or return none() if there is no such prompt.
+maybe_t<sample_prompt_t> sample_prompt_load(const wcstring &name);
+
+/// What a prompt prints.
+struct prompt_output_t {
+    /// The lines of fish_mode_prompt and fish_prompt.
+    wcstring_list_t left;
+
+    /// The output of fish_right_prompt, if it is defined.
+    wcstring right;
+};
+
+/// \return what the prompt defined by \p function prints after a failed command. It is run in a
+/// new fish, so its functions and variables do not change this one.
+prompt_output_t sample_prompt_run(parser_t &parser, const wcstring &function);
+
+/// \return what the prompt of this session prints after a failed command.
+prompt_output_t sample_prompt_run_current(parser_t &parser);
+
+/// \return \p output laid out as on a screen \p width columns wide: the right prompt goes at the
+/// end of the last line of the left prompt, if it fits there.
+wcstring sample_prompt_render(const prompt_output_t &output, size_t width);
+
+/// Define the functions of the prompt \p function in this session and save fish_prompt to the
+/// user's function directory. \return whether that worked.
+bool sample_prompt_use(parser_t &parser, const wcstring &function);
+
+#endif
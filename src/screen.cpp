@@ -10,6 +10,7 @@
 #include <stddef.h>
 #include <stdio.h>
 #include <stdlib.h>
+#include <sys/uio.h>
 #include <termios.h>
 #include <time.h>
 #include <unistd.h>
@@ -58,6 +59,10 @@ class scoped_buffer_t {
     ~scoped_buffer_t() { screen_.outp().end_buffering(); }
 };
 
+right_prompt_overflow_t g_right_prompt_overflow = right_prompt_overflow_t::hide;
+maybe_t<wide_char_wrap_t> g_wide_char_wrap{};
+invisible_style_t g_invisible_style = invisible_style_t::caret;
+
 // Singleton of the cached escape sequences seen in prompts and similar strings.
 // Note this is deliberately exported so that init_curses can clear it.
 layout_cache_t layout_cache_t::shared;
@@ -84,6 +89,34 @@ static size_t next_tab_stop(size_t current_line_width) {
 /// Like fish_wcwidth, but returns 0 for control characters instead of -1.
 static int fish_wcwidth_min_0(wchar_t widechar) { return std::max(0, fish_wcwidth(widechar)); }
 
+/// Whether \p c is shown as an escape in the command line, because it would be invisible or move
+/// the cursor. Newlines are not, they end the line. Zero-width joiners and non-joiners are not
+/// either, since they are part of emoji sequences and some scripts.
+static bool is_shown_as_escape(wchar_t c) {
+    if (g_invisible_style == invisible_style_t::none || c == L'\n') return false;
+    return c < 0x20 || (c >= 0x7F && c < 0xA0) || c == 0x061C || c == 0x200B || c == 0x200E ||
+           c == 0x200F || (c >= 0x202A && c <= 0x202E) || (c >= 0x2060 && c <= 0x2064) ||
+           (c >= 0x2066 && c <= 0x2069) || c == 0xFEFF;
+}
+
+/// \return how \p c is shown in the command line, in the style of $fish_invisible_style.
+/// This must only be called if is_shown_as_escape(c).
+static wcstring invisible_char_escape(wchar_t c) {
+    bool control = c < 0x20 || c == 0x7F;
+    if (g_invisible_style == invisible_style_t::escape) {
+        if (control) return escape_string(wcstring(1, c), ESCAPE_ALL | ESCAPE_NO_QUOTED);
+        return format_string(L"\\u%04x", static_cast<unsigned>(c));
+    }
+    if (control) return {L'^', c == 0x7F ? L'?' : static_cast<wchar_t>(c + L'@')};
+    return format_string(L"<U+%04X>", static_cast<unsigned>(c));
+}
+
+/// \return the width of \p c in the command line.
+static int commandline_char_width(wchar_t c) {
+    if (is_shown_as_escape(c)) return static_cast<int>(invisible_char_escape(c).size());
+    return fish_wcwidth_min_0(c);
+}
+
 int line_t::wcswidth_min_0(size_t max) const {
     int result = 0;
     for (size_t idx = 0, end = std::min(max, text.size()); idx < end; idx++) {
@@ -99,6 +132,14 @@ static bool allow_soft_wrap() {
     return auto_right_margin;
 }
 
+/// Whether to pad a line with spaces when a wide character does not fit at its end. Padding relies on
+/// soft wrapping, so it is only done if that is allowed.
+static bool pad_wide_char_wrap() {
+    if (!allow_soft_wrap()) return false;
+    if (g_wide_char_wrap) return *g_wide_char_wrap == wide_char_wrap_t::pad;
+    return term_has_xn;
+}
+
 /// Does this look like the escape sequence for setting a screen name?
 static bool is_screen_name_escape_seq(const wchar_t *code, size_t *resulting_length) {
     if (code[1] != L'k') {
@@ -467,6 +508,30 @@ static void s_check_status(screen_t *s) {
 
 /// Appends a character to the end of the line that the output cursor is on. This function
 /// automatically handles linebreaks and lines longer than the screen width.
+/// If a character of width \p cw does not fit on the current line of the desired screen, continue on
+/// the next one. This must happen before the cursor position is taken for that character.
+static void s_desired_wrap_for_char(screen_t *s, int cw) {
+    int screen_width = s->desired.screen_width;
+    if (s->desired.cursor.x + cw <= screen_width) return;
+
+    s->desired.create_line(s->desired.cursor.y);
+    line_t &line = s->desired.line(s->desired.cursor.y);
+    if (pad_wide_char_wrap()) {
+        // Fill up the line, so the terminal wraps it like any other, instead of each terminal
+        // deciding on its own where a wide character in the last column goes.
+        while (s->desired.cursor.x < screen_width) {
+            line.append(L' ', highlight_spec_t{});
+            s->desired.cursor.x++;
+        }
+    }
+    // Current line is soft wrapped (assuming we support it).
+    line.is_soft_wrapped = true;
+
+    s->desired.add_line();
+    s->desired.cursor.y++;
+    s->desired.cursor.x = 0;
+}
+
 static void s_desired_append_char(screen_t *s, wchar_t b, highlight_spec_t c, int indent,
                                   size_t prompt_width, size_t bwidth) {
     int line_no = s->desired.cursor.y;
@@ -495,15 +560,8 @@ static void s_desired_append_char(screen_t *s, wchar_t b, highlight_spec_t c, in
         s->desired.create_line(line_no);
 
         // Check if we are at the end of the line. If so, continue on the next line.
-        if ((s->desired.cursor.x + cw) > screen_width) {
-            // Current line is soft wrapped (assuming we support it).
-            s->desired.line(s->desired.cursor.y).is_soft_wrapped = true;
-
-            line_no = static_cast<int>(s->desired.line_count());
-            s->desired.add_line();
-            s->desired.cursor.y++;
-            s->desired.cursor.x = 0;
-        }
+        s_desired_wrap_for_char(s, cw);
+        line_no = s->desired.cursor.y;
 
         line_t &line = s->desired.line(line_no);
         line.append(b, c);
@@ -735,7 +793,17 @@ static void s_update(screen_t *scr, const wcstring &left_prompt, const wcstring
     if (scr->desired.line_count() < lines_with_stuff) need_clear_screen = true;
 
     // Output the left prompt if it has changed.
-    if (left_prompt != scr->actual_left_prompt) {
+    const bool left_prompt_changed = left_prompt != scr->actual_left_prompt;
+    // If it replaces a prompt that is still on screen, like when the right prompt moves to its own
+    // line, it must be written from the first line of the old one. If the number of lines changed,
+    // everything after it moved.
+    bool prompt_lines_changed = false;
+    if (left_prompt_changed && !scr->actual_left_prompt.empty()) {
+        const size_t actual_prompt_lines = calc_prompt_lines(scr->actual_left_prompt);
+        scr->actual.cursor.y += static_cast<int>(actual_prompt_lines - 1);
+        prompt_lines_changed = actual_prompt_lines != left_prompt_layout.line_breaks.size() + 1;
+    }
+    if (left_prompt_changed) {
         s_move(scr, 0, 0);
         size_t start = 0;
         for (const size_t line_break : left_prompt_layout.line_breaks) {
@@ -748,6 +816,12 @@ static void s_update(screen_t *scr, const wcstring &left_prompt, const wcstring
         s_write_str(scr, left_prompt.c_str() + start);
         scr->actual_left_prompt = left_prompt;
         scr->actual.cursor.x = static_cast<int>(left_prompt_width);
+        if (prompt_lines_changed) {
+            // Nothing on screen is where we think it is anymore.
+            scr->actual.resize(0);
+            need_clear_lines = true;
+            need_clear_screen = true;
+        }
     }
 
     // Output all lines.
@@ -765,6 +839,19 @@ static void s_update(screen_t *scr, const wcstring &left_prompt, const wcstring
             !(scr->desired.cursor.x == 0 &&
               scr->desired.cursor.y == static_cast<int>(scr->desired.line_count()));
 
+        // If the line is what we output before, and nothing forces us to clear it, there is nothing
+        // to do. This is most lines of a big command line or pager, so don't look at each
+        // character. Note that if this line soft wraps and the next line changes, we need to
+        // rewrite its end to keep it wrapping.
+        if (o_line == s_line && !should_clear_screen_this_line && !need_clear_lines &&
+            right_prompt_width >= scr->last_right_prompt_width &&
+            !(i == 0 && (left_prompt_changed || right_prompt != scr->actual_right_prompt)) &&
+            !(o_line.is_soft_wrapped && i + 1 < scr->desired.line_count() &&
+              (i + 1 >= scr->actual.line_count() ||
+               !(scr->desired.line(i + 1) == scr->actual.line(i + 1))))) {
+            continue;
+        }
+
         // skip_remaining is how many columns are unchanged on this line.
         // Note that skip_remaining is a width, not a character count.
         size_t skip_remaining = start_pos;
@@ -875,8 +962,14 @@ static void s_update(screen_t *scr, const wcstring &left_prompt, const wcstring
             s_write_mbs(scr, clr_eol);
         }
 
-        // Output any rprompt if this is the first line.
-        if (i == 0 && right_prompt_width > 0) {  //!OCLINT(Use early exit/continue)
+        // Output any rprompt if this is the first line, unless it is still there: we did not clear
+        // any of the line, and did not write over where it starts.
+        const bool right_prompt_intact =
+            right_prompt == scr->actual_right_prompt && !left_prompt_changed &&
+            !has_cleared_screen && !has_cleared_line && !clear_remainder &&
+            current_width <= static_cast<int>(screen_width - right_prompt_width);
+        if (i == 0 && right_prompt_width > 0 &&
+            !right_prompt_intact) {  //!OCLINT(Use early exit/continue)
             s_move(scr, static_cast<int>(screen_width - right_prompt_width), static_cast<int>(i));
             set_color(highlight_spec_t{});
             s_write_str(scr, right_prompt.c_str());
@@ -912,11 +1005,11 @@ static void s_update(screen_t *scr, const wcstring &left_prompt, const wcstring
     // We have now synced our actual screen against our desired screen. Note that this is a big
     // assignment!
     scr->actual = scr->desired;
+    scr->actual_right_prompt = right_prompt;
     scr->last_right_prompt_width = right_prompt_width;
 }
 
-/// Returns true if we are using a dumb terminal.
-static bool is_dumb() {
+bool screen_is_dumb() {
     if (!cur_term) return true;
     return !cursor_up || !cursor_down || !cursor_left || !cursor_right;
 }
@@ -967,7 +1060,21 @@ static screen_layout_t compute_layout(screen_t *s, size_t screen_width,
     size_t left_prompt_width = left_prompt_layout.last_line_width;
     size_t right_prompt_width = right_prompt_layout.last_line_width;
 
-    if (left_prompt_width + right_prompt_width > screen_width) {
+    // If the right prompt should be truncated rather than hidden, truncate it to fit in the given
+    // width, leaving a space before it. Don't bother if only the ellipsis would be left.
+    auto truncate_right_prompt = [&](size_t available_width) {
+        if (g_right_prompt_overflow != right_prompt_overflow_t::truncate || available_width < 3) {
+            return false;
+        }
+        right_prompt_width = layout_cache_t::shared
+                                 .calc_prompt_layout(right_untrunc_prompt, &right_prompt,
+                                                     available_width - 1)
+                                 .last_line_width;
+        return true;
+    };
+
+    if (left_prompt_width + right_prompt_width > screen_width &&
+        !truncate_right_prompt(screen_width - left_prompt_width)) {
         // Nix right_prompt.
         right_prompt = L"";
         right_prompt_width = 0;
@@ -984,7 +1091,7 @@ static screen_layout_t compute_layout(screen_t *s, size_t screen_width,
             multiline = true;
             break;
         } else {
-            first_line_width += fish_wcwidth_min_0(c);
+            first_line_width += commandline_char_width(c);
         }
     }
     const size_t first_command_line_width = first_line_width;
@@ -999,7 +1106,7 @@ static screen_layout_t compute_layout(screen_t *s, size_t screen_width,
         autosuggest_truncated_widths.reserve(1 + autosuggestion_str.size());
         for (size_t i = 0; autosuggestion[i] != L'\0'; i++) {
             autosuggest_truncated_widths.push_back(autosuggest_total_width);
-            autosuggest_total_width += fish_wcwidth_min_0(autosuggestion[i]);
+            autosuggest_total_width += commandline_char_width(autosuggestion[i]);
         }
     }
 
@@ -1010,6 +1117,9 @@ static screen_layout_t compute_layout(screen_t *s, size_t screen_width,
     // 2. Left prompt visible, right prompt visible, command line visible, autosuggestion truncated
     // (possibly to zero).
     //
+    // 2b. Left prompt visible, right prompt truncated, command line visible, autosuggestion hidden,
+    // if $fish_right_prompt_overflow is "truncate".
+    //
     // 3. Left prompt visible, right prompt hidden, command line visible, autosuggestion visible
     //
     // 4. Left prompt visible, right prompt hidden, command line visible, autosuggestion truncated
@@ -1017,6 +1127,9 @@ static screen_layout_t compute_layout(screen_t *s, size_t screen_width,
     // 5. Newline separator (left prompt visible, right prompt hidden, command line visible,
     // autosuggestion visible).
     //
+    // If $fish_right_prompt_overflow is "newline", a right prompt hidden by layouts #3 to #5 is
+    // shown on a line of its own instead, above the last line of the left prompt.
+    //
     // A remark about layout #4: if we've pushed the command line to a new line, why can't we draw
     // the right prompt? The issue is resizing: if you resize the window smaller, then the right
     // prompt will wrap to the next line. This means that we can't go back to the line that we were
@@ -1059,6 +1172,16 @@ static screen_layout_t compute_layout(screen_t *s, size_t screen_width,
         }
     }
 
+    // Case 2b
+    if (!done && right_prompt_width > 0 &&
+        left_prompt_width + first_command_line_width < screen_width &&
+        truncate_right_prompt(screen_width - (left_prompt_width + first_command_line_width))) {
+        result.left_prompt = left_prompt;
+        result.left_prompt_space = left_prompt_width;
+        result.right_prompt = right_prompt;
+        done = true;
+    }
+
     // Case 3
     if (!done) {
         calculated_width = left_prompt_width + first_command_line_width + autosuggest_total_width;
@@ -1097,14 +1220,31 @@ static screen_layout_t compute_layout(screen_t *s, size_t screen_width,
         result.autosuggestion = autosuggestion;
     }
 
+    if (g_right_prompt_overflow == right_prompt_overflow_t::own_line &&
+        result.right_prompt.empty() && screen_width > 1) {
+        // Right-align it, but leave the last column free so the line does not wrap.
+        wcstring rprompt_line;
+        size_t rprompt_line_width =
+            layout_cache_t::shared
+                .calc_prompt_layout(right_untrunc_prompt, &rprompt_line, screen_width - 1)
+                .last_line_width;
+        if (rprompt_line_width > 0) {
+            rprompt_line.insert(0, screen_width - 1 - rprompt_line_width, L' ');
+            rprompt_line.push_back(L'\n');
+            size_t last_line_start = result.left_prompt.find_last_of(L"\n\f");
+            last_line_start = last_line_start == wcstring::npos ? 0 : last_line_start + 1;
+            result.left_prompt.insert(last_line_start, rprompt_line);
+        }
+    }
+
     return result;
 }
 
 void s_write(screen_t *s, const wcstring &left_prompt, const wcstring &right_prompt,
              const wcstring &commandline, size_t explicit_len,
              const std::vector<highlight_spec_t> &colors, const std::vector<int> &indent,
-             size_t cursor_pos, pager_t &pager, page_rendering_t &page_rendering,
-             bool cursor_is_within_pager) {
+             size_t cursor_pos, const wcstring &message, pager_t &pager,
+             page_rendering_t &page_rendering, bool cursor_is_within_pager) {
     termsize_t curr_termsize = termsize_last();
     int screen_width = curr_termsize.width;
     static relaxed_atomic_t<uint32_t> s_repaints{0};
@@ -1117,13 +1257,24 @@ void s_write(screen_t *s, const wcstring &left_prompt, const wcstring &right_pro
 
     // If we are using a dumb terminal, don't try any fancy stuff, just print out the text.
     // right_prompt not supported.
-    if (is_dumb()) {
+    if (screen_is_dumb()) {
+        wcstring shown_command_line;
+        for (wchar_t c : explicit_command_line) {
+            if (is_shown_as_escape(c)) {
+                shown_command_line += invisible_char_escape(c);
+            } else {
+                shown_command_line.push_back(c);
+            }
+        }
         const std::string prompt_narrow = wcs2string(left_prompt);
-        const std::string command_line_narrow = wcs2string(explicit_command_line);
+        const std::string command_line_narrow = wcs2string(shown_command_line);
 
-        write_loop(STDOUT_FILENO, "\r", 1);
-        write_loop(STDOUT_FILENO, prompt_narrow.c_str(), prompt_narrow.size());
-        write_loop(STDOUT_FILENO, command_line_narrow.c_str(), command_line_narrow.size());
+        char cr = '\r';
+        struct iovec iov[] = {{&cr, 1},
+                              {const_cast<char *>(prompt_narrow.data()), prompt_narrow.size()},
+                              {const_cast<char *>(command_line_narrow.data()),
+                               command_line_narrow.size()}};
+        writev_loop(STDOUT_FILENO, iov, sizeof iov / sizeof *iov);
 
         return;
     }
@@ -1162,13 +1313,27 @@ void s_write(screen_t *s, const wcstring &left_prompt, const wcstring &right_pro
     // Output the command line.
     size_t i;
     for (i = 0; i < effective_commandline.size(); i++) {
+        const wchar_t c = effective_commandline.at(i);
+        // Characters which would be invisible or move the cursor are shown as escapes, which are
+        // kept on one line if possible.
+        const wcstring escape = is_shown_as_escape(c) ? invisible_char_escape(c) : wcstring{};
+        const int width = escape.empty() ? fish_wcwidth_min_0(c) : static_cast<int>(escape.size());
+
         // Grab the current cursor's x,y position if this character matches the cursor's offset.
+        // A character which does not fit on this line is on the next one, and so is the cursor.
+        if (c != L'\n' && c != L'\r') s_desired_wrap_for_char(s, std::min(width, screen_width));
         if (!cursor_is_within_pager && i == cursor_pos) {
             cursor_arr = s->desired.cursor;
         }
-        s_desired_append_char(s, effective_commandline.at(i), colors[i], indent[i],
-                              first_line_prompt_space,
-                              fish_wcwidth_min_0(effective_commandline.at(i)));
+        if (escape.empty()) {
+            s_desired_append_char(s, c, colors[i], indent[i], first_line_prompt_space, width);
+        } else {
+            highlight_spec_t color = colors[i];
+            color.foreground = highlight_role_t::escape;
+            for (wchar_t ec : escape) {
+                s_desired_append_char(s, ec, color, indent[i], first_line_prompt_space, 1);
+            }
+        }
     }
 
     // Cursor may have been at the end too.
@@ -1180,6 +1345,37 @@ void s_write(screen_t *s, const wcstring &left_prompt, const wcstring &right_pro
     // above.
     s->desired.cursor = cursor_arr;
 
+    // Show the first line of the message below the command line, truncated to the screen width.
+    size_t message_lines = 0;
+    if (!message.empty()) {
+        wcstring shown;
+        for (wchar_t c : message.substr(0, message.find(L'\n'))) {
+            if (is_shown_as_escape(c)) {
+                shown += invisible_char_escape(c);
+            } else {
+                shown.push_back(c);
+            }
+        }
+        int width = 0;
+        size_t len = 0;
+        for (; len < shown.size(); len++) {
+            int w = fish_wcwidth_min_0(shown.at(len));
+            if (width + w > screen_width) break;
+            width += w;
+        }
+        if (len < shown.size()) {
+            // Make room for the ellipsis.
+            while (len > 0 && width + 1 > screen_width) {
+                width -= fish_wcwidth_min_0(shown.at(--len));
+            }
+            shown.resize(len);
+            shown.push_back(get_ellipsis_char());
+        }
+        line_t &line = s->desired.add_line();
+        for (wchar_t c : shown) line.append(c, highlight_spec_t{});
+        message_lines = 1;
+    }
+
     if (cursor_is_within_pager) {
         s->desired.cursor.x = static_cast<int>(cursor_pos);
         s->desired.cursor.y = static_cast<int>(s->desired.line_count());
@@ -1187,7 +1383,7 @@ void s_write(screen_t *s, const wcstring &left_prompt, const wcstring &right_pro
 
     // Re-render our completions page if necessary. Limit the term size of the pager to the true
     // term size, minus the number of lines consumed by our string.
-    int full_line_count = cursor_arr.y + 1;
+    int full_line_count = cursor_arr.y + 1 + static_cast<int>(message_lines);
     pager.set_term_size(termsize_t{std::max(1, curr_termsize.width),
                                    std::max(1, curr_termsize.height - full_line_count)});
     pager.update_rendering(&page_rendering);
@@ -1216,11 +1412,13 @@ void s_reset_line(screen_t *s, bool repaint_prompt) {
         s->actual.cursor.y += (prompt_line_count - 1);
         s->actual_left_prompt.clear();
     }
+    s->actual_right_prompt.clear();
     s->actual.resize(0);
     s->need_clear_lines = true;
 
-    // This should prevent resetting the cursor position during the next repaint.
-    write_loop(STDOUT_FILENO, "\r", 1);
+    // This should prevent resetting the cursor position during the next repaint. Go through the
+    // outputter, so this stays in order with anything it has buffered.
+    s->outp().push_back('\r');
     s->actual.cursor.x = 0;
 
     fstat(STDOUT_FILENO, &s->prev_buff_1);
@@ -1233,6 +1431,7 @@ void s_reset_abandoning_line(screen_t *s, int screen_width) {
     s->actual.cursor.y = 0;
     s->actual.resize(0);
     s->actual_left_prompt.clear();
+    s->actual_right_prompt.clear();
     s->need_clear_lines = true;
 
     // Do the PROMPT_SP hack.
@@ -1295,19 +1494,26 @@ void s_reset_abandoning_line(screen_t *s, int screen_width) {
     // line above your prompt. This doesn't make a difference in normal usage, but copying and
     // pasting your terminal log becomes a pain. This commit clears that line, making it an
     // actual empty line.
-    if (!is_dumb() && clr_eol) {
+    if (!screen_is_dumb() && clr_eol) {
         abandon_line_string.append(str2wcstring(clr_eol));
     }
 
     const std::string narrow_abandon_line_string = wcs2string(abandon_line_string);
-    write_loop(STDOUT_FILENO, narrow_abandon_line_string.c_str(),
-               narrow_abandon_line_string.size());
+    s->outp().writestr(narrow_abandon_line_string.data(), narrow_abandon_line_string.size());
     s->actual.cursor.x = 0;
 
     fstat(STDOUT_FILENO, &s->prev_buff_1);
     fstat(STDERR_FILENO, &s->prev_buff_2);
 }
 
+void s_note_prompt_written(screen_t *s, const wcstring &prompt) {
+    assert(s->actual_left_prompt.empty() && "Prompt should have been abandoned");
+    s->actual_left_prompt = prompt;
+    s->actual.cursor.x =
+        static_cast<int>(layout_cache_t::shared.calc_prompt_layout(prompt).last_line_width);
+    s->actual.cursor.y = 0;
+}
+
 void screen_force_clear_to_end() {
     if (clr_eos) {
         writembs(outputter_t::stdoutput(), clr_eos);
@@ -1322,5 +1528,5 @@ bool screen_t::cursor_is_wrapped_to_own_line() const {
     // should return false.
     // Don't consider dumb terminals to have wrapping for the purposes of this function.
     return actual.cursor.x == 0 && static_cast<size_t>(actual.cursor.y) == actual.line_count() &&
-           !is_dumb();
+           !screen_is_dumb();
 }
@@ -25,6 +25,7 @@
 
 #include "common.h"
 #include "highlight.h"
+#include "maybe.h"
 #include "wcstringutil.h"
 
 class pager_t;
@@ -35,6 +36,10 @@ struct line_t {
     struct highlighted_char_t {
         highlight_spec_t highlight;
         wchar_t character;
+
+        bool operator==(const highlighted_char_t &rhs) const {
+            return character == rhs.character && highlight == rhs.highlight;
+        }
     };
 
     /// A pair of a character, and the color with which to draw it.
@@ -75,6 +80,11 @@ struct line_t {
     /// This follows fish_wcswidth() semantics, except that characters whose width would be -1 are
     /// treated as 0.
     int wcswidth_min_0(size_t max = std::numeric_limits<size_t>::max()) const;
+
+    bool operator==(const line_t &rhs) const {
+        return text == rhs.text && is_soft_wrapped == rhs.is_soft_wrapped &&
+               indentation == rhs.indentation;
+    }
 };
 
 /// A class representing screen contents.
@@ -142,6 +152,8 @@ class screen_t {
     screen_data_t actual{};
     /// A string containing the prompt which was last printed to the screen.
     wcstring actual_left_prompt{};
+    /// The right prompt which was last printed to the screen, or empty if it may be gone.
+    wcstring actual_right_prompt{};
     /// Last right prompt width.
     size_t last_right_prompt_width{0};
     /// If we support soft wrapping, we can output to this location without any cursor motion.
@@ -185,14 +197,43 @@ class screen_t {
 /// \param colors the colors to use for the comand line
 /// \param indent the indent to use for the command line
 /// \param cursor_pos where the cursor is
+/// \param message a line to show below the command line, or empty
 /// \param pager the pager to render below the command line
 /// \param page_rendering to cache the current pager view
 /// \param cursor_is_within_pager whether the position is within the pager line (first line)
 void s_write(screen_t *s, const wcstring &left_prompt, const wcstring &right_prompt,
              const wcstring &commandline, size_t explicit_len,
              const std::vector<highlight_spec_t> &colors, const std::vector<int> &indent,
-             size_t cursor_pos, pager_t &pager, page_rendering_t &page_rendering,
-             bool cursor_is_within_pager);
+             size_t cursor_pos, const wcstring &message, pager_t &pager,
+             page_rendering_t &page_rendering, bool cursor_is_within_pager);
+
+/// What to do with the right prompt when it does not fit next to the left prompt and the command
+/// line. This is set from $fish_right_prompt_overflow.
+enum class right_prompt_overflow_t {
+    hide,      // don't show it until there is room again
+    truncate,  // truncate it from the left, with an ellipsis
+    own_line,  // show it on its own line, above the command line ("newline")
+};
+extern right_prompt_overflow_t g_right_prompt_overflow;
+
+/// What to do when a double-width character does not fit in the last column of a line. This is set
+/// from $fish_wide_char_wrap. If it is none, padding is used when the terminal wraps lines itself
+/// and has the eat_newline_glitch, because then it reliably continues on the next line.
+enum class wide_char_wrap_t {
+    wrap,  // leave the last column empty and move to the next line
+    pad,   // fill the last column with a space, so the terminal wraps and keeps the lines joined
+};
+extern maybe_t<wide_char_wrap_t> g_wide_char_wrap;
+
+/// How characters in the command line which would be invisible or move the cursor, like control
+/// characters, zero-width spaces and bidi controls, are shown. This is set from
+/// $fish_invisible_style.
+enum class invisible_style_t {
+    caret,   // ^A for control characters, <U+200B> for the others
+    escape,  // as fish escapes, like \ca and \u200b
+    none,    // write them as they are
+};
+extern invisible_style_t g_invisible_style;
 
 /// Resets the screen buffer's internal knowledge about the contents of the screen,
 /// optionally repainting the prompt as well.
@@ -205,12 +246,21 @@ void s_reset_line(screen_t *s, bool repaint_prompt = false);
 /// The screen width must be provided for the PROMPT_SP hack.
 void s_reset_abandoning_line(screen_t *s, int screen_width);
 
+/// Note that \p prompt was written at the start of the current line behind the screen's back, after
+/// s_reset_abandoning_line(), so the next update draws over it. \p prompt must already be truncated
+/// to the screen width.
+void s_note_prompt_written(screen_t *s, const wcstring &prompt);
+
 /// Stat stdout and stderr and save result as the current timestamp.
 void s_save_status(screen_t *s);
 
 /// Issues an immediate clr_eos.
 void screen_force_clear_to_end();
 
+/// Returns true if we are using a dumb terminal, which can't move the cursor. Only the prompt and
+/// the command line are written to it.
+bool screen_is_dumb();
+
 // Information about the layout of a prompt.
 struct prompt_layout_t {
     std::vector<size_t> line_breaks;  // line breaks when rendering the prompt
@@ -0,0 +1,10 @@
+// The SHA-256 hash function, for recognizing files which were trusted before.
+#ifndef FISH_SHA256_H
+#define FISH_SHA256_H
+
+#include <string>
+
+/// \return the SHA-256 hash of \p data, as 64 lowercase hex digits.
+std::string sha256_hex(const std::string &data);
+
+#endif
@@ -8,10 +8,17 @@
 #include <siginfo.h>
 #endif
 #include <pthread.h>
+#include <unistd.h>
+#include <wctype.h>
+
+#include <algorithm>
+#include <mutex>
+#include <vector>
 
 #include "common.h"
 #include "event.h"
 #include "fallback.h"  // IWYU pragma: keep
+#include "fds.h"
 #include "parser.h"
 #include "proc.h"
 #include "reader.h"
@@ -130,7 +137,7 @@ static const struct lookup_entry signal_table[] = {
     {SIGINFO, L"SIGINFO", N_(L"Information request")},
 #endif
 #ifdef SIGSTKFLT
-    {SIGSTKFLT, L"SISTKFLT", N_(L"Stack fault")},
+    {SIGSTKFLT, L"SIGSTKFLT", N_(L"Stack fault")},
 #endif
 #ifdef SIGEMT
     {SIGEMT, L"SIGEMT", N_(L"Emulator trap")},
@@ -150,6 +157,64 @@ static int match_signal_name(const wchar_t *canonical, const wchar_t *name) {
     return wcscasecmp(canonical + 3, name) == 0;
 }
 
+bool is_realtime_signal(int sig) {
+#ifdef SIGRTMIN
+    return sig >= SIGRTMIN && sig <= SIGRTMAX;
+#else
+    UNUSED(sig);
+    return false;
+#endif
+}
+
+/// \return the names of the real-time signals, indexed from SIGRTMIN. Like in other shells and
+/// kill, the first half is counted from SIGRTMIN and the second half back from SIGRTMAX.
+static const wcstring_list_t &realtime_signal_names() {
+    static const wcstring_list_t names = [] {
+        wcstring_list_t result;
+#ifdef SIGRTMIN
+        int count = SIGRTMAX - SIGRTMIN + 1;
+        for (int i = 0; i < count; i++) {
+            if (i == 0) {
+                result.push_back(L"SIGRTMIN");
+            } else if (i == count - 1) {
+                result.push_back(L"SIGRTMAX");
+            } else if (i <= (count - 1) / 2) {
+                result.push_back(format_string(L"SIGRTMIN+%d", i));
+            } else {
+                result.push_back(format_string(L"SIGRTMAX-%d", count - 1 - i));
+            }
+        }
+#endif
+        return result;
+    }();
+    return names;
+}
+
+/// \return the real-time signal \p name (without "SIG") refers to, like "RTMIN+3" or "rtmax", or
+/// -1 if it does not name one.
+static int realtime_signal_from_name(const wchar_t *name) {
+#ifdef SIGRTMIN
+    int base;
+    if (wcsncasecmp(name, L"rtmin", 5) == 0) {
+        base = SIGRTMIN;
+    } else if (wcsncasecmp(name, L"rtmax", 5) == 0) {
+        base = SIGRTMAX;
+    } else {
+        return -1;
+    }
+    name += 5;
+    if (*name == L'\0') return base;
+    if ((*name != L'+' && *name != L'-') || !iswdigit(name[1])) return -1;
+    int offset = fish_wcstoi(name + 1);
+    if (errno) return -1;
+    int sig = *name == L'+' ? base + offset : base - offset;
+    return is_realtime_signal(sig) ? sig : -1;
+#else
+    UNUSED(name);
+    return -1;
+#endif
+}
+
 int wcs2sig(const wchar_t *str) {
     for (const auto &data : signal_table) {
         if (match_signal_name(data.name, str)) {
@@ -157,6 +222,10 @@ int wcs2sig(const wchar_t *str) {
         }
     }
 
+    const wchar_t *name = wcsncasecmp(str, L"sig", 3) == 0 ? str + 3 : str;
+    int sig = realtime_signal_from_name(name);
+    if (sig != -1) return sig;
+
     int res = fish_wcstoi(str);
     if (errno || res < 0) return -1;
     return res;
@@ -169,9 +238,25 @@ const wchar_t *sig2wcs(int sig) {
         }
     }
 
+#ifdef SIGRTMIN
+    if (is_realtime_signal(sig)) return realtime_signal_names().at(sig - SIGRTMIN).c_str();
+#endif
     return _(L"Unknown");
 }
 
+std::vector<int> signal_list() {
+    std::vector<int> result;
+    for (const auto &data : signal_table) {
+        result.push_back(data.signal);
+    }
+#ifdef SIGRTMIN
+    for (int sig = SIGRTMIN; sig <= SIGRTMAX; sig++) result.push_back(sig);
+#endif
+    std::sort(result.begin(), result.end());
+    result.erase(std::unique(result.begin(), result.end()), result.end());
+    return result;
+}
+
 const wchar_t *signal_get_desc(int sig) {
     for (const auto &data : signal_table) {
         if (data.signal == sig) {
@@ -179,6 +264,7 @@ const wchar_t *signal_get_desc(int sig) {
         }
     }
 
+    if (is_realtime_signal(sig)) return _(L"Real-time signal");
     return _(L"Unknown");
 }
 
@@ -212,10 +298,43 @@ static volatile relaxed_atomic_t<uint32_t> s_sigio_count{0};
 
 uint32_t signal_get_sigio_count() { return s_sigio_count; }
 
+/// The ends of the pipe written to by the signal handler, or -1 before it is created.
+/// These are never closed.
+static relaxed_atomic_t<int> s_wakeup_read_fd{-1};
+static relaxed_atomic_t<int> s_wakeup_write_fd{-1};
+
+/// Create the wakeup pipe, if it has not been created yet.
+static void create_wakeup_pipe() {
+    static std::once_flag s_once;
+    std::call_once(s_once, [] {
+        auto pipes = make_autoclose_pipes();
+        if (!pipes) return;
+        // Both ends are nonblocking: the signal handler must not block on a full pipe, and
+        // clearing it reads until it is empty.
+        if (make_fd_nonblocking(pipes->read.fd()) || make_fd_nonblocking(pipes->write.fd())) {
+            return;
+        }
+        fd_set_purpose(pipes->read.fd(), N_(L"signal wakeup pipe"));
+        fd_set_purpose(pipes->write.fd(), N_(L"signal wakeup pipe"));
+        s_wakeup_read_fd = pipes->read.acquire();
+        s_wakeup_write_fd = pipes->write.acquire();
+    });
+}
+
+int signal_get_wakeup_fd() { return s_wakeup_read_fd; }
+
+void signal_clear_wakeup() {
+    int fd = s_wakeup_read_fd;
+    if (fd < 0) return;
+    char buf[64];
+    while (read(fd, buf, sizeof buf) > 0) {
+        // Keep reading until the pipe is empty.
+    }
+}
+
 /// The single signal handler. By centralizing signal handling we ensure that we can never install
 /// the "wrong" signal handler (see #5969).
 static void fish_signal_handler(int sig, siginfo_t *info, void *context) {
-    UNUSED(info);
     UNUSED(context);
 
     // Ensure we preserve errno.
@@ -230,7 +349,7 @@ static void fish_signal_handler(int sig, siginfo_t *info, void *context) {
     // Check if fish script cares about this.
     const bool observed = event_is_signal_observed(sig);
     if (observed) {
-        event_enqueue_signal(sig);
+        event_enqueue_signal(sig, info);
     }
 
     // Do some signal-specific stuff.
@@ -285,6 +404,13 @@ static void fish_signal_handler(int sig, siginfo_t *info, void *context) {
             break;
 #endif
     }
+
+    // Wake up the main loop, so it handles the signal. If the pipe is full, it will wake up anyway.
+    int wakeup_fd = s_wakeup_write_fd;
+    if (wakeup_fd >= 0) {
+        const char c = 0;
+        ignore_result(write(wakeup_fd, &c, 1));
+    }
     errno = saved_errno;
 }
 
@@ -302,6 +428,11 @@ void signal_reset_handlers() {
         }
         sigaction(data.signal, &act, nullptr);
     }
+#ifdef SIGRTMIN
+    for (int sig = SIGRTMIN; sig <= SIGRTMAX; sig++) {
+        sigaction(sig, &act, nullptr);
+    }
+#endif
 }
 
 static void set_interactive_handlers() {
@@ -347,6 +478,8 @@ static void set_interactive_handlers() {
 
 /// Sets up appropriate signal handlers.
 void signal_set_handlers(bool interactive) {
+    create_wakeup_pipe();
+
     struct sigaction act;
     act.sa_flags = 0;
     sigemptyset(&act.sa_mask);
@@ -418,6 +551,13 @@ void get_signals_with_handlers(sigset_t *set) {
         if (data.signal == SIGHUP && act.sa_handler == SIG_IGN) continue;
         if (act.sa_handler != SIG_DFL) sigaddset(set, data.signal);
     }
+#ifdef SIGRTMIN
+    for (int sig = SIGRTMIN; sig <= SIGRTMAX; sig++) {
+        struct sigaction act = {};
+        sigaction(sig, nullptr, &act);
+        if (act.sa_handler != SIG_DFL) sigaddset(set, sig);
+    }
+#endif
 }
 
 /// Ensure we did not inherit any blocked signals. See issue #3964.
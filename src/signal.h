@@ -4,12 +4,21 @@
 
 #include <signal.h>
 
+#include <vector>
+
 /// Get the integer signal value representing the specified signal, or -1 of no signal was found.
 int wcs2sig(const wchar_t *str);
 
 /// Get string representation of a signal.
 const wchar_t *sig2wcs(int sig);
 
+/// \return whether \p sig is one of the real-time signals, from SIGRTMIN to SIGRTMAX.
+bool is_realtime_signal(int sig);
+
+/// \return all signals fish knows about, in numerical order. Aliases like SIGIOT are only listed
+/// once, under the name sig2wcs() returns.
+std::vector<int> signal_list();
+
 /// Returns a description of the specified signal.
 const wchar_t *signal_get_desc(int sig);
 
@@ -46,6 +55,15 @@ void signal_clear_cancel();
 /// This is used by universal variables, and is a simple unsigned counter which wraps to 0.
 uint32_t signal_get_sigio_count();
 
+/// \return an fd which becomes readable when fish receives a signal, or -1 if there is none.
+/// The main loop waits on this, and runs the handlers for the signals when it is readable, so that
+/// they run at a well-defined point instead of from the signal handler.
+int signal_get_wakeup_fd();
+
+/// Make the wakeup fd unreadable again. Call this before handling the signals, so that any that
+/// arrive while they are handled wake up the main loop again.
+void signal_clear_wakeup();
+
 enum class topic_t : uint8_t;
 /// A sigint_detector_t can be used to check if a SIGINT (or SIGHUP) has been delivered.
 class sigchecker_t {
@@ -2,8 +2,20 @@
 
 #include "termsize.h"
 
+#include <sys/select.h>
+#include <termios.h>
+#include <unistd.h>
+
+#include <chrono>
+#include <cstdio>
+#include <cstdlib>
+#include <cstring>
+#include <string>
+
+#include "env.h"
 #include "maybe.h"
 #include "parser.h"
+#include "proc.h"
 #include "wcstringutil.h"
 #include "wutil.h"
 
@@ -15,17 +27,97 @@ static maybe_t<termsize_t> read_termsize_from_tty() {
     maybe_t<termsize_t> result{};
 #ifdef HAVE_WINSIZE
     struct winsize winsize = {0, 0, 0, 0};
-    if (ioctl(STDOUT_FILENO, TIOCGWINSZ, &winsize) >= 0) {
+    // Serial lines and some terminals on illumos and Haiku report a size of 0x0, which means that
+    // nobody told the tty.
+    if (ioctl(STDOUT_FILENO, TIOCGWINSZ, &winsize) >= 0 && winsize.ws_col > 0 &&
+        winsize.ws_row > 0) {
         result = termsize_t{winsize.ws_col, winsize.ws_row};
     }
 #endif
     return result;
 }
 
+/// Read the terminal's answer to a cursor position request, which looks like \e[ROWS;COLSR.
+/// \return the position as a termsize, or none if the terminal did not answer in time.
+static maybe_t<termsize_t> read_cursor_position_report() {
+    // Not all terminals answer, so don't wait long. Serial lines need a few milliseconds.
+    const auto deadline = std::chrono::steady_clock::now() + std::chrono::milliseconds(250);
+    std::string answer;
+    while (answer.size() < 32) {
+        auto remaining = std::chrono::duration_cast<std::chrono::microseconds>(
+            deadline - std::chrono::steady_clock::now());
+        if (remaining.count() <= 0) break;
+        fd_set fds;
+        FD_ZERO(&fds);
+        FD_SET(STDIN_FILENO, &fds);
+        struct timeval tv = {static_cast<time_t>(remaining.count() / 1000000),
+                             static_cast<suseconds_t>(remaining.count() % 1000000)};
+        int res = select(STDIN_FILENO + 1, &fds, nullptr, nullptr, &tv);
+        if (res < 0 && errno == EINTR) continue;
+        if (res <= 0) break;
+        char c;
+        if (read(STDIN_FILENO, &c, 1) != 1) break;
+        answer.push_back(c);
+        if (c == 'R') break;
+    }
+
+    // Anything typed before the answer is lost.
+    size_t start = answer.rfind("\x1b[");
+    int rows, cols;
+    char end;
+    if (start == std::string::npos ||
+        std::sscanf(answer.c_str() + start, "\x1b[%d;%d%c", &rows, &cols, &end) != 3 ||
+        end != 'R' || rows <= 0 || cols <= 0 || rows > USHRT_MAX || cols > USHRT_MAX) {
+        return none();
+    }
+    return termsize_t{cols, rows};
+}
+
+/// Ask the terminal for its size by moving the cursor to the bottom right corner and requesting its
+/// position, and store the size in the tty so commands see it too.
+/// \return the size, or none if the terminal can't be asked or did not answer.
+static maybe_t<termsize_t> query_termsize_from_terminal(bool *in_tty) {
+    *in_tty = false;
+    // Only interactive shells ask, so scripts don't print the request. The answer only comes back
+    // to us if we own the terminal, and dumb terminals don't answer.
+    const char *term = std::getenv("TERM");
+    if (!is_interactive_session() || !isatty(STDIN_FILENO) || !isatty(STDOUT_FILENO) ||
+        (term && !std::strcmp(term, "dumb")) || tcgetpgrp(STDIN_FILENO) != getpgrp()) {
+        return none();
+    }
+
+    // Read the answer without waiting for a newline and without echoing it.
+    struct termios saved;
+    if (tcgetattr(STDIN_FILENO, &saved) < 0) return none();
+    struct termios raw = saved;
+    raw.c_lflag &= ~(ICANON | ECHO);
+    raw.c_cc[VMIN] = 1;
+    raw.c_cc[VTIME] = 0;
+    if (tcsetattr(STDIN_FILENO, TCSANOW, &raw) < 0) return none();
+
+    // Save the cursor, move it as far as it goes, request its position and restore it.
+    const char request[] = "\x1b" "7" "\x1b[9999;9999H" "\x1b[6n" "\x1b" "8";
+    maybe_t<termsize_t> result{};
+    if (write_loop(STDOUT_FILENO, request, sizeof request - 1) >= 0) {
+        result = read_cursor_position_report();
+    }
+    (void)tcsetattr(STDIN_FILENO, TCSANOW, &saved);
+
+#ifdef HAVE_WINSIZE
+    if (result) {
+        struct winsize winsize = {static_cast<unsigned short>(result->height),
+                                  static_cast<unsigned short>(result->width), 0, 0};
+        *in_tty = ioctl(STDOUT_FILENO, TIOCSWINSZ, &winsize) >= 0;
+    }
+#endif
+    return result;
+}
+
 // static
 termsize_container_t &termsize_container_t::shared() {
     // Heap-allocated to avoid runtime dtor registration.
-    static auto *res = new termsize_container_t(read_termsize_from_tty);
+    static auto *res =
+        new termsize_container_t(read_termsize_from_tty, query_termsize_from_terminal);
     return *res;
 }
 
@@ -64,6 +156,7 @@ termsize_t termsize_container_t::updating(parser_t &parser) {
             // Apply any updates.
             data->last_tty_gen_count = tty_gen_count;
             data->last_from_tty = this->tty_size_reader_();
+            if (!data->last_from_tty) data->last_from_tty = data->last_from_query;
         }
         new_size = data->current();
     }
@@ -76,11 +169,22 @@ termsize_t termsize_container_t::updating(parser_t &parser) {
 void termsize_container_t::set_columns_lines_vars(termsize_t val, parser_t &parser) {
     const bool saved = setting_env_vars_;
     setting_env_vars_ = true;
-    parser.set_var_and_fire(L"COLUMNS", ENV_GLOBAL, to_string(val.width));
-    parser.set_var_and_fire(L"LINES", ENV_GLOBAL, to_string(val.height));
+    env_mode_flags_t mode = ENV_GLOBAL;
+    if (should_export_columns_lines(parser.vars())) mode |= ENV_EXPORT;
+    parser.set_var_and_fire(L"COLUMNS", mode, to_string(val.width));
+    parser.set_var_and_fire(L"LINES", mode, to_string(val.height));
     setting_env_vars_ = saved;
 }
 
+bool termsize_container_t::should_export_columns_lines(const environment_t &vars) const {
+    if (data_.acquire()->size_unknown_to_tty) return true;
+    for (const wchar_t *name : {L"COLUMNS", L"LINES"}) {
+        auto var = vars.get(name, ENV_GLOBAL);
+        if (var && var->exports()) return true;
+    }
+    return false;
+}
+
 /// Convert an environment variable to an int, or return a default value.
 /// The int must be >0 and <USHRT_MAX (from struct winsize).
 static int var_to_int_or(const maybe_t<env_var_t> &var, int def) {
@@ -105,6 +209,13 @@ termsize_t termsize_container_t::initialize(const environment_t &vars) {
     } else {
         data->last_tty_gen_count = s_tty_termsize_gen_count;
         data->last_from_tty = this->tty_size_reader_();
+        if (!data->last_from_tty && this->tty_size_query_) {
+            // Only ask once, as the answer may take a while and typed keys are lost meanwhile.
+            bool in_tty = false;
+            data->last_from_query = this->tty_size_query_(&in_tty);
+            data->size_unknown_to_tty = data->last_from_query && !in_tty;
+            data->last_from_tty = data->last_from_query;
+        }
     }
     return data->current();
 }
@@ -54,10 +54,15 @@ struct termsize_container_t {
     termsize_t updating(parser_t &parser);
 
     /// Initialize our termsize, using the given environment stack.
-    /// This will prefer to use COLUMNS and LINES, but will fall back to the tty size reader.
+    /// This will prefer to use COLUMNS and LINES, but will fall back to the tty size reader, and if
+    /// the tty does not know its size, to asking the terminal.
     /// This does not change any variables in the environment.
     termsize_t initialize(const environment_t &vars);
 
+    /// \return whether COLUMNS and LINES should be exported, because one of them already is in
+    /// \p vars, or because commands can't get the size from the tty.
+    bool should_export_columns_lines(const environment_t &vars) const;
+
     /// Note that a WINCH signal is received.
     /// Naturally this may be called from within a signal handler.
     static void handle_winch();
@@ -75,6 +80,11 @@ struct termsize_container_t {
     /// A function used for accessing the termsize from the tty. This is only exposed for testing.
     using tty_size_reader_func_t = maybe_t<termsize_t> (*)();
 
+    /// A function used for asking the terminal for its size, when the tty does not know it, like on
+    /// serial lines. It sets \p in_tty to whether it could store the size in the tty for commands
+    /// to see. This is only exposed for testing.
+    using tty_size_query_func_t = maybe_t<termsize_t> (*)(bool *in_tty);
+
     struct data_t {
         // The last termsize returned by TIOCGWINSZ, or none if none.
         maybe_t<termsize_t> last_from_tty{};
@@ -82,6 +92,13 @@ struct termsize_container_t {
         // The last termsize seen from the environment (COLUMNS/LINES), or none if none.
         maybe_t<termsize_t> last_from_env{};
 
+        // The termsize the terminal reported when asked, or none if it was not asked or did not
+        // answer. This is used when TIOCGWINSZ fails.
+        maybe_t<termsize_t> last_from_query{};
+
+        // Whether the terminal was asked for its size, but it could not be stored in the tty.
+        bool size_unknown_to_tty{false};
+
         // The last-seen tty-invalidation generation count.
         // Set to a huge value so it's initially stale.
         uint32_t last_tty_gen_count{UINT32_MAX};
@@ -93,8 +110,10 @@ struct termsize_container_t {
         void mark_override_from_env(termsize_t ts);
     };
 
-    // Construct from a reader function.
-    explicit termsize_container_t(tty_size_reader_func_t func) : tty_size_reader_(func) {}
+    // Construct from a reader function, and optionally a function to ask the terminal.
+    explicit termsize_container_t(tty_size_reader_func_t func,
+                                  tty_size_query_func_t query = nullptr)
+        : tty_size_reader_(func), tty_size_query_(query) {}
 
     // Update COLUMNS and LINES in the parser's stack.
     void set_columns_lines_vars(termsize_t val, parser_t &parser);
@@ -106,6 +125,7 @@ struct termsize_container_t {
     // not react to any changes.
     relaxed_atomic_bool_t setting_env_vars_{false};
     const tty_size_reader_func_t tty_size_reader_;
+    const tty_size_query_func_t tty_size_query_;
 
     friend termsize_tester_t;
 };
@@ -0,0 +1,261 @@
+// Color themes: files which set fish's color variables.
+#include "config.h"  // IWYU pragma: keep
+
+#include "theme.h"
+
+#include <errno.h>
+#include <fcntl.h>
+#include <unistd.h>
+
+#include <algorithm>
+#include <cstring>
+
+#include "color.h"
+#include "event.h"
+#include "fds.h"
+#include "output.h"
+#include "parser.h"
+#include "tokenizer.h"
+#include "wcstringutil.h"
+#include "wutil.h"  // IWYU pragma: keep
+
+/// The suffix of theme files.
+static const wchar_t *const theme_suffix = L".theme";
+
+/// The sample command lines which show what a theme looks like, as pieces of text and the
+/// variable giving their color, if any.
+static const struct {
+    const wchar_t *var;
+    const wchar_t *text;
+} sample_pieces[] = {
+    {L"fish_color_command", L"/bright/vixens"},
+    {nullptr, L" "},
+    {L"fish_color_param", L"jump"},
+    {nullptr, L" "},
+    {L"fish_color_end", L"|"},
+    {nullptr, L" "},
+    {L"fish_color_quote", L"\"fowl\""},
+    {nullptr, L" "},
+    {L"fish_color_redirection", L"> quack"},
+    {nullptr, L" "},
+    {L"fish_color_end", L"&"},
+    {nullptr, L" "},
+    {L"fish_color_comment", L"# This is a comment"},
+    {nullptr, L"\n"},
+    {L"fish_color_command", L"echo"},
+    {nullptr, L" "},
+    {L"fish_color_error", L"'Errors are the portal to discovery"},
+    {nullptr, L"\n"},
+    {L"fish_color_command", L"Th"},
+    {L"fish_color_autosuggestion", L"is is an autosuggestion"},
+    {nullptr, L"\n"},
+};
+
+maybe_t<wcstring_list_t> theme_t::get(const wcstring &name) const {
+    for (const auto &color : colors) {
+        if (color.first == name) return color.second;
+    }
+    return none();
+}
+
+bool theme_is_color_variable(const wcstring &name) {
+    return (string_prefixes_string(L"fish_color_", name) ||
+            string_prefixes_string(L"fish_pager_color_", name)) &&
+           valid_var_name(name);
+}
+
+/// \return whether \p value is something a color variable may contain, as set_color takes it.
+static bool is_valid_color_value(const wcstring &value) {
+    static const wchar_t *const modifiers[] = {L"--bold",    L"-o", L"--underline", L"-u",
+                                               L"--italics", L"-i", L"--dim",       L"-d",
+                                               L"--reverse", L"-r"};
+    if (contains(modifiers, value)) return true;
+    wcstring color = value;
+    if (string_prefixes_string(L"--background=", value)) {
+        color = value.substr(std::wcslen(L"--background="));
+    }
+    return !rgb_color_t(color).is_none();
+}
+
+maybe_t<theme_t> theme_parse(const wcstring &name, const wcstring &contents, wcstring *out_err) {
+    theme_t theme;
+    theme.name = name;
+    size_t line_number = 0;
+    for (const wcstring &line : split_string(contents, L'\n')) {
+        line_number++;
+        wcstring trimmed = trim(line);
+        if (trimmed.empty()) continue;
+        if (trimmed.front() == L'#') {
+            const wchar_t *const key = L"preferred_background:";
+            wcstring comment = trim(trimmed.substr(1));
+            if (string_prefixes_string(key, comment)) {
+                theme.preferred_background = trim(comment.substr(std::wcslen(key)));
+            }
+            continue;
+        }
+
+        wcstring_list_t words;
+        tokenizer_t tok(trimmed.c_str(), 0);
+        while (auto token = tok.next()) {
+            if (token->type == token_type_t::comment) break;
+            wcstring word;
+            if (token->type != token_type_t::string ||
+                !unescape_string(tok.text_of(*token), &word, UNESCAPE_DEFAULT)) {
+                *out_err = format_string(_(L"line %lu: expected a variable and its colors, not '%ls'"),
+                                         static_cast<unsigned long>(line_number), trimmed.c_str());
+                return none();
+            }
+            words.push_back(std::move(word));
+        }
+        if (words.empty()) continue;
+
+        if (!theme_is_color_variable(words.front())) {
+            *out_err = format_string(_(L"line %lu: '%ls' is not a color variable"),
+                                     static_cast<unsigned long>(line_number),
+                                     words.front().c_str());
+            return none();
+        }
+        for (size_t i = 1; i < words.size(); i++) {
+            if (!is_valid_color_value(words[i])) {
+                *out_err = format_string(_(L"line %lu: '%ls' is not a color"),
+                                         static_cast<unsigned long>(line_number), words[i].c_str());
+                return none();
+            }
+        }
+        wcstring var_name = std::move(words.front());
+        words.erase(words.begin());
+        theme.colors.emplace_back(std::move(var_name), std::move(words));
+    }
+    return theme;
+}
+
+wcstring_list_t theme_directories(const environment_t &vars) {
+    wcstring_list_t result;
+    for (const wchar_t *dir_var : {L"__fish_config_dir", L"__fish_data_dir"}) {
+        auto dir = vars.get(dir_var);
+        if (!dir.missing_or_empty()) result.push_back(dir->as_string() + L"/themes");
+    }
+    return result;
+}
+
+wcstring_list_t theme_list(const environment_t &vars) {
+    wcstring_list_t result;
+    for (const wcstring &dir_path : theme_directories(vars)) {
+        dir_t dir(dir_path);
+        if (!dir.valid()) continue;
+        wcstring file_name;
+        while (dir.read(file_name)) {
+            if (!string_suffixes_string(theme_suffix, file_name)) continue;
+            wcstring name = file_name.substr(0, file_name.size() - std::wcslen(theme_suffix));
+            if (!name.empty() && !contains(result, name)) result.push_back(std::move(name));
+        }
+    }
+    std::sort(result.begin(), result.end());
+    return result;
+}
+
+maybe_t<theme_t> theme_load(const wcstring &name, const environment_t &vars, wcstring *out_err) {
+    if (name.empty() || name.find(L'/') != wcstring::npos) {
+        *out_err = format_string(_(L"No such theme: '%ls'"), name.c_str());
+        return none();
+    }
+    for (const wcstring &dir : theme_directories(vars)) {
+        wcstring path = dir + L"/" + name + theme_suffix;
+        autoclose_fd_t fd{wopen_cloexec(path, O_RDONLY)};
+        if (!fd.valid()) continue;
+
+        std::string contents;
+        char buff[4096];
+        ssize_t amt;
+        while ((amt = read_loop(fd.fd(), buff, sizeof buff)) > 0) {
+            contents.append(buff, amt);
+        }
+        if (amt < 0) {
+            *out_err = format_string(_(L"Could not read '%ls': %s"), path.c_str(),
+                                     std::strerror(errno));
+            return none();
+        }
+
+        wcstring err;
+        if (auto theme = theme_parse(name, str2wcstring(contents), &err)) return theme;
+        *out_err = format_string(L"%ls: %ls", path.c_str(), err.c_str());
+        return none();
+    }
+    *out_err = format_string(_(L"No such theme: '%ls'"), name.c_str());
+    return none();
+}
+
+void theme_apply(const theme_t &theme, env_stack_t &vars, env_mode_flags_t mode,
+                 std::vector<event_t> *out_events) {
+    for (const auto &color : theme.colors) {
+        vars.set(color.first, mode, color.second, out_events);
+    }
+}
+
+wcstring theme_sample(const theme_t *theme, const environment_t &vars) {
+    outputter_t outp;
+    for (const auto &piece : sample_pieces) {
+        rgb_color_t fg = rgb_color_t::normal(), bg = rgb_color_t::normal();
+        if (piece.var) {
+            maybe_t<wcstring_list_t> values = theme ? theme->get(piece.var) : none();
+            if (!values) {
+                if (auto var = vars.get(piece.var)) values = var->as_list();
+            }
+            if (values) {
+                env_var_t var{piece.var, *values};
+                fg = parse_color(var, false);
+                bg = parse_color(var, true);
+                if (fg.is_none()) fg = rgb_color_t::normal();
+                if (bg.is_none()) bg = rgb_color_t::normal();
+            }
+        }
+        if (piece.text[0] == L'\n') {
+            outp.set_color(rgb_color_t::reset(), rgb_color_t::reset());
+        } else {
+            outp.set_color(fg, bg);
+        }
+        outp.writestr(piece.text);
+    }
+    return str2wcstring(outp.contents());
+}
+
+void theme_use(parser_t &parser, const theme_t &theme, env_mode_flags_t mode) {
+    std::vector<event_t> evts;
+    theme_apply(theme, parser.vars(), mode, &evts);
+    if (mode & ENV_UNIVERSAL) {
+        // Global variables would hide the universal ones we just set.
+        for (const auto &color : theme.colors) {
+            if (parser.vars().get(color.first, ENV_GLOBAL)) {
+                parser.vars().remove(color.first, ENV_GLOBAL, &evts);
+            }
+        }
+    }
+    for (const auto &evt : evts) {
+        event_fire(parser, evt);
+    }
+}
+
+std::vector<std::pair<wcstring, env_var_t>> theme_color_variables(const environment_t &vars) {
+    // fish_color_ sorts before fish_pager_color_, so the result stays sorted.
+    auto result = vars.get_matching(L"fish_color_*");
+    vec_append(result, vars.get_matching(L"fish_pager_color_*"));
+    result.erase(std::remove_if(result.begin(), result.end(),
+                                [](const std::pair<wcstring, env_var_t> &kv) {
+                                    return !theme_is_color_variable(kv.first);
+                                }),
+                 result.end());
+    return result;
+}
+
+wcstring theme_dump(const environment_t &vars) {
+    wcstring result;
+    for (const auto &kv : theme_color_variables(vars)) {
+        result.append(kv.first);
+        for (const wcstring &value : kv.second.as_list()) {
+            result.push_back(L' ');
+            result.append(escape_string(value, ESCAPE_ALL));
+        }
+        result.push_back(L'\n');
+    }
+    return result;
+}
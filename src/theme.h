@@ -0,0 +1,71 @@
+// Color themes: files which set fish's color variables.
+#ifndef FISH_THEME_H
+#define FISH_THEME_H
+
+#include <utility>
+#include <vector>
+
+#include "common.h"
+#include "env.h"
+#include "maybe.h"
+
+class parser_t;
+struct event_t;
+
+/// A parsed theme file.
+///
+/// A theme file has one line per variable, with its name followed by its values, like
+/// `fish_color_command blue --bold`. Comments start with #, and a comment like
+/// `# preferred_background: 002b36` says what background color the theme is meant for.
+struct theme_t {
+    /// The name of the theme, which is its file name without the .theme suffix.
+    wcstring name;
+
+    /// The background color the theme is meant for, or empty if it doesn't say.
+    wcstring preferred_background;
+
+    /// The variables the theme sets and their values, in the order they are in the file.
+    std::vector<std::pair<wcstring, wcstring_list_t>> colors;
+
+    /// \return the values the theme gives the variable \p name, or none() if it does not set it.
+    maybe_t<wcstring_list_t> get(const wcstring &name) const;
+};
+
+/// \return whether \p name is a variable which themes may set.
+bool theme_is_color_variable(const wcstring &name);
+
+/// \return the color variables which are set in \p vars, sorted by name.
+std::vector<std::pair<wcstring, env_var_t>> theme_color_variables(const environment_t &vars);
+
+/// Parse \p contents, the contents of the theme file for the theme \p name. If it is not valid,
+/// return none() and set \p out_err to say why.
+maybe_t<theme_t> theme_parse(const wcstring &name, const wcstring &contents, wcstring *out_err);
+
+/// \return the directories themes are found in, with those of the user first.
+wcstring_list_t theme_directories(const environment_t &vars);
+
+/// \return the names of all themes, sorted. A theme hides those of the same name in later
+/// directories.
+wcstring_list_t theme_list(const environment_t &vars);
+
+/// Load the theme called \p name. If there is no such theme, or it is not valid, return none() and
+/// set \p out_err to say why.
+maybe_t<theme_t> theme_load(const wcstring &name, const environment_t &vars, wcstring *out_err);
+
+/// Set the variables of \p theme in the scope \p mode. Events for the changes are added to
+/// \p out_events rather than fired, so the caller can fire them once every variable is set.
+void theme_apply(const theme_t &theme, env_stack_t &vars, env_mode_flags_t mode,
+                 std::vector<event_t> *out_events);
+
+/// \return sample command lines in the colors of \p theme, or the current colors if it is null,
+/// with the escape sequences which set them. Colors the theme does not set are the current ones.
+wcstring theme_sample(const theme_t *theme, const environment_t &vars);
+
+/// Set the variables of \p theme in the scope \p mode, and fire the events for them once they are
+/// all set. If \p mode is universal, global variables which would hide them are erased.
+void theme_use(parser_t &parser, const theme_t &theme, env_mode_flags_t mode);
+
+/// \return the current values of the color variables, as a theme file.
+wcstring theme_dump(const environment_t &vars);
+
+#endif
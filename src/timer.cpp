@@ -12,6 +12,7 @@
 #include <ctime>
 
 #include "builtin.h"
+#include "cgroup.h"
 #include "common.h"
 #include "exec.h"
 #include "fallback.h"  // IWYU pragma: keep
@@ -61,6 +62,70 @@ static int64_t micros(const std::chrono::duration<D1, D2> &d) {
     return std::chrono::duration_cast<std::chrono::microseconds>(d).count();
 };
 
+enum class tunit {
+    minutes,
+    seconds,
+    milliseconds,
+    microseconds,
+};
+
+static tunit get_unit(int64_t micros) {
+    if (micros > 900 * 1E6) {
+        return tunit::minutes;
+    } else if (micros >= 999995) {  // Move to seconds if we would overflow the %6.2 format.
+        return tunit::seconds;
+    } else if (micros >= 1000) {
+        return tunit::milliseconds;
+    } else {
+        return tunit::microseconds;
+    }
+}
+
+static const char *unit_name(tunit unit) {
+    switch (unit) {
+        case tunit::minutes:
+            return "minutes";
+        case tunit::seconds:
+            return "seconds";
+        case tunit::milliseconds:
+            return "milliseconds";
+        case tunit::microseconds:
+            return "microseconds";
+    }
+    // GCC does not recognize the exhaustive switch above
+    return "";
+}
+
+static const char *unit_short_name(tunit unit) {
+    switch (unit) {
+        case tunit::minutes:
+            return "mins";
+        case tunit::seconds:
+            return "secs";
+        case tunit::milliseconds:
+            return "millis";
+        case tunit::microseconds:
+            return "micros";
+    }
+    // GCC does not recognize the exhaustive switch above
+    return "";
+}
+
+static double convert(int64_t micros, tunit unit) {
+    switch (unit) {
+        case tunit::minutes:
+            return micros / 1.0E6 / 60.0;
+        case tunit::seconds:
+            return micros / 1.0E6;
+        case tunit::milliseconds:
+            return micros / 1.0E3;
+        case tunit::microseconds:
+            return micros / 1.0;
+    }
+    // GCC does not recognize the exhaustive switch above
+    return 0.0;
+}
+
 timer_snapshot_t timer_snapshot_t::take() {
     timer_snapshot_t snapshot;
 
@@ -91,70 +156,6 @@ wcstring timer_snapshot_t::print_delta(timer_snapshot_t t1, timer_snapshot_t t2,
     int64_t net_usr_micros = fish_usr_micros + child_usr_micros;
     int64_t net_wall_micros = micros(t2.wall - t1.wall);
 
-    enum class tunit {
-        minutes,
-        seconds,
-        milliseconds,
-        microseconds,
-    };
-
-    auto get_unit = [](int64_t micros) {
-        if (micros > 900 * 1E6) {
-            return tunit::minutes;
-        } else if (micros >= 999995) {  // Move to seconds if we would overflow the %6.2 format.
-            return tunit::seconds;
-        } else if (micros >= 1000) {
-            return tunit::milliseconds;
-        } else {
-            return tunit::microseconds;
-        }
-    };
-
-    auto unit_name = [](tunit unit) {
-        switch (unit) {
-            case tunit::minutes:
-                return "minutes";
-            case tunit::seconds:
-                return "seconds";
-            case tunit::milliseconds:
-                return "milliseconds";
-            case tunit::microseconds:
-                return "microseconds";
-        }
-        // GCC does not recognize the exhaustive switch above
-        return "";
-    };
-
-    auto unit_short_name = [](tunit unit) {
-        switch (unit) {
-            case tunit::minutes:
-                return "mins";
-            case tunit::seconds:
-                return "secs";
-            case tunit::milliseconds:
-                return "millis";
-            case tunit::microseconds:
-                return "micros";
-        }
-        // GCC does not recognize the exhaustive switch above
-        return "";
-    };
-
-    auto convert = [](int64_t micros, tunit unit) {
-        switch (unit) {
-            case tunit::minutes:
-                return micros / 1.0E6 / 60.0;
-            case tunit::seconds:
-                return micros / 1.0E6;
-            case tunit::milliseconds:
-                return micros / 1.0E3;
-            case tunit::microseconds:
-                return micros / 1.0;
-        }
-        // GCC does not recognize the exhaustive switch above
-        return 0.0;
-    };
-
     auto wall_unit = get_unit(net_wall_micros);
     auto cpu_unit = get_unit(std::max(net_sys_micros, net_usr_micros));
     auto wall_time = convert(net_wall_micros, wall_unit);
@@ -198,9 +199,86 @@ wcstring timer_snapshot_t::print_delta(timer_snapshot_t t1, timer_snapshot_t t2,
     return output;
 };
 
+void timer_record_external_exit(process_t *p, const struct rusage &usage) {
+    p->timing.end = std::chrono::steady_clock::now();
+    p->timing.usr_micros = micros(usage.ru_utime);
+    p->timing.sys_micros = micros(usage.ru_stime);
+}
+
+void timer_record_internal_exit(process_t *p, const timer_snapshot_t &start) {
+    // Internal processes run in fish itself, so they also get the time of any commands they ran.
+    auto end = timer_snapshot_t::take();
+    p->timing.end = end.wall;
+    p->timing.usr_micros = micros(end.cpu_fish.ru_utime) - micros(start.cpu_fish.ru_utime) +
+                           micros(end.cpu_children.ru_utime) -
+                           micros(start.cpu_children.ru_utime);
+    p->timing.sys_micros = micros(end.cpu_fish.ru_stime) - micros(start.cpu_fish.ru_stime) +
+                           micros(end.cpu_children.ru_stime) -
+                           micros(start.cpu_children.ru_stime);
+}
+
+/// \return a short description of the process \p p for the per-stage timing table.
+static wcstring describe_process(const process_t &p) {
+    if (p.type == process_type_t::block_node) {
+        // Use the first line of the block's source.
+        wcstring src = p.internal_block_node->source(p.block_node_source->src);
+        return src.substr(0, src.find(L'\n'));
+    }
+    wcstring result;
+    for (const wchar_t *const *arg = p.get_argv(); arg && *arg; arg++) {
+        if (!result.empty()) result.push_back(L' ');
+        result.append(escape_string(*arg, ESCAPE_ALL | ESCAPE_NO_QUOTED));
+    }
+    return result;
+}
+
+/// \return a table with the timing of each process in \p job.
+static wcstring print_stages(const job_t &job) {
+    auto format_micros = [](int64_t micros) {
+        micros = std::max(int64_t(0), micros);
+        tunit unit = get_unit(micros);
+        return format_string(L"%6.2F %-6s", convert(micros, unit), unit_short_name(unit));
+    };
+
+    wcstring output = L"\nStage  Wall time      usr time       sys time       Command\n";
+    size_t idx = 0;
+    for (const auto &p : job.processes) {
+        idx++;
+        const process_t::timing_t &timing = p->timing;
+        if (timing.end <= timing.start) {
+            // The process did not finish, e.g. because it was stopped.
+            append_format(output, L"%5lu  %-13s  %-13s  %-13s  %ls\n",
+                          static_cast<unsigned long>(idx), "-", "-", "-",
+                          describe_process(*p).c_str());
+            continue;
+        }
+        append_format(output, L"%5lu  %ls  %ls  %ls  %ls\n", static_cast<unsigned long>(idx),
+                      format_micros(micros(timing.end - timing.start)).c_str(),
+                      format_micros(timing.usr_micros).c_str(),
+                      format_micros(timing.sys_micros).c_str(), describe_process(*p).c_str());
+    }
+    return output;
+}
+
+/// \return the resources used in the cgroup of a job, which include those of any processes it
+/// started but did not wait for.
+static wcstring print_cgroup_usage(const cgroup_usage_t &usage) {
+    auto usr_micros = static_cast<int64_t>(usage.usr_micros);
+    auto sys_micros = static_cast<int64_t>(usage.sys_micros);
+    tunit unit = get_unit(std::max(usr_micros, sys_micros));
+    wcstring output = format_string(L"\nJob cgroup   usr time  %6.2F %s   sys time  %6.2F %s",
+                                    convert(usr_micros, unit), unit_short_name(unit),
+                                    convert(sys_micros, unit), unit_short_name(unit));
+    if (usage.memory_peak_bytes) {
+        append_format(output, L"   peak memory  %.2F MB", *usage.memory_peak_bytes / 1E6);
+    }
+    output.push_back(L'\n');
+    return output;
+}
+
 static std::vector<timer_snapshot_t> active_timers;
 
-static void pop_timer() {
+static void pop_timer(const std::shared_ptr<const job_t> &job) {
     auto t1 = active_timers.back();
     active_timers.pop_back();
     auto t2 = timer_snapshot_t::take();
@@ -208,11 +286,17 @@ static void pop_timer() {
     // Well, this is awkward. By defining `time` as a decorator and not a built-in, there's
     // no associated stream for its output!
     auto output = timer_snapshot_t::print_delta(t1, t2, true);
+    if (job && job->processes.size() > 1) {
+        output.append(print_stages(*job));
+    }
+    if (job && job->cgroup) {
+        if (auto usage = job->cgroup->usage()) output.append(print_cgroup_usage(*usage));
+    }
     std::fwprintf(stderr, L"%S\n", output.c_str());
 }
 
-cleanup_t push_timer(bool enabled) {
+cleanup_t push_timer(bool enabled, std::shared_ptr<const job_t> job) {
     if (!enabled) return {[] {}};
     active_timers.emplace_back(timer_snapshot_t::take());
-    return {[] { pop_timer(); }};
+    return {[job] { pop_timer(job); }};
 }
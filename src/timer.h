@@ -7,13 +7,18 @@
 #include <sys/types.h>
 
 #include <chrono>
+#include <memory>
 
 #include "common.h"
 
+class job_t;
 class parser_t;
+class process_t;
 struct io_streams_t;
 
-cleanup_t push_timer(bool enabled);
+/// Start timing, if \p enabled. When the returned cleanup runs, the elapsed time is printed. If a
+/// \p job with more than one process is given, the time of each process is printed as well.
+cleanup_t push_timer(bool enabled, std::shared_ptr<const job_t> job = nullptr);
 
 struct timer_snapshot_t {
    public:
@@ -28,4 +33,10 @@ struct timer_snapshot_t {
     timer_snapshot_t() {}
 };
 
+/// Record the timing of the external process \p p, which has exited after using \p usage.
+void timer_record_external_exit(process_t *p, const struct rusage &usage);
+
+/// Record the timing of the internal process \p p, which ran in fish since \p start.
+void timer_record_internal_exit(process_t *p, const timer_snapshot_t &start);
+
 #endif
@@ -23,6 +23,7 @@
  */
 
 // This version has been altered and ported to C++ for inclusion in fish.
+#include "common.h"
 #include "fallback.h"  // IWYU pragma: keep
 #include "tinyexpr.h"
 #include "wutil.h"
@@ -73,6 +74,9 @@ typedef struct te_expr {
         double value;
         const void *function;
     };
+    // For constants, whether the value is an integer that's exactly represented by ivalue.
+    bool exact;
+    long long ivalue;
     te_expr *parameters[];
 } te_expr;
 
@@ -87,6 +91,8 @@ using state = struct {
         double value;
         const void *function;
     };
+    bool exact;
+    long long ivalue;
     const wchar_t *start;
     const wchar_t *next;
     int type;
@@ -182,6 +188,18 @@ static constexpr double bit_xor(double a, double b) {
     return static_cast<double>(static_cast<long long>(a) ^ static_cast<long long>(b));
 }
 
+static double bit_shl(double a, double b) {
+    if (b < 0 || b >= 64) return NAN;
+    // Shift as unsigned so bits shifted into the sign bit aren't undefined behavior.
+    auto ua = static_cast<unsigned long long>(static_cast<long long>(a));
+    return static_cast<double>(static_cast<long long>(ua << static_cast<int>(b)));
+}
+
+static double bit_shr(double a, double b) {
+    if (b < 0 || b >= 64) return NAN;
+    return static_cast<double>(static_cast<long long>(a) >> static_cast<int>(b));
+}
+
 static const te_builtin functions[] = {
     /* must be in alphabetical order */
     {L"abs", reinterpret_cast<const void *>(static_cast<te_fun1>(fabs)), TE_FUNCTION1},
@@ -191,6 +209,8 @@ static const te_builtin functions[] = {
     {L"atan2", reinterpret_cast<const void *>(static_cast<te_fun2>(atan2)), TE_FUNCTION2},
     {L"bitand", reinterpret_cast<const void *>(static_cast<te_fun2>(bit_and)), TE_FUNCTION2},
     {L"bitor", reinterpret_cast<const void *>(static_cast<te_fun2>(bit_or)), TE_FUNCTION2},
+    {L"bitshl", reinterpret_cast<const void *>(static_cast<te_fun2>(bit_shl)), TE_FUNCTION2},
+    {L"bitshr", reinterpret_cast<const void *>(static_cast<te_fun2>(bit_shr)), TE_FUNCTION2},
     {L"bitxor", reinterpret_cast<const void *>(static_cast<te_fun2>(bit_xor)), TE_FUNCTION2},
     {L"ceil", reinterpret_cast<const void *>(static_cast<te_fun1>(ceil)), TE_FUNCTION1},
     {L"cos", reinterpret_cast<const void *>(static_cast<te_fun1>(cos)), TE_FUNCTION1},
@@ -241,6 +261,44 @@ static constexpr double divide(double a, double b) {
 
 static constexpr double negate(double a) { return -a; }
 
+/// Try to read an integer literal - decimal, hexadecimal with a "0x" prefix or binary with a "0b"
+/// prefix - at s->next, keeping its exact value.
+/// Returns false without consuming anything if there is none, e.g. because it's a float.
+static bool read_integer(state *s) {
+    const wchar_t *p = s->next;
+    int base = 10;
+    if (p[0] == '0' && (p[1] == 'x' || p[1] == 'X') && convert_digit(p[2], 16) >= 0) {
+        base = 16;
+        p += 2;
+    } else if (p[0] == '0' && (p[1] == 'b' || p[1] == 'B') && convert_digit(p[2], 2) >= 0) {
+        base = 2;
+        p += 2;
+    }
+
+    const wchar_t *const digits_start = p;
+    bool overflow = false;
+    long long value = 0;
+    double dvalue = 0;
+    for (long digit; (digit = convert_digit(*p, base)) >= 0; p++) {
+        if (!overflow && value > (LLONG_MAX - digit) / base) overflow = true;
+        if (!overflow) value = value * base + digit;
+        dvalue = dvalue * base + digit;
+    }
+    if (p == digits_start) return false;
+
+    // Leave fractions and exponents to wcstod.
+    if (base == 10 && (*p == '.' || *p == 'e' || *p == 'E')) return false;
+    if (base == 16 && (*p == '.' || *p == 'p' || *p == 'P')) return false;
+    // Decimals that are too large are also better rounded by wcstod.
+    if (base == 10 && overflow) return false;
+
+    s->exact = !overflow;
+    s->ivalue = overflow ? 0 : value;
+    s->value = overflow ? dvalue : static_cast<double>(value);
+    s->next = p;
+    return true;
+}
+
 static void next_token(state *s) {
     s->type = TOK_NULL;
 
@@ -252,7 +310,10 @@ static void next_token(state *s) {
 
         /* Try reading a number. */
         if ((s->next[0] >= '0' && s->next[0] <= '9') || s->next[0] == '.') {
-            s->value = fish_wcstod(s->next, const_cast<wchar_t **>(&s->next));
+            if (!read_integer(s)) {
+                s->value = fish_wcstod(s->next, const_cast<wchar_t **>(&s->next));
+                s->exact = false;
+            }
             s->type = TOK_NUMBER;
         } else {
             /* Look for a function call. */
@@ -359,6 +420,8 @@ static te_expr *base(state *s) {
         case TOK_NUMBER:
             ret = new_expr(TE_CONSTANT, nullptr);
             ret->value = s->value;
+            ret->exact = s->exact;
+            ret->ivalue = s->ivalue;
             next_token(s);
             break;
 
@@ -553,6 +616,83 @@ double te_eval(const te_expr *n) {
 #undef TE_FUN
 #undef M
 
+/// Multiply two integers, returning false if the result would overflow.
+static bool int_mul(long long a, long long b, long long *result) {
+    if (a > 0 ? (b > 0 ? a > LLONG_MAX / b : b < LLONG_MIN / a)
+              : (b > 0 ? a < LLONG_MIN / b : (a != 0 && b < LLONG_MAX / a))) {
+        return false;
+    }
+    *result = a * b;
+    return true;
+}
+
+/// Evaluate the expression using exact 64-bit integer arithmetic.
+/// Returns false if that's not possible, because a value isn't an integer, the operation has no
+/// integer equivalent or the result would overflow. The caller then falls back to te_eval.
+static bool te_eval_integer(const te_expr *n, long long *result) {
+    if (!n) return false;
+    if (n->type == TE_CONSTANT) {
+        if (!n->exact) return false;
+        *result = n->ivalue;
+        return true;
+    }
+
+    const int arity = get_arity(n->type);
+    if (arity == 1 && n->function == reinterpret_cast<const void *>(negate)) {
+        long long a;
+        if (!te_eval_integer(n->parameters[0], &a) || a == LLONG_MIN) return false;
+        *result = -a;
+        return true;
+    }
+    if (arity != 2) return false;
+
+    long long a, b;
+    if (!te_eval_integer(n->parameters[0], &a) || !te_eval_integer(n->parameters[1], &b)) {
+        return false;
+    }
+    const auto is = [n](te_fun2 fun) { return n->function == reinterpret_cast<const void *>(fun); };
+    if (is(add)) {
+        if ((b > 0 && a > LLONG_MAX - b) || (b < 0 && a < LLONG_MIN - b)) return false;
+        *result = a + b;
+    } else if (is(sub)) {
+        if ((b < 0 && a > LLONG_MAX + b) || (b > 0 && a < LLONG_MIN + b)) return false;
+        *result = a - b;
+    } else if (is(mul)) {
+        return int_mul(a, b, result);
+    } else if (is(divide)) {
+        // Only divisions without a remainder stay integers.
+        if (b == 0 || (a == LLONG_MIN && b == -1) || a % b != 0) return false;
+        *result = a / b;
+    } else if (is(fmod)) {
+        if (b == 0 || (a == LLONG_MIN && b == -1)) return false;
+        *result = a % b;
+    } else if (is(pow)) {
+        if (b < 0) return false;
+        long long ret = 1;
+        while (b > 0) {
+            if ((b & 1) && !int_mul(ret, a, &ret)) return false;
+            b >>= 1;
+            if (b > 0 && !int_mul(a, a, &a)) return false;
+        }
+        *result = ret;
+    } else if (is(bit_and)) {
+        *result = a & b;
+    } else if (is(bit_or)) {
+        *result = a | b;
+    } else if (is(bit_xor)) {
+        *result = a ^ b;
+    } else if (is(bit_shl)) {
+        if (b < 0 || b >= 64) return false;
+        *result = static_cast<long long>(static_cast<unsigned long long>(a) << b);
+    } else if (is(bit_shr)) {
+        if (b < 0 || b >= 64) return false;
+        *result = a >> b;
+    } else {
+        return false;
+    }
+    return true;
+}
+
 static void optimize(te_expr *n) {
     /* Evaluates as much as possible. */
     if (n->type == TE_CONSTANT) return;
@@ -566,10 +706,14 @@ static void optimize(te_expr *n) {
         }
     }
     if (known) {
-        const double value = te_eval(n);
+        long long ivalue = 0;
+        const bool exact = te_eval_integer(n, &ivalue);
+        const double value = exact ? static_cast<double>(ivalue) : te_eval(n);
         te_free_parameters(n);
         n->type = TE_CONSTANT;
         n->value = value;
+        n->exact = exact;
+        n->ivalue = ivalue;
     }
 }
 
@@ -604,10 +748,12 @@ te_expr *te_compile(const wchar_t *expression, te_error_t *error) {
     }
 }
 
-double te_interp(const wchar_t *expression, te_error_t *error) {
+double te_interp(const wchar_t *expression, te_error_t *error, te_integer_t *integer) {
     te_expr *n = te_compile(expression, error);
     double ret;
+    if (integer) integer->exact = false;
     if (n) {
+        if (integer) integer->exact = te_eval_integer(n, &integer->value);
         ret = te_eval(n);
         te_free(n);
     } else {
@@ -45,8 +45,15 @@ typedef struct te_error_t {
     int position;
 } te_error_t;
 
+/* The result of an expression that could be computed using exact 64-bit integer arithmetic. */
+typedef struct te_integer_t {
+    bool exact;
+    long long value;
+} te_integer_t;
+
 /* Parses the input expression, evaluates it, and frees it. */
 /* Returns NaN on error. */
-double te_interp(const wchar_t *expression, te_error_t *error);
+/* If integer is not NULL, it is filled in with the exact integer result, if there is one. */
+double te_interp(const wchar_t *expression, te_error_t *error, te_integer_t *integer = nullptr);
 
 #endif /*__TINYEXPR_H__*/
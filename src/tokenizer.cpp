@@ -50,6 +50,14 @@ const wchar_t *tokenizer_get_error_message(tokenizer_error_t err) {
             return _(L"Unexpected '}' found, expecting ')'");
         case tokenizer_error_t::expected_bclose_found_pclose:
             return _(L"Unexpected ')' found, expecting '}'");
+        case tokenizer_error_t::invalid_heredoc_marker:
+            return _(L"Expected a here-document marker of letters, digits and underscores");
+        case tokenizer_error_t::unterminated_heredoc:
+            return _(L"Unexpected end of string, here-document is not terminated");
+        case tokenizer_error_t::multiple_heredocs:
+            return _(L"Only one here-document may begin on a line");
+        case tokenizer_error_t::continued_heredoc_line:
+            return _(L"The line beginning a here-document may not continue on the next line");
     }
     assert(0 && "Unexpected tokenizer error");
     return nullptr;
@@ -173,6 +181,8 @@ tok_t tokenizer_t::read_string() {
         // has been explicitly ignored (escaped).
         else if (c == L'\\') {
             mode |= tok_modes::char_escape;
+        } else if (c == L'<' && is_first && this->token_cursor[1] == L'(') {
+            // The start of a process substitution like <(cmd). The parenthesis is handled next.
         } else if (c == L'(') {
             paran_offsets.push_back(this->token_cursor - this->start);
             expecting.push_back(L')');
@@ -311,6 +321,8 @@ maybe_t<pipe_or_redir_t> pipe_or_redir_t::from_string(const wchar_t *buff) {
         cmd >| cmd       pipe with explicit fd
         cmd 2>| cmd      pipe with explicit fd
         cmd < file       stdin redirection
+        cmd <<< text     here-string
+        cmd <<END        here-document, whose body follows on the next lines
         cmd > file       redirection
         cmd >> file      appending redirection
         cmd >? file      noclobber redirection
@@ -323,10 +335,23 @@ maybe_t<pipe_or_redir_t> pipe_or_redir_t::from_string(const wchar_t *buff) {
         cmd &> file      redirection with stderr merge
         cmd ^ file       caret (stderr) redirection, perhaps disabled via feature flags
         cmd ^^ file      caret (stderr) redirection, perhaps disabled via feature flags
+        cmd {fd}> file   named fd redirection; the shell picks the fd and stores it in $fd
+        cmd {fd}>&-      close the named fd stored in $fd
     */
 
     const wchar_t *cursor = buff;
 
+    // Extract a leading variable name in braces, for named fd redirections.
+    if (*cursor == L'{') {
+        const wchar_t *name_start = cursor + 1;
+        const wchar_t *name_end = name_start;
+        while (valid_var_name_char(*name_end)) name_end++;
+        if (name_end == name_start || name_end[0] != L'}') return none();
+        if (name_end[1] != L'>' && name_end[1] != L'<') return none();
+        result.fd_var.assign(name_start, name_end);
+        cursor = name_end + 1;
+    }
+
     // Extract a range of leading fd.
     const wchar_t *fd_start = cursor;
     while (iswdigit(*cursor)) cursor++;
@@ -395,7 +420,11 @@ maybe_t<pipe_or_redir_t> pipe_or_redir_t::from_string(const wchar_t *buff) {
         }
         case L'<': {
             consume(L'<');
-            if (try_consume('&')) {
+            if (try_consume(L'<')) {
+                // <<< is a here-string, << is a here-document.
+                result.mode = try_consume(L'<') ? redirection_mode_t::here_string
+                                                : redirection_mode_t::here_doc;
+            } else if (try_consume('&')) {
                 result.mode = redirection_mode_t::fd;
             } else {
                 result.mode = redirection_mode_t::input;
@@ -450,6 +479,13 @@ maybe_t<pipe_or_redir_t> pipe_or_redir_t::from_string(const wchar_t *buff) {
         }
     }
 
+    // Named fds cannot be piped, or fed from text.
+    if (!result.fd_var.empty() &&
+        (result.is_pipe || result.mode == redirection_mode_t::here_string ||
+         result.mode == redirection_mode_t::here_doc)) {
+        return none();
+    }
+
     result.consumed = (cursor - buff);
     assert(result.consumed > 0 && "Should have consumed at least one character on success");
     return result;
@@ -491,6 +527,63 @@ static bool iswspace_not_nl(wchar_t c) {
     }
 }
 
+/// Read the target of a here-document redirection like <<END, which is the marker. Its body
+/// begins on the next line, and is read along with the newline which ends this one.
+tok_t tokenizer_t::read_heredoc_marker() {
+    const wchar_t *const buff_start = this->token_cursor;
+    const wchar_t *cursor = buff_start;
+    while (valid_var_name_char(*cursor)) cursor++;
+    this->token_cursor = cursor;
+
+    if (cursor == buff_start || tok_is_string_character(*cursor, false)) {
+        while (tok_is_string_character(*this->token_cursor, false)) this->token_cursor++;
+        return this->call_error(tokenizer_error_t::invalid_heredoc_marker, buff_start, cursor);
+    }
+    if (this->heredoc_marker_start) {
+        return this->call_error(tokenizer_error_t::multiple_heredocs, buff_start, buff_start);
+    }
+    this->heredoc_marker.assign(buff_start, cursor);
+    this->heredoc_marker_start = buff_start;
+
+    tok_t result(token_type_t::string);
+    result.offset = buff_start - this->start;
+    result.length = cursor - buff_start;
+    return result;
+}
+
+/// Read the newline which ends the line of a pending here-document, followed by the lines of its
+/// body and finally the line consisting of just the marker (and optional indentation). These are
+/// returned as a single end token.
+tok_t tokenizer_t::read_heredoc_body() {
+    const wchar_t *const buff_start = this->token_cursor;
+    assert(*buff_start == L'\n' && this->heredoc_marker_start && "No here-document body here");
+    this->heredoc_marker_start = nullptr;
+    if (this->continues_line) {
+        return this->call_error(tokenizer_error_t::continued_heredoc_line, buff_start, buff_start);
+    }
+
+    // Look for the terminating line.
+    const wchar_t *cursor = buff_start;
+    bool terminated = false;
+    while (!terminated && *cursor == L'\n') {
+        const wchar_t *line_start = cursor + 1;
+        while (iswspace_not_nl(*line_start)) line_start++;
+        const wchar_t *line_end = line_start;
+        while (*line_end != L'\n' && *line_end != L'\0') line_end++;
+        terminated = wcstring(line_start, line_end) == this->heredoc_marker;
+        cursor = line_end;
+    }
+
+    this->token_cursor = cursor;
+    if (!terminated && !this->accept_unfinished) {
+        return this->call_error(tokenizer_error_t::unterminated_heredoc, buff_start, buff_start);
+    }
+    tok_t result(token_type_t::end);
+    result.offset = buff_start - this->start;
+    result.length = cursor - buff_start;
+    return result;
+}
+
 maybe_t<tok_t> tokenizer_t::next() {
     if (!this->has_next) {
         return none();
@@ -534,19 +627,34 @@ maybe_t<tok_t> tokenizer_t::next() {
     this->continue_line_after_comment = false;
     const size_t start_pos = this->token_cursor - this->start;
 
+    if (this->expecting_heredoc) {
+        this->expecting_heredoc = false;
+        if (*this->token_cursor != L'\0') return this->read_heredoc_marker();
+    }
+
     maybe_t<tok_t> result{};
     switch (*this->token_cursor) {
         case L'\0': {
+            if (this->heredoc_marker_start && !this->accept_unfinished) {
+                this->heredoc_marker_start = nullptr;
+                return this->call_error(tokenizer_error_t::unterminated_heredoc,
+                                        this->token_cursor, this->token_cursor);
+            }
             this->has_next = false;
             return none();
         }
         case L'\r':  // carriage-return
         case L'\n':  // newline
         case L';': {
-            result.emplace(token_type_t::end);
-            result->offset = start_pos;
-            result->length = 1;
-            this->token_cursor++;
+            if (*this->token_cursor == L'\n' && this->heredoc_marker_start) {
+                result = this->read_heredoc_body();
+                if (result->type == token_type_t::error) return result;
+            } else {
+                result.emplace(token_type_t::end);
+                result->offset = start_pos;
+                result->length = 1;
+                this->token_cursor++;
+            }
             // Hack: when we get a newline, swallow as many as we can. This compresses multiple
             // subsequent newlines into a single one.
             if (!this->show_blank_lines) {
@@ -605,6 +713,11 @@ maybe_t<tok_t> tokenizer_t::next() {
         }
         case L'>':
         case L'<': {
+            if (this->token_cursor[0] == L'<' && this->token_cursor[1] == L'(') {
+                // A process substitution like <(cmd) is a string.
+                result = this->read_string();
+                break;
+            }
             // There's some duplication with the code in the default case below. The key
             // difference here is that we must never parse these as a string; a failed
             // redirection is an error!
@@ -618,13 +731,16 @@ maybe_t<tok_t> tokenizer_t::next() {
             result->offset = start_pos;
             result->length = redir_or_pipe->consumed;
             this->token_cursor += redir_or_pipe->consumed;
+            this->expecting_heredoc =
+                !redir_or_pipe->is_pipe && redir_or_pipe->mode == redirection_mode_t::here_doc;
             break;
         }
         default: {
             // Maybe a redirection like '2>&1', maybe a pipe like 2>|, maybe just a string.
             const wchar_t *error_location = this->token_cursor;
             maybe_t<pipe_or_redir_t> redir_or_pipe{};
-            if (iswdigit(*this->token_cursor) || (*this->token_cursor == L'^' && caret_redirs())) {
+            if (iswdigit(*this->token_cursor) || *this->token_cursor == L'{' ||
+                (*this->token_cursor == L'^' && caret_redirs())) {
                 redir_or_pipe = pipe_or_redir_t::from_string(this->token_cursor);
             }
 
@@ -640,6 +756,9 @@ maybe_t<tok_t> tokenizer_t::next() {
                 result->offset = start_pos;
                 result->length = redir_or_pipe->consumed;
                 this->token_cursor += redir_or_pipe->consumed;
+                this->expecting_heredoc =
+                    !redir_or_pipe->is_pipe &&
+                    redir_or_pipe->mode == redirection_mode_t::here_doc;
             } else {
                 // Not a redirection or pipe, so just a string.
                 result = this->read_string();
@@ -648,6 +767,9 @@ maybe_t<tok_t> tokenizer_t::next() {
         }
     }
     assert(result.has_value() && "Should have a token");
+    this->continues_line = result->type == token_type_t::pipe ||
+                           result->type == token_type_t::andand ||
+                           result->type == token_type_t::oror;
     return result;
 }
 
@@ -676,6 +798,15 @@ wcstring tok_command(const wcstring &str) {
     return {};
 }
 
+wcstring tok_heredoc_source(const wcstring &src, size_t oper_offset) {
+    // Only the end token of a line beginning a here-document is longer than one character.
+    tokenizer_t t(src.c_str() + oper_offset, 0);
+    while (auto token = t.next()) {
+        if (token->type == token_type_t::end && token->length > 1) return t.text_of(*token);
+    }
+    return {};
+}
+
 bool move_word_state_machine_t::consume_char_punctuation(wchar_t c) {
     enum { s_always_one = 0, s_rest, s_whitespace_rest, s_whitespace, s_alphanumeric, s_end };
 
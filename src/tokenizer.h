@@ -54,6 +54,10 @@ enum class tokenizer_error_t {
     unterminated_brace,
     expected_pclose_found_bclose,
     expected_bclose_found_pclose,
+    invalid_heredoc_marker,
+    unterminated_heredoc,
+    multiple_heredocs,
+    continued_heredoc_line,
 };
 
 /// Get the error message for an error \p err.
@@ -108,10 +112,20 @@ class tokenizer_t {
     bool continue_after_error{false};
     /// Whether to continue the previous line after the comment.
     bool continue_line_after_comment{false};
+    /// Whether the next token is the target of a here-document redirection.
+    bool expecting_heredoc{false};
+    /// The marker of the here-document whose body follows the current line, and where it starts,
+    /// or null if there is none.
+    wcstring heredoc_marker;
+    const wchar_t *heredoc_marker_start{nullptr};
+    /// Whether the last token continues the job on the next line, like a pipe.
+    bool continues_line{false};
 
     tok_t call_error(tokenizer_error_t error_type, const wchar_t *token_start,
                      const wchar_t *error_loc, maybe_t<size_t> token_length = {});
     tok_t read_string();
+    tok_t read_heredoc_marker();
+    tok_t read_heredoc_body();
 
    public:
     /// Constructor for a tokenizer. b is the string that is to be tokenized. It is not copied, and
@@ -144,6 +158,11 @@ wcstring tok_first(const wcstring &str);
 /// Like to tok_first, but skip variable assignments like A=B.
 wcstring tok_command(const wcstring &str);
 
+/// Given the offset \p oper_offset of a here-document's << operator in \p src, \return the text
+/// which follows its line: the newline, the body and the terminating line. This is empty if the
+/// here-document is not terminated.
+wcstring tok_heredoc_source(const wcstring &src, size_t oper_offset);
+
 /// Struct wrapping up a parsed pipe or redirection.
 struct pipe_or_redir_t {
     // The redirected fd, or -1 on overflow.
@@ -162,6 +181,10 @@ struct pipe_or_redir_t {
     // For example &| or &>
     bool stderr_merge{false};
 
+    // For a named fd redirection like {fd}>file, the name of the variable which receives the
+    // allocated fd. Empty otherwise.
+    wcstring fd_var{};
+
     // Number of characters consumed when parsing the string.
     size_t consumed{0};
 
@@ -5,6 +5,7 @@
 #include <limits.h>
 #include <unistd.h>
 
+#include "fds.h"
 #include "flog.h"
 #include "iothread.h"
 #include "wcstringutil.h"
@@ -24,6 +25,52 @@
 #define TOPIC_MONITOR_TSAN_WORKAROUND
 #endif
 
+// Park the reader in the kernel where we can. Under tsan use the pipe, for the reason above.
+#if !defined(TOPIC_MONITOR_TSAN_WORKAROUND) && \
+    (defined(__linux__) || defined(__FreeBSD__) || defined(__OpenBSD__))
+#define TOPIC_MONITOR_USE_FUTEX
+#endif
+
+#ifdef TOPIC_MONITOR_USE_FUTEX
+#if defined(__linux__)
+#include <linux/futex.h>
+#include <sys/syscall.h>
+#elif defined(__FreeBSD__)
+#include <sys/types.h>
+#include <sys/umtx.h>
+#elif defined(__OpenBSD__)
+#include <sys/futex.h>
+#endif
+
+static_assert(sizeof(std::atomic<uint32_t>) == sizeof(uint32_t),
+              "futex word must be a plain 32 bit integer");
+
+/// Park the calling thread while \p word is \p val, until woken. This may also return early, e.g.
+/// with EINTR.
+static int futex_wait(std::atomic<uint32_t> *word, uint32_t val) {
+    auto addr = reinterpret_cast<uint32_t *>(word);
+#if defined(__linux__)
+    return static_cast<int>(syscall(SYS_futex, addr, FUTEX_WAIT_PRIVATE, val, nullptr, nullptr, 0));
+#elif defined(__FreeBSD__)
+    return _umtx_op(addr, UMTX_OP_WAIT_UINT_PRIVATE, val, nullptr, nullptr);
+#else
+    return futex(addr, FUTEX_WAIT | FUTEX_PRIVATE_FLAG, val, nullptr, nullptr);
+#endif
+}
+
+/// Wake a thread parked on \p word. This is async-signal safe.
+static int futex_wake(std::atomic<uint32_t> *word) {
+    auto addr = reinterpret_cast<uint32_t *>(word);
+#if defined(__linux__)
+    return static_cast<int>(syscall(SYS_futex, addr, FUTEX_WAKE_PRIVATE, 1, nullptr, nullptr, 0));
+#elif defined(__FreeBSD__)
+    return _umtx_op(addr, UMTX_OP_WAKE_PRIVATE, 1, nullptr, nullptr);
+#else
+    return futex(addr, FUTEX_WAKE | FUTEX_PRIVATE_FLAG, 1, nullptr, nullptr);
+#endif
+}
+#endif
+
 wcstring generation_list_t::describe() const {
     wcstring result;
     for (generation_t gen : this->as_array()) {
@@ -37,80 +84,75 @@ wcstring generation_list_t::describe() const {
     return result;
 }
 
-binary_semaphore_t::binary_semaphore_t() : sem_ok_(false) {
-    // sem_init always fails with ENOSYS on Mac and has an annoying deprecation warning.
-    // On BSD sem_init uses a file descriptor under the hood which doesn't get CLOEXEC (see #7304).
-    // So use fast semaphores on Linux only.
-#ifdef __linux__
-    sem_ok_ = (0 == sem_init(&sem_, 0, 0));
-#endif
-    if (!sem_ok_) {
-        auto pipes = make_autoclose_pipes();
-        assert(pipes.has_value() && "Failed to make pubsub pipes");
-        pipes_ = pipes.acquire();
+binary_semaphore_t::binary_semaphore_t() {
+    // Note sem_t is no alternative for the pipe: sem_init always fails with ENOSYS on Mac, and on
+    // BSD it uses a file descriptor under the hood which doesn't get CLOEXEC (see #7304).
+#ifndef TOPIC_MONITOR_USE_FUTEX
+    auto pipes = make_autoclose_pipes();
+    assert(pipes.has_value() && "Failed to make pubsub pipes");
+    fd_set_purpose(pipes->read.fd(), N_(L"signal and child process monitor"));
+    fd_set_purpose(pipes->write.fd(), N_(L"signal and child process monitor"));
+    pipes_ = pipes.acquire();
 
 #ifdef TOPIC_MONITOR_TSAN_WORKAROUND
-        DIE_ON_FAILURE(make_fd_nonblocking(pipes_.read.fd()));
+    DIE_ON_FAILURE(make_fd_nonblocking(pipes_.read.fd()));
 #endif
-    }
-}
-
-binary_semaphore_t::~binary_semaphore_t() {
-    // We never use sem_t on Mac. The #ifdef avoids deprecation warnings.
-#ifndef __APPLE__
-    if (sem_ok_) (void)sem_destroy(&sem_);
 #endif
 }
 
+binary_semaphore_t::~binary_semaphore_t() = default;
+
 void binary_semaphore_t::die(const wchar_t *msg) const {
     wperror(msg);
     DIE("unexpected failure");
 }
 
 void binary_semaphore_t::post() {
-    if (sem_ok_) {
-        int res = sem_post(&sem_);
-        // sem_post is non-interruptible.
-        if (res < 0) die(L"sem_post");
-    } else {
-        // Write exactly one byte.
-        ssize_t ret;
-        do {
-            const uint8_t v = 0;
-            ret = write(pipes_.write.fd(), &v, sizeof v);
-        } while (ret < 0 && errno == EINTR);
-        if (ret < 0) die(L"write");
-    }
+#ifdef TOPIC_MONITOR_USE_FUTEX
+    futex_.store(1, std::memory_order_release);
+    if (futex_wake(&futex_) < 0) die(L"futex wake");
+#else
+    // Write exactly one byte.
+    ssize_t ret;
+    do {
+        const uint8_t v = 0;
+        ret = write(pipes_.write.fd(), &v, sizeof v);
+    } while (ret < 0 && errno == EINTR);
+    if (ret < 0) die(L"write");
+#endif
 }
 
 void binary_semaphore_t::wait() {
-    if (sem_ok_) {
-        int res;
-        do {
-            res = sem_wait(&sem_);
-        } while (res < 0 && errno == EINTR);
-        // Other errors here are very unexpected.
-        if (res < 0) die(L"sem_wait");
-    } else {
-        int fd = pipes_.read.fd();
-        // We must read exactly one byte.
-        for (;;) {
+#ifdef TOPIC_MONITOR_USE_FUTEX
+    for (;;) {
+        uint32_t posted = 1;
+        if (futex_.compare_exchange_strong(posted, 0, std::memory_order_acquire)) break;
+        // Not posted yet. This returns right away if a post() came in since we looked (EAGAIN on
+        // Linux), and may return early for a signal, so loop.
+        if (futex_wait(&futex_, 0) < 0 && errno != EINTR && errno != EAGAIN) {
+            die(L"futex wait");
+        }
+    }
+#else
+    int fd = pipes_.read.fd();
+    // We must read exactly one byte.
+    for (;;) {
 #ifdef TOPIC_MONITOR_TSAN_WORKAROUND
-            // Under tsan our notifying pipe is non-blocking, so we would busy-loop on the read()
-            // call until data is available (that is, fish would use 100% cpu while waiting for
-            // processes). The select prevents that.
-            fd_set fds;
-            FD_ZERO(&fds);
-            FD_SET(fd, &fds);
-            (void)select(fd + 1, &fds, nullptr, nullptr, nullptr /* timeout */);
+        // Under tsan our notifying pipe is non-blocking, so we would busy-loop on the read()
+        // call until data is available (that is, fish would use 100% cpu while waiting for
+        // processes). The select prevents that.
+        fd_set fds;
+        FD_ZERO(&fds);
+        FD_SET(fd, &fds);
+        (void)select(fd + 1, &fds, nullptr, nullptr, nullptr /* timeout */);
 #endif
-            uint8_t ignored;
-            auto amt = read(fd, &ignored, sizeof ignored);
-            if (amt == 1) break;
-            // EAGAIN should only be returned in TSan case.
-            if (amt < 0 && errno != EINTR && errno != EAGAIN) die(L"read");
-        }
+        uint8_t ignored;
+        auto amt = read(fd, &ignored, sizeof ignored);
+        if (amt == 1) break;
+        // EAGAIN should only be returned in TSan case.
+        if (amt < 0 && errno != EINTR && errno != EAGAIN) die(L"read");
     }
+#endif
 }
 
 /// Implementation of the principal monitor. This uses new (and leaks) to avoid registering a
@@ -1,7 +1,7 @@
 #ifndef FISH_TOPIC_MONITOR_H
 #define FISH_TOPIC_MONITOR_H
 
-#include <semaphore.h>
+#include <stdint.h>
 
 #include <array>
 #include <atomic>
@@ -130,7 +130,8 @@ class generation_list_t {
 };
 
 /// A simple binary semaphore.
-/// On systems that do not support unnamed semaphores (macOS in particular) this is built on top of
+/// On Linux, FreeBSD and OpenBSD the waiting thread is parked with the kernel's futex API, so a
+/// post wakes it with a single system call. Elsewhere (macOS in particular) this is built on top of
 /// a self-pipe. Note that post() must be async-signal safe.
 class binary_semaphore_t {
    public:
@@ -148,13 +149,10 @@ class binary_semaphore_t {
     // Print a message and exit.
     void die(const wchar_t *msg) const;
 
-    // Whether our semaphore was successfully initialized.
-    bool sem_ok_{};
+    // The futex word, 1 if posted and 0 if not, if we use futexes.
+    std::atomic<uint32_t> futex_{0};
 
-    // The semaphore, if initalized.
-    sem_t sem_{};
-
-    // Pipes used to emulate a semaphore, if not initialized.
+    // Pipes used to emulate a semaphore, if we don't use futexes.
     autoclose_pipes_t pipes_{};
 };
 
@@ -2,11 +2,38 @@
 
 #include "trace.h"
 
+#include <errno.h>
+#include <fcntl.h>
+
+#include <cstring>
+
 #include "common.h"
+#include "fds.h"
 #include "flog.h"
+#include "json.h"
+#include "parse_util.h"
 #include "parser.h"
+#include "util.h"
+#include "wildcard.h"
+#include "wutil.h"  // IWYU pragma: keep
 
 static const wcstring VAR_fish_trace = L"fish_trace";
+static const wcstring VAR_fish_trace_target = L"fish_trace_target";
+static const wcstring VAR_fish_trace_format = L"fish_trace_format";
+static const wcstring VAR_fish_trace_max_depth = L"fish_trace_max_depth";
+static const wcstring VAR_fish_trace_filter = L"fish_trace_filter";
+
+/// Timestamps are relative to when fish started.
+static const long long s_start_time = get_time();
+
+namespace {
+/// The file named by fish_trace_target, which is kept open until the variable changes.
+struct trace_file_t {
+    wcstring path;
+    autoclose_fd_t fd;
+};
+}  // namespace
+static owning_lock<trace_file_t> s_trace_file;
 
 bool trace_enabled(const parser_t &parser) {
     const auto &ld = parser.libdata();
@@ -15,23 +42,102 @@ bool trace_enabled(const parser_t &parser) {
     return !parser.vars().get(VAR_fish_trace).missing_or_empty();
 }
 
+/// Write \p text to the target given by fish_trace_target: a file descriptor if it is a number,
+/// and otherwise a path to append to. If it is not set, write to the debug output file.
+static void write_trace(const parser_t &parser, const wcstring &text) {
+    auto target = parser.vars().get(VAR_fish_trace_target);
+    if (target.missing_or_empty()) {
+        log_extra_to_flog_file(text);
+        return;
+    }
+
+    std::string narrow = wcs2string(text);
+    wcstring name = target->as_string();
+    int fd = fish_wcstoi(name.c_str());
+    if (!errno && fd >= 0) {
+        write_loop(fd, narrow.data(), narrow.size());
+        return;
+    }
+
+    // Hold the lock while writing so traces from different threads aren't interleaved.
+    auto file = s_trace_file.acquire();
+    if (file->path != name) {
+        file->path = name;
+        file->fd.reset(wopen_cloexec(name, O_WRONLY | O_CREAT | O_APPEND, 0666));
+        if (!file->fd.valid()) {
+            FLOGF(warning, _(L"Could not open trace file '%ls': %s"), name.c_str(),
+                  std::strerror(errno));
+        }
+    }
+    if (file->fd.valid()) {
+        write_loop(file->fd.fd(), narrow.data(), narrow.size());
+    } else {
+        log_extra_to_flog_file(text);
+    }
+}
+
+/// \return whether a command should be traced, according to fish_trace_max_depth and
+/// fish_trace_filter. \p line is the traced command line, without the depth prefix.
+static bool trace_wanted(const parser_t &parser, size_t depth, const wcstring &line) {
+    auto max_depth = parser.vars().get(VAR_fish_trace_max_depth);
+    if (!max_depth.missing_or_empty()) {
+        long max = fish_wcstol(max_depth->as_string().c_str());
+        if (!errno && max >= 0 && depth > static_cast<unsigned long>(max)) return false;
+    }
+
+    auto filter = parser.vars().get(VAR_fish_trace_filter);
+    if (filter.missing_or_empty()) return true;
+    for (const wcstring &pattern : filter->as_list()) {
+        if (wildcard_match(line, parse_util_unescape_wildcards(pattern))) return true;
+    }
+    return false;
+}
+
 /// Trace an "argv": a list of arguments where the first is the command.
 void trace_argv(const parser_t &parser, const wchar_t *command, const wcstring_list_t &argv) {
-    // Format into a string to prevent interleaving with flog in other threads.
-    // Add the + prefix.
-    wcstring trace_text(parser.blocks().size() - 1, L'-');
-    trace_text.push_back(L'>');
+    size_t depth = parser.blocks().size() - 1;
 
-    if (command && command[0]) {
-        trace_text.push_back(L' ');
-        trace_text.append(command);
-    }
+    wcstring line;
+    if (command && command[0]) line.append(command);
     for (const wcstring &arg : argv) {
-        trace_text.push_back(L' ');
-        trace_text.append(escape_string(arg, ESCAPE_ALL));
+        if (!line.empty()) line.push_back(L' ');
+        line.append(escape_string(arg, ESCAPE_ALL));
+    }
+    if (!trace_wanted(parser, depth, line)) return;
+
+    // Format into a string to prevent interleaving with flog in other threads.
+    auto format = parser.vars().get(VAR_fish_trace_format);
+    wcstring style = format ? format->as_string() : wcstring{};
+    double elapsed = (get_time() - s_start_time) / 1E6;
+    wcstring trace_text;
+    if (style == L"json") {
+        // One object per line, with the arguments unescaped.
+        append_format(trace_text, L"{\"time\":%.6f,\"depth\":%lu", elapsed,
+                      static_cast<unsigned long>(depth));
+        const wchar_t *filename = parser.current_filename();
+        trace_text.append(L",\"file\":");
+        trace_text.append(filename ? json_string(filename) : L"null");
+        append_format(trace_text, L",\"line\":%d", parser.get_lineno());
+        if (command && command[0]) {
+            trace_text.append(L",\"keyword\":");
+            trace_text.append(json_string(command));
+        }
+        trace_text.append(L",\"argv\":[");
+        for (size_t i = 0; i < argv.size(); i++) {
+            if (i > 0) trace_text.push_back(L',');
+            trace_text.append(json_string(argv.at(i)));
+        }
+        trace_text.append(L"]}");
+    } else {
+        if (style == L"time") append_format(trace_text, L"%.6f ", elapsed);
+        // Add the + prefix.
+        trace_text.append(depth, L'-');
+        trace_text.push_back(L'>');
+        if (!line.empty()) trace_text.push_back(L' ');
+        trace_text.append(line);
     }
     trace_text.push_back(L'\n');
-    log_extra_to_flog_file(trace_text);
+    write_trace(parser, trace_text);
 }
 
 void trace_if_enabled(const parser_t &parser, const wchar_t *command, const wcstring_list_t &argv) {
@@ -268,6 +268,35 @@ size_t ifind(const std::string &haystack, const std::string &needle, bool fuzzy)
     return fuzzy ? ifind_impl<true>(haystack, needle) : ifind_impl<false>(haystack, needle);
 }
 
+maybe_t<size_t> string_edit_distance(const wcstring &a, const wcstring &b, size_t max_distance) {
+    size_t len_diff = a.size() > b.size() ? a.size() - b.size() : b.size() - a.size();
+    if (len_diff > max_distance) return none();
+
+    // The rows of the distances between the prefixes of a and b, for the last two characters of a
+    // and the current one.
+    std::vector<size_t> before_last(b.size() + 1), last(b.size() + 1), current(b.size() + 1);
+    for (size_t j = 0; j <= b.size(); j++) last[j] = j;
+    for (size_t i = 1; i <= a.size(); i++) {
+        current[0] = i;
+        size_t row_min = current[0];
+        for (size_t j = 1; j <= b.size(); j++) {
+            size_t cost = a[i - 1] == b[j - 1] ? 0 : 1;
+            size_t dist = std::min({last[j] + 1, current[j - 1] + 1, last[j - 1] + cost});
+            if (i > 1 && j > 1 && a[i - 1] == b[j - 2] && a[i - 2] == b[j - 1]) {
+                dist = std::min(dist, before_last[j - 2] + 1);
+            }
+            current[j] = dist;
+            row_min = std::min(row_min, dist);
+        }
+        // The distance can only grow from here.
+        if (row_min > max_distance) return none();
+        std::swap(before_last, last);
+        std::swap(last, current);
+    }
+    if (last[b.size()] > max_distance) return none();
+    return last[b.size()];
+}
+
 wcstring_list_t split_string(const wcstring &val, wchar_t sep) {
     wcstring_list_t out;
     size_t pos = 0, end = val.size();
@@ -117,6 +117,11 @@ inline maybe_t<string_fuzzy_match_t> string_fuzzy_match_string(const wcstring &s
     return string_fuzzy_match_t::try_create(string, match_against, anchor_start);
 }
 
+/// \return the number of insertions, deletions and substitutions of single characters and swaps of
+/// adjacent characters needed to turn \p a into \p b, or none() if it is more than \p max_distance.
+/// This stops as soon as the distance exceeds \p max_distance, so it is cheap for unlike strings.
+maybe_t<size_t> string_edit_distance(const wcstring &a, const wcstring &b, size_t max_distance);
+
 /// Split a string by a separator character.
 wcstring_list_t split_string(const wcstring &val, wchar_t sep);
 
@@ -0,0 +1,1076 @@
+// The web-based configuration started by `fish_config`.
+//
+// This is a small HTTP server for the pages in the web_config directory of fish's data directory.
+// Their requests for colors, functions, variables and so on are answered from the running shell,
+// one at a time on the main thread. The server only listens on the loopback interface, and only
+// answers requests whose path starts with a random key. The browser is given the key through a
+// temporary file, so it does not show up on any command line.
+#include "config.h"  // IWYU pragma: keep
+
+#include "web_config.h"
+
+#include <errno.h>
+#include <fcntl.h>
+#include <netinet/in.h>
+#include <poll.h>
+#include <stdlib.h>
+#include <strings.h>
+#include <sys/socket.h>
+#include <sys/stat.h>
+#include <sys/time.h>
+#include <unistd.h>
+
+#include <algorithm>
+#include <cstring>
+#include <cwchar>
+#include <map>
+#include <memory>
+#include <random>
+#include <string>
+#include <utility>
+#include <vector>
+
+#include "builtin.h"
+#include "color.h"
+#include "common.h"
+#include "env.h"
+#include "event.h"
+#include "exec.h"
+#include "expand.h"
+#include "fallback.h"  // IWYU pragma: keep
+#include "fds.h"
+#include "fish_indent_common.h"
+#include "function.h"
+#include "highlight.h"
+#include "history.h"
+#include "input.h"
+#include "io.h"
+#include "json.h"
+#include "output.h"
+#include "parser.h"
+#include "reader.h"
+#include "sample_prompt.h"
+#include "signal.h"
+#include "theme.h"
+#include "wcstringutil.h"
+#include "wutil.h"  // IWYU pragma: keep
+
+/// The range of ports the server tries to listen on.
+static constexpr int first_port = 8000;
+static constexpr int last_port = 9000;
+
+/// Requests whose headers or body are larger than this are refused.
+static constexpr size_t max_request_size = 16 * 1024 * 1024;
+
+/// The tabs of the web pages. fish_config accepts any prefix of their names.
+static const wchar_t *const tab_names[] = {L"functions", L"prompt",   L"colors",
+                                           L"variables", L"history",  L"bindings",
+                                           L"abbreviations"};
+
+/// The colors the web pages know about, with their descriptions. These are listed even if their
+/// variable is not set, so they can be set again after being erased.
+static const struct {
+    const wchar_t *name;
+    const wchar_t *description;
+} color_descriptions[] = {
+    {L"normal", L"Default text"},
+    {L"command", L"Ordinary commands"},
+    {L"quote", L"Text within quotes"},
+    {L"redirection", L"Like | and >"},
+    {L"end", L"Like ; and &"},
+    {L"error", L"Potential errors"},
+    {L"param", L"Command parameters"},
+    {L"comment", L"Comments start with #"},
+    {L"match", L"Matching parenthesis"},
+    {L"selection", L"Selected text"},
+    {L"search_match", L"History searching"},
+    {L"history_current", L"Directory history"},
+    {L"operator", L"Like * and ~"},
+    {L"escape", L"Escapes like \\n"},
+    {L"cwd", L"Current directory"},
+    {L"cwd_root", L"cwd for root user"},
+    {L"valid_path", L"Valid paths"},
+    {L"autosuggestion", L"Suggested completion"},
+    {L"user", L"Username in the prompt"},
+    {L"host", L"Hostname in the prompt"},
+    {L"cancel", L"The ^C cancel indicator"},
+};
+
+/// The HTML colors of the named colors of set_color.
+static const struct {
+    const wchar_t *name;
+    const wchar_t *html;
+} named_colors[] = {
+    {L"black", L"000000"},    {L"red", L"800000"},       {L"green", L"008000"},
+    {L"brown", L"725000"},    {L"yellow", L"808000"},    {L"blue", L"000080"},
+    {L"magenta", L"800080"},  {L"purple", L"800080"},    {L"cyan", L"008080"},
+    {L"grey", L"e5e5e5"},     {L"brgrey", L"555555"},    {L"white", L"c0c0c0"},
+    {L"brblack", L"808080"},  {L"brred", L"ff0000"},     {L"brgreen", L"00ff00"},
+    {L"brbrown", L"ffff00"},  {L"bryellow", L"ffff00"},  {L"brblue", L"0000ff"},
+    {L"brmagenta", L"ff00ff"}, {L"brpurple", L"ff00ff"}, {L"brcyan", L"00ffff"},
+    {L"brwhite", L"ffffff"},
+};
+
+/// Descriptions of the keys bound with `bind -k` whose terminfo names are not clear.
+static const struct {
+    const wchar_t *name;
+    const wchar_t *description;
+} key_descriptions[] = {
+    {L"btab", L"Shift Tab"},      {L"dc", L"Delete"},          {L"down", L"Down Arrow"},
+    {L"left", L"Left Arrow"},     {L"npage", L"Page Down"},    {L"ppage", L"Page Up"},
+    {L"right", L"Right Arrow"},   {L"sdc", L"Shift Delete"},   {L"shome", L"Shift Home"},
+    {L"sleft", L"Shift Left"},    {L"sright", L"Shift Right"}, {L"up", L"Up Arrow"},
+};
+
+/// Descriptions of the escape sequences terminals commonly send for special keys.
+static const struct {
+    const wchar_t *seq;
+    const wchar_t *description;
+} escape_sequence_descriptions[] = {
+    {L"\x1B[A", L"Up Arrow"},   {L"\x1B[B", L"Down Arrow"}, {L"\x1B[C", L"Right Arrow"},
+    {L"\x1B[D", L"Left Arrow"}, {L"\x1B[H", L"Home"},       {L"\x1B[F", L"End"},
+    {L"\x1BOA", L"Up Arrow"},   {L"\x1BOB", L"Down Arrow"}, {L"\x1BOC", L"Right Arrow"},
+    {L"\x1BOD", L"Left Arrow"}, {L"\x1BOH", L"Home"},       {L"\x1BOF", L"End"},
+    {L"\x1B[1~", L"Home"},      {L"\x1B[2~", L"Insert"},    {L"\x1B[3~", L"Delete"},
+    {L"\x1B[4~", L"End"},       {L"\x1B[5~", L"Page Up"},   {L"\x1B[6~", L"Page Down"},
+};
+
+/// The content types of the files which are served from the web_config directory. No other
+/// files are served.
+static const struct {
+    const char *suffix;
+    const char *content_type;
+} content_types[] = {
+    {".html", "text/html; charset=utf-8"},
+    {".css", "text/css; charset=utf-8"},
+    {".js", "application/javascript; charset=utf-8"},
+    {".png", "image/png"},
+};
+
+/// The page which redirects the browser to the server.
+static const char *const redirect_template =
+    "<!DOCTYPE html>\n"
+    "<html>\n"
+    " <head>\n"
+    "  <meta http-equiv=\"refresh\" content=\"0;URL='%s'\" />\n"
+    " </head>\n"
+    " <body>\n"
+    "  <p><a href=\"%s\">Start the Fish Web config</a></p>\n"
+    " </body>\n"
+    "</html>\n";
+
+namespace {
+/// The state of the server.
+struct web_config_t {
+    parser_t &parser;
+    /// The secret which the path of every request starts with.
+    std::string key;
+    /// The directory of the web pages.
+    wcstring root;
+};
+
+/// A request from the browser.
+struct http_request_t {
+    std::string method;
+    std::string path;
+    std::string content_type;
+    std::string body;
+};
+
+/// The parameters of a POST request, from a form or a JSON object. Only the first value of each
+/// is kept.
+using post_params_t = std::map<wcstring, wcstring>;
+}  // namespace
+
+/// \return a random key of 32 hex digits.
+static std::string make_key() {
+    std::random_device rd;
+    std::string result;
+    for (int i = 0; i < 4; i++) {
+        char buff[9];
+        snprintf(buff, sizeof buff, "%08x", static_cast<unsigned>(rd()));
+        result.append(buff);
+    }
+    return result;
+}
+
+/// \return the contents of the regular file at \p path, or none if it is not one or can't be read.
+static maybe_t<std::string> read_regular_file(const wcstring &path) {
+    autoclose_fd_t fd{wopen_cloexec(path, O_RDONLY)};
+    struct stat buf;
+    if (!fd.valid() || fstat(fd.fd(), &buf) < 0 || !S_ISREG(buf.st_mode)) return none();
+    return read_file_contents(fd.fd());
+}
+
+/// Append \p c to \p out, escaped for HTML.
+static void append_html_escaped(wcstring *out, wchar_t c) {
+    switch (c) {
+        case L'&':
+            out->append(L"&amp;");
+            break;
+        case L'<':
+            out->append(L"&lt;");
+            break;
+        case L'>':
+            out->append(L"&gt;");
+            break;
+        case L'"':
+            out->append(L"&quot;");
+            break;
+        case L'\'':
+            out->append(L"&#x27;");
+            break;
+        default:
+            out->push_back(c);
+            break;
+    }
+}
+
+/// \return the HTML color for the color \p comp of a color variable, like 'red' or 'FFA000', or an
+/// empty string if it is not a color.
+static wcstring parse_one_color(const wcstring &comp) {
+    for (const auto &color : named_colors) {
+        if (comp == color.name) return color.html;
+    }
+    if (comp.size() >= 3 && std::all_of(comp.begin(), comp.begin() + 3, iswxdigit)) return comp;
+    return wcstring();
+}
+
+/// \return the color \p name with the description \p description and the values \p values, as a
+/// JSON object with its foreground and background colors and its attributes.
+static wcstring json_color(const wcstring &name, const wchar_t *description,
+                           const wcstring_list_t &values) {
+    wcstring color, background;
+    bool bold = false, underline = false, italics = false, dim = false, reverse = false;
+    for (const wcstring &value : values) {
+        // A variable may have been set to a single value with all options.
+        for (const wcstring &comp : split_string(value, L' ')) {
+            if (comp == L"--bold") {
+                bold = true;
+            } else if (comp == L"--underline") {
+                underline = true;
+            } else if (comp == L"--italics") {
+                italics = true;
+            } else if (comp == L"--dim") {
+                dim = true;
+            } else if (comp == L"--reverse") {
+                reverse = true;
+            } else if (string_prefixes_string(L"--background=", comp)) {
+                if (background.empty()) background = parse_one_color(comp.substr(13));
+            } else if (color.empty()) {
+                color = parse_one_color(comp);
+            }
+        }
+    }
+    if (color.empty()) color = L"normal";
+
+    auto json_bool = [](bool val) { return val ? L"true" : L"false"; };
+    return L"{\"name\":" + json_string(name) + L",\"description\":" + json_string(description) +
+           L",\"color\":" + json_string(color) + L",\"background\":" + json_string(background) +
+           L",\"bold\":" + json_bool(bold) + L",\"underline\":" + json_bool(underline) +
+           L",\"italics\":" + json_bool(italics) + L",\"dim\":" + json_bool(dim) +
+           L",\"reverse\":" + json_bool(reverse) + L"}";
+}
+
+/// \return the fish_color_* variables as a JSON array of colors, sorted by name.
+static wcstring get_colors(const environment_t &vars) {
+    static const wchar_t prefix[] = L"fish_color_";
+    const size_t prefix_len = std::wcslen(prefix);
+    wcstring_list_t names;
+    for (const wcstring &var_name : vars.get_names(0)) {
+        if (string_prefixes_string(prefix, var_name)) names.push_back(var_name.substr(prefix_len));
+    }
+    std::sort(names.begin(), names.end());
+
+    auto describe = [](const wcstring &name) {
+        for (const auto &color : color_descriptions) {
+            if (name == color.name) return color.description;
+        }
+        return L"";
+    };
+    wcstring result = L"[";
+    for (const wcstring &name : names) {
+        auto var = vars.get(prefix + name);
+        if (!var) continue;
+        if (result.size() > 1) result.push_back(L',');
+        result.append(json_color(name, describe(name), var->as_list()));
+    }
+    for (const auto &color : color_descriptions) {
+        if (contains(names, color.name)) continue;
+        if (result.size() > 1) result.push_back(L',');
+        result.append(json_color(color.name, color.description, {}));
+    }
+    result.push_back(L']');
+    return result;
+}
+
+/// \return the variables as a JSON array of objects with their name, their value and whether
+/// they are universal or exported, sorted by name ignoring case.
+static wcstring get_variables(const environment_t &vars) {
+    wcstring_list_t names = vars.get_names(0);
+    std::sort(names.begin(), names.end(), [](const wcstring &a, const wcstring &b) {
+        return wcscasecmp(a.c_str(), b.c_str()) < 0;
+    });
+    wcstring result = L"[";
+    for (const wcstring &name : names) {
+        auto var = vars.get(name);
+        if (!var) continue;
+        wcstring flags;
+        if (vars.get(name, ENV_UNIVERSAL)) flags = L"universal";
+        if (var->exports()) flags.append(flags.empty() ? L"exported" : L", exported");
+        if (result.size() > 1) result.push_back(L',');
+        result.append(L"{\"name\":" + json_string(name) +
+                      L",\"value\":" + json_string(expand_escape_variable(*var)) +
+                      L",\"Flags\":" + json_string(flags) + L"}");
+    }
+    result.push_back(L']');
+    return result;
+}
+
+/// \return the history of the session.
+static std::shared_ptr<history_t> session_history(const parser_t &parser) {
+    std::shared_ptr<history_t> history = reader_get_history();
+    if (!history) history = history_t::with_name(history_session_id(parser.vars()));
+    return history;
+}
+
+/// \return the HTML color of the color with index \p idx in the 256 color palette, or an empty
+/// string if there is none.
+static wcstring palette_html_color(long idx) {
+    static const wchar_t *const ansi_colors[] = {
+        L"black",   L"#AA0000", L"#00AA00", L"#AA5500", L"#0000AA", L"#AA00AA",
+        L"#00AAAA", L"#AAAAAA", L"#555555", L"#FF5555", L"#55FF55", L"#FFFF55",
+        L"#5555FF", L"#FF55FF", L"#55FFFF", L"white"};
+    if (idx < 0) return wcstring();
+    if (idx < 16) return ansi_colors[idx];
+    if (idx < 232) {
+        // A 6x6x6 color cube.
+        static const int levels[] = {0x00, 0x5f, 0x87, 0xaf, 0xd7, 0xff};
+        idx -= 16;
+        return format_string(L"#%02x%02x%02x", levels[idx / 36], levels[idx / 6 % 6],
+                             levels[idx % 6]);
+    }
+    if (idx < 256) {
+        // A ramp of grays.
+        int gray = 8 + 10 * static_cast<int>(idx - 232);
+        return format_string(L"#%02x%02x%02x", gray, gray, gray);
+    }
+    return wcstring();
+}
+
+/// \return the HTML color which the SGR escape sequence with the parameters \p params sets, an
+/// empty string if it resets the colors, or none() if it does neither.
+static maybe_t<wcstring> sgr_html_color(const wcstring &params) {
+    if (params.empty() || params == L"0") return wcstring();
+    std::vector<long> nums;
+    for (const wcstring &param : split_string(params, L';')) {
+        long num = fish_wcstol(param.c_str());
+        if (errno) return none();
+        nums.push_back(num);
+    }
+    if (nums.size() == 5 && nums[0] == 38 && nums[1] == 2) {
+        auto clamp = [](long val) { return static_cast<int>(std::min(std::max(val, 0L), 255L)); };
+        return format_string(L"#%02x%02x%02x", clamp(nums[2]), clamp(nums[3]), clamp(nums[4]));
+    }
+    if (nums.size() == 3 && nums[0] == 38 && nums[1] == 5) return palette_html_color(nums[2]);
+    if (nums.size() == 1 && nums[0] >= 30 && nums[0] <= 37) return palette_html_color(nums[0] - 30);
+    if (nums.size() == 1 && nums[0] >= 90 && nums[0] <= 97) return palette_html_color(nums[0] - 82);
+    return none();
+}
+
+/// \return the output of a prompt as HTML, with its foreground colors. Set \p out_width to the
+/// number of characters in its longest line.
+static wcstring ansi_to_html(const wcstring &ansi, size_t *out_width) {
+    wcstring result;
+    wcstring color, open_color;
+    size_t width = 0;
+    *out_width = 0;
+    for (size_t pos = 0; pos < ansi.size();) {
+        wchar_t c = ansi.at(pos);
+        if (c == L'\x1B') {
+            // The escape sequences we care about end with 'm'. tmux ends its sgr0 with Control+O.
+            size_t end = ansi.find(L'm', pos);
+            if (end == wcstring::npos) break;
+            wcstring seq = ansi.substr(pos + 1, end - pos - 1);
+            pos = end + 1;
+            if (pos < ansi.size() && ansi.at(pos) == L'\x0F') pos++;
+            // Only the last CSI counts, for sequences like sgr0 in xterm, "\e(B\e[m".
+            size_t csi = seq.rfind(L'[');
+            if (csi == wcstring::npos) continue;
+            if (auto html = sgr_html_color(seq.substr(csi + 1))) color = *html;
+            continue;
+        }
+        pos++;
+        if (c == L'\n') {
+            width = 0;
+        } else if (c < L' ') {
+            continue;
+        } else {
+            *out_width = std::max(*out_width, ++width);
+        }
+        if (color != open_color) {
+            if (!open_color.empty()) result.append(L"</span>");
+            if (!color.empty()) result.append(L"<span style=\"color: " + color + L"\">");
+            open_color = color;
+        }
+        append_html_escaped(&result, c);
+    }
+    if (!open_color.empty()) result.append(L"</span>");
+    return result;
+}
+
+/// \return the font size in which a prompt whose longest line has \p width characters fits in
+/// the web pages.
+static const wchar_t *font_size_for_prompt(size_t width) {
+    if (width >= 70) return L"8pt";
+    if (width >= 60) return L"10pt";
+    if (width >= 50) return L"11pt";
+    if (width >= 40) return L"13pt";
+    if (width >= 30) return L"15pt";
+    if (width >= 25) return L"16pt";
+    if (width >= 20) return L"17pt";
+    return L"18pt";
+}
+
+/// \return a prompt as a JSON object for the web pages, with the text of its function, its
+/// output \p output as HTML and the members \p extras.
+static wcstring json_prompt(const wcstring &function, const wcstring_list_t &output,
+                            const std::vector<std::pair<wcstring, wcstring>> &extras) {
+    size_t width;
+    wcstring demo = ansi_to_html(join_strings(output, L'\n'), &width);
+    wcstring result = L"{\"function\":" + json_string(function) + L",\"demo\":" +
+                      json_string(demo) + L",\"font_size\":" +
+                      json_string(font_size_for_prompt(width));
+    for (const auto &extra : extras) {
+        result.append(L"," + json_string(extra.first) + L":" + json_string(extra.second));
+    }
+    result.push_back(L'}');
+    return result;
+}
+
+/// \return the prompt of the session, with the output it has after a failed command.
+static wcstring current_prompt(parser_t &parser) {
+    wcstring function;
+    if (function_exists(L"fish_prompt", parser)) function = functions_def(L"fish_prompt");
+    return json_prompt(trim(function), sample_prompt_run_current(parser).left,
+                       {{L"name", L"Current"}});
+}
+
+/// \return the current prompt and the sample prompts as a JSON array.
+static wcstring get_sample_prompts(parser_t &parser) {
+    wcstring result = L"[" + current_prompt(parser);
+    for (const wcstring &name : sample_prompt_list()) {
+        auto prompt = sample_prompt_load(name);
+        if (!prompt) continue;
+        result.append(L"," + json_prompt(prompt->function,
+                                         sample_prompt_run(parser, prompt->function).left,
+                                         prompt->extras));
+    }
+    result.push_back(L']');
+    return result;
+}
+
+/// \return a description of the key sequence \p seq, like "CTRL - f" or "Up Arrow".
+static wcstring describe_key_sequence(const wcstring &seq) {
+    wcstring name;
+    if (input_terminfo_get_name(seq, &name)) {
+        for (const auto &key : key_descriptions) {
+            if (name == key.name) return key.description;
+        }
+        if (!name.empty()) name.at(0) = towupper(name.at(0));
+        return name;
+    }
+    for (const auto &esc : escape_sequence_descriptions) {
+        if (seq == esc.seq) return esc.description;
+    }
+    if (seq.empty()) return L"Any other key";
+
+    // Keys with modifiers, like "\e[1;5C" for Control+Right.
+    if (seq.size() == 6 && string_prefixes_string(L"\x1B[1;", seq)) {
+        const wchar_t *modifier = nullptr;
+        switch (seq.at(4)) {
+            case L'2':
+                modifier = L"SHIFT - ";
+                break;
+            case L'3':
+            case L'9':
+                modifier = L"ALT - ";
+                break;
+            case L'5':
+                modifier = L"CTRL - ";
+                break;
+            default:
+                break;
+        }
+        if (modifier) return modifier + describe_key_sequence(wcstring(L"\x1B[") + seq.at(5));
+    }
+    if (seq.size() > 1 && seq.at(0) == L'\x1B') {
+        return L"ALT - " + describe_key_sequence(seq.substr(1));
+    }
+
+    wcstring result;
+    bool last_was_named = false;
+    for (wchar_t c : seq) {
+        wcstring key;
+        if (c == L'\r' || c == L'\n') {
+            key = L"Enter";
+        } else if (c == L'\t') {
+            key = L"Tab";
+        } else if (c == L'\b' || c == 0x7F) {
+            key = L"Backspace";
+        } else if (c == L'\x1B') {
+            key = L"ESC";
+        } else if (c == L' ') {
+            key = L"Space";
+        } else if (c >= 1 && c <= 26) {
+            key = L"CTRL - ";
+            key.push_back(L'a' + c - 1);
+        } else if (c < L' ') {
+            key = L"CTRL - ";
+            key.push_back(L'@' + c);
+        }
+        bool named = !key.empty();
+        if (!named) key.push_back(c);
+        if (!result.empty() && (named || last_was_named)) result.push_back(L' ');
+        result.append(key);
+        last_was_named = named;
+    }
+    return result;
+}
+
+/// \return the bindings as a JSON array with an object for each command, which lists the key
+/// sequences it is bound to.
+static wcstring get_bindings() {
+    struct binding_t {
+        wcstring command;
+        // The descriptions of the key sequences, with the bind commands which bind them.
+        std::vector<std::pair<wcstring, wcstring_list_t>> keys;
+    };
+    std::vector<binding_t> bindings;
+
+    auto mappings = input_mappings();
+    for (bool user : {false, true}) {
+        for (const input_mapping_name_t &name : mappings->get_names(user)) {
+            wcstring_list_t cmds;
+            wcstring sets_mode;
+            if (!mappings->get(name.seq, name.mode, &cmds, user, &sets_mode)) continue;
+            // Inserting text and doing nothing are not interesting.
+            if (cmds.size() == 1 &&
+                (cmds.front() == L"self-insert" || cmds.front() == L"begin;end")) {
+                continue;
+            }
+
+            // The command which creates the binding, as `bind` would list it.
+            wcstring bind_cmd = L"bind";
+            if (!user) bind_cmd.append(L" --preset");
+            if (name.mode != DEFAULT_BIND_MODE) {
+                bind_cmd.append(L" -M " + escape_string(name.mode, ESCAPE_ALL));
+            }
+            if (!sets_mode.empty() && sets_mode != name.mode) {
+                bind_cmd.append(L" -m " + escape_string(sets_mode, ESCAPE_ALL));
+            }
+            wcstring tname;
+            if (input_terminfo_get_name(name.seq, &tname)) {
+                bind_cmd.append(L" -k " + tname);
+            } else {
+                bind_cmd.append(L" " + escape_string(name.seq, ESCAPE_ALL));
+            }
+            wcstring command;
+            for (const wcstring &cmd : cmds) {
+                if (!command.empty()) command.push_back(L' ');
+                command.append(escape_string(cmd, ESCAPE_ALL));
+            }
+            bind_cmd.append(L" " + command);
+
+            auto binding = std::find_if(bindings.begin(), bindings.end(),
+                                        [&](const binding_t &b) { return b.command == command; });
+            if (binding == bindings.end()) {
+                bindings.push_back(binding_t{command, {}});
+                binding = bindings.end() - 1;
+            }
+            wcstring description = describe_key_sequence(name.seq);
+            auto key = std::find_if(binding->keys.begin(), binding->keys.end(),
+                                    [&](const std::pair<wcstring, wcstring_list_t> &k) {
+                                        return k.first == description;
+                                    });
+            if (key == binding->keys.end()) {
+                binding->keys.emplace_back(description, wcstring_list_t{});
+                key = binding->keys.end() - 1;
+            }
+            key->second.push_back(bind_cmd);
+        }
+    }
+
+    wcstring result = L"[";
+    for (const binding_t &binding : bindings) {
+        if (result.size() > 1) result.push_back(L',');
+        result.append(L"{\"command\":" + json_string(binding.command) + L",\"bindings\":[");
+        bool first = true;
+        for (const auto &key : binding.keys) {
+            if (!first) result.push_back(L',');
+            first = false;
+            result.append(L"{\"readable_binding\":" + json_string(key.first) +
+                          L",\"raw_bindings\":" + json_string_list(key.second) + L"}");
+        }
+        result.append(L"],\"description\":null}");
+    }
+    result.push_back(L']');
+    return result;
+}
+
+/// \return the abbreviations as a JSON array of objects with their word and phrase.
+static wcstring get_abbreviations_json(const environment_t &vars) {
+    wcstring result = L"[";
+    for (const auto &abbr : get_abbreviations(vars)) {
+        if (result.size() > 1) result.push_back(L',');
+        result.append(L"{\"word\":" + json_string(abbr.first) +
+                      L",\"phrase\":" + json_string(abbr.second) + L"}");
+    }
+    result.push_back(L']');
+    return result;
+}
+
+/// \return the answer to the GET request for \p path as JSON, or none() if it is not a request
+/// for data but for a file.
+static maybe_t<wcstring> handle_get(const web_config_t &cfg, const std::string &path) {
+    parser_t &parser = cfg.parser;
+    if (path == "/colors/") {
+        return get_colors(parser.vars());
+    } else if (path == "/functions/") {
+        wcstring_list_t names = function_get_names(false);
+        std::sort(names.begin(), names.end());
+        return json_string_list(names);
+    } else if (path == "/variables/") {
+        return get_variables(parser.vars());
+    } else if (path == "/history/") {
+        wcstring_list_t items;
+        session_history(parser)->get_history(items);
+        return json_string_list(items);
+    } else if (path == "/sample_prompts/") {
+        return get_sample_prompts(cfg.parser);
+    } else if (path == "/bindings/") {
+        return get_bindings();
+    } else if (path == "/abbreviations/") {
+        return get_abbreviations_json(parser.vars());
+    }
+    return none();
+}
+
+/// \return whether the value of a checkbox in a form is true.
+static bool parse_bool(const wcstring &val) {
+    if (val.empty()) return false;
+    wchar_t c = towlower(val.at(0));
+    return c != L'f' && c != L'0';
+}
+
+/// Set the color in \p params, which are those of the form of the colors tab, as a universal
+/// variable. \return the answer to the request as JSON.
+static wcstring set_color(parser_t &parser, const post_params_t &params) {
+    auto get = [&](const wchar_t *key) {
+        auto param = params.find(key);
+        return param == params.end() ? wcstring() : param->second;
+    };
+    wcstring name = get(L"what");
+    // Pager colors are given with their full name.
+    wcstring var_name = string_prefixes_string(L"fish_", name) ? name : L"fish_color_" + name;
+    if (name.empty() || !theme_is_color_variable(var_name)) return json_string(L"Bad request");
+
+    wcstring color = get(L"color");
+    wcstring_list_t values;
+    for (const wcstring &comp : split_string(color.empty() ? L"normal" : color, L' ')) {
+        if (!comp.empty()) values.push_back(comp);
+    }
+    wcstring background = get(L"background_color");
+    if (!background.empty()) values.push_back(L"--background=" + background);
+    for (const wchar_t *attr : {L"bold", L"underline", L"italics", L"dim", L"reverse"}) {
+        if (parse_bool(get(attr))) values.push_back(wcstring(L"--") + attr);
+    }
+
+    parser.set_var_and_fire(var_name, ENV_UNIVERSAL, std::move(values));
+    // A global variable would hide the universal one.
+    if (parser.vars().get(var_name, ENV_GLOBAL)) {
+        std::vector<event_t> evts;
+        parser.vars().remove(var_name, ENV_GLOBAL, &evts);
+        for (const auto &evt : evts) {
+            event_fire(parser, evt);
+        }
+    }
+    return json_string(L"");
+}
+
+/// \return the definition of the function \p name, indented and highlighted as HTML.
+static wcstring function_html(parser_t &parser, const wcstring &name) {
+    if (name.empty() || !function_exists(name, parser)) return wcstring();
+    wcstring def = prettify(functions_def(name), indent_options_t{});
+    std::vector<highlight_spec_t> colors;
+    highlight_shell(def, colors, parser.context());
+    return html_colorize(def, colors);
+}
+
+/// Run the abbr function with the arguments \p args, which must be escaped. \return a JSON array
+/// with "OK" or the error message it printed.
+static wcstring run_abbr(parser_t &parser, const wcstring &args) {
+    wcstring_list_t output;
+    exec_subshell(L"abbr " + args + L" 2>&1", parser, output, false /* apply_exit_status */);
+    if (output.empty()) return L"[\"OK\"]";
+    return json_string_list({join_strings(output, L'\n')});
+}
+
+/// \return the answer to the POST request for \p path with the parameters \p params as JSON, or
+/// none() if there is no such request.
+static maybe_t<wcstring> handle_post(const web_config_t &cfg, const std::string &path,
+                                     const post_params_t &params) {
+    parser_t &parser = cfg.parser;
+    auto get = [&](const wchar_t *key) {
+        auto param = params.find(key);
+        return param == params.end() ? wcstring() : param->second;
+    };
+    if (path == "/set_color/") {
+        return set_color(parser, params);
+    } else if (path == "/get_function/") {
+        return json_string_list({function_html(parser, get(L"what"))});
+    } else if (path == "/delete_history_item/") {
+        auto history = session_history(parser);
+        history->remove(get(L"what"));
+        history->save();
+        return wcstring(L"[\"OK\"]");
+    } else if (path == "/set_prompt/") {
+        // Define the prompt in this session too.
+        bool ok = sample_prompt_use(parser, get(L"fish_prompt"));
+        return wcstring(ok ? L"[\"OK\"]" : L"[\"Unable to set prompt\"]");
+    } else if (path == "/save_abbreviation/") {
+        return run_abbr(parser, L"--add -- " + escape_string(get(L"word"), ESCAPE_ALL) + L" " +
+                                    escape_string(get(L"phrase"), ESCAPE_ALL));
+    } else if (path == "/remove_abbreviation/") {
+        return run_abbr(parser, L"--erase -- " + escape_string(get(L"word"), ESCAPE_ALL));
+    }
+    return none();
+}
+
+/// \return \p str with the %XX escapes of a URL decoded, and also + if \p plus_is_space is set.
+static std::string url_decode(const std::string &str, bool plus_is_space) {
+    std::string result;
+    for (size_t i = 0; i < str.size(); i++) {
+        char c = str.at(i);
+        if (c == '+' && plus_is_space) {
+            result.push_back(' ');
+        } else if (c == '%' && i + 2 < str.size() &&
+                   isxdigit(static_cast<unsigned char>(str[i + 1])) &&
+                   isxdigit(static_cast<unsigned char>(str[i + 2]))) {
+            result.push_back(static_cast<char>(std::stoi(str.substr(i + 1, 2), nullptr, 16)));
+            i += 2;
+        } else {
+            result.push_back(c);
+        }
+    }
+    return result;
+}
+
+/// \return the parameters of the POST request \p req, which may be a form or a JSON object.
+static post_params_t parse_params(const http_request_t &req) {
+    post_params_t result;
+    std::string content_type = req.content_type.substr(0, req.content_type.find(';'));
+    if (strcasecmp(content_type.c_str(), "application/x-www-form-urlencoded") == 0) {
+        size_t start = 0;
+        while (start <= req.body.size()) {
+            size_t end = req.body.find('&', start);
+            if (end == std::string::npos) end = req.body.size();
+            std::string pair = req.body.substr(start, end - start);
+            size_t eq = pair.find('=');
+            wcstring key = str2wcstring(url_decode(pair.substr(0, eq), true));
+            wcstring value;
+            if (eq != std::string::npos) {
+                value = str2wcstring(url_decode(pair.substr(eq + 1), true));
+            }
+            if (!key.empty()) result.emplace(std::move(key), std::move(value));
+            start = end + 1;
+        }
+    } else if (strcasecmp(content_type.c_str(), "application/json") == 0) {
+        auto json = json_parse(str2wcstring(req.body));
+        if (json) {
+            for (const auto &member : json->members) {
+                if (member.second.type == json_value_t::type_t::string) {
+                    result.emplace(member.first, member.second.string);
+                }
+            }
+        }
+    }
+    return result;
+}
+
+/// Read a request from the connection \p fd into \p out_req.
+/// \return false if the connection was closed or timed out, or the request is malformed or too
+/// large.
+static bool read_request(int fd, http_request_t *out_req) {
+    std::string input;
+    char buff[4096];
+    ssize_t amt;
+    size_t headers_end;
+    while ((headers_end = input.find("\r\n\r\n")) == std::string::npos) {
+        if (input.size() > max_request_size) return false;
+        if ((amt = read_loop(fd, buff, sizeof buff)) <= 0) return false;
+        input.append(buff, amt);
+    }
+    std::string headers = input.substr(0, headers_end);
+    input.erase(0, headers_end + 4);
+
+    size_t line_end = headers.find("\r\n");
+    std::string request_line = headers.substr(0, line_end);
+    size_t path_start = request_line.find(' ');
+    if (path_start == std::string::npos) return false;
+    size_t path_end = request_line.find(' ', path_start + 1);
+    if (path_end == std::string::npos) return false;
+    out_req->method = request_line.substr(0, path_start);
+    out_req->path = request_line.substr(path_start + 1, path_end - path_start - 1);
+
+    size_t content_length = 0;
+    while (line_end != std::string::npos) {
+        size_t start = line_end + 2;
+        line_end = headers.find("\r\n", start);
+        std::string line = headers.substr(start, line_end == std::string::npos
+                                                     ? std::string::npos
+                                                     : line_end - start);
+        size_t colon = line.find(':');
+        if (colon == std::string::npos) continue;
+        std::string name = line.substr(0, colon);
+        size_t value_start = line.find_first_not_of(" \t", colon + 1);
+        std::string value = value_start == std::string::npos ? "" : line.substr(value_start);
+        if (strcasecmp(name.c_str(), "content-length") == 0) {
+            errno = 0;
+            char *end = nullptr;
+            unsigned long long len = strtoull(value.c_str(), &end, 10);
+            if (errno || *end != '\0' || len > max_request_size) return false;
+            content_length = static_cast<size_t>(len);
+        } else if (strcasecmp(name.c_str(), "content-type") == 0) {
+            out_req->content_type = value;
+        }
+    }
+
+    while (input.size() < content_length) {
+        if ((amt = read_loop(fd, buff, sizeof buff)) <= 0) return false;
+        input.append(buff, amt);
+    }
+    out_req->body = input.substr(0, content_length);
+    return true;
+}
+
+/// Send a response with the status \p status and the contents \p body to the connection \p fd.
+static void send_response(int fd, int status, const char *content_type,
+                          const std::string &body) {
+    const char *reason;
+    switch (status) {
+        case 200:
+            reason = "OK";
+            break;
+        case 403:
+            reason = "Forbidden";
+            break;
+        case 404:
+            reason = "Not Found";
+            break;
+        default:
+            reason = "Bad Request";
+            break;
+    }
+    std::string response = "HTTP/1.0 " + std::to_string(status) + " " + reason + "\r\n";
+    response.append("Content-Type: " + std::string(content_type) + "\r\n");
+    response.append("Content-Length: " + std::to_string(body.size()) + "\r\n");
+    response.append("Cache-Control: no-store\r\nConnection: close\r\n\r\n");
+    response.append(body);
+    write_loop(fd, response.data(), response.size());
+}
+
+/// Send the file at \p path in the directory of the web pages to the connection \p fd.
+static void serve_file(const web_config_t &cfg, int fd, const std::string &path) {
+    std::string file = path == "/" ? "/index.html" : url_decode(path, false);
+    const char *content_type = nullptr;
+    for (const auto &type : content_types) {
+        size_t len = std::strlen(type.suffix);
+        if (file.size() > len && file.compare(file.size() - len, len, type.suffix) == 0) {
+            content_type = type.content_type;
+        }
+    }
+    // Don't serve anything outside the directory, or hidden files.
+    maybe_t<std::string> contents;
+    if (content_type && file.at(0) == '/' && file.find("/.") == std::string::npos &&
+        file.find('\0') == std::string::npos) {
+        contents = read_regular_file(cfg.root + str2wcstring(file));
+    }
+    if (!contents) {
+        send_response(fd, 404, "text/plain", "Not found\n");
+        return;
+    }
+    send_response(fd, 200, content_type, *contents);
+}
+
+/// Answer the request on the connection \p fd.
+static void handle_connection(const web_config_t &cfg, int fd) {
+    http_request_t req;
+    if (!read_request(fd, &req)) return;
+
+    // Compare the key in constant time, so it can't be guessed one character at a time.
+    std::string prefix = "/" + cfg.key;
+    if (req.path.size() < prefix.size()) {
+        send_response(fd, 403, "text/plain", "Forbidden\n");
+        return;
+    }
+    unsigned char diff = 0;
+    for (size_t i = 0; i < prefix.size(); i++) {
+        diff |= static_cast<unsigned char>(req.path[i] ^ prefix[i]);
+    }
+    if (diff != 0) {
+        send_response(fd, 403, "text/plain", "Forbidden\n");
+        return;
+    }
+    std::string path = req.path.substr(prefix.size());
+    path = path.substr(0, path.find('?'));
+    if (path.empty()) {
+        send_response(fd, 404, "text/plain", "Not found\n");
+        return;
+    }
+
+    if (req.method == "GET") {
+        if (auto json = handle_get(cfg, path)) {
+            send_response(fd, 200, "application/json", wcs2string(*json));
+        } else {
+            serve_file(cfg, fd, path);
+        }
+    } else if (req.method == "POST") {
+        if (auto json = handle_post(cfg, path, parse_params(req))) {
+            send_response(fd, 200, "application/json", wcs2string(*json));
+        } else {
+            send_response(fd, 404, "text/plain", "Not found\n");
+        }
+    } else {
+        send_response(fd, 400, "text/plain", "Bad request\n");
+    }
+}
+
+/// Write a page which redirects to \p url to a new file in a new temporary directory.
+/// \return the path of the file, or none() if it could not be written.
+static maybe_t<std::string> write_redirect_file(const std::string &url) {
+    const char *tmpdir = getenv("TMPDIR");
+    std::string dir = std::string(tmpdir && tmpdir[0] ? tmpdir : "/tmp") + "/web_config.XXXXXX";
+    if (!mkdtemp(&dir[0])) return none();
+    std::string path = dir + "/web_config.html";
+    autoclose_fd_t fd{open_cloexec(path, O_WRONLY | O_CREAT | O_EXCL, 0600)};
+    if (fd.valid()) {
+        std::string contents(std::strlen(redirect_template) + 2 * url.size(), '\0');
+        int len = snprintf(&contents[0], contents.size(), redirect_template, url.c_str(),
+                           url.c_str());
+        contents.resize(len > 0 ? len : 0);
+        if (write_loop(fd.fd(), contents.data(), contents.size()) >= 0) return path;
+        unlink(path.c_str());
+    }
+    rmdir(dir.c_str());
+    return none();
+}
+
+int web_config_run(parser_t &parser, io_streams_t &streams, const wcstring &initial_tab) {
+    const wchar_t *cmd = L"fish_config";
+    auto data_dir = parser.vars().get(L"__fish_data_dir");
+    if (data_dir.missing_or_empty()) {
+        streams.err.append_format(_(L"%ls: Could not find fish's data directory\n"), cmd);
+        return STATUS_CMD_ERROR;
+    }
+    web_config_t cfg{parser, make_key(), data_dir->as_string() + L"/tools/web_config"};
+
+    // Listen on the first free port.
+    autoclose_fd_t listen_fd;
+    int port;
+    for (port = first_port; port <= last_port; port++) {
+        autoclose_fd_t fd(socket(AF_INET, SOCK_STREAM, 0));
+        if (!fd.valid() || set_cloexec(fd.fd())) {
+            wperror(L"socket");
+            return STATUS_CMD_ERROR;
+        }
+        struct sockaddr_in addr = {};
+        addr.sin_family = AF_INET;
+        addr.sin_port = htons(port);
+        addr.sin_addr.s_addr = htonl(INADDR_LOOPBACK);
+        if (bind(fd.fd(), reinterpret_cast<struct sockaddr *>(&addr), sizeof addr) == 0 &&
+            listen(fd.fd(), 16) == 0) {
+            listen_fd = std::move(fd);
+            break;
+        }
+        if (errno != EADDRINUSE) {
+            streams.err.append_format(_(L"%ls: Could not listen on port %d: %s\n"), cmd, port,
+                                      std::strerror(errno));
+            return STATUS_CMD_ERROR;
+        }
+    }
+    if (!listen_fd.valid()) {
+        streams.err.append_format(_(L"%ls: Unable to find an open port between %d and %d\n"),
+                                  cmd, first_port, last_port);
+        return STATUS_CMD_ERROR;
+    }
+
+    std::string url = "http://localhost:" + std::to_string(port) + "/" + cfg.key + "/";
+    for (const wchar_t *tab : tab_names) {
+        if (!initial_tab.empty() && string_prefixes_string(initial_tab, tab)) {
+            url.append("#" + wcs2string(tab));
+            break;
+        }
+    }
+
+    // The browser gets the URL through a file, so the key is not on its command line, where other
+    // users could see it.
+    maybe_t<std::string> redirect_path = write_redirect_file(url);
+    if (!redirect_path) {
+        streams.err.append_format(_(L"%ls: Could not write a temporary file: %s\n"), cmd,
+                                  std::strerror(errno));
+        return STATUS_CMD_ERROR;
+    }
+    cleanup_t remove_redirect([&] {
+        unlink(redirect_path->c_str());
+        rmdir(redirect_path->substr(0, redirect_path->rfind('/')).c_str());
+    });
+    wcstring file_url = str2wcstring("file://" + *redirect_path);
+
+    outputter_t outp;
+    rgb_color_t underline = rgb_color_t::normal(), bold = rgb_color_t::normal();
+    underline.set_underline(true);
+    bold.set_bold(true);
+    outp.writestr(_(L"Web config started at "));
+    outp.set_color(underline, rgb_color_t::normal());
+    outp.writestr(file_url);
+    outp.set_color(rgb_color_t::normal(), rgb_color_t::normal());
+    outp.writestr(L"\n");
+    outp.set_color(bold, rgb_color_t::normal());
+    outp.writestr(_(L"Hit ENTER to stop."));
+    outp.set_color(rgb_color_t::normal(), rgb_color_t::normal());
+    outp.writestr(L"\n");
+    streams.out.append(str2wcstring(outp.contents()));
+
+    parser.eval(L"__fish_config_open_browser " + escape_string(file_url, ESCAPE_ALL) + L" " +
+                    escape_string(str2wcstring(url), ESCAPE_ALL),
+                io_chain_t{});
+
+    int in_fd = streams.stdin_fd;
+    for (;;) {
+        struct pollfd fds[2] = {{listen_fd.fd(), POLLIN, 0}, {in_fd, POLLIN, 0}};
+        if (poll(fds, in_fd >= 0 ? 2 : 1, -1) < 0) {
+            if (errno == EINTR && !signal_check_cancel()) continue;
+            streams.out.push_back(L'\n');
+            break;
+        }
+        if (in_fd >= 0 && fds[1].revents) {
+            // Consume the line, so it is not read by the next command.
+            char c;
+            while (read_loop(in_fd, &c, 1) == 1 && c != '\n') {
+            }
+            break;
+        }
+        if (fds[0].revents & POLLIN) {
+            autoclose_fd_t conn(accept(listen_fd.fd(), nullptr, nullptr));
+            if (!conn.valid()) continue;
+            set_cloexec(conn.fd());
+            // Don't wait forever for a client which stops sending.
+            struct timeval timeout = {5, 0};
+            setsockopt(conn.fd(), SOL_SOCKET, SO_RCVTIMEO, &timeout, sizeof timeout);
+            handle_connection(cfg, conn.fd());
+        }
+    }
+    streams.out.append(_(L"Shutting down.\n"));
+    return STATUS_CMD_OK;
+}
@@ -0,0 +1,17 @@
+// The web-based configuration started by `fish_config`.
+#ifndef FISH_WEB_CONFIG_H
+#define FISH_WEB_CONFIG_H
+
+#include "common.h"
+
+class parser_t;
+struct io_streams_t;
+
+/// Serve the web-based configuration on a port of this machine and open it in a browser, with
+/// the tab \p initial_tab, if it is not empty. Requests are answered from the variables, functions
+/// and history of \p parser, until a line is read from the standard input of \p streams or the
+/// user presses Control+C.
+/// \return a status for the fish_config builtin.
+int web_config_run(parser_t &parser, io_streams_t &streams, const wcstring &initial_tab);
+
+#endif
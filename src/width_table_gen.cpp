@@ -0,0 +1,80 @@
+// Generates width_table.h, the table fish uses to look up the width of a character, from the
+// ranges in widechar_width.h. This runs at build time; see CMakeLists.txt.
+//
+// Looking up a character in the ranges takes several binary searches, which adds up when laying
+// out long command lines and big pagers. The generated table is in two stages instead: the high
+// bits of a character select a block of 256 entries, and the low bits an entry in it. Most blocks
+// are the same, so identical blocks are shared, which keeps the table small.
+#include <cstdint>
+#include <cstdio>
+#include <map>
+#include <vector>
+
+#include "widecharwidth/widechar_width.h"
+
+/// The number of characters in a block.
+static const uint32_t block_size = 256;
+
+/// One past the largest Unicode character.
+static const uint32_t max_char = 0x110000;
+
+/// \return the entry for \p c: a width, or one of the negative widechar_* values, which
+/// fish_wcwidth resolves according to the user's settings.
+static int8_t width_entry(uint32_t c) {
+    // Check for VS16 which selects emoji presentation. This "promotes" a character like U+2764
+    // (width 1) to an emoji (probably width 2). So treat it as width 1 so the sums work. See #2652.
+    // VS15 selects text presentation.
+    if (c == 0xFE0F) return 1;
+    if (c == 0xFE0E) return 0;
+
+    // Korean Hangul Jamo median vowels and final consonants.
+    // These can either appear in combined form, taking 0 width themselves,
+    // or standalone with a 1 width. Since that's literally not expressible with wcwidth(),
+    // we take the position that the typical way for them to show up is composed.
+    if (c >= 0x1160 && c <= 0x11FF) return 0;
+    return static_cast<int8_t>(widechar_wcwidth(c));
+}
+
+int main() {
+    std::vector<std::vector<int8_t>> blocks;
+    std::map<std::vector<int8_t>, size_t> block_ids;
+    std::vector<size_t> index;
+    for (uint32_t start = 0; start < max_char; start += block_size) {
+        std::vector<int8_t> block(block_size);
+        for (uint32_t i = 0; i < block_size; i++) block[i] = width_entry(start + i);
+        auto inserted = block_ids.emplace(block, blocks.size());
+        if (inserted.second) blocks.push_back(block);
+        index.push_back(inserted.first->second);
+    }
+
+    std::printf("// Generated by width_table_gen from widechar_width.h. Do not edit.\n");
+    std::printf("#ifndef FISH_WIDTH_TABLE_H\n#define FISH_WIDTH_TABLE_H\n\n");
+    std::printf("#include <cstdint>\n\n");
+    std::printf("enum {\n");
+    std::printf("    width_nonprint = %d,\n", widechar_nonprint);
+    std::printf("    width_combining = %d,\n", widechar_combining);
+    std::printf("    width_ambiguous = %d,\n", widechar_ambiguous);
+    std::printf("    width_private_use = %d,\n", widechar_private_use);
+    std::printf("    width_unassigned = %d,\n", widechar_unassigned);
+    std::printf("    width_widened_in_9 = %d,\n", widechar_widened_in_9);
+    std::printf("};\n\n");
+    std::printf("static const uint32_t width_table_block_size = %u;\n", block_size);
+    std::printf("static const uint32_t width_table_max_char = 0x%X;\n\n", max_char);
+
+    std::printf("static const uint16_t width_table_index[%zu] = {", index.size());
+    for (size_t i = 0; i < index.size(); i++) {
+        std::printf("%s%zu,", i % 16 ? " " : "\n    ", index[i]);
+    }
+    std::printf("\n};\n\n");
+
+    std::printf("static const int8_t width_table_blocks[%zu][%u] = {\n", blocks.size(), block_size);
+    for (const auto &block : blocks) {
+        std::printf("    {");
+        for (size_t i = 0; i < block.size(); i++) {
+            std::printf("%s%d,", i % 16 ? " " : "\n        ", block[i]);
+        }
+        std::printf("\n    },\n");
+    }
+    std::printf("};\n\n#endif\n");
+    return 0;
+}
@@ -458,9 +458,11 @@ static const wchar_t *file_get_desc(int lstat_res, const struct stat &lbuf, int
 /// Test if the given file is an executable (if executables_only) or directory (if
 /// directories_only). If it matches, call wildcard_complete() with some description that we make
 /// up. Note that the filename came from a readdir() call, so we know it exists.
-static bool wildcard_test_flags_then_complete(const wcstring &filepath, const wcstring &filename,
-                                              const wchar_t *wc, expand_flags_t expand_flags,
+static bool wildcard_test_flags_then_complete(const wcstring &filepath,
+                                              const dir_iter_t::entry_t &entry, const wchar_t *wc,
+                                              expand_flags_t expand_flags,
                                               completion_receiver_t *out) {
+    const wcstring &filename = entry.name;
     // Check if it will match before stat().
     if (wildcard_complete(filename, wc, {}, nullptr, expand_flags, 0) != wildcard_result_t::match) {
         return false;
@@ -469,25 +471,35 @@ static bool wildcard_test_flags_then_complete(const wcstring &filepath, const wc
     struct stat lstat_buf = {}, stat_buf = {};
     int stat_res = -1;
     int stat_errno = 0;
-    int lstat_res = lwstat(filepath, &lstat_buf);
-    if (lstat_res >= 0) {
-        if (S_ISLNK(lstat_buf.st_mode)) {
-            stat_res = wstat(filepath, &stat_buf);
-
-            if (stat_res < 0) {
-                // In order to differentiate between e.g. rotten symlinks and symlink loops, we also
-                // need to know the error status of wstat.
-                stat_errno = errno;
+    int lstat_res = -1;
+    long long file_size = 0;
+    bool is_directory = false;
+    bool is_executable = false;
+    if (!(expand_flags & expand_flag::gen_descriptions) &&
+        !(expand_flags & expand_flag::executables_only)) {
+        // Only the type is needed, which readdir() usually tells without a stat().
+        is_directory = entry.is_dir();
+    } else {
+        lstat_res = lwstat(filepath, &lstat_buf);
+        if (lstat_res >= 0) {
+            if (S_ISLNK(lstat_buf.st_mode)) {
+                stat_res = wstat(filepath, &stat_buf);
+
+                if (stat_res < 0) {
+                    // In order to differentiate between e.g. rotten symlinks and symlink loops, we
+                    // also need to know the error status of wstat.
+                    stat_errno = errno;
+                }
+            } else {
+                stat_buf = lstat_buf;
+                stat_res = lstat_res;
             }
-        } else {
-            stat_buf = lstat_buf;
-            stat_res = lstat_res;
         }
-    }
 
-    const long long file_size = stat_res == 0 ? stat_buf.st_size : 0;
-    const bool is_directory = stat_res == 0 && S_ISDIR(stat_buf.st_mode);
-    const bool is_executable = stat_res == 0 && S_ISREG(stat_buf.st_mode);
+        file_size = stat_res == 0 ? stat_buf.st_size : 0;
+        is_directory = stat_res == 0 && S_ISDIR(stat_buf.st_mode);
+        is_executable = stat_res == 0 && S_ISREG(stat_buf.st_mode);
+    }
 
     const bool need_directory = expand_flags & expand_flag::directories_only;
     if (need_directory && !is_directory) {
@@ -553,27 +565,28 @@ class wildcard_expander_t {
     /// We are a trailing slash - expand at the end.
     void expand_trailing_slash(const wcstring &base_dir, const wcstring &prefix);
 
-    /// Given a directory base_dir, which is opened as base_dir_fp, expand an intermediate segment
+    /// Given a directory base_dir, which is opened as base_dir_iter, expand an intermediate segment
     /// of the wildcard. Treat ANY_STRING_RECURSIVE as ANY_STRING. wc_segment is the wildcard
     /// segment for this directory, wc_remainder is the wildcard for subdirectories,
     /// prefix is the prefix for completions.
-    void expand_intermediate_segment(const wcstring &base_dir, DIR *base_dir_fp,
+    void expand_intermediate_segment(const wcstring &base_dir, dir_iter_t &base_dir_iter,
                                      const wcstring &wc_segment, const wchar_t *wc_remainder,
                                      const wcstring &prefix);
 
-    /// Given a directory base_dir, which is opened as base_dir_fp, expand an intermediate literal
+    /// Given a directory base_dir, which is opened as base_dir_iter, expand an intermediate literal
     /// segment. Use a fuzzy matching algorithm.
-    void expand_literal_intermediate_segment_with_fuzz(const wcstring &base_dir, DIR *base_dir_fp,
+    void expand_literal_intermediate_segment_with_fuzz(const wcstring &base_dir,
+                                                       dir_iter_t &base_dir_iter,
                                                        const wcstring &wc_segment,
                                                        const wchar_t *wc_remainder,
                                                        const wcstring &prefix);
 
-    /// Given a directory base_dir, which is opened as base_dir_fp, expand the last segment of the
+    /// Given a directory base_dir, which is opened as base_dir_iter, expand the last segment of the
     /// wildcard. Treat ANY_STRING_RECURSIVE as ANY_STRING. wc is the wildcard segment to use for
     /// matching, wc_remainder is the wildcard for subdirectories, prefix is the prefix for
     /// completions.
-    void expand_last_segment(const wcstring &base_dir, DIR *base_dir_fp, const wcstring &wc,
-                             const wcstring &prefix);
+    void expand_last_segment(const wcstring &base_dir, dir_iter_t &base_dir_iter,
+                             const wcstring &wc, const wcstring &prefix);
 
     /// Indicate whether we should cancel wildcard expansion. This latches 'interrupt'.
     bool interrupted_or_overflowed() {
@@ -603,19 +616,18 @@ class wildcard_expander_t {
         wcstring abs_unique_hierarchy = start_point;
 
         bool stop_descent = false;
-        DIR *dir;
-        while (!stop_descent && (dir = wopendir(abs_unique_hierarchy))) {
+        while (!stop_descent) {
+            dir_iter_t dir(abs_unique_hierarchy);
+            if (!dir.valid()) break;
             // We keep track of the single unique_entry entry. If we get more than one, it's not
             // unique and we stop the descent.
             wcstring unique_entry;
 
-            bool child_is_dir;
-            wcstring child_entry;
-            while (wreaddir_resolving(dir, abs_unique_hierarchy, child_entry, &child_is_dir)) {
-                if (child_entry.empty() || child_entry.at(0) == L'.') {
+            while (const dir_iter_t::entry_t *child = dir.next()) {
+                if (child->name.empty() || child->name.at(0) == L'.') {
                     continue;  // either hidden, or . and .. entries -- skip them
-                } else if (child_is_dir && unique_entry.empty()) {
-                    unique_entry = child_entry;  // first candidate
+                } else if (child->is_dir() && unique_entry.empty()) {
+                    unique_entry = child->name;  // first candidate
                 } else {
                     // We either have two or more candidates, or the child is not a directory. We're
                     // done.
@@ -637,12 +649,11 @@ class wildcard_expander_t {
                 append_path_component(abs_unique_hierarchy, unique_entry);
                 abs_unique_hierarchy.push_back(L'/');
             }
-            closedir(dir);
         }
         return unique_hierarchy;
     }
 
-    void try_add_completion_result(const wcstring &filepath, const wcstring &filename,
+    void try_add_completion_result(const wcstring &filepath, const dir_iter_t::entry_t &entry,
                                    const wcstring &wildcard, const wcstring &prefix) {
         // This function is only for the completions case.
         assert(this->flags & expand_flag::for_completions);
@@ -654,7 +665,7 @@ class wildcard_expander_t {
         if (flags & expand_flag::special_for_cd) abs_path = normalize_path(abs_path);
 
         size_t before = this->resolved_completions->size();
-        if (wildcard_test_flags_then_complete(abs_path, filename, wildcard.c_str(), this->flags,
+        if (wildcard_test_flags_then_complete(abs_path, entry, wildcard.c_str(), this->flags,
                                               this->resolved_completions)) {
             // Hack. We added this completion result based on the last component of the wildcard.
             // Prepend our prefix to each wildcard that replaces its token.
@@ -688,7 +699,7 @@ class wildcard_expander_t {
     }
 
     // Helper to resolve using our prefix.
-    DIR *open_dir(const wcstring &base_dir) const {
+    wcstring resolve_dir(const wcstring &base_dir) const {
         wcstring path = this->working_directory;
         append_path_component(path, base_dir);
         if (flags & expand_flag::special_for_cd) {
@@ -696,7 +707,7 @@ class wildcard_expander_t {
             // for example, cd ../<tab> should complete "without resolving symlinks".
             path = normalize_path(path);
         }
-        return wopendir(path);
+        return path;
     }
 
    public:
@@ -740,39 +751,36 @@ void wildcard_expander_t::expand_trailing_slash(const wcstring &base_dir, const
         }
     } else {
         // Trailing slashes and accepting incomplete, e.g. `echo /xyz/<tab>`. Everything is added.
-        DIR *dir = open_dir(base_dir);
-        if (dir) {
-            wcstring next;
-            while (wreaddir(dir, next) && !interrupted_or_overflowed()) {
-                if (!next.empty() && next.at(0) != L'.') {
-                    this->try_add_completion_result(base_dir + next, next, L"", prefix);
-                }
+        dir_iter_t dir(resolve_dir(base_dir));
+        while (const dir_iter_t::entry_t *next = dir.next()) {
+            if (interrupted_or_overflowed()) break;
+            if (!next->name.empty() && next->name.at(0) != L'.') {
+                this->try_add_completion_result(base_dir + next->name, *next, L"", prefix);
             }
-            closedir(dir);
         }
     }
 }
 
-void wildcard_expander_t::expand_intermediate_segment(const wcstring &base_dir, DIR *base_dir_fp,
+void wildcard_expander_t::expand_intermediate_segment(const wcstring &base_dir,
+                                                      dir_iter_t &base_dir_iter,
                                                       const wcstring &wc_segment,
                                                       const wchar_t *wc_remainder,
                                                       const wcstring &prefix) {
-    wcstring name_str;
-    while (!interrupted_or_overflowed() && wreaddir_for_dirs(base_dir_fp, &name_str)) {
+    while (const dir_iter_t::entry_t *entry = base_dir_iter.next()) {
+        if (interrupted_or_overflowed()) break;
         // Note that it's critical we ignore leading dots here, else we may descend into . and ..
-        if (!wildcard_match(name_str, wc_segment, true)) {
+        if (!wildcard_match(entry->name, wc_segment, true)) {
             // Doesn't match the wildcard for this segment, skip it.
             continue;
         }
 
-        wcstring full_path = base_dir + name_str;
-        struct stat buf;
-        if (0 != wstat(full_path, &buf) || !S_ISDIR(buf.st_mode)) {
+        if (!entry->is_dir()) {
             // We either can't stat it, or we did but it's not a directory.
             continue;
         }
 
-        const file_id_t file_id = file_id_t::from_stat(buf);
+        wcstring full_path = base_dir + entry->name;
+        const file_id_t file_id = file_id_t::from_stat(*entry->stat());
         if (!this->visited_files.insert(file_id).second) {
             // Symlink loop! This directory was already visited, so skip it.
             continue;
@@ -790,17 +798,18 @@ void wildcard_expander_t::expand_intermediate_segment(const wcstring &base_dir,
 }
 
 void wildcard_expander_t::expand_literal_intermediate_segment_with_fuzz(const wcstring &base_dir,
-                                                                        DIR *base_dir_fp,
+                                                                        dir_iter_t &base_dir_iter,
                                                                         const wcstring &wc_segment,
                                                                         const wchar_t *wc_remainder,
                                                                         const wcstring &prefix) {
     // This only works with tab completions. Ordinary wildcard expansion should never go fuzzy.
-    wcstring name_str;
 
     // Mark that we are fuzzy for the duration of this function
     const scoped_push<bool> scoped_fuzzy(&this->has_fuzzy_ancestor, true);
 
-    while (!interrupted_or_overflowed() && wreaddir_for_dirs(base_dir_fp, &name_str)) {
+    while (const dir_iter_t::entry_t *entry = base_dir_iter.next()) {
+        if (interrupted_or_overflowed()) break;
+        const wcstring &name_str = entry->name;
         // Don't bother with . and ..
         if (name_str == L"." || name_str == L"..") {
             continue;
@@ -811,13 +820,12 @@ void wildcard_expander_t::expand_literal_intermediate_segment_with_fuzz(const wc
         const maybe_t<string_fuzzy_match_t> match = string_fuzzy_match_string(wc_segment, name_str);
         if (!match || match->is_samecase_exact()) continue;
 
-        wcstring new_full_path = base_dir + name_str;
-        new_full_path.push_back(L'/');
-        struct stat buf;
-        if (0 != wstat(new_full_path, &buf) || !S_ISDIR(buf.st_mode)) {
+        if (!entry->is_dir()) {
             /* We either can't stat it, or we did but it's not a directory */
             continue;
         }
+        wcstring new_full_path = base_dir + name_str;
+        new_full_path.push_back(L'/');
 
         // Determine the effective prefix for our children.
         // Normally this would be the wildcard segment, but here we know our segment doesn't have
@@ -848,16 +856,17 @@ void wildcard_expander_t::expand_literal_intermediate_segment_with_fuzz(const wc
     }
 }
 
-void wildcard_expander_t::expand_last_segment(const wcstring &base_dir, DIR *base_dir_fp,
-                                              const wcstring &wc, const wcstring &prefix) {
-    wcstring name_str;
-    while (!interrupted_or_overflowed() && wreaddir(base_dir_fp, name_str)) {
+void wildcard_expander_t::expand_last_segment(const wcstring &base_dir,
+                                              dir_iter_t &base_dir_iter, const wcstring &wc,
+                                              const wcstring &prefix) {
+    while (const dir_iter_t::entry_t *entry = base_dir_iter.next()) {
+        if (interrupted_or_overflowed()) break;
         if (flags & expand_flag::for_completions) {
-            this->try_add_completion_result(base_dir + name_str, name_str, wc, prefix);
+            this->try_add_completion_result(base_dir + entry->name, *entry, wc, prefix);
         } else {
             // Normal wildcard expansion, not for completions.
-            if (wildcard_match(name_str, wc, true /* skip files with leading dots */)) {
-                this->add_expansion_result(base_dir + name_str);
+            if (wildcard_match(entry->name, wc, true /* skip files with leading dots */)) {
+                this->add_expansion_result(base_dir + entry->name);
             }
         }
     }
@@ -921,11 +930,10 @@ void wildcard_expander_t::expand(const wcstring &base_dir, const wchar_t *wc,
         if (allow_fuzzy && this->resolved_completions->size() == before &&
             waccess(intermediate_dirpath, F_OK) != 0) {
             assert(this->flags & expand_flag::for_completions);
-            DIR *base_dir_fd = open_dir(base_dir);
-            if (base_dir_fd != nullptr) {
+            dir_iter_t base_dir_iter(resolve_dir(base_dir));
+            if (base_dir_iter.valid()) {
                 this->expand_literal_intermediate_segment_with_fuzz(
-                    base_dir, base_dir_fd, wc_segment, wc_remainder, effective_prefix);
-                closedir(base_dir_fd);
+                    base_dir, base_dir_iter, wc_segment, wc_remainder, effective_prefix);
             }
         }
     } else {
@@ -944,8 +952,8 @@ void wildcard_expander_t::expand(const wcstring &base_dir, const wchar_t *wc,
             }
         }
 
-        DIR *dir = open_dir(base_dir);
-        if (dir) {
+        dir_iter_t dir(resolve_dir(base_dir));
+        if (dir.valid()) {
             if (is_last_segment) {
                 // Last wildcard segment, nonempty wildcard.
                 this->expand_last_segment(base_dir, dir, wc_segment, effective_prefix);
@@ -967,11 +975,10 @@ void wildcard_expander_t::expand(const wcstring &base_dir, const wchar_t *wc,
                 assert(head_any.at(head_any.size() - 1) == ANY_STRING_RECURSIVE);
                 assert(any_tail[0] == ANY_STRING_RECURSIVE);
 
-                rewinddir(dir);
+                dir.rewind();
                 this->expand_intermediate_segment(base_dir, dir, head_any, any_tail,
                                                   effective_prefix);
             }
-            closedir(dir);
         }
     }
 }
@@ -1027,3 +1034,137 @@ wildcard_result_t wildcard_expand_string(const wcstring &wc, const wcstring &wor
     expander.expand(base_dir, effective_wc.c_str(), base_dir);
     return expander.status_code();
 }
+
+maybe_t<wcstring> parse_glob_qualifiers(const wcstring &str, glob_qualifiers_t *out) {
+    glob_qualifiers_t result;
+    for (const wcstring &qual : split_string(str, L',')) {
+        size_t eq = qual.find(L'=');
+        if (eq == wcstring::npos) {
+            return format_string(_(L"Glob qualifier '%ls' needs a value"), qual.c_str());
+        }
+        wcstring key = qual.substr(0, eq);
+        wcstring value = qual.substr(eq + 1);
+        if (key == L"type") {
+            if (value == L"file") {
+                result.type = glob_qualifiers_t::type_t::file;
+            } else if (value == L"dir") {
+                result.type = glob_qualifiers_t::type_t::dir;
+            } else if (value == L"link") {
+                result.type = glob_qualifiers_t::type_t::link;
+            } else {
+                return format_string(_(L"Unknown glob type '%ls'"), value.c_str());
+            }
+        } else if (key == L"sort") {
+            result.reverse = string_prefixes_string(L"-", value);
+            if (result.reverse) value.erase(0, 1);
+            if (value == L"name") {
+                result.sort = glob_qualifiers_t::sort_t::name;
+            } else if (value == L"mtime") {
+                result.sort = glob_qualifiers_t::sort_t::mtime;
+            } else if (value == L"size") {
+                result.sort = glob_qualifiers_t::sort_t::size;
+            } else {
+                return format_string(_(L"Unknown glob sort order '%ls'"), value.c_str());
+            }
+        } else if (key == L"limit") {
+            long long limit = fish_wcstoll(value.c_str());
+            if (errno || limit < 0) {
+                return format_string(_(L"Invalid glob limit '%ls'"), value.c_str());
+            }
+            result.limit = static_cast<size_t>(limit);
+        } else if (key == L"exclude") {
+            if (value.empty()) {
+                return format_string(_(L"Glob qualifier '%ls' needs a value"), qual.c_str());
+            }
+            // Convert to an internal wildcard. Since it is matched per file, ** is the same as *.
+            bool qmark_is_wild = !feature_test(features_t::qmark_noglob);
+            wcstring wc;
+            for (wchar_t c : value) {
+                if (c == L'*') {
+                    if (wc.empty() || wc.back() != ANY_STRING) wc.push_back(ANY_STRING);
+                } else if (c == L'?' && qmark_is_wild) {
+                    wc.push_back(ANY_CHAR);
+                } else {
+                    wc.push_back(c);
+                }
+            }
+            result.excludes.push_back(std::move(wc));
+        } else {
+            return format_string(_(L"Unknown glob qualifier '%ls'"), key.c_str());
+        }
+    }
+    *out = result;
+    return none();
+}
+
+/// \return whether the wildcard match \p path is excluded by \p quals.
+static bool matches_glob_exclusion(const glob_qualifiers_t &quals, const wcstring &path) {
+    if (quals.excludes.empty()) return false;
+    // Matches like dir/ for the wildcard */ should compare as dir.
+    wcstring whole = path;
+    if (whole.size() > 1 && whole.back() == L'/') whole.pop_back();
+    size_t slash = whole.rfind(L'/');
+    wcstring name = slash == wcstring::npos ? whole : whole.substr(slash + 1);
+    for (const wcstring &exclude : quals.excludes) {
+        bool whole_path = exclude.find(L'/') != wcstring::npos;
+        if (wildcard_match(whole_path ? whole : name, exclude)) return true;
+    }
+    return false;
+}
+
+void apply_glob_qualifiers(const glob_qualifiers_t &quals, completion_list_t *matches,
+                           const wcstring &working_directory) {
+    // Stat each match once. The matches are already sorted by name.
+    struct match_info_t {
+        completion_t match;
+        struct stat buf;
+    };
+    std::vector<match_info_t> infos;
+    for (completion_t &match : *matches) {
+        if (matches_glob_exclusion(quals, match.completion)) continue;
+        match_info_t info{std::move(match), {}};
+        wcstring path = path_apply_working_directory(info.match.completion, working_directory);
+        bool is_link = quals.type == glob_qualifiers_t::type_t::link;
+        if ((is_link ? lwstat(path, &info.buf) : wstat(path, &info.buf)) != 0) continue;
+        switch (quals.type) {
+            case glob_qualifiers_t::type_t::any:
+                break;
+            case glob_qualifiers_t::type_t::file:
+                if (!S_ISREG(info.buf.st_mode)) continue;
+                break;
+            case glob_qualifiers_t::type_t::dir:
+                if (!S_ISDIR(info.buf.st_mode)) continue;
+                break;
+            case glob_qualifiers_t::type_t::link:
+                if (!S_ISLNK(info.buf.st_mode)) continue;
+                break;
+        }
+        infos.push_back(std::move(info));
+    }
+
+    switch (quals.sort) {
+        case glob_qualifiers_t::sort_t::name:
+            break;
+        case glob_qualifiers_t::sort_t::mtime:
+            std::stable_sort(infos.begin(), infos.end(),
+                             [](const match_info_t &a, const match_info_t &b) {
+                                 return a.buf.st_mtime > b.buf.st_mtime;
+                             });
+            break;
+        case glob_qualifiers_t::sort_t::size:
+            std::stable_sort(infos.begin(), infos.end(),
+                             [](const match_info_t &a, const match_info_t &b) {
+                                 return a.buf.st_size > b.buf.st_size;
+                             });
+            break;
+    }
+    if (quals.reverse) std::reverse(infos.begin(), infos.end());
+    if (quals.limit && infos.size() > *quals.limit) {
+        infos.erase(infos.begin() + *quals.limit, infos.end());
+    }
+
+    matches->clear();
+    for (match_info_t &info : infos) {
+        matches->push_back(std::move(info.match));
+    }
+}
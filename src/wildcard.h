@@ -17,6 +17,8 @@ enum {
     ANY_STRING,
     /// Character representing any character string.
     ANY_STRING_RECURSIVE,
+    /// Character separating a wildcard from its qualifiers, like the [type=dir] in *[type=dir].
+    GLOB_QUALIFIER,
     /// This is a special pseudo-char that is not used other than to mark the
     /// end of the the special characters so we can sanity check the enum range.
     ANY_SENTINEL
@@ -50,6 +52,36 @@ wildcard_result_t wildcard_expand_string(const wcstring &wc, const wcstring &wor
                                          const cancel_checker_t &cancel_checker,
                                          completion_receiver_t *output);
 
+/// Qualifiers which select and order the matches of a wildcard, like *[type=dir,sort=mtime,limit=5]
+/// or *.c[exclude=*_test.c].
+struct glob_qualifiers_t {
+    enum class type_t { any, file, dir, link };
+    enum class sort_t { name, mtime, size };
+
+    /// Only keep matches of this type.
+    type_t type{type_t::any};
+
+    /// How to order the matches. Times and sizes put the newest and largest first.
+    sort_t sort{sort_t::name};
+    bool reverse{false};
+
+    /// If set, keep at most this many matches.
+    maybe_t<size_t> limit{};
+
+    /// Drop matches matching any of these wildcards. Wildcards without a slash are matched
+    /// against the file name, others against the whole match.
+    wcstring_list_t excludes{};
+};
+
+/// Parse qualifier text \p str like "type=dir,limit=5" into \p out.
+/// \return an error message, or none() on success.
+maybe_t<wcstring> parse_glob_qualifiers(const wcstring &str, glob_qualifiers_t *out);
+
+/// Filter, sort and limit the wildcard \p matches according to \p quals. Relative matches are
+/// resolved against \p working_directory.
+void apply_glob_qualifiers(const glob_qualifiers_t &quals, completion_list_t *matches,
+                           const wcstring &working_directory);
+
 /// Test whether the given wildcard matches the string. Does not perform any I/O.
 ///
 /// \param str The string to test
@@ -25,6 +25,7 @@
 
 #include "common.h"
 #include "fallback.h"  // IWYU pragma: keep
+#include "fds.h"
 #include "flog.h"
 #include "wcstringutil.h"
 #include "wutil.h"  // IWYU pragma: keep
@@ -42,6 +43,9 @@ const file_id_t kInvalidFileID = {static_cast<dev_t>(-1LL),
 /// Map used as cache by wgettext.
 static owning_lock<std::unordered_map<wcstring, wcstring>> wgettext_map;
 
+/// Map used as cache by wngettext, from the translation gettext picked to its wide version.
+static owning_lock<std::unordered_map<std::string, wcstring>> wngettext_map;
+
 bool wreaddir_resolving(DIR *dir, const wcstring &dir_path, wcstring &out_name, bool *out_is_dir) {
     struct dirent *result = readdir(dir);
     if (!result) {
@@ -99,35 +103,6 @@ bool wreaddir(DIR *dir, wcstring &out_name) {
     return true;
 }
 
-bool wreaddir_for_dirs(DIR *dir, wcstring *out_name) {
-    struct dirent *result = nullptr;
-    while (!result) {
-        result = readdir(dir);
-        if (!result) break;
-
-#if HAVE_STRUCT_DIRENT_D_TYPE
-        switch (result->d_type) {
-            case DT_DIR:
-            case DT_LNK:
-            case DT_UNKNOWN: {
-                break;  // these may be directories
-            }
-            default: {
-                break;  // nothing else can
-            }
-        }
-#else
-        // We can't determine if it's a directory or not, so just return it.
-        break;
-#endif
-    }
-
-    if (result && out_name) {
-        *out_name = str2wcstring(result->d_name);
-    }
-    return result != nullptr;
-}
-
 wcstring wgetcwd() {
     char cwd[PATH_MAX];
     char *res = getcwd(cwd, sizeof(cwd));
@@ -161,6 +136,83 @@ bool dir_t::valid() const { return this->dir != nullptr; }
 
 bool dir_t::read(wcstring &name) const { return wreaddir(this->dir, name); }
 
+/// \return the type of a file from its st_mode, or none() if it is not one we know.
+static maybe_t<dir_entry_type_t> type_from_mode(mode_t mode) {
+    if (S_ISDIR(mode)) return dir_entry_type_t::dir;
+    if (S_ISREG(mode)) return dir_entry_type_t::reg;
+    if (S_ISFIFO(mode)) return dir_entry_type_t::fifo;
+    if (S_ISCHR(mode)) return dir_entry_type_t::chr;
+    if (S_ISBLK(mode)) return dir_entry_type_t::blk;
+    if (S_ISSOCK(mode)) return dir_entry_type_t::sock;
+    return none();
+}
+
+void dir_iter_t::entry_t::reset(int dirfd, const struct dirent *dent) {
+    dirfd_ = dirfd;
+    narrow_name_ = dent->d_name;
+    name = str2wcstring(narrow_name_);
+    type_ = none();
+    stat_ = none();
+    did_stat_ = false;
+#ifdef HAVE_STRUCT_DIRENT_D_TYPE
+    // Symlinks and unknown types are resolved with stat().
+    switch (dent->d_type) {
+        case DT_DIR:
+            type_ = dir_entry_type_t::dir;
+            break;
+        case DT_REG:
+            type_ = dir_entry_type_t::reg;
+            break;
+        case DT_FIFO:
+            type_ = dir_entry_type_t::fifo;
+            break;
+        case DT_CHR:
+            type_ = dir_entry_type_t::chr;
+            break;
+        case DT_BLK:
+            type_ = dir_entry_type_t::blk;
+            break;
+        case DT_SOCK:
+            type_ = dir_entry_type_t::sock;
+            break;
+        default:
+            break;
+    }
+#endif
+}
+
+maybe_t<dir_entry_type_t> dir_iter_t::entry_t::check_type() const {
+    if (!type_ && stat()) type_ = type_from_mode(stat()->st_mode);
+    return type_;
+}
+
+const maybe_t<struct stat> &dir_iter_t::entry_t::stat() const {
+    if (!did_stat_) {
+        did_stat_ = true;
+        struct stat buf;
+        if (fstatat(dirfd_, narrow_name_.c_str(), &buf, 0) == 0) stat_ = buf;
+    }
+    return stat_;
+}
+
+dir_iter_t::dir_iter_t(const wcstring &path) : dir_(wopendir(path)) {}
+
+dir_iter_t::~dir_iter_t() {
+    if (dir_) closedir(dir_);
+}
+
+const dir_iter_t::entry_t *dir_iter_t::next() {
+    if (!dir_) return nullptr;
+    const struct dirent *dent = readdir(dir_);
+    if (!dent) return nullptr;
+    entry_.reset(dirfd(dir_), dent);
+    return &entry_;
+}
+
+void dir_iter_t::rewind() {
+    if (dir_) rewinddir(dir_);
+}
+
 int wstat(const wcstring &file_name, struct stat *buf) {
     const cstring tmp = wcs2string(file_name);
     return stat(tmp.c_str(), buf);
@@ -322,6 +374,47 @@ maybe_t<wcstring> wrealpath(const wcstring &pathname) {
     return str2wcstring(real_path);
 }
 
+maybe_t<std::string> read_file_contents(int fd) {
+    std::string result;
+    char buff[4096];
+    ssize_t amt;
+    while ((amt = read_loop(fd, buff, sizeof buff)) > 0) result.append(buff, amt);
+    if (amt < 0) return none();
+    return result;
+}
+
+maybe_t<std::string> read_file_contents(const std::string &path) {
+    autoclose_fd_t fd{open_cloexec(path, O_RDONLY)};
+    if (!fd.valid()) return none();
+    return read_file_contents(fd.fd());
+}
+
+maybe_t<std::string> read_file_contents(const wcstring &path) {
+    autoclose_fd_t fd{wopen_cloexec(path, O_RDONLY)};
+    if (!fd.valid()) return none();
+    return read_file_contents(fd.fd());
+}
+
+int write_file_atomically(const std::string &path, const std::string &contents, mode_t mode) {
+    // mkstemp creates a new file with a name nobody can predict, so we never write through a
+    // symlink or into a file someone else is writing.
+    std::string tmp_path = path + ".XXXXXX";
+    autoclose_fd_t fd{fish_mkstemp_cloexec(&tmp_path[0])};
+    if (!fd.valid()) return errno;
+
+    mode_t mask = umask(0);
+    umask(mask);
+    int err = 0;
+    if (fchmod(fd.fd(), mode & ~mask) == -1 ||
+        write_loop(fd.fd(), contents.data(), contents.size()) < 0 || fsync(fd.fd()) == -1) {
+        err = errno;
+    }
+    fd.close();
+    if (!err && rename(tmp_path.c_str(), path.c_str()) == -1) err = errno;
+    if (err) unlink(tmp_path.c_str());
+    return err;
+}
+
 wcstring normalize_path(const wcstring &path, bool allow_leading_double_slashes) {
     // Count the leading slashes.
     const wchar_t sep = L'/';
@@ -425,6 +518,11 @@ static void wgettext_init_if_necessary() {
     std::call_once(s_wgettext_init, wgettext_really_init);
 }
 
+void wgettext_set_localedir(const std::string &dir) {
+    wgettext_init_if_necessary();
+    fish_bindtextdomain(PACKAGE_NAME, dir.c_str());
+}
+
 const wcstring &wgettext(const wchar_t *in) {
     // Preserve errno across this since this is often used in printing error messages.
     int err = errno;
@@ -445,6 +543,18 @@ const wcstring &wgettext(const wchar_t *in) {
     return val;
 }
 
+const wcstring &wngettext(const wchar_t *singular, const wchar_t *plural, unsigned long n) {
+    int err = errno;
+    wgettext_init_if_necessary();
+    // Which form is picked depends on n, so look up the translation every time.
+    std::string out = fish_ngettext(wcs2string(singular).c_str(), wcs2string(plural).c_str(), n);
+    auto wmap = wngettext_map.acquire();
+    wcstring &val = (*wmap)[out];
+    if (val.empty()) val = str2wcstring(out);
+    errno = err;
+    return val;
+}
+
 int wmkdir(const wcstring &name, int mode) {
     cstring name_narrow = wcs2string(name);
     return mkdir(name_narrow.c_str(), mode);
@@ -6,6 +6,7 @@
 #include <locale.h>
 #include <stddef.h>
 #include <stdio.h>
+#include <sys/stat.h>
 #include <sys/types.h>
 #include <time.h>
 #include <wctype.h>
@@ -51,6 +52,20 @@ wcstring wgetcwd();
 /// \returns the canonicalized path, or none if the path is invalid.
 maybe_t<wcstring> wrealpath(const wcstring &pathname);
 
+/// \return everything that can be read from \p fd, or none if reading failed.
+maybe_t<std::string> read_file_contents(int fd);
+
+/// \return the contents of the file at \p path, or none if it can't be opened or read.
+maybe_t<std::string> read_file_contents(const std::string &path);
+maybe_t<std::string> read_file_contents(const wcstring &path);
+
+/// Replace the file at \p path with \p contents by writing a new file next to it and renaming that,
+/// so the file is never seen half written, even by another fish writing it at the same time. The
+/// file gets the permissions \p mode, less the umask. \return 0 on success, or the errno of the
+/// failure.
+int write_file_atomically(const std::string &path, const std::string &contents,
+                          mode_t mode = 0666);
+
 /// Given an input path, "normalize" it:
 /// 1. Collapse multiple /s into a single /, except maybe at the beginning.
 /// 2. .. goes up a level.
@@ -68,12 +83,6 @@ bool wreaddir(DIR *dir, wcstring &out_name);
 bool wreaddir_resolving(DIR *dir, const std::wstring &dir_path, wcstring &out_name,
                         bool *out_is_dir);
 
-/// Like wreaddir, but skip items that are known to not be directories. If this requires a stat
-/// (i.e. the file is a symlink), then return it. Note that this does not guarantee that everything
-/// returned is a directory, it's just an optimization for cases where we would check for
-/// directories anyways.
-bool wreaddir_for_dirs(DIR *dir, wcstring *out_name);
-
 /// Wide character version of dirname().
 std::wstring wdirname(const std::wstring &path);
 
@@ -86,6 +95,14 @@ std::wstring wbasename(const std::wstring &path);
 /// will be nothing more than a wrapper around gettext, like all other functions in this file.
 const wcstring &wgettext(const wchar_t *in);
 
+/// Like wgettext, for a message about \p n things: \return the translation of \p singular or
+/// \p plural, whichever the language uses for \p n. Some languages have more than two forms.
+const wcstring &wngettext(const wchar_t *singular, const wchar_t *plural, unsigned long n);
+
+/// Look for fish's message catalogs in \p dir instead of the compiled-in LOCALEDIR. Messages which
+/// were translated already keep their translation.
+void wgettext_set_localedir(const std::string &dir);
+
 /// Wide character version of mkdir.
 int wmkdir(const wcstring &name, int mode);
 
@@ -166,6 +183,64 @@ struct dir_t {
     ~dir_t();
 };
 
+/// The type of a file, as readdir() or stat() tells it.
+enum class dir_entry_type_t { fifo, chr, dir, blk, reg, sock };
+
+/// Iterates over the entries of a directory. Each entry knows its type without calling stat() if
+/// readdir() says what it is, which most filesystems do except for symlinks. That saves a stat()
+/// per entry when only the type matters, which is slow on large directories and network
+/// filesystems.
+class dir_iter_t {
+   public:
+    /// An entry returned by next(). It is only valid until the next call to next().
+    class entry_t {
+       public:
+        /// The name of the entry, without the directory.
+        wcstring name;
+
+        /// \return the type of the entry, following symlinks, or none() if it can't be stat'ed.
+        /// This calls stat() only if readdir() did not say.
+        maybe_t<dir_entry_type_t> check_type() const;
+
+        /// \return whether the entry is a directory, or a symlink to one.
+        bool is_dir() const { return check_type() == dir_entry_type_t::dir; }
+
+        /// \return the result of stat() on the entry, following symlinks, or none() if that
+        /// failed. The result is cached.
+        const maybe_t<struct stat> &stat() const;
+
+       private:
+        friend class dir_iter_t;
+        void reset(int dirfd, const struct dirent *dent);
+
+        int dirfd_{-1};
+        std::string narrow_name_;
+        mutable maybe_t<dir_entry_type_t> type_{};
+        mutable maybe_t<struct stat> stat_{};
+        mutable bool did_stat_{false};
+    };
+
+    /// Open the directory \p path. Check valid() to see whether that worked.
+    explicit dir_iter_t(const wcstring &path);
+    ~dir_iter_t();
+
+    dir_iter_t(const dir_iter_t &) = delete;
+    void operator=(const dir_iter_t &) = delete;
+
+    /// \return whether the directory could be opened.
+    bool valid() const { return dir_ != nullptr; }
+
+    /// \return the next entry, including . and .., or nullptr at the end or on error.
+    const entry_t *next();
+
+    /// Start again from the first entry.
+    void rewind();
+
+   private:
+    DIR *dir_{nullptr};
+    entry_t entry_{};
+};
+
 #ifndef HASH_FILE_ID
 #define HASH_FILE_ID 1
 namespace std {
@@ -0,0 +1,51 @@
+#RUN: %fish -C 'set -g fish %fish' %s
+# Autoloaded files are parsed once, and their parsed form is cached until they change.
+
+set -l dir (mktemp -d)
+mkdir $dir/functions
+printf '%s\n' 'function cached_fn' '    echo version 1' end >$dir/functions/cached_fn.fish
+set -l cmd "set fish_function_path $dir/functions \$fish_function_path; cached_fn"
+
+XDG_CACHE_HOME=$dir/cache $fish -d ast-cache -c $cmd 2>&1 | string match -v '*/share/*' | string replace $dir DIR
+# CHECK: ast-cache: Cached the ast of 'DIR/functions/cached_fn.fish'
+# CHECK: version 1
+XDG_CACHE_HOME=$dir/cache $fish -d ast-cache -c $cmd 2>&1 | string match -v '*/share/*' | string replace $dir DIR
+# CHECK: ast-cache: Loaded the cached ast of 'DIR/functions/cached_fn.fish'
+# CHECK: version 1
+
+# Changing the file makes the cache out of date.
+printf '%s\n' 'function cached_fn' '    echo version 2' '    echo and a new line' end >$dir/functions/cached_fn.fish
+XDG_CACHE_HOME=$dir/cache $fish -d ast-cache -c $cmd 2>&1 | string match -v '*/share/*' | string replace $dir DIR
+# CHECK: ast-cache: The cached ast of 'DIR/functions/cached_fn.fish' is out of date
+# CHECK: ast-cache: Cached the ast of 'DIR/functions/cached_fn.fish'
+# CHECK: version 2
+# CHECK: and a new line
+
+# A broken cache file is ignored.
+for file in $dir/cache/fish/ast/*
+    echo garbage >>$file
+end
+XDG_CACHE_HOME=$dir/cache $fish -d ast-cache -c $cmd 2>&1 | string match -v '*/share/*' | string replace $dir DIR
+# CHECK: ast-cache: The cached ast of 'DIR/functions/cached_fn.fish' is malformed
+# CHECK: ast-cache: Cached the ast of 'DIR/functions/cached_fn.fish'
+# CHECK: version 2
+# CHECK: and a new line
+
+# Flags which change parsing make the cache out of date.
+printf '%s\n' 'function caret_fn' '    echo hi ^/dev/null' end >$dir/functions/caret_fn.fish
+set -l cmd "set fish_function_path $dir/functions \$fish_function_path; caret_fn"
+XDG_CACHE_HOME=$dir/cache $fish --features=stderr-nocaret -d ast-cache -c $cmd 2>&1 | string match -v '*/share/*' | string replace $dir DIR
+# CHECK: ast-cache: Cached the ast of 'DIR/functions/caret_fn.fish'
+# CHECK: hi ^/dev/null
+XDG_CACHE_HOME=$dir/cache $fish --features=no-stderr-nocaret -d ast-cache -c $cmd 2>&1 | string match -v '*/share/*' | string replace $dir DIR
+# CHECK: ast-cache: The cached ast of 'DIR/functions/caret_fn.fish' is out of date
+# CHECK: ast-cache: Cached the ast of 'DIR/functions/caret_fn.fish'
+# CHECK: hi
+
+# Files with errors are not cached.
+printf '%s\n' 'function broken_fn' '    echo (' end >$dir/functions/broken_fn.fish
+XDG_CACHE_HOME=$dir/cache $fish -d ast-cache -c "set fish_function_path $dir/functions; broken_fn" 2>&1 | string match -q '*Cached*'
+or echo not cached
+# CHECK: not cached
+
+rm -r $dir
@@ -350,6 +350,49 @@ end
 emit test3 foo bar
 #CHECK: received event test3 with args: foo bar
 
+# Event names may be wildcards.
+function plugin_handler --on-event 'plugin_*'
+    echo plugin event with args: $argv
+end
+
+emit plugin_load foo
+#CHECK: plugin event with args: foo
+emit plugin_
+#CHECK: plugin event with args:
+emit other_plugin_load
+emit plugin_unload bar
+#CHECK: plugin event with args: bar
+functions -e plugin_handler
+
+# Events can carry variables.
+function plugin_handler --on-event 'plugin_*'
+    echo args: $argv
+    for var in name files number obj none
+        set -q $var
+        and echo $var: (count $$var) $$var
+    end
+end
+emit -v name=foo -v files=a -v files=b --json '{"number": 1.5, "obj": {"a": [true]}, "none": null}' plugin_load x -v
+#CHECK: args: x -v
+#CHECK: name: 1 foo
+#CHECK: files: 2 a b
+#CHECK: number: 1 1.5
+#CHECK: obj: 1 {"a":[true]}
+#CHECK: none: 0
+emit --json '{"files": ["c", "d"], "name": "bar"}' plugin_load
+#CHECK: args:
+#CHECK: name: 1 bar
+#CHECK: files: 2 c d
+emit --json '[1]' plugin_load
+#CHECKERR: emit: Expected a JSON object, got '[1]'
+emit -v argv=1 plugin_load
+#CHECKERR: emit: Variable name 'argv' is not valid. See `help identifiers`.
+emit -v status=1 plugin_load
+#CHECKERR: emit: Tried to change the read-only variable 'status'
+emit -v name plugin_load
+#CHECKERR: emit: Expected NAME=VALUE, got 'name'
+functions -e plugin_handler
+
 # test empty argument
 emit
 #CHECKERR: emit: expected event name
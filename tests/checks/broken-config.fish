@@ -4,6 +4,8 @@ begin
     set -gx XDG_CONFIG_HOME $dir/broken-config/
     set -gx HOME $dir/broken-config/
     $fish -l -c 'echo but still going'
+    # CHECK: conf.d before
+    # CHECK: conf.d after
     # CHECK: broken
     # CHECK: but still going
     # CHECKERR: fish: Unknown command: syntax-error
@@ -13,8 +15,51 @@ begin
     # CHECKERR: from sourcing file ~//fish/config.fish
     # CHECKERR: called during startup
 
-    $fish -c "echo normal command" -C "echo init"
+    # Syntax errors in config files only skip the affected statement, and are reported together.
+    # CHECKERR: fish: Errors occurred while sourcing files:
+    # CHECKERR:
+    # CHECKERR: ~/{{/?}}fish/conf.d/typo.fish (line 2): $$ is not the pid. In fish, please use $fish_pid.
+    # CHECKERR: echo $$
+    # CHECKERR: ^
+    # CHECKERR: from sourcing file ~/{{/?}}fish/conf.d/typo.fish
+    # CHECKERR: called on line {{\d+}} of file {{.*}}/config.fish
+    # CHECKERR: from sourcing file {{.*}}/config.fish
+    # CHECKERR: called during startup
+
+    $fish -c "echo normal command" -C "echo init" 2>/dev/null
+    # CHECK: conf.d before
+    # CHECK: conf.d after
     # CHECK: broken
     # CHECK: init
     # CHECK: normal command
 end
+
+# source --keep-going skips statements with errors.
+set -l tmpfile (mktemp)
+printf '%s\n' 'echo one' 'echo $$' 'if true' '    echo two' 'end; end' 'echo three' >$tmpfile
+source --keep-going $tmpfile
+# CHECK: one
+# CHECK: three
+# CHECKERR: fish: Errors occurred while sourcing files:
+# CHECKERR:
+# CHECKERR: {{.*}} (line 2): $$ is not the pid. In fish, please use $fish_pid.
+# CHECKERR: echo $$
+# CHECKERR: ^
+# CHECKERR: from sourcing file {{.*}}
+# CHECKERR: called on line {{\d+}} of file {{.*}}broken-config.fish
+# CHECKERR:
+# CHECKERR: {{.*}} (line 5): 'end' outside of a block
+# CHECKERR: end; end
+# CHECKERR: ^
+# CHECKERR: from sourcing file {{.*}}
+# CHECKERR: called on line {{\d+}} of file {{.*}}broken-config.fish
+
+# Without it, the file is not run at all.
+source $tmpfile
+# CHECKERR: {{.*}} (line 5): 'end' outside of a block
+# CHECKERR: end; end
+# CHECKERR: ^
+# CHECKERR: from sourcing file {{.*}}
+# CHECKERR: called on line {{\d+}} of file {{.*}}broken-config.fish
+# CHECKERR: source: Error while reading file {{.*}}
+rm $tmpfile
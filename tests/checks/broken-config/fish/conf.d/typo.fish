@@ -0,0 +1,3 @@
+echo conf.d before
+echo $$
+echo conf.d after
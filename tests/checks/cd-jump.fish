@@ -0,0 +1,71 @@
+#RUN: %fish -C 'set -g fish %fish' %s
+
+set -l tmp (mktemp -d)
+set fish (builtin realpath $fish)
+mkdir -p $tmp/src/fish-shell $tmp/src/fish-site $tmp/docs/fish $tmp/data/fish
+# The data directory of the tests is relative, so it would be lost after cd.
+set -lx XDG_DATA_HOME $tmp/data
+set -l db $tmp/data/fish/cd_frecency
+set -l now (date +%s)
+
+# Directories which were visited more often and more recently are better matches.
+begin
+    printf '%s\t%s\t%s\n' 10 $now $tmp/src/fish-shell
+    printf '%s\t%s\t%s\n' 30 (math $now - 30 \* 24 \* 60 \* 60) $tmp/src/fish-site
+    printf '%s\t%s\t%s\n' 1 $now $tmp/docs/fish
+    printf '%s\t%s\t%s\n' 50 $now $tmp/gone
+    echo garbage
+end >$db
+
+function jump -V tmp
+    $fish -c 'cd --jump $argv; and pwd' $argv | string replace $tmp ''
+    return $pipestatus[1]
+end
+
+cd $tmp
+jump
+#CHECK: /src/fish-shell
+#CHECK: /src/fish-site
+#CHECK: /docs/fish
+
+jump fish
+#CHECK: /src/fish-shell
+
+# The current directory is never a match.
+cd src/fish-shell
+jump fish
+#CHECK: /src/fish-site
+cd $tmp
+
+# The last pattern must match the last component.
+jump src fish
+#CHECK: /src/fish-shell
+jump docs fish
+#CHECK: /docs/fish
+jump fish src
+#CHECKERR: cd: No visited directory matches 'fish src'
+
+# Patterns with uppercase letters are case-sensitive.
+jump SITE
+#CHECKERR: cd: No visited directory matches 'SITE'
+jump site
+#CHECK: /src/fish-site
+
+jump gone
+#CHECKERR: cd: No visited directory matches 'gone'
+echo $status
+#CHECK: 1
+
+# Directories are only recorded in interactive sessions.
+$fish -c "cd $tmp/src"
+string match -q "*$tmp/src" <$db
+or echo not recorded
+#CHECK: not recorded
+
+$fish -c 'complete -C"cd --jump "' | string replace $tmp ''
+#CHECK: /src/fish-shell
+#CHECK: /src/fish-site
+#CHECK: /docs/fish
+
+cd /
+rm -rf $tmp
@@ -30,7 +30,7 @@ end
 # Command sub just under the limit should succeed.
 set a (subme 511)
 set --show a
-#CHECK: $a: set in global scope, unexported, with 1 elements
+#CHECK: $a: set in global scope, unexported, with 1 element
 #CHECK: $a[1]: |{{x{510}x}}|
 
 # Command sub at the limit should fail
@@ -49,7 +49,7 @@ set --show b
 set c (subme 513)
 set --show c
 
-#CHECK: $c: set in global scope, unexported, with 1 elements
+#CHECK: $c: set in global scope, unexported, with 1 element
 #CHECK: $c[1]: ||
 #CHECKERR: {{.*}}: Too much data emitted by command substitution so it was discarded
 #CHECKERR:     set -l x (string repeat -n $argv x)
@@ -72,3 +72,23 @@ or echo expected status 122, saw $saved_status >&2
 #CHECKERR: {{.*}}: Too much data emitted by command substitution so it was discarded
 #CHECKERR: echo this will fail (string repeat --max 513 b) to output anything
 #CHECKERR:                     ^
+
+set -e fish_read_limit
+
+# Substitutions which only run builtins are collected without a pipe. Check that they still see
+# what a pipe would.
+echo (string upper a; echo b >/dev/null)
+#CHECK: A
+echo (begin; echo c >&2; end 2>&1)
+#CHECK: c
+function printf
+    command printf %s- $argv
+end
+echo (printf d)
+#CHECK: d-
+functions -e printf
+function wraps-builtins
+    string join , $argv
+end
+echo (wraps-builtins e f) (count (wraps-builtins g))
+#CHECK: e,f 1
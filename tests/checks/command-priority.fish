@@ -0,0 +1,55 @@
+#RUN: %fish %s
+# command --nice and --ionice change the scheduling priority of the command.
+
+function niceness
+    string trim -- (ps -o ni= -p $argv[1])
+end
+set -l base (niceness $fish_pid)
+
+math (command --nice 5 sh -c 'ps -o ni= -p $$') - $base
+# CHECK: 5
+
+# Nested uses add up, and the command gets its own options.
+math (command --nice 2 command --nice 3 sh -c 'ps -o ni= -p $$') - $base
+# CHECK: 5
+command --nice 1 printf '%s\n' -v
+# CHECK: -v
+
+# The priority only applies to the command.
+math (sh -c 'ps -o ni= -p $$') - $base
+# CHECK: 0
+
+# Functions are not run, like with plain `command`.
+function true
+    echo function
+end
+command --nice 1 true
+echo $status
+# CHECK: 0
+functions -e true
+
+command --nice x true
+# CHECKERR: command: Invalid niceness 'x'
+command --nice 1 -s true
+# CHECKERR: command: Invalid combination of options
+command --nice 1
+# CHECKERR: command: Expected at least 1 args, got 0
+
+if test (uname) = Linux
+    command --ionice idle:3 true
+    command --ionice nonesuch true
+else
+    echo "command: Invalid I/O scheduling class 'idle:3'" >&2
+    echo "command: Invalid I/O scheduling class 'nonesuch'" >&2
+end
+# CHECKERR: command: Invalid I/O scheduling class 'idle:3'
+# CHECKERR: command: Invalid I/O scheduling class 'nonesuch'
+
+# These always fork, since posix_spawn can't set the priority.
+function spawn_stat
+    status spawn-stats | string replace -rf "^$argv[1]: " ''
+end
+set -l forks (spawn_stat '  scheduling priority')
+command --nice 1 true
+math (spawn_stat '  scheduling priority') - $forks
+# CHECK: 1
@@ -387,3 +387,29 @@ complete -C ': $dir/'
 # CHECK: $dir/target
 rm $dir/target
 rmdir $dir
+
+# Completion files are sourced once, and again only when they change.
+set -l dir (mktemp -d)
+set -g fish_complete_path $dir $fish_complete_path
+function autoloaded-cmd
+end
+echo 'echo sourced >&2; complete -c autoloaded-cmd -x -a "one two"' >$dir/autoloaded-cmd.fish
+complete -C 'autoloaded-cmd '
+complete -C 'autoloaded-cmd t'
+#CHECKERR: sourced
+#CHECK: one
+#CHECK: two
+#CHECK: two
+# Replace the file, so the directory changes even where only entries are watched.
+echo 'echo sourced again >&2; complete -c autoloaded-cmd -x -a "three"' >$dir/new
+mv $dir/new $dir/autoloaded-cmd.fish
+complete -C 'autoloaded-cmd '
+#CHECKERR: sourced again
+#CHECK: three
+# Changing the path loads the completions again.
+set -g fish_complete_path $fish_complete_path
+complete -C 'autoloaded-cmd '
+#CHECKERR: sourced again
+#CHECK: three
+set -e fish_complete_path[1]
+rm -r $dir
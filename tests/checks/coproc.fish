@@ -0,0 +1,56 @@
+#RUN: %fish %s
+coproc sed -u 's/^/> /'
+echo hello >&$COPROC[2]
+read -l reply <&$COPROC[1]
+echo $reply
+#CHECK: > hello
+test -n "$COPROC_PID"
+and echo have pid
+#CHECK: have pid
+fdopen
+#CHECK: {{\d+}}	coproc-write	sed -u 's/^/> /'
+#CHECK: {{\d+}}	coproc-read	sed -u 's/^/> /'
+
+# Closing the input ends the command, and its output then ends too.
+echo last >&$COPROC[2]
+fdclose $COPROC[2]
+cat <&$COPROC[1]
+#CHECK: > last
+fdclose $COPROC[1]
+wait
+
+# The fds are closed when the block ends, unless they are global.
+function f
+    coproc -n local_coproc cat
+    set -g leaked $local_coproc
+    coproc -g -n global_coproc cat
+end
+f
+fdopen -q $leaked
+or echo closed after function
+#CHECK: closed after function
+fdopen -q $global_coproc
+and echo global still open
+#CHECK: global still open
+echo global >&$global_coproc[2]
+fdclose $global_coproc[2]
+cat <&$global_coproc[1]
+#CHECK: global
+fdclose $global_coproc[1]
+wait
+
+coproc
+#CHECKERR: coproc: Expected at least 1 args, got 0
+function fn
+end
+coproc fn
+#CHECKERR: coproc: 'fn' is not an external command
+coproc echo
+#CHECKERR: coproc: 'echo' is not an external command
+coproc -n a-b cat
+#CHECKERR: coproc: Variable name 'a-b' is not valid. See `help identifiers`.
+coproc -l -g cat
+#CHECKERR: coproc: Variable scope can only be one of universal, global and local
+fdopen -q
+or echo none open
+#CHECK: none open
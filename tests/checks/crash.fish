@@ -0,0 +1,22 @@
+# RUN: %fish -C 'set -g fish %fish' %s
+# A fatal signal makes fish write a crash report to its data directory.
+
+set -l dir (mktemp -d)
+set -lx XDG_DATA_HOME $dir
+$fish -d exec-job-exec -o /dev/null -c 'echo hello; kill -SEGV $fish_pid' 2>| string replace $dir DIR
+# CHECK: hello
+# CHECK: fish: crashed with SIGSEGV. A report was written to DIR/fish/fish-crash-{{\d+}}.txt
+# CHECK: Please attach it to a bug report at https://github.com/fish-shell/fish-shell/issues
+# CHECKERR: fish: Process {{\d+}}, {{.*}} terminated by signal SIGSEGV (Address boundary error)
+
+set -l report $dir/fish/fish-crash-*.txt
+string match -r '^(?:fish \d+ crashed with SIGSEGV|Version: .*|Command: .*|Backtrace:)$' <$report
+# CHECK: fish {{\d+}} crashed with SIGSEGV
+# CHECK: Version: {{.+}}
+# CHECK: Command: kill -SEGV $fish_pid
+# CHECK: Backtrace:
+# The debug output from before the crash is included, even though it went to /dev/null.
+string match -e "from command 'echo hello'" <$report
+# CHECK: exec-job-exec: Executed job -1 from command 'echo hello' with pgrp -2
+
+rm -r $dir
@@ -0,0 +1,77 @@
+# RUN: %fish -C 'set -g fish %fish' %s
+
+set -g dir (mktemp -d)
+mkfifo $dir/in
+printf '%s\n' 'function greet' '    set -l greeting hello' '    echo "$greeting $argv"' end \
+    'greet world' 'greet again' 'exit 3' >$dir/prog.fish
+
+# Keep the pipe open for writing, so that the input doesn't end after the first message.
+# fish would open the fifos itself and block, so let sh do it in the background.
+sh -c 'echo $$ >"$2"; exec sleep 60 >"$1"' sh $dir/in $dir/holder &
+sh -c 'exec "$1" --debug-adapter <"$2" >"$3"' sh $fish $dir/in $dir/out &
+
+set -g seq 0
+function send -a command arguments
+    set -g seq (math $seq + 1)
+    set -l msg '{"seq":'$seq',"type":"request","command":"'$command'","arguments":'$arguments'}'
+    printf 'Content-Length: %d\r\n\r\n%s' (string length -- $msg) $msg >$dir/in
+end
+
+# Print the messages from the adapter, one per line.
+function messages
+    string replace -ra '\r|Content-Length: \d+' '' <$dir/out | string match -rv '^$'
+end
+
+# Wait until the adapter has sent $count messages containing $text.
+function wait_for -a text count
+    for i in (seq 200)
+        test (messages | string match -e -- $text | count) -ge $count
+        and return
+        sleep 0.05
+    end
+    echo "Timed out waiting for $text" >&2
+end
+
+send initialize '{"adapterID":"fish"}'
+send launch '{"program":"'$dir/prog.fish'"}'
+send setBreakpoints '{"source":{"path":"'$dir/prog.fish'"},"breakpoints":[{"line":3}]}'
+send configurationDone '{}'
+wait_for '"event":"stopped"' 1
+send stackTrace '{"threadId":1}'
+send scopes '{"frameId":0}'
+send variables '{"variablesReference":1}'
+send stepOut '{"threadId":1}'
+wait_for '"event":"stopped"' 2
+send stackTrace '{"threadId":1}'
+send setBreakpoints '{"source":{"path":"'$dir/prog.fish'"},"breakpoints":[]}'
+send continue '{"threadId":1}'
+wait_for '"event":"terminated"' 1
+send disconnect '{}'
+wait_for '"command":"disconnect"' 1
+kill (cat $dir/holder)
+wait
+
+# Output is forwarded as it arrives, so check it separately.
+messages | string match -v '*"event":"output"*'
+# CHECK: {"seq":1,"type":"response","request_seq":1,"success":true,"command":"initialize","body":{"supportsConfigurationDoneRequest":true,"supportsTerminateRequest":true}}
+# CHECK: {"seq":2,"type":"event","event":"initialized","body":{}}
+# CHECK: {"seq":3,"type":"response","request_seq":2,"success":true,"command":"launch","body":{}}
+# CHECK: {"seq":4,"type":"response","request_seq":3,"success":true,"command":"setBreakpoints","body":{"breakpoints":[{"verified":true,"line":3}]}}
+# CHECK: {"seq":5,"type":"response","request_seq":4,"success":true,"command":"configurationDone","body":{}}
+# CHECK: {"seq":6,"type":"event","event":"stopped","body":{"reason":"breakpoint","threadId":1,"allThreadsStopped":true}}
+# CHECK: {"seq":7,"type":"response","request_seq":5,"success":true,"command":"stackTrace","body":{"stackFrames":[{"id":0,"name":"greet","line":3,"column":1,"source":{"name":"prog.fish","path":"{{.*}}/prog.fish"}},{"id":1,"name":"prog.fish","line":5,"column":1,"source":{"name":"prog.fish","path":"{{.*}}/prog.fish"}}],"totalFrames":2}}
+# CHECK: {"seq":8,"type":"response","request_seq":6,"success":true,"command":"scopes","body":{"scopes":[{"name":"Local","variablesReference":1,"expensive":false},{"name":"Global","variablesReference":2,"expensive":false},{"name":"Universal","variablesReference":3,"expensive":false}]}}
+# CHECK: {"seq":9,"type":"response","request_seq":7,"success":true,"command":"variables","body":{"variables":[{"name":"argv","value":"world","variablesReference":0},{"name":"greeting","value":"hello","variablesReference":0}]}}
+# CHECK: {"seq":{{\d+}},"type":"response","request_seq":8,"success":true,"command":"stepOut","body":{}}
+# CHECK: {"seq":{{\d+}},"type":"event","event":"stopped","body":{"reason":"step","threadId":1,"allThreadsStopped":true}}
+# CHECK: {"seq":{{\d+}},"type":"response","request_seq":9,"success":true,"command":"stackTrace","body":{"stackFrames":[{"id":0,"name":"prog.fish","line":6,"column":1,"source":{"name":"prog.fish","path":"{{.*}}/prog.fish"}}],"totalFrames":1}}
+# CHECK: {"seq":{{\d+}},"type":"response","request_seq":10,"success":true,"command":"setBreakpoints","body":{"breakpoints":[]}}
+# CHECK: {"seq":{{\d+}},"type":"response","request_seq":11,"success":true,"command":"continue","body":{"allThreadsContinued":true}}
+# CHECK: {"seq":{{\d+}},"type":"event","event":"exited","body":{"exitCode":3}}
+# CHECK: {"seq":{{\d+}},"type":"event","event":"terminated","body":{}}
+# CHECK: {"seq":{{\d+}},"type":"response","request_seq":12,"success":true,"command":"disconnect","body":{}}
+
+messages | string match -e '"event":"output"' | string replace -r '.*"output":"(.*)"}}$' '$1' | string join ''
+# CHECK: hello world\nhello again\n
+
+rm -r $dir
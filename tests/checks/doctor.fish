@@ -0,0 +1,53 @@
+#RUN: %fish -C 'set -g fish %fish' %s
+# fish --doctor reports problems with the environment, with suggested fixes.
+
+set -l dir (mktemp -d)
+mkdir -p $dir/config/fish/conf.d $dir/data $dir/vendor/fish/vendor_conf.d
+set -l env -u FISH_UNIT_TESTS_RUNNING HOME=$dir XDG_CONFIG_HOME=$dir/config XDG_DATA_HOME=$dir/data XDG_DATA_DIRS=$dir/vendor
+
+# Let fish write the universal variable file, then break it.
+env $env $fish -c 'set -U foo bar'
+echo 'not a variable' >>$dir/config/fish/fish_variables
+
+# A user snippet hides a vendor snippet of the same name. Vendor directories are only used
+# outside of tests.
+echo 'set -g from user' >$dir/config/fish/conf.d/snippet.fish
+echo 'set -g from vendor' >$dir/vendor/fish/vendor_conf.d/snippet.fish
+
+# The locale depends on the system, so leave it out.
+env $env TERM=xterm PATH=/usr/bin:/bin:/usr/bin:relative:$dir/missing $fish --doctor 2>/dev/null |
+    string replace -a $dir DIR | string match -rv 'locale|LANG|LC_|Found'
+echo $pipestatus[1]
+# CHECK: Checking terminal: ok
+# CHECK: Checking config and data directories: ok
+# CHECK: Checking universal variables: 1 problem
+# CHECK: The universal variable file 'DIR/config/fish/fish_variables' has lines that can not be read: {{\d+}}
+# CHECK: Fix: Fix or remove those lines. fish drops them the next time it saves the file.
+# CHECK: Checking conf.d snippets: 1 problem
+# CHECK: The conf.d snippet 'DIR/vendor/fish/vendor_conf.d/snippet.fish' is not loaded, because 'DIR/config/fish/conf.d/snippet.fish' has the same name.
+# CHECK: Fix: Rename one of them if both should run, or remove the one that isn't needed.
+# CHECK: Checking PATH: 3 problems
+# CHECK: $PATH contains '/usr/bin' more than once.
+# CHECK: Fix: Check that the config files add it only once, including via $fish_user_paths.
+# CHECK: $PATH contains the relative directory 'relative', so which commands are found depends on the current directory.
+# CHECK: Fix: Remove it from $PATH, or use an absolute path instead.
+# CHECK: $PATH contains 'DIR/missing', which does not exist.
+# CHECK: Fix: Remove it from $PATH.
+# The warning about the universal variable file, logged at startup, is reported too.
+# CHECK: Checking logged errors and warnings: 1 problem
+# CHECK: warning: Unable to parse universal variable message: 'not a variable'
+# CHECK: Fix: Fix what the message describes. It was logged to the 'warning' debug category.
+# CHECK: 1
+
+# A snippet which isn't a file masks the one with the same name on purpose.
+rm $dir/config/fish/conf.d/snippet.fish
+ln -s /dev/null $dir/config/fish/conf.d/snippet.fish
+env $env TERM=unknown-terminal PATH=/usr/bin $fish --doctor 2>/dev/null |
+    string match -e -r 'terminal|conf.d|PATH'
+# CHECK: Checking terminal: 1 problem
+# CHECK: The terminal type 'unknown-terminal' from $TERM is unknown, so fish uses 'ansi', which has fewer features.
+# CHECK: Fix: Install the terminfo entry for the terminal, which is often in a package like ncurses-term, or set TERM to a known type like xterm-256color.
+# CHECK: Checking conf.d snippets: ok
+# CHECK: Checking PATH: ok
+
+rm -r $dir
@@ -317,3 +317,31 @@ $fish -c 'command (asd)'
 #CHECKERR: command (asd)
 #CHECKERR: ^
 true
+
+# Numeric brace ranges
+echo {1..5}
+#CHECK: 1 2 3 4 5
+echo {5..1}
+#CHECK: 5 4 3 2 1
+echo {1..10..3} {10..1..3} {1..10..-3}
+#CHECK: 1 4 7 10 10 7 4 1 1 4 7 10
+echo {-2..2}
+#CHECK: -2 -1 0 1 2
+echo file{08..11}.txt
+#CHECK: file08.txt file09.txt file10.txt file11.txt
+echo {1..010..4}
+#CHECK: 001 005 009
+echo {-05..5..5}
+#CHECK: -05 000 005
+echo {3..3}
+#CHECK: 3
+echo {1..2}{a,b} {a,{1..2}}
+#CHECK: 1a 2a 1b 2b a 1 2
+echo x{1..2..0}y
+#CHECK: x1y x2y
+# Anything else is not a range.
+echo {1..} {a..c} {1...3} {1..2..3..4} '{1..3}' {1'..'3} {1.5..3}
+#CHECK: {1..} {a..c} {1...3} {1..2..3..4} {1..3} {1..3} {1.5..3}
+set -l range 1..3
+echo {$range}
+#CHECK: 1..3
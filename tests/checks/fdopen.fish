@@ -0,0 +1,91 @@
+#RUN: %fish %s
+set -g tmp (mktemp -d)
+
+fdopen -w out $tmp/file
+test $out -ge 10; and echo high fd
+#CHECK: high fd
+echo hello >&$out
+command echo world >&$out
+fdopen
+#CHECK: {{\d+}}	write	{{.*}}/file
+fdclose $out
+fdopen -q $out
+or echo closed
+#CHECK: closed
+cat $tmp/file
+#CHECK: hello
+#CHECK: world
+
+# Files are opened for reading by default.
+fdopen in $tmp/file
+read -l first <&$in
+read -l second <&$in
+echo $first $second
+#CHECK: hello world
+fdclose $in
+
+# Local fds are closed when the block they were opened in ends.
+function f
+    fdopen -w fd $tmp/local
+    set -g leaked $fd
+    fdopen -q $fd
+    and echo open in function
+end
+f
+#CHECK: open in function
+fdopen -q $leaked
+or echo closed after function
+#CHECK: closed after function
+begin
+    fdopen -l -a fd $tmp/local
+    set -g leaked $fd
+end
+fdopen -q $leaked
+or echo closed after block
+#CHECK: closed after block
+
+# Global ones stay open.
+function g
+    fdopen -g -a gfd $tmp/global
+end
+g
+fdopen -q $gfd
+and echo still open
+#CHECK: still open
+echo global >&$gfd
+fdclose $gfd
+cat $tmp/global
+#CHECK: global
+
+# Named fd redirections are listed and closed the same way.
+true {named}>>$tmp/named
+fdopen
+#CHECK: {{\d+}}	append	{{.*}}/named
+fdclose $named
+fdopen
+fdopen -q
+or echo none open
+#CHECK: none open
+
+fdclose 1
+#CHECKERR: fdclose: 1 is not an open file descriptor
+fdclose nope
+#CHECKERR: fdclose: Argument 'nope' is not a valid integer
+fdopen x
+#CHECKERR: fdopen: Expected 2 args, got 1
+fdopen -r -w x $tmp/file
+#CHECKERR: fdopen: Invalid combination of options,
+#CHECKERR: only one of the open modes may be given
+fdopen -l -g x $tmp/file
+#CHECKERR: fdopen: Variable scope can only be one of universal, global and local
+fdopen x $tmp/nonexistent
+#CHECKERR: fdopen: Could not open '{{.*}}/nonexistent': No such file or directory
+fdopen -s x $tmp/nonexistent
+#CHECKERR: fdopen: Could not open '{{.*}}/nonexistent': No such file or directory
+fdopen -g fish_pid $tmp/file
+#CHECKERR: fdopen: Tried to change the read-only variable 'fish_pid'
+fdopen
+echo $status
+#CHECK: 0
+
+rm -r $tmp
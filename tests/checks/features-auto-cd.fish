@@ -0,0 +1,46 @@
+#RUN: %fish --features auto-cd %s
+set -l dir (mktemp -d)
+cd $dir
+mkdir -p sub/deeper
+
+# A directory name as a command changes to it.
+sub
+basename $PWD
+# CHECK: sub
+deeper
+basename $PWD
+# CHECK: deeper
+
+# Directories aren't used if there are arguments.
+cd $dir
+sub foo
+# CHECKERR: fish: Unknown command: sub
+# CHECKERR: {{.*}}checks/features-auto-cd.fish (line {{\d+}}):
+# CHECKERR: sub foo
+# CHECKERR: ^
+echo $status
+# CHECK: 127
+
+# A function of the same name wins.
+function sub
+    echo function sub
+end
+sub
+# CHECK: function sub
+functions -e sub
+
+# Without the feature, the directory needs a slash.
+status features disable auto-cd
+sub
+# CHECKERR: fish: Unknown command: sub
+# CHECKERR: {{.*}}checks/features-auto-cd.fish (line {{\d+}}):
+# CHECKERR: sub
+# CHECKERR: ^
+echo $status
+# CHECK: 127
+sub/
+basename $PWD
+# CHECK: sub
+
+cd /
+rm -r $dir
@@ -54,4 +54,52 @@ fish_add_path -nP $tmpdir/etc | string replace -- $tmpdir ''
 test "$oldpath" = "$PATH"
 or echo "PATH CHANGED!!!" >&2
 
+# Paths which are not directories are skipped, and repeated ones are added once.
+touch $tmpdir/file
+fish_add_path -v $tmpdir/nonexistent $tmpdir/file $tmpdir/etc $tmpdir/etc/ 2>&1 \
+    | string replace -a -- $tmpdir ''
+# CHECK: set fish_user_paths /etc /sbin /link /bin
+# CHECK: fish_add_path: Skipping '/nonexistent' because it does not exist
+# CHECK: fish_add_path: Skipping '/file' because it is not a directory
+
+fish_add_path $tmpdir/nonexistent
+echo $status
+# CHECK: 1
+
+# Other path variables can be changed too.
+set -g MANPATH $tmpdir/bin $tmpdir/etc $tmpdir/bin/ $tmpdir/sbin $tmpdir/etc
+fish_add_path --variable MANPATH -a $tmpdir/link
+string replace -- $tmpdir '' $MANPATH | string join ' '
+# CHECK: /bin /etc /bin/ /sbin /etc /link
+
+# --dedup removes the entries which are already there.
+fish_add_path --variable MANPATH --dedup
+string replace -- $tmpdir '' $MANPATH | string join ' '
+# CHECK: /bin /etc /sbin /link
+
+fish_add_path -n --variable NEWPATH $tmpdir/bin | string replace -- $tmpdir ''
+# CHECK: set -g NEWPATH /bin
+set -q NEWPATH
+or echo NEWPATH was not set
+# CHECK: NEWPATH was not set
+
+fish_add_path --variable 'bad name' $tmpdir/bin
+# CHECKERR: fish_add_path: Variable name 'bad name' is not valid. See `help identifiers`.
+fish_add_path -UP $tmpdir/bin
+# CHECKERR: fish_add_path: $PATH can't be made universal
+
+# A universal variable which is shadowed by a global one is changed, but with a warning.
+set -U fish_user_paths $tmpdir/bin
+fish_add_path -U $tmpdir/sbin
+# CHECKERR: fish_add_path: Universal variable 'fish_user_paths' is shadowed by the global variable of the same name.
+set -S fish_user_paths | string match -r 'universal scope.*'
+# CHECK: universal scope, unexported, with 2 elements
+
+# A new global starts out with the paths of the universal variable.
+set -e -g fish_user_paths
+fish_add_path -g $tmpdir/etc
+string replace -- $tmpdir '' $fish_user_paths | string join ' '
+# CHECK: /etc /sbin /bin
+set -e -U fish_user_paths
+
 exit 0
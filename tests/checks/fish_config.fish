@@ -0,0 +1,77 @@
+#RUN: %fish %s
+
+fish_config theme list | string match -e Solarized
+# CHECK: Solarized Dark
+# CHECK: Solarized Light
+
+# A theme of the user hides one of the same name which comes with fish.
+mkdir -p $__fish_config_dir/themes
+printf '%s\n' '# preferred_background: black' 'fish_color_command red --bold' \
+    'fish_pager_color_prefix cyan # the prefix' >$__fish_config_dir/themes/Nord.theme
+printf '%s\n' 'fish_color_command blue' 'fish_color_param notacolor' >$__fish_config_dir/themes/broken.theme
+fish_config theme list | string match -e -r 'Nord|broken'
+# CHECK: Nord
+# CHECK: broken
+
+function on_command --on-variable fish_color_command
+    echo fish_color_command is $fish_color_command and fish_pager_color_prefix is $fish_pager_color_prefix
+end
+fish_config theme choose Nord
+# CHECK: fish_color_command is red --bold and fish_pager_color_prefix is cyan
+set -S fish_color_command | string match -e global
+# CHECK: $fish_color_command: set in global scope, unexported, with 2 elements
+
+# A theme with an error changes nothing.
+fish_config theme choose broken
+# CHECKERR: fish_config: {{.*}}/themes/broken.theme: line 2: 'notacolor' is not a color
+echo $status $fish_color_command
+# CHECK: 1 red --bold
+
+fish_config theme choose nosuchtheme
+# CHECKERR: fish_config: No such theme: 'nosuchtheme'
+
+fish_config theme dump | string match -e -r 'fish_color_command|fish_pager_color_prefix'
+# CHECK: fish_color_command red --bold
+# CHECK: fish_pager_color_prefix cyan
+
+# Saving makes the colors universal.
+fish_config theme save
+# CHECK: fish_color_command is red --bold and fish_pager_color_prefix is cyan
+# CHECK: fish_color_command is red --bold and fish_pager_color_prefix is cyan
+set -S fish_color_command | string match -e -r 'global|universal'
+# CHECK: $fish_color_command: set in universal scope, unexported, with 2 elements
+
+fish_config theme bogus 2>| head -n1
+# CHECK: fish_config: Subcommand 'bogus' is not valid
+
+rm -r $__fish_config_dir/themes
+
+# The sample prompts are built into fish.
+fish_config prompt list | string match -r '^(?:classic|justadollar)$'
+# CHECK: classic
+# CHECK: justadollar
+
+fish_config prompt show justadollar | string escape
+# CHECK: justadollar
+# CHECK: '$ '
+
+fish_config prompt show nosuchprompt
+# CHECKERR: fish_config: No such prompt: 'nosuchprompt'
+echo $status
+# CHECK: 1
+
+# Choosing a prompt shows it, and saves it.
+fish_config prompt choose justadollar | string escape
+# CHECK: '$ '
+string escape -- (fish_prompt)
+# CHECK: '$ '
+cat $__fish_config_dir/functions/fish_prompt.fish | string match -e echo
+# CHECK: echo -n '$ '
+
+fish_config prompt choose nosuchprompt
+# CHECKERR: fish_config: No such prompt: 'nosuchprompt'
+
+fish_config prompt bogus 2>| head -n1
+# CHECK: fish_config: Subcommand 'bogus' is not valid
+
+rm $__fish_config_dir/functions/fish_prompt.fish
@@ -0,0 +1,43 @@
+#RUN: %fish %s
+
+set -l tmp (mktemp -d)
+cd $tmp
+set -l tmp (pwd)
+mkdir proj
+echo 'set -g PROJ_VAR 1' >proj/.envrc.fish
+
+# The files are only loaded at the interactive prompt.
+fish_envrc status
+echo $status
+#CHECK: 1
+
+fish_envrc allow proj
+echo $status
+#CHECK: 0
+set -l entry (string match -- "* $tmp/proj/.envrc.fish" $fish_envrc_allowed)
+count $entry
+#CHECK: 1
+string match -qr '^[0-9a-f]{64} ' -- $entry; and echo hash
+#CHECK: hash
+
+# Allowing it again replaces the hash of the old contents.
+echo 'set -g PROJ_VAR 2' >>proj/.envrc.fish
+fish_envrc allow proj
+set -l new_entry (string match -- "* $tmp/proj/.envrc.fish" $fish_envrc_allowed)
+count $new_entry
+#CHECK: 1
+test "$entry" != "$new_entry"; and echo changed
+#CHECK: changed
+
+fish_envrc deny proj
+string match -q -- "* $tmp/proj/.envrc.fish" $fish_envrc_allowed; or echo denied
+#CHECK: denied
+
+fish_envrc deny proj
+#CHECKERR: fish_envrc: '{{.*}}/proj/.envrc.fish' is not trusted
+fish_envrc allow nowhere
+#CHECKERR: fish_envrc: Could not read '{{.*}}/nowhere/.envrc.fish'
+fish_envrc status proj
+#CHECKERR: fish_envrc status: Expected 0 args, got 1
+
+rm -r $tmp
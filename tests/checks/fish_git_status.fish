@@ -0,0 +1,108 @@
+#RUN: %fish %s
+#REQUIRES: command -v git
+
+# Ensure that no git environment variables are inherited, and git doesn't read the user's config.
+for varname in (set -x | string match 'GIT_*' | string replace -r ' .*' '')
+    set -e $varname
+end
+set -l tmp (mktemp -d)
+set -gx HOME $tmp
+set -e XDG_CONFIG_HOME
+
+cd $tmp
+fish_git_status
+echo $status
+#CHECK: 1
+
+mkdir repo
+cd repo
+git init >/dev/null 2>&1
+git config user.email fish@example.com
+git config user.name fish
+git checkout -b newbranch >/dev/null 2>&1
+
+# Without commits, there is a branch but no sha.
+fish_git_status branch sha inside-work-tree bare | string escape
+#CHECK: newbranch
+#CHECK: ''
+#CHECK: true
+#CHECK: false
+test (fish_git_status git-dir) = (realpath .git); and echo same git dir
+#CHECK: same git dir
+
+echo hello >tracked
+echo ignored >ignored.log
+echo '*.log' >.gitignore
+mkdir -p sub/dir
+echo new >sub/dir/untracked
+git add tracked
+fish_git_status staged dirty unmerged untracked
+#CHECK: 1
+#CHECK: 0
+#CHECK: 0
+#CHECK: 2
+
+# Untracked files are only counted if asked for.
+fish_git_status
+#CHECK: git-dir {{.*}}/repo/.git
+#CHECK: work-tree {{.*}}/repo
+#CHECK: inside-git-dir false
+#CHECK: inside-work-tree true
+#CHECK: bare false
+#CHECK: branch newbranch
+#CHECK: staged 1
+#CHECK: dirty 0
+#CHECK: unmerged 0
+
+git add .gitignore sub
+git commit -m initial >/dev/null
+test (fish_git_status sha) = (git rev-parse HEAD); and echo same sha
+#CHECK: same sha
+fish_git_status staged dirty untracked
+#CHECK: 0
+#CHECK: 0
+#CHECK: 0
+
+# Changes are found from any directory of the work tree, also when git had to pack its objects.
+git gc --quiet >/dev/null 2>&1
+echo changed >>tracked
+rm sub/dir/untracked
+echo other >other
+git add other
+cd sub
+fish_git_status staged dirty untracked
+#CHECK: 1
+#CHECK: 2
+#CHECK: 0
+cd ..
+
+# Detached HEADs have no branch.
+git commit -qam second
+git checkout -q HEAD~1
+fish_git_status branch
+#CHECK:
+git checkout -q newbranch
+
+# A merge with a conflict.
+git checkout -q -b side
+echo side >tracked
+git commit -qam side
+git checkout -q newbranch
+echo main >tracked
+git commit -qam main
+git merge side >/dev/null 2>&1
+fish_git_status operation staged unmerged
+#CHECK: MERGING
+#CHECK: 0
+#CHECK: 1
+git merge --abort
+
+fish_git_status frob
+#CHECKERR: fish_git_status: Unknown field 'frob'
+#CHECKERR: {{.*}}checks/fish_git_status.fish (line {{\d+}}):
+#CHECKERR: fish_git_status frob
+#CHECKERR: ^
+#CHECKERR: (Type 'help fish_git_status' for related documentation)
+
+cd /
+rm -rf $tmp
@@ -0,0 +1,25 @@
+#RUN: %fish -C "set fish %fish" %s
+# Color support is detected again when the variables it looks at change, and fish_reload_term
+# sets up the terminal again.
+
+set -l term_env env -u COLORTERM -u STY -u TERM_PROGRAM -u fish_term24bit TERM=xterm
+
+$term_env $fish -d term-support -c 'set -gx COLORTERM truecolor' 2>&1 |
+    string match -e COLORTERM
+# CHECK: term-support: Truecolor support: Enabling per $COLORTERM='truecolor'
+
+$term_env $fish -d term-support -c 'set -gx TERM_PROGRAM iTerm.app' 2>&1 |
+    string match -e 'emoji width'
+# CHECK: term-support: default emoji width: {{\d}}
+# CHECK: term-support: default emoji width: 1
+
+# Curses is set up at startup, and once more.
+$term_env $fish -d term-support -c fish_reload_term 2>&1 |
+    string match -e "curses var TERM="
+# CHECK: term-support: curses var TERM='xterm'
+# CHECK: term-support: curses var TERM='xterm'
+
+fish_reload_term extra
+# CHECKERR: fish_reload_term: Expected 0 args, got 1
+echo $status
+# CHECK: 2
@@ -0,0 +1,61 @@
+#RUN: %fish %s
+
+set -l tmp (mktemp -d)
+mkdir -p $tmp/projects/fish $tmp/projects/.hidden $tmp/bin
+printf '#!/bin/sh\n' >$tmp/bin/frobnicate
+chmod +x $tmp/bin/frobnicate
+set -gx PATH $tmp/bin $PATH
+set -gx HOME $tmp
+cd $tmp
+
+function spelltest
+end
+
+fish_spell_correct 'gti status'
+#CHECK: git status
+fish_spell_correct 'echo one | strnig upper; and spelltset'
+#CHECK: echo one | string upper; and spelltest
+fish_spell_correct 'frobnicte --all'
+#CHECK: frobnicate --all
+
+# Swapped letters are preferred.
+fish_spell_correct sl
+#CHECK: ls
+
+# Directories are corrected component by component.
+fish_spell_correct 'cd projcts/fsh'
+#CHECK: cd projects/fish
+fish_spell_correct 'cd ~/projetcs/'
+#CHECK: cd ~/projects/
+fish_spell_correct "cd $tmp/prjects"
+#CHECK: cd {{.*}}/projects
+fish_spell_correct 'cd projects/.hiden'
+#CHECK: cd projects/.hidden
+
+# Nothing to correct.
+fish_spell_correct ls
+echo $status
+#CHECK: 1
+fish_spell_correct 'cd projects'
+echo $status
+#CHECK: 1
+fish_spell_correct 'cd projects/nothinglikeit'
+echo $status
+#CHECK: 1
+fish_spell_correct 'gti$foo status'
+echo $status
+#CHECK: 1
+fish_spell_correct 'cd (echo prjects)'
+echo $status
+#CHECK: 1
+fish_spell_correct 'echo (gti'
+echo $status
+#CHECK: 1
+
+fish_spell_correct
+#CHECKERR: fish_spell_correct: Expected 1 args, got 0
+echo $status
+#CHECK: 2
+
+cd /
+rm -rf $tmp
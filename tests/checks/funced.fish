@@ -0,0 +1,105 @@
+#RUN: %fish -C 'set -g fish %fish' %s
+set fish (builtin realpath $fish)
+
+set -l tmp (mktemp -d)
+# The "editor" replaces $SEARCH with $REPLACE in the file.
+begin
+    echo "#!$fish"
+    echo 'string replace -r -- $SEARCH $REPLACE <$argv[1] >$argv[1].new'
+    echo 'command mv $argv[1].new $argv[1]'
+end >$tmp/edit
+chmod +x $tmp/edit
+set -gx VISUAL $tmp/edit
+
+function foo
+    echo hello
+end
+
+set -gx SEARCH hello
+set -gx REPLACE goodbye
+funced foo
+#CHECK: --- a/foo.fish
+#CHECK: +++ b/foo.fish
+#CHECK: @@ -1,3 +1,3 @@
+#CHECK:  function foo
+#CHECK: -    echo hello
+#CHECK: +    echo goodbye
+#CHECK:  end
+foo
+#CHECK: goodbye
+
+# Definitions with syntax errors are rejected.
+set -gx SEARCH 'echo goodbye'
+set -gx REPLACE 'echo goodbye |'
+funced foo
+echo $status
+#CHECKERR: foo.fish (line 3): Expected a command, but found keyword 'end'
+#CHECKERR: end
+#CHECKERR: ^
+#CHECKERR: Cancelled function editing
+#CHECK: 1
+foo
+#CHECK: goodbye
+
+# So are files which define another function.
+set -gx SEARCH 'function foo'
+set -gx REPLACE 'function bar'
+funced foo
+#CHECKERR: foo.fish: The function 'foo' is not defined
+#CHECKERR: Cancelled function editing
+functions -q bar
+or echo bar was not defined
+#CHECK: bar was not defined
+
+set -gx SEARCH nothing
+funced foo
+#CHECKERR: Editor exited but the function was not modified
+
+# New functions start out empty.
+set -gx SEARCH '^$'
+set -gx REPLACE '    echo new'
+funced newfunc
+#CHECK: --- a/newfunc.fish
+#CHECK: +++ b/newfunc.fish
+#CHECK: @@ -1,3 +1,3 @@
+#CHECK:  function newfunc
+#CHECK: -
+#CHECK: +    echo new
+#CHECK:  end
+newfunc
+#CHECK: new
+
+set -gx VISUAL nonexistent-editor --wait
+funced foo </dev/null
+#CHECKERR: funced: The value for $EDITOR 'nonexistent-editor --wait' could not be used because the command 'nonexistent-editor' could not be found
+#CHECKERR: funced: Editing in the shell requires a terminal
+
+funced
+#CHECKERR: funced: Expected 1 args, got 0
+funced foo bar
+#CHECKERR: funced: Expected 1 args, got 2
+
+# Functions are saved to the functions directory in the configuration.
+set -g __fish_config_dir $tmp/config
+set -gx VISUAL $tmp/edit
+set -gx SEARCH 'echo new'
+set -gx REPLACE 'echo newer'
+funced --save newfunc >/dev/null
+cat $tmp/config/functions/newfunc.fish
+#CHECK: function newfunc
+#CHECK:     echo newer
+#CHECK: end
+
+funcsave --directory $tmp/other foo
+string match -q '*echo goodbye*' <$tmp/other/foo.fish
+and echo saved foo
+#CHECK: saved foo
+ls $tmp/other
+#CHECK: foo.fish
+
+funcsave nosuchfunction
+echo $status
+#CHECKERR: funcsave: Unknown function 'nosuchfunction'
+#CHECK: 1
+
+rm -r $tmp
@@ -12,9 +12,9 @@ function frob -V foo -V bar -V baz
 end
 
 frob
-#CHECK: $foo: set in local scope, unexported, with 1 elements
+#CHECK: $foo: set in local scope, unexported, with 1 element
 #CHECK: $foo[1]: |local foo|
-#CHECK: $foo: set in global scope, unexported, with 1 elements
+#CHECK: $foo: set in global scope, unexported, with 1 element
 #CHECK: $foo[1]: |global foo|
 #CHECK: $bar: set in local scope, unexported, with 5 elements
 #CHECK: $bar[1]: |one|
@@ -35,9 +35,9 @@ set foo 'bad foo'
 set bar 'bad bar'
 set baz 'bad baz'
 frob
-#CHECK: $foo: set in local scope, unexported, with 1 elements
+#CHECK: $foo: set in local scope, unexported, with 1 element
 #CHECK: $foo[1]: |local foo|
-#CHECK: $foo: set in global scope, unexported, with 1 elements
+#CHECK: $foo: set in global scope, unexported, with 1 element
 #CHECK: $foo[1]: |global foo|
 #CHECK: $bar: set in local scope, unexported, with 5 elements
 #CHECK: $bar[1]: |one|
@@ -45,10 +45,10 @@ frob
 #CHECK: $bar[3]: |\t|
 #CHECK: $bar[4]: ||
 #CHECK: $bar[5]: |3|
-#CHECK: $bar: set in global scope, unexported, with 1 elements
+#CHECK: $bar: set in global scope, unexported, with 1 element
 #CHECK: $bar[1]: |bad bar|
 #CHECK: $baz: set in local scope, unexported, with 0 elements
-#CHECK: $baz: set in global scope, unexported, with 1 elements
+#CHECK: $baz: set in global scope, unexported, with 1 element
 #CHECK: $baz[1]: |bad baz|
 
 # This sequence of tests originally verified that functions `name2` and
@@ -112,6 +112,49 @@ end
 #CHECKERR: function test
 #CHECKERR: ^
 
+function every_minute --on-interval 60
+end
+function often --on-interval 0.25
+end
+functions often | string match -r -- '--on-interval.*'
+#CHECK: --on-interval 0.25
+functions --handlers-type interval
+#CHECK: Event interval
+#CHECK: 0.25 often
+#CHECK: 60 every_minute
+functions -e every_minute often
+
+function never --on-interval 0
+end
+#CHECKERR: {{.*}}checks/function.fish (line {{\d+}}): function: Invalid interval '0'
+#CHECKERR: function never --on-interval 0
+#CHECKERR: ^
+functions -q never; or echo not defined
+#CHECK: not defined
+
+# Paths are made absolute where the function is defined.
+cd /
+function on_change --on-file-change 'tmp/some file' --on-file-change /tmp/
+end
+functions on_change | string match -r -- '--on-file-change.*'
+#CHECK: --on-file-change '/tmp/some file' --on-file-change /tmp
+functions --handlers-type file-change
+#CHECK: Event file-change
+#CHECK: /tmp on_change
+#CHECK: /tmp/some file on_change
+functions -e on_change
+cd -
+
+# Handlers of async functions run as usual when the shell is not interactive.
+function async_handler --async --on-event async_test
+    echo handled $argv
+end
+functions async_handler | string match -r -- '--async.*'
+#CHECK: --async --on-event async_test
+emit async_test now
+#CHECK: handled now
+functions -e async_handler
+
 functions -q; or echo False
 #CHECK: False
 exit 0
@@ -85,6 +85,55 @@ string join \n **/bar | sort
 # CHECK: bar
 # CHECK: foo/bar
 
+rm -Rf *
+
+# Glob qualifiers select, order and limit matches.
+mkdir dir1 dir2
+touch -d '2020-01-01' old
+touch -d '2020-06-01' newer
+touch -d '2021-01-01' newest
+printf '%s\n' 1 2 3 4 >big
+touch -d '2019-01-01' big
+ln -s old link
+echo *[type=dir]
+# CHECK: dir1 dir2
+echo *[type=file,sort=mtime]
+# CHECK: newest newer link old big
+echo *[type=file,sort=-mtime,limit=2]
+# CHECK: big old
+echo *[type=link]
+# CHECK: link
+echo *[type=file,sort=size,limit=1]
+# CHECK: big
+echo d*[sort=-name]
+# CHECK: dir2 dir1
+echo n*[limit=0]
+# CHECKERR: {{.*}}glob.fish (line {{\d+}}): No matches for wildcard 'n*[limit=0]'. See `help expand`.
+# CHECKERR: echo n*[limit=0]
+# CHECKERR: ^
+for f in *[type=pipe]
+end
+# CHECKERR: {{.*}}glob.fish: Unknown glob type 'pipe'
+# Exclusions drop matches by name, or by path if they contain a slash.
+mkdir -p src/sub
+touch src/main.c src/main_test.c src/util.c src/sub/sub.c src/sub/sub_test.c
+echo src/*.c[exclude=*_test.c]
+# CHECK: src/main.c src/util.c
+echo src/**.c[exclude=*_test.c,exclude=util.?]
+# CHECK: src/main.c src/sub/sub.c
+echo src/**.c[exclude=src/sub/*]
+# CHECK: src/main.c src/main_test.c src/util.c
+echo src/*/[exclude=sub]
+# CHECKERR: {{.*}}glob.fish (line {{\d+}}): No matches for wildcard 'src/*/[exclude=sub]'. See `help expand`.
+# CHECKERR: echo src/*/[exclude=sub]
+# CHECKERR: ^
+rm -r src
+
+# Without a wildcard or '=', brackets are literal.
+touch 'x[1]' 'y[a=b]'
+echo x*[1] y[a=b] '*[type=dir]'
+# CHECK: x[1] y[a=b] *[type=dir]
+
 # Clean up.
 cd $oldpwd
 rm -Rf $tmpdir
@@ -0,0 +1,84 @@
+#RUN: %fish %s
+
+# Here-strings provide their expansion as input, one element per line.
+cat <<<hello
+#CHECK: hello
+set -l name world
+string upper <<<"hello $name"
+#CHECK: HELLO WORLD
+count <<<(seq 5)
+#CHECK: 5
+cat <<<""
+#CHECK:
+set -l empty
+count <<<$empty
+#CHECK: 0
+cat 3<<<three <&3
+#CHECK: three
+
+# Large here-strings do not block.
+string repeat -n 10000 abcdefgh | read -l big
+cat <<<$big | wc -c | string trim
+#CHECK: 80001
+
+# Here-documents are literal.
+cat <<END
+$name (not expanded)
+  'quotes' too
+END
+#CHECK: $name (not expanded)
+#CHECK:   'quotes' too
+
+# Indentation of the terminator is removed from the body.
+function indented
+    cat <<EOF
+    first
+      second
+    EOF
+end
+indented
+#CHECK: first
+#CHECK:   second
+
+cat <<EMPTY
+EMPTY
+echo after
+#CHECK: after
+
+# Redirections and a pipe may follow the marker on its line.
+cat <<EOF | string upper
+piped
+EOF
+#CHECK: PIPED
+set -l tmpfile (mktemp)
+cat <<EOF >$tmpfile; cat $tmpfile
+redirected
+EOF
+#CHECK: redirected
+rm $tmpfile
+cat <<EOF 2>/dev/null | string length
+four
+EOF
+#CHECK: 4
+
+echo "cat <<'EOF'
+EOF" | source
+#CHECKERR: - (line 1): Expected a here-document marker of letters, digits and underscores
+#CHECKERR: cat <<'EOF'
+#CHECKERR:       ^
+#CHECKERR: from sourcing file -
+#CHECKERR: {{\s*}}called on line {{\d+}} of file {{.*}}heredoc.fish
+#CHECKERR: source: Error while reading file '<stdin>'
+
+echo 'cat <<EOF
+unterminated' | source
+#CHECKERR: - (line 1): Unexpected end of string, here-document is not terminated
+#CHECKERR: cat <<EOF
+#CHECKERR: unterminated
+#CHECKERR:          ^
+#CHECKERR: from sourcing file -
+#CHECKERR: {{\s*}}called on line {{\d+}} of file {{.*}}heredoc.fish
+#CHECKERR: source: Error while reading file '<stdin>'
+
+printf '%s\n' 'cat <<EOF |' 'string upper' 'continued' EOF | source 2>&1 | string match '*(line*'
+#CHECK: - (line 1): The line beginning a here-document may not continue on the next line
@@ -55,6 +55,10 @@ builtin history --contains save
 #CHECKERR: history: you cannot use any options with the save command
 builtin history -t merge
 #CHECKERR: history: you cannot use any options with the merge command
+builtin history merge --strategy=bogus
+#CHECKERR: history: Invalid merge strategy 'bogus'
+builtin history save --strategy=session
+#CHECKERR: history: you cannot use --strategy with the save command
 
 # Now do a history command that should succeed so we exit with a zero,
 # success, status.
@@ -389,3 +389,52 @@ echo 'if true; and false; or true
 end' | $fish_indent --check
 echo $status
 #CHECK: 0
+
+# Formatting options.
+echo 'switch $x
+case a
+echo a
+end
+if true
+echo aaaaaaaaa bbbbbbbbb | string upper | string lower | sort
+end' | $fish_indent --indent-width=2 --case-style=flat --max-line-length=40
+#CHECK: switch $x
+#CHECK: case a
+#CHECK: {{  }}echo a
+#CHECK: end
+#CHECK: if true
+#CHECK: {{  }}echo aaaaaaaaa bbbbbbbbb \
+#CHECK: {{    }}| string upper \
+#CHECK: {{    }}| string lower \
+#CHECK: {{    }}| sort
+#CHECK: end
+
+# Short pipelines and ones that are already split are left alone.
+echo 'echo a | cat
+echo aaaaaaaaaa bbbbbbbbbbbb | cat \
+    | cat' | $fish_indent --max-line-length=20
+#CHECK: echo a | cat
+#CHECK: echo aaaaaaaaaa bbbbbbbbbbbb | cat \
+#CHECK: {{    }}| cat
+
+$fish_indent --indent-width=0 </dev/null
+#CHECKERR: Invalid indent width '0'
+$fish_indent --case-style=weird </dev/null
+#CHECKERR: Invalid case style 'weird'
+
+# Options are read from .editorconfig files, but the command line takes precedence.
+set -l tmpdir (mktemp -d)
+mkdir -p $tmpdir/sub
+printf '%s\n' 'root = true' '[*.fish]' 'indent_size = 3' '[sub/*.{fish,in}]' 'fish_case_style = flat' >$tmpdir/.editorconfig
+printf '%s\n' 'switch $x' 'case a' 'echo a' 'end' >$tmpdir/sub/test.fish
+$fish_indent $tmpdir/sub/test.fish
+#CHECK: switch $x
+#CHECK: case a
+#CHECK: {{   }}echo a
+#CHECK: end
+$fish_indent --indent-width=4 --case-style=indented $tmpdir/sub/test.fish
+#CHECK: switch $x
+#CHECK: {{    }}case a
+#CHECK: {{        }}echo a
+#CHECK: end
+rm -r $tmpdir
@@ -78,3 +78,32 @@ and echo matched
 string match -rq "echo thisshouldneverbeintheconfig" < $tmp/full.prof
 and echo matched
 # CHECK: matched
+
+# The JSON and callgrind formats say where each command is, and add up the times per function.
+printf '%s\n' 'function slow' '    true' end slow >$tmp/script.fish
+$fish --profile $tmp/json.prof --profile-format=json $tmp/script.fish
+string match -q '*{"command":"true","file":"'$tmp'/script.fish","line":2,"function":"slow",*' <$tmp/json.prof
+and echo matched
+# CHECK: matched
+string match -rq '"functions":\[{"name":"slow","file":"[^"]*","self":\d+,"total":\d+,"calls":1}\]' <$tmp/json.prof
+and echo matched
+# CHECK: matched
+
+$fish --profile $tmp/callgrind.prof --profile-format=callgrind $tmp/script.fish
+string match -r '^(?:events|fn|cfn|calls)=?.*' <$tmp/callgrind.prof | string replace $tmp TMP
+# CHECK: events: us
+# CHECK: fn=TMP/script.fish
+# CHECK: cfn=slow
+# CHECK: calls=1 2
+# CHECK: fn=slow
+
+$fish --profile-format=yaml -c true
+echo $status
+# CHECKERR: fish: Invalid profile format 'yaml', expected text, json or callgrind
+# CHECK: 1
+
+# --no-config skips the configuration files, including fish's own.
+$fish --no-config -c 'set -q fish_function_path; or echo no config read'
+# CHECK: no config read
+$fish -c 'set -q fish_function_path; and echo config read'
+# CHECK: config read
@@ -0,0 +1,34 @@
+#RUN: %fish %s
+# With fish_job_cgroups, jobs with job control run in a cgroup of their own, if the system lets
+# fish create one. Otherwise they run as usual.
+
+status job-control full
+set -g fish_job_cgroups 1
+
+sleep 0.5 &
+set -l line (jobs --verbose | string match -e cgroup)
+if set -q line[1]
+    string match -rq '^\t-\tcgroup\t[0-9.]+s\t(-|\d+ kB)\t/(.*/)?fish-\d+-job-\d+$' -- $line
+    and echo cgroup ok
+else
+    echo cgroup ok
+end
+# CHECK: cgroup ok
+
+# The processes of the job are in the cgroup.
+set -l cgroup (string split \t -- $line)[6]
+if set -q cgroup[1]
+    cat /proc/(jobs --last --pid)/cgroup | string match -q "0::$cgroup"
+    and echo in cgroup
+else
+    echo in cgroup
+end
+# CHECK: in cgroup
+wait
+
+echo hello | string upper
+# CHECK: HELLO
+set -e fish_job_cgroups
+sleep 0.5 &
+jobs --verbose | string match -e cgroup
+wait
@@ -101,3 +101,29 @@ echo $status
 #CHECK: Command
 #CHECK: sleep
 #CHECK: 0
+
+# --verbose prints a line per process, with the name of the program and its working directory.
+set -l dir (mktemp -d)
+pushd $dir
+sleep 0.5 | sleep 0.5 &
+popd
+set -l details (jobs -lv)[2..]
+count $details
+#CHECK: 2
+string split \t -- $details[1] | count
+#CHECK: 6
+for line in $details
+    set -l fields (string split \t -- $line)
+    echo $fields[3]
+    if contains (uname) Linux Darwin
+        test "$fields[6]" = (realpath $dir); and echo cwd ok
+    else
+        echo cwd ok
+    end
+end
+#CHECK: sleep
+#CHECK: cwd ok
+#CHECK: sleep
+#CHECK: cwd ok
+wait
+rm -r $dir
@@ -0,0 +1,65 @@
+#RUN: %fish %s
+kill -l 9 137 KILL sigterm
+#CHECK: KILL
+#CHECK: KILL
+#CHECK: 9
+#CHECK: 15
+kill -l | string match INT
+#CHECK: INT
+kill -L | string match -r '^ ?15 .*'
+#CHECK: 15 TERM         Polite quit request
+kill -l BOGUS
+#CHECKERR: kill: Unknown signal 'BOGUS'
+
+# Jobs can be given by id, as the most recent one or by their command.
+sleep 100 &
+sleep 200 &
+kill --verbose %-
+#CHECK: Sent SIGTERM to job 1, 'sleep 100 &'
+kill -s HUP --verbose %%
+#CHECK: Sent SIGHUP to job 2, 'sleep 200 &'
+wait
+jobs
+#CHECK: jobs: There are no jobs
+
+sleep 100 &
+set -l pid (jobs -lp)
+# exec, so that killing the shell leaves no sleep behind.
+sh -c 'exec sleep 100' &
+kill -0 $pid
+and echo exists
+#CHECK: exists
+kill %s
+#CHECKERR: kill: Ambiguous job specification: %s
+kill -KILL --verbose '%?sh -c'
+#CHECK: Sent SIGKILL to job 2, 'sh -c 'exec sleep 100' &'
+kill --verbose -- $pid
+#CHECK: Sent SIGTERM to process {{\d+}}
+wait
+
+# A stopped job is continued when it is asked to quit.
+sleep 100 &
+kill -STOP %1
+kill %1
+wait
+jobs
+#CHECK: jobs: There are no jobs
+
+# Nothing is signalled if an argument is invalid.
+sleep 100 &
+kill %1 %5
+#CHECKERR: kill: No suitable job: %5
+kill nope %1
+#CHECKERR: kill: 'nope' is not a valid process id or job
+kill -s BOGUS %1
+#CHECKERR: kill: Unknown signal 'BOGUS'
+jobs -q
+and echo still running
+#CHECK: still running
+kill %1
+wait
+
+kill
+#CHECKERR: kill: Expected at least 1 args, got 0
+kill -l -s KILL
+#CHECKERR: kill: Invalid combination of options
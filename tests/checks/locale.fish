@@ -80,3 +80,9 @@ env LC_ALL=C $fish -c 'echo -n Y\u00FCY' | display_bytes
 env LC_ALL=C $fish -c 'echo -n T\u01FDT' | display_bytes
 #CHECK: 0000000 124 077 124
 #CHECK: 0000003
+
+# Changing several locale variables in a row only reinitializes the locale once, before the next
+# command that isn't `set`.
+$fish -d env-dispatch -c 'echo; echo marker >&2; set -gx LANG C; set -gx LC_CTYPE C; set -e LC_ALL; echo' 2>&1 | string match -r 'marker|Reinitializing.*'
+#CHECK: marker
+#CHECK: Reinitializing locale
@@ -147,17 +147,17 @@ begin
     set --show loop_var
 end
 set --show loop_var
-#CHECK: $loop_var: set in local scope, unexported, with 1 elements
+#CHECK: $loop_var: set in local scope, unexported, with 1 element
 #CHECK: $loop_var[1]: |c|
-#CHECK: $loop_var: set in local scope, unexported, with 1 elements
+#CHECK: $loop_var: set in local scope, unexported, with 1 element
 #CHECK: $loop_var[1]: |b|
-#CHECK: $loop_var: set in global scope, unexported, with 1 elements
+#CHECK: $loop_var: set in global scope, unexported, with 1 element
 #CHECK: $loop_var[1]: |global_val|
-#CHECK: $loop_var: set in global scope, unexported, with 1 elements
+#CHECK: $loop_var: set in global scope, unexported, with 1 element
 #CHECK: $loop_var[1]: |global_val|
-#CHECK: $loop_var: set in local scope, unexported, with 1 elements
+#CHECK: $loop_var: set in local scope, unexported, with 1 element
 #CHECK: $loop_var[1]: |cc|
-#CHECK: $loop_var: set in global scope, unexported, with 1 elements
+#CHECK: $loop_var: set in global scope, unexported, with 1 element
 #CHECK: $loop_var[1]: |global_val|
-#CHECK: $loop_var: set in global scope, unexported, with 1 elements
+#CHECK: $loop_var: set in global scope, unexported, with 1 element
 #CHECK: $loop_var[1]: |global_val|
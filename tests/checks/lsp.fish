@@ -0,0 +1,63 @@
+# RUN: %fish -C 'set -g fish %fish' %s
+
+# Send each argument as a message to a language server, and print the bodies of its replies one
+# per line.
+function lsp
+    for msg in $argv
+        printf 'Content-Length: %d\r\n\r\n%s' (string length -- $msg) $msg
+    end | $fish --lsp | string replace -ra '\r|Content-Length: \d+' '' | string match -rv '^$'
+    return $pipestatus[2]
+end
+
+set -l init '{"jsonrpc":"2.0","id":1,"method":"initialize","params":{}}'
+set -l shutdown '{"jsonrpc":"2.0","id":99,"method":"shutdown"}'
+set -l exit '{"jsonrpc":"2.0","method":"exit"}'
+set -l uri 'file:///tmp/test.fish'
+set -l open '{"jsonrpc":"2.0","method":"textDocument/didOpen","params":{"textDocument":{"uri":"'$uri'","languageId":"fish","version":1,"text":"function foo\necho hi\nend\nfoo\n__fish_seen_argument\nset --qu"}}}'
+
+lsp $init $shutdown $exit
+echo $status
+# CHECK: {"jsonrpc":"2.0","id":1,"result":{"capabilities":{"textDocumentSync":1,"completionProvider":{"triggerCharacters":["-","$","/"]},"hoverProvider":true,"definitionProvider":true,"documentFormattingProvider":true},"serverInfo":{"name":"fish","version":"{{.*}}"}}}
+# CHECK: {"jsonrpc":"2.0","id":99,"result":null}
+# CHECK: 0
+
+# Exiting without a shutdown request is an error.
+lsp $init $exit
+echo $status
+# CHECK: {"jsonrpc":"2.0","id":1,{{.*}}
+# CHECK: 1
+
+# Syntax errors are published as diagnostics whenever a document changes, and cleared on close.
+lsp $init \
+    '{"jsonrpc":"2.0","method":"textDocument/didOpen","params":{"textDocument":{"uri":"'$uri'","languageId":"fish","version":1,"text":"echo hi\nif true"}}}' \
+    '{"jsonrpc":"2.0","method":"textDocument/didChange","params":{"textDocument":{"uri":"'$uri'","version":2},"contentChanges":[{"text":"echo hi"}]}}' \
+    '{"jsonrpc":"2.0","method":"textDocument/didClose","params":{"textDocument":{"uri":"'$uri'"}}}' \
+    $shutdown $exit | string match -v '*"id":*'
+# CHECK: {"jsonrpc":"2.0","method":"textDocument/publishDiagnostics","params":{"uri":"file:///tmp/test.fish","diagnostics":[{"range":{"start":{"line":1,"character":0},"end":{"line":1,"character":2}},"severity":1,"source":"fish","message":"Missing end to balance this if statement"}]}}
+# CHECK: {"jsonrpc":"2.0","method":"textDocument/publishDiagnostics","params":{"uri":"file:///tmp/test.fish","diagnostics":[]}}
+# CHECK: {"jsonrpc":"2.0","method":"textDocument/publishDiagnostics","params":{"uri":"file:///tmp/test.fish","diagnostics":[]}}
+
+# Hover, definitions, completions and formatting.
+lsp $init $open \
+    '{"jsonrpc":"2.0","id":2,"method":"textDocument/hover","params":{"textDocument":{"uri":"'$uri'"},"position":{"line":1,"character":2}}}' \
+    '{"jsonrpc":"2.0","id":3,"method":"textDocument/definition","params":{"textDocument":{"uri":"'$uri'"},"position":{"line":3,"character":1}}}' \
+    '{"jsonrpc":"2.0","id":4,"method":"textDocument/definition","params":{"textDocument":{"uri":"'$uri'"},"position":{"line":4,"character":3}}}' \
+    '{"jsonrpc":"2.0","id":5,"method":"textDocument/definition","params":{"textDocument":{"uri":"'$uri'"},"position":{"line":1,"character":6}}}' \
+    '{"jsonrpc":"2.0","id":6,"method":"textDocument/completion","params":{"textDocument":{"uri":"'$uri'"},"position":{"line":5,"character":8}}}' \
+    '{"jsonrpc":"2.0","id":7,"method":"textDocument/formatting","params":{"textDocument":{"uri":"'$uri'"},"options":{"tabSize":4,"insertSpaces":true}}}' \
+    '{"jsonrpc":"2.0","id":8,"method":"workspace/bogus","params":{}}' \
+    $shutdown $exit | string match -e '"id":' | string match -v '*"id":1,*'
+# CHECK: {"jsonrpc":"2.0","id":2,"result":{"contents":{"kind":"markdown","value":"**echo** (builtin)\n\nPrint arguments"}}}
+# CHECK: {"jsonrpc":"2.0","id":3,"result":{"uri":"file:///tmp/test.fish","range":{"start":{"line":0,"character":9},"end":{"line":0,"character":12}}}}
+# CHECK: {"jsonrpc":"2.0","id":4,"result":{"uri":"file://{{.*}}/functions/__fish_seen_argument.fish","range":{"start":{"line":0,"character":0},"end":{"line":0,"character":0}}}}
+# CHECK: {"jsonrpc":"2.0","id":5,"result":null}
+# CHECK: {"jsonrpc":"2.0","id":6,"result":[{"label":"--query","detail":"Test if variable is defined","filterText":"--query","textEdit":{"range":{"start":{"line":5,"character":4},"end":{"line":5,"character":8}},"newText":"--query"}}]}
+# CHECK: {"jsonrpc":"2.0","id":7,"result":[{"range":{"start":{"line":0,"character":0},"end":{"line":5,"character":8}},"newText":"function foo\n    echo hi\nend\nfoo\n__fish_seen_argument\nset --qu\n"}]}
+# CHECK: {"jsonrpc":"2.0","id":8,"error":{"code":-32601,"message":"Method not found: workspace/bogus"}}
+# CHECK: {"jsonrpc":"2.0","id":99,"result":null}
+
+# Nothing but protocol messages is written to stdout, even if the config prints something.
+lsp $init $shutdown $exit </dev/null | count
+$fish --lsp -C 'echo noise' </dev/null 2>/dev/null | count
+# CHECK: 2
+# CHECK: 0
@@ -182,7 +182,42 @@ math --base hex 12 + 0x50
 # CHECK: 0x5c
 math --base octal --scale=0 55
 # CHECK: 067
+math --base 2 "bitand(0b1100, 0b1010)"
+# CHECK: 0b1000
+math --base 36 35
+# CHECK: z
+math --base hex -- -255
+# CHECK: -0xff
+math --base 37 1
+# CHECKERR: math: '37' is not a valid base value
 math --base notabase
 # CHECKERR: math: 'notabase' is not a valid base value
 echo $status
 # CHECK: 2
+
+# Integer expressions are exact
+math '2 ^ 62'
+# CHECK: 4611686018427387904
+math 9007199254740993 - 1
+# CHECK: 9007199254740992
+math 0x7fffffffffffffff
+# CHECK: 9223372036854775807
+math --base hex "bitxor(0x5eadbeefcafe1234, 0xffff)"
+# CHECK: 0x5eadbeefcafeedcb
+math 6 / 2
+# CHECK: 3
+math 7 / 2
+# CHECK: 3.5
+math "bitshl(1, 40)"
+# CHECK: 1099511627776
+math "bitshr(-16, 2)"
+# CHECK: -4
+math --base hex "bitshr(0xff00, 8)"
+# CHECK: 0xff
+math "bitshl(1, 64)"
+# CHECKERR: math: Error: Result is not a number
+# CHECKERR: 'bitshl(1, 64)'
+# Overflowing integers fall back to floating point
+math '2 ^ 64'
+# CHECKERR: math: Error: Result magnitude is too large
+# CHECKERR: '2 ^ 64'
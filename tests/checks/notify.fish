@@ -0,0 +1,48 @@
+# RUN: %fish %s
+# REQUIRES: command -v python3
+
+# Without a service manager, there is nobody to notify.
+set -e NOTIFY_SOCKET
+status notify-ready
+echo $status
+#CHECK: 1
+
+set -l dir (mktemp -d)
+set -lx NOTIFY_SOCKET $dir/notify
+# Receive three messages, like a service manager would.
+python3 -c '
+import os, socket, sys
+sock = socket.socket(socket.AF_UNIX, socket.SOCK_DGRAM)
+sock.bind(sys.argv[1])
+open(sys.argv[1] + ".bound", "w").close()
+for i in range(3):
+    print(repr(sock.recv(4096).decode()))
+' $NOTIFY_SOCKET >$dir/received &
+while not test -e $NOTIFY_SOCKET.bound
+    sleep 0.01
+end
+
+status notify-ready
+echo $status
+#CHECK: 0
+status notify-ready "Serving requests
+on port 80"
+status notify-watchdog
+wait
+cat $dir/received
+#CHECK: 'READY=1'
+#CHECK: 'READY=1\nSTATUS=Serving requests'
+#CHECK: 'WATCHDOG=1'
+
+set NOTIFY_SOCKET relative
+status notify-watchdog
+echo $status
+#CHECKERR: status: Invalid NOTIFY_SOCKET 'relative'
+#CHECK: 2
+set NOTIFY_SOCKET $dir/notify
+status notify-watchdog
+echo $status
+#CHECKERR: status: Could not notify the service manager: Connection refused
+#CHECK: 2
+
+rm -r $dir
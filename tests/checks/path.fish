@@ -0,0 +1,38 @@
+#RUN: %fish %s
+# path convert translates paths between their Windows and Unix forms.
+
+path convert --unix --style wsl 'C:\Users\me' C:/x d: '\\\\wsl$\Ubuntu\home' 'a\b'
+# CHECK: /mnt/c/Users/me
+# CHECK: /mnt/c/x
+# CHECK: /mnt/d
+# CHECK: /home
+# CHECK: a/b
+path convert -u -s msys 'C:\Program Files\Git'
+# CHECK: /c/Program Files/Git
+
+path convert --windows --style wsl /mnt/c/Users/me /mnt/d a/b
+# CHECK: C:\Users\me
+# CHECK: D:\
+# CHECK: a\b
+path convert -w -s msys /c/Users/me
+# CHECK: C:\Users\me
+
+# Paths which have no name in the other form are errors.
+path convert --unix --style msys 'C:foo' '\\\\wsl$\Ubuntu\home' 'C:\ok'
+echo $status
+# CHECKERR: path convert: 'C:foo' cannot be translated
+# CHECKERR: path convert: '\\wsl$\Ubuntu\home' cannot be translated
+# CHECK: /c/ok
+# CHECK: 1
+path convert --windows --style wsl /usr /mnt/cc
+# CHECKERR: path convert: '/usr' cannot be translated
+# CHECKERR: path convert: '/mnt/cc' cannot be translated
+
+path convert --unix --windows --style wsl x
+# CHECKERR: path convert: Invalid combination of options,
+# CHECKERR: Exactly one of --windows and --unix must be given
+path convert --unix --style dos x
+# CHECKERR: path convert: Invalid style 'dos'
+
+path nonesuch 2>&1 | head -n1
+# CHECK: path: Subcommand 'nonesuch' is not valid
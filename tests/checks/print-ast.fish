@@ -0,0 +1,36 @@
+# RUN: %fish -C 'set -g fish %fish; set -g fish_indent %fish_indent' %s
+
+$fish --print-ast -c 'echo hi'
+# CHECK: {"ast":{"type":"job_list","start":0,"length":7,"children":[{"type":"job_conjunction","start":0,"length":7,"children":[{"type":"job","start":0,"length":7,"children":[{"type":"variable_assignment_list","start":0,"length":0,"children":[]},{"type":"statement","start":0,"length":7,"children":[{"type":"decorated_statement","start":0,"length":7,"children":[{"type":"token_base","start":0,"length":4,"token":"string","source":"echo"},{"type":"argument_or_redirection_list","start":5,"length":2,"children":[{"type":"argument_or_redirection","start":5,"length":2,"children":[{"type":"argument","start":5,"length":2,"source":"hi"}]}]}]}]},{"type":"job_continuation_list","start":0,"length":0,"children":[]}]},{"type":"job_conjunction_continuation_list","start":0,"length":0,"children":[]}]}]},"comments":[],"errors":[]}
+echo $status
+# CHECK: 0
+
+# Keywords, comments and escaping.
+$fish --print-ast -c 'if true # "quoted"
+end' | string match -ar '"(?:keyword|source)":"[^"\\\\]*(?:\\\\.[^"\\\\]*)*"'
+# CHECK: "keyword":"if"
+# CHECK: "source":"if"
+# CHECK: "source":"true"
+# CHECK: "source":"\n"
+# CHECK: "keyword":"end"
+# CHECK: "source":"end"
+# CHECK: "source":"# \"quoted\""
+
+# Errors are reported with their location, and the script is not run.
+$fish --print-ast -c 'echo $$' | string match -r '"errors":.*'
+# CHECK: "errors":[{"start":6,"length":0,"message":"$$ is not the pid. In fish, please use $fish_pid."}]}
+echo $pipestatus[1]
+# CHECK: 1
+
+$fish --print-ast -c 'echo (' | string match -r '"errors":\[{"start":5,.*'
+# CHECK: "errors":[{"start":5,{{.*}}
+
+# Scripts can be read from files or stdin.
+echo 'echo hi' | $fish --print-ast | string match -q '*"source":"hi"*'
+and echo stdin ok
+# CHECK: stdin ok
+
+# fish_indent prints the same.
+test (printf 'echo hi' | $fish_indent --dump-ast) = ($fish --print-ast -c 'echo hi')
+and echo same
+# CHECK: same
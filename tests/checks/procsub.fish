@@ -0,0 +1,85 @@
+#RUN: %fish %s
+
+# Process substitution provides the output of a command as a file.
+cat <(echo foo; echo bar)
+# CHECK: foo
+# CHECK: bar
+
+string match -q '/dev/fd/*' <(true)
+and echo is a path
+# CHECK: is a path
+
+diff <(seq 3) <(seq 3)
+and echo same
+# CHECK: same
+
+# It works for builtins and functions too, and as a redirection target.
+source <(echo echo sourced)
+# CHECK: sourced
+read -l line < <(echo redirected)
+echo $line
+# CHECK: redirected
+function first_line
+    head -n1 $argv
+end
+first_line <(printf '%s\n' one two)
+# CHECK: one
+
+# Output is passed through unsplit, including large amounts.
+count <(seq 100000)
+# CHECK: 1
+wc -l <(seq 100000) | string match -r '^\s*\d+'
+# CHECK: 100000
+
+# External commands run alongside the job, so their output may be endless.
+head -n2 <(yes)
+# CHECK: y
+# CHECK: y
+head -n1 <(yes | tr y z)
+# CHECK: z
+
+# Command substitutions inside work.
+cat <(echo (echo nested))
+# CHECK: nested
+
+# The pipe is closed after the job.
+set -l path <(echo gone)
+test -e $path
+or echo closed
+# CHECK: closed
+
+# Reading nothing is fine.
+head -c0 <(seq 100000)
+echo $status
+# CHECK: 0
+
+# The command's status is kept like with command substitutions.
+echo <(false) >/dev/null
+echo $status
+# CHECK: 0
+true <(false)
+echo $status
+# CHECK: 0
+
+# A redirection followed by a command substitution is still a redirection.
+echo (echo redirect-target) >/dev/null
+cat < (echo /dev/null)
+echo $status
+# CHECK: 0
+
+# Quoted or escaped, it is just text.
+echo '<(foo)' \<(echo bar)
+# CHECK: <(foo) <bar
+
+echo <(echo foo)suffix
+# CHECKERR: {{.*}}procsub.fish (line {{\d+}}): A process substitution must be a complete argument
+# CHECKERR: echo <(echo foo)suffix
+# CHECKERR: ^
+
+# There is no command to read it outside of one.
+for x in <(echo foo)
+    echo $x
+end
+# CHECKERR: {{.*}}procsub.fish (line {{\d+}}): A process substitution may only be used in the arguments or redirections of a command
+# CHECKERR: for x in <(echo foo)
+# CHECKERR: {{ *}}^
@@ -0,0 +1,20 @@
+# RUN: %fish %s
+
+prompt_segment
+# CHECKERR: prompt_segment: Expected at least 2 args, got 0
+# CHECKERR: {{.*}}checks/prompt_segment.fish (line {{\d+}}):
+# CHECKERR: prompt_segment
+# CHECKERR: ^
+# CHECKERR: (Type 'help prompt_segment' for related documentation)
+echo $status
+# CHECK: 2
+
+prompt_segment name this-command-does-not-exist
+# CHECKERR: prompt_segment: Unknown command 'this-command-does-not-exist'
+# CHECKERR: {{.*}}checks/prompt_segment.fish (line {{\d+}}):
+# CHECKERR: prompt_segment name this-command-does-not-exist
+# CHECKERR: ^
+# CHECKERR: (Type 'help prompt_segment' for related documentation)
+echo $status
+# CHECK: 127
+
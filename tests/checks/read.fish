@@ -249,9 +249,9 @@ end
 
 # Confirm reading non-interactively works -- \#4206 regression
 echo abc\ndef | $fish -i -c 'read a; read b; set --show a; set --show b'
-#CHECK: $a: set in global scope, unexported, with 1 elements
+#CHECK: $a: set in global scope, unexported, with 1 element
 #CHECK: $a[1]: |abc|
-#CHECK: $b: set in global scope, unexported, with 1 elements
+#CHECK: $b: set in global scope, unexported, with 1 element
 #CHECK: $b[1]: |def|
 
 # Test --delimiter (and $IFS, for now)
@@ -345,3 +345,13 @@ echo c $c
 # CHECK: a 'afoo barb'
 # CHECK: b
 # CHECK: c
+
+echo foo | read --mask '**' a
+# CHECKERR: read: Mask must be a single character or empty, not '**'
+echo $status
+# CHECK: 2
+
+# The mask only matters when reading from the terminal.
+echo hunter2 | read --mask '' a
+echo $a
+# CHECK: hunter2
@@ -113,3 +113,33 @@ echo "/bin/echo pipe 12 <&12 12<&-" | source 12<&0
 #CHECK: pipe 10
 #CHECK: pipe 11
 #CHECK: pipe 12
+
+# Named fd redirections open an fd in the shell and store it in a variable.
+set -l tmpdir (mktemp -d)
+true {logfd}>$tmpdir/named.txt
+test $logfd -ge 10; and echo high fd
+#CHECK: high fd
+echo first >&$logfd
+command echo second >&$logfd
+cat $tmpdir/named.txt
+#CHECK: first
+#CHECK: second
+true {logfd}>&-
+echo third >&$logfd
+#CHECKERR: write: Bad file descriptor
+true {logfd}>&-
+#CHECKERR: {{.*}}: Variable 'logfd' does not name an open file descriptor
+#CHECKERR: true {logfd}>&-
+#CHECKERR:      ^
+
+true {infd}<$tmpdir/named.txt
+read -l line <&$infd
+echo $line
+#CHECK: first
+true {infd}<&-
+
+# Braces that do not name a variable are not redirections.
+echo {a,b}>$tmpdir/braces.txt
+cat $tmpdir/braces.txt
+#CHECK: a b
+rm -r $tmpdir
@@ -203,6 +203,23 @@ set -eU __fish_test_universal_variables_variable_foo
 
 functions -e watch_foo
 
+# Universal variable events say which session made the change, and give the old value.
+function watch_foo --on-variable __fish_test_universal_variables_variable_foo
+    set -l origin (string replace -- "$fish_pid@$hostname" self $argv[4])
+    echo $argv[1..3] $origin "($argv[5..-1])"
+end
+set -U __fish_test_universal_variables_variable_foo 1 2
+# CHECK: VARIABLE SET __fish_test_universal_variables_variable_foo self ()
+set -U __fish_test_universal_variables_variable_foo 3
+# CHECK: VARIABLE SET __fish_test_universal_variables_variable_foo self (1 2)
+$FISH -c 'set -U __fish_test_universal_variables_variable_foo 4'
+set -U __fish_test_universal_variables_variable_bar 1
+# CHECK: VARIABLE SET __fish_test_universal_variables_variable_foo {{\d+}}@{{.*}} (3)
+set -eU __fish_test_universal_variables_variable_foo
+# CHECK: VARIABLE ERASE __fish_test_universal_variables_variable_foo self (4)
+set -eU __fish_test_universal_variables_variable_bar
+functions -e watch_foo
+
 # test erasing variables without a specified scope
 
 set -g test16res
@@ -498,7 +515,7 @@ set -Ux EDITOR emacs -nw
 $FISH -c 'set -S EDITOR' | string match -r -e 'global|universal'
 
 # When the variable has been changed outside of fish we accept it.
-# CHECK: $EDITOR: set in global scope, exported, with 1 elements
+# CHECK: $EDITOR: set in global scope, exported, with 1 element
 # CHECK: $EDITOR: set in universal scope, exported, with 2 elements
 sh -c "EDITOR='vim -g' $FISH -c "'\'set -S EDITOR\'' | string match -r -e 'global|universal'
 
@@ -509,12 +526,12 @@ set --show 'argle bargle'
 # Verify behavior of `set --show`
 set semiempty ''
 set --show semiempty
-#CHECK: $semiempty: set in global scope, unexported, with 1 elements
+#CHECK: $semiempty: set in global scope, unexported, with 1 element
 #CHECK: $semiempty[1]: ||
 
 set -U var1 hello
 set --show var1
-#CHECK: $var1: set in universal scope, unexported, with 1 elements
+#CHECK: $var1: set in universal scope, unexported, with 1 element
 #CHECK: $var1[1]: |hello|
 
 set -l var1
@@ -524,7 +541,7 @@ set --show var1
 #CHECK: $var1: set in global scope, unexported, with 2 elements
 #CHECK: $var1[1]: |goodbye|
 #CHECK: $var1[2]: |and don\'t come back|
-#CHECK: $var1: set in universal scope, unexported, with 1 elements
+#CHECK: $var1: set in universal scope, unexported, with 1 element
 #CHECK: $var1[1]: |hello|
 
 set -g var2
@@ -551,7 +568,7 @@ set --show var3b
 set -g var3c
 set -a var3c 'one string'
 set --show var3c
-#CHECK: $var3c: set in global scope, unexported, with 1 elements
+#CHECK: $var3c: set in global scope, unexported, with 1 element
 #CHECK: $var3c[1]: |one string|
 
 # Prepending works
@@ -574,7 +591,7 @@ set --show var4b
 set -g var4c
 set -p var4c 'one string'
 set --show var4c
-#CHECK: $var4c: set in global scope, unexported, with 1 elements
+#CHECK: $var4c: set in global scope, unexported, with 1 element
 #CHECK: $var4c[1]: |one string|
 
 # Appending and prepending at same time works
@@ -601,10 +618,35 @@ end
 #CHECK: $var6[1]: |ghi|
 #CHECK: $var6[2]: |jkl|
 #CHECK: $var6[3]: |mno|
+
 #CHECK: $var6: set in global scope, unexported, with 2 elements
 #CHECK: $var6[1]: |ghi|
 #CHECK: $var6[2]: |jkl|
 
+# --unique skips values which are already there or given twice
+set -g var7 a b a
+set -a --unique var7 b c c d
+set -p --unique var7 a e
+echo $var7
+#CHECK: e a b a c d
+# Paths are compared normalized
+set -g var7PATH /usr/bin
+set -a --unique var7PATH /usr/bin/ /usr//local/bin/ /usr/local/bin
+echo $var7PATH
+#CHECK: /usr/bin /usr//local/bin/
+set -g --unpath var7PATH /usr/bin
+set -a --unique var7PATH /usr/bin/
+echo $var7PATH
+#CHECK: /usr/bin /usr/bin/
+set --unique var7 x
+#CHECKERR: set: Invalid combination of options,
+#CHECKERR: --unique requires --append or --prepend
+#CHECKERR: {{.*}}checks/set.fish (line {{\d+}}):
+#CHECKERR: set --unique var7 x
+#CHECKERR: ^
+#CHECKERR: (Type 'help set' for related documentation)
+set -e var7 var7PATH
+
 # Exporting works
 set -x TESTVAR0
 set -x TESTVAR1 a
@@ -628,13 +670,13 @@ function test_ifforwhile_scope
     set --show ifvar1 ifvar2 ifvar3 whilevar1
 end
 test_ifforwhile_scope
-#CHECK: $ifvar1: set in local scope, unexported, with 1 elements
+#CHECK: $ifvar1: set in local scope, unexported, with 1 element
 #CHECK: $ifvar1[1]: |val1|
-#CHECK: $ifvar2: set in local scope, unexported, with 1 elements
+#CHECK: $ifvar2: set in local scope, unexported, with 1 element
 #CHECK: $ifvar2[1]: |val2|
-#CHECK: $ifvar3: set in local scope, unexported, with 1 elements
+#CHECK: $ifvar3: set in local scope, unexported, with 1 element
 #CHECK: $ifvar3[1]: |val3|
-#CHECK: $whilevar1: set in local scope, unexported, with 1 elements
+#CHECK: $whilevar1: set in local scope, unexported, with 1 element
 #CHECK: $whilevar1[1]: |val3|
 
 # $status should always be read-only, setting it makes no sense because it's immediately overwritten.
@@ -652,7 +694,7 @@ env | string match -e __fish_test_universal_exported_var
 # If they can be set they can only be set in global scope,
 # so they should only be shown in global scope.
 set -S status
-#CHECK: $status: set in global scope, unexported, with 1 elements
+#CHECK: $status: set in global scope, unexported, with 1 element
 #CHECK: $status[1]: |0|
 
 set -ql history
@@ -661,7 +703,7 @@ echo $status
 
 set --path newvariable foo
 set -S newvariable
-#CHECK: $newvariable: set in global scope, unexported, a path variable with 1 elements
+#CHECK: $newvariable: set in global scope, unexported, a path variable with 1 element
 #CHECK: $newvariable[1]: |foo|
 
 set foo foo
@@ -683,3 +725,67 @@ echo $bar
 #CHECK: 1 3
 
 true
+
+# Universal variables can be set for this host only, which shadows the shared value.
+set -U __fish_test_host_var shared
+set -U --host __fish_test_host_var mine
+echo $__fish_test_host_var
+#CHECK: mine
+set -S __fish_test_host_var
+#CHECK: $__fish_test_host_var: set in universal scope for this host, unexported, with 1 element
+#CHECK: $__fish_test_host_var[1]: |mine|
+$FISH -c 'echo $__fish_test_host_var; set -U __fish_test_host_var changed'
+#CHECK: mine
+echo $__fish_test_host_var
+#CHECK: changed
+set -Ue __fish_test_host_var
+echo $__fish_test_host_var
+#CHECK: shared
+set -Ue __fish_test_host_var
+set -q __fish_test_host_var
+or echo erased
+#CHECK: erased
+
+set --host __fish_test_host_var 1
+#CHECKERR: set: Invalid combination of options,
+#CHECKERR: --host requires --universal
+#CHECKERR: {{.*}}checks/set.fish (line {{\d+}}):
+#CHECKERR: set --host __fish_test_host_var 1
+#CHECKERR: ^
+#CHECKERR: (Type 'help set' for related documentation)
+
+# Universal variables can be exported to a file, and imported from it.
+set -l uvar_file (mktemp)
+set -U __fish_test_export_a 1 2
+set -Ux __fish_test_export_b 'x y'
+set -U --export-file $uvar_file __fish_test_export_a __fish_test_export_b
+string match -v '#*' <$uvar_file
+#CHECK: SETUVAR __fish_test_export_a:1\x1e2
+#CHECK: SETUVAR --export __fish_test_export_b:x\x20y
+set -Ue __fish_test_export_a
+set -U __fish_test_export_b changed
+
+# Variables which are set keep their values, unless --overwrite is given.
+set -U --import-file $uvar_file --dry-run
+#CHECK: + __fish_test_export_a '1'  '2'
+set -U --import-file $uvar_file --overwrite --dry-run
+#CHECK: + __fish_test_export_a '1'  '2'
+#CHECK: - __fish_test_export_b changed
+#CHECK: + __fish_test_export_b 'x y'
+set -U --import-file $uvar_file
+echo $__fish_test_export_a / $__fish_test_export_b
+#CHECK: 1 2 / changed
+set -U --import-file $uvar_file --overwrite __fish_test_export_b
+set -S __fish_test_export_b
+#CHECK: $__fish_test_export_b: set in universal scope, exported, with 1 element
+#CHECK: $__fish_test_export_b[1]: |x y|
+set -U --import-file $uvar_file --overwrite --dry-run
+echo $status
+#CHECK: 0
+
+set -U --import-file $uvar_file __fish_test_export_c
+#CHECKERR: set: Variable '__fish_test_export_c' is not in '{{.*}}'
+set -U --export-file $uvar_file __fish_test_export_c
+#CHECKERR: set: Universal variable '__fish_test_export_c' does not exist
+set -Ue __fish_test_export_a __fish_test_export_b
+rm $uvar_file
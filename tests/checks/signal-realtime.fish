@@ -0,0 +1,50 @@
+#RUN: %fish %s
+# The util-linux kill can queue signals with a value.
+#REQUIRES: env kill --help 2>&1 | grep -q -- --queue
+
+function rt --on-signal RTMIN+2
+    echo $argv (test "$signal_uid" = (id -u); and echo from user) value: $signal_value
+end
+functions --handlers -t signal | string match '*RTMIN*'
+# CHECK: SIGRTMIN+2 rt
+
+env kill -s RTMIN+2 $fish_pid
+# CHECK: SIGRTMIN+2 from user value:
+env kill -q 42 -s RTMIN+2 $fish_pid
+# CHECK: SIGRTMIN+2 from user value: 42
+
+# The sender is the kill process.
+function usr2 --on-signal USR2
+    set -g sender $signal_pid
+end
+env kill -s USR2 $fish_pid
+set -q sender[1]
+and test $sender != $fish_pid
+and echo sender is known
+# CHECK: sender is known
+
+# The variables are only visible in the handler.
+set -q signal_pid
+or echo not set outside
+# CHECK: not set outside
+
+# Names are case insensitive, and counted from the minimum or maximum.
+function rtmax --on-signal sigrtmax-0
+end
+functions rtmax | string match -r 'on-signal \S+'
+# CHECK: on-signal SIGRTMAX
+function rtbad --on-signal RTMAX+1
+end
+# CHECKERR: {{.*}}Unknown signal 'RTMAX+1'
+# CHECKERR: {{.*}}
+# CHECKERR: {{.*}}
+# CHECKERR: {{.*}}
+
+trap 'echo trapped $argv $signal_value' SIGRTMIN+3
+env kill -q 7 -s RTMIN+3 $fish_pid
+# CHECK: trapped SIGRTMIN+3 7
+trap -p RTMIN+3
+# CHECK: # Defined via `source`
+# CHECK: function __trap_handler_RTMIN+3 --on-signal SIGRTMIN+3
+# CHECK:  echo trapped $argv $signal_value;
+# CHECK: end
@@ -57,6 +57,7 @@ status features
 #CHECK: stderr-nocaret	off	3.0	^ no longer redirects stderr
 #CHECK: qmark-noglob	off	3.0	? no longer globs
 #CHECK: regex-easyesc	off	3.1	string replace -r needs fewer \'s
+#CHECK: auto-cd	off	3.2	a directory name as a command cds to it
 status test-feature stderr-nocaret
 echo $status
 #CHECK: 1
@@ -80,3 +81,149 @@ if false
 end
 echo $status
 #CHECK: 0
+
+# spawn-stats counts external commands by launch strategy.
+function spawn_stat
+    status spawn-stats | string replace -rf "^$argv[1]: " ''
+end
+set -l forks (spawn_stat fork)
+set -l disabled (spawn_stat '  posix_spawn disabled')
+set -g fish_use_posix_spawn 0
+command true
+set -e fish_use_posix_spawn
+math (spawn_stat fork) - $forks
+#CHECK: 1
+math (spawn_stat '  posix_spawn disabled') - $disabled
+#CHECK: 1
+status spawn-stats foo
+#CHECKERR: status spawn-stats: Expected 0 args, got 1
+
+# log lists the debug categories, turns them on and off, and sends them to a file.
+status log | string match -r '^exec-job-status\t.*'
+#CHECK: exec-job-status	off	Jobs changing status
+set -l logfile (mktemp)
+status log output $logfile
+status log enable 'exec-job-*' nonesuch
+#CHECKERR: status: Unknown debug category 'nonesuch'
+status log | string match -r '^exec-job-\S+\ton'
+#CHECK: exec-job-exec	on
+#CHECK: exec-job-status	on
+false
+status log disable exec-job-exec,exec-job-status
+status log output stderr
+string match -q '*Set status of job*(false ...)*' <$logfile
+and echo logged to file
+#CHECK: logged to file
+rm $logfile
+status log | string match -r '^exec-job-\S+\ton'
+status log frob
+#CHECKERR: status: Invalid log action 'frob'
+
+# events --follow traces each event with its handlers, nesting events emitted by a handler.
+function outer_handler --on-event trace-outer
+    emit trace-inner $argv
+end
+function inner_handler --on-event trace-inner
+end
+status events
+or echo not following
+#CHECK: not following
+set -l tracefile (mktemp)
+status events --follow $tracefile
+status events
+and echo following
+#CHECK: following
+emit trace-outer 'a b'
+status events --no-follow
+status log output stderr
+emit trace-outer ignored
+string replace -r '[\d.]+ ms$' 'N ms' <$tracefile
+#CHECK: event-trace: generic trace-outer 'a b' (1 handler)
+#CHECK: event-trace:   generic trace-inner 'a b' (1 handler)
+#CHECK: event-trace:     inner_handler: N ms
+#CHECK: event-trace:   outer_handler: N ms
+rm $tracefile
+status events --no-follow extra
+#CHECKERR: status events: Expected 0 args, got 1
+status log --follow
+#CHECKERR: status: Invalid combination of options,
+#CHECKERR: --follow and --no-follow can only be used with 'events'
+
+# Features can be printed as JSON, and some can be changed at runtime.
+status features --json | string match -r '{"name":"regex-easyesc",[^}]*}'
+#CHECK: {"name":"regex-easyesc","enabled":false,"groups":["3.1"],"description":"string replace -r needs fewer \\'s","runtime":true}
+status features enable regex-easyesc
+status test-feature regex-easyesc
+echo $status
+#CHECK: 0
+status features disable regex-easyesc qmark-noglob
+#CHECKERR: status: Feature 'qmark-noglob' can only be set at startup, with fish --features or $fish_features
+status test-feature regex-easyesc
+echo $status
+#CHECK: 1
+status is-login --json
+#CHECKERR: status: Invalid combination of options,
+#CHECKERR: --json can only be used with 'features', 'stack-trace' and 'fds'
+
+# The stack trace can be printed as JSON, with the arguments of each frame.
+function inner
+    status stack-trace --json
+end
+function outer
+    inner $argv[2..-1] 'with "quotes"'
+end
+outer first second
+#CHECK: [{"type":"function","function":"inner","arguments":["second","with \"quotes\""],"file":{{.*}}status.fish","line":{{\d+}}},{"type":"function","function":"outer","arguments":["first","second"],"file":{{.*}}status.fish","line":{{\d+}}}]
+function handler --on-event stack_json
+    status stack-trace --json
+end
+emit stack_json arg
+#CHECK: [{"type":"function","function":"handler","arguments":["arg"],"file":{{.*}}status.fish","line":{{\d+}}},{"type":"event","event":"handler for generic event 'stack_json'","arguments":["arg"],"file":{{.*}}status.fish","line":{{\d+}}}]
+echo 'status stack-trace --json' | source
+#CHECK: [{"type":"source","sourced_file":"-","file":{{.*}}status.fish","line":{{\d+}}}]
+
+# The open file descriptors are listed with what they are for.
+status fds | string match -r '^\d+\tdirectory\tcloexec\tworking directory\t.*'
+#CHECK: {{\d+}}	directory	cloexec	working directory	{{.*}}
+status fds | string match -q -r '^2\t.*\tstandard error'
+and echo stderr listed
+#CHECK: stderr listed
+set -g tmp (mktemp -d)
+fdopen -w fd $tmp/file
+status fds | string match -r "^$fd\t.*"
+#CHECK: {{\d+}}	file	cloexec	script fd (write)	{{.*}}/file
+status fds --json | string match -r '{"fd":'$fd'[^}]*}'
+#CHECK: {"fd":{{\d+}},"type":"file","inherited":false,"purpose":"script fd (write)","target":"{{.*}}/file"}
+fdclose $fd
+rm -r $tmp
+status fds foo
+#CHECKERR: status fds: Expected 0 args, got 1
+
+# pipestatus shows the stages of the last pipeline, with the names of signals.
+true | false | true
+status pipestatus
+echo $status
+#CHECK: 0|1|0
+#CHECK: 1
+status pipestatus 0 141 130 255
+echo $status
+#CHECK: 0|SIGPIPE|SIGINT|255
+#CHECK: 1
+status pipestatus 0 141
+echo $status
+#CHECK: 0|SIGPIPE
+#CHECK: 0
+# Stages which did not fail are not colored.
+status pipestatus --pretty 0 141 | string escape
+#CHECK: '0|SIGPIPE'
+status pipestatus foo
+#CHECKERR: status: Argument 'foo' is not a valid integer
+status fds --pretty
+#CHECKERR: status: Invalid combination of options,
+#CHECKERR: --pretty can only be used with 'pipestatus'
+prompt_pipestatus 0 141
+or echo nothing failed
+#CHECK: nothing failed
+set -g fish_color_status normal
+prompt_pipestatus 0 2 | string replace -ra '\e\[[^m]*m|\e\(B' ''
+#CHECK: [0|2]
@@ -703,6 +703,31 @@ yes | string replace -q y n
 echo $status
 # CHECK: 0
 
+# string replace --function replaces matches with the output of a function
+function __string_double
+    math $argv[1] \* 2
+end
+string replace -ra --function __string_double '\d+' '3 apples and 20 pears' 'no numbers'
+# CHECK: 6 apples and 40 pears
+# CHECK: no numbers
+string replace -f --function __string_double 2 1 2 22
+# CHECK: 4
+# CHECK: 42
+echo 2 | string replace -a --function __string_double 2
+# CHECK: 4
+function __string_show_args
+    echo "<"(string join , -- $argv)">"
+end
+string replace -r --function __string_show_args '(a)(x)?(b)' 'zabz'
+# CHECK: z<ab,a,,b>z
+string replace -ra --function __string_show_args 'x*' 'ab'
+# CHECK: <>a<>b<>
+string replace --function __string_not_a_function a b
+# CHECKERR: string replace: Unknown function '__string_not_a_function'
+string replace --function __string_double
+# CHECKERR: string replace: Expected an argument
+functions -e __string_double __string_show_args
+
 # `string` can't be wrapped properly anymore, since `string match` creates variables:
 function string
     builtin string $argv
@@ -0,0 +1,29 @@
+#RUN: %fish -C "set fish %fish" %s
+# In Termux, fish uses the C.UTF-8 locale if the one asked for doesn't exist, since Android has no
+# others, and finds the terminfo database under $PREFIX.
+
+set -l prefix (mktemp -d)/com.termux/files/usr
+mkdir -p $prefix/share/terminfo
+
+# Only Linux has a C.UTF-8 locale to fall back to.
+if test (uname) = Linux
+    env -u LC_ALL LANG=xx_XX.UTF-8 PREFIX=$prefix $fish -c 'string length ü'
+    env -u LC_ALL -u LANG PREFIX=$prefix $fish -c 'string length ü'
+else
+    echo 1
+    echo 1
+end
+# CHECK: 1
+# CHECK: 1
+
+# A locale which exists is used, even if it is not UTF-8.
+env LC_ALL=C PREFIX=$prefix $fish -c 'string length ü'
+# CHECK: 2
+
+env -u TERMINFO -u TERMINFO_DIRS -u COLORTERM -u STY TERM=xterm-256color PREFIX=$prefix \
+    $fish -d term-support -c true 2>&1 |
+    string match -e Termux | string replace -- $prefix PREFIX
+# CHECK: term-support: Using the terminfo database in 'PREFIX/share/terminfo' for Termux
+# CHECK: term-support: Truecolor support: Enabling for Termux
+
+rm -r (string replace /com.termux/files/usr '' -- $prefix)
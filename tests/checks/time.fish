@@ -31,6 +31,35 @@ true && time a=b not builtin true | true
 #CHECKERR: {{.*}}
 #CHECKERR: {{.*}}
 #CHECKERR: {{.*}}
+#CHECKERR:
+#CHECKERR: Stage  Wall time      usr time       sys time       Command
+#CHECKERR: {{\s*}}1 {{.*}} true
+#CHECKERR: {{\s*}}2 {{.*}} true
+
+# Blocks can be timed.
+time begin
+    echo block
+end
+#CHECK: block
+#CHECKERR: ________________________________________________________
+#CHECKERR: Executed in {{[\d,.\s]*}} {{millis|micros|secs}} {{\s*}}fish {{\s*}}external
+#CHECKERR: usr time {{[\d,.\s]*}} {{millis|micros|secs}} {{[\d,.\s]*}} {{millis|micros|secs}} {{[\d,.\s]*}} {{millis|micros|secs}}
+#CHECKERR: sys time {{[\d,.\s]*}} {{millis|micros|secs}} {{[\d,.\s]*}} {{millis|micros|secs}} {{[\d,.\s]*}} {{millis|micros|secs}}
+
+# Pipelines report the time of each stage.
+time echo pipe | begin
+    command cat
+end | string upper
+#CHECK: PIPE
+#CHECKERR: ________________________________________________________
+#CHECKERR: Executed in {{.*}}
+#CHECKERR: usr time {{.*}}
+#CHECKERR: sys time {{.*}}
+#CHECKERR:
+#CHECKERR: Stage  Wall time      usr time       sys time       Command
+#CHECKERR: {{\s*}}1 {{.*}} echo pipe
+#CHECKERR: {{\s*}}2 {{.*}} begin
+#CHECKERR: {{\s*}}3 {{.*}} string upper
 
 not time true
 #CHECKERR: ___{{.*}}
@@ -0,0 +1,53 @@
+#RUN: %fish %s
+timeout 0.2 sleep 10
+echo $status
+#CHECK: 124
+timeout 10 sh -c 'exit 3'
+echo $status
+#CHECK: 3
+
+# The status of the killed command may be kept.
+timeout --preserve-status 0.2 sleep 10
+echo $status
+#CHECK: 143
+timeout -s INT 0.2 sleep 10
+echo $status
+#CHECK: 124
+echo still running
+#CHECK: still running
+
+# Commands which ignore the signal are killed afterwards.
+timeout -k 0.2 0.2 sh -c 'trap "" TERM; while true; do sleep 0.1; done'
+echo $status
+#CHECK: 124
+
+# Functions run to completion, but their commands are signalled.
+function slow
+    sleep 10
+    echo after sleep
+    sleep 10
+end
+timeout 0.2 slow
+echo $status
+#CHECK: after sleep
+#CHECK: 124
+
+timeout 1 echo hi | string upper
+#CHECK: HI
+timeout 0.2 sleep 10 | cat
+echo $pipestatus
+#CHECK: 124 0
+
+# 0 means no timeout.
+timeout 0 true
+echo $status
+#CHECK: 0
+
+timeout 1
+#CHECKERR: timeout: Expected at least 2 args, got 1
+timeout x true
+#CHECKERR: timeout: Invalid duration 'x'
+timeout -k 1x 1 true
+#CHECKERR: timeout: Invalid duration '1x'
+timeout -s NOSUCHSIGNAL 1 true
+#CHECKERR: timeout: Unknown signal 'NOSUCHSIGNAL'
@@ -70,3 +70,27 @@ set -e fish_trace
 
 echo untraced
 # CHECK: untraced
+
+# Traces can go to a file, as JSON, and be filtered.
+set -l tracefile (mktemp)
+set fish_trace_target $tracefile
+set fish_trace_format json
+set fish_trace_filter 'echo *'
+set fish_trace 1
+function trace_me
+    echo "a\"b"
+    true
+end
+trace_me
+set fish_trace_max_depth 0
+trace_me
+echo top
+set -e fish_trace fish_trace_target fish_trace_format fish_trace_filter fish_trace_max_depth
+# CHECK: a"b
+# CHECK: a"b
+# CHECK: top
+
+cat $tracefile
+rm $tracefile
+# CHECK: {"time":{{[\d.]+}},"depth":2,"file":"{{.*}}trace.fish","line":81,"argv":["echo","a\"b"]}
+# CHECK: {"time":{{[\d.]+}},"depth":0,"file":"{{.*}}trace.fish","line":87,"argv":["echo","top"]}
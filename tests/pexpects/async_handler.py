@@ -0,0 +1,38 @@
+#!/usr/bin/env python3
+from pexpect_helper import SpawnedProc
+
+sp = SpawnedProc()
+send, sendline, sleep, expect_prompt, expect_re, expect_str = (
+    sp.send,
+    sp.sendline,
+    sp.sleep,
+    sp.expect_prompt,
+    sp.expect_re,
+    sp.expect_str,
+)
+expect_prompt()
+
+sendline(
+    "function slow --on-event go --async; sleep 1; echo slow $argv $var; set -g leaked 1; end"
+)
+expect_prompt()
+
+# The handler does not block, and gets the arguments and the event's variables.
+sendline("emit --var var=two go one; echo emitted")
+expect_prompt("emitted\r\n")
+
+# Its output is printed before the prompt after it finished.
+sendline("sleep 2")
+expect_re("slow one two\r\n")
+expect_prompt()
+
+# It ran in another process.
+sendline("echo leaked: $leaked")
+expect_prompt("leaked:\r\n")
+
+# It is not started again while it is still running.
+sendline("emit go a; emit go b; sleep 2")
+expect_re("slow a\r\n")
+expect_prompt()
+sendline("echo done")
+expect_prompt("done\r\n")
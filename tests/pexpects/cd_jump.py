@@ -0,0 +1,37 @@
+#!/usr/bin/env python3
+from pexpect_helper import SpawnedProc
+import os
+import tempfile
+
+# The data directory of the tests is relative, so it would be lost after cd.
+data_home = tempfile.mkdtemp()
+sp = SpawnedProc(env=dict(os.environ, XDG_DATA_HOME=data_home))
+sendline, expect_prompt = sp.sendline, sp.expect_prompt
+expect_prompt()
+
+# Changing the directory in an interactive session records it.
+sendline("set -g jumptmp (mktemp -d); mkdir -p $jumptmp/one/target $jumptmp/two")
+expect_prompt()
+sendline("cd $jumptmp/one/target; cd $jumptmp/two; cd $jumptmp/one/target; cd /")
+expect_prompt()
+sendline(
+    "string replace -r '^\\d+\\t\\d+\\t' '' <$__fish_user_data_dir/cd_frecency | string replace $jumptmp ''"
+)
+expect_prompt("/one/target\r\n/two\r\n/\r\n")
+sendline("string match -r '^\\d+' <$__fish_user_data_dir/cd_frecency")
+expect_prompt("2\r\n1\r\n1\r\n")
+
+sendline("cd --jump targ; string replace $jumptmp '' $PWD")
+expect_prompt("/one/target")
+
+# The cd function keeps the directory history.
+sendline("prevd >/dev/null; pwd")
+expect_prompt("/\r\n")
+
+# Private mode does not record anything.
+sendline("set -g fish_private_mode 1; cd $jumptmp; set -e fish_private_mode")
+expect_prompt()
+sendline("count <$__fish_user_data_dir/cd_frecency")
+expect_prompt("3\r\n")
+sendline("cd /; rm -rf $jumptmp $XDG_DATA_HOME")
+expect_prompt()
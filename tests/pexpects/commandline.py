@@ -30,3 +30,11 @@ expect_prompt()
 
 sendline("echo foo")
 expect_prompt("foo")
+
+# A message can be set and read back by a binding.
+sendline("bind \\cg 'commandline --message hello from; commandline -i (commandline --message)'")
+expect_prompt()
+send("echo ")
+send("\x07")
+sendline("")
+expect_prompt("hello from")
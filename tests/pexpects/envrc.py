@@ -0,0 +1,65 @@
+#!/usr/bin/env python3
+from pexpect_helper import SpawnedProc
+import os
+import tempfile
+
+sp = SpawnedProc()
+send, sendline, sleep, expect_prompt, expect_str = (
+    sp.send,
+    sp.sendline,
+    sp.sleep,
+    sp.expect_prompt,
+    sp.expect_str,
+)
+expect_prompt()
+
+tmpdir = os.path.realpath(tempfile.mkdtemp())
+proj = os.path.join(tmpdir, "proj")
+sub = os.path.join(proj, "sub")
+os.makedirs(sub)
+with open(os.path.join(proj, ".envrc.fish"), "w") as f:
+    f.write("set -gx PROJ_VAR outer\nset -g OLD_VAR changed\n")
+with open(os.path.join(sub, ".envrc.fish"), "w") as f:
+    f.write("set -g PROJ_VAR inner\n")
+
+sendline(
+    "function on_enter --on-event fish_dir_enter; echo entered $argv; end; "
+    + "function on_leave --on-event fish_dir_leave; echo left $argv; end; "
+    + "set -g OLD_VAR old"
+)
+expect_prompt()
+
+# Files are not loaded until they are trusted.
+sendline("cd %s" % proj)
+expect_str("'%s/.envrc.fish' is not trusted" % proj)
+expect_str("entered %s\r\n" % proj)
+expect_prompt()
+sendline("fish_envrc allow; echo $PROJ_VAR $OLD_VAR")
+expect_prompt("outer changed")
+
+# The files of the directories around are loaded first.
+sendline("fish_envrc allow sub; cd sub")
+expect_str("entered %s\r\n" % sub)
+expect_prompt()
+sendline("echo $PROJ_VAR")
+expect_prompt("inner")
+sendline("fish_envrc status")
+expect_str("%s/.envrc.fish: loaded\r\n  changed variables: OLD_VAR PROJ_VAR\r\n" % proj)
+expect_str("%s/.envrc.fish: loaded\r\n  changed variables: PROJ_VAR\r\n" % sub)
+expect_prompt()
+
+# Leaving reverts the variables, from the innermost directory out.
+sendline("cd %s" % tmpdir)
+expect_str("left %s\r\nleft %s\r\n" % (sub, proj))
+expect_prompt()
+sendline("set -q PROJ_VAR; or echo $OLD_VAR")
+expect_prompt("\r\nold\r\n")
+
+# A file which changed is no longer trusted.
+with open(os.path.join(proj, ".envrc.fish"), "a") as f:
+    f.write("echo evil\n")
+sendline("cd %s" % proj)
+expect_str("is not trusted")
+expect_prompt()
+sendline("set -q PROJ_VAR; or echo not loaded")
+expect_prompt("not loaded", unmatched="Loaded a changed file")
@@ -0,0 +1,51 @@
+#!/usr/bin/env python3
+from pexpect_helper import SpawnedProc
+import os
+import tempfile
+
+sp = SpawnedProc()
+send, sendline, sleep, expect_prompt, expect_str = (
+    sp.send,
+    sp.sendline,
+    sp.sleep,
+    sp.expect_prompt,
+    sp.expect_str,
+)
+expect_prompt()
+
+tmpdir = tempfile.mkdtemp()
+conf = os.path.join(tmpdir, "conf")
+subdir = os.path.join(tmpdir, "dir")
+os.mkdir(subdir)
+
+sendline(
+    "function on_change --on-file-change %s --on-file-change %s; echo changed $argv; end"
+    % (conf, subdir)
+)
+expect_prompt()
+
+# Creating and changing a file is noticed while waiting at the prompt.
+with open(conf, "w") as f:
+    f.write("a\n")
+expect_str("changed %s\r\n" % conf)
+with open(conf, "a") as f:
+    f.write("b\n")
+expect_str("changed %s\r\n" % conf)
+
+# For a directory, the handler gets the entries which changed.
+open(os.path.join(subdir, "x"), "w").close()
+expect_str("changed %s/x\r\n" % subdir)
+os.rename(os.path.join(subdir, "x"), os.path.join(subdir, "y"))
+expect_str("changed %s/x %s/y\r\n" % (subdir, subdir))
+
+# Changes made by commands are noticed when the prompt returns.
+sendline("rm %s/y; echo done" % subdir)
+expect_prompt("done\r\n")
+expect_str("changed %s/y\r\n" % subdir)
+
+# No more events once the function is gone.
+sendline("functions -e on_change")
+expect_prompt()
+os.unlink(conf)
+sendline("echo erased")
+expect_prompt("\r\nerased\r\n", unmatched="Event after erasing the handler")
@@ -0,0 +1,83 @@
+#!/usr/bin/env python3
+from pexpect_helper import SpawnedProc
+import json
+import os
+import re
+import tempfile
+import urllib.error
+import urllib.parse
+import urllib.request
+
+tmp = tempfile.mkdtemp()
+urlfile = os.path.join(tmp, "url")
+browser = os.path.join(tmp, "browser.sh")
+with open(browser, "w") as f:
+    f.write('#!/bin/sh\necho "$1" > %s\n' % urlfile)
+os.chmod(browser, 0o755)
+
+sp = SpawnedProc(env=dict(os.environ, BROWSER=browser))
+send, sendline, sleep, expect_prompt, expect_re, expect_str = (
+    sp.send,
+    sp.sendline,
+    sp.sleep,
+    sp.expect_prompt,
+    sp.expect_re,
+    sp.expect_str,
+)
+expect_prompt()
+
+sendline("function wc_test_func; echo hi; end; set -g fish_color_command 123456")
+expect_prompt()
+sendline("fish_config colors")
+expect_str("Hit ENTER to stop.")
+
+# The browser is given a file which redirects to the server, so the key is not on its command line.
+for _ in range(50):
+    if os.path.exists(urlfile) and os.path.getsize(urlfile):
+        break
+    sleep(0.1)
+with open(urlfile) as f:
+    redirect = f.read().strip()
+assert redirect.startswith("file://"), redirect
+with open(redirect[len("file://") :]) as f:
+    url = re.search(r"URL='([^']*)'", f.read()).group(1)
+assert url.endswith("#colors"), url
+url = url[: -len("#colors")]
+
+
+def get(path, base=url):
+    with urllib.request.urlopen(base + path) as resp:
+        return resp.read()
+
+
+def post(path, **params):
+    data = urllib.parse.urlencode(params).encode()
+    with urllib.request.urlopen(url + path, data) as resp:
+        return json.loads(resp.read())
+
+
+assert b"<html" in get("")
+assert "wc_test_func" in json.loads(get("functions/"))
+
+colors = {c["name"]: c for c in json.loads(get("colors/"))}
+assert colors["command"]["color"] == "123456", colors["command"]
+
+post("set_color/", what="command", color="ff0000")
+assert "wc_test_func" in post("get_function/", what="wc_test_func")[0]
+
+# Without the key nothing is served.
+try:
+    get("colors/", base=re.sub(r"/[0-9a-f]+/$", "/0000/", url))
+    assert False, "request without the key succeeded"
+except urllib.error.HTTPError as e:
+    assert e.code == 403, e.code
+
+send("\r")
+expect_str("Shutting down.")
+expect_prompt()
+
+# The color was saved as a universal variable, and the global one which hid it was erased.
+sendline("set -S fish_color_command | string match -r 'set in .*'")
+expect_prompt("set in universal scope, unexported")
+sendline("echo $fish_color_command")
+expect_prompt("ff0000")
@@ -0,0 +1,62 @@
+#!/usr/bin/env python3
+from pexpect_helper import SpawnedProc
+import os
+
+# The terminal interface needs a terminal which can move the cursor.
+sp = SpawnedProc(env=dict(os.environ, TERM="xterm"))
+send, sendline, sleep, expect_prompt, expect_re, expect_str = (
+    sp.send,
+    sp.sendline,
+    sp.sleep,
+    sp.expect_prompt,
+    sp.expect_re,
+    sp.expect_str,
+)
+expect_prompt()
+
+# Edit a variable: search for it, edit its value as the arguments of set and quit.
+sendline("set -g tuivar old; fish_config tui variables")
+expect_str("Enter: edit")
+send("tuivar")
+sleep(0.2)
+send("\r")
+expect_str("Enter: set the variable")
+# Control+U clears the value.
+send("\x15new 'x y' $tuivar\r")
+expect_str("Set 'tuivar'")
+send("\x1b")
+expect_prompt()
+sendline("printf '<%s>' $tuivar")
+expect_prompt("<new><x y><old>")
+
+# Choose a theme, which is saved as universal variables.
+sendline("fish_config tui theme")
+expect_str("Enter: use this theme")
+send("Nord\r")
+expect_str("Using the theme 'Nord'")
+send("\x1b")
+expect_prompt()
+sendline("set -qU fish_color_command; and echo $fish_color_command")
+expect_prompt("81a1c1")
+
+# Without a terminal, it says so.
+sendline("fish_config tui </dev/null")
+expect_prompt("fish_config tui: Needs a terminal which can move the cursor")
+sendline("fish_config tui frob")
+expect_prompt("fish_config tui: Unknown page 'frob'")
+
+# Start on the menu, open the prompts and choose one.
+sendline("fish_config tui")
+expect_str("Enter: open, Escape: quit, type to search")
+send("prompt\r")
+expect_str("Enter: use this prompt, Escape: back")
+send("justadollar\r")
+expect_str("Using the prompt 'justadollar'")
+# Escape goes back to the menu, and quits from there.
+send("\x1b")
+expect_str("Enter: open, Escape: quit, type to search")
+send("\x1b")
+# The new prompt is used right away, and saved.
+expect_str("$ ")
+sendline("test -f $__fish_config_dir/functions/fish_prompt.fish; and echo saved")
+expect_str("saved")
@@ -0,0 +1,29 @@
+#!/usr/bin/env python3
+from pexpect_helper import SpawnedProc
+import os
+import shutil
+import subprocess
+import tempfile
+import time
+
+# Ensure git doesn't read the user's config.
+tmp = tempfile.mkdtemp()
+env = dict((k, v) for k, v in os.environ.items() if not k.startswith("GIT_"))
+env["HOME"] = tmp
+subprocess.check_call(["git", "init", "-q", tmp], env=env)
+
+sp = SpawnedProc(env=env)
+sendline, expect_prompt, expect_str = sp.sendline, sp.expect_prompt, sp.expect_str
+expect_prompt()
+
+# The first time, the changes are counted right away.
+sendline("cd " + tmp + "; touch a; fish_git_status --background untracked")
+expect_prompt("\r\n1\r\n")
+
+# After that, the last count is printed, and the files are counted again in the background.
+sendline("touch b; fish_git_status --background untracked")
+expect_prompt("\r\n1\r\n")
+time.sleep(0.5)
+sendline("fish_git_status --background untracked")
+expect_prompt("\r\n2\r\n")
+shutil.rmtree(tmp)
@@ -0,0 +1,51 @@
+#!/usr/bin/env python3
+from pexpect_helper import SpawnedProc
+
+sp = SpawnedProc()
+send, sendline, expect_prompt, expect_str = (
+    sp.send,
+    sp.sendline,
+    sp.expect_prompt,
+    sp.expect_str,
+)
+expect_prompt()
+
+# New functions start out empty, the "end" is replaced here.
+sendline("funced -i newfunc")
+expect_str("newfunc> ")
+send("\b\b\b")
+sendline("echo new; end")
+expect_str("+++ b/newfunc.fish")
+expect_str("+    echo new")
+expect_prompt()
+sendline("newfunc")
+expect_prompt("new")
+
+# Definitions with errors are edited again until they are fixed.
+sendline("funced -i badfunc")
+expect_str("badfunc> ")
+send("\b\b\b")
+sendline("echo $$; end")
+expect_str("$$ is not the pid")
+send("\b" * len("$$; end"))
+sendline("fixed; end")
+expect_str("+    echo fixed")
+expect_prompt()
+sendline("badfunc")
+expect_prompt("fixed")
+
+# Clearing the definition cancels editing.
+sendline("funced -i cancelled")
+expect_str("cancelled> ")
+send("\b" * len("function cancelled\n\nend"))
+sendline("")
+expect_str("Cancelled function editing")
+expect_prompt()
+sendline("echo $status; functions -q cancelled; or echo not defined")
+expect_prompt("1\r\nnot defined")
+
+# Accepting the definition as it is changes nothing.
+sendline("funced -i newfunc")
+expect_str("newfunc> ")
+sendline("")
+expect_prompt("Editor exited but the function was not modified")
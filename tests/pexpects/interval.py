@@ -0,0 +1,40 @@
+#!/usr/bin/env python3
+from pexpect_helper import SpawnedProc
+
+sp = SpawnedProc()
+send, sendline, sleep, expect_prompt, expect_re, expect_str = (
+    sp.send,
+    sp.sendline,
+    sp.sleep,
+    sp.expect_prompt,
+    sp.expect_re,
+    sp.expect_str,
+)
+expect_prompt()
+
+sendline(
+    "set -g ticks 0; "
+    + "function tick --on-interval 0.4; set ticks (math $ticks + 1); echo tick $argv; end"
+)
+expect_prompt()
+
+# Handlers run while waiting at the prompt.
+expect_str("tick 0.4")
+expect_str("tick 0.4")
+
+# But not while a command runs, and only once for all the intervals missed.
+sendline("set ticks 0; sleep 2; echo ran $ticks")
+expect_prompt("ran 0")
+sendline("echo ticks $ticks")
+expect_re("ticks [12]\r\n")
+expect_prompt()
+
+# A handler can repaint the prompt, like for a clock.
+sendline(
+    'set ticks 0; function fish_prompt; echo -n "prompt $ticks> "; end; '
+    + "function tick --on-interval 0.2; set ticks (math $ticks + 1); commandline -f repaint; end"
+)
+expect_str("prompt 1>")
+expect_str("prompt 2>")
+sendline("functions --handlers-type interval")
+expect_str("Event interval\r\n0.2 tick\r\n")
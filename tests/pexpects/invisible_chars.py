@@ -0,0 +1,21 @@
+#!/usr/bin/env python3
+from pexpect_helper import SpawnedProc
+
+sp = SpawnedProc()
+send, sendline, expect_prompt, expect_str = sp.send, sp.sendline, sp.expect_prompt, sp.expect_str
+expect_prompt()
+
+# Control characters and zero-width spaces in the command line are shown as escapes.
+sendline("bind \\cg 'commandline -i (printf \"echo a\\\\x01b\\\\u200bc\")'")
+expect_prompt()
+send("\x07")
+expect_str("echo a^Ab<U+200B>c")
+sendline("")
+expect_prompt()
+
+sendline("set -g fish_invisible_style escape")
+expect_prompt()
+send("\x07")
+expect_str("echo a\\cab\\u200bc")
+sendline("")
+expect_prompt()
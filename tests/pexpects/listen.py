@@ -0,0 +1,67 @@
+#!/usr/bin/env python3
+from pexpect_helper import SpawnedProc
+import json
+import os
+import socket
+import stat
+import tempfile
+
+path = os.path.join(tempfile.mkdtemp(), "fish.sock")
+sp = SpawnedProc(args=["--listen", path])
+send, sendline, sleep, expect_prompt, expect_re, expect_str = (
+    sp.send,
+    sp.sendline,
+    sp.sleep,
+    sp.expect_prompt,
+    sp.expect_re,
+    sp.expect_str,
+)
+expect_prompt()
+
+# Only the user may connect.
+assert stat.S_IMODE(os.stat(path).st_mode) == 0o600
+
+client = socket.socket(socket.AF_UNIX, socket.SOCK_STREAM)
+client.connect(path)
+conn = client.makefile("rw")
+
+
+def request(**req):
+    conn.write(json.dumps(req) + "\n")
+    conn.flush()
+    return json.loads(conn.readline())
+
+
+sendline("set -g foo bar baz; cd /; sleep 100 &")
+expect_prompt()
+assert request(id=1, request="variable", name="foo") == {"id": 1, "value": ["bar", "baz"]}
+assert request(request="variable", name="nonexistent") == {"value": None}
+assert request(request="cwd") == {"cwd": "/"}
+
+jobs = request(request="jobs")["jobs"]
+assert len(jobs) == 1 and jobs[0]["id"] == 1 and jobs[0]["command"] == "sleep 100 &", jobs
+
+# Commands run in the session, and the prompt is repainted afterwards.
+assert request(request="eval", command="set -g evaluated yes; echo hi; false") == {
+    "status": 1,
+    "output": ["hi"],
+}
+sendline("echo $evaluated")
+expect_prompt("yes")
+
+# Input is handled as if it was typed.
+assert request(request="input", text="echo typed", functions=["execute"]) == {}
+expect_prompt("typed")
+
+assert request(request="input", functions=["frobnicate"]) == {
+    "error": "Unknown input function 'frobnicate'"
+}
+assert request(id="x", request="frobnicate") == {"id": "x", "error": "Unknown request 'frobnicate'"}
+conn.write("not json\n")
+conn.flush()
+assert json.loads(conn.readline()) == {"error": "Invalid JSON"}
+
+# The socket is removed on exit.
+sendline("kill %1; wait; exit")
+sp.spawn.wait()
+assert not os.path.exists(path)
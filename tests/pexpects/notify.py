@@ -0,0 +1,43 @@
+#!/usr/bin/env python3
+from pexpect_helper import SpawnedProc
+
+sp = SpawnedProc()
+send, sendline, sleep, expect_prompt, expect_str = (
+    sp.send,
+    sp.sendline,
+    sp.sleep,
+    sp.expect_prompt,
+    sp.expect_str,
+)
+expect_prompt()
+
+sendline(
+    "set -g fish_notify_duration 100; set -g fish_notify_method event; "
+    + "function on_notify --on-event fish_notify; echo notified $argv; end"
+)
+expect_prompt()
+
+# A command which took long and finished while the terminal is not focused.
+sendline("sleep 0.2; false")
+expect_prompt()
+send("\x1b[O")
+expect_str("notified sleep 0.2; false")
+expect_str(" 1\r\n")
+
+# Nothing is sent if the terminal is focused when the command finishes.
+sendline("sleep 0.2")
+expect_prompt()
+send("\x1b[I")
+sleep(0.1)
+send("\x1b[O")
+sleep(0.1)
+sendline("echo no notification")
+expect_prompt("\r\nno notification\r\n", unmatched="Notified a focused terminal")
+
+# Or if the command was quick.
+sendline("true")
+expect_prompt()
+send("\x1b[O")
+sleep(0.1)
+sendline("echo still none")
+expect_prompt("\r\nstill none\r\n", unmatched="Notified for a quick command")
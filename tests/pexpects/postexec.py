@@ -8,7 +8,7 @@ sendline, expect_prompt, expect_str = sp.sendline, sp.expect_prompt, sp.expect_s
 expect_prompt()
 
 sendline(
-    "function test_fish_postexec --on-event fish_postexec; printf 'pipestatus:%s, generation:%d, command:%s\\n' (string join '|' $pipestatus) $status_generation $argv; end"
+    "function test_fish_postexec --on-event fish_postexec; printf 'pipestatus:%s, generation:%d, command:%s\\n' (string join '|' $pipestatus) $status_generation $argv[1]; end"
 )
 expect_prompt()
 
@@ -145,3 +145,57 @@ expect_str(
     % generation
 )
 expect_prompt()
+
+# $CMD_DURATIONS has the duration of each process of the last job, like $pipestatus.
+sendline("functions -e test_fish_postexec")
+expect_prompt()
+sendline(
+    "function test_fish_postexec --on-event fish_postexec; set -g postexec_durations $argv[2..-1]; end"
+)
+expect_prompt()
+sendline("true; sleep 0.3 | true")
+expect_prompt()
+sendline(
+    "test (count $CMD_DURATIONS) -eq 2 -a $CMD_DURATIONS[1] -ge 300 -a $CMD_DURATIONS[2] -lt $CMD_DURATIONS[1]; and echo durations ok"
+)
+expect_str("durations ok")
+expect_prompt()
+
+# The handler of fish_postexec got the same durations.
+sendline("test \"$postexec_durations\" = \"$CMD_DURATIONS\"; and echo postexec ok")
+expect_str("postexec ok")
+expect_prompt()
+
+# The handlers also get the command line, how each process of the last job exited and its
+# expanded arguments as local variables.
+sendline("functions -e test_fish_postexec")
+expect_prompt()
+sendline(
+    "function test_fish_postexec --on-event fish_postexec; printf 'line:%s status:%s pipestatus:%s count:%s argv1:%s (%d) argv2:%s durations:%d\\n' \"$command_line\" \"$command_status\" \"$command_pipestatus\" \"$command_process_count\" \"$command_argv_1\" (count $command_argv_1) \"$command_argv_2\" (count $command_durations); end"
+)
+expect_prompt()
+sendline("set -l x 'a b'; true $x c | false")
+expect_str(
+    "line:set -l x 'a b'; true $x c | false status:1 pipestatus:0 1 count:2 argv1:true a b c (3) argv2:false durations:2"
+)
+expect_prompt()
+
+# Without a status, there is nothing about processes.
+sendline("sleep 1000 &")
+expect_str("line:sleep 1000 & status: pipestatus: count: argv1: (0) argv2: durations:1")
+expect_prompt()
+
+sendline(
+    "function test_fish_preexec --on-event fish_preexec; echo preexec:$command_line; end"
+)
+expect_prompt()
+sendline("true")
+expect_str("preexec:true")
+expect_prompt()
+
+sendline(
+    "function test_fish_posterror --on-event fish_posterror; echo posterror:$command_line; end"
+)
+expect_prompt()
+sendline("end")
+expect_str("posterror:end")
@@ -0,0 +1,23 @@
+#!/usr/bin/env python3
+from pexpect_helper import SpawnedProc
+import time
+
+sp = SpawnedProc()
+sendline, expect_prompt, expect_str = sp.sendline, sp.expect_prompt, sp.expect_str
+expect_prompt()
+
+# Nothing has finished the first time, the command runs in the background.
+sendline("prompt_segment test sh -c 'echo in-$PWD'; echo segment status $status")
+expect_str("segment status 1")
+expect_prompt()
+
+# Once it has finished, its output is printed.
+time.sleep(0.5)
+sendline("cd /; prompt_segment test sh -c 'echo in-$PWD'; echo segment status $status")
+expect_str("segment status 1")
+expect_prompt()
+time.sleep(0.5)
+sendline("prompt_segment test sh -c 'echo in-$PWD'; echo segment status $status")
+expect_str("in-/")
+expect_str("segment status 0")
+expect_prompt()
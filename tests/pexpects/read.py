@@ -63,6 +63,23 @@ expect_prompt()
 expect_marker(3)
 print_var_contents("foo", "read_s")
 
+# read --mask shows the mask instead, and backspace removes the last character.
+sendline("read --mask '#' foo")
+expect_read_prompt()
+send("pass")
+expect_str("####")
+send("\x7fw")
+sendline("\r_marker 3")
+expect_prompt()
+expect_marker(3)
+print_var_contents("foo", "pasw")
+
+# With an empty mask, nothing is shown.
+sendline("read --mask '' foo; echo -n /$foo/")
+expect_read_prompt()
+sendline("secret")
+expect_prompt("/secret/")
+
 # read -n
 
 sendline("read -n 3 foo")
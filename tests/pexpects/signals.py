@@ -56,6 +56,15 @@ subprocess.call(["pkill", "-TERM", "sleep", "-P", str(sp.spawn.pid)])
 expect_str("fish_kill_signal 15")
 expect_prompt()
 
+# Verify that signal handlers run while fish is waiting for input, before any input that follows.
+sendline("function usr1 --on-signal USR1; echo usr1 handled; end")
+expect_prompt()
+sleep(0.100)
+os.kill(sp.spawn.pid, signal.SIGUSR1)
+expect_str("usr1 handled")
+sendline("echo typed")
+expect_prompt("typed")
+
 # Verify that sending SIGHUP to the shell, such as will happen when the tty is
 # closed by the terminal, terminates the shell and the foreground command and
 # any background commands run from that shell.
@@ -0,0 +1,50 @@
+#!/usr/bin/env python3
+from pexpect_helper import SpawnedProc
+
+sp = SpawnedProc()
+send, sendline, expect_prompt, expect_str = (
+    sp.send,
+    sp.sendline,
+    sp.expect_prompt,
+    sp.expect_str,
+)
+expect_prompt()
+
+# Nothing is offered unless $fish_spell_correction is set.
+sendline("function spelltest; echo ran spelltest $argv; end; ehco one")
+expect_prompt()
+
+sendline("set -g fish_spell_correction 1")
+expect_prompt()
+
+# Accepting runs the corrected command line.
+sendline("ehco two | string upper")
+expect_str("Did you mean ")
+expect_str("echo two | string upper")
+send("y")
+# The prompt for the corrected command line is not drawn, since it is executed right away.
+sp.prompt_counter += 1
+expect_prompt("TWO")
+
+# Declining does nothing, the status stays that of the mistyped command.
+sendline("spelltets three")
+expect_str("spelltest three")
+send("n")
+expect_prompt()
+sendline("echo status $status")
+expect_prompt("status 127")
+
+# Directories given to cd are corrected too.
+sendline("mkdir -p $__fish_config_dir/spell-dir; cd $__fish_config_dir; cd spel-dir")
+expect_str("cd spell-dir")
+send("y")
+sp.prompt_counter += 1
+expect_prompt()
+sendline("basename $PWD")
+expect_prompt("spell-dir")
+
+# Commands which failed for other reasons are left alone.
+sendline("false")
+expect_prompt()
+sendline("echo done")
+expect_prompt("done")
@@ -0,0 +1,27 @@
+#!/usr/bin/env python3
+# A tty which does not know its size, like a serial line, makes fish ask the terminal.
+from pexpect_helper import SpawnedProc
+import os
+
+# Dumb terminals are not asked.
+sp = SpawnedProc(env=dict(os.environ, TERM="xterm"), dimensions=(0, 0))
+send, sendline, sleep, expect_prompt, expect_re, expect_str = (
+    sp.send,
+    sp.sendline,
+    sp.sleep,
+    sp.expect_prompt,
+    sp.expect_re,
+    sp.expect_str,
+)
+
+# Answer the cursor position request, as a terminal with 43 rows and 132 columns would.
+expect_str("\x1b[6n")
+send("\x1b[43;132R")
+expect_prompt()
+
+sendline("echo $COLUMNS $LINES")
+expect_prompt("132 43")
+
+# The size is stored in the tty, so commands see it too.
+sendline("stty size")
+expect_prompt("43 132")
@@ -34,6 +34,10 @@ if not set -q __fish_is_running_tests
     rm -rf $XDG_DATA_HOME/fish
     mkdir -p $XDG_DATA_HOME/fish; or die
 
+    set -lx XDG_CACHE_HOME ../test/xdg_cache_home
+    rm -rf $XDG_CACHE_HOME/fish
+    mkdir -p $XDG_CACHE_HOME/fish; or die
+
     set -lx XDG_CONFIG_HOME ../test/xdg_config_home
     rm -rf $XDG_CONFIG_HOME/fish
     mkdir -p $XDG_CONFIG_HOME/fish; or die